@@ -0,0 +1,195 @@
+use super::{Chunk, ChunkType, SemanticChunker};
+use super::markdown::{HeaderInfo, MarkdownProcessor};
+use anyhow::Result;
+use sha2::{Sha256, Digest};
+
+pub struct AsciiDocProcessor;
+
+/// A delimited block (`----` listing or `....` literal) being collected
+struct SourceBlock {
+    delimiter: String,
+    language: Option<String>,
+    start_line: usize,
+    lines: Vec<String>,
+}
+
+impl AsciiDocProcessor {
+    /// Chunk AsciiDoc by its `=` heading hierarchy. Prose in each section is chunked as
+    /// text, while `[source,lang]` / listing blocks are kept whole as code chunks.
+    pub fn extract_and_chunk(content: &str, file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+
+        let mut all_chunks = Vec::new();
+        let mut header_stack: Vec<HeaderInfo> = Vec::new();
+        let mut current_section = String::new();
+        let mut pending_language: Option<String> = None;
+        let mut block: Option<SourceBlock> = None;
+
+        for (line_no, line) in content.lines().enumerate() {
+            let trimmed = line.trim_end();
+
+            // Inside a delimited block everything is verbatim until the closing delimiter
+            if let Some(open) = block.as_mut() {
+                if trimmed == open.delimiter {
+                    let finished = block.take().unwrap();
+                    all_chunks.extend(Self::code_chunks(finished, line_no + 1, &header_stack, file_path, &file_hash, chunker)?);
+                } else {
+                    open.lines.push(line.to_string());
+                }
+                continue;
+            }
+
+            if let Some((level, title)) = Self::parse_heading(trimmed) {
+                Self::flush_section(&mut current_section, &header_stack, file_path, chunker, &mut all_chunks)?;
+                header_stack.retain(|h| h.level < level);
+                header_stack.push(HeaderInfo { text: title, level });
+                pending_language = None;
+                continue;
+            }
+
+            if Self::is_block_delimiter(trimmed) {
+                block = Some(SourceBlock {
+                    delimiter: trimmed.to_string(),
+                    language: pending_language.take(),
+                    start_line: line_no + 1,
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+
+            // Block attribute lines like [source,systemverilog] describe the next block
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                pending_language = Self::parse_source_language(trimmed);
+                continue;
+            }
+
+            // Line comments carry no searchable content
+            if trimmed.starts_with("//") {
+                continue;
+            }
+
+            current_section.push_str(line);
+            current_section.push('\n');
+        }
+
+        // An unterminated block still holds content worth keeping
+        if let Some(unclosed) = block.take() {
+            let end_line = content.lines().count();
+            all_chunks.extend(Self::code_chunks(unclosed, end_line, &header_stack, file_path, &file_hash, chunker)?);
+        }
+
+        Self::flush_section(&mut current_section, &header_stack, file_path, chunker, &mut all_chunks)?;
+
+        Ok(all_chunks)
+    }
+
+    /// `= Title`, `== Section`, ... (levels 1-6)
+    fn parse_heading(line: &str) -> Option<(u32, String)> {
+        let level = line.chars().take_while(|&c| c == '=').count();
+        if level == 0 || level > 6 {
+            return None;
+        }
+
+        let rest = &line[level..];
+        if !rest.starts_with(' ') || rest.trim().is_empty() {
+            return None;
+        }
+
+        Some((level as u32, rest.trim().to_string()))
+    }
+
+    fn is_block_delimiter(line: &str) -> bool {
+        line.len() >= 4 && (line.chars().all(|c| c == '-') || line.chars().all(|c| c == '.'))
+    }
+
+    /// `[source,python]` or `[source, python, linenums]` -> Some("python")
+    fn parse_source_language(attributes: &str) -> Option<String> {
+        let inner = attributes.trim_start_matches('[').trim_end_matches(']');
+        let mut parts = inner.split(',').map(|p| p.trim());
+        match parts.next() {
+            Some("source") => parts.next().filter(|lang| !lang.is_empty()).map(|lang| lang.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn flush_section(
+        section: &mut String,
+        headers: &[HeaderInfo],
+        file_path: &str,
+        chunker: &SemanticChunker,
+        all_chunks: &mut Vec<Chunk>,
+    ) -> Result<()> {
+        if section.trim().is_empty() {
+            section.clear();
+            return Ok(());
+        }
+
+        let mut chunks = chunker.chunk_text(section, file_path)?;
+        let (chapter, section_name) = MarkdownProcessor::extract_chapter_and_section(headers);
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::AsciiDoc;
+            chunk.metadata.chapter = chapter.clone();
+            chunk.metadata.section = section_name.clone();
+        }
+
+        all_chunks.extend(chunks);
+        section.clear();
+        Ok(())
+    }
+
+    fn code_chunks(
+        block: SourceBlock,
+        end_line: usize,
+        headers: &[HeaderInfo],
+        file_path: &str,
+        file_hash: &str,
+        chunker: &SemanticChunker,
+    ) -> Result<Vec<Chunk>> {
+        let code = block.lines.join("\n");
+        if code.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let language = block.language.unwrap_or_else(|| "text".to_string());
+
+        // Keep blocks whole unless they exceed the size limit
+        let mut chunks = if code.len() > chunker.max_chunk_size() {
+            chunker.chunk_code(&code, &language, file_path)?
+        } else {
+            vec![SemanticChunker::build_code_chunk(&code, &language, file_path, file_hash, (block.start_line, end_line))]
+        };
+
+        let (chapter, section_name) = MarkdownProcessor::extract_chapter_and_section(headers);
+        for chunk in &mut chunks {
+            chunk.metadata.chapter = chapter.clone();
+            if chunk.metadata.section.is_none() {
+                chunk.metadata.section = section_name.clone();
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_blocks_are_kept_whole() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let content = "= DMA Guide\n\n== Reset Sequence\n\nThe controller must be held in reset for sixteen cycles before enabling.\n\n// internal note\n[source,systemverilog]\n----\nalways_ff @(posedge clk) begin\n\n  if (rst) state <= IDLE;\nend\n----\n";
+
+        let chunks = AsciiDocProcessor::extract_and_chunk(content, "guide.adoc", &chunker).unwrap();
+
+        let code: Vec<_> = chunks.iter().filter(|c| matches!(c.metadata.chunk_type, ChunkType::Code)).collect();
+        assert_eq!(code.len(), 1);
+        assert!(code[0].content.contains("if (rst) state <= IDLE;"));
+        assert_eq!(code[0].metadata.chapter.as_deref(), Some("DMA Guide"));
+
+        let prose = chunks.iter().find(|c| matches!(c.metadata.chunk_type, ChunkType::AsciiDoc)).unwrap();
+        assert_eq!(prose.metadata.section.as_deref(), Some("Reset Sequence"));
+        assert!(!chunks.iter().any(|c| c.content.contains("internal note")));
+    }
+}
\ No newline at end of file
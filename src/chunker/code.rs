@@ -1,11 +1,19 @@
-use super::{Chunk, ChunkMetadata, ChunkType};
+use super::{Chunk, ChunkMetadata, ChunkType, hdl::HdlProcessor};
 use anyhow::Result;
 
 pub struct CodeProcessor;
 
 impl CodeProcessor {
     pub fn extract_and_chunk(content: &str, language: &str, file_path: &str, chunker: &super::SemanticChunker) -> Result<Vec<Chunk>> {
-        chunker.chunk_code(content, language, file_path)
+        match language {
+            "systemverilog" | "verilog" | "vhdl" => HdlProcessor::extract_and_chunk(content, language, file_path, chunker),
+            _ => {
+                let mut chunks = chunker.chunk_code(content, language, file_path)?;
+                let docs = chunker.doc_chunks(&chunks, language);
+                chunks.extend(docs);
+                Ok(chunks)
+            }
+        }
     }
 
     pub fn detect_language(file_path: &str) -> Option<String> {
@@ -22,6 +30,9 @@ impl CodeProcessor {
             Some("cpp" | "cc" | "cxx") => Some("cpp".to_string()),
             Some("c") => Some("c".to_string()),
             Some("go") => Some("go".to_string()),
+            Some("sv" | "svh") => Some("systemverilog".to_string()),
+            Some("v" | "vh") => Some("verilog".to_string()),
+            Some("vhd" | "vhdl") => Some("vhdl".to_string()),
             _ => None,
         }
     }
@@ -0,0 +1,330 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Sha256, Digest};
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+/// Upper bound on key-name tags per chunk, as for JSON records
+const MAX_KEY_TAGS: usize = 32;
+
+/// Section path for keys that appear before the first TOML table or INI section
+const ROOT_SECTION: &str = "(root)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Ini,
+}
+
+impl ConfigFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            "ini" | "cfg" => Some(ConfigFormat::Ini),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Ini => "ini",
+        }
+    }
+}
+
+/// A run of lines under one key path
+#[derive(Debug)]
+struct Section<'a> {
+    path: String,
+    first_line: usize,
+    lines: Vec<&'a str>,
+}
+
+pub struct ConfigFileProcessor;
+
+impl ConfigFileProcessor {
+    /// Chunk a YAML, TOML or INI file by its top-level sections (YAML top-level keys, TOML
+    /// tables, INI sections), keeping comments with the keys they describe. Each chunk
+    /// records its key path as the section and is tagged with the key names it contains.
+    /// Oversized YAML sections are split by their child keys (`server.tls`).
+    pub fn extract_and_chunk(content: &str, file_path: &str, format: ConfigFormat, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut sections = match format {
+            ConfigFormat::Yaml => Self::yaml_sections(&lines, 0, 1, None),
+            ConfigFormat::Toml | ConfigFormat::Ini => Self::table_sections(&lines),
+        };
+
+        if format == ConfigFormat::Yaml {
+            sections = sections.into_iter()
+                .flat_map(|section| {
+                    let size: usize = section.lines.iter().map(|l| l.len() + 1).sum();
+                    if size > chunker.max_chunk_size() && section.lines.len() > 1 {
+                        let children = Self::yaml_sections(&section.lines[1..], 1, section.first_line + 1, Some(&section.path));
+                        if children.len() > 1 {
+                            return Self::with_header(section.lines[0], section.first_line, children);
+                        }
+                    }
+                    vec![section]
+                })
+                .collect();
+        }
+
+        let mut chunks = Vec::new();
+        for section in sections {
+            chunks.extend(Self::build_chunks(&section, format, file_path, &file_hash, chunker)?);
+        }
+        Ok(chunks)
+    }
+
+    /// Split YAML lines at keys indented at the shallowest level found (at least
+    /// `min_indent`). Comments and blank lines just above a key belong to that key.
+    fn yaml_sections<'a>(lines: &[&'a str], min_indent: usize, first_line: usize, parent: Option<&str>) -> Vec<Section<'a>> {
+        let indent = lines.iter()
+            .filter(|line| Self::yaml_key(line).is_some())
+            .map(|line| Self::indentation(line))
+            .filter(|&indent| indent >= min_indent)
+            .min();
+        let Some(indent) = indent else {
+            return Vec::new();
+        };
+
+        let mut sections: Vec<Section> = Vec::new();
+        let mut pending: Vec<&str> = Vec::new();  // Comments/blanks not yet assigned
+        let mut pending_start = first_line;
+
+        for (offset, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            let key = Self::yaml_key(line).filter(|_| Self::indentation(line) == indent);
+
+            if let Some(key) = key {
+                let path = match parent {
+                    Some(parent) => format!("{}.{}", parent, key),
+                    None => key,
+                };
+                let (start, mut section_lines) = Self::take_leading_comments(&mut pending, pending_start, first_line + offset, sections.last_mut());
+                section_lines.push(line);
+                sections.push(Section { path, first_line: start, lines: section_lines });
+            } else if trimmed.is_empty() || (trimmed.starts_with('#') && Self::indentation(line) <= indent) || trimmed == "---" {
+                if pending.is_empty() {
+                    pending_start = first_line + offset;
+                }
+                pending.push(line);
+            } else {
+                // Nested content: flush held comments into the current section
+                match sections.last_mut() {
+                    Some(section) => {
+                        section.lines.append(&mut pending);
+                        section.lines.push(line);
+                    }
+                    None => {
+                        pending.push(line);
+                    }
+                }
+            }
+        }
+
+        // Trailing comments stay with the last key; a file without keys keeps everything
+        match sections.last_mut() {
+            Some(section) => section.lines.append(&mut pending),
+            None if pending.iter().any(|l| !l.trim().is_empty()) => {
+                sections.push(Section { path: ROOT_SECTION.to_string(), first_line, lines: pending });
+            }
+            None => {}
+        }
+
+        sections
+    }
+
+    /// Repeat the parent key line above each child section so chunks stay readable YAML
+    fn with_header<'a>(header: &'a str, header_line: usize, children: Vec<Section<'a>>) -> Vec<Section<'a>> {
+        children.into_iter()
+            .map(|mut child| {
+                child.lines.insert(0, header);
+                child.first_line = child.first_line.min(header_line + 1);
+                child
+            })
+            .collect()
+    }
+
+    /// Split TOML/INI lines at `[table]` / `[[array]]` / `[section]` headers. Keys before the
+    /// first header form the root section.
+    fn table_sections<'a>(lines: &[&'a str]) -> Vec<Section<'a>> {
+        static HEADER: OnceLock<Regex> = OnceLock::new();
+        let header = HEADER.get_or_init(|| Regex::new(r"^\s*\[\[?\s*([^\[\]]+?)\s*\]\]?\s*(?:[#;].*)?$").unwrap());
+
+        let mut sections: Vec<Section> = vec![Section { path: ROOT_SECTION.to_string(), first_line: 1, lines: Vec::new() }];
+        let mut pending: Vec<&str> = Vec::new();
+        let mut pending_start = 1;
+
+        for (index, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if let Some(captures) = header.captures(line) {
+                let path = captures[1].split('.').map(|part| part.trim().trim_matches('"')).collect::<Vec<_>>().join(".");
+                let (start, mut section_lines) = Self::take_leading_comments(&mut pending, pending_start, index + 1, sections.last_mut());
+                section_lines.push(line);
+                sections.push(Section { path, first_line: start, lines: section_lines });
+            } else if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                if pending.is_empty() {
+                    pending_start = index + 1;
+                }
+                pending.push(line);
+            } else if let Some(section) = sections.last_mut() {
+                if section.lines.is_empty() {
+                    section.first_line = if pending.is_empty() { index + 1 } else { pending_start };
+                }
+                section.lines.append(&mut pending);
+                section.lines.push(line);
+            }
+        }
+
+        if let Some(section) = sections.last_mut() {
+            section.lines.append(&mut pending);
+        }
+
+        sections.retain(|section| section.lines.iter().any(|line| !line.trim().is_empty()));
+        sections
+    }
+
+    /// Split held lines before a new key: lines up to the last blank stay with the previous
+    /// section, and the comment block directly above the key moves with it. Without a
+    /// previous section everything held is the new section's preamble. Returns the new
+    /// section's first line and its leading lines.
+    fn take_leading_comments<'a>(
+        pending: &mut Vec<&'a str>,
+        pending_start: usize,
+        key_line: usize,
+        previous: Option<&mut Section<'a>>,
+    ) -> (usize, Vec<&'a str>) {
+        let split = match previous {
+            Some(previous) => {
+                let split = pending.iter().rposition(|line| line.trim().is_empty()).map(|i| i + 1).unwrap_or(0);
+                previous.lines.extend(pending.drain(..split));
+                split
+            }
+            None => 0,
+        };
+        let comments = std::mem::take(pending);
+
+        let start = if comments.is_empty() { key_line } else { pending_start + split };
+        (start, comments)
+    }
+
+    /// `key:` at the start of a YAML line (optionally as a list item), without the colon
+    fn yaml_key(line: &str) -> Option<String> {
+        static KEY: OnceLock<Regex> = OnceLock::new();
+        let key = KEY.get_or_init(|| Regex::new(r#"^\s*(?:-\s+)?("[^"]+"|'[^']+'|[^\s#:'"\-][^:#]*?)\s*:(?:\s|$)"#).unwrap());
+        key.captures(line).map(|c| c[1].trim_matches(['"', '\'']).to_string())
+    }
+
+    /// `key = value` (TOML/INI) or `key: value` (INI)
+    fn assignment_key(line: &str) -> Option<String> {
+        static KEY: OnceLock<Regex> = OnceLock::new();
+        let key = KEY.get_or_init(|| Regex::new(r#"^\s*("[^"]+"|[A-Za-z0-9_.\-]+)\s*[=:]"#).unwrap());
+        key.captures(line).map(|c| c[1].trim_matches('"').to_string())
+    }
+
+    fn indentation(line: &str) -> usize {
+        line.len() - line.trim_start().len()
+    }
+
+    fn build_chunks(section: &Section, format: ConfigFormat, file_path: &str, file_hash: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let text = section.lines.join("\n");
+        let text = text.trim();
+        let last_line = section.first_line + section.lines.len().saturating_sub(1);
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_text(text, file_path)?
+        } else {
+            vec![SemanticChunker::build_text_chunk(text, file_path, file_hash, (section.first_line, last_line), ChunkStrategy::StructuredRecord)]
+        };
+
+        let mut keys = BTreeSet::new();
+        for line in &section.lines {
+            let key = match format {
+                ConfigFormat::Yaml => Self::yaml_key(line),
+                ConfigFormat::Toml | ConfigFormat::Ini => Self::assignment_key(line),
+            };
+            if let Some(key) = key {
+                keys.insert(key.to_lowercase());
+            }
+        }
+        for part in section.path.split('.').filter(|p| *p != ROOT_SECTION) {
+            keys.insert(part.to_lowercase());
+        }
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Config;
+            chunk.metadata.section = Some(section.path.clone());
+            chunk.metadata.line_start = section.first_line;
+            chunk.metadata.line_end = last_line;
+            chunk.metadata.attributes.insert("key_path".to_string(), section.path.clone());
+            chunk.metadata.attributes.insert("config_format".to_string(), format.as_str().to_string());
+            for key in keys.iter().take(MAX_KEY_TAGS) {
+                if !chunk.metadata.tags.contains(key) {
+                    chunk.metadata.tags.push(key.clone());
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_top_level_keys_and_split() {
+        let yaml = "\
+# Storage settings
+storage:
+  data_dir: ./data
+  max_chunk_size: 512
+
+# Embedding model
+embedding:
+  model_name: minilm
+  tls:
+    verify: true
+";
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let chunks = ConfigFileProcessor::extract_and_chunk(yaml, "rag.yaml", ConfigFormat::Yaml, &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].metadata.section.as_deref(), Some("storage"));
+        assert!(chunks[0].content.starts_with("# Storage settings"));
+        assert_eq!((chunks[1].metadata.line_start, chunks[1].metadata.line_end), (6, 10));
+        assert!(chunks[1].metadata.tags.contains(&"verify".to_string()));
+
+        // A tight size limit splits the embedding section by its child keys
+        let small = SemanticChunker::new(40, 10, 0);
+        let chunks = ConfigFileProcessor::extract_and_chunk(yaml, "rag.yaml", ConfigFormat::Yaml, &small).unwrap();
+        let paths: Vec<_> = chunks.iter().filter_map(|c| c.metadata.section.clone()).collect();
+        assert!(paths.contains(&"embedding.tls".to_string()));
+    }
+
+    #[test]
+    fn test_toml_tables_and_ini_sections() {
+        let toml = "name = \"rag\"\n\n[dependencies]\nserde = \"1\"\n\n# TLS options\n[server.\"tls\"]\nverify = true\n";
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let chunks = ConfigFileProcessor::extract_and_chunk(toml, "Cargo.toml", ConfigFormat::Toml, &chunker).unwrap();
+
+        let paths: Vec<_> = chunks.iter().filter_map(|c| c.metadata.section.as_deref()).collect();
+        assert_eq!(paths, vec!["(root)", "dependencies", "server.tls"]);
+        assert!(chunks[2].content.starts_with("# TLS options"));
+        assert!(chunks[2].metadata.tags.contains(&"verify".to_string()));
+
+        let ini = "; global\ntimeout: 30\n[database]\nhost=localhost\n";
+        let chunks = ConfigFileProcessor::extract_and_chunk(ini, "app.ini", ConfigFormat::Ini, &chunker).unwrap();
+        assert_eq!(chunks[1].metadata.section.as_deref(), Some("database"));
+        assert!(chunks[0].metadata.tags.contains(&"timeout".to_string()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::Result;
+use sha2::{Sha256, Digest};
+
+pub struct CsvProcessor;
+
+impl CsvProcessor {
+    /// Chunk delimited tabular data by groups of rows. Each chunk repeats the header row
+    /// so it stays interpretable on its own, and column names are stored as tags.
+    pub fn extract_and_chunk(content: &str, file_path: &str, delimiter: u8, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)  // Tolerate ragged rows common in hand-edited sheets
+            .from_reader(content.as_bytes());
+
+        let separator = (delimiter as char).to_string();
+        let columns: Vec<String> = reader.headers()?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+        let header_line = columns.join(&separator);
+        let column_tags: Vec<String> = columns.iter()
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_lowercase())
+            .collect();
+
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let max_size = chunker.max_chunk_size();
+
+        let mut chunks = Vec::new();
+        let mut rows: Vec<String> = Vec::new();
+        let mut rows_len = 0;
+        let mut first_line = 0;
+        let mut last_line = 0;
+
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map(|p| p.line() as usize).unwrap_or(last_line + 1);
+            let row = record.iter().collect::<Vec<_>>().join(&separator);
+
+            if !rows.is_empty() && header_line.len() + rows_len + row.len() + 1 > max_size {
+                chunks.push(Self::build_chunk(&header_line, &rows, file_path, &file_hash, (first_line, last_line), &column_tags));
+                rows.clear();
+                rows_len = 0;
+            }
+
+            if rows.is_empty() {
+                first_line = line;
+            }
+            rows_len += row.len() + 1;
+            rows.push(row);
+            last_line = line;
+        }
+
+        if !rows.is_empty() {
+            chunks.push(Self::build_chunk(&header_line, &rows, file_path, &file_hash, (first_line, last_line), &column_tags));
+        }
+
+        Ok(chunks)
+    }
+
+    fn build_chunk(
+        header_line: &str,
+        rows: &[String],
+        file_path: &str,
+        file_hash: &str,
+        lines: (usize, usize),
+        column_tags: &[String],
+    ) -> Chunk {
+        let content = format!("{}\n{}", header_line, rows.join("\n"));
+
+        let mut chunk = SemanticChunker::build_text_chunk(&content, file_path, file_hash, lines, ChunkStrategy::RowGroup);
+        chunk.metadata.chunk_type = ChunkType::Csv;
+        chunk.metadata.section = Some(format!("rows {}-{}", lines.0, lines.1));
+        chunk.metadata.attributes.insert("columns".to_string(), header_line.to_string());
+        for tag in column_tags {
+            if !chunk.metadata.tags.contains(tag) {
+                chunk.metadata.tags.push(tag.clone());
+            }
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_is_repeated_in_each_chunk() {
+        let chunker = SemanticChunker::new(60, 10, 0);
+        let content = "name,offset,width\nCTRL,0x00,32\nSTATUS,0x04,32\nIRQ_EN,0x08,16\nIRQ_STAT,0x0C,16\n";
+
+        let chunks = CsvProcessor::extract_and_chunk(content, "regs.csv", b',', &chunker).unwrap();
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.content.starts_with("name,offset,width\n"));
+            assert!(chunk.metadata.tags.contains(&"offset".to_string()));
+            assert!(matches!(chunk.metadata.chunk_type, ChunkType::Csv));
+        }
+        assert!(chunks.iter().any(|c| c.content.contains("IRQ_STAT")));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,269 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::{Result, anyhow};
+use mailparse::{DispositionType, MailHeaderMap, ParsedMail};
+use sha2::{Sha256, Digest};
+
+pub struct EmailProcessor;
+
+/// Headers and readable body of one message
+struct Message {
+    from: Option<String>,
+    to: Option<String>,
+    date: Option<String>,
+    subject: Option<String>,
+    message_id: Option<String>,
+    in_reply_to: Option<String>,
+    body: String,
+}
+
+impl EmailProcessor {
+    /// Chunk a single .eml message or an mbox archive, one chunk per message. Emails are read
+    /// as raw bytes because each message declares its own charset and transfer encoding.
+    pub fn extract_and_chunk(file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let bytes = std::fs::read(file_path)?;
+        Self::chunk_bytes(&bytes, file_path, chunker)
+    }
+
+    pub fn chunk_bytes(bytes: &[u8], file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(bytes));
+
+        let messages = if bytes.starts_with(b"From ") {
+            Self::split_mbox(bytes)
+        } else {
+            vec![(1, bytes.to_vec())]
+        };
+
+        let mut chunks = Vec::new();
+        for (index, (line, raw)) in messages.iter().enumerate() {
+            let parsed = match mailparse::parse_mail(raw) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    tracing::warn!("Skipping unparseable message {} in {}: {}", index + 1, file_path, e);
+                    continue;
+                }
+            };
+
+            let message = Self::read_message(&parsed);
+            chunks.extend(Self::build_chunks(&message, *line, file_path, &file_hash, chunker)?);
+        }
+
+        if chunks.is_empty() && !messages.is_empty() {
+            return Err(anyhow!("No readable messages found in {}", file_path));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Split an mbox archive on "From " separator lines, undoing ">From " quoting.
+    /// Returns each message with the line its separator was on.
+    fn split_mbox(bytes: &[u8]) -> Vec<(usize, Vec<u8>)> {
+        let mut messages = Vec::new();
+        let mut current: Option<(usize, Vec<u8>)> = None;
+        let mut previous_blank = true;
+
+        for (line_no, line) in bytes.split_inclusive(|&b| b == b'\n').enumerate() {
+            if previous_blank && line.starts_with(b"From ") {
+                if let Some(message) = current.take() {
+                    messages.push(message);
+                }
+                current = Some((line_no + 1, Vec::new()));
+                previous_blank = false;
+                continue;
+            }
+
+            previous_blank = line.iter().all(|b| b.is_ascii_whitespace());
+            if let Some((_, body)) = current.as_mut() {
+                // mboxrd escapes body lines matching ^>*From with one extra '>'
+                let depth = line.iter().take_while(|&&b| b == b'>').count();
+                let unquoted = if depth > 0 && line[depth..].starts_with(b"From ") { &line[1..] } else { line };
+                body.extend_from_slice(unquoted);
+            }
+        }
+
+        if let Some(message) = current {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn read_message(parsed: &ParsedMail) -> Message {
+        let header = |name: &str| parsed.headers.get_first_value(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        // In-Reply-To names the parent directly; otherwise the last References entry does
+        let in_reply_to = header("In-Reply-To")
+            .and_then(|v| Self::message_ids(&v).into_iter().next())
+            .or_else(|| header("References").and_then(|v| Self::message_ids(&v).pop()));
+
+        Message {
+            from: header("From"),
+            to: header("To"),
+            date: header("Date"),
+            subject: header("Subject"),
+            message_id: header("Message-ID").and_then(|v| Self::message_ids(&v).into_iter().next()),
+            in_reply_to,
+            body: Self::readable_body(parsed).unwrap_or_default(),
+        }
+    }
+
+    /// `<a@x> <b@y>` -> ["a@x", "b@y"]
+    fn message_ids(value: &str) -> Vec<String> {
+        value.split(|c: char| c == '<' || c == '>' || c.is_whitespace() || c == ',')
+            .filter(|id| id.contains('@'))
+            .map(|id| id.to_string())
+            .collect()
+    }
+
+    /// Prefer text/plain, fall back to tag-stripped text/html; attachments are skipped
+    fn readable_body(part: &ParsedMail) -> Option<String> {
+        if part.get_content_disposition().disposition == DispositionType::Attachment {
+            return None;
+        }
+
+        if part.subparts.is_empty() {
+            let body = part.get_body().ok()?;
+            return match part.ctype.mimetype.as_str() {
+                "text/plain" => Some(Self::strip_quoted(&body)),
+                "text/html" => Some(Self::strip_quoted(&Self::strip_html(&body))),
+                _ => None,
+            };
+        }
+
+        part.subparts.iter()
+            .find(|p| p.ctype.mimetype == "text/plain" || p.ctype.mimetype.starts_with("multipart/"))
+            .and_then(Self::readable_body)
+            .or_else(|| part.subparts.iter().find_map(Self::readable_body))
+    }
+
+    /// Drop quoted reply text; the quoted message is its own chunk when it is in the corpus
+    fn strip_quoted(body: &str) -> String {
+        body.lines()
+            .filter(|line| !line.trim_start().starts_with('>'))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+
+    fn strip_html(html: &str) -> String {
+        let mut text = String::with_capacity(html.len());
+        let mut in_tag = false;
+        for c in html.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => {
+                    in_tag = false;
+                    text.push(' ');
+                }
+                _ if !in_tag => text.push(c),
+                _ => {}
+            }
+        }
+        text.replace("&nbsp;", " ").replace("&amp;", "&").replace("&lt;", "<").replace("&gt;", ">")
+    }
+
+    fn build_chunks(message: &Message, line: usize, file_path: &str, file_hash: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let mut text = String::new();
+        for (label, value) in [("Subject", &message.subject), ("From", &message.from), ("Date", &message.date)] {
+            if let Some(value) = value {
+                text.push_str(&format!("{}: {}\n", label, value));
+            }
+        }
+        text.push('\n');
+        text.push_str(&message.body);
+        let text = text.trim();
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_text(text, file_path)?
+        } else {
+            vec![SemanticChunker::build_text_chunk(text, file_path, file_hash, (line, line + 1), ChunkStrategy::StructuredRecord)]
+        };
+
+        let sender = message.from.as_deref().and_then(Self::sender_address);
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Email;
+            chunk.metadata.section = message.subject.clone();
+            chunk.metadata.line_start = line;
+
+            let attributes = &mut chunk.metadata.attributes;
+            for (key, value) in [
+                ("from", &message.from),
+                ("to", &message.to),
+                ("date", &message.date),
+                ("subject", &message.subject),
+                ("message_id", &message.message_id),
+                ("in_reply_to", &message.in_reply_to),
+            ] {
+                if let Some(value) = value {
+                    attributes.insert(key.to_string(), value.clone());
+                }
+            }
+            if let Some(timestamp) = message.date.as_deref().and_then(|d| mailparse::dateparse(d).ok()) {
+                attributes.insert("date_unix".to_string(), timestamp.to_string());
+            }
+
+            if let Some(sender) = &sender {
+                if !chunk.metadata.tags.contains(sender) {
+                    chunk.metadata.tags.push(sender.clone());
+                }
+            }
+            for word in message.subject.iter().flat_map(|s| Self::subject_tags(s)) {
+                if !chunk.metadata.tags.contains(&word) {
+                    chunk.metadata.tags.push(word);
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    fn sender_address(from: &str) -> Option<String> {
+        mailparse::addrparse(from).ok()?
+            .extract_single_info()
+            .map(|info| info.addr.to_lowercase())
+    }
+
+    /// Significant subject words, without reply/forward prefixes
+    fn subject_tags(subject: &str) -> Vec<String> {
+        subject.split(|c: char| !c.is_alphanumeric() && c != '_')
+            .map(|w| w.to_lowercase())
+            .filter(|w| w.len() > 3 && !matches!(w.as_str(), "fwd" | "re" | "aw"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbox_messages_keep_thread_headers() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let mbox = b"From alice@example.com Mon Jan  1 00:00:00 2024\n\
+From: Alice <alice@example.com>\n\
+Subject: FIFO depth for DMA\n\
+Message-ID: <root@example.com>\n\
+Date: Mon, 1 Jan 2024 10:00:00 +0000\n\
+\n\
+Should the DMA FIFO be 16 or 32 entries deep?\n\
+>From the spec it is unclear.\n\
+\n\
+From bob@example.com Mon Jan  1 01:00:00 2024\n\
+From: Bob <bob@example.com>\n\
+Subject: Re: FIFO depth for DMA\n\
+Message-ID: <reply@example.com>\n\
+In-Reply-To: <root@example.com>\n\
+\n\
+> Should the DMA FIFO be 16 or 32 entries deep?\n\
+32, to cover the worst-case burst.\n";
+
+        let chunks = EmailProcessor::chunk_bytes(mbox, "dma.mbox", &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].content.contains("From the spec it is unclear."));
+        assert!(chunks[0].metadata.tags.contains(&"alice@example.com".to_string()));
+        assert!(chunks[0].metadata.tags.contains(&"fifo".to_string()));
+        assert_eq!(chunks[1].metadata.attributes.get("in_reply_to").map(|s| s.as_str()), Some("root@example.com"));
+        assert!(!chunks[1].content.contains("> Should"));
+        assert!(chunks[1].content.contains("worst-case burst"));
+    }
+}
\ No newline at end of file
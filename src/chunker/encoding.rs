@@ -0,0 +1,79 @@
+use encoding_rs::{Encoding, UTF_8};
+
+/// Text decoded to UTF-8 along with the encoding it was read as
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: String,
+    pub had_bom: bool,
+    pub had_errors: bool,  // Some bytes were replaced with U+FFFD
+}
+
+pub struct EncodingDetector;
+
+impl EncodingDetector {
+    /// Decode raw file bytes to UTF-8, honoring a BOM when present and otherwise
+    /// guessing the encoding (Latin-1/Windows-1252, Shift_JIS, ...) with chardetng
+    pub fn decode(bytes: &[u8]) -> DecodedText {
+        // A BOM is authoritative (UTF-8, UTF-16LE, UTF-16BE)
+        if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+            let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+            return DecodedText {
+                text: text.into_owned(),
+                encoding: encoding.name().to_string(),
+                had_bom: true,
+                had_errors,
+            };
+        }
+
+        // Plain UTF-8 is by far the common case, so skip detection when it is valid
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return DecodedText {
+                text: text.to_string(),
+                encoding: UTF_8.name().to_string(),
+                had_bom: false,
+                had_errors: false,
+            };
+        }
+
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        let encoding = detector.guess(None, true);
+
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        DecodedText {
+            text: text.into_owned(),
+            encoding: encoding.name().to_string(),
+            had_bom: false,
+            had_errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_bom_is_stripped() {
+        let decoded = EncodingDetector::decode(b"\xEF\xBB\xBFhello");
+        assert_eq!(decoded.text, "hello");
+        assert_eq!(decoded.encoding, "UTF-8");
+        assert!(decoded.had_bom);
+    }
+
+    #[test]
+    fn test_utf16le_is_transcoded() {
+        let decoded = EncodingDetector::decode(b"\xFF\xFEh\x00i\x00");
+        assert_eq!(decoded.text, "hi");
+        assert_eq!(decoded.encoding, "UTF-16LE");
+    }
+
+    #[test]
+    fn test_latin1_is_detected() {
+        // "café crème" in ISO-8859-1 is not valid UTF-8
+        let decoded = EncodingDetector::decode(b"caf\xE9 cr\xE8me br\xFBl\xE9e");
+        assert_eq!(decoded.text, "café crème brûlée");
+        assert!(!decoded.had_errors);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,420 @@
+use super::{Chunk, SemanticChunker};
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Sha256, Digest};
+use std::sync::OnceLock;
+
+/// Section for lines outside any design unit: `include/`define, timescale, library clauses
+const HEADER_SECTION: &str = "(header)";
+
+/// SystemVerilog/Verilog blocks and the keyword closing each
+const SV_BLOCKS: &[(&str, &str)] = &[
+    ("module", "endmodule"),
+    ("macromodule", "endmodule"),
+    ("interface", "endinterface"),
+    ("program", "endprogram"),
+    ("package", "endpackage"),
+    ("class", "endclass"),
+    ("function", "endfunction"),
+    ("task", "endtask"),
+    ("checker", "endchecker"),
+    ("primitive", "endprimitive"),
+    ("covergroup", "endgroup"),
+];
+
+/// A design unit (module, class, entity, ...) or a member inside one (task, function, process)
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    kind: String,
+    name: String,
+    extends: Option<String>,
+    first_line: usize,   // 1-based, including the comment block directly above
+    last_line: usize,
+    members: Vec<Block>,
+}
+
+/// The file being chunked
+struct Source<'a> {
+    language: &'a str,
+    file_path: &'a str,
+    file_hash: &'a str,
+}
+
+pub struct HdlProcessor;
+
+impl HdlProcessor {
+    /// Chunk SystemVerilog, Verilog or VHDL source along design-unit boundaries: one chunk per
+    /// module/interface/package/class (entity/architecture/package for VHDL) with the
+    /// comment above it. Units larger than max_chunk_size are split into their tasks,
+    /// functions and processes, each recorded as `unit::member`, plus the remaining
+    /// declarations. `include and import dependencies from the file are kept on every chunk.
+    pub fn extract_and_chunk(content: &str, language: &str, file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let lines: Vec<&str> = content.lines().collect();
+        let units = if language == "vhdl" { Self::vhdl_units(&lines) } else { Self::sv_units(&lines) };
+
+        let mut covered = vec![false; lines.len()];
+        for unit in &units {
+            covered[unit.first_line - 1..unit.last_line].iter_mut().for_each(|c| *c = true);
+        }
+        let header: Vec<usize> = (1..=lines.len()).filter(|&line| !covered[line - 1]).collect();
+        let file_dependencies = SemanticChunker::extract_dependencies(&Self::join(&lines, &header), language);
+        let source = Source { language, file_path, file_hash: &file_hash };
+
+        let mut chunks = Vec::new();
+        if header.iter().any(|&line| !lines[line - 1].trim().is_empty()) {
+            let text = Self::join(&lines, &header);
+            let range = (header[0], header[header.len() - 1]);
+            chunks.extend(Self::build_chunks(&text, range, HEADER_SECTION, None, &source, chunker)?);
+        }
+
+        for unit in &units {
+            let unit_lines: Vec<usize> = (unit.first_line..=unit.last_line).collect();
+            let text = Self::join(&lines, &unit_lines);
+            let range = (unit.first_line, unit.last_line);
+
+            if text.len() <= chunker.max_chunk_size() || unit.members.is_empty() {
+                chunks.extend(Self::build_chunks(&text, range, &unit.name, Some(unit), &source, chunker)?);
+                continue;
+            }
+
+            // Members in their own chunks, the unit's declarations in one more
+            let mut rest = unit_lines.clone();
+            for member in &unit.members {
+                rest.retain(|line| *line < member.first_line || *line > member.last_line);
+                let member_lines: Vec<usize> = (member.first_line..=member.last_line).collect();
+                let section = format!("{}::{}", unit.name, member.name);
+                let range = (member.first_line, member.last_line);
+                chunks.extend(Self::build_chunks(&Self::join(&lines, &member_lines), range, &section, Some(member), &source, chunker)?);
+            }
+            chunks.extend(Self::build_chunks(&Self::join(&lines, &rest), range, &unit.name, Some(unit), &source, chunker)?);
+        }
+
+        for chunk in &mut chunks {
+            for dependency in &file_dependencies {
+                if !chunk.metadata.dependencies.contains(dependency) {
+                    chunk.metadata.dependencies.push(dependency.clone());
+                }
+            }
+        }
+
+        chunks.sort_by_key(|chunk| chunk.metadata.line_start);
+        Ok(chunks)
+    }
+
+    /// Units and their members from matching SystemVerilog/Verilog open and end keywords.
+    /// Prototypes without bodies (`extern`, `pure virtual`, DPI imports, `typedef class`)
+    /// and `virtual interface` handles are not blocks.
+    fn sv_units(lines: &[&str]) -> Vec<Block> {
+        static WORD: OnceLock<Regex> = OnceLock::new();
+        let word = WORD.get_or_init(|| Regex::new(r"[A-Za-z_][\w$]*(?:::[A-Za-z_][\w$]*)*").unwrap());
+
+        let mut units: Vec<Block> = Vec::new();
+        let mut stack: Vec<Block> = Vec::new();
+        let mut in_comment = false;
+        let mut comment_start: Option<usize> = None;
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            let code = Self::strip_comments(line, &mut in_comment, "//");
+            let tokens: Vec<(usize, &str)> = word.find_iter(&code).map(|m| (m.start(), m.as_str())).collect();
+
+            for (position, &(offset, token)) in tokens.iter().enumerate() {
+                if let Some(&(opener, _)) = SV_BLOCKS.iter().find(|(opener, _)| *opener == token) {
+                    let statement_start = code[..offset].rfind(';').map(|i| i + 1).unwrap_or(0);
+                    let before: Vec<&str> = tokens[..position].iter()
+                        .filter(|(o, _)| *o >= statement_start)
+                        .map(|(_, t)| *t)
+                        .collect();
+                    let prototype = before.iter().any(|t| matches!(*t, "extern" | "pure" | "typedef" | "import" | "export"));
+                    let handle = opener == "interface" && (before.last() == Some(&"virtual") || tokens.get(position + 1).is_some_and(|(_, t)| *t == "class"));
+                    if prototype || handle {
+                        continue;
+                    }
+
+                    let rest = &code[offset + token.len()..];
+                    let first_line = if stack.len() <= 1 { comment_start.unwrap_or(line_number) } else { line_number };
+                    stack.push(Block {
+                        kind: opener.to_string(),
+                        name: Self::sv_name(opener, rest),
+                        extends: Self::sv_extends(rest),
+                        first_line,
+                        last_line: line_number,
+                        members: Vec::new(),
+                    });
+                } else if SV_BLOCKS.iter().any(|(_, closer)| *closer == token) {
+                    // Close the nearest matching block so a missing end keyword cannot swallow the file
+                    let Some(depth) = stack.iter().rposition(|block| SV_BLOCKS.contains(&(block.kind.as_str(), token))) else {
+                        continue;
+                    };
+                    let mut block = stack.remove(depth);
+                    stack.truncate(depth);
+                    block.last_line = line_number;
+                    match stack.len() {
+                        0 => units.push(block),
+                        1 => stack[0].members.push(block),
+                        _ => {}
+                    }
+                }
+            }
+
+            // Comment lines directly above a unit or member belong to it
+            let is_comment = code.trim().is_empty() && !line.trim().is_empty();
+            if is_comment {
+                comment_start.get_or_insert(line_number);
+            } else {
+                comment_start = None;
+            }
+        }
+
+        // Unterminated units run to the end of the file
+        if let Some(mut unit) = stack.into_iter().next() {
+            unit.last_line = lines.len();
+            units.push(unit);
+        }
+        units
+    }
+
+    /// The declared name: the first identifier for units, the last before the argument list
+    /// for tasks and functions (`function automatic bit [7:0] crc(`, `task my_driver::run_phase(`)
+    fn sv_name(kind: &str, rest: &str) -> String {
+        let declaration = rest.split(['(', ';', '#']).next().unwrap_or(rest);
+        let words: Vec<&str> = declaration.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '$'))
+            .filter(|w| !w.is_empty() && !w.starts_with(|c: char| c.is_ascii_digit()))
+            .collect();
+
+        let name = match kind {
+            "function" | "task" => words.last(),
+            _ => words.iter().find(|w| !matches!(**w, "automatic" | "static" | "virtual")),
+        };
+        name.map(|n| n.trim_matches(':').to_string()).unwrap_or_else(|| kind.to_string())
+    }
+
+    fn sv_extends(rest: &str) -> Option<String> {
+        static EXTENDS: OnceLock<Regex> = OnceLock::new();
+        let extends = EXTENDS.get_or_init(|| Regex::new(r"\bextends\s+([\w:]+)").unwrap());
+        extends.captures(rest.split(';').next().unwrap_or(rest)).map(|c| c[1].to_string())
+    }
+
+    /// VHDL entities, architectures, packages (and bodies) and configurations, with the
+    /// processes and subprogram bodies inside them. VHDL is case-insensitive; a unit ends at
+    /// `end [kind] [name];` or where the next unit starts.
+    fn vhdl_units(lines: &[&str]) -> Vec<Block> {
+        static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+        let (unit_start, member_start, end) = PATTERNS.get_or_init(|| (
+            Regex::new(r"(?i)^\s*(entity|architecture|package\s+body|package|configuration)\s+(\w+)(?:\s+of\s+(\w+))?\s+is\b").unwrap(),
+            Regex::new(r"(?i)^\s*(?:(\w+)\s*:\s*)?(?:postponed\s+)?(process)\b|^\s*(?:pure\s+|impure\s+)?(function|procedure)\s+(\w+)").unwrap(),
+            Regex::new(r"(?i)^\s*end\b\s*(entity|architecture|package\s+body|package|configuration|process|function|procedure)?\s*(\w+)?\s*;").unwrap(),
+        ));
+
+        let mut units: Vec<Block> = Vec::new();
+        let mut unit: Option<Block> = None;
+        let mut member: Option<Block> = None;
+        let mut in_comment = false;
+        let mut comment_start: Option<usize> = None;
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            let code = Self::strip_comments(line, &mut in_comment, "--");
+            let leading = comment_start.unwrap_or(line_number);
+
+            if let Some(captures) = unit_start.captures(&code) {
+                units.extend(unit.take().map(|mut open| {
+                    open.last_line = leading - 1;
+                    open
+                }));
+                member = None;
+                let kind = captures[1].split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+                unit = Some(Block {
+                    kind,
+                    name: captures[2].to_string(),
+                    extends: captures.get(3).map(|m| m.as_str().to_string()),
+                    first_line: leading,
+                    last_line: line_number,
+                    members: Vec::new(),
+                });
+            } else if let Some(open) = unit.as_mut() {
+                if member.is_none() {
+                    if let Some(captures) = member_start.captures(&code) {
+                        // Subprogram declarations in a package header end at `;` with no `is`
+                        let is_body = captures.get(2).is_some() || code.to_lowercase().contains(" is") || !code.contains(';');
+                        if is_body {
+                            let (kind, name) = match captures.get(2) {
+                                Some(process) => (process.as_str().to_lowercase(), captures.get(1).map(|m| m.as_str().to_string()).unwrap_or_else(|| format!("process@{}", line_number))),
+                                None => (captures[3].to_lowercase(), captures[4].to_string()),
+                            };
+                            member = Some(Block { kind, name, extends: None, first_line: leading, last_line: line_number, members: Vec::new() });
+                        }
+                    }
+                }
+
+                if let Some(captures) = end.captures(&code) {
+                    let kind = captures.get(1).map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+                    let name = captures.get(2).map(|m| m.as_str());
+                    let closes = |block: &Block| match (&kind, name) {
+                        (Some(kind), _) => *kind == block.kind,
+                        (None, Some(name)) => name.eq_ignore_ascii_case(&block.name),
+                        (None, None) => false,
+                    };
+
+                    if let Some(mut closed) = member.take_if(|m| closes(m)) {
+                        closed.last_line = line_number;
+                        open.members.push(closed);
+                    } else if member.is_none() && closes(open) {
+                        open.last_line = line_number;
+                        units.extend(unit.take());
+                    }
+                }
+            }
+
+            let is_comment = code.trim().is_empty() && !line.trim().is_empty();
+            if is_comment {
+                comment_start.get_or_insert(line_number);
+            } else {
+                comment_start = None;
+            }
+        }
+
+        units.extend(unit.map(|mut open| {
+            open.last_line = lines.len();
+            open
+        }));
+        units
+    }
+
+    /// Code on a line with line comments, `/* */` comments and string literals removed
+    fn strip_comments(line: &str, in_comment: &mut bool, line_comment: &str) -> String {
+        let mut code = String::new();
+        let mut rest = line;
+        let mut in_string = false;
+
+        while let Some(c) = rest.chars().next() {
+            if *in_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        rest = &rest[end + 2..];
+                        *in_comment = false;
+                    }
+                    None => break,
+                }
+                continue;
+            }
+            if in_string {
+                if c == '"' {
+                    in_string = false;
+                }
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            if rest.starts_with(line_comment) {
+                break;
+            }
+            if rest.starts_with("/*") {
+                *in_comment = true;
+                rest = &rest[2..];
+                continue;
+            }
+            if c == '"' {
+                in_string = true;
+            } else {
+                code.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        code
+    }
+
+    fn join(lines: &[&str], line_numbers: &[usize]) -> String {
+        line_numbers.iter().map(|&line| lines[line - 1]).collect::<Vec<_>>().join("\n")
+    }
+
+    fn build_chunks(text: &str, range: (usize, usize), section: &str, block: Option<&Block>, source: &Source, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_code(text, source.language, source.file_path)?
+        } else {
+            vec![SemanticChunker::build_code_chunk(text, source.language, source.file_path, source.file_hash, range)]
+        };
+
+        for chunk in &mut chunks {
+            let metadata = &mut chunk.metadata;
+            metadata.section = Some(section.to_string());
+            metadata.line_start = range.0;
+            metadata.line_end = range.1;
+
+            let Some(block) = block else {
+                continue;
+            };
+            metadata.attributes.insert("hdl_unit".to_string(), block.kind.clone());
+            let mut tags = vec![block.name.to_lowercase()];
+            if let Some(extends) = &block.extends {
+                // `extends` for classes; the entity an architecture implements
+                metadata.attributes.insert("extends".to_string(), extends.clone());
+                tags.push(extends.to_lowercase());
+            }
+            for tag in tags {
+                if !metadata.tags.contains(&tag) {
+                    metadata.tags.push(tag);
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uvm_classes_split_into_tasks() {
+        let sv = r#"`include "uvm_macros.svh"
+import uvm_pkg::*;
+
+// Drives AXI bursts
+class axi_driver extends uvm_driver #(axi_item);
+  `uvm_component_utils(axi_driver)
+  virtual axi_if vif;
+  extern function void build_phase(uvm_phase phase);
+
+  task run_phase(uvm_phase phase);
+    forever begin
+      seq_item_port.get_next_item(req);
+      drive(req);
+      seq_item_port.item_done();
+    end
+  endtask
+
+  function void report(); // endfunction in a comment is ignored
+    $display("endclass");
+  endfunction
+endclass : axi_driver
+
+module top;
+endmodule
+"#;
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let chunks = HdlProcessor::extract_and_chunk(sv, "systemverilog", "axi_driver.sv", &chunker).unwrap();
+        let sections: Vec<_> = chunks.iter().filter_map(|c| c.metadata.section.as_deref()).collect();
+        assert_eq!(sections, vec![HEADER_SECTION, "axi_driver", "top"]);
+        assert_eq!((chunks[1].metadata.line_start, chunks[1].metadata.line_end), (4, 21));
+        assert!(chunks[1].metadata.tags.contains(&"uvm_driver".to_string()));
+        assert_eq!(chunks[2].metadata.dependencies, vec!["uvm_macros.svh", "uvm_pkg"]);
+
+        // Past the size limit the class is split into its tasks and functions
+        let small = SemanticChunker::new(240, 10, 0);
+        let chunks = HdlProcessor::extract_and_chunk(sv, "systemverilog", "axi_driver.sv", &small).unwrap();
+        let sections: Vec<_> = chunks.iter().filter_map(|c| c.metadata.section.as_deref()).collect();
+        assert_eq!(sections, vec![HEADER_SECTION, "axi_driver", "axi_driver::run_phase", "axi_driver::report", "top"]);
+
+        let vhdl = "library ieee;\nuse ieee.std_logic_1164.all;\n\n-- Counter\nentity counter is\n  port (clk : in std_logic);\nend entity counter;\n\narchitecture rtl of counter is\nbegin\n  tick : process (clk)\n  begin\n  end process tick;\nend rtl;\n";
+        let units = HdlProcessor::vhdl_units(&vhdl.lines().collect::<Vec<_>>());
+        assert_eq!(units.iter().map(|u| (u.name.as_str(), u.first_line, u.last_line)).collect::<Vec<_>>(), vec![("counter", 4, 7), ("rtl", 9, 14)]);
+        assert_eq!(units[1].members[0].name, "tick");
+    }
+}
\ No newline at end of file
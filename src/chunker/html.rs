@@ -0,0 +1,195 @@
+use super::{Chunk, SemanticChunker};
+use super::markdown::MarkdownProcessor;
+use anyhow::Result;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Elements whose content is never page text
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "svg", "nav", "head"];
+
+pub struct HtmlProcessor;
+
+impl HtmlProcessor {
+    /// Chunk an HTML page by its heading hierarchy: the page is converted to markdown
+    /// (headings, lists, preformatted blocks) and chunked like a markdown document, with
+    /// the `<title>` recorded as the document title.
+    pub fn extract_and_chunk(content: &str, file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let markdown = Self::to_markdown(content);
+        let mut chunks = MarkdownProcessor::extract_and_chunk(&markdown, file_path, chunker)?;
+
+        if let Some(title) = Self::title(content) {
+            for chunk in &mut chunks {
+                chunk.metadata.title.get_or_insert_with(|| title.clone());
+            }
+        }
+        Ok(chunks)
+    }
+
+    pub fn title(html: &str) -> Option<String> {
+        static TITLE: OnceLock<Regex> = OnceLock::new();
+        let title = TITLE.get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+        let text = Self::collapse_whitespace(&Self::decode_entities(title.captures(html)?.get(1)?.as_str()));
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Markdown rendering of the page body; markup without a markdown equivalent is dropped
+    pub fn to_markdown(html: &str) -> String {
+        static TAG: OnceLock<Regex> = OnceLock::new();
+        let tag = TAG.get_or_init(|| Regex::new(r"(?s)<!--.*?-->|<[!?][^>]*>|<(/?)([a-zA-Z][a-zA-Z0-9]*)[^>]*>").unwrap());
+
+        let mut markdown = String::new();
+        let mut skipping: Option<String> = None;
+        let mut pre_depth: usize = 0;
+        let mut position = 0;
+
+        for captures in tag.captures_iter(html) {
+            let whole = captures.get(0).unwrap();
+            if skipping.is_none() {
+                Self::push_text(&mut markdown, &html[position..whole.start()], pre_depth > 0);
+            }
+            position = whole.end();
+
+            // Comments, doctype and processing instructions
+            let Some(name) = captures.get(2) else { continue };
+            let name = name.as_str().to_lowercase();
+            let closing = !captures[1].is_empty();
+
+            if let Some(skipped) = &skipping {
+                if closing && *skipped == name {
+                    skipping = None;
+                }
+                continue;
+            }
+            if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) && !whole.as_str().ends_with("/>") {
+                skipping = Some(name);
+                continue;
+            }
+
+            match (name.as_str(), closing) {
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                    let level = name[1..].parse::<usize>().unwrap_or(1);
+                    markdown.push_str(&format!("\n\n{} ", "#".repeat(level)));
+                }
+                ("pre", false) => {
+                    pre_depth += 1;
+                    markdown.push_str("\n\n```\n");
+                }
+                ("pre", true) => {
+                    pre_depth = pre_depth.saturating_sub(1);
+                    markdown.push_str("\n```\n\n");
+                }
+                ("li", false) => markdown.push_str("\n- "),
+                ("br", _) => markdown.push('\n'),
+                ("td" | "th", false) => markdown.push(' '),
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "div" | "section" | "article" | "main" | "header" | "footer"
+                    | "table" | "tr" | "ul" | "ol" | "dl" | "dt" | "dd" | "blockquote" | "figure" | "figcaption" | "hr", _) => {
+                    markdown.push_str("\n\n");
+                }
+                _ => {}
+            }
+        }
+        if skipping.is_none() {
+            Self::push_text(&mut markdown, &html[position..], false);
+        }
+
+        Self::tidy(&markdown)
+    }
+
+    fn push_text(markdown: &mut String, raw: &str, preformatted: bool) {
+        let text = Self::decode_entities(raw);
+        if preformatted {
+            markdown.push_str(&text);
+            return;
+        }
+
+        // Runs of whitespace render as one space, and none at the start of a line
+        let needs_space = !markdown.is_empty() && !markdown.ends_with([' ', '\n']);
+        let collapsed = Self::collapse_whitespace(&text);
+        if collapsed.is_empty() {
+            if !text.is_empty() && needs_space {
+                markdown.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) && needs_space {
+            markdown.push(' ');
+        }
+        markdown.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) {
+            markdown.push(' ');
+        }
+    }
+
+    fn collapse_whitespace(text: &str) -> String {
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn decode_entities(text: &str) -> String {
+        static ENTITY: OnceLock<Regex> = OnceLock::new();
+        let entity = ENTITY.get_or_init(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+        entity.replace_all(text, |captures: &regex::Captures| {
+            let name = &captures[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ if name.starts_with("#x") || name.starts_with("#X") => u32::from_str_radix(&name[2..], 16).ok().and_then(char::from_u32),
+                _ if name.starts_with('#') => name[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            decoded.map(String::from).unwrap_or_else(|| captures[0].to_string())
+        }).into_owned()
+    }
+
+    /// Trim trailing spaces and collapse the blank lines left by nested block elements
+    fn tidy(markdown: &str) -> String {
+        let mut tidy = String::new();
+        let mut blank_lines = 0;
+        for line in markdown.lines() {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !tidy.is_empty() {
+                tidy.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            tidy.push_str(line);
+            blank_lines = 0;
+        }
+        tidy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_converts_to_headed_markdown() {
+        let html = r#"<!DOCTYPE html><html><head><title>DMA Guide &amp; Reference</title><style>p { color: red }</style></head>
+<body><nav><a href="/">Home</a></nav>
+<h1>DMA Guide</h1><!-- generated -->
+<h2 id="reset">Reset   <code>sequence</code></h2>
+<p>Hold <b>rst_n</b> low for
+   16 cycles &lt;before&gt; programming.</p>
+<ul><li>Clear status</li><li>Enable&nbsp;IRQ</li></ul>
+<pre>if (rst)
+  state &lt;= IDLE;</pre>
+<script>alert("x")</script></body></html>"#;
+
+        let markdown = HtmlProcessor::to_markdown(html);
+        assert_eq!(markdown, "# DMA Guide\n\n## Reset sequence\n\nHold rst_n low for 16 cycles <before> programming.\n\n- Clear status\n- Enable IRQ\n\n```\nif (rst)\n  state <= IDLE;\n```");
+
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let chunks = HtmlProcessor::extract_and_chunk(html, "https://example.com/dma.html", &chunker).unwrap();
+        let reset = chunks.iter().find(|c| c.content.contains("16 cycles")).unwrap();
+        assert_eq!(reset.metadata.section.as_deref(), Some("Reset sequence"));
+        assert_eq!(reset.metadata.title.as_deref(), Some("DMA Guide & Reference"));
+        assert!(!chunks.iter().any(|c| c.content.contains("alert") || c.content.contains("Home")));
+    }
+}
\ No newline at end of file
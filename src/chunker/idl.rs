@@ -0,0 +1,325 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Sha256, Digest};
+use std::sync::OnceLock;
+
+/// Section for lines outside any definition: syntax, package, imports, options and, for
+/// Avro, the protocol's messages
+const HEADER_SECTION: &str = "(header)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlFormat {
+    Protobuf,
+    Thrift,
+    Avro,
+}
+
+impl IdlFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "proto" => Some(IdlFormat::Protobuf),
+            "thrift" => Some(IdlFormat::Thrift),
+            "avdl" => Some(IdlFormat::Avro),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            IdlFormat::Protobuf => "protobuf",
+            IdlFormat::Thrift => "thrift",
+            IdlFormat::Avro => "avro",
+        }
+    }
+
+    /// Brace depth of top-level definitions: Avro IDL nests them in a `protocol` block
+    fn definition_depth(&self) -> i32 {
+        match self {
+            IdlFormat::Avro => 1,
+            IdlFormat::Protobuf | IdlFormat::Thrift => 0,
+        }
+    }
+}
+
+/// One message/service/enum/... definition, or the header lines outside them
+#[derive(Debug)]
+struct Definition<'a> {
+    kind: String,
+    name: String,
+    first_line: usize,
+    last_line: usize,
+    lines: Vec<&'a str>,
+}
+
+pub struct IdlProcessor;
+
+impl IdlProcessor {
+    /// Chunk a Protocol Buffers, Thrift or Avro IDL file into one chunk per top-level
+    /// definition (message, service, enum, struct, record, ...), with the doc comment above
+    /// it. The type name is the chunk's section and the file's imports are recorded as
+    /// dependencies on every chunk. Nested definitions stay inside their parent.
+    pub fn extract_and_chunk(content: &str, file_path: &str, format: IdlFormat, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let lines: Vec<&str> = content.lines().collect();
+        let imports = Self::imports(content, format);
+        let package = Self::package(content, format);
+
+        let mut chunks = Vec::new();
+        for definition in Self::definitions(&lines, format) {
+            chunks.extend(Self::build_chunks(&definition, format, package.as_deref(), &imports, file_path, &file_hash, chunker)?);
+        }
+        Ok(chunks)
+    }
+
+    fn definitions<'a>(lines: &[&'a str], format: IdlFormat) -> Vec<Definition<'a>> {
+        static DEFINITION: OnceLock<Regex> = OnceLock::new();
+        let definition = DEFINITION.get_or_init(|| Regex::new(
+            r"^\s*(?:@\w+(?:\([^)]*\))?\s+)*(message|service|enum|extend|struct|union|exception|senum|typedef|const|record|error|fixed)\s+(?:[\w.<>, ]+?\s+)??([A-Za-z_][\w.]*)\s*(?:[{=(;]|extends\b|$)"
+        ).unwrap());
+
+        let level = format.definition_depth();
+        let mut definitions = Vec::new();
+        let mut header = Definition { kind: "header".to_string(), name: HEADER_SECTION.to_string(), first_line: 0, last_line: 0, lines: Vec::new() };
+        let mut current: Option<Definition> = None;
+        let mut pending: Vec<&str> = Vec::new();  // Comment lines directly above the next line
+        let mut pending_start = 0;
+        let mut depth = 0;
+        let mut in_comment = false;
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            let code = Self::strip_comments(line, &mut in_comment, format);
+            let delta = code.matches('{').count() as i32 - code.matches('}').count() as i32;
+
+            if let Some(mut open) = current.take() {
+                open.lines.push(line);
+                open.last_line = line_number;
+                depth += delta;
+                if depth <= level {
+                    definitions.push(open);
+                } else {
+                    current = Some(open);
+                }
+                continue;
+            }
+
+            let trimmed = code.trim();
+            let captures = (depth == level).then(|| definition.captures(&code)).flatten();
+            if let Some(captures) = captures {
+                let mut lines = std::mem::take(&mut pending);
+                let first_line = if lines.is_empty() { line_number } else { pending_start };
+                lines.push(line);
+                let open = Definition {
+                    kind: captures[1].to_string(),
+                    name: captures[2].to_string(),
+                    first_line,
+                    last_line: line_number,
+                    lines,
+                };
+
+                depth += delta;
+                if depth <= level {
+                    definitions.push(open);
+                } else {
+                    current = Some(open);
+                }
+            } else if trimmed.is_empty() && !line.trim().is_empty() {
+                // Comment-only line, kept for the definition it documents
+                if pending.is_empty() {
+                    pending_start = line_number;
+                }
+                pending.push(line);
+            } else {
+                if !line.trim().is_empty() || !header.lines.is_empty() {
+                    if header.lines.is_empty() {
+                        header.first_line = if pending.is_empty() { line_number } else { pending_start };
+                    }
+                    header.lines.append(&mut pending);
+                    header.lines.push(line);
+                    header.last_line = line_number;
+                }
+                pending.clear();
+                depth += delta;
+            }
+        }
+
+        // An unterminated definition keeps what it has
+        definitions.extend(current);
+        if header.lines.iter().any(|line| !line.trim().is_empty()) {
+            definitions.insert(0, header);
+        }
+        definitions
+    }
+
+    /// Code on a line with `//`, `/* */` (and, for Thrift, `#`) comments and string literals
+    /// removed, so braces inside them do not count
+    fn strip_comments(line: &str, in_comment: &mut bool, format: IdlFormat) -> String {
+        let mut code = String::new();
+        let mut chars = line.chars().peekable();
+        let mut in_string = false;
+
+        while let Some(c) = chars.next() {
+            if *in_comment {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    *in_comment = false;
+                }
+                continue;
+            }
+            if in_string {
+                if c == '\\' {
+                    chars.next();
+                } else if c == '"' {
+                    in_string = false;
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '/' if chars.peek() == Some(&'/') => break,
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    *in_comment = true;
+                }
+                '#' if format == IdlFormat::Thrift => break,
+                _ => code.push(c),
+            }
+        }
+        code
+    }
+
+    /// Files this one imports: proto `import`, Thrift `include`, Avro `import idl|protocol|schema`
+    fn imports(content: &str, format: IdlFormat) -> Vec<String> {
+        static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+        let (proto, thrift, avro) = PATTERNS.get_or_init(|| (
+            Regex::new(r#"(?m)^\s*import\s+(?:public\s+|weak\s+)?"([^"]+)"\s*;"#).unwrap(),
+            Regex::new(r#"(?m)^\s*(?:cpp_)?include\s+"([^"]+)""#).unwrap(),
+            Regex::new(r#"(?m)^\s*import\s+(?:idl|protocol|schema)\s+"([^"]+)"\s*;"#).unwrap(),
+        ));
+
+        let pattern = match format {
+            IdlFormat::Protobuf => proto,
+            IdlFormat::Thrift => thrift,
+            IdlFormat::Avro => avro,
+        };
+        let mut imports: Vec<String> = Vec::new();
+        for captures in pattern.captures_iter(content) {
+            if !imports.contains(&captures[1].to_string()) {
+                imports.push(captures[1].to_string());
+            }
+        }
+        imports
+    }
+
+    /// proto `package`, the first Thrift `namespace`, or Avro `@namespace`
+    fn package(content: &str, format: IdlFormat) -> Option<String> {
+        static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+        let (proto, thrift, avro) = PATTERNS.get_or_init(|| (
+            Regex::new(r"(?m)^\s*package\s+([\w.]+)\s*;").unwrap(),
+            Regex::new(r"(?m)^\s*namespace\s+[\w.*]+\s+([\w.]+)").unwrap(),
+            Regex::new(r#"@namespace\(\s*"([\w.]+)"\s*\)"#).unwrap(),
+        ));
+
+        let pattern = match format {
+            IdlFormat::Protobuf => proto,
+            IdlFormat::Thrift => thrift,
+            IdlFormat::Avro => avro,
+        };
+        pattern.captures(content).map(|c| c[1].to_string())
+    }
+
+    fn build_chunks(
+        definition: &Definition,
+        format: IdlFormat,
+        package: Option<&str>,
+        imports: &[String],
+        file_path: &str,
+        file_hash: &str,
+        chunker: &SemanticChunker,
+    ) -> Result<Vec<Chunk>> {
+        let text = definition.lines.join("\n");
+        let text = text.trim();
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_text(text, file_path)?
+        } else {
+            vec![SemanticChunker::build_text_chunk(text, file_path, file_hash, (definition.first_line, definition.last_line), ChunkStrategy::CodeStructure)]
+        };
+
+        let is_header = definition.name == HEADER_SECTION;
+        let qualified_name = match package {
+            Some(package) if !is_header => format!("{}.{}", package, definition.name),
+            _ => definition.name.clone(),
+        };
+
+        for chunk in &mut chunks {
+            let metadata = &mut chunk.metadata;
+            metadata.chunk_type = ChunkType::Schema;
+            metadata.language = Some(format.as_str().to_string());
+            metadata.section = Some(definition.name.clone());
+            metadata.line_start = definition.first_line;
+            metadata.line_end = definition.last_line;
+            metadata.dependencies = imports.to_vec();
+
+            let attributes = &mut metadata.attributes;
+            attributes.insert("idl_format".to_string(), format.as_str().to_string());
+            attributes.insert("definition_kind".to_string(), definition.kind.clone());
+            if let Some(package) = package {
+                attributes.insert("package".to_string(), package.to_string());
+            }
+            if !is_header {
+                attributes.insert("type_name".to_string(), qualified_name.clone());
+                let tag = definition.name.to_lowercase();
+                if !metadata.tags.contains(&tag) {
+                    metadata.tags.push(tag);
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proto_definitions_and_imports() {
+        let proto = r#"syntax = "proto3";
+package dma.v1;
+
+import "google/protobuf/empty.proto";
+import public "dma/v1/common.proto";
+
+// A single descriptor. Braces in comments { are ignored.
+message Descriptor {
+  uint64 src = 1;
+  message Flags {
+    bool irq = 1;
+  }
+  string label = 2 [default = "}"];
+}
+
+service DmaControl {
+  rpc Start(Descriptor) returns (google.protobuf.Empty);
+}
+"#;
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let chunks = IdlProcessor::extract_and_chunk(proto, "dma.proto", IdlFormat::Protobuf, &chunker).unwrap();
+
+        let sections: Vec<_> = chunks.iter().filter_map(|c| c.metadata.section.as_deref()).collect();
+        assert_eq!(sections, vec![HEADER_SECTION, "Descriptor", "DmaControl"]);
+        assert!(chunks[1].content.starts_with("// A single descriptor"));
+        assert!(chunks[1].content.contains("message Flags"));
+        assert_eq!((chunks[1].metadata.line_start, chunks[1].metadata.line_end), (7, 14));
+        assert_eq!(chunks[1].metadata.attributes.get("type_name").map(|s| s.as_str()), Some("dma.v1.Descriptor"));
+        assert_eq!(chunks[2].metadata.dependencies, vec!["google/protobuf/empty.proto", "dma/v1/common.proto"]);
+
+        let avro = "@namespace(\"org.dma\")\nprotocol Dma {\n  import idl \"common.avdl\";\n  record Descriptor {\n    long src;\n  }\n  void start(Descriptor d);\n}\n";
+        let chunks = IdlProcessor::extract_and_chunk(avro, "dma.avdl", IdlFormat::Avro, &chunker).unwrap();
+        assert_eq!(chunks[1].metadata.attributes.get("type_name").map(|s| s.as_str()), Some("org.dma.Descriptor"));
+        assert!(chunks[0].content.contains("void start(Descriptor d);"));
+    }
+}
\ No newline at end of file
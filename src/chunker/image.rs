@@ -0,0 +1,392 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker, encoding::EncodingDetector};
+use anyhow::{Result, anyhow};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sha2::{Sha256, Digest};
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const XMP_JPEG_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// EXIF IFD0 tags carrying text about the image
+const TAG_IMAGE_DESCRIPTION: u16 = 0x010E;
+const TAG_XP_TITLE: u16 = 0x9C9B;
+const TAG_XP_COMMENT: u16 = 0x9C9C;
+const TAG_XP_KEYWORDS: u16 = 0x9C9E;
+
+pub struct ImageProcessor;
+
+/// Descriptive metadata embedded in an image file
+#[derive(Debug, Default)]
+struct ImageMetadata {
+    format: &'static str,
+    width: Option<u32>,
+    height: Option<u32>,
+    title: Option<String>,
+    description: Option<String>,
+    keywords: Vec<String>,
+}
+
+impl ImageMetadata {
+    fn set_title(&mut self, text: &str) {
+        if self.title.is_none() && !text.trim().is_empty() {
+            self.title = Some(text.trim().to_string());
+        }
+    }
+
+    fn set_description(&mut self, text: &str) {
+        if self.description.is_none() && !text.trim().is_empty() {
+            self.description = Some(text.trim().to_string());
+        }
+    }
+
+    fn add_keywords(&mut self, text: &str) {
+        for keyword in text.split([';', ',']).map(str::trim).filter(|k| !k.is_empty()) {
+            if !self.keywords.iter().any(|k| k == keyword) {
+                self.keywords.push(keyword.to_string());
+            }
+        }
+    }
+}
+
+impl ImageProcessor {
+    /// Describe a PNG or JPEG image as a single chunk: its file name, EXIF/XMP title,
+    /// description and keywords, and a sidecar caption if one exists (`diagram.png.caption`,
+    /// `diagram.png.txt` or `diagram.caption`). Pixel content is not analysed, so diagrams
+    /// are only findable by what is written about them.
+    pub fn extract_and_chunk(file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let bytes = std::fs::read(file_path)?;
+        let file_hash = format!("{:x}", Sha256::digest(&bytes));
+        let metadata = Self::read_metadata(&bytes)
+            .map_err(|e| anyhow!("{}: {}", file_path, e))?;
+        let caption = Self::read_caption(file_path);
+
+        let file_name = Path::new(file_path).file_name().and_then(|n| n.to_str()).unwrap_or(file_path);
+        let text = Self::describe(file_name, &metadata, caption.as_ref().map(|(_, text)| text.as_str()));
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_text(&text, file_path)?
+        } else {
+            vec![SemanticChunker::build_text_chunk(&text, file_path, &file_hash, (0, 0), ChunkStrategy::NaturalSection)]
+        };
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Image;
+            chunk.metadata.section = Some(metadata.title.clone().unwrap_or_else(|| file_name.to_string()));
+            for keyword in &metadata.keywords {
+                let tag = keyword.to_lowercase();
+                if !chunk.metadata.tags.contains(&tag) {
+                    chunk.metadata.tags.push(tag);
+                }
+            }
+
+            let attributes = &mut chunk.metadata.attributes;
+            attributes.insert("image_format".to_string(), metadata.format.to_string());
+            if let (Some(width), Some(height)) = (metadata.width, metadata.height) {
+                attributes.insert("width".to_string(), width.to_string());
+                attributes.insert("height".to_string(), height.to_string());
+            }
+            if let Some((caption_file, _)) = &caption {
+                attributes.insert("caption_file".to_string(), caption_file.clone());
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    fn read_metadata(bytes: &[u8]) -> Result<ImageMetadata> {
+        if bytes.starts_with(PNG_SIGNATURE) {
+            Ok(Self::parse_png(bytes))
+        } else if bytes.starts_with(&[0xFF, 0xD8]) {
+            Ok(Self::parse_jpeg(bytes))
+        } else {
+            Err(anyhow!("not a PNG or JPEG image"))
+        }
+    }
+
+    /// Walk PNG chunks for dimensions, text chunks, XMP and eXIf. Compressed text (zTXt,
+    /// compressed iTXt) is skipped.
+    fn parse_png(bytes: &[u8]) -> ImageMetadata {
+        let mut metadata = ImageMetadata { format: "png", ..Default::default() };
+        let mut offset = PNG_SIGNATURE.len();
+
+        while offset + 8 <= bytes.len() {
+            let length = u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as usize;
+            let kind = &bytes[offset + 4..offset + 8];
+            let Some(data) = bytes.get(offset + 8..offset + 8 + length) else {
+                break;
+            };
+
+            match kind {
+                b"IHDR" if data.len() >= 8 => {
+                    metadata.width = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+                    metadata.height = Some(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+                }
+                b"tEXt" => {
+                    if let Some(split) = data.iter().position(|&b| b == 0) {
+                        // tEXt is Latin-1
+                        let text: String = data[split + 1..].iter().map(|&b| b as char).collect();
+                        Self::apply_png_text(&mut metadata, &data[..split], &text);
+                    }
+                }
+                b"iTXt" => {
+                    if let Some((keyword, text)) = Self::parse_itxt(data) {
+                        Self::apply_png_text(&mut metadata, keyword, &text);
+                    }
+                }
+                b"eXIf" => Self::parse_exif(data, &mut metadata),
+                b"IEND" => break,
+                _ => {}
+            }
+
+            offset += 12 + length;  // length, type, data, CRC
+        }
+
+        metadata
+    }
+
+    /// Keyword and UTF-8 text of an uncompressed iTXt chunk
+    fn parse_itxt(data: &[u8]) -> Option<(&[u8], String)> {
+        let keyword_end = data.iter().position(|&b| b == 0)?;
+        let compressed = *data.get(keyword_end + 1)? != 0;
+        if compressed {
+            return None;
+        }
+
+        // Skip the compression method, then the null-terminated language tag and translated keyword
+        let mut rest = data.get(keyword_end + 3..)?;
+        for _ in 0..2 {
+            let end = rest.iter().position(|&b| b == 0)?;
+            rest = &rest[end + 1..];
+        }
+        Some((&data[..keyword_end], String::from_utf8_lossy(rest).to_string()))
+    }
+
+    fn apply_png_text(metadata: &mut ImageMetadata, keyword: &[u8], text: &str) {
+        match keyword {
+            b"XML:com.adobe.xmp" => Self::parse_xmp(text, metadata),
+            b"Title" => metadata.set_title(text),
+            b"Description" | b"Comment" => metadata.set_description(text),
+            b"Keywords" => metadata.add_keywords(text),
+            _ => {}
+        }
+    }
+
+    /// Walk JPEG segments up to the image data for dimensions, EXIF, XMP and comments
+    fn parse_jpeg(bytes: &[u8]) -> ImageMetadata {
+        let mut metadata = ImageMetadata { format: "jpeg", ..Default::default() };
+        let mut offset = 2;
+
+        while offset + 4 <= bytes.len() {
+            if bytes[offset] != 0xFF {
+                break;
+            }
+            let marker = bytes[offset + 1];
+            if marker == 0xFF {
+                offset += 1;  // Fill byte
+                continue;
+            }
+            if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+                offset += 2;  // Markers without a payload
+                continue;
+            }
+            if marker == 0xDA || marker == 0xD9 {
+                break;  // Start of scan / end of image
+            }
+
+            let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+            let Some(data) = bytes.get(offset + 4..offset + 2 + length.max(2)) else {
+                break;
+            };
+
+            match marker {
+                0xE1 if data.starts_with(b"Exif\0\0") => Self::parse_exif(&data[6..], &mut metadata),
+                0xE1 if data.starts_with(XMP_JPEG_HEADER) => {
+                    Self::parse_xmp(&String::from_utf8_lossy(&data[XMP_JPEG_HEADER.len()..]), &mut metadata);
+                }
+                0xFE => metadata.set_description(&String::from_utf8_lossy(data)),
+                0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && data.len() >= 5 => {
+                    metadata.height = Some(u16::from_be_bytes([data[1], data[2]]) as u32);
+                    metadata.width = Some(u16::from_be_bytes([data[3], data[4]]) as u32);
+                }
+                _ => {}
+            }
+
+            offset += 2 + length;
+        }
+
+        metadata
+    }
+
+    /// Read the text tags of IFD0 from a TIFF-structured EXIF block
+    fn parse_exif(tiff: &[u8], metadata: &mut ImageMetadata) {
+        let little_endian = match tiff.get(..2) {
+            Some(b"II") => true,
+            Some(b"MM") => false,
+            _ => return,
+        };
+        let u16_at = |at: usize| tiff.get(at..at + 2).map(|b| if little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) });
+        let u32_at = |at: usize| tiff.get(at..at + 4).map(|b| if little_endian { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) } else { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) });
+
+        let Some(ifd) = u32_at(4).map(|o| o as usize) else {
+            return;
+        };
+        let Some(entries) = u16_at(ifd) else {
+            return;
+        };
+
+        for index in 0..entries as usize {
+            let entry = ifd + 2 + index * 12;
+            let (Some(tag), Some(count)) = (u16_at(entry), u32_at(entry + 4)) else {
+                break;
+            };
+            if !matches!(tag, TAG_IMAGE_DESCRIPTION | TAG_XP_TITLE | TAG_XP_COMMENT | TAG_XP_KEYWORDS) {
+                continue;
+            }
+
+            // Values of up to four bytes are stored inline in the entry
+            let count = count as usize;
+            let start = if count <= 4 { entry + 8 } else { u32_at(entry + 8).unwrap_or(0) as usize };
+            let Some(value) = tiff.get(start..start + count) else {
+                continue;
+            };
+
+            if tag == TAG_IMAGE_DESCRIPTION {
+                metadata.set_description(String::from_utf8_lossy(value).trim_end_matches('\0'));
+                continue;
+            }
+
+            // Windows XP* tags are UTF-16LE regardless of the TIFF byte order
+            let units: Vec<u16> = value.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+            let text = String::from_utf16_lossy(&units);
+            let text = text.trim_end_matches('\0');
+            match tag {
+                TAG_XP_TITLE => metadata.set_title(text),
+                TAG_XP_COMMENT => metadata.set_description(text),
+                _ => metadata.add_keywords(text),
+            }
+        }
+    }
+
+    /// Pull dc:title, dc:description and dc:subject out of an XMP packet
+    fn parse_xmp(xml: &str, metadata: &mut ImageMetadata) {
+        let mut reader = Reader::from_str(xml);
+        let mut field: Option<Vec<u8>> = None;
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) if matches!(e.name().as_ref(), b"dc:title" | b"dc:description" | b"dc:subject") => {
+                    field = Some(e.name().as_ref().to_vec());
+                }
+                Ok(Event::End(e)) if field.as_deref() == Some(e.name().as_ref()) => field = None,
+                Ok(Event::Text(text)) => {
+                    let Some(name) = field.as_deref() else {
+                        continue;
+                    };
+                    let Ok(text) = text.unescape() else {
+                        continue;
+                    };
+                    match name {
+                        b"dc:title" => metadata.set_title(&text),
+                        b"dc:description" => metadata.set_description(&text),
+                        _ => metadata.add_keywords(&text),
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// The first sidecar caption file next to the image, with its text
+    fn read_caption(file_path: &str) -> Option<(String, String)> {
+        let candidates = [
+            format!("{}.caption", file_path),
+            format!("{}.txt", file_path),
+            Path::new(file_path).with_extension("caption").to_string_lossy().to_string(),
+        ];
+
+        candidates.into_iter().find_map(|candidate| {
+            let bytes = std::fs::read(&candidate).ok()?;
+            let text = EncodingDetector::decode(&bytes).text.trim().to_string();
+            (!text.is_empty()).then_some((candidate, text))
+        })
+    }
+
+    fn describe(file_name: &str, metadata: &ImageMetadata, caption: Option<&str>) -> String {
+        // Spell out the file name so "axi_dma_block_diagram.png" matches "block diagram"
+        let stem = file_name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(file_name);
+        let words = stem.split(['_', '-', '.', ' ']).filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" ");
+
+        let mut text = format!("Image: {} ({})", file_name, words);
+        if let Some(title) = &metadata.title {
+            text.push_str(&format!("\nTitle: {}", title));
+        }
+        if let Some(description) = metadata.description.as_ref().filter(|d| Some(*d) != metadata.title.as_ref()) {
+            text.push_str(&format!("\nDescription: {}", description));
+        }
+        if !metadata.keywords.is_empty() {
+            text.push_str(&format!("\nKeywords: {}", metadata.keywords.join(", ")));
+        }
+        if let Some(caption) = caption {
+            text.push_str(&format!("\nCaption: {}", caption));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);  // CRC is not checked
+        chunk
+    }
+
+    #[test]
+    fn test_png_text_exif_and_xmp() {
+        let mut ihdr = 640u32.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&480u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        // Little-endian TIFF with one ImageDescription entry pointing past the IFD
+        let description = b"Read and write channels of the DMA engine\0";
+        let mut exif = b"II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        exif.extend_from_slice(&1u16.to_le_bytes());
+        exif.extend_from_slice(&TAG_IMAGE_DESCRIPTION.to_le_bytes());
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        exif.extend_from_slice(&(description.len() as u32).to_le_bytes());
+        exif.extend_from_slice(&26u32.to_le_bytes());
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif.extend_from_slice(description);
+
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description>
+            <dc:subject><rdf:Bag><rdf:li>AXI</rdf:li><rdf:li>DMA</rdf:li></rdf:Bag></dc:subject>
+        </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        let mut itxt = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+        itxt.extend_from_slice(xmp.as_bytes());
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(png_chunk(b"IHDR", &ihdr));
+        png.extend(png_chunk(b"tEXt", b"Title\0AXI DMA block diagram"));
+        png.extend(png_chunk(b"eXIf", &exif));
+        png.extend(png_chunk(b"iTXt", &itxt));
+        png.extend(png_chunk(b"IEND", &[]));
+
+        let metadata = ImageProcessor::read_metadata(&png).unwrap();
+
+        assert_eq!((metadata.width, metadata.height), (Some(640), Some(480)));
+        assert_eq!(metadata.title.as_deref(), Some("AXI DMA block diagram"));
+        assert_eq!(metadata.description.as_deref(), Some("Read and write channels of the DMA engine"));
+        assert_eq!(metadata.keywords, vec!["AXI".to_string(), "DMA".to_string()]);
+
+        let text = ImageProcessor::describe("axi_dma-top.png", &metadata, Some("Figure 3"));
+        assert!(text.starts_with("Image: axi_dma-top.png (axi dma top)\nTitle: AXI DMA block diagram"));
+        assert!(text.ends_with("Keywords: AXI, DMA\nCaption: Figure 3"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,195 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use sha2::{Sha256, Digest};
+use std::collections::BTreeSet;
+
+/// Upper bound on key-derived tags per chunk so deeply nested payloads stay readable
+const MAX_KEY_TAGS: usize = 32;
+
+pub struct JsonProcessor;
+
+impl JsonProcessor {
+    /// Chunk a JSON document by its top-level items, or by the items under each of the
+    /// configured JSON pointers (e.g. "/issues"). Each chunk records its pointer.
+    pub fn extract_and_chunk(content: &str, file_path: &str, json_paths: &[String], chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let root: Value = serde_json::from_str(content)
+            .map_err(|e| anyhow!("Invalid JSON in {}: {}", file_path, e))?;
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+
+        let mut records = Vec::new();
+        if json_paths.is_empty() {
+            Self::collect_children(&root, "", &mut records);
+        } else {
+            for path in json_paths {
+                match root.pointer(path) {
+                    Some(value) => Self::collect_children(value, path, &mut records),
+                    None => tracing::warn!("JSON path {} not found in {}", path, file_path),
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+        for (index, (pointer, value)) in records.into_iter().enumerate() {
+            chunks.extend(Self::build_chunks(value, &pointer, None, file_path, &file_hash, index, chunker)?);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Chunk JSON Lines: every non-empty line is one record
+    pub fn extract_and_chunk_lines(content: &str, file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let mut chunks = Vec::new();
+        let mut record_index = 0;
+
+        for (line_no, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let value: Value = serde_json::from_str(line)
+                .map_err(|e| anyhow!("Invalid JSON on line {} of {}: {}", line_no + 1, file_path, e))?;
+            let pointer = format!("/{}", record_index);
+            chunks.extend(Self::build_chunks(&value, &pointer, Some(line_no + 1), file_path, &file_hash, line_no, chunker)?);
+            record_index += 1;
+        }
+
+        Ok(chunks)
+    }
+
+    fn collect_children<'a>(value: &'a Value, pointer: &str, records: &mut Vec<(String, &'a Value)>) {
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    records.push((format!("{}/{}", pointer, i), item));
+                }
+            }
+            Value::Object(map) => {
+                for (key, item) in map {
+                    records.push((format!("{}/{}", pointer, Self::escape_pointer_token(key)), item));
+                }
+            }
+            scalar => records.push((pointer.to_string(), scalar)),
+        }
+    }
+
+    fn build_chunks(
+        value: &Value,
+        pointer: &str,
+        line: Option<usize>,
+        file_path: &str,
+        file_hash: &str,
+        position: usize,
+        chunker: &SemanticChunker,
+    ) -> Result<Vec<Chunk>> {
+        let text = serde_json::to_string_pretty(value)?;
+
+        // Oversized records fall back to sentence windows so no single chunk blows the limit
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            // The windows are cut from the pretty-printed record, not the document, so place
+            // them at the record like a whole-record chunk
+            let mut windows = chunker.chunk_text(&text, file_path)?;
+            for window in &mut windows {
+                window.metadata.file_hash = Some(file_hash.to_string());
+                window.metadata.line_start = position;
+                window.metadata.line_end = position + 1;
+                window.metadata.byte_start = None;
+                window.metadata.byte_end = None;
+            }
+            windows
+        } else {
+            vec![SemanticChunker::build_text_chunk(&text, file_path, file_hash, (position, position + 1), ChunkStrategy::StructuredRecord)]
+        };
+
+        let mut keys = BTreeSet::new();
+        Self::collect_keys(value, &mut keys);
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Json;
+            chunk.metadata.section = Some(pointer.to_string());
+            chunk.metadata.attributes.insert("json_pointer".to_string(), pointer.to_string());
+            if let Some(line) = line {
+                chunk.metadata.line_start = line;
+                chunk.metadata.line_end = line;
+            }
+            for key in keys.iter().take(MAX_KEY_TAGS) {
+                if !chunk.metadata.tags.contains(key) {
+                    chunk.metadata.tags.push(key.clone());
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    fn collect_keys(value: &Value, keys: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, item) in map {
+                    keys.insert(key.to_lowercase());
+                    Self::collect_keys(item, keys);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    Self::collect_keys(item, keys);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // RFC 6901: "~" and "/" inside a key must be escaped
+    fn escape_pointer_token(token: &str) -> String {
+        token.replace('~', "~0").replace('/', "~1")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_path_chunks_array_items() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let content = r#"{"meta": {"count": 2}, "issues": [{"id": 1, "title": "FIFO overflow"}, {"id": 2, "title": "Reset glitch"}]}"#;
+
+        let chunks = JsonProcessor::extract_and_chunk(content, "export.json", &["/issues".to_string()], &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].metadata.attributes.get("json_pointer").map(|s| s.as_str()), Some("/issues/1"));
+        assert!(chunks[0].metadata.tags.contains(&"title".to_string()));
+        assert!(chunks[1].content.contains("Reset glitch"));
+    }
+
+    #[test]
+    fn test_jsonl_records_keep_line_numbers() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let content = "{\"event\": \"start\"}\n\n{\"event\": \"stop\"}\n";
+
+        let chunks = JsonProcessor::extract_and_chunk_lines(content, "events.jsonl", &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].metadata.line_start, 3);
+        assert_eq!(chunks[1].metadata.attributes.get("json_pointer").map(|s| s.as_str()), Some("/1"));
+    }
+
+    #[test]
+    fn test_oversized_record_keeps_document_position() {
+        let chunker = SemanticChunker::new(64, 10, 0);
+        let notes = "The FIFO overflows when the write clock outruns the read clock. ".repeat(4);
+        let content = format!(r#"[{{"id": 1}}, {{"id": 2, "notes": "{}"}}]"#, notes);
+
+        let chunks = JsonProcessor::extract_and_chunk(&content, "issues.json", &[], &chunker).unwrap();
+
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let windows: Vec<_> = chunks.iter().filter(|c| c.metadata.section.as_deref() == Some("/1")).collect();
+        assert!(windows.len() > 1);
+        for window in windows {
+            assert_eq!(window.metadata.file_hash.as_deref(), Some(file_hash.as_str()));
+            assert_eq!((window.metadata.line_start, window.metadata.line_end), (1, 2));
+            assert_eq!(window.metadata.byte_start, None);
+        }
+    }
+}
\ No newline at end of file
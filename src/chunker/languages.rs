@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+
+/// How `chunk_code` splits one language. In `chunking.languages` every field is optional;
+/// fields left out keep the built-in value for the language.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LanguageRules {
+    #[serde(default)]
+    pub max_chunk_size: Option<usize>,            // Replaces the code max_chunk_size for this language
+    #[serde(default)]
+    pub boundary_keywords: Option<Vec<String>>,   // Line prefixes, after indentation, that start a definition
+    #[serde(default)]
+    pub line_comment: Option<Vec<String>>,        // e.g. ["//"]
+    #[serde(default)]
+    pub block_comment: Option<(String, String)>,  // e.g. ["/*", "*/"]
+    #[serde(default)]
+    pub doc_line_comment: Option<Vec<String>>,    // e.g. ["///", "//!"]; extracted as documentation
+    #[serde(default)]
+    pub doc_block_comment: Option<Vec<(String, String)>>,  // e.g. [["/**", "*/"]] or [['"""', '"""']]
+}
+
+const C_LIKE_COMMENTS: (&[&str], Option<(&str, &str)>) = (&["//"], Some(("/*", "*/")));
+
+impl LanguageRules {
+    /// Rules for a language id from `CodeProcessor::detect_language`; unknown languages get
+    /// generic definition keywords and no comment syntax
+    pub fn builtin(language: &str) -> Self {
+        let keywords: &[&str] = match language {
+            "rust" => &["fn ", "pub fn ", "impl ", "pub struct ", "struct ", "enum ", "pub enum ", "trait ", "pub trait "],
+            "python" => &["def ", "class ", "async def "],
+            "javascript" | "typescript" => &["function ", "class ", "const ", "export function ", "export class "],
+            "java" => &["public class ", "class ", "public static ", "private ", "protected "],
+            "go" => &["func ", "type ", "struct "],
+            _ => &["fn ", "def ", "function ", "class "],
+        };
+        let (line_comment, block_comment): (&[&str], Option<(&str, &str)>) = match language {
+            "rust" | "javascript" | "typescript" | "java" | "go" | "c" | "cpp" => C_LIKE_COMMENTS,
+            "python" => (&["#"], None),
+            _ => (&[], None),
+        };
+        let (doc_line_comment, doc_block_comment): (&[&str], &[(&str, &str)]) = match language {
+            "rust" => (&["///", "//!"], &[("/**", "*/"), ("/*!", "*/")]),
+            "c" | "cpp" => (&["///"], &[("/**", "*/")]),
+            "javascript" | "typescript" | "java" => (&[], &[("/**", "*/")]),
+            "python" => (&[], &[("\"\"\"", "\"\"\""), ("'''", "'''")]),
+            _ => (&[], &[]),
+        };
+
+        Self {
+            max_chunk_size: None,
+            boundary_keywords: Some(keywords.iter().map(|k| k.to_string()).collect()),
+            line_comment: Some(line_comment.iter().map(|c| c.to_string()).collect()),
+            block_comment: block_comment.map(|(start, end)| (start.to_string(), end.to_string())),
+            doc_line_comment: Some(doc_line_comment.iter().map(|c| c.to_string()).collect()),
+            doc_block_comment: Some(doc_block_comment.iter().map(|(start, end)| (start.to_string(), end.to_string())).collect()),
+        }
+    }
+
+    /// `self` with every field that `overrides` sets replaced
+    pub fn overridden_by(self, overrides: &LanguageRules) -> Self {
+        Self {
+            max_chunk_size: overrides.max_chunk_size.or(self.max_chunk_size),
+            boundary_keywords: overrides.boundary_keywords.clone().or(self.boundary_keywords),
+            line_comment: overrides.line_comment.clone().or(self.line_comment),
+            block_comment: overrides.block_comment.clone().or(self.block_comment),
+            doc_line_comment: overrides.doc_line_comment.clone().or(self.doc_line_comment),
+            doc_block_comment: overrides.doc_block_comment.clone().or(self.doc_block_comment),
+        }
+    }
+
+    /// Whether a line, with its indentation removed, starts a definition
+    pub fn is_boundary(&self, trimmed: &str) -> bool {
+        self.boundary_keywords.iter().flatten().any(|keyword| trimmed.starts_with(keyword.as_str()))
+    }
+
+    /// The code on `line` with comments removed. `in_block` carries an unterminated block
+    /// comment over to the next line. String literals are not recognized.
+    pub fn strip_comments(&self, line: &str, in_block: &mut bool) -> String {
+        let mut code = String::new();
+        let mut rest = line;
+        loop {
+            if *in_block {
+                let Some((_, end)) = &self.block_comment else { return code };
+                match rest.find(end.as_str()) {
+                    Some(at) => {
+                        rest = &rest[at + end.len()..];
+                        *in_block = false;
+                    }
+                    None => return code,
+                }
+            }
+
+            let line_start = self.line_comment.iter().flatten().filter_map(|marker| rest.find(marker.as_str())).min();
+            let block_start = self.block_comment.as_ref().and_then(|(start, _)| rest.find(start.as_str()).map(|at| (at, start.len())));
+            match (line_start, block_start) {
+                (Some(line_at), Some((block_at, _))) if line_at <= block_at => {
+                    code.push_str(&rest[..line_at]);
+                    return code;
+                }
+                (_, Some((block_at, marker_len))) => {
+                    code.push_str(&rest[..block_at]);
+                    rest = &rest[block_at + marker_len..];
+                    *in_block = true;
+                }
+                (Some(line_at), None) => {
+                    code.push_str(&rest[..line_at]);
+                    return code;
+                }
+                (None, None) => {
+                    code.push_str(rest);
+                    return code;
+                }
+            }
+        }
+    }
+
+    /// The prose of the doc comments (and Python docstrings) in `code`, one paragraph per
+    /// comment, with the first and last line they cover relative to `code`
+    pub fn doc_comments(&self, code: &str) -> Option<(usize, usize, String)> {
+        let mut paragraphs = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut span: Option<(usize, usize)> = None;
+        let mut open_block: Option<&str> = None;
+
+        let flush = |current: &mut Vec<String>, paragraphs: &mut Vec<String>| {
+            let text = current.join("\n").trim().to_string();
+            if !text.is_empty() {
+                paragraphs.push(text);
+            }
+            current.clear();
+        };
+
+        for (i, line) in code.lines().enumerate() {
+            let trimmed = line.trim();
+            if let Some(end) = open_block {
+                let text = match trimmed.find(end) {
+                    Some(at) => {
+                        open_block = None;
+                        &trimmed[..at]
+                    }
+                    None => trimmed,
+                };
+                current.push(text.trim_start_matches('*').trim().to_string());
+                span = Some((span.map_or(i, |(first, _)| first), i));
+                if open_block.is_none() {
+                    flush(&mut current, &mut paragraphs);
+                }
+                continue;
+            }
+
+            if let Some(marker) = self.doc_line_comment.iter().flatten().find(|marker| trimmed.starts_with(marker.as_str())) {
+                current.push(trimmed[marker.len()..].trim().to_string());
+                span = Some((span.map_or(i, |(first, _)| first), i));
+                continue;
+            }
+            flush(&mut current, &mut paragraphs);
+
+            if let Some((start, end)) = self.doc_block_comment.iter().flatten().find(|(start, _)| trimmed.starts_with(start.as_str())) {
+                let rest = &trimmed[start.len()..];
+                match rest.find(end.as_str()) {
+                    Some(at) => current.push(rest[..at].trim().to_string()),
+                    None => {
+                        current.push(rest.trim().to_string());
+                        open_block = Some(end.as_str());
+                    }
+                }
+                span = Some((span.map_or(i, |(first, _)| first), i));
+                if open_block.is_none() {
+                    flush(&mut current, &mut paragraphs);
+                }
+            }
+        }
+        flush(&mut current, &mut paragraphs);
+
+        let (first, last) = span?;
+        (!paragraphs.is_empty()).then(|| (first, last, paragraphs.join("\n\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_and_comment_stripping() {
+        let overrides = LanguageRules {
+            max_chunk_size: Some(2048),
+            boundary_keywords: Some(vec!["proc ".to_string()]),
+            ..Default::default()
+        };
+        let rules = LanguageRules::builtin("rust").overridden_by(&overrides);
+        assert_eq!(rules.max_chunk_size, Some(2048));
+        assert!(rules.is_boundary("proc main {") && !rules.is_boundary("fn main() {"));
+        assert_eq!(rules.line_comment, Some(vec!["//".to_string()]));
+
+        let mut in_block = false;
+        assert_eq!(rules.strip_comments("let a = 1; // {", &mut in_block), "let a = 1; ");
+        assert_eq!(rules.strip_comments("x /* { */ y /* {", &mut in_block), "x  y ");
+        assert!(in_block);
+        assert_eq!(rules.strip_comments("still } */ }", &mut in_block), " }");
+        assert!(!in_block);
+        assert_eq!(LanguageRules::builtin("lisp").strip_comments("; (", &mut in_block), "; (");
+
+        // Doc comments move to the definition they precede, and braces in comments are ignored
+        let code = "def first():\n    # close early )\n    run()\n# Second does more\ndef second():\n    run()\n";
+        let chunker = crate::chunker::SemanticChunker::new(512, 1, 0);
+        let chunks = chunker.chunk_code(code, "python", "tool.py").unwrap();
+        assert_eq!(chunks.iter().map(|c| c.boundaries).collect::<Vec<_>>(), vec![(0, 3), (3, 6)]);
+        assert!(chunks[1].content.starts_with("# Second"));
+
+        let chunker = chunker.with_language_overrides([("python".to_string(), LanguageRules { line_comment: Some(vec![]), ..Default::default() })].into());
+        assert_eq!(chunker.chunk_code(code, "python", "tool.py").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_doc_comment_chunks() {
+        let rules = LanguageRules::builtin("rust");
+        let code = "/// Resets the DMA engine.\n///\n/// Waits for PLL lock.\nfn reset() {\n    /** Inline\n     * block */\n    run();\n}";
+        let (first, last, prose) = rules.doc_comments(code).unwrap();
+        assert_eq!((first, last), (0, 5));
+        assert_eq!(prose, "Resets the DMA engine.\n\nWaits for PLL lock.\n\nInline\nblock");
+        assert!(rules.doc_comments("// plain comment\nfn x() {}").is_none());
+
+        let python = "def reset():\n    \"\"\"Hold the engine in reset\n    until the clocks settle.\"\"\"\n    run()\n";
+        let (_, _, prose) = LanguageRules::builtin("python").doc_comments(python).unwrap();
+        assert_eq!(prose, "Hold the engine in reset\nuntil the clocks settle.");
+
+        let chunker = crate::chunker::SemanticChunker::new(512, 1, 0);
+        let chunks = crate::chunker::code::CodeProcessor::extract_and_chunk(python, "python", "dma.py", &chunker).unwrap();
+        assert_eq!(chunks.len(), 2);
+        let (code, doc) = (&chunks[0], &chunks[1]);
+        assert!(matches!(doc.metadata.chunk_type, crate::chunker::ChunkType::Text));
+        assert_eq!(doc.metadata.attributes.get("documents"), Some(&code.id));
+        assert_eq!(doc.boundaries, (1, 3));
+
+        let mut graph = crate::graph::GraphBuilder::new(0.99);
+        graph.build_relationships(&chunks).unwrap();
+        assert!(graph.get_edges().iter().any(|e| e.edge_type == crate::graph::EdgeType::Reference && e.from == doc.id && e.to == code.id));
+        assert!(!graph.get_edges().iter().any(|e| e.edge_type == crate::graph::EdgeType::Sequential));
+    }
+}
\ No newline at end of file
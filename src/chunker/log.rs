@@ -0,0 +1,283 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime};
+use regex::Regex;
+use sha2::{Sha256, Digest};
+use std::sync::OnceLock;
+
+pub struct LogProcessor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Info,
+    Warning,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Fatal => "fatal",
+        }
+    }
+}
+
+/// A parsed timestamp. Wall-clock times have absolute seconds for windowing; simulation
+/// times (`@ 1500ns`) are only reported, since their units say nothing about wall time.
+#[derive(Debug, Clone)]
+struct LogTime {
+    seconds: Option<f64>,
+    display: String,
+}
+
+struct LogLine<'a> {
+    number: usize,
+    text: &'a str,
+    time: Option<LogTime>,
+    severity: Severity,
+}
+
+impl LogProcessor {
+    /// Chunk a log by time windows of `window_secs`, cutting a separate chunk for each error
+    /// block (the error line plus its stack trace) so failures are retrievable on their own.
+    /// Logs without wall-clock timestamps fall back to size-bounded windows.
+    pub fn extract_and_chunk(content: &str, file_path: &str, window_secs: u64, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let max_size = chunker.max_chunk_size();
+        let window = window_secs.max(1) as f64;
+
+        let mut chunks = Vec::new();
+        let mut block: Vec<LogLine> = Vec::new();
+        let mut block_len = 0;
+        let mut window_start: Option<f64> = None;
+        let mut in_error = false;
+        let mut error_timed = false;
+
+        for (index, text) in content.lines().enumerate() {
+            let line = Self::parse_line(index + 1, text);
+
+            if block.is_empty() && text.trim().is_empty() {
+                continue;
+            }
+
+            let continues_error = in_error && (line.severity >= Severity::Error || Self::is_continuation(&line, error_timed));
+            let starts_error = !in_error && line.severity >= Severity::Error;
+            let window_elapsed = match (window_start, line.time.as_ref().and_then(|t| t.seconds)) {
+                (Some(start), Some(seconds)) => seconds - start >= window,
+                _ => false,
+            };
+
+            let cut = !block.is_empty() && (
+                starts_error
+                || (in_error && !continues_error)
+                || (!in_error && window_elapsed)
+                || block_len + text.len() + 1 > max_size
+            );
+            if cut {
+                let strategy = if in_error { ChunkStrategy::ErrorBlock } else { ChunkStrategy::TimeWindow };
+                chunks.push(Self::build_chunk(&block, file_path, &file_hash, strategy));
+                block.clear();
+                block_len = 0;
+                window_start = None;
+            }
+
+            in_error = starts_error || continues_error;
+            if starts_error {
+                error_timed = line.time.is_some();
+            }
+            if window_start.is_none() {
+                window_start = line.time.as_ref().and_then(|t| t.seconds);
+            }
+            block_len += text.len() + 1;
+            block.push(line);
+        }
+
+        if block.iter().any(|line| !line.text.trim().is_empty()) {
+            let strategy = if in_error { ChunkStrategy::ErrorBlock } else { ChunkStrategy::TimeWindow };
+            chunks.push(Self::build_chunk(&block, file_path, &file_hash, strategy));
+        }
+
+        Ok(chunks)
+    }
+
+    fn parse_line(number: usize, text: &str) -> LogLine<'_> {
+        LogLine {
+            number,
+            text,
+            time: Self::wall_clock_time(text).or_else(|| Self::simulation_time(text)),
+            severity: Self::severity(text),
+        }
+    }
+
+    /// Stack frames and indented detail belong to the preceding error, as do untimestamped
+    /// lines when the error itself carried a timestamp
+    fn is_continuation(line: &LogLine, error_timed: bool) -> bool {
+        let trimmed = line.text.trim_start();
+        (error_timed && line.time.is_none() && !trimmed.is_empty())
+            || (line.text.starts_with(char::is_whitespace) && !trimmed.is_empty())
+            || trimmed.starts_with("at ")
+            || trimmed.starts_with("File \"")
+            || trimmed.starts_with("Traceback")
+    }
+
+    fn severity(text: &str) -> Severity {
+        static PATTERNS: OnceLock<(Regex, Regex, Regex, Regex)> = OnceLock::new();
+        let (summary, fatal, error, warning) = PATTERNS.get_or_init(|| (
+            // UVM report summaries ("UVM_ERROR :    0") are counts, not errors
+            Regex::new(r"UVM_(?:INFO|WARNING|ERROR|FATAL)\s*:\s*\d+\s*$").unwrap(),
+            Regex::new(r"\b(?:FATAL|UVM_FATAL|PANIC|panicked at)\b|\*F[,:]").unwrap(),
+            Regex::new(r"\b(?:ERROR|UVM_ERROR|Error|FAILED|FAIL|Traceback|Assertion failed)\b|\*E[,:]").unwrap(),
+            Regex::new(r"\b(?:WARN|WARNING|UVM_WARNING|Warning)\b|\*W[,:]").unwrap(),
+        ));
+
+        if summary.is_match(text) {
+            Severity::Info
+        } else if fatal.is_match(text) {
+            Severity::Fatal
+        } else if error.is_match(text) {
+            Severity::Error
+        } else if warning.is_match(text) {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+
+    /// ISO-8601 (`2024-03-01T10:00:00Z`, `[2024-03-01 10:00:00,123]`) or syslog
+    /// (`Mar  1 10:00:00`) timestamps at the start of the line
+    fn wall_clock_time(text: &str) -> Option<LogTime> {
+        let tokens: Vec<&str> = text.split_whitespace().take(3)
+            .map(|t| t.trim_matches(|c| c == '[' || c == ']'))
+            .collect();
+        let first = *tokens.first()?;
+
+        if let Ok(time) = DateTime::parse_from_rfc3339(first) {
+            return Some(LogTime { seconds: Some(Self::seconds(&time.naive_utc())), display: time.to_rfc3339() });
+        }
+
+        let iso = match tokens.get(1) {
+            Some(clock) if first.len() == 10 && first.as_bytes()[4] == b'-' => format!("{}T{}", first, clock),
+            _ => first.to_string(),
+        };
+        let iso = iso.replace(',', ".");
+        if let Ok(time) = NaiveDateTime::parse_from_str(iso.trim_end_matches('Z'), "%Y-%m-%dT%H:%M:%S%.f") {
+            return Some(LogTime { seconds: Some(Self::seconds(&time)), display: time.format("%Y-%m-%dT%H:%M:%S%.f").to_string() });
+        }
+
+        // Syslog omits the year; any fixed year keeps intervals within one file correct
+        if tokens.len() == 3 && first.len() == 3 && first.chars().all(|c| c.is_ascii_alphabetic()) {
+            let stamped = format!("2000 {} {} {}", tokens[0], tokens[1], tokens[2]);
+            if let Ok(time) = NaiveDateTime::parse_from_str(&stamped, "%Y %b %d %H:%M:%S") {
+                return Some(LogTime { seconds: Some(Self::seconds(&time)), display: time.format("%b %d %H:%M:%S").to_string() });
+            }
+        }
+
+        None
+    }
+
+    /// Simulator time, e.g. `UVM_INFO tb.sv(12) @ 1500: ...` or `# Time: 1500 ns`
+    fn simulation_time(text: &str) -> Option<LogTime> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(r"(?:@|\bTime:)\s*(\d+(?:\.\d+)?)\s*(fs|ps|ns|us|ms|s)?\b").unwrap()
+        });
+
+        let captures = pattern.captures(text)?;
+        let unit = captures.get(2).map(|m| m.as_str()).unwrap_or("");
+        Some(LogTime { seconds: None, display: format!("{}{}", &captures[1], unit) })
+    }
+
+    fn seconds(time: &NaiveDateTime) -> f64 {
+        time.and_utc().timestamp_millis() as f64 / 1000.0
+    }
+
+    fn build_chunk(lines: &[LogLine], file_path: &str, file_hash: &str, strategy: ChunkStrategy) -> Chunk {
+        let content = lines.iter().map(|l| l.text).collect::<Vec<_>>().join("\n");
+        let first_line = lines.first().map(|l| l.number).unwrap_or(1);
+        let last_line = lines.last().map(|l| l.number).unwrap_or(first_line);
+
+        let mut chunk = SemanticChunker::build_text_chunk(content.trim_end(), file_path, file_hash, (first_line, last_line), strategy);
+        chunk.metadata.chunk_type = ChunkType::Log;
+
+        let start = lines.iter().find_map(|l| l.time.as_ref());
+        let end = lines.iter().rev().find_map(|l| l.time.as_ref());
+        let severity = lines.iter().map(|l| l.severity).max().unwrap_or(Severity::Info);
+        let errors = lines.iter().filter(|l| l.severity >= Severity::Error).count();
+        let warnings = lines.iter().filter(|l| l.severity == Severity::Warning).count();
+
+        chunk.metadata.section = Some(match (strategy, start, end) {
+            (ChunkStrategy::ErrorBlock, _, _) => {
+                let message = lines.iter().find(|l| l.severity >= Severity::Error).map(|l| l.text.trim()).unwrap_or("");
+                message.chars().take(120).collect()
+            }
+            (_, Some(start), Some(end)) if start.display != end.display => format!("{} - {}", start.display, end.display),
+            (_, Some(start), _) => start.display.clone(),
+            _ => format!("lines {}-{}", first_line, last_line),
+        });
+
+        let attributes = &mut chunk.metadata.attributes;
+        if let Some(start) = start {
+            attributes.insert("start_time".to_string(), start.display.clone());
+        }
+        if let Some(end) = end {
+            attributes.insert("end_time".to_string(), end.display.clone());
+        }
+        attributes.insert("severity".to_string(), severity.as_str().to_string());
+        attributes.insert("error_count".to_string(), errors.to_string());
+        attributes.insert("warning_count".to_string(), warnings.to_string());
+
+        if severity > Severity::Info && !chunk.metadata.tags.contains(&severity.as_str().to_string()) {
+            chunk.metadata.tags.push(severity.as_str().to_string());
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_and_error_blocks() {
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let log = "\
+2024-03-01T10:00:00Z INFO starting regression
+2024-03-01T10:00:20Z INFO compiled 42 files
+2024-03-01T10:01:30Z INFO running test dma_burst
+2024-03-01T10:01:35Z ERROR scoreboard mismatch on channel 2
+    expected 0x1F got 0x0F
+    at scoreboard.sv:88
+2024-03-01T10:01:40Z INFO test finished
+";
+
+        let chunks = LogProcessor::extract_and_chunk(log, "ci.log", 60, &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].metadata.attributes.get("start_time").map(|s| s.as_str()), Some("2024-03-01T10:00:00+00:00"));
+        assert_eq!(chunks[0].metadata.attributes.get("end_time").map(|s| s.as_str()), Some("2024-03-01T10:00:20+00:00"));
+
+        let error = &chunks[2];
+        assert_eq!(error.metadata.chunk_strategy, Some(ChunkStrategy::ErrorBlock));
+        assert!(error.content.contains("at scoreboard.sv:88"));
+        assert_eq!(error.metadata.attributes.get("severity").map(|s| s.as_str()), Some("error"));
+        assert!(error.metadata.tags.contains(&"error".to_string()));
+        assert_eq!(chunks[3].metadata.attributes.get("severity").map(|s| s.as_str()), Some("info"));
+    }
+
+    #[test]
+    fn test_simulation_times_and_report_summary() {
+        let uvm = LogProcessor::parse_line(1, "UVM_ERROR tb_top.sv(120) @ 1500ns: uvm_test_top.env.scb [SCB] mismatch");
+        assert_eq!(uvm.severity, Severity::Error);
+        assert_eq!(uvm.time.unwrap().display, "1500ns");
+
+        assert_eq!(LogProcessor::severity("UVM_ERROR :    0"), Severity::Info);
+        let python = LogProcessor::parse_line(2, "[2024-03-01 10:00:00,250] WARNING retrying");
+        assert_eq!(python.severity, Severity::Warning);
+        assert_eq!(python.time.unwrap().display, "2024-03-01T10:00:00.250");
+    }
+}
\ No newline at end of file
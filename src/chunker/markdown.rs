@@ -1,32 +1,105 @@
-use super::{Chunk, ChunkMetadata, ChunkType};
+use super::{Chunk, ChunkMetadata, ChunkStrategy, ChunkType, SemanticChunker};
 use anyhow::Result;
-use pulldown_cmark::{Parser, Event, Tag, TagEnd, HeadingLevel};
+use pulldown_cmark::{CodeBlockKind, Options, Parser, Event, Tag, TagEnd, HeadingLevel};
 use uuid::Uuid;
 
 pub struct MarkdownProcessor;
 
+/// A run of prose under one heading, a fenced code block or a table
+enum Block {
+    Prose { text: String, first_line: usize },
+    Code { language: Option<String>, code: String, lines: (usize, usize) },
+    Table { markdown: String, lines: (usize, usize) },
+}
+
+/// Fields from a YAML front matter block (`---` ... `---`) at the top of a note
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FrontMatter {
+    pub(crate) title: Option<String>,
+    pub(crate) author: Option<String>,
+    pub(crate) date: Option<String>,
+    pub(crate) tags: Vec<String>,                // tags, keywords and categories
+    pub(crate) fields: Vec<(String, String)>,    // Remaining scalar fields
+}
+
 #[derive(Debug, Clone)]
-struct HeaderInfo {
-    text: String,
-    level: u32,
+pub(crate) struct HeaderInfo {
+    pub(crate) text: String,
+    pub(crate) level: u32,
 }
 
 impl MarkdownProcessor {
     pub fn extract_and_chunk(content: &str, file_path: &str, chunker: &super::SemanticChunker) -> Result<Vec<Chunk>> {
+        // Front matter is metadata, not text: strip it before parsing so it is neither
+        // indexed nor mistaken for a setext heading
+        let (front_matter, body) = Self::split_front_matter(content, file_path);
+        let front_matter_lines = content[..content.len() - body.len()].matches('\n').count();
+        let line_of = |offset: usize| front_matter_lines + body[..offset].matches('\n').count() + 1;
+
         let mut sections = Vec::new();
         let mut current_section = String::new();
+        let mut section_offset = 0;  // Where the prose in current_section begins in body
         let mut header_stack: Vec<HeaderInfo> = Vec::new();
 
-        let parser = Parser::new(content);
+        let parser = Parser::new_ext(body, Options::ENABLE_TABLES).into_offset_iter();
         let mut in_heading = false;
         let mut heading_text = String::new();
         let mut heading_level = 1;
+        // Fenced code is collected whole and becomes its own chunk; indented code stays prose
+        let mut fence: Option<(Option<String>, String)> = None;
+        // Tables are kept as written, so their rows stay aligned under the header
+        let mut in_table = false;
+
+        for (event, range) in parser {
+            if in_table {
+                if let Event::End(TagEnd::Table) = event {
+                    in_table = false;
+                    let markdown = body[range.clone()].trim_end().to_string();
+                    let lines = (line_of(range.start), line_of(range.start) + markdown.matches('\n').count());
+                    sections.push((header_stack.clone(), Block::Table { markdown, lines }));
+                }
+                continue;
+            }
+            if current_section.is_empty() {
+                section_offset = range.start;
+            }
 
-        for event in parser {
             match event {
+                Event::Start(Tag::Table(_)) => {
+                    if !current_section.is_empty() {
+                        sections.push((header_stack.clone(), Block::Prose { text: current_section.clone(), first_line: line_of(section_offset) }));
+                        current_section.clear();
+                    }
+                    in_table = true;
+                }
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                    if !current_section.is_empty() {
+                        sections.push((header_stack.clone(), Block::Prose { text: current_section.clone(), first_line: line_of(section_offset) }));
+                        current_section.clear();
+                    }
+                    // The info string may carry attributes after the language ("rust,ignore", "python title=x")
+                    let language = info.split(|c: char| c.is_whitespace() || c == ',' || c == '{').next()
+                        .filter(|language| !language.is_empty())
+                        .map(|language| language.to_lowercase());
+                    fence = Some((language, String::new()));
+                }
+                Event::End(TagEnd::CodeBlock) if fence.is_some() => {
+                    let (language, code) = fence.take().unwrap_or_default();
+                    if !code.trim().is_empty() {
+                        // Lines of the code itself, inside the fences
+                        let end = body[..range.end].trim_end_matches(['\n', '\r']).len();
+                        let lines = (line_of(range.start) + 1, line_of(end).saturating_sub(1).max(line_of(range.start) + 1));
+                        sections.push((header_stack.clone(), Block::Code { language, code, lines }));
+                    }
+                }
+                Event::Text(text) if fence.is_some() => {
+                    if let Some((_, code)) = fence.as_mut() {
+                        code.push_str(&text);
+                    }
+                }
                 Event::Start(Tag::Heading { level, .. }) => {
                     if !current_section.is_empty() {
-                        sections.push((header_stack.clone(), current_section.clone()));
+                        sections.push((header_stack.clone(), Block::Prose { text: current_section.clone(), first_line: line_of(section_offset) }));
                         current_section.clear();
                     }
                     in_heading = true;
@@ -71,22 +144,54 @@ impl MarkdownProcessor {
 
         // Add final section
         if !current_section.is_empty() {
-            sections.push((header_stack, current_section));
+            sections.push((header_stack, Block::Prose { text: current_section, first_line: line_of(section_offset) }));
         }
 
         let mut all_chunks = Vec::new();
+        let file_hash = SemanticChunker::calculate_file_hash(content);
 
-        for (headers, section_content) in sections {
-            let mut chunks = chunker.chunk_text(&section_content, file_path)?;
+        for (headers, block) in sections {
+            let mut chunks = match block {
+                Block::Prose { text, first_line } => {
+                    let mut chunks = chunker.chunk_text(&text, file_path)?;
+                    for chunk in &mut chunks {
+                        chunk.metadata.chunk_type = ChunkType::Markdown;
+                        // The prose is rebuilt from parser events, so only its lines carry over to the file
+                        chunk.metadata.line_start += first_line - 1;
+                        chunk.metadata.line_end += first_line - 1;
+                        chunk.metadata.byte_start = None;
+                        chunk.metadata.byte_end = None;
+                    }
+                    chunks
+                }
+                // Never split, however long: a partial example is worse than a large chunk
+                Block::Code { language, code, lines } => {
+                    let mut chunk = SemanticChunker::build_code_chunk(&code, language.as_deref().unwrap_or("text"), file_path, &file_hash, lines);
+                    chunk.metadata.language = language;
+                    chunk.metadata.chunk_strategy = Some(ChunkStrategy::FencedCode);
+                    vec![chunk]
+                }
+                // Also never split: rows cut off from their header are meaningless
+                Block::Table { markdown, lines } => {
+                    let mut chunk = SemanticChunker::build_text_chunk(&markdown, file_path, &file_hash, lines, ChunkStrategy::WholeTable);
+                    chunk.metadata.chunk_type = ChunkType::Markdown;
+                    if !chunk.metadata.tags.iter().any(|tag| tag == "table") {
+                        chunk.metadata.tags.push("table".to_string());
+                    }
+                    vec![chunk]
+                }
+            };
 
             // Extract chapter and section information from header stack
             let (chapter, section) = Self::extract_chapter_and_section(&headers);
 
             // Update metadata
             for chunk in &mut chunks {
-                chunk.metadata.chunk_type = ChunkType::Markdown;
                 chunk.metadata.chapter = chapter.clone();
                 chunk.metadata.section = section.clone();
+                if let Some(front_matter) = &front_matter {
+                    Self::apply_front_matter(chunk, front_matter);
+                }
             }
 
             all_chunks.extend(chunks);
@@ -95,7 +200,109 @@ impl MarkdownProcessor {
         Ok(all_chunks)
     }
 
-    fn extract_chapter_and_section(headers: &[HeaderInfo]) -> (Option<String>, Option<String>) {
+    /// Split a leading YAML front matter block from the body. Blocks that are not a YAML
+    /// mapping are left in the body untouched.
+    pub(crate) fn split_front_matter<'a>(content: &'a str, file_path: &str) -> (Option<FrontMatter>, &'a str) {
+        let text = content.trim_start_matches('\u{feff}');
+        let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+            return (None, content);
+        };
+
+        let mut offset = 0;
+        let mut end = None;
+        for line in rest.split_inclusive('\n') {
+            if matches!(line.trim_end(), "---" | "...") {
+                end = Some((offset, offset + line.len()));
+                break;
+            }
+            offset += line.len();
+        }
+        let Some((yaml_end, body_start)) = end else {
+            return (None, content);
+        };
+
+        let mapping = match serde_yaml::from_str::<serde_yaml::Value>(&rest[..yaml_end]) {
+            Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+            Ok(_) => return (None, content),
+            Err(e) => {
+                tracing::warn!("Ignoring unparseable front matter in {}: {}", file_path, e);
+                return (None, content);
+            }
+        };
+
+        let mut front_matter = FrontMatter::default();
+        for (key, value) in &mapping {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            match key.to_lowercase().as_str() {
+                "title" => front_matter.title = Self::yaml_text(value),
+                "author" | "authors" => front_matter.author = Self::yaml_text(value),
+                "date" | "created" => front_matter.date = Self::yaml_text(value),
+                "tags" | "tag" | "keywords" | "categories" | "category" => {
+                    for tag in Self::yaml_list(value) {
+                        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+                        if !tag.is_empty() && !front_matter.tags.contains(&tag) {
+                            front_matter.tags.push(tag);
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(text) = Self::yaml_scalar(value) {
+                        front_matter.fields.push((key.to_string(), text));
+                    }
+                }
+            }
+        }
+
+        (Some(front_matter), &rest[body_start..])
+    }
+
+    fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+        match value {
+            serde_yaml::Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            serde_yaml::Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Lists are joined ("Ann, Bob"); scalars are used as-is
+    fn yaml_text(value: &serde_yaml::Value) -> Option<String> {
+        match value {
+            serde_yaml::Value::Sequence(items) => {
+                let items: Vec<String> = items.iter().filter_map(Self::yaml_scalar).collect();
+                (!items.is_empty()).then(|| items.join(", "))
+            }
+            other => Self::yaml_scalar(other),
+        }
+    }
+
+    /// A YAML list, or a comma/space separated string ("tags: dma uvm" in Obsidian)
+    fn yaml_list(value: &serde_yaml::Value) -> Vec<String> {
+        match value {
+            serde_yaml::Value::Sequence(items) => items.iter().filter_map(Self::yaml_scalar).collect(),
+            other => Self::yaml_scalar(other)
+                .map(|s| s.split([',', ' ']).filter(|t| !t.is_empty()).map(|t| t.to_string()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn apply_front_matter(chunk: &mut Chunk, front_matter: &FrontMatter) {
+        chunk.metadata.title = front_matter.title.clone();
+        chunk.metadata.author = front_matter.author.clone();
+        chunk.metadata.date = front_matter.date.clone();
+        for tag in &front_matter.tags {
+            if !chunk.metadata.tags.contains(tag) {
+                chunk.metadata.tags.push(tag.clone());
+            }
+        }
+        for (key, value) in &front_matter.fields {
+            chunk.metadata.attributes.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    pub(crate) fn extract_chapter_and_section(headers: &[HeaderInfo]) -> (Option<String>, Option<String>) {
         if headers.is_empty() {
             return (None, None);
         }
@@ -148,4 +355,67 @@ impl MarkdownProcessor {
 
         false
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::SemanticChunker;
+
+    #[test]
+    fn test_front_matter_maps_to_metadata() {
+        let note = "---\ntitle: DMA bring-up notes\nauthor: [Ann, Bob]\ndate: 2024-03-01\ntags: [\"#dma\", Bring-Up]\nstatus: draft\n---\n# Reset\n\nThe DMA engine must be held in reset until the clocks are stable.\n";
+        let chunker = SemanticChunker::new(512, 10, 0);
+
+        let chunks = MarkdownProcessor::extract_and_chunk(note, "notes/dma.md", &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 1);
+        let metadata = &chunks[0].metadata;
+        assert!(!chunks[0].content.contains("title:"));
+        assert_eq!(metadata.title.as_deref(), Some("DMA bring-up notes"));
+        assert_eq!(metadata.author.as_deref(), Some("Ann, Bob"));
+        assert_eq!(metadata.date.as_deref(), Some("2024-03-01"));
+        assert!(metadata.tags.contains(&"dma".to_string()) && metadata.tags.contains(&"bring-up".to_string()));
+        assert_eq!(metadata.attributes.get("status").map(|s| s.as_str()), Some("draft"));
+        assert_eq!(metadata.section.as_deref(), Some("Reset"));
+
+        let (front_matter, body) = MarkdownProcessor::split_front_matter("---\n\n# Not front matter\n", "x.md");
+        assert!(front_matter.is_none() && body.starts_with("---"));
+    }
+
+    #[test]
+    fn test_fenced_code_blocks_are_whole_code_chunks() {
+        let code = (0..40).map(|i| format!("    write_reg(DMA_CTRL, {});", i)).collect::<Vec<_>>().join("\n");
+        let doc = format!("---\ntitle: DMA\n---\n# Reset\n\nHold the engine in reset first.\n\n```C {{.numberLines}}\nvoid dma_reset(void) {{\n{}\n}}\n```\n\nThen    indented code stays prose:\n\n    not_a_fence();\n", code);
+        let chunker = SemanticChunker::new(200, 10, 0);
+
+        let chunks = MarkdownProcessor::extract_and_chunk(&doc, "docs/dma.md", &chunker).unwrap();
+        let code_chunks: Vec<&Chunk> = chunks.iter().filter(|c| matches!(c.metadata.chunk_type, ChunkType::Code)).collect();
+        assert_eq!(code_chunks.len(), 1);
+        let block = &code_chunks[0];
+        assert!(block.content.starts_with("void dma_reset(void) {") && block.content.ends_with("write_reg(DMA_CTRL, 39);\n}"));
+        assert_eq!(block.metadata.language.as_deref(), Some("c"));
+        assert_eq!(block.metadata.chunk_strategy, Some(ChunkStrategy::FencedCode));
+        assert_eq!((block.metadata.line_start, block.metadata.line_end), (9, 50));
+        assert_eq!(block.metadata.section.as_deref(), Some("Reset"));
+        assert_eq!(block.metadata.title.as_deref(), Some("DMA"));
+        assert!(chunks.iter().any(|c| matches!(c.metadata.chunk_type, ChunkType::Markdown) && c.content.contains("not_a_fence")));
+    }
+
+    #[test]
+    fn test_tables_are_whole_chunks() {
+        let rows = (0..30).map(|i| format!("| 0x{:02x} | REG_{} | Reset value of register {} |", i * 4, i, i)).collect::<Vec<_>>().join("\n");
+        let doc = format!("# Registers\n\nThe register map:\n\n| Offset | Name | Description |\n|--------|------|-------------|\n{}\n\nAll registers are 32 bits wide.\n", rows);
+        let chunker = SemanticChunker::new(200, 10, 0);
+
+        let chunks = MarkdownProcessor::extract_and_chunk(&doc, "docs/regs.md", &chunker).unwrap();
+        let tables: Vec<&Chunk> = chunks.iter().filter(|c| c.metadata.chunk_strategy == Some(ChunkStrategy::WholeTable)).collect();
+        assert_eq!(tables.len(), 1);
+        assert!(tables[0].content.starts_with("| Offset | Name | Description |\n|---") && tables[0].content.ends_with("| Reset value of register 29 |"));
+        assert!(tables[0].metadata.tags.contains(&"table".to_string()));
+        assert_eq!((tables[0].metadata.line_start, tables[0].metadata.line_end), (5, 36));
+        assert_eq!(tables[0].metadata.section.as_deref(), Some("Registers"));
+        assert!(chunks.iter().all(|c| c.metadata.chunk_strategy == Some(ChunkStrategy::WholeTable) || !c.content.contains("REG_")));
+        assert!(chunks.iter().any(|c| c.content.contains("32 bits wide")));
+    }
 }
\ No newline at end of file
@@ -3,5 +3,26 @@ pub mod pdf;
 pub mod markdown;
 pub mod text;
 pub mod code;
+pub mod encoding;
+pub mod csv;
+pub mod json;
+pub mod xml;
+pub mod asciidoc;
+pub mod org;
+pub mod pptx;
+pub mod email;
+pub mod log;
+pub mod image;
+pub mod subtitle;
+pub mod config_file;
+pub mod idl;
+pub mod hdl;
+pub mod html;
+pub mod window;
+pub mod languages;
+pub mod summary;
+pub mod split;
+pub mod strategy;
+pub mod natural_language;
 
 pub use semantic::*;
\ No newline at end of file
@@ -0,0 +1,146 @@
+/// Letters a text needs before its language is guessed
+const MIN_LETTERS: usize = 20;
+
+/// Common function words by ISO 639-1 code, used to tell Latin-script languages apart and
+/// left out of keyword matching
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "for", "with", "as", "are", "this", "be", "on", "by", "or", "from", "an", "was"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "mit", "den", "ein", "eine", "zu", "von", "auf", "für", "sich", "dem", "des", "wird", "auch", "werden"]),
+    ("fr", &["le", "la", "les", "et", "des", "est", "une", "un", "du", "que", "dans", "pour", "pas", "sur", "au", "avec", "qui", "sont", "ce", "par"]),
+    ("es", &["el", "la", "los", "las", "y", "que", "es", "en", "del", "un", "una", "por", "con", "para", "se", "no", "su", "al", "como", "está"]),
+    ("it", &["il", "di", "che", "è", "la", "per", "un", "una", "non", "con", "sono", "gli", "della", "del", "le", "nel", "alla", "anche", "come", "questo"]),
+    ("pt", &["o", "de", "que", "e", "do", "da", "em", "um", "uma", "para", "com", "não", "os", "no", "na", "se", "por", "mais", "dos", "é"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "dat", "op", "te", "zijn", "met", "voor", "er", "ook", "wordt", "aan", "bij", "deze", "worden"]),
+];
+
+/// Languages whose words are not separated by spaces; keyword matching uses character bigrams
+const UNSPACED: &[&str] = &["zh", "ja", "th"];
+
+#[derive(Default)]
+struct ScriptCounts {
+    latin: usize,
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    cyrillic: usize,
+    greek: usize,
+    arabic: usize,
+    hebrew: usize,
+    thai: usize,
+    devanagari: usize,
+}
+
+/// The human language of `text` as an ISO 639-1 code, or None when the text is too short or
+/// no language stands out (code, tables of numbers, mixed fragments)
+pub fn detect(text: &str) -> Option<&'static str> {
+    let mut counts = ScriptCounts::default();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        match c as u32 {
+            0x0041..=0x024F => counts.latin += 1,
+            0x0370..=0x03FF => counts.greek += 1,
+            0x0400..=0x04FF => counts.cyrillic += 1,
+            0x0590..=0x05FF => counts.hebrew += 1,
+            0x0600..=0x06FF => counts.arabic += 1,
+            0x0900..=0x097F => counts.devanagari += 1,
+            0x0E00..=0x0E7F => counts.thai += 1,
+            0x3040..=0x30FF => counts.kana += 1,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => counts.hangul += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => counts.han += 1,
+            _ => {}
+        }
+    }
+
+    // Japanese mixes kana into Han text; a little kana is enough to tell it from Chinese
+    let scripts = [
+        (counts.han + counts.kana, if counts.kana * 10 >= counts.han + counts.kana { "ja" } else { "zh" }),
+        (counts.hangul, "ko"),
+        (counts.cyrillic, "ru"),
+        (counts.greek, "el"),
+        (counts.arabic, "ar"),
+        (counts.hebrew, "he"),
+        (counts.thai, "th"),
+        (counts.devanagari, "hi"),
+    ];
+    let (count, language) = scripts.into_iter().max_by_key(|(count, _)| *count)?;
+    // Han characters carry a word each, so fewer of them make a text
+    let weight = if matches!(language, "zh" | "ja") { 4 } else { 1 };
+    if count > counts.latin && count * weight >= MIN_LETTERS {
+        return Some(language);
+    }
+    if counts.latin < MIN_LETTERS {
+        return None;
+    }
+
+    let words = words(text);
+    let (language, hits) = STOPWORDS.iter()
+        .map(|(language, stopwords)| (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count()))
+        .max_by_key(|(_, hits)| *hits)?;
+    (hits >= 2 && hits * 20 >= words.len()).then_some(language)
+}
+
+/// Whether `language` is a natural language code this module knows, as opposed to a
+/// programming language recorded in `ChunkMetadata.language`
+pub fn is_natural(language: &str) -> bool {
+    STOPWORDS.iter().any(|(code, _)| *code == language)
+        || UNSPACED.contains(&language)
+        || ["ko", "ru", "el", "ar", "he", "hi"].contains(&language)
+}
+
+/// Lowercased keyword terms of `text` in `language`: words without surrounding punctuation
+/// and without the language's stopwords, with runs of Han, kana and Thai characters cut into
+/// overlapping character pairs
+pub fn tokenize(text: &str, language: &str) -> Vec<String> {
+    let stopwords = STOPWORDS.iter().find(|(code, _)| *code == language).map_or(&[][..], |(_, words)| words);
+    let unspaced = UNSPACED.contains(&language);
+
+    let mut terms = Vec::new();
+    for word in words(text) {
+        if stopwords.contains(&word.as_str()) {
+            continue;
+        }
+        if unspaced && word.chars().any(is_unspaced_char) {
+            let chars: Vec<char> = word.chars().collect();
+            if chars.len() == 1 {
+                terms.push(word);
+            } else {
+                terms.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+            }
+            continue;
+        }
+        terms.push(word);
+    }
+    terms
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_unspaced_char(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0x0E00..=0x0E7F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_language_and_tokenizes_for_it() {
+        assert_eq!(detect("The DMA engine must be held in reset until the clocks are stable."), Some("en"));
+        assert_eq!(detect("Die DMA-Einheit wird zurückgesetzt, bis die Takte stabil sind und der Bus frei ist."), Some("de"));
+        assert_eq!(detect("Le moteur DMA est maintenu en reset tant que les horloges ne sont pas stables."), Some("fr"));
+        assert_eq!(detect("时钟稳定之前，必须将DMA引擎保持在复位状态。"), Some("zh"));
+        assert_eq!(detect("クロックが安定するまで、DMAエンジンをリセット状態に保つ必要があります。"), Some("ja"));
+        assert_eq!(detect("Контроллер DMA удерживается в сбросе до стабилизации тактов."), Some("ru"));
+        assert_eq!(detect("fn reset(dma: &mut Dma) { dma.ctrl.write(0x1); }"), None);
+        assert_eq!(detect("Reset"), None);
+
+        assert_eq!(tokenize("The reset of the DMA engine.", "en"), vec!["reset", "dma", "engine"]);
+        assert_eq!(tokenize("复位状态 DMA", "zh"), vec!["复位", "位状", "状态", "dma"]);
+        assert!(is_natural("de") && !is_natural("rust"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,246 @@
+use super::{Chunk, ChunkType, SemanticChunker};
+use super::markdown::{HeaderInfo, MarkdownProcessor};
+use anyhow::Result;
+use sha2::{Sha256, Digest};
+
+/// TODO-state keywords stripped from heading titles
+const TODO_KEYWORDS: &[&str] = &["TODO", "DONE", "NEXT", "WAITING", "CANCELLED", "CANCELED"];
+
+pub struct OrgProcessor;
+
+/// A `#+BEGIN_SRC` / `#+BEGIN_EXAMPLE` block being collected
+struct SourceBlock {
+    end_marker: String,
+    language: Option<String>,
+    start_line: usize,
+    lines: Vec<String>,
+}
+
+impl OrgProcessor {
+    /// Chunk an org file by its `*` heading hierarchy. Prose in each section is chunked as
+    /// text, source blocks are kept whole as code chunks, and heading tags become chunk tags.
+    pub fn extract_and_chunk(content: &str, file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+
+        let mut all_chunks = Vec::new();
+        let mut header_stack: Vec<HeaderInfo> = Vec::new();
+        let mut heading_tags: Vec<Vec<String>> = Vec::new();  // Parallel to header_stack
+        let mut current_section = String::new();
+        let mut block: Option<SourceBlock> = None;
+        let mut in_drawer = false;
+
+        for (line_no, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+
+            if let Some(open) = block.as_mut() {
+                if trimmed.eq_ignore_ascii_case(&open.end_marker) {
+                    let finished = block.take().unwrap();
+                    let tags = Self::active_tags(&heading_tags);
+                    all_chunks.extend(Self::code_chunks(finished, line_no + 1, &header_stack, &tags, file_path, &file_hash, chunker)?);
+                } else {
+                    open.lines.push(line.to_string());
+                }
+                continue;
+            }
+
+            // Property and logbook drawers hold bookkeeping, not content
+            if in_drawer {
+                if trimmed.eq_ignore_ascii_case(":END:") {
+                    in_drawer = false;
+                }
+                continue;
+            }
+
+            if let Some((level, title, tags)) = Self::parse_heading(line) {
+                let section_tags = Self::active_tags(&heading_tags);
+                Self::flush_section(&mut current_section, &header_stack, &section_tags, file_path, chunker, &mut all_chunks)?;
+
+                while header_stack.last().is_some_and(|h| h.level >= level) {
+                    header_stack.pop();
+                    heading_tags.pop();
+                }
+                header_stack.push(HeaderInfo { text: title, level });
+                heading_tags.push(tags);
+                continue;
+            }
+
+            let upper = trimmed.to_uppercase();
+            if upper.starts_with("#+BEGIN_SRC") || upper.starts_with("#+BEGIN_EXAMPLE") {
+                let mut words = trimmed.split_whitespace();
+                let kind = words.next().unwrap_or_default()["#+BEGIN_".len()..].to_uppercase();
+                let language = if kind == "SRC" { words.next().map(|lang| lang.to_lowercase()) } else { None };
+
+                block = Some(SourceBlock {
+                    end_marker: format!("#+END_{}", kind),
+                    language,
+                    start_line: line_no + 1,
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+
+            if trimmed.starts_with(':') && trimmed.ends_with(':') && trimmed.len() > 2 && !trimmed.contains(' ') {
+                in_drawer = true;
+                continue;
+            }
+
+            // Keyword lines (#+TITLE:, #+OPTIONS:, ...) and comments
+            if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") {
+                continue;
+            }
+
+            current_section.push_str(line);
+            current_section.push('\n');
+        }
+
+        if let Some(unclosed) = block.take() {
+            let tags = Self::active_tags(&heading_tags);
+            let end_line = content.lines().count();
+            all_chunks.extend(Self::code_chunks(unclosed, end_line, &header_stack, &tags, file_path, &file_hash, chunker)?);
+        }
+
+        let tags = Self::active_tags(&heading_tags);
+        Self::flush_section(&mut current_section, &header_stack, &tags, file_path, chunker, &mut all_chunks)?;
+
+        Ok(all_chunks)
+    }
+
+    /// `** TODO [#A] Title   :tag1:tag2:` -> (2, "Title", ["tag1", "tag2"])
+    fn parse_heading(line: &str) -> Option<(u32, String, Vec<String>)> {
+        let level = line.chars().take_while(|&c| c == '*').count();
+        if level == 0 || !line[level..].starts_with(' ') {
+            return None;
+        }
+
+        let mut title = line[level..].trim().to_string();
+        let mut tags = Vec::new();
+
+        // Trailing :tag1:tag2: block
+        if let Some(last) = title.split_whitespace().last() {
+            if last.len() > 2 && last.starts_with(':') && last.ends_with(':') {
+                tags = last.trim_matches(':').split(':').filter(|t| !t.is_empty()).map(|t| t.to_lowercase()).collect();
+                title = title[..title.len() - last.len()].trim_end().to_string();
+            }
+        }
+
+        let mut words: Vec<&str> = title.split_whitespace().collect();
+        if words.first().is_some_and(|w| TODO_KEYWORDS.contains(w)) {
+            words.remove(0);
+        }
+        if words.first().is_some_and(|w| w.starts_with("[#") && w.ends_with(']')) {
+            words.remove(0);
+        }
+
+        let title = words.join(" ");
+        if title.is_empty() {
+            return None;
+        }
+
+        Some((level as u32, title, tags))
+    }
+
+    /// Org tags are inherited by sub-headings
+    fn active_tags(heading_tags: &[Vec<String>]) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in heading_tags.iter().flatten() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    fn apply_tags(chunk: &mut Chunk, tags: &[String]) {
+        for tag in tags {
+            if !chunk.metadata.tags.contains(tag) {
+                chunk.metadata.tags.push(tag.clone());
+            }
+        }
+    }
+
+    fn flush_section(
+        section: &mut String,
+        headers: &[HeaderInfo],
+        tags: &[String],
+        file_path: &str,
+        chunker: &SemanticChunker,
+        all_chunks: &mut Vec<Chunk>,
+    ) -> Result<()> {
+        if section.trim().is_empty() {
+            section.clear();
+            return Ok(());
+        }
+
+        let mut chunks = chunker.chunk_text(section, file_path)?;
+        let (chapter, section_name) = MarkdownProcessor::extract_chapter_and_section(headers);
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Org;
+            chunk.metadata.chapter = chapter.clone();
+            chunk.metadata.section = section_name.clone();
+            Self::apply_tags(chunk, tags);
+        }
+
+        all_chunks.extend(chunks);
+        section.clear();
+        Ok(())
+    }
+
+    fn code_chunks(
+        block: SourceBlock,
+        end_line: usize,
+        headers: &[HeaderInfo],
+        tags: &[String],
+        file_path: &str,
+        file_hash: &str,
+        chunker: &SemanticChunker,
+    ) -> Result<Vec<Chunk>> {
+        let code = block.lines.join("\n");
+        if code.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let language = block.language.unwrap_or_else(|| "text".to_string());
+
+        // Keep blocks whole unless they exceed the size limit
+        let mut chunks = if code.len() > chunker.max_chunk_size() {
+            chunker.chunk_code(&code, &language, file_path)?
+        } else {
+            vec![SemanticChunker::build_code_chunk(&code, &language, file_path, file_hash, (block.start_line, end_line))]
+        };
+
+        let (chapter, section_name) = MarkdownProcessor::extract_chapter_and_section(headers);
+        for chunk in &mut chunks {
+            chunk.metadata.chapter = chapter.clone();
+            if chunk.metadata.section.is_none() {
+                chunk.metadata.section = section_name.clone();
+            }
+            Self::apply_tags(chunk, tags);
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings_tags_and_source_blocks() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let content = "#+TITLE: Notes\n* Verification :uvm:\n** TODO Scoreboard design\n:PROPERTIES:\n:ID: 1234\n:END:\nThe scoreboard compares expected and actual transactions per channel.\n#+begin_src python\ndef compare(a, b):\n\n    return a == b\n#+end_src\n";
+
+        let chunks = OrgProcessor::extract_and_chunk(content, "notes.org", &chunker).unwrap();
+
+        let prose = chunks.iter().find(|c| matches!(c.metadata.chunk_type, ChunkType::Org)).unwrap();
+        assert_eq!(prose.metadata.chapter.as_deref(), Some("Verification"));
+        assert_eq!(prose.metadata.section.as_deref(), Some("Scoreboard design"));
+        assert!(prose.metadata.tags.contains(&"uvm".to_string()));
+        assert!(!prose.content.contains(":ID:"));
+
+        let code: Vec<_> = chunks.iter().filter(|c| matches!(c.metadata.chunk_type, ChunkType::Code)).collect();
+        assert_eq!(code.len(), 1);
+        assert!(code[0].content.contains("return a == b"));
+    }
+}
\ No newline at end of file
@@ -1,21 +1,442 @@
-use super::{Chunk, ChunkMetadata, ChunkType};
-use anyhow::Result;
-use pdf_extract::extract_text;
-use uuid::Uuid;
+use super::markdown::HeaderInfo;
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::{Result, anyhow};
+use pdf_extract::{Document, MediaBox, OutputDev, OutputError, Transform};
+use sha2::{Sha256, Digest};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Lines set at least this much larger than body text are heading candidates
+const HEADING_SIZE_RATIO: f64 = 1.15;
+const MAX_HEADING_LEN: usize = 120;
+
+/// Lines at the top and at the bottom of a page checked for headers, footers and page numbers
+const EDGE_LINES: usize = 2;
+/// Share of pages a header or footer must appear on, unless it runs over consecutive pages
+const RUNNING_SHARE: f64 = 0.4;
 
 pub struct PdfProcessor;
 
+/// One visual line of text with its page and dominant font size
+#[derive(Debug, Clone)]
+struct PdfLine {
+    page: u32,
+    text: String,
+    font_size: f64,
+    gap_before: bool,  // Extra vertical space above the line, i.e. a paragraph break
+}
+
+/// Body text between headings, with the pages and character offsets it spans
+struct Paragraph {
+    text: String,
+    page_start: u32,
+    page_end: u32,
+    offset: usize,
+}
+
 impl PdfProcessor {
-    pub fn extract_and_chunk(file_path: &str, chunker: &super::SemanticChunker) -> Result<Vec<Chunk>> {
-        let text = extract_text(file_path)?;
+    /// Chunk a PDF from its text layout: headings are detected by font size (and section
+    /// numbering) to fill chapter/section, and every chunk records the pages it covers.
+    pub fn extract_and_chunk(file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let bytes = std::fs::read(file_path)?;
+        Self::chunk_bytes(&bytes, file_path, chunker)
+    }
+
+    pub fn chunk_bytes(bytes: &[u8], file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(bytes));
+
+        let mut document = Document::load_mem(bytes)
+            .map_err(|e| anyhow!("Failed to parse PDF {}: {}", file_path, e))?;
+        if document.is_encrypted() {
+            document.decrypt("")
+                .map_err(|e| anyhow!("{} is encrypted and could not be opened without a password: {}", file_path, e))?;
+        }
+
+        let mut collector = LayoutCollector::default();
+        pdf_extract::output_doc(&document, &mut collector)
+            .map_err(|e| anyhow!("Failed to extract text from {}: {}", file_path, e))?;
+
+        Self::chunk_lines(Self::remove_running_headers(collector.lines), file_path, &file_hash, chunker)
+    }
+
+    fn chunk_lines(lines: Vec<PdfLine>, file_path: &str, file_hash: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let body_size = Self::body_font_size(&lines);
+        let heading_ranks = Self::heading_ranks(&lines, body_size);
+
+        let mut chunks = Vec::new();
+        let mut header_stack: Vec<HeaderInfo> = Vec::new();
+        let mut paragraphs: Vec<Paragraph> = Vec::new();
+        let mut offset = 0;
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = &lines[index];
+
+            if Self::is_heading(line, body_size) {
+                // Headings wrapped over several lines share a size and have no gap between them
+                let mut title = line.text.clone();
+                while let Some(next) = lines.get(index + 1) {
+                    if next.gap_before || next.page != line.page || Self::size_key(next.font_size) != Self::size_key(line.font_size) {
+                        break;
+                    }
+                    title.push(' ');
+                    title.push_str(&next.text);
+                    offset += lines[index].text.chars().count() + 1;
+                    index += 1;
+                }
+
+                chunks.extend(Self::flush_section(&mut paragraphs, &header_stack, file_path, file_hash, chunker)?);
+                let level = Self::numbering_depth(&title)
+                    .unwrap_or_else(|| heading_ranks.get(&Self::size_key(line.font_size)).copied().unwrap_or(1));
+                header_stack.retain(|h| h.level < level);
+                header_stack.push(HeaderInfo { text: title, level });
+            } else {
+                match paragraphs.last_mut() {
+                    Some(paragraph) if !line.gap_before => {
+                        // Re-join words hyphenated across a line break
+                        let continues_word = paragraph.text.ends_with('-') && line.text.starts_with(char::is_lowercase);
+                        if continues_word {
+                            paragraph.text.pop();
+                        } else {
+                            paragraph.text.push(' ');
+                        }
+                        paragraph.text.push_str(&line.text);
+                        paragraph.page_end = line.page;
+                    }
+                    _ => paragraphs.push(Paragraph {
+                        text: line.text.clone(),
+                        page_start: line.page,
+                        page_end: line.page,
+                        offset,
+                    }),
+                }
+            }
+
+            offset += lines[index].text.chars().count() + 1;
+            index += 1;
+        }
+
+        chunks.extend(Self::flush_section(&mut paragraphs, &header_stack, file_path, file_hash, chunker)?);
+        Ok(chunks)
+    }
+
+    /// Pack a section's paragraphs into chunks, splitting paragraphs that are too long on their own
+    fn flush_section(
+        paragraphs: &mut Vec<Paragraph>,
+        headers: &[HeaderInfo],
+        file_path: &str,
+        file_hash: &str,
+        chunker: &SemanticChunker,
+    ) -> Result<Vec<Chunk>> {
+        // The outermost open heading is the chapter and the innermost the section, so a chunk
+        // under "4.3 Reset Sequence" cites both "4 Clocking and Reset" and "4.3"
+        let chapter = headers.first().map(|h| h.text.clone());
+        let section = headers.last().map(|h| h.text.clone());
+        let max_size = chunker.max_chunk_size();
+        let mut chunks = Vec::new();
+        let mut packed: Vec<&Paragraph> = Vec::new();
+        let mut packed_len = 0;
+
+        for paragraph in paragraphs.iter() {
+            if !packed.is_empty() && packed_len + paragraph.text.len() + 2 > max_size {
+                chunks.push(Self::packed_chunk(&packed, file_path, file_hash));
+                packed.clear();
+                packed_len = 0;
+            }
 
-        let mut chunks = chunker.chunk_text(&text, file_path)?;
+            if paragraph.text.len() > max_size {
+                for mut chunk in chunker.chunk_text(&paragraph.text, file_path)? {
+                    chunk.boundaries = (chunk.boundaries.0 + paragraph.offset, chunk.boundaries.1 + paragraph.offset);
+                    chunk.metadata.line_start = chunk.boundaries.0;
+                    chunk.metadata.line_end = chunk.boundaries.1;
+                    chunk.metadata.file_hash = Some(file_hash.to_string());
+                    chunk.metadata.page_start = Some(paragraph.page_start);
+                    chunk.metadata.page_end = Some(paragraph.page_end);
+                    chunks.push(chunk);
+                }
+                continue;
+            }
+
+            packed_len += paragraph.text.len() + 2;
+            packed.push(paragraph);
+        }
+
+        if !packed.is_empty() {
+            chunks.push(Self::packed_chunk(&packed, file_path, file_hash));
+        }
 
-        // Update chunk metadata to indicate PDF source
         for chunk in &mut chunks {
             chunk.metadata.chunk_type = ChunkType::Pdf;
+            chunk.metadata.chapter = chapter.clone();
+            chunk.metadata.section = section.clone();
         }
 
+        paragraphs.clear();
         Ok(chunks)
     }
+
+    fn packed_chunk(paragraphs: &[&Paragraph], file_path: &str, file_hash: &str) -> Chunk {
+        let content = paragraphs.iter().map(|p| p.text.as_str()).collect::<Vec<_>>().join("\n\n");
+        let first = paragraphs[0];
+        let last = paragraphs[paragraphs.len() - 1];
+        let boundaries = (first.offset, last.offset + last.text.chars().count());
+
+        let mut chunk = SemanticChunker::build_text_chunk(&content, file_path, file_hash, boundaries, ChunkStrategy::ParagraphPacked);
+        chunk.metadata.page_start = Some(first.page_start);
+        chunk.metadata.page_end = Some(last.page_end);
+        chunk
+    }
+
+    /// Drop page numbers and running headers/footers from the top and bottom `EDGE_LINES`
+    /// lines of each page: lines that are only a page number ("12", "- 12 -", "Page 3 of 40",
+    /// "iv"), and lines whose text (ignoring digits) recurs at a page edge on at least 3 pages,
+    /// either on `RUNNING_SHARE` of all pages (document titles, alternating odd/even headers)
+    /// or on consecutive pages (chapter titles)
+    fn remove_running_headers(lines: Vec<PdfLine>) -> Vec<PdfLine> {
+        let normalized = |text: &str| text.chars().filter(|c| !c.is_ascii_digit()).collect::<String>().trim().to_lowercase();
+
+        let mut bounds: HashMap<u32, (usize, usize)> = HashMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            let bound = bounds.entry(line.page).or_insert((i, i));
+            bound.1 = i;
+        }
+        let pages = bounds.len();
+        let is_edge = |i: usize, page: u32| bounds.get(&page).is_some_and(|&(first, last)| i < first + EDGE_LINES || i + EDGE_LINES > last);
+
+        let mut on_pages: HashMap<String, BTreeSet<u32>> = HashMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            if is_edge(i, line.page) {
+                on_pages.entry(normalized(&line.text)).or_default().insert(line.page);
+            }
+        }
+        let repeated: HashSet<String> = on_pages.into_iter()
+            .filter(|(text, on_pages)| text.is_empty() || Self::is_running(on_pages, pages))
+            .map(|(text, _)| text)
+            .collect();
+
+        lines.into_iter()
+            .enumerate()
+            .filter(|(i, line)| !(is_edge(*i, line.page) && (Self::is_page_number(&line.text) || repeated.contains(&normalized(&line.text)))))
+            .map(|(_, line)| line)
+            .collect()
+    }
+
+    fn is_running(on_pages: &BTreeSet<u32>, pages: usize) -> bool {
+        let consecutive = on_pages.iter().zip(on_pages.iter().skip(2)).any(|(first, third)| third - first == 2);
+        on_pages.len() >= 3 && (on_pages.len() as f64 >= pages as f64 * RUNNING_SHARE || consecutive)
+    }
+
+    /// "12", "- 12 -", "[12]", "Page 12", "Page 12 of 40", "12/40", "Seite 3 von 9", "xiv"
+    fn is_page_number(text: &str) -> bool {
+        let text = text.trim().trim_matches(|c: char| matches!(c, '-' | '–' | '—' | '(' | ')' | '[' | ']' | '|') || c.is_whitespace()).to_lowercase();
+        let text = ["page", "seite", "pg.", "p."].iter()
+            .find_map(|prefix| text.strip_prefix(prefix))
+            .unwrap_or(&text)
+            .trim();
+        let number = |word: &str| !word.is_empty() && (word.chars().all(|c| c.is_ascii_digit()) || (word.len() <= 6 && word.chars().all(|c| "ivxlc".contains(c))));
+
+        let words: Vec<&str> = text.split(|c: char| c.is_whitespace() || c == '/').filter(|word| !word.is_empty()).collect();
+        match words.as_slice() {
+            [page] => number(page),
+            [page, total] => number(page) && total.chars().all(|c| c.is_ascii_digit()),
+            [page, "of" | "von" | "de", total] => number(page) && number(total),
+            _ => false,
+        }
+    }
+
+    /// The font size covering the most text
+    fn body_font_size(lines: &[PdfLine]) -> f64 {
+        let mut weights: HashMap<u32, usize> = HashMap::new();
+        for line in lines {
+            *weights.entry(Self::size_key(line.font_size)).or_insert(0) += line.text.len();
+        }
+        weights.into_iter()
+            .max_by_key(|&(key, weight)| (weight, std::cmp::Reverse(key)))
+            .map(|(key, _)| key as f64 / 2.0)
+            .unwrap_or(0.0)
+    }
+
+    fn is_heading(line: &PdfLine, body_size: f64) -> bool {
+        body_size > 0.0
+            && line.font_size >= body_size * HEADING_SIZE_RATIO
+            && line.text.len() <= MAX_HEADING_LEN
+            && line.text.chars().any(char::is_alphabetic)
+            && !line.text.ends_with(['.', ',', ';'])
+    }
+
+    /// Heading level by font size: the largest heading size is level 1
+    fn heading_ranks(lines: &[PdfLine], body_size: f64) -> HashMap<u32, u32> {
+        let sizes: BTreeSet<u32> = lines.iter()
+            .filter(|line| Self::is_heading(line, body_size))
+            .map(|line| Self::size_key(line.font_size))
+            .collect();
+        sizes.into_iter().rev().enumerate().map(|(rank, key)| (key, rank as u32 + 1)).collect()
+    }
+
+    /// "4.3 Reset" -> 2, "Chapter 4" -> 1; numbering is a better level signal than font size
+    fn numbering_depth(title: &str) -> Option<u32> {
+        let mut words = title.split_whitespace();
+        let first = words.next()?;
+        if first.eq_ignore_ascii_case("chapter") {
+            return Some(1);
+        }
+
+        let numbering = first.trim_end_matches('.');
+        let parts: Vec<&str> = numbering.split('.').collect();
+        let numbered = words.next().is_some() && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+        numbered.then_some(parts.len() as u32)
+    }
+
+    /// Font sizes bucketed to half points
+    fn size_key(size: f64) -> u32 {
+        (size * 2.0).round() as u32
+    }
+}
+
+/// Receives positioned glyphs from pdf-extract and groups them into lines, tracking the
+/// font size of each line. Word and line breaks follow pdf-extract's plain-text heuristics.
+#[derive(Default)]
+struct LayoutCollector {
+    lines: Vec<PdfLine>,
+    page: u32,
+    page_height: f64,
+    line: String,
+    line_sizes: HashMap<u32, usize>,
+    last_x_end: f64,
+    last_y: f64,
+    new_word: bool,
+    gap: bool,
+}
+
+impl LayoutCollector {
+    fn finish_line(&mut self) {
+        let text = self.line.trim();
+        if !text.is_empty() {
+            let font_size = self.line_sizes.iter()
+                .max_by_key(|&(key, count)| (*count, *key))
+                .map(|(key, _)| *key as f64 / 2.0)
+                .unwrap_or(0.0);
+            self.lines.push(PdfLine { page: self.page, text: text.to_string(), font_size, gap_before: self.gap });
+            self.gap = false;
+        }
+        self.line.clear();
+        self.line_sizes.clear();
+    }
+}
+
+impl OutputDev for LayoutCollector {
+    fn begin_page(&mut self, page_num: u32, media_box: &MediaBox, _art_box: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.finish_line();
+        self.page = page_num;
+        self.page_height = media_box.ury - media_box.lly;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.finish_line();
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        let size = ((font_size * (trm.m11 + trm.m21)) * (font_size * (trm.m12 + trm.m22))).abs().sqrt();
+        let (x, y) = (trm.m31, self.page_height - trm.m32);
+
+        if self.new_word && !self.line.is_empty() {
+            let dy = (y - self.last_y).abs();
+            if dy > size * 1.5 || (x < self.last_x_end && dy > size * 0.5) {
+                self.finish_line();
+                self.gap = dy > size * 1.5;
+            } else if x > self.last_x_end + size * 0.1 {
+                self.line.push(' ');
+            }
+        }
+
+        self.line.push_str(char);
+        *self.line_sizes.entry(PdfProcessor::size_key(size)).or_insert(0) += 1;
+        self.new_word = false;
+        self.last_y = y;
+        self.last_x_end = x + width * size;
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.new_word = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(page: u32, text: &str, font_size: f64, gap_before: bool) -> PdfLine {
+        PdfLine { page, text: text.to_string(), font_size, gap_before }
+    }
+
+    #[test]
+    fn test_headings_and_pages_from_layout() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let mut lines = Vec::new();
+        for page in 1..=4 {
+            lines.push(line(page, "DMA Controller User Guide", 8.0, false));
+        }
+        lines.insert(1, line(1, "4 Clocking and Reset", 18.0, true));
+        lines.insert(2, line(1, "The controller has two clock domains, the bus clock and the", 10.0, true));
+        lines.insert(3, line(1, "core clock, synchronized by a two-flop synchro-", 10.0, false));
+        lines.insert(4, line(1, "nizer on every crossing.", 10.0, false));
+        lines.insert(6, line(2, "4.3 Reset Sequence", 14.0, true));
+        lines.insert(7, line(2, "Assert rst_n for at least 16 core clock cycles before the first access.", 10.0, true));
+        lines.insert(9, line(3, "Release reset only after both clocks are stable.", 10.0, false));
+
+        let lines = PdfProcessor::remove_running_headers(lines);
+        assert!(lines.iter().all(|l| l.text != "DMA Controller User Guide"));
+
+        let chunks = PdfProcessor::chunk_lines(lines, "dma.pdf", "hash", &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].content.contains("two-flop synchronizer on every crossing"));
+        assert_eq!(chunks[0].metadata.chapter.as_deref(), Some("4 Clocking and Reset"));
+        assert_eq!(chunks[0].metadata.page_start, Some(1));
+
+        let reset = &chunks[1];
+        assert_eq!(reset.metadata.section.as_deref(), Some("4.3 Reset Sequence"));
+        assert_eq!(reset.metadata.chapter.as_deref(), Some("4 Clocking and Reset"));
+        assert_eq!((reset.metadata.page_start, reset.metadata.page_end), (Some(2), Some(3)));
+        assert!(matches!(reset.metadata.chunk_type, ChunkType::Pdf));
+    }
+
+    #[test]
+    fn test_strips_alternating_headers_and_page_number_footers() {
+        let mut lines = Vec::new();
+        for page in 1..=6 {
+            lines.push(line(page, if page % 2 == 1 { "DMA Controller User Guide" } else { "Rev 1.2 Confidential" }, 8.0, false));
+            let step = ["Assert rst_n.", "Wait for PLL lock.", "Enable the bus clock.", "Release rst_n.", "Poll the status register.", "Start the channels."][page as usize - 1];
+            lines.push(line(page, step, 10.0, true));
+            lines.push(line(page, "Clock domains stay separate.", 10.0, false));
+            lines.push(line(page, step, 10.0, false));
+            lines.push(line(page, "(c) 2024 Example Corp.", 8.0, true));
+            lines.push(line(page, &match page {
+                1 => "Page 1 of 6".to_string(),
+                2 => "- 2 -".to_string(),
+                3 => "iii".to_string(),
+                _ => format!("{page} / 6"),
+            }, 8.0, false));
+        }
+
+        let kept: Vec<String> = PdfProcessor::remove_running_headers(lines).into_iter().map(|l| l.text).collect();
+        assert_eq!(kept.len(), 18);
+        assert!(kept.iter().all(|text| !text.contains("Guide") && !text.contains("Rev") && !text.contains("Corp")));
+        // Repeated body text away from the page edges stays
+        assert_eq!(kept.iter().filter(|text| *text == "Clock domains stay separate.").count(), 6);
+
+        assert!(PdfProcessor::is_page_number("Seite 3 von 9") && PdfProcessor::is_page_number("[12]"));
+        assert!(!PdfProcessor::is_page_number("Figure 3") && !PdfProcessor::is_page_number("4 Clocking and Reset"));
+    }
 }
\ No newline at end of file
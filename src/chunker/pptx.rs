@@ -0,0 +1,297 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::{Result, anyhow};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Placeholder types that only carry slide furniture
+const SKIPPED_PLACEHOLDERS: &[&str] = &["sldNum", "dt", "ftr", "hdr", "sldImg"];
+
+pub struct PptxProcessor;
+
+/// Text pulled out of one slide or notes page
+#[derive(Default)]
+struct SlideText {
+    title: Option<String>,
+    body: Vec<String>,
+}
+
+impl PptxProcessor {
+    /// Chunk a PowerPoint deck with one chunk per slide: title, body text and speaker notes.
+    /// Slides too large for a single chunk are split, keeping the slide number on each part.
+    pub fn extract_and_chunk(file_path: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let bytes = std::fs::read(file_path)?;
+        let file_hash = format!("{:x}", Sha256::digest(&bytes));
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+            .map_err(|e| anyhow!("{} is not a valid PPTX archive: {}", file_path, e))?;
+
+        let mut chunks = Vec::new();
+        for (index, slide_path) in Self::slide_order(&mut archive)?.iter().enumerate() {
+            let slide_number = index + 1;
+            let slide = Self::extract_text(&Self::read_entry(&mut archive, slide_path)?)?;
+
+            let notes = match Self::notes_path(&mut archive, slide_path)? {
+                Some(notes_path) => Self::extract_text(&Self::read_entry(&mut archive, &notes_path)?)?.body,
+                None => Vec::new(),
+            };
+
+            let mut text = String::new();
+            if let Some(title) = &slide.title {
+                text.push_str(title);
+                text.push_str("\n\n");
+            }
+            text.push_str(&slide.body.join("\n"));
+            if !notes.is_empty() {
+                text.push_str("\n\nNotes:\n");
+                text.push_str(&notes.join("\n"));
+            }
+
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let mut slide_chunks = if text.len() > chunker.max_chunk_size() {
+                chunker.chunk_text(text, file_path)?
+            } else {
+                vec![SemanticChunker::build_text_chunk(text, file_path, &file_hash, (slide_number, slide_number + 1), ChunkStrategy::NaturalSection)]
+            };
+
+            for chunk in &mut slide_chunks {
+                chunk.metadata.chunk_type = ChunkType::Presentation;
+                chunk.metadata.section = Some(match &slide.title {
+                    Some(title) => format!("Slide {}: {}", slide_number, title),
+                    None => format!("Slide {}", slide_number),
+                });
+                chunk.metadata.line_start = slide_number;
+                chunk.metadata.line_end = slide_number;
+                chunk.metadata.attributes.insert("slide_number".to_string(), slide_number.to_string());
+                if !notes.is_empty() {
+                    chunk.metadata.attributes.insert("has_notes".to_string(), "true".to_string());
+                }
+            }
+
+            chunks.extend(slide_chunks);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Slide part names in presentation order, from presentation.xml's slide id list
+    fn slide_order<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<Vec<String>> {
+        let rels = Self::relationships(archive, "ppt/_rels/presentation.xml.rels", "ppt/")?;
+        let presentation = Self::read_entry(archive, "ppt/presentation.xml")?;
+
+        let mut order = Vec::new();
+        let mut reader = Reader::from_str(&presentation);
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sldId" => {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.local_name().as_ref() == b"id" && attr.key.prefix().is_some() {
+                            let rel_id = String::from_utf8_lossy(&attr.value).to_string();
+                            if let Some(target) = rels.get(&rel_id) {
+                                order.push(target.clone());
+                            }
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        // Fall back to slideN.xml numbering if the id list is missing
+        if order.is_empty() {
+            let mut slides: Vec<(usize, String)> = archive.file_names()
+                .filter(|name| name.starts_with("ppt/slides/slide") && name.ends_with(".xml"))
+                .filter_map(|name| {
+                    let number = name.trim_start_matches("ppt/slides/slide").trim_end_matches(".xml").parse().ok()?;
+                    Some((number, name.to_string()))
+                })
+                .collect();
+            slides.sort();
+            order = slides.into_iter().map(|(_, name)| name).collect();
+        }
+
+        Ok(order)
+    }
+
+    /// The notes page linked from a slide's relationships, if any
+    fn notes_path<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>, slide_path: &str) -> Result<Option<String>> {
+        let (dir, file) = slide_path.rsplit_once('/').unwrap_or(("", slide_path));
+        let rels_path = format!("{}/_rels/{}.rels", dir, file);
+        if archive.by_name(&rels_path).is_err() {
+            return Ok(None);
+        }
+
+        let content = Self::read_entry(archive, &rels_path)?;
+        let mut reader = Reader::from_str(&content);
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    let attrs = Self::attributes(&e);
+                    if attrs.get("Type").is_some_and(|t| t.ends_with("/notesSlide")) {
+                        if let Some(target) = attrs.get("Target") {
+                            return Ok(Some(Self::resolve(&format!("{}/", dir), target)));
+                        }
+                    }
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    /// Relationship id -> resolved part name
+    fn relationships<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>, rels_path: &str, base: &str) -> Result<HashMap<String, String>> {
+        let content = Self::read_entry(archive, rels_path)?;
+        let mut reader = Reader::from_str(&content);
+        let mut rels = HashMap::new();
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    let attrs = Self::attributes(&e);
+                    if let (Some(id), Some(target)) = (attrs.get("Id"), attrs.get("Target")) {
+                        rels.insert(id.clone(), Self::resolve(base, target));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(rels)
+    }
+
+    /// Resolve a relationship target (possibly with ../) against the directory of its source part
+    fn resolve(base: &str, target: &str) -> String {
+        if let Some(absolute) = target.strip_prefix('/') {
+            return absolute.to_string();
+        }
+
+        let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+        for segment in target.split('/') {
+            match segment {
+                ".." => { parts.pop(); }
+                "." | "" => {}
+                other => parts.push(other),
+            }
+        }
+        parts.join("/")
+    }
+
+    fn attributes(element: &quick_xml::events::BytesStart) -> HashMap<String, String> {
+        element.attributes()
+            .flatten()
+            .map(|attr| (
+                String::from_utf8_lossy(attr.key.local_name().as_ref()).to_string(),
+                attr.unescape_value().map(|v| v.to_string()).unwrap_or_default(),
+            ))
+            .collect()
+    }
+
+    fn read_entry<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Result<String> {
+        let mut entry = archive.by_name(name)
+            .map_err(|e| anyhow!("Missing {} in PPTX: {}", name, e))?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    /// Collect paragraphs from a slide or notes part. Text in title placeholders becomes the
+    /// title; slide numbers, dates and footers are dropped.
+    fn extract_text(xml: &str) -> Result<SlideText> {
+        let mut reader = Reader::from_str(xml);
+        let mut slide = SlideText::default();
+
+        let mut shape_depth = 0;
+        let mut placeholder: Option<String> = None;
+        let mut shape_paragraphs: Vec<String> = Vec::new();
+        let mut paragraph = String::new();
+        let mut in_text = false;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"sp" => {
+                        shape_depth += 1;
+                        if shape_depth == 1 {
+                            placeholder = None;
+                            shape_paragraphs.clear();
+                        }
+                    }
+                    b"t" => in_text = true,
+                    b"ph" => placeholder = Some(Self::attributes(&e).get("type").cloned().unwrap_or_default()),
+                    _ => {}
+                },
+                Event::Empty(e) => match e.local_name().as_ref() {
+                    b"ph" => placeholder = Some(Self::attributes(&e).get("type").cloned().unwrap_or_default()),
+                    b"br" => paragraph.push('\n'),
+                    _ => {}
+                },
+                Event::Text(text) if in_text => paragraph.push_str(&text.unescape()?),
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"t" => in_text = false,
+                    b"p" => {
+                        let text = paragraph.trim().to_string();
+                        paragraph.clear();
+                        if !text.is_empty() {
+                            if shape_depth > 0 {
+                                shape_paragraphs.push(text);
+                            } else {
+                                slide.body.push(text);  // Table cells and other non-shape text
+                            }
+                        }
+                    }
+                    b"sp" => {
+                        shape_depth -= 1;
+                        if shape_depth == 0 {
+                            let paragraphs = std::mem::take(&mut shape_paragraphs);
+                            match placeholder.as_deref() {
+                                Some("title") | Some("ctrTitle") if slide.title.is_none() && !paragraphs.is_empty() => {
+                                    slide.title = Some(paragraphs.join(" "));
+                                }
+                                Some(kind) if SKIPPED_PLACEHOLDERS.contains(&kind) => {}
+                                _ => slide.body.extend(paragraphs),
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(slide)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_body_and_furniture() {
+        let xml = r#"<p:sld xmlns:p="p" xmlns:a="a"><p:cSld><p:spTree>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>DMA Review</a:t></a:r></a:p></p:txBody></p:sp>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph idx="1"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>Burst length </a:t></a:r><a:r><a:t>is 16 beats</a:t></a:r></a:p><a:p><a:r><a:t>Open issue: reset</a:t></a:r></a:p></p:txBody></p:sp>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="sldNum"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>7</a:t></a:r></a:p></p:txBody></p:sp>
+        </p:spTree></p:cSld></p:sld>"#;
+
+        let slide = PptxProcessor::extract_text(xml).unwrap();
+
+        assert_eq!(slide.title.as_deref(), Some("DMA Review"));
+        assert_eq!(slide.body, vec!["Burst length is 16 beats".to_string(), "Open issue: reset".to_string()]);
+    }
+
+    #[test]
+    fn test_relative_targets_resolve() {
+        assert_eq!(PptxProcessor::resolve("ppt/slides/", "../notesSlides/notesSlide3.xml"), "ppt/notesSlides/notesSlide3.xml");
+        assert_eq!(PptxProcessor::resolve("ppt/", "slides/slide1.xml"), "ppt/slides/slide1.xml");
+    }
+}
\ No newline at end of file
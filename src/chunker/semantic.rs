@@ -1,8 +1,9 @@
 use anyhow::Result;
-use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
 use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use super::languages::LanguageRules;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
@@ -24,10 +25,99 @@ pub struct ChunkMetadata {
     pub timestamp: chrono::DateTime<chrono::Utc>,  // When the chunk was created
     pub line_start: usize,                // Starting line number in source file
     pub line_end: usize,                  // Ending line number in source file
+    #[serde(default)]
+    pub byte_start: Option<usize>,        // Byte offsets in the text the chunk was cut from, when known
+    #[serde(default)]
+    pub byte_end: Option<usize>,
     pub tags: Vec<String>,                 // Searchable tags
     pub dependencies: Vec<String>,        // For code: imported modules/packages
     pub chunk_size: usize,                // Size of chunk in bytes
     pub parent_chunk_id: Option<String>,  // For hierarchical chunking
+    #[serde(default)]
+    pub chunk_strategy: Option<ChunkStrategy>,  // How the chunk boundaries were chosen
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,  // Processor-specific metadata (encoding, source pointers, ...)
+    #[serde(default)]
+    pub page_start: Option<u32>,          // For paginated sources (PDF): first page the chunk covers
+    #[serde(default)]
+    pub page_end: Option<u32>,
+    #[serde(default)]
+    pub title: Option<String>,            // Document title from front matter or file properties
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,             // Document date as written in the source (e.g. 2024-03-01)
+}
+
+impl Chunk {
+    /// Where the chunk sits, e.g. "File: uart.md > Chapter: Registers > Section: BAUD"
+    pub fn context_header(&self) -> String {
+        let metadata = &self.metadata;
+        let file = std::path::Path::new(&metadata.source_file)
+            .file_name()
+            .map_or(metadata.source_file.clone(), |name| name.to_string_lossy().into_owned());
+        let mut parts = vec![format!("File: {}", file)];
+        if let Some(chapter) = &metadata.chapter {
+            parts.push(format!("Chapter: {}", chapter));
+        }
+        if let Some(section) = metadata.section.as_ref().filter(|section| metadata.chapter.as_ref() != Some(section)) {
+            parts.push(format!("Section: {}", section));
+        }
+        parts.join(" > ")
+    }
+
+    /// The text embedded for this chunk: its content, below the context header if
+    /// `with_header` is set and followed by the `fields` it has, e.g. "Tags: dma, reset", so
+    /// queries naming a tag or module match chunks whose body does not repeat it
+    pub fn embedding_text(&self, with_header: bool, fields: &[EmbeddedField]) -> String {
+        let mut text = if with_header {
+            format!("{}\n\n{}", self.context_header(), self.content)
+        } else {
+            self.content.clone()
+        };
+        let metadata = &self.metadata;
+        for field in fields {
+            let value = match field {
+                EmbeddedField::Tags => metadata.tags.join(", "),
+                EmbeddedField::Dependencies => metadata.dependencies.join(", "),
+                // The header already names the section
+                EmbeddedField::Section if with_header => continue,
+                EmbeddedField::Section => metadata.section.clone().unwrap_or_default(),
+            };
+            if !value.is_empty() {
+                text.push_str(&format!("\n{}: {}", field.label(), value));
+            }
+        }
+        text
+    }
+}
+
+impl ChunkMetadata {
+    /// The document's own date: `date` as written in the source, or an email's Date header
+    pub fn document_date(&self) -> Option<chrono::DateTime<Utc>> {
+        self.date.as_deref().and_then(parse_date).or_else(|| {
+            let seconds = self.attributes.get("date_unix")?.parse().ok()?;
+            chrono::DateTime::from_timestamp(seconds, 0)
+        })
+    }
+}
+
+/// A time as documents commonly write it: RFC 3339, RFC 2822, or a date with an optional
+/// time of day, taken as UTC
+pub fn parse_date(text: &str) -> Option<chrono::DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(text).or_else(|_| chrono::DateTime::parse_from_rfc2822(text)) {
+        return Some(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = chrono::NaiveDateTime::parse_from_str(text, format) {
+            return Some(time.and_utc());
+        }
+    }
+    ["%Y-%m-%d", "%Y/%m/%d"].iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(text, format).ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,12 +126,77 @@ pub enum ChunkType {
     Code,
     Markdown,
     Pdf,
+    Csv,
+    Json,
+    Xml,
+    AsciiDoc,
+    Org,
+    Presentation,
+    Email,
+    Log,
+    Image,
+    Subtitle,
+    Config,
+    Memory,
+    Schema,
+}
+
+/// Metadata that can be embedded along with a chunk's content (`embedding.metadata_fields`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddedField {
+    Tags,
+    Section,
+    Dependencies,
+}
+
+impl EmbeddedField {
+    /// The name used in the config and in chunk attributes
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmbeddedField::Tags => "tags",
+            EmbeddedField::Section => "section",
+            EmbeddedField::Dependencies => "dependencies",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [EmbeddedField::Tags, EmbeddedField::Section, EmbeddedField::Dependencies].into_iter().find(|field| field.name() == name)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            EmbeddedField::Tags => "Tags",
+            EmbeddedField::Section => "Section",
+            EmbeddedField::Dependencies => "Dependencies",
+        }
+    }
 }
 
+/// Strategy that produced a chunk's boundaries, recorded so chunking is explainable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkStrategy {
+    SentenceWindow,   // Sentences packed up to max_chunk_size with overlap
+    NaturalSection,   // Whole section fit within max_chunk_size and was kept intact
+    ParagraphPacked,  // Paragraphs packed up to max_chunk_size without splitting
+    CodeStructure,    // Split on function/class boundaries
+    RowGroup,         // Table rows packed up to max_chunk_size under a repeated header
+    StructuredRecord, // One record/element of a structured document (JSON, XML, ...)
+    TimeWindow,       // Timestamped lines grouped into a fixed time window
+    ErrorBlock,       // An error line with its stack trace / continuation lines
+    SlidingWindow,    // Fixed token windows at a fixed stride, ignoring sentences and structure
+    FencedCode,       // A fenced code block from a document, kept whole
+    WholeTable,       // A table from a document, kept whole
+    DocComment,       // Doc comments pulled out of a code chunk as prose
+}
+
+#[derive(Clone)]
 pub struct SemanticChunker {
     max_chunk_size: usize,
     min_chunk_size: usize,
     overlap_tokens: usize,
+    adaptive_sizing: bool,
+    language_overrides: HashMap<String, LanguageRules>,  // Language id -> chunk_code rules replacing the built-in ones
 }
 
 impl SemanticChunker {
@@ -50,95 +205,287 @@ impl SemanticChunker {
             max_chunk_size,
             min_chunk_size,
             overlap_tokens,
+            adaptive_sizing: false,
+            language_overrides: HashMap::new(),
         }
     }
 
+    /// Pick chunk boundaries per document from its structure instead of always
+    /// filling chunks up to max_chunk_size
+    pub fn with_adaptive_sizing(mut self, adaptive: bool) -> Self {
+        self.adaptive_sizing = adaptive;
+        self
+    }
+
+    /// Per-language code chunking rules, keyed by `CodeProcessor::detect_language` ids
+    pub fn with_language_overrides(mut self, overrides: HashMap<String, LanguageRules>) -> Self {
+        self.language_overrides = overrides;
+        self
+    }
+
+    /// The same chunker with other size bounds, e.g. for one document type
+    pub fn with_size_limits(mut self, max_chunk_size: usize, min_chunk_size: usize) -> Self {
+        self.max_chunk_size = max_chunk_size;
+        self.min_chunk_size = min_chunk_size;
+        self
+    }
+
+    pub fn max_chunk_size(&self) -> usize {
+        self.max_chunk_size
+    }
+
+    /// Chunks of prose. Boundaries are character positions in `text`; line numbers (1-based,
+    /// inclusive, ignoring surrounding whitespace) and byte offsets are set from them.
     pub fn chunk_text(&self, text: &str, source_file: &str) -> Result<Vec<Chunk>> {
+        let mut chunks = if self.adaptive_sizing {
+            self.chunk_text_adaptive(text, source_file)?
+        } else {
+            self.chunk_text_fixed(text, source_file)?
+        };
+        Self::locate_lines(text, &mut chunks);
+        Ok(chunks)
+    }
+
+    /// Set line numbers and byte offsets of chunks whose boundaries are character positions
+    fn locate_lines(text: &str, chunks: &mut [Chunk]) {
+        // Character positions of interest -> (byte offset, line), found in one pass over `text`
+        let mut positions: Vec<usize> = Vec::with_capacity(chunks.len() * 4);
+        for chunk in chunks.iter() {
+            let (start, end) = Self::content_span(chunk);
+            positions.extend([chunk.boundaries.0, chunk.boundaries.1, start, end.saturating_sub(1).max(start)]);
+        }
+        positions.sort_unstable();
+        positions.dedup();
+
+        let mut located = std::collections::HashMap::with_capacity(positions.len());
+        let mut wanted = positions.iter().peekable();
+        let mut line = 1;
+        for (char_pos, (byte, ch)) in text.char_indices().enumerate() {
+            if wanted.next_if(|&&pos| pos == char_pos).is_some() {
+                located.insert(char_pos, (byte, line));
+            }
+            if wanted.peek().is_none() {
+                break;
+            }
+            if ch == '\n' {
+                line += 1;
+            }
+        }
+        for pos in wanted {
+            located.insert(*pos, (text.len(), line));
+        }
+
+        for chunk in chunks.iter_mut() {
+            let (start, end) = Self::content_span(chunk);
+            chunk.metadata.byte_start = Some(located[&chunk.boundaries.0].0);
+            chunk.metadata.byte_end = Some(located[&chunk.boundaries.1].0);
+            chunk.metadata.line_start = located[&start].1;
+            chunk.metadata.line_end = located[&end.saturating_sub(1).max(start)].1;
+        }
+    }
+
+    /// Character positions of a chunk's content without leading and trailing whitespace
+    fn content_span(chunk: &Chunk) -> (usize, usize) {
+        let leading = chunk.content.chars().take_while(|c| c.is_whitespace()).count();
+        let trailing = chunk.content.chars().rev().take_while(|c| c.is_whitespace()).count();
+        let start = chunk.boundaries.0 + leading;
+        (start, chunk.boundaries.1.saturating_sub(trailing).max(start))
+    }
+
+    fn chunk_text_fixed(&self, text: &str, source_file: &str) -> Result<Vec<Chunk>> {
         // Calculate file hash for metadata
         let file_hash = Self::calculate_file_hash(text);
         let mut chunks = Vec::new();
         let sentences = self.split_sentences(text);
 
         let mut current_chunk = String::new();
+        let mut sentence_starts = Vec::new();  // Byte offsets in current_chunk
         let mut start_pos = 0;
         let mut current_pos = 0;
 
         for sentence in sentences {
             if current_chunk.len() + sentence.len() > self.max_chunk_size && !current_chunk.is_empty() {
                 if current_chunk.len() >= self.min_chunk_size {
-                    let chunk = Chunk {
-                        id: Uuid::new_v4().to_string(),
-                        content: current_chunk.clone(),
-                        embedding: vec![], // Will be filled by embedder
-                        metadata: ChunkMetadata {
-                            source_file: source_file.to_string(),
-                            chunk_type: ChunkType::Text,
-                            chapter: None,
-                            section: None,
-                            language: None,
-                            file_hash: Some(file_hash.clone()),
-                            timestamp: Utc::now(),
-                            line_start: start_pos,
-                            line_end: current_pos,
-                            tags: Self::extract_tags(&current_chunk),
-                            dependencies: vec![],
-                            chunk_size: current_chunk.len(),
-                            parent_chunk_id: None,
-                        },
-                        boundaries: (start_pos, current_pos),
-                    };
-                    chunks.push(chunk);
+                    chunks.push(Self::build_text_chunk(
+                        &current_chunk,
+                        source_file,
+                        &file_hash,
+                        (start_pos, current_pos),
+                        ChunkStrategy::SentenceWindow,
+                    ));
                 }
 
-                // Start new chunk with overlap (Unicode-safe)
-                let overlap_chars = self.overlap_tokens;
-                let chars: Vec<char> = current_chunk.chars().collect();
-                let overlap_start_chars = chars.len().saturating_sub(overlap_chars);
-
-                // Use character-based slicing instead of byte-based
-                current_chunk = chars[overlap_start_chars..].iter().collect::<String>();
-
-                // Calculate position based on character boundaries
-                let chars_before_overlap = chars.len() - current_chunk.chars().count();
-                start_pos = current_pos - (chars.len() - chars_before_overlap);
+                // Start new chunk with overlap, beginning at a sentence or, failing that, a word
+                let overlap_start = Self::overlap_start(&current_chunk, self.overlap_tokens, &sentence_starts)
+                    .or_else(|| Self::word_overlap_start(&current_chunk, self.overlap_tokens))
+                    .unwrap_or(current_chunk.len());
+                current_chunk.drain(..overlap_start);
+                sentence_starts = sentence_starts.iter()
+                    .filter(|&&start| start >= overlap_start)
+                    .map(|start| start - overlap_start)
+                    .collect();
+                start_pos = current_pos - current_chunk.chars().count();
             }
 
+            sentence_starts.push(current_chunk.len());
             current_chunk.push_str(&sentence);
             current_pos += sentence.chars().count(); // Use character count instead of byte length
         }
 
         // Add final chunk
         if !current_chunk.is_empty() && current_chunk.len() >= self.min_chunk_size {
-            let chunk = Chunk {
-                id: Uuid::new_v4().to_string(),
-                content: current_chunk.clone(),
-                embedding: vec![],
-                metadata: ChunkMetadata {
-                    source_file: source_file.to_string(),
-                    chunk_type: ChunkType::Text,
-                    chapter: None,
-                    section: None,
-                    language: None,
-                    file_hash: Some(file_hash),
-                    timestamp: Utc::now(),
-                    line_start: start_pos,
-                    line_end: current_pos,
-                    tags: Self::extract_tags(&current_chunk),
-                    dependencies: vec![],
-                    chunk_size: current_chunk.len(),
-                    parent_chunk_id: None,
-                },
-                boundaries: (start_pos, current_pos),
-            };
-            chunks.push(chunk);
+            chunks.push(Self::build_text_chunk(
+                &current_chunk,
+                source_file,
+                &file_hash,
+                (start_pos, current_pos),
+                ChunkStrategy::SentenceWindow,
+            ));
         }
 
         Ok(chunks)
     }
 
+    fn chunk_text_adaptive(&self, text: &str, source_file: &str) -> Result<Vec<Chunk>> {
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let file_hash = Self::calculate_file_hash(text);
+
+        // Short sections keep their natural boundaries, even below min_chunk_size
+        if text.len() <= self.max_chunk_size {
+            return Ok(vec![Self::build_text_chunk(
+                text,
+                source_file,
+                &file_hash,
+                (0, text.chars().count()),
+                ChunkStrategy::NaturalSection,
+            )]);
+        }
+
+        let paragraphs = Self::split_paragraphs(text);
+
+        // Dense prose: most of the text sits in paragraphs too long to keep whole,
+        // so fall back to sentence windows at the configured max size
+        let oversized: usize = paragraphs.iter()
+            .filter(|(_, p)| p.len() > self.max_chunk_size)
+            .map(|(_, p)| p.len())
+            .sum();
+        if oversized * 2 > text.len() {
+            return self.chunk_text_fixed(text, source_file);
+        }
+
+        let mut chunks = Vec::new();
+        let mut current_chunk = String::new();
+        let mut start_pos = 0;
+        let mut end_pos = 0;
+
+        for (offset, paragraph) in paragraphs {
+            let needs_flush = !current_chunk.is_empty()
+                && current_chunk.len() + paragraph.len() + 2 > self.max_chunk_size;
+            if needs_flush {
+                chunks.push(Self::build_text_chunk(
+                    &current_chunk,
+                    source_file,
+                    &file_hash,
+                    (start_pos, end_pos),
+                    ChunkStrategy::ParagraphPacked,
+                ));
+                current_chunk.clear();
+            }
+
+            if paragraph.len() > self.max_chunk_size {
+                // A single long paragraph still gets sentence windows
+                for mut chunk in self.chunk_text_fixed(paragraph, source_file)? {
+                    chunk.boundaries = (chunk.boundaries.0 + offset, chunk.boundaries.1 + offset);
+                    chunk.metadata.file_hash = Some(file_hash.clone());
+                    chunks.push(chunk);
+                }
+                continue;
+            }
+
+            if current_chunk.is_empty() {
+                start_pos = offset;
+            } else {
+                current_chunk.push_str("\n\n");
+            }
+            current_chunk.push_str(paragraph);
+            end_pos = offset + paragraph.chars().count();
+        }
+
+        if !current_chunk.is_empty() {
+            chunks.push(Self::build_text_chunk(
+                &current_chunk,
+                source_file,
+                &file_hash,
+                (start_pos, end_pos),
+                ChunkStrategy::ParagraphPacked,
+            ));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Split text on blank lines, returning each paragraph with its starting character offset
+    fn split_paragraphs(text: &str) -> Vec<(usize, &str)> {
+        let mut paragraphs = Vec::new();
+        let mut offset = 0;
+
+        for part in text.split("\n\n") {
+            if !part.trim().is_empty() {
+                paragraphs.push((offset, part));
+            }
+            offset += part.chars().count() + 2;
+        }
+
+        paragraphs
+    }
+
+    pub(crate) fn build_text_chunk(
+        content: &str,
+        source_file: &str,
+        file_hash: &str,
+        boundaries: (usize, usize),
+        strategy: ChunkStrategy,
+    ) -> Chunk {
+        Chunk {
+            id: Self::chunk_id(source_file, file_hash, boundaries, content),
+            content: content.to_string(),
+            embedding: vec![], // Will be filled by embedder
+            metadata: ChunkMetadata {
+                source_file: source_file.to_string(),
+                chunk_type: ChunkType::Text,
+                chapter: None,
+                section: None,
+                language: None,
+                file_hash: Some(file_hash.to_string()),
+                timestamp: Utc::now(),
+                line_start: boundaries.0,
+                line_end: boundaries.1,
+                byte_start: None,
+                byte_end: None,
+                tags: Self::extract_tags(content),
+                dependencies: vec![],
+                chunk_size: content.len(),
+                parent_chunk_id: None,
+                chunk_strategy: Some(strategy),
+                attributes: HashMap::new(),
+                page_start: None,
+                page_end: None,
+                title: None,
+                author: None,
+                date: None,
+            },
+            boundaries,
+        }
+    }
+
     pub fn chunk_code(&self, code: &str, language: &str, source_file: &str) -> Result<Vec<Chunk>> {
         // Calculate file hash for metadata
         let file_hash = Self::calculate_file_hash(code);
+        let rules = self.language_rules(language);
+        let max_chunk_size = rules.max_chunk_size.unwrap_or(self.max_chunk_size);
 
         // Enhanced code chunking with better structure awareness
         let lines: Vec<&str> = code.lines().collect();
@@ -147,12 +494,21 @@ impl SemanticChunker {
         let mut start_line = 0;
         let mut brace_depth: i32 = 0;
         let mut in_function = false;
+        let mut in_block_comment = false;
+        // Where the comment lines at the end of the current chunk begin (line, byte offset);
+        // they document whatever comes next, so a split moves them along with it
+        let mut trailing_comments: Option<(usize, usize)> = None;
+        // Length of the overlap the current chunk starts with, already in the previous chunk
+        let mut carried_overlap = 0;
 
         for (i, line) in lines.iter().enumerate() {
             let trimmed = line.trim_start();
+            let starts_in_comment = in_block_comment;
+            let code_part = rules.strip_comments(line, &mut in_block_comment);
+            let is_comment = !trimmed.is_empty() && (starts_in_comment || code_part.trim().is_empty());
 
-            // Track brace depth for better boundaries
-            for ch in line.chars() {
+            // Track brace depth for better boundaries; braces in comments do not count
+            for ch in code_part.chars() {
                 match ch {
                     '{' | '(' | '[' => brace_depth += 1,
                     '}' | ')' | ']' => brace_depth = brace_depth.saturating_sub(1),
@@ -161,129 +517,186 @@ impl SemanticChunker {
             }
 
             // Detect function/class boundaries with language-specific patterns
-            let is_function_start = match language {
-                "rust" => trimmed.starts_with("fn ") || trimmed.starts_with("pub fn ") ||
-                          trimmed.starts_with("impl ") || trimmed.starts_with("pub struct ") ||
-                          trimmed.starts_with("struct ") || trimmed.starts_with("enum ") ||
-                          trimmed.starts_with("pub enum ") || trimmed.starts_with("trait ") ||
-                          trimmed.starts_with("pub trait "),
-                "python" => trimmed.starts_with("def ") || trimmed.starts_with("class ") ||
-                            trimmed.starts_with("async def "),
-                "javascript" | "typescript" => trimmed.starts_with("function ") ||
-                                              trimmed.starts_with("class ") ||
-                                              trimmed.starts_with("const ") && trimmed.contains(" = ") ||
-                                              trimmed.starts_with("export function ") ||
-                                              trimmed.starts_with("export class "),
-                "java" => trimmed.starts_with("public class ") || trimmed.starts_with("class ") ||
-                         trimmed.starts_with("public static ") || trimmed.starts_with("private ") ||
-                         trimmed.starts_with("protected "),
-                "go" => trimmed.starts_with("func ") || trimmed.starts_with("type ") ||
-                       trimmed.starts_with("struct "),
-                _ => trimmed.starts_with("fn ") || trimmed.starts_with("def ") ||
-                    trimmed.starts_with("function ") || trimmed.starts_with("class ")
-            };
+            let is_function_start = !is_comment && rules.is_boundary(trimmed);
 
             // Decide whether to start a new chunk
             let should_split = is_function_start && !current_chunk.is_empty() &&
                               (brace_depth == 0 || (language == "python" && !in_function));
 
             if should_split {
-                // Save current chunk if it meets minimum size
-                if current_chunk.len() >= self.min_chunk_size {
-                    let chunk = Chunk {
-                        id: Uuid::new_v4().to_string(),
-                        content: current_chunk.trim().to_string(),
-                        embedding: vec![],
-                        metadata: ChunkMetadata {
-                            source_file: source_file.to_string(),
-                            chunk_type: ChunkType::Code,
-                            chapter: None,
-                            section: Self::extract_function_name(&current_chunk),
-                            language: Some(language.to_string()),
-                            file_hash: Some(file_hash.clone()),
-                            timestamp: Utc::now(),
-                            line_start: start_line,
-                            line_end: i,
-                            tags: Self::extract_code_tags(&current_chunk, language),
-                            dependencies: Self::extract_dependencies(&current_chunk, language),
-                            chunk_size: current_chunk.len(),
-                            parent_chunk_id: None,
-                        },
-                        boundaries: (start_line, i),
-                    };
-                    chunks.push(chunk);
+                let (next_start, carried) = match trailing_comments.take() {
+                    Some((comment_line, offset)) if offset > 0 => (comment_line, current_chunk.split_off(offset)),
+                    _ => (i, String::new()),
+                };
+                // Save current chunk if it meets minimum size and is more than overlap
+                if current_chunk.len() >= self.min_chunk_size && current_chunk.len() > carried_overlap {
+                    chunks.push(Self::build_code_chunk(
+                        &current_chunk,
+                        language,
+                        source_file,
+                        &file_hash,
+                        (start_line, next_start),
+                    ));
                 }
-                current_chunk.clear();
-                start_line = i;
+                current_chunk = carried;
+                carried_overlap = 0;
+                start_line = next_start;
                 in_function = is_function_start;
             } else if is_function_start {
                 in_function = true;
             }
 
+            if is_comment {
+                trailing_comments.get_or_insert((i, current_chunk.len()));
+            } else if !trimmed.is_empty() {
+                trailing_comments = None;
+            }
+
             current_chunk.push_str(line);
             current_chunk.push('\n');
 
             // Split if chunk gets too large, but try to respect boundaries
-            if current_chunk.len() > self.max_chunk_size && brace_depth == 0 {
+            if current_chunk.len() > max_chunk_size && brace_depth == 0 {
                 if current_chunk.len() >= self.min_chunk_size {
-                    let chunk = Chunk {
-                        id: Uuid::new_v4().to_string(),
-                        content: current_chunk.trim().to_string(),
-                        embedding: vec![],
-                        metadata: ChunkMetadata {
-                            source_file: source_file.to_string(),
-                            chunk_type: ChunkType::Code,
-                            chapter: None,
-                            section: Self::extract_function_name(&current_chunk),
-                            language: Some(language.to_string()),
-                            file_hash: Some(file_hash.clone()),
-                            timestamp: Utc::now(),
-                            line_start: start_line,
-                            line_end: i + 1,
-                            tags: Self::extract_code_tags(&current_chunk, language),
-                            dependencies: Self::extract_dependencies(&current_chunk, language),
-                            chunk_size: current_chunk.len(),
-                            parent_chunk_id: None,
-                        },
-                        boundaries: (start_line, i + 1),
-                    };
-                    chunks.push(chunk);
+                    chunks.push(Self::build_code_chunk(
+                        &current_chunk,
+                        language,
+                        source_file,
+                        &file_hash,
+                        (start_line, i + 1),
+                    ));
                 }
-                current_chunk.clear();
-                start_line = i + 1;
+                // Carry the last whole lines over as overlap
+                let line_starts: Vec<usize> = current_chunk.match_indices('\n').map(|(at, _)| at + 1).collect();
+                let overlap_start = Self::overlap_start(&current_chunk, self.overlap_tokens, &line_starts)
+                    .unwrap_or(current_chunk.len());
+                current_chunk.drain(..overlap_start);
+                carried_overlap = current_chunk.len();
+                start_line = i + 1 - current_chunk.lines().count();
                 in_function = false;
+                trailing_comments = None;
             }
         }
 
         // Add final chunk
-        if !current_chunk.trim().is_empty() && current_chunk.len() >= self.min_chunk_size {
-            let chunk = Chunk {
-                id: Uuid::new_v4().to_string(),
-                content: current_chunk.trim().to_string(),
-                embedding: vec![],
-                metadata: ChunkMetadata {
-                    source_file: source_file.to_string(),
-                    chunk_type: ChunkType::Code,
-                    chapter: None,
-                    section: Self::extract_function_name(&current_chunk),
-                    language: Some(language.to_string()),
-                    file_hash: Some(file_hash),
-                    timestamp: Utc::now(),
-                    line_start: start_line,
-                    line_end: lines.len(),
-                    tags: Self::extract_code_tags(&current_chunk, language),
-                    dependencies: Self::extract_dependencies(&current_chunk, language),
-                    chunk_size: current_chunk.len(),
-                    parent_chunk_id: None,
-                },
-                boundaries: (start_line, lines.len()),
-            };
-            chunks.push(chunk);
+        if current_chunk.len() > carried_overlap && !current_chunk.trim().is_empty() && current_chunk.len() >= self.min_chunk_size {
+            chunks.push(Self::build_code_chunk(
+                &current_chunk,
+                language,
+                source_file,
+                &file_hash,
+                (start_line, lines.len()),
+            ));
         }
 
         Ok(chunks)
     }
 
+    /// One Text chunk per code chunk with doc comments, holding their prose. Each records the
+    /// code chunk it documents in the `documents` attribute, which the graph turns into a
+    /// Reference edge, so conceptual queries can land on the prose and reach the code.
+    pub fn doc_chunks(&self, code_chunks: &[Chunk], language: &str) -> Vec<Chunk> {
+        let rules = self.language_rules(language);
+        code_chunks.iter()
+            .filter_map(|code| {
+                let (first, last, prose) = rules.doc_comments(&code.content)?;
+                if prose.len() < self.min_chunk_size {
+                    return None;
+                }
+                let start = code.boundaries.0 + first;
+                let mut chunk = Self::build_text_chunk(
+                    &prose,
+                    &code.metadata.source_file,
+                    code.metadata.file_hash.as_deref().unwrap_or_default(),
+                    (start, start + last - first + 1),
+                    ChunkStrategy::DocComment,
+                );
+                chunk.metadata.section = code.metadata.section.clone();
+                chunk.metadata.language = Some(language.to_string());
+                chunk.metadata.attributes.insert("documents".to_string(), code.id.clone());
+                Some(chunk)
+            })
+            .collect()
+    }
+
+    /// Where the overlap carried from `text` into the next chunk begins: the boundary (byte
+    /// offset into `text`) nearest to `overlap` characters from the end. Boundaries that would
+    /// carry over the whole text, or more than twice the overlap, are not used.
+    fn overlap_start(text: &str, overlap: usize, boundaries: &[usize]) -> Option<usize> {
+        let cut = Self::overlap_cut(text, overlap)?;
+        let earliest = (2 * cut).saturating_sub(text.len());
+        boundaries.iter()
+            .copied()
+            .filter(|&start| start > 0 && start >= earliest && start < text.len())
+            .min_by_key(|&start| start.abs_diff(cut))
+    }
+
+    /// The first word that starts within the last `overlap` characters of `text`
+    fn word_overlap_start(text: &str, overlap: usize) -> Option<usize> {
+        let cut = Self::overlap_cut(text, overlap)?;
+        if cut > 0 && !text[..cut].ends_with(char::is_whitespace) {
+            let (at, space) = text[cut..].char_indices().find(|(_, c)| c.is_whitespace())?;
+            return Some(cut + at + space.len_utf8()).filter(|&start| start < text.len());
+        }
+        Some(cut)
+    }
+
+    /// Byte offset `overlap` characters before the end of `text`
+    fn overlap_cut(text: &str, overlap: usize) -> Option<usize> {
+        if overlap == 0 {
+            return None;
+        }
+        Some(text.char_indices().rev().nth(overlap - 1).map_or(0, |(at, _)| at))
+    }
+
+    /// Built-in rules for `language` with the configured overrides applied
+    fn language_rules(&self, language: &str) -> LanguageRules {
+        let rules = LanguageRules::builtin(language);
+        match self.language_overrides.get(language) {
+            Some(overrides) => rules.overridden_by(overrides),
+            None => rules,
+        }
+    }
+
+    pub(crate) fn build_code_chunk(
+        raw_content: &str,
+        language: &str,
+        source_file: &str,
+        file_hash: &str,
+        boundaries: (usize, usize),
+    ) -> Chunk {
+        Chunk {
+            id: Self::chunk_id(source_file, file_hash, boundaries, raw_content),
+            content: raw_content.trim().to_string(),
+            embedding: vec![],
+            metadata: ChunkMetadata {
+                source_file: source_file.to_string(),
+                chunk_type: ChunkType::Code,
+                chapter: None,
+                section: Self::extract_function_name(raw_content),
+                language: Some(language.to_string()),
+                file_hash: Some(file_hash.to_string()),
+                timestamp: Utc::now(),
+                line_start: boundaries.0,
+                line_end: boundaries.1,
+                byte_start: None,
+                byte_end: None,
+                tags: Self::extract_code_tags(raw_content, language),
+                dependencies: Self::extract_dependencies(raw_content, language),
+                chunk_size: raw_content.len(),
+                parent_chunk_id: None,
+                chunk_strategy: Some(ChunkStrategy::CodeStructure),
+                attributes: HashMap::new(),
+                page_start: None,
+                page_end: None,
+                title: None,
+                author: None,
+                date: None,
+            },
+            boundaries,
+        }
+    }
+
     fn extract_function_name(code: &str) -> Option<String> {
         // Extract the function/class name from the code chunk (Unicode-safe)
         for line in code.lines() {
@@ -384,7 +797,22 @@ impl SemanticChunker {
             .collect()
     }
 
-    fn calculate_file_hash(content: &str) -> String {
+    /// Id derived from where a chunk comes from and what it holds, so chunking unchanged
+    /// content again yields the same ids, and graph edges and feedback that name them stay
+    /// valid. The source file is part of it, so copies of a file do not collide.
+    pub(crate) fn chunk_id(source_file: &str, file_hash: &str, boundaries: (usize, usize), content: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [source_file, file_hash, &format!("{}:{}", boundaries.0, boundaries.1), &Self::calculate_file_hash(content)] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        let digest = hasher.finalize();
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string()
+    }
+
+    pub(crate) fn calculate_file_hash(content: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(content.as_bytes());
         format!("{:x}", hasher.finalize())
@@ -424,7 +852,7 @@ impl SemanticChunker {
         tags
     }
 
-    fn extract_dependencies(code: &str, language: &str) -> Vec<String> {
+    pub(crate) fn extract_dependencies(code: &str, language: &str) -> Vec<String> {
         let mut deps = Vec::new();
 
         for line in code.lines() {
@@ -452,6 +880,26 @@ impl SemanticChunker {
                         deps.push(trimmed.to_string());
                     }
                 },
+                "systemverilog" | "verilog" => {
+                    // `include "file.svh" and import pkg::*, other_pkg::item;
+                    if let Some(file) = trimmed.strip_prefix("`include") {
+                        deps.push(file.trim().trim_matches(['"', '<', '>']).to_string());
+                    } else if let Some(imports) = trimmed.strip_prefix("import ").filter(|i| !i.trim_start().starts_with('"')) {
+                        for import in imports.trim_end_matches(';').split(',') {
+                            if let Some((package, _)) = import.split_once("::") {
+                                deps.push(package.trim().to_string());
+                            }
+                        }
+                    }
+                },
+                "vhdl" => {
+                    // use ieee.std_logic_1164.all;
+                    let lower = trimmed.to_lowercase();
+                    if let Some(used) = lower.strip_prefix("use ") {
+                        let used = used.trim_end_matches(';').trim();
+                        deps.push(used.strip_suffix(".all").unwrap_or(used).to_string());
+                    }
+                },
                 _ => {}
             }
         }
@@ -459,4 +907,103 @@ impl SemanticChunker {
         deps.dedup();
         deps
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_overlap_snaps_to_boundaries() {
+        let text = "The DMA engine moves data between buffers. It must be reset before use. \
+            Release reset only after the PLL reports lock. Then program the descriptor ring. \
+            Transfers start when the enable bit is set. Completion raises an interrupt.";
+        let chunker = SemanticChunker::new(100, 1, 30);
+        let chunks = chunker.chunk_text(text, "dma.md").unwrap();
+        assert!(chunks.len() > 2);
+        let sentences = chunker.split_sentences(text);
+        for chunk in &chunks[1..] {
+            assert!(sentences.iter().any(|s| chunk.content.starts_with(s.as_str())), "{:?}", chunk.content);
+            assert_eq!(&text.chars().skip(chunk.boundaries.0).collect::<String>()[..20], &chunk.content[..20]);
+        }
+
+        // One long sentence falls back to a word boundary
+        let long = "word ".repeat(30);
+        assert_eq!(SemanticChunker::word_overlap_start(&long, 12), Some(long.len() - 10));
+        assert_eq!(SemanticChunker::overlap_start("ab.cd.", 2, &[0]), None);
+
+        // Size-based code splits carry whole lines
+        let code = (0..40).map(|i| format!("    total += value_{}\n", i)).collect::<String>();
+        let chunks = chunker.chunk_code(&format!("def sum():\n{}", code), "python", "sum.py").unwrap();
+        assert!(chunks.len() > 2);
+        for pair in chunks.windows(2) {
+            let last_line = pair[0].content.lines().last().unwrap().trim();
+            assert_eq!(pair[1].content.lines().next().unwrap().trim(), last_line);
+            assert_eq!(pair[1].boundaries.0 + 1, pair[0].boundaries.1);
+        }
+    }
+
+    #[test]
+    fn test_chunk_ids_are_stable() {
+        let text = "The DMA engine moves data between buffers. It must be reset before use. \
+            Release reset only after the PLL reports lock. Then program the descriptor ring.";
+        let chunker = SemanticChunker::new(60, 1, 0);
+        let ids = |text: &str, file: &str| chunker.chunk_text(text, file).unwrap().into_iter().map(|c| c.id).collect::<Vec<_>>();
+
+        let first = ids(text, "dma.md");
+        assert_eq!(first, ids(text, "dma.md"));
+        assert_eq!(first.iter().collect::<std::collections::HashSet<_>>().len(), first.len());
+        assert!(Uuid::parse_str(&first[0]).is_ok());
+        assert!(ids(text, "copy/dma.md").iter().all(|id| !first.contains(id)));
+        assert_ne!(ids(&text.replace("PLL", "clock"), "dma.md")[0], first[0]);
+    }
+
+    #[test]
+    fn test_context_header() {
+        let mut chunk = SemanticChunker::build_text_chunk("Divisor for 115200 baud.", "docs/uart.md", "h", (0, 1), ChunkStrategy::NaturalSection);
+        assert_eq!(chunk.embedding_text(false, &[]), "Divisor for 115200 baud.");
+        assert_eq!(chunk.embedding_text(true, &[]), "File: uart.md\n\nDivisor for 115200 baud.");
+
+        chunk.metadata.chapter = Some("Registers".to_string());
+        chunk.metadata.section = Some("BAUD".to_string());
+        assert_eq!(chunk.context_header(), "File: uart.md > Chapter: Registers > Section: BAUD");
+        chunk.metadata.section = Some("Registers".to_string());
+        assert_eq!(chunk.context_header(), "File: uart.md > Chapter: Registers");
+    }
+
+    #[test]
+    fn test_embedding_text_appends_metadata_fields() {
+        let mut chunk = SemanticChunker::build_text_chunk("Divisor for 115200 baud.", "docs/uart.md", "h", (0, 1), ChunkStrategy::NaturalSection);
+        chunk.metadata.tags = vec!["serial".to_string(), "clocking".to_string()];
+        chunk.metadata.section = Some("BAUD".to_string());
+        let fields = [EmbeddedField::Tags, EmbeddedField::Section, EmbeddedField::Dependencies];
+        assert_eq!(chunk.embedding_text(false, &fields), "Divisor for 115200 baud.\nTags: serial, clocking\nSection: BAUD");
+        // The section is not repeated below a header naming it
+        assert_eq!(chunk.embedding_text(true, &fields[..2]), "File: uart.md > Section: BAUD\n\nDivisor for 115200 baud.\nTags: serial, clocking");
+        assert_eq!(EmbeddedField::from_name("dependencies"), Some(EmbeddedField::Dependencies));
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let text = "The DMA engine moves data between buffers. It must be reset before use. \
+            Release reset only after the PLL reports lock. Then program the descriptor ring.";
+        let chunker = SemanticChunker::new(60, 1, 0);
+        assert!(chunker.chunk_text(text, "dma.md").unwrap().len() > 1);
+        let wide = chunker.clone().with_size_limits(1000, 1);
+        assert_eq!(wide.chunk_text(text, "dma.md").unwrap().len(), 1);
+        assert!(chunker.clone().with_size_limits(1000, 500).chunk_text(text, "dma.md").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_text_chunks_carry_line_numbers() {
+        let text = "Überblick über den DMA.\nReset first. Then wait.\n\nProgram the ring last. Enable transfers.\n";
+        let chunker = SemanticChunker::new(40, 1, 0);
+        let chunks = chunker.chunk_text(text, "dma.txt").unwrap();
+        let lines: Vec<_> = chunks.iter().map(|c| (c.metadata.line_start, c.metadata.line_end)).collect();
+        assert_eq!(lines, vec![(1, 2), (2, 4), (4, 4)]);
+        for chunk in &chunks {
+            assert_eq!(&text[chunk.metadata.byte_start.unwrap()..chunk.metadata.byte_end.unwrap()], chunk.content);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use super::{Chunk, ChunkStrategy};
+use crate::config::SplitterConfig;
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+/// Splits a document into segments at lines matching a configured pattern, before the
+/// segments are chunked as usual, so no chunk spans two segments
+pub struct Splitter {
+    pattern: Regex,
+    doc_types: Vec<String>,
+    extensions: Vec<String>,
+    keep_delimiter: bool,
+}
+
+/// Part of a document between two delimiter lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub first_line: usize,       // Lines of the document before the segment
+    pub char_offset: usize,      // Characters of the document before the segment
+    pub byte_offset: usize,
+    pub marker: Option<String>,  // The delimiter line that opened the segment
+}
+
+impl Splitter {
+    pub fn from_config(config: &SplitterConfig) -> Result<Self> {
+        let pattern = Regex::new(&config.pattern)
+            .map_err(|e| anyhow!("Invalid chunking.splitters pattern {:?}: {}", config.pattern, e))?;
+        Ok(Self {
+            pattern,
+            doc_types: config.doc_types.clone(),
+            extensions: config.extensions.iter().map(|ext| ext.trim_start_matches('.').to_lowercase()).collect(),
+            keep_delimiter: config.keep_delimiter,
+        })
+    }
+
+    /// Whether the splitter is configured for a file of this detected type
+    pub fn applies_to(&self, path: &str, doc_type: &str) -> bool {
+        let extension = std::path::Path::new(path).extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        (self.doc_types.is_empty() || self.doc_types.iter().any(|t| t == doc_type))
+            && (self.extensions.is_empty() || self.extensions.contains(&extension))
+    }
+
+    /// `text` cut before every line the pattern matches; blank segments are left out
+    pub fn segments(&self, text: &str) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut current = Segment { text: String::new(), first_line: 0, char_offset: 0, byte_offset: 0, marker: None };
+        let mut char_offset = 0;
+        let mut byte_offset = 0;
+
+        for (i, line) in text.split_inclusive('\n').enumerate() {
+            let content = line.trim_end_matches(['\n', '\r']);
+            if self.pattern.is_match(content) {
+                let next = Segment {
+                    text: String::new(),
+                    first_line: if self.keep_delimiter { i } else { i + 1 },
+                    char_offset: if self.keep_delimiter { char_offset } else { char_offset + line.chars().count() },
+                    byte_offset: if self.keep_delimiter { byte_offset } else { byte_offset + line.len() },
+                    marker: Some(content.trim().to_string()).filter(|marker| !marker.is_empty()),
+                };
+                segments.push(std::mem::replace(&mut current, next));
+                if self.keep_delimiter {
+                    current.text.push_str(line);
+                }
+            } else {
+                current.text.push_str(line);
+            }
+            char_offset += line.chars().count();
+            byte_offset += line.len();
+        }
+        segments.push(current);
+        segments.retain(|segment| !segment.text.trim().is_empty());
+        segments
+    }
+}
+
+impl Segment {
+    /// Move positions of chunks made from this segment to positions in the whole document,
+    /// and record which segment they came from
+    pub fn place(&self, chunks: &mut [Chunk], index: usize) {
+        for chunk in chunks {
+            // Sentence and paragraph packing count characters; the other strategies count lines
+            let offset = match chunk.metadata.chunk_strategy {
+                Some(ChunkStrategy::SentenceWindow | ChunkStrategy::ParagraphPacked | ChunkStrategy::NaturalSection) => self.char_offset,
+                _ => self.first_line,
+            };
+            chunk.boundaries = (chunk.boundaries.0 + offset, chunk.boundaries.1 + offset);
+            chunk.metadata.line_start += self.first_line;
+            chunk.metadata.line_end += self.first_line;
+            chunk.metadata.byte_start = chunk.metadata.byte_start.map(|byte| byte + self.byte_offset);
+            chunk.metadata.byte_end = chunk.metadata.byte_end.map(|byte| byte + self.byte_offset);
+
+            chunk.metadata.attributes.insert("segment".to_string(), index.to_string());
+            if chunk.metadata.section.is_none() {
+                chunk.metadata.section = self.marker.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::SemanticChunker;
+
+    #[test]
+    fn test_split_on_delimiter_lines() {
+        let config = |pattern: &str, keep_delimiter: bool| SplitterConfig {
+            pattern: pattern.to_string(),
+            doc_types: vec!["text".to_string()],
+            extensions: vec![],
+            keep_delimiter,
+        };
+        let text = "preamble\nbegin_test reset\ncheck a\nbegin_test dma\ncheck b\n";
+        let splitter = Splitter::from_config(&config("^begin_test", true)).unwrap();
+        assert!(splitter.applies_to("suite.tst", "text") && !splitter.applies_to("suite.md", "markdown"));
+
+        let segments = splitter.segments(text);
+        assert_eq!(segments.iter().map(|s| (s.text.as_str(), s.first_line, s.char_offset)).collect::<Vec<_>>(), vec![
+            ("preamble\n", 0, 0),
+            ("begin_test reset\ncheck a\n", 1, 9),
+            ("begin_test dma\ncheck b\n", 3, 34),
+        ]);
+        assert_eq!(segments[2].marker.as_deref(), Some("begin_test dma"));
+
+        // Dropped delimiters leave only the content between them
+        let segments = Splitter::from_config(&config("^---$", false)).unwrap().segments("a\n---\nb\n---\n");
+        assert_eq!(segments.iter().map(|s| (s.text.as_str(), s.first_line)).collect::<Vec<_>>(), vec![("a\n", 0), ("b\n", 2)]);
+        assert!(Splitter::from_config(&config("(", true)).is_err());
+
+        let chunker = SemanticChunker::new(512, 1, 0);
+        let splitter = Splitter::from_config(&config("^begin_test", true)).unwrap();
+        let segment = &splitter.segments(text)[2];
+        let mut chunks = chunker.chunk_text(&segment.text, "suite.tst").unwrap();
+        segment.place(&mut chunks, 2);
+        assert_eq!(chunks[0].boundaries.0, 34);
+        assert_eq!((chunks[0].metadata.line_start, chunks[0].metadata.line_end), (4, 5));
+        assert_eq!(&text[chunks[0].metadata.byte_start.unwrap()..chunks[0].metadata.byte_end.unwrap()], segment.text);
+        assert_eq!(chunks[0].metadata.section.as_deref(), Some("begin_test dma"));
+        assert_eq!(chunks[0].metadata.attributes.get("segment").map(String::as_str), Some("2"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,182 @@
+use super::{Chunk, ChunkType, SemanticChunker};
+use super::{pdf::PdfProcessor, markdown::MarkdownProcessor, text::TextProcessor, code::CodeProcessor, csv::CsvProcessor, json::JsonProcessor, xml::XmlProcessor, asciidoc::AsciiDocProcessor, org::OrgProcessor, pptx::PptxProcessor, email::EmailProcessor, log::LogProcessor, image::ImageProcessor, subtitle::SubtitleProcessor, html::HtmlProcessor, config_file::{ConfigFileProcessor, ConfigFormat}, idl::{IdlFormat, IdlProcessor}, window::WindowProcessor};
+use crate::config::ChunkingConfig;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Strategy that doc types without an assignment fall back to
+pub const FALLBACK_STRATEGY: &str = "text";
+
+/// Doc types whose text is extracted from the file itself, so `content` is not their text
+pub const FILE_DOC_TYPES: &[&str] = &["pdf", "pptx", "email", "image"];
+
+/// A document handed to a strategy
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkInput<'a> {
+    pub path: &'a str,
+    pub doc_type: &'a str,  // Detected, or given by the caller
+    pub content: &'a str,   // Decoded text; not used by the FILE_DOC_TYPES strategies
+}
+
+/// Turns one document into chunks. `chunker` carries the size limits for the document's type.
+/// Closures of the same shape are strategies too.
+pub trait ChunkingStrategy: Send + Sync {
+    fn chunk(&self, input: &ChunkInput<'_>, chunker: &SemanticChunker) -> Result<Vec<Chunk>>;
+}
+
+impl<F> ChunkingStrategy for F
+where
+    F: Fn(&ChunkInput<'_>, &SemanticChunker) -> Result<Vec<Chunk>> + Send + Sync,
+{
+    fn chunk(&self, input: &ChunkInput<'_>, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        self(input, chunker)
+    }
+}
+
+/// Named chunking strategies and the doc type each one handles. The built-in strategies are
+/// named after the doc type they were written for, plus `sliding_window`; `chunking.strategies`
+/// reassigns doc types, including to strategies registered with `register`.
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    strategies: HashMap<String, Arc<dyn ChunkingStrategy>>,
+    assignments: HashMap<String, String>,  // Doc type -> strategy name
+}
+
+impl StrategyRegistry {
+    pub fn from_config(config: &ChunkingConfig) -> Self {
+        let mut registry = Self { strategies: HashMap::new(), assignments: HashMap::new() };
+
+        registry.register_fn("pdf", |input, chunker| PdfProcessor::extract_and_chunk(input.path, chunker));
+        registry.register_fn("pptx", |input, chunker| PptxProcessor::extract_and_chunk(input.path, chunker));
+        registry.register_fn("email", |input, chunker| EmailProcessor::extract_and_chunk(input.path, chunker));
+        registry.register_fn("image", |input, chunker| ImageProcessor::extract_and_chunk(input.path, chunker));
+        registry.register_fn("markdown", |input, chunker| MarkdownProcessor::extract_and_chunk(input.content, input.path, chunker));
+        registry.register_fn("html", |input, chunker| HtmlProcessor::extract_and_chunk(input.content, input.path, chunker));
+        registry.register_fn("code", |input, chunker| {
+            let language = CodeProcessor::detect_language(input.path).unwrap_or_else(|| "text".to_string());
+            CodeProcessor::extract_and_chunk(input.content, &language, input.path, chunker)
+        });
+        registry.register_fn("csv", |input, chunker| CsvProcessor::extract_and_chunk(input.content, input.path, b',', chunker));
+        registry.register_fn("tsv", |input, chunker| CsvProcessor::extract_and_chunk(input.content, input.path, b'\t', chunker));
+        let json_paths = config.json_paths.clone();
+        registry.register_fn("json", move |input, chunker| JsonProcessor::extract_and_chunk(input.content, input.path, &json_paths, chunker));
+        registry.register_fn("jsonl", |input, chunker| JsonProcessor::extract_and_chunk_lines(input.content, input.path, chunker));
+        let xml_elements = config.xml_elements.clone();
+        registry.register_fn("xml", move |input, chunker| XmlProcessor::extract_and_chunk(input.content, input.path, &xml_elements, chunker));
+        registry.register_fn("asciidoc", |input, chunker| AsciiDocProcessor::extract_and_chunk(input.content, input.path, chunker));
+        registry.register_fn("org", |input, chunker| OrgProcessor::extract_and_chunk(input.content, input.path, chunker));
+        let log_window_secs = config.log_window_secs;
+        registry.register_fn("log", move |input, chunker| LogProcessor::extract_and_chunk(input.content, input.path, log_window_secs, chunker));
+        for name in ["yaml", "toml", "ini"] {
+            registry.register_fn(name, |input, chunker| {
+                let format = ConfigFormat::from_extension(input.doc_type).unwrap_or(ConfigFormat::Ini);
+                ConfigFileProcessor::extract_and_chunk(input.content, input.path, format, chunker)
+            });
+        }
+        for name in ["proto", "thrift", "avdl"] {
+            registry.register_fn(name, |input, chunker| {
+                let format = IdlFormat::from_extension(input.doc_type).unwrap_or(IdlFormat::Protobuf);
+                IdlProcessor::extract_and_chunk(input.content, input.path, format, chunker)
+            });
+        }
+        let subtitle_window_secs = config.subtitle_window_secs;
+        registry.register_fn("subtitle", move |input, chunker| SubtitleProcessor::extract_and_chunk(input.content, input.path, subtitle_window_secs, chunker));
+        registry.register_fn(FALLBACK_STRATEGY, |input, chunker| TextProcessor::extract_and_chunk(input.content, input.path, chunker));
+
+        let window = config.sliding_window.clone();
+        registry.register_fn("sliding_window", move |input, _| {
+            let mut chunks = WindowProcessor::extract_and_chunk(input.content, input.path, window.window_tokens, window.stride_tokens)?;
+            let language = (input.doc_type == "code").then(|| CodeProcessor::detect_language(input.path)).flatten();
+            for chunk in &mut chunks {
+                match input.doc_type {
+                    "code" => chunk.metadata.chunk_type = ChunkType::Code,
+                    "log" => chunk.metadata.chunk_type = ChunkType::Log,
+                    _ => {}
+                }
+                chunk.metadata.language = language.clone();
+            }
+            Ok(chunks)
+        });
+        for doc_type in config.sliding_window.doc_types.iter().filter(|t| !FILE_DOC_TYPES.contains(&t.as_str())) {
+            registry.assign(doc_type, "sliding_window");
+        }
+
+        for (doc_type, name) in &config.strategies {
+            registry.assign(doc_type, name);
+        }
+        registry
+    }
+
+    /// Add or replace the strategy called `name`; doc types named `name` use it unless assigned
+    /// another one
+    pub fn register(&mut self, name: &str, strategy: impl ChunkingStrategy + 'static) {
+        self.strategies.insert(name.to_string(), Arc::new(strategy));
+    }
+
+    pub fn assign(&mut self, doc_type: &str, name: &str) {
+        self.assignments.insert(doc_type.to_string(), name.to_string());
+    }
+
+    /// Helps closure parameters infer their lifetimes
+    fn register_fn<F>(&mut self, name: &str, strategy: F)
+    where
+        F: Fn(&ChunkInput<'_>, &SemanticChunker) -> Result<Vec<Chunk>> + Send + Sync + 'static,
+    {
+        self.register(name, strategy);
+    }
+
+    /// Name of the strategy that chunks `doc_type`
+    pub fn strategy_name<'a>(&'a self, doc_type: &'a str) -> &'a str {
+        match self.assignments.get(doc_type) {
+            Some(name) => name,
+            None if self.strategies.contains_key(doc_type) => doc_type,
+            None => FALLBACK_STRATEGY,
+        }
+    }
+
+    pub fn chunk(&self, input: &ChunkInput<'_>, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let name = self.strategy_name(input.doc_type);
+        let strategy = self.strategies.get(name)
+            .ok_or_else(|| anyhow!("Doc type {} is assigned to unknown chunking strategy {:?}", input.doc_type, name))?;
+        strategy.chunk(input, chunker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkStrategy;
+
+    /// One chunk per `begin_test` block, as a downstream strategy might do
+    struct TestbenchLog;
+
+    impl ChunkingStrategy for TestbenchLog {
+        fn chunk(&self, input: &ChunkInput<'_>, _: &SemanticChunker) -> Result<Vec<Chunk>> {
+            Ok(input.content.split("begin_test").filter(|block| !block.trim().is_empty()).enumerate()
+                .map(|(i, block)| SemanticChunker::build_text_chunk(block.trim(), input.path, "h", (i, i + 1), ChunkStrategy::SentenceWindow))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_registered_strategies() {
+        let config: ChunkingConfig = serde_yaml::from_str(
+            "overlap_tokens: 0\nsemantic_threshold: 0.7\ncode_languages: []\nstrategies: {log: testbench_log, text: missing}\nsliding_window: {doc_types: [code, pdf]}"
+        ).unwrap();
+        let mut registry = StrategyRegistry::from_config(&config);
+        let chunker = SemanticChunker::new(512, 1, 0);
+        let input = |doc_type| ChunkInput { path: "run.log", doc_type, content: "begin_test reset\nok\nbegin_test dma\nfail" };
+
+        assert_eq!(registry.strategy_name("markdown"), "markdown");
+        assert_eq!(registry.strategy_name("code"), "sliding_window");
+        assert_eq!(registry.strategy_name("pdf"), "pdf");
+        assert_eq!(registry.strategy_name("vhdl"), FALLBACK_STRATEGY);
+        assert!(registry.chunk(&input("log"), &chunker).is_err());
+        assert!(registry.chunk(&input("text"), &chunker).unwrap_err().to_string().contains("missing"));
+
+        registry.register("testbench_log", TestbenchLog);
+        let chunks = registry.chunk(&input("log"), &chunker).unwrap();
+        assert_eq!(chunks.iter().map(|c| c.content.as_str()).collect::<Vec<_>>(), vec!["reset\nok", "dma\nfail"]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,218 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Sha256, Digest};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Extensions checked, in order, for the recording a subtitle file belongs to
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov", "m4v", "avi"];
+
+pub struct SubtitleProcessor;
+
+/// One timed caption
+#[derive(Debug, Clone, PartialEq)]
+struct Cue {
+    line: usize,
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+impl SubtitleProcessor {
+    /// Chunk an SRT or WebVTT transcript into windows of `window_secs`, recording each
+    /// window's start/end timestamps and the video the transcript belongs to so results
+    /// can be cited as "talk.mp4 at 00:12:30".
+    pub fn extract_and_chunk(content: &str, file_path: &str, window_secs: u64, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let video = Self::video_file(file_path);
+        let window = window_secs.max(1) as f64;
+        let max_size = chunker.max_chunk_size();
+
+        let mut chunks = Vec::new();
+        let mut block: Vec<Cue> = Vec::new();
+        let mut block_len = 0;
+
+        for cue in Self::parse_cues(content) {
+            // Rolling auto-captions repeat the previous cue's text
+            if block.last().is_some_and(|last| last.text == cue.text) {
+                if let Some(last) = block.last_mut() {
+                    last.end = last.end.max(cue.end);
+                }
+                continue;
+            }
+
+            let window_elapsed = block.first().is_some_and(|first| cue.start - first.start >= window);
+            if !block.is_empty() && (window_elapsed || block_len + cue.text.len() + 1 > max_size) {
+                chunks.push(Self::build_chunk(&block, file_path, &file_hash, &video));
+                block.clear();
+                block_len = 0;
+            }
+
+            block_len += cue.text.len() + 1;
+            block.push(cue);
+        }
+
+        if !block.is_empty() {
+            chunks.push(Self::build_chunk(&block, file_path, &file_hash, &video));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Cues from SRT (`00:01:02,500 --> ...`) or WebVTT (`01:02.500 --> ... align:start`)
+    /// blocks. Numbering, cue identifiers, NOTE/STYLE/REGION blocks and markup are dropped.
+    fn parse_cues(content: &str) -> Vec<Cue> {
+        let mut cues = Vec::new();
+        let mut lines = content.lines().enumerate().peekable();
+
+        while let Some((index, line)) = lines.next() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.starts_with("NOTE") || line.starts_with("STYLE") || line.starts_with("REGION") {
+                // Skip the whole block
+                while lines.next_if(|(_, l)| !l.trim().is_empty()).is_some() {}
+                continue;
+            }
+
+            let Some((start, end)) = Self::parse_timing(line) else {
+                continue;
+            };
+
+            let mut text_lines = Vec::new();
+            while let Some((_, text)) = lines.next_if(|(_, l)| !l.trim().is_empty()) {
+                let cleaned = Self::clean_text(text);
+                if !cleaned.is_empty() {
+                    text_lines.push(cleaned);
+                }
+            }
+
+            if !text_lines.is_empty() {
+                cues.push(Cue { line: index + 1, start, end, text: text_lines.join(" ") });
+            }
+        }
+
+        cues
+    }
+
+    fn parse_timing(line: &str) -> Option<(f64, f64)> {
+        let (start, rest) = line.split_once("-->")?;
+        let end = rest.split_whitespace().next()?;
+        Some((Self::parse_timestamp(start.trim())?, Self::parse_timestamp(end)?))
+    }
+
+    /// `HH:MM:SS,mmm`, `HH:MM:SS.mmm` or `MM:SS.mmm` in seconds
+    fn parse_timestamp(text: &str) -> Option<f64> {
+        let text = text.replace(',', ".");
+        let mut seconds = 0.0;
+        for part in text.split(':') {
+            seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+        }
+        Some(seconds)
+    }
+
+    fn format_timestamp(seconds: f64) -> String {
+        let millis = (seconds * 1000.0).round() as u64;
+        format!("{:02}:{:02}:{:02}.{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+    }
+
+    /// Strip formatting tags and ASS overrides, keeping VTT speaker names as "Name: "
+    fn clean_text(text: &str) -> String {
+        static PATTERNS: OnceLock<(Regex, Regex)> = OnceLock::new();
+        let (voice, markup) = PATTERNS.get_or_init(|| (
+            Regex::new(r"<v(?:\.[\w.-]+)?\s+([^>]+)>").unwrap(),
+            Regex::new(r"</?[^>]*>|\{\\[^}]*\}").unwrap(),
+        ));
+
+        let text = voice.replace_all(text, "$1: ");
+        let text = markup.replace_all(&text, "");
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// The recording next to the transcript (`talk.en.vtt` -> `talk.mp4`), or the transcript's
+    /// base name when no video file is present
+    fn video_file(file_path: &str) -> String {
+        let path = Path::new(file_path);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(file_path);
+
+        let mut bases = vec![stem];
+        if let Some((base, _language)) = stem.rsplit_once('.') {
+            bases.push(base);
+        }
+
+        for base in &bases {
+            for extension in VIDEO_EXTENSIONS {
+                let candidate = path.with_file_name(format!("{}.{}", base, extension));
+                if candidate.exists() {
+                    return candidate.file_name().and_then(|n| n.to_str()).unwrap_or(base).to_string();
+                }
+            }
+        }
+
+        bases.last().copied().unwrap_or(stem).to_string()
+    }
+
+    fn build_chunk(cues: &[Cue], file_path: &str, file_hash: &str, video: &str) -> Chunk {
+        let content = cues.iter().map(|c| c.text.as_str()).collect::<Vec<_>>().join("\n");
+        let first = &cues[0];
+        let last = &cues[cues.len() - 1];
+
+        let mut chunk = SemanticChunker::build_text_chunk(&content, file_path, file_hash, (first.line, last.line), ChunkStrategy::TimeWindow);
+        chunk.metadata.chunk_type = ChunkType::Subtitle;
+
+        let start = Self::format_timestamp(first.start);
+        let end = Self::format_timestamp(cues.iter().map(|c| c.end).fold(last.end, f64::max));
+        chunk.metadata.section = Some(format!("{} {} - {}", video, start, end));
+
+        let attributes = &mut chunk.metadata.attributes;
+        attributes.insert("start_time".to_string(), start);
+        attributes.insert("end_time".to_string(), end);
+        attributes.insert("video_file".to_string(), video.to_string());
+
+        let tag = video.to_lowercase();
+        if !chunk.metadata.tags.contains(&tag) {
+            chunk.metadata.tags.push(tag);
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vtt_cues_grouped_by_window() {
+        let chunker = SemanticChunker::new(2048, 10, 0);
+        let vtt = "\
+WEBVTT
+
+NOTE recorded at the March training
+
+intro
+00:00.000 --> 00:04.500 align:start
+<v Alice>Welcome to the <i>UVM</i> training</v>
+
+00:04.500 --> 00:09.000
+<v Alice>Welcome to the <i>UVM</i> training</v>
+
+00:40.000 --> 00:45.000
+Today we cover the factory
+
+01:05.250 --> 01:10.000
+and the config database
+";
+
+        let chunks = SubtitleProcessor::extract_and_chunk(vtt, "recordings/uvm_intro.en.vtt", 60, &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, "Alice: Welcome to the UVM training\nToday we cover the factory");
+        assert_eq!(chunks[0].metadata.attributes.get("start_time").map(|s| s.as_str()), Some("00:00:00.000"));
+        assert_eq!(chunks[0].metadata.attributes.get("end_time").map(|s| s.as_str()), Some("00:00:45.000"));
+        assert_eq!(chunks[0].metadata.attributes.get("video_file").map(|s| s.as_str()), Some("uvm_intro"));
+        assert_eq!(chunks[1].metadata.attributes.get("start_time").map(|s| s.as_str()), Some("00:01:05.250"));
+
+        let srt = SubtitleProcessor::parse_cues("1\r\n00:00:01,000 --> 00:00:02,500\r\nHello\r\nworld\r\n\r\n");
+        assert_eq!(srt, vec![Cue { line: 2, start: 1.0, end: 2.5, text: "Hello world".to_string() }]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,205 @@
+use super::{Chunk, ChunkType};
+use crate::config::{SummarizerConfig, SummaryConfig};
+use anyhow::{Result, anyhow};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Titles longer than this are cut at a word boundary
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Line prefixes that start a comment in the supported languages
+const COMMENT_PREFIXES: &[&str] = &["//", "#", "/*", "*", "--", "\"\"\"", "'''"];
+
+/// Gives each chunk a short title and summary, recorded in the `chunk_title` and
+/// `chunk_summary` attributes. Titles come from the chunk itself (its first heading, a code
+/// chunk's signature, or its section); summaries come from the configured summarizer, or
+/// otherwise from the chunk's opening sentences or comments.
+pub struct ChunkSummarizer {
+    max_chars: usize,
+    remote: Option<RemoteSummarizer>,
+}
+
+impl ChunkSummarizer {
+    /// None when `chunking.summaries` is disabled
+    pub fn from_config(config: &SummaryConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let remote = config.summarizer.as_ref().map(RemoteSummarizer::from_config).transpose()?;
+        Ok(Some(Self { max_chars: config.max_chars, remote }))
+    }
+
+    pub fn annotate(&self, chunks: &mut [Chunk]) {
+        for chunk in chunks.iter_mut() {
+            let remote = self.remote.as_ref().and_then(|remote| {
+                remote.summarize(&chunk.content, self.max_chars)
+                    .map_err(|e| tracing::warn!("Summarizer failed for a chunk of {}: {}", chunk.metadata.source_file, e))
+                    .ok()
+            });
+            let (remote_title, remote_summary) = remote.unwrap_or_default();
+
+            let title = remote_title.or_else(|| title_of(chunk));
+            let summary = remote_summary.or_else(|| extract_summary(chunk, self.max_chars));
+            let attributes = &mut chunk.metadata.attributes;
+            if let Some(title) = title {
+                attributes.insert("chunk_title".to_string(), truncate(&title, MAX_TITLE_CHARS));
+            }
+            if let Some(summary) = summary {
+                attributes.insert("chunk_summary".to_string(), truncate(&summary, self.max_chars));
+            }
+        }
+    }
+}
+
+/// The first heading, a code chunk's first signature line, or failing those its section
+fn title_of(chunk: &Chunk) -> Option<String> {
+    let lines = || chunk.content.lines().map(str::trim).filter(|line| !line.is_empty());
+    let title = match chunk.metadata.chunk_type {
+        ChunkType::Code => lines()
+            .find(|line| !is_comment(line) && !is_annotation(line))
+            .map(|line| line.trim_end_matches(['{', ':']).trim_end().to_string()),
+        _ => lines()
+            .find_map(|line| line.strip_prefix('#').map(|heading| heading.trim_start_matches('#').trim().to_string()))
+            .filter(|heading| !heading.is_empty()),
+    };
+    title.or_else(|| chunk.metadata.section.clone()).or_else(|| chunk.metadata.chapter.clone())
+}
+
+/// Opening sentences of prose, or the comment text of a code chunk, up to `max_chars`
+fn extract_summary(chunk: &Chunk, max_chars: usize) -> Option<String> {
+    let text: Vec<&str> = match chunk.metadata.chunk_type {
+        ChunkType::Code => chunk.content.lines()
+            .map(str::trim)
+            .filter(|line| is_comment(line))
+            .map(|line| line.trim_start_matches(|c: char| "/#*-\"'!".contains(c)).trim())
+            .collect(),
+        _ => chunk.content.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#') && !line.starts_with("```") && !line.starts_with('|'))
+            .collect(),
+    };
+    let text = text.join(" ").split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+
+    // Whole sentences while they fit; a first sentence that does not fit is cut
+    let mut summary = String::new();
+    for sentence in text.split_inclusive(['.', '!', '?']) {
+        if !summary.is_empty() && summary.chars().count() + sentence.chars().count() > max_chars {
+            break;
+        }
+        summary.push_str(sentence);
+    }
+    Some(summary.trim().to_string())
+}
+
+fn is_comment(line: &str) -> bool {
+    !is_annotation(line) && COMMENT_PREFIXES.iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Rust attributes and Python/Java decorators, which precede a signature
+fn is_annotation(line: &str) -> bool {
+    line.starts_with("#[") || line.starts_with("#![") || line.starts_with('@')
+}
+
+/// `text` cut at a word boundary to at most `max_chars` characters, marked with an ellipsis
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end())
+}
+
+/// An OpenAI-compatible `/chat/completions` endpoint asked for a title and a summary
+struct RemoteSummarizer {
+    endpoint: String,
+    model: Option<String>,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl RemoteSummarizer {
+    fn from_config(config: &SummarizerConfig) -> Result<Self> {
+        let api_key = match &config.api_key_env {
+            Some(variable) => Some(std::env::var(variable)
+                .map_err(|_| anyhow!("Summarizer: environment variable {} is not set", variable))?),
+            None => None,
+        };
+        Ok(Self {
+            endpoint: config.endpoint.clone(),
+            model: config.model.clone(),
+            api_key,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(config.timeout_secs)).build(),
+        })
+    }
+
+    /// (title, summary); either is None when the reply leaves it out
+    fn summarize(&self, content: &str, max_chars: usize) -> Result<(Option<String>, Option<String>)> {
+        let instructions = format!(
+            "Give the passage a title of at most {} characters and summarize it in at most {} characters. \
+             Reply with exactly two lines: \"Title: ...\" and \"Summary: ...\".",
+            MAX_TITLE_CHARS, max_chars
+        );
+        let mut body = json!({
+            "messages": [
+                {"role": "system", "content": instructions},
+                {"role": "user", "content": content}
+            ],
+            "temperature": 0.0
+        });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+
+        let mut request = self.agent.post(&self.endpoint).set("Content-Type", "application/json");
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let response: Value = match request.send_string(&body.to_string()) {
+            Ok(response) => serde_json::from_str(&response.into_string()?)?,
+            Err(ureq::Error::Status(status, _)) => return Err(anyhow!("{} answered HTTP {}", self.endpoint, status)),
+            Err(e) => return Err(anyhow!("Request to {} failed: {}", self.endpoint, e)),
+        };
+        let reply = response["choices"][0]["message"]["content"].as_str()
+            .ok_or_else(|| anyhow!("Invalid summarizer response: missing choices[0].message.content"))?;
+        Ok(parse_reply(reply))
+    }
+}
+
+fn parse_reply(reply: &str) -> (Option<String>, Option<String>) {
+    let field = |name: &str| reply.lines()
+        .find_map(|line| line.trim().strip_prefix(name))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    (field("Title:"), field("Summary:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    #[test]
+    fn test_titles_and_extractive_summaries() {
+        let summarizer = ChunkSummarizer { max_chars: 60, remote: None };
+        let markdown = "## DMA reset\n\nHold the engine in reset until the clocks are stable. Then release it. Finally program the ring.";
+        let code = "/// Program the descriptor ring before enabling transfers\npub fn start(ring: &Ring) -> Result<()> {\n    ring.enable()\n}";
+        let mut chunks = vec![
+            SemanticChunker::build_text_chunk(markdown, "dma.md", "h", (0, 3), ChunkStrategy::NaturalSection),
+            SemanticChunker::build_code_chunk(code, "rust", "dma.rs", "h", (0, 4)),
+        ];
+        summarizer.annotate(&mut chunks);
+
+        let attribute = |chunk: &Chunk, key: &str| chunk.metadata.attributes.get(key).cloned().unwrap_or_default();
+        assert_eq!(attribute(&chunks[0], "chunk_title"), "DMA reset");
+        assert_eq!(attribute(&chunks[0], "chunk_summary"), "Hold the engine in reset until the clocks are stable.");
+        assert_eq!(attribute(&chunks[1], "chunk_title"), "pub fn start(ring: &Ring) -> Result<()>");
+        assert_eq!(attribute(&chunks[1], "chunk_summary"), "Program the descriptor ring before enabling transfers");
+
+        assert_eq!(truncate("one two three four", 10), "one two…");
+        assert_eq!(parse_reply("Title: Reset\nSummary:  Waits for lock. "), (Some("Reset".to_string()), Some("Waits for lock.".to_string())));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use super::{Chunk, ChunkStrategy, SemanticChunker};
+use anyhow::{Result, anyhow};
+use sha2::{Sha256, Digest};
+use unicode_segmentation::UnicodeSegmentation;
+
+pub struct WindowProcessor;
+
+impl WindowProcessor {
+    /// Chunk text into windows of `window_tokens` tokens, starting a new window every
+    /// `stride_tokens` tokens, without looking for sentences or structure. Tokens are Unicode
+    /// words and punctuation marks, so text without spaces between words (CJK) is still split.
+    pub fn extract_and_chunk(content: &str, file_path: &str, window_tokens: usize, stride_tokens: usize) -> Result<Vec<Chunk>> {
+        if window_tokens == 0 || stride_tokens == 0 || stride_tokens > window_tokens {
+            return Err(anyhow!(
+                "Sliding windows need 0 < stride_tokens <= window_tokens, got window {} and stride {}",
+                window_tokens, stride_tokens
+            ));
+        }
+
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let tokens: Vec<(usize, &str)> = content.split_word_bound_indices()
+            .filter(|(_, token)| !token.trim().is_empty())
+            .collect();
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let end = (start + window_tokens).min(tokens.len());
+            let (first_offset, _) = tokens[start];
+            let (last_offset, last) = tokens[end - 1];
+            let text = &content[first_offset..last_offset + last.len()];
+
+            // Boundaries are 1-based line numbers, as for logs
+            let first_line = content[..first_offset].matches('\n').count() + 1;
+            let last_line = first_line + text.matches('\n').count();
+            chunks.push(SemanticChunker::build_text_chunk(text, file_path, &file_hash, (first_line, last_line), ChunkStrategy::SlidingWindow));
+
+            if end == tokens.len() {
+                break;
+            }
+            start += stride_tokens;
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_overlap_by_window_minus_stride() {
+        let content = "one two three\nfour five six\nseven eight";
+        let chunks = WindowProcessor::extract_and_chunk(content, "notes.txt", 4, 3).unwrap();
+        let windows: Vec<(&str, (usize, usize))> = chunks.iter().map(|c| (c.content.as_str(), c.boundaries)).collect();
+        assert_eq!(windows, vec![
+            ("one two three\nfour", (1, 2)),
+            ("four five six\nseven", (2, 3)),
+            ("seven eight", (3, 3)),
+        ]);
+        assert_eq!(chunks[0].metadata.chunk_strategy, Some(ChunkStrategy::SlidingWindow));
+
+        // Ideographs are tokens of their own
+        let chunks = WindowProcessor::extract_and_chunk("直接内存访问", "notes.txt", 4, 2).unwrap();
+        assert_eq!(chunks.iter().map(|c| c.content.as_str()).collect::<Vec<_>>(), vec!["直接内存", "内存访问"]);
+        assert!(WindowProcessor::extract_and_chunk(content, "notes.txt", 2, 3).is_err());
+    }
+}
\ No newline at end of file
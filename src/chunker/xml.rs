@@ -0,0 +1,210 @@
+use super::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use anyhow::{Result, anyhow};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+
+pub struct XmlProcessor;
+
+/// An element currently being collected into a chunk
+struct Capture {
+    depth: usize,
+    xpath: String,
+    element: String,
+    lines: Vec<String>,
+}
+
+impl XmlProcessor {
+    /// Chunk an XML document by the configured element names (matched by qualified or local
+    /// name, e.g. "section", "testcase", "spirit:register"). With no names configured, each
+    /// child of the root element becomes a chunk. Every chunk records its element XPath.
+    pub fn extract_and_chunk(content: &str, file_path: &str, elements: &[String], chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let file_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+
+        let mut reader = Reader::from_str(content);
+        reader.config_mut().trim_text(true);
+
+        let mut path: Vec<String> = Vec::new();     // "name[index]" per open element
+        let mut names: Vec<String> = Vec::new();    // local names of open elements
+        let mut sibling_counts: Vec<HashMap<String, usize>> = vec![HashMap::new()];
+        let mut capture: Option<Capture> = None;
+        let mut chunks = Vec::new();
+
+        loop {
+            let event = reader.read_event()
+                .map_err(|e| anyhow!("Invalid XML in {} at byte {}: {}", file_path, reader.buffer_position(), e))?;
+
+            match event {
+                Event::Start(e) => {
+                    let (qname, local) = Self::names(&e);
+                    Self::enter(&qname, &local, &mut path, &mut names, &mut sibling_counts);
+
+                    if capture.is_none() && Self::is_target(&qname, &local, path.len(), elements) {
+                        capture = Some(Capture {
+                            depth: path.len(),
+                            xpath: format!("/{}", path.join("/")),
+                            element: local,
+                            lines: vec![Self::element_header(&e)],
+                        });
+                    } else if let Some(capture) = capture.as_mut() {
+                        if e.attributes().next().is_some() {
+                            capture.lines.push(Self::element_header(&e));
+                        }
+                    }
+                }
+                Event::Empty(e) => {
+                    let (qname, local) = Self::names(&e);
+                    Self::enter(&qname, &local, &mut path, &mut names, &mut sibling_counts);
+
+                    if let Some(capture) = capture.as_mut() {
+                        capture.lines.push(Self::element_header(&e));
+                    } else if Self::is_target(&qname, &local, path.len(), elements) {
+                        let record = Capture {
+                            depth: path.len(),
+                            xpath: format!("/{}", path.join("/")),
+                            element: local,
+                            lines: vec![Self::element_header(&e)],
+                        };
+                        chunks.extend(Self::build_chunks(record, chunks.len(), file_path, &file_hash, chunker)?);
+                    }
+
+                    Self::leave(&mut path, &mut names, &mut sibling_counts);
+                }
+                Event::End(_) => {
+                    if capture.as_ref().is_some_and(|c| c.depth == path.len()) {
+                        let record = capture.take().unwrap();
+                        chunks.extend(Self::build_chunks(record, chunks.len(), file_path, &file_hash, chunker)?);
+                    }
+                    Self::leave(&mut path, &mut names, &mut sibling_counts);
+                }
+                Event::Text(text) => {
+                    if let Some(capture) = capture.as_mut() {
+                        let text = text.unescape()?;
+                        Self::push_text(capture, names.last(), text.trim());
+                    }
+                }
+                Event::CData(data) => {
+                    if let Some(capture) = capture.as_mut() {
+                        let text = String::from_utf8_lossy(&data.into_inner()).to_string();
+                        Self::push_text(capture, names.last(), text.trim());
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    fn names(element: &BytesStart) -> (String, String) {
+        let qname = String::from_utf8_lossy(element.name().as_ref()).to_string();
+        let local = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+        (qname, local)
+    }
+
+    fn enter(
+        qname: &str,
+        local: &str,
+        path: &mut Vec<String>,
+        names: &mut Vec<String>,
+        sibling_counts: &mut Vec<HashMap<String, usize>>,
+    ) {
+        // XPath positions are 1-based among siblings with the same name
+        let counts = sibling_counts.last_mut().expect("root sibling map always present");
+        let index = counts.entry(qname.to_string()).or_insert(0);
+        *index += 1;
+
+        path.push(format!("{}[{}]", qname, index));
+        names.push(local.to_string());
+        sibling_counts.push(HashMap::new());
+    }
+
+    fn leave(path: &mut Vec<String>, names: &mut Vec<String>, sibling_counts: &mut Vec<HashMap<String, usize>>) {
+        path.pop();
+        names.pop();
+        sibling_counts.pop();
+    }
+
+    fn is_target(qname: &str, local: &str, depth: usize, elements: &[String]) -> bool {
+        if elements.is_empty() {
+            depth == 2  // Direct children of the root element
+        } else {
+            elements.iter().any(|name| name == qname || name == local)
+        }
+    }
+
+    /// Render an element's name and attributes, e.g. `testcase name="reset" time="0.4"`
+    fn element_header(element: &BytesStart) -> String {
+        let mut header = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+        for attr in element.attributes().flatten() {
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_string();
+            let value = attr.unescape_value().map(|v| v.to_string()).unwrap_or_default();
+            header.push_str(&format!(" {}=\"{}\"", key, value));
+        }
+        header
+    }
+
+    fn push_text(capture: &mut Capture, element: Option<&String>, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        match element {
+            Some(name) if *name != capture.element => capture.lines.push(format!("{}: {}", name, text)),
+            _ => capture.lines.push(text.to_string()),
+        }
+    }
+
+    fn build_chunks(record: Capture, position: usize, file_path: &str, file_hash: &str, chunker: &SemanticChunker) -> Result<Vec<Chunk>> {
+        let text = record.lines.join("\n");
+
+        let mut chunks = if text.len() > chunker.max_chunk_size() {
+            chunker.chunk_text(&text, file_path)?
+        } else {
+            vec![SemanticChunker::build_text_chunk(&text, file_path, file_hash, (position, position + 1), ChunkStrategy::StructuredRecord)]
+        };
+
+        for chunk in &mut chunks {
+            chunk.metadata.chunk_type = ChunkType::Xml;
+            chunk.metadata.section = Some(record.xpath.clone());
+            chunk.metadata.attributes.insert("xpath".to_string(), record.xpath.clone());
+            chunk.metadata.attributes.insert("element".to_string(), record.element.clone());
+            if !chunk.metadata.tags.contains(&record.element) {
+                chunk.metadata.tags.push(record.element.clone());
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_junit_testcases_get_xpath() {
+        let chunker = SemanticChunker::new(512, 10, 0);
+        let content = r#"<?xml version="1.0"?>
+<testsuites>
+  <testsuite name="smoke">
+    <testcase name="reset_test" time="0.4"/>
+    <testcase name="dma_test" time="1.2">
+      <failure message="timeout">Scoreboard mismatch on channel 2</failure>
+    </testcase>
+  </testsuite>
+</testsuites>"#;
+
+        let chunks = XmlProcessor::extract_and_chunk(content, "results.xml", &["testcase".to_string()], &chunker).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[1].metadata.attributes.get("xpath").map(|s| s.as_str()),
+            Some("/testsuites[1]/testsuite[1]/testcase[2]")
+        );
+        assert!(chunks[1].content.contains("failure: Scoreboard mismatch on channel 2"));
+        assert!(chunks[0].content.contains("name=\"reset_test\""));
+    }
+}
\ No newline at end of file
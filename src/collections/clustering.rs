@@ -0,0 +1,199 @@
+use std::collections::{HashMap, HashSet};
+
+const MAX_ITERATIONS: usize = 50;
+const LABEL_KEYWORDS: usize = 3;
+
+const STOP_WORDS: &[&str] = &[
+    "this", "that", "with", "from", "have", "will", "when", "which", "their", "there",
+    "were", "been", "into", "each", "than", "then", "they", "them", "only", "also",
+    "must", "should", "would", "could", "shall", "used", "using", "other", "more", "some",
+];
+
+/// One document with its aggregate (mean chunk) embedding and text used for labelling
+pub struct DocumentProfile {
+    pub path: String,
+    pub embedding: Vec<f32>,
+    pub text: String,
+}
+
+/// A group of similar documents with a keyword label
+#[derive(Debug, Clone)]
+pub struct DocumentCluster {
+    pub documents: Vec<String>,
+    pub keywords: Vec<String>,
+    pub cohesion: f32,  // Mean cosine similarity of members to the centroid
+}
+
+/// Number of clusters when the caller does not specify one: roughly sqrt(n / 2)
+pub fn default_cluster_count(documents: usize) -> usize {
+    ((documents as f32 / 2.0).sqrt().round() as usize).clamp(1, documents.max(1))
+}
+
+/// Spherical k-means over normalised document embeddings. Seeding is deterministic
+/// (farthest-point), so the same corpus always yields the same proposals.
+pub fn cluster_documents(documents: &[DocumentProfile], k: usize) -> Vec<DocumentCluster> {
+    if documents.is_empty() {
+        return Vec::new();
+    }
+
+    let vectors: Vec<Vec<f32>> = documents.iter().map(|d| normalized(&d.embedding)).collect();
+    let k = k.clamp(1, documents.len());
+
+    let mut centroids = seed_centroids(&vectors, k);
+    let mut assignment = vec![0usize; vectors.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (i, vector) in vectors.iter().enumerate() {
+            let best = nearest(vector, &centroids);
+            if best != assignment[i] {
+                assignment[i] = best;
+                changed = true;
+            }
+        }
+
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f32>> = vectors.iter().zip(&assignment)
+                .filter(|(_, &a)| a == c)
+                .map(|(v, _)| v)
+                .collect();
+            if members.is_empty() {
+                continue;  // Keep the old centroid; an empty cluster is dropped below
+            }
+
+            let mut mean = vec![0.0; centroid.len()];
+            for member in &members {
+                for (m, x) in mean.iter_mut().zip(member.iter()) {
+                    *m += x;
+                }
+            }
+            *centroid = normalized(&mean);
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let document_frequencies = document_frequencies(documents);
+    let mut clusters = Vec::new();
+    for (c, centroid) in centroids.iter().enumerate() {
+        let members: Vec<usize> = (0..documents.len()).filter(|&i| assignment[i] == c).collect();
+        if members.is_empty() {
+            continue;
+        }
+
+        let cohesion = members.iter().map(|&i| dot(&vectors[i], centroid)).sum::<f32>() / members.len() as f32;
+        clusters.push(DocumentCluster {
+            documents: members.iter().map(|&i| documents[i].path.clone()).collect(),
+            keywords: label_keywords(&members, documents, &document_frequencies),
+            cohesion,
+        });
+    }
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.documents.len()));
+    clusters
+}
+
+fn seed_centroids(vectors: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[0].clone()];
+    while centroids.len() < k {
+        // Next seed: the vector least similar to its closest existing seed
+        let next = vectors.iter()
+            .map(|v| centroids.iter().map(|c| dot(v, c)).fold(f32::MIN, f32::max))
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        centroids.push(vectors[next].clone());
+    }
+    centroids
+}
+
+fn nearest(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids.iter()
+        .enumerate()
+        .map(|(i, c)| (i, dot(vector, c)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|x| x / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+fn keywords(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() > 3 && !w.chars().all(|c| c.is_ascii_digit()) && !STOP_WORDS.contains(&w.as_str()))
+}
+
+fn document_frequencies(documents: &[DocumentProfile]) -> HashMap<String, usize> {
+    let mut frequencies = HashMap::new();
+    for document in documents {
+        let unique: HashSet<String> = keywords(&document.text).collect();
+        for word in unique {
+            *frequencies.entry(word).or_insert(0) += 1;
+        }
+    }
+    frequencies
+}
+
+/// Pick the words that are frequent inside the cluster but rare across the corpus (tf-idf)
+fn label_keywords(members: &[usize], documents: &[DocumentProfile], document_frequencies: &HashMap<String, usize>) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for &i in members {
+        for word in keywords(&documents[i].text) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    let total = documents.len() as f32;
+    let mut scored: Vec<(String, f32)> = counts.into_iter()
+        .map(|(word, count)| {
+            let df = document_frequencies.get(&word).copied().unwrap_or(1) as f32;
+            let score = count as f32 * (1.0 + total / df).ln();
+            (word, score)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+
+    scored.into_iter().take(LABEL_KEYWORDS).map(|(word, _)| word).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(path: &str, embedding: Vec<f32>, text: &str) -> DocumentProfile {
+        DocumentProfile { path: path.to_string(), embedding, text: text.to_string() }
+    }
+
+    #[test]
+    fn test_similar_documents_cluster_together() {
+        let documents = vec![
+            profile("uvm_driver.md", vec![1.0, 0.1, 0.0], "uvm driver sequence sequencer"),
+            profile("uvm_monitor.md", vec![0.9, 0.2, 0.0], "uvm monitor scoreboard sequence"),
+            profile("notes_monday.txt", vec![0.0, 0.1, 1.0], "meeting notes action items"),
+            profile("notes_friday.txt", vec![0.1, 0.0, 0.9], "meeting notes schedule review"),
+        ];
+
+        let clusters = cluster_documents(&documents, 2);
+
+        assert_eq!(clusters.len(), 2);
+        let uvm = clusters.iter().find(|c| c.documents.contains(&"uvm_driver.md".to_string())).unwrap();
+        assert!(uvm.documents.contains(&"uvm_monitor.md".to_string()));
+        assert!(uvm.keywords.contains(&"sequence".to_string()));
+        assert!(!uvm.documents.contains(&"notes_monday.txt".to_string()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,196 @@
+use crate::chunker::Chunk;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Longest sentence considered a definition; longer ones are rarely a single definition
+const MAX_DEFINITION_CHARS: usize = 400;
+
+/// Definitions kept per term, explicit ones first
+const MAX_DEFINITIONS_PER_TERM: usize = 5;
+
+/// Sentence subjects that are never the term being defined ("It is a ...", "This is the ...")
+const NON_TERMS: &[&str] = &[
+    "it", "this", "that", "these", "those", "there", "here", "which", "what", "who", "he", "she",
+    "they", "we", "you", "i", "one", "each", "every", "all", "some", "any", "such", "its", "our",
+    "your", "their", "his", "her", "result", "answer", "goal", "idea", "problem", "reason", "default",
+];
+
+/// A sentence defining a term, with the chunk it was found in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlossaryEntry {
+    pub term: String,               // As written in the source
+    pub definition: String,         // The whole defining sentence
+    pub chunk_id: String,
+    pub source_file: String,
+    pub explicit: bool,             // "refers to", "is defined as", ...; otherwise "X is a ..."
+}
+
+/// Definition-style sentences in a chunk: "X refers to ...", "X is defined as ...",
+/// "X stands for ..." and "X means ...", or the weaker "X is a/an/the ..."
+pub fn extract_definitions(chunk: &Chunk) -> Vec<GlossaryEntry> {
+    static EXPLICIT: OnceLock<Regex> = OnceLock::new();
+    static COPULAR: OnceLock<Regex> = OnceLock::new();
+    static RUN_ON: OnceLock<Regex> = OnceLock::new();
+    let explicit = EXPLICIT.get_or_init(|| Regex::new(
+        r"^(?:(?:[Aa]n?|[Tt]he)\s+)?(?P<term>[\w][\w\-/+.#]*(?:\s+[\w\-/+.#]+){0,3}?)(?:\s+\([^)]{1,40}\))?,?\s+(?:refers? to|is defined as|are defined as|stands for|means|denotes)\s+\S"
+    ).unwrap());
+    let copular = COPULAR.get_or_init(|| Regex::new(
+        r"^(?:(?:[Aa]n?|[Tt]he)\s+)?(?P<term>[\w][\w\-/+.#]*(?:\s+[\w\-/+.#]+){0,2}?)(?:\s+\([^)]{1,40}\))?\s+(?:is|are)\s+(?:an?|the)\s+\S+\s+\S"
+    ).unwrap());
+
+    // Sentence chunking can join sentences without a space ("the CPU.The UART ...")
+    let run_on = RUN_ON.get_or_init(|| Regex::new(r"([\w)]{2}[.!?])(\p{Lu}\p{Ll})").unwrap());
+    let content = run_on.replace_all(&chunk.content, "$1 $2");
+
+    let mut entries = Vec::new();
+    for sentence in content.lines().flat_map(|line| line.unicode_sentences()) {
+        // Markdown emphasis, bullets and headings around the sentence are not part of it
+        let sentence = sentence.trim().trim_start_matches(['#', '>', '-', '*', '+', ' ']).replace(['*', '`'], "");
+        let sentence = sentence.split_whitespace().collect::<Vec<_>>().join(" ");
+        if sentence.len() > MAX_DEFINITION_CHARS || !sentence.ends_with(['.', '!']) {
+            continue;
+        }
+
+        let (captures, is_explicit) = match explicit.captures(&sentence) {
+            Some(captures) => (captures, true),
+            None => match copular.captures(&sentence) {
+                Some(captures) => (captures, false),
+                None => continue,
+            },
+        };
+        let term = captures["term"].trim_end_matches([',', '.']).to_string();
+        let first_word = term.split_whitespace().next().unwrap_or_default().to_lowercase();
+        if NON_TERMS.contains(&first_word.as_str()) || term.chars().all(|c| c.is_numeric()) {
+            continue;
+        }
+
+        entries.push(GlossaryEntry {
+            term,
+            definition: sentence.to_string(),
+            chunk_id: chunk.id.clone(),
+            source_file: chunk.metadata.source_file.clone(),
+            explicit: is_explicit,
+        });
+    }
+    entries
+}
+
+/// Lookup key for a term: lowercase with single spaces and no plural "s"
+pub fn term_key(term: &str) -> String {
+    let key = term.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    match key.strip_suffix('s') {
+        Some(singular) if singular.len() > 2 && !singular.ends_with('s') => singular.to_string(),
+        _ => key,
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GlossaryState {
+    built_at: Option<DateTime<Utc>>,
+    chunks_scanned: usize,
+    terms: BTreeMap<String, Vec<GlossaryEntry>>,  // term_key -> definitions, explicit first
+}
+
+/// The glossary extracted from the corpus, persisted as JSON in the data dir
+pub struct GlossaryStore {
+    path: PathBuf,
+    state: GlossaryState,
+}
+
+impl GlossaryStore {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("glossary.json");
+        let state = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .map_err(|e| anyhow!("Failed to read glossary from {:?}: {}", path, e))?
+        } else {
+            GlossaryState::default()
+        };
+
+        Ok(Self { path, state })
+    }
+
+    /// Replace the glossary with the definitions found in `chunks`
+    pub fn rebuild<'a>(&mut self, chunks: impl Iterator<Item = &'a Chunk>) -> Result<(usize, usize)> {
+        let mut terms: BTreeMap<String, Vec<GlossaryEntry>> = BTreeMap::new();
+        let mut scanned = 0;
+        for chunk in chunks {
+            scanned += 1;
+            for entry in extract_definitions(chunk) {
+                let definitions = terms.entry(term_key(&entry.term)).or_default();
+                if !definitions.iter().any(|d| d.definition == entry.definition) {
+                    definitions.push(entry);
+                }
+            }
+        }
+        for definitions in terms.values_mut() {
+            definitions.sort_by_key(|d| !d.explicit);
+            definitions.truncate(MAX_DEFINITIONS_PER_TERM);
+        }
+
+        let definitions = terms.values().map(|d| d.len()).sum();
+        self.state = GlossaryState { built_at: Some(Utc::now()), chunks_scanned: scanned, terms };
+        self.save()?;
+        Ok((self.state.terms.len(), definitions))
+    }
+
+    pub fn built_at(&self) -> Option<DateTime<Utc>> {
+        self.state.built_at
+    }
+
+    pub fn define(&self, term: &str) -> &[GlossaryEntry] {
+        self.state.terms.get(&term_key(term)).map(|d| d.as_slice()).unwrap_or_default()
+    }
+
+    /// Terms containing `term`, for lookups that found no definition
+    pub fn similar_terms(&self, term: &str, limit: usize) -> Vec<&str> {
+        let key = term_key(term);
+        self.state.terms.iter()
+            .filter(|(other, _)| other.contains(&key) || key.contains(other.as_str()))
+            .filter_map(|(_, definitions)| definitions.first().map(|d| d.term.as_str()))
+            .take(limit)
+            .collect()
+    }
+
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&self.state)?)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    #[test]
+    fn test_definitions_are_extracted_and_looked_up() {
+        let text = "# Glossary\n\n**DMA** refers to direct memory access by peripherals. \
+            A descriptor ring is a circular list of transfer descriptors. \
+            It is a good idea to reset first. The result is the same either way. \
+            Scatter-gather lists (SGL) are defined as chained descriptors.Reset the engine now.";
+        let chunk = SemanticChunker::build_text_chunk(text, "dma.md", "hash", (1, 4), ChunkStrategy::NaturalSection);
+
+        let entries = extract_definitions(&chunk);
+        let terms: Vec<(&str, bool)> = entries.iter().map(|e| (e.term.as_str(), e.explicit)).collect();
+        assert_eq!(terms, vec![("DMA", true), ("descriptor ring", false), ("Scatter-gather lists", true)]);
+        assert_eq!(entries[0].definition, "DMA refers to direct memory access by peripherals.");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = GlossaryStore::open(dir.path()).unwrap();
+        assert_eq!(store.rebuild(std::iter::once(&chunk)).unwrap(), (3, 3));
+        let store = GlossaryStore::open(dir.path()).unwrap();
+        assert_eq!(store.define("descriptor rings")[0].chunk_id, chunk.id);
+        assert_eq!(store.define("scatter-gather list").len(), 1);
+        assert!(store.define("ring").is_empty());
+        assert_eq!(store.similar_terms("ring", 5), vec!["descriptor ring"]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+pub mod clustering;
+pub mod glossary;
+pub mod pins;
+pub mod store;
+
+pub use clustering::*;
+pub use glossary::*;
+pub use pins::*;
+pub use store::*;
\ No newline at end of file
@@ -0,0 +1,127 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Tools whose output pinned context is prepended to
+pub const PINNABLE_TOOLS: &[&str] = &["search_knowledge_chunk", "search_knowledge_chapter"];
+
+/// A chunk or whole document always included with the output of some tools, e.g. a glossary
+/// or a coding standard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    pub id: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,     // Every chunk of the document, in order
+    #[serde(default)]
+    pub tools: Vec<String>,              // Empty: every pinnable tool
+    #[serde(default)]
+    pub collections: Vec<String>,        // Only when a result is in one of these; empty: always
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Pin {
+    /// Whether the pin belongs with `tool`'s output for results in `collections`
+    pub fn applies(&self, tool: &str, collections: &[&str]) -> bool {
+        (self.tools.is_empty() || self.tools.iter().any(|t| t == tool))
+            && (self.collections.is_empty() || self.collections.iter().any(|c| collections.contains(&c.as_str())))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PinState {
+    next_id: usize,
+    pins: Vec<Pin>,
+}
+
+/// Pinned context, persisted as JSON in the data dir
+pub struct PinStore {
+    path: PathBuf,
+    state: PinState,
+}
+
+impl PinStore {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("pins.json");
+        let state = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .map_err(|e| anyhow!("Failed to read pins from {:?}: {}", path, e))?
+        } else {
+            PinState::default()
+        };
+
+        Ok(Self { path, state })
+    }
+
+    /// Pin one chunk or one document (give exactly one of them), optionally only for some
+    /// tools or collections. Pinning the same target again replaces its scope and note.
+    pub fn pin(&mut self, chunk_id: Option<String>, source_file: Option<String>, tools: Vec<String>, collections: Vec<String>, note: Option<String>) -> Result<Pin> {
+        if chunk_id.is_some() == source_file.is_some() {
+            return Err(anyhow!("Pin either a chunk_id or a source_file"));
+        }
+        if let Some(tool) = tools.iter().find(|tool| !PINNABLE_TOOLS.contains(&tool.as_str())) {
+            return Err(anyhow!("Context cannot be pinned to '{}'; expected one of: {}", tool, PINNABLE_TOOLS.join(", ")));
+        }
+
+        self.state.pins.retain(|pin| pin.chunk_id != chunk_id || pin.source_file != source_file);
+        let pin = Pin { id: self.state.next_id, chunk_id, source_file, tools, collections, note, created_at: Utc::now() };
+        self.state.next_id += 1;
+        self.state.pins.push(pin.clone());
+        self.save()?;
+        Ok(pin)
+    }
+
+    pub fn unpin(&mut self, pin_id: usize) -> Result<Pin> {
+        let position = self.state.pins.iter().position(|pin| pin.id == pin_id)
+            .ok_or_else(|| anyhow!("No pin with id {}", pin_id))?;
+        let pin = self.state.pins.remove(position);
+        self.save()?;
+        Ok(pin)
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.state.pins
+    }
+
+    /// Pins for `tool`'s output, oldest first
+    pub fn applicable(&self, tool: &str, collections: &[&str]) -> Vec<&Pin> {
+        self.state.pins.iter().filter(|pin| pin.applies(tool, collections)).collect()
+    }
+
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&self.state)?)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pins_are_scoped_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = PinStore::open(dir.path()).unwrap();
+        let glossary = store.pin(None, Some("glossary.md".to_string()), vec![], vec![], None).unwrap();
+        let standard = store.pin(Some("c1".to_string()), None, vec!["search_knowledge_chunk".to_string()], vec!["firmware".to_string()], None).unwrap();
+        assert!(store.pin(None, None, vec![], vec![], None).is_err());
+        assert!(store.pin(Some("c2".to_string()), None, vec!["health".to_string()], vec![], None).is_err());
+
+        let ids = |pins: Vec<&Pin>| pins.iter().map(|pin| pin.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.applicable("search_knowledge_chunk", &["firmware"])), vec![glossary.id, standard.id]);
+        assert_eq!(ids(store.applicable("search_knowledge_chunk", &["hardware"])), vec![glossary.id]);
+        assert_eq!(ids(store.applicable("search_knowledge_chapter", &["firmware"])), vec![glossary.id]);
+
+        // Re-pinning a target replaces it; pins survive reopening
+        let glossary = store.pin(None, Some("glossary.md".to_string()), vec![], vec![], Some("terms".to_string())).unwrap();
+        store.unpin(standard.id).unwrap();
+        assert!(store.unpin(standard.id).is_err());
+        assert_eq!(PinStore::open(dir.path()).unwrap().pins(), &[glossary]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,102 @@
+use super::clustering::DocumentCluster;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A proposed collection awaiting user acceptance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionProposal {
+    pub id: usize,
+    pub suggested_name: String,
+    pub keywords: Vec<String>,
+    pub documents: Vec<String>,
+    pub cohesion: f32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CollectionState {
+    proposals: Vec<CollectionProposal>,
+    assignments: BTreeMap<String, String>,  // Document path -> collection name
+}
+
+/// Collection proposals and accepted assignments, persisted as JSON in the data dir
+pub struct CollectionStore {
+    path: PathBuf,
+    state: CollectionState,
+}
+
+impl CollectionStore {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("collections.json");
+        let state = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .map_err(|e| anyhow!("Failed to read collections from {:?}: {}", path, e))?
+        } else {
+            CollectionState::default()
+        };
+
+        Ok(Self { path, state })
+    }
+
+    /// Replace the pending proposals with freshly computed clusters
+    pub fn propose(&mut self, clusters: Vec<DocumentCluster>) -> Result<&[CollectionProposal]> {
+        self.state.proposals = clusters.into_iter()
+            .enumerate()
+            .map(|(id, cluster)| CollectionProposal {
+                id,
+                suggested_name: cluster.keywords.join(" / "),
+                keywords: cluster.keywords,
+                documents: cluster.documents,
+                cohesion: cluster.cohesion,
+            })
+            .collect();
+        self.save()?;
+        Ok(&self.state.proposals)
+    }
+
+    /// Assign every document of a proposal to a collection, named by the user or by the proposal
+    pub fn accept(&mut self, proposal_id: usize, name: Option<String>) -> Result<(String, usize)> {
+        let proposal = self.state.proposals.iter()
+            .find(|p| p.id == proposal_id)
+            .ok_or_else(|| anyhow!("No collection proposal with id {}", proposal_id))?;
+
+        let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| proposal.suggested_name.clone());
+        for document in &proposal.documents {
+            self.state.assignments.insert(document.clone(), name.clone());
+        }
+        let count = proposal.documents.len();
+
+        self.save()?;
+        Ok((name, count))
+    }
+
+    /// Assign a single document to a named collection
+    pub fn assign(&mut self, document: &str, collection: &str) -> Result<()> {
+        self.state.assignments.insert(document.to_string(), collection.to_string());
+        self.save()
+    }
+
+    pub fn collection_of(&self, document: &str) -> Option<&String> {
+        self.state.assignments.get(document)
+    }
+
+    /// Documents assigned to `collection`
+    pub fn documents_in(&self, collection: &str) -> Vec<String> {
+        self.state.assignments.iter()
+            .filter(|(_, name)| name.as_str() == collection)
+            .map(|(document, _)| document.clone())
+            .collect()
+    }
+
+    pub fn assignments(&self) -> &BTreeMap<String, String> {
+        &self.state.assignments
+    }
+
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&self.state)?)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
\ No newline at end of file
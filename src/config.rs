@@ -1,4 +1,8 @@
+use crate::chunker::languages::LanguageRules;
+use crate::chunker::semantic::EmbeddedField;
+use crate::search::{FusionWeights, RankingStage, DEFAULT_PIPELINE};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -8,6 +12,20 @@ pub struct Config {
     pub embedding: EmbeddingConfig,
     pub mcp: McpConfig,
     pub graph: GraphConfig,
+    #[serde(default)]
+    pub ingestion: IngestionConfig,
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub ranking: RankingConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
+    pub sources: Vec<SourceConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -17,6 +35,22 @@ pub struct StorageConfig {
     pub min_chunk_size: usize,
     #[serde(default)]
     pub instance_id: Option<String>,  // Optional instance ID for multi-server setups
+    #[serde(default = "default_embedding_format")]
+    pub embedding_format: String,     // "f32" or "int8" (per-vector scale, ~4x smaller); int8 migrates existing chunks on startup
+    #[serde(default)]
+    pub cache_f32: bool,              // With int8, keep f32 vectors in the in-memory search cache anyway
+    #[serde(default)]
+    pub binary_prefilter: bool,       // Keep sign bits of each vector and prefilter vector search by Hamming distance
+    #[serde(default = "default_binary_oversample")]
+    pub binary_oversample: usize,     // Candidates scored exactly per requested result after the prefilter
+}
+
+fn default_embedding_format() -> String {
+    "f32".to_string()
+}
+
+fn default_binary_oversample() -> usize {
+    10
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -24,13 +58,281 @@ pub struct ChunkingConfig {
     pub overlap_tokens: usize,
     pub semantic_threshold: f32,
     pub code_languages: Vec<String>,
+    #[serde(default)]
+    pub adaptive_sizing: bool,  // Pick chunk boundaries from document structure
+    #[serde(default)]
+    pub json_paths: Vec<String>,  // JSON pointers whose items become chunks; empty means top-level items
+    #[serde(default)]
+    pub xml_elements: Vec<String>,  // XML element names that become chunks; empty means children of the root
+    #[serde(default = "default_log_window_secs")]
+    pub log_window_secs: u64,       // Timestamped log lines are grouped into windows of this length
+    #[serde(default = "default_subtitle_window_secs")]
+    pub subtitle_window_secs: u64,  // SRT/VTT cues are grouped into windows of this length
+    #[serde(default)]
+    pub sliding_window: SlidingWindowConfig,
+    #[serde(default)]
+    pub languages: HashMap<String, LanguageRules>,  // Language id (rust, python, ...) -> code chunking overrides
+    #[serde(default)]
+    pub summaries: SummaryConfig,
+    #[serde(default)]
+    pub size_limits: HashMap<String, ChunkSizeLimits>,  // Detected doc type (text, markdown, code, pdf, ...) -> chunk size bounds
+    #[serde(default)]
+    pub splitters: Vec<SplitterConfig>,  // The first one that applies to a document segments it before chunking
+    #[serde(default)]
+    pub strategies: HashMap<String, String>,  // Detected doc type -> chunking strategy name, overriding the built-in choice
+    #[serde(default = "default_detect_language")]
+    pub detect_language: bool,  // Record the human language (en, de, zh, ...) of non-code chunks as their language
+}
+
+/// A user-defined split: every line matching `pattern` starts a new segment, and segments
+/// are chunked separately
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SplitterConfig {
+    pub pattern: String,            // Regex matched against each line, e.g. "^---$" or "^begin_test"
+    #[serde(default)]
+    pub doc_types: Vec<String>,     // Detected types it applies to; empty means every type chunked from text
+    #[serde(default)]
+    pub extensions: Vec<String>,    // File extensions it applies to, e.g. ["tst"]; empty means any
+    #[serde(default = "default_keep_delimiter")]
+    pub keep_delimiter: bool,       // false drops the matching lines, e.g. for "---" separators
+}
+
+fn default_keep_delimiter() -> bool {
+    true
+}
+
+fn default_detect_language() -> bool {
+    true
+}
+
+/// Chunk size bounds for one document type; unset fields keep storage.max_chunk_size and
+/// storage.min_chunk_size
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ChunkSizeLimits {
+    #[serde(default)]
+    pub max_chunk_size: Option<usize>,
+    #[serde(default)]
+    pub min_chunk_size: Option<usize>,
+}
+
+/// Short per-chunk titles and summaries made at ingest time
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SummaryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_summary_max_chars")]
+    pub max_chars: usize,
+    #[serde(default)]
+    pub summarizer: Option<SummarizerConfig>,  // Without it, summaries are a chunk's opening sentences
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_chars: default_summary_max_chars(),
+            summarizer: None,
+        }
+    }
+}
+
+/// An OpenAI-compatible /chat/completions endpoint that writes chunk titles and summaries
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SummarizerConfig {
+    pub endpoint: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,  // Environment variable holding the API key
+    #[serde(default = "default_provider_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_summary_max_chars() -> usize {
+    200
+}
+
+/// Plain token windows for document types where sentence detection does poorly
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SlidingWindowConfig {
+    #[serde(default = "default_window_tokens")]
+    pub window_tokens: usize,
+    #[serde(default = "default_stride_tokens")]
+    pub stride_tokens: usize,       // Consecutive windows overlap by window_tokens - stride_tokens
+    #[serde(default)]
+    pub doc_types: Vec<String>,     // Detected types chunked this way, e.g. "log", "text", "code"
+}
+
+impl Default for SlidingWindowConfig {
+    fn default() -> Self {
+        Self {
+            window_tokens: default_window_tokens(),
+            stride_tokens: default_stride_tokens(),
+            doc_types: Vec::new(),
+        }
+    }
+}
+
+fn default_window_tokens() -> usize {
+    256
+}
+
+fn default_stride_tokens() -> usize {
+    192
+}
+
+fn default_log_window_secs() -> u64 {
+    60
+}
+
+fn default_subtitle_window_secs() -> u64 {
+    60
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct EmbeddingConfig {
     pub model_name: String,
     pub dimension: usize,
-    pub batch_size: usize,
+    #[serde(default)]
+    pub truncate_dimension: Option<usize>,  // Keep the first N components (Matryoshka models); changing it needs a re-index
+    pub batch_size: usize,          // Max texts per embedding request
+    #[serde(default = "default_max_batch_tokens")]
+    pub max_batch_tokens: usize,    // Max estimated tokens per embedding request
+    #[serde(default = "default_max_concurrent_batches")]
+    pub max_concurrent_batches: usize,  // Embedding requests of one document in flight at once
+    #[serde(default = "default_query_cache_size")]
+    pub query_cache_size: usize,    // Recent query vectors kept to skip re-embedding repeated queries; 0 disables
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,            // L2-normalize chunk and query embeddings
+    #[serde(default = "default_pooling")]
+    pub pooling: String,            // "mean" or "max" over token and n-gram vectors
+    #[serde(default = "default_backend")]
+    pub backend: String,            // "local" (model_name run in-process), "tei" (text-embeddings-inference server) or "http" (OpenAI-compatible)
+    #[serde(default = "default_device")]
+    pub device: String,             // Where local transformer models run: "auto", "cpu", "cuda[:N]" or "metal[:N]"; falls back to the CPU
+    #[serde(default)]
+    pub endpoint: Option<String>,   // Server URL of a remote backend
+    #[serde(default)]
+    pub api_key_env: Option<String>,  // Environment variable holding the remote backend's API key
+    #[serde(default = "default_provider_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,     // Idle connections kept open to a remote backend
+    #[serde(default)]
+    pub query_template: Option<String>,    // Instruction for the backend around queries, e.g. "query: {text}" (E5); no {text} means a prefix
+    #[serde(default)]
+    pub passage_template: Option<String>,  // Same for chunks, e.g. "passage: " (E5)
+    #[serde(default)]
+    pub providers: Vec<EmbeddingProviderConfig>,  // Tried in order, replacing the backend; empty uses the backend with the hash model as fallback
+    #[serde(default)]
+    pub code_providers: Vec<EmbeddingProviderConfig>,  // Chain for Code chunks, e.g. a code-specialized model; queries are embedded by both chains. Empty embeds code like other chunks
+    #[serde(default = "default_provider_cooldown_secs")]
+    pub provider_cooldown_secs: u64,  // A failed provider is skipped for this long before being retried
+    #[serde(default = "default_drift_check_interval_secs")]
+    pub drift_check_interval_secs: u64,  // Re-embed a sample of chunks this often to detect model drift; 0 disables
+    #[serde(default = "default_drift_sample_size")]
+    pub drift_sample_size: usize,
+    #[serde(default = "default_drift_threshold")]
+    pub drift_threshold: f32,         // Re-indexing is suggested when 1 - mean similarity exceeds this
+    #[serde(default)]
+    pub context_headers: bool,        // Embed "File > Chapter > Section" above each chunk's content
+    #[serde(default)]
+    pub metadata_fields: Vec<EmbeddedField>,  // Metadata embedded below each chunk's content: tags, section, dependencies
+    #[serde(default)]
+    pub sparse: Option<SparseEmbeddingConfig>,  // Learned sparse term weights next to the dense vectors
+}
+
+/// A SPLADE-style model whose term weights form a further list in fused hybrid search
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SparseEmbeddingConfig {
+    pub model: String,                // Hugging Face id or local directory of a BERT masked-LM SPLADE model
+    #[serde(default = "default_sparse_max_terms")]
+    pub max_terms: usize,             // Heaviest terms kept per chunk or query
+}
+
+fn default_sparse_max_terms() -> usize {
+    256
+}
+
+/// One entry in the embedding provider fallback chain
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EmbeddingProviderConfig {
+    pub name: String,                 // Recorded on chunks and in responses
+    pub kind: String,                 // "transformer" (local sentence-transformers model), "tei" (text-embeddings-inference server), "http" (OpenAI-compatible /embeddings API) or "local" (hash-based)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,  // Environment variable holding the API key
+    #[serde(default = "default_provider_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,       // Idle connections kept open to the endpoint
+    #[serde(default)]
+    pub late_chunking: bool,          // Embed a document's consecutive chunks together so each vector sees its neighbours
+    #[serde(default = "default_max_passage_tokens")]
+    pub max_passage_tokens: usize,    // Longest passage embedded at once with late_chunking (estimated tokens)
+    #[serde(default)]
+    pub query_template: Option<String>,    // Instruction templates for this provider's model, like embedding.query_template
+    #[serde(default)]
+    pub passage_template: Option<String>,
+}
+
+fn default_max_passage_tokens() -> usize {
+    8192
+}
+
+fn default_max_batch_tokens() -> usize {
+    8192
+}
+
+fn default_query_cache_size() -> usize {
+    256
+}
+
+fn default_max_concurrent_batches() -> usize {
+    4
+}
+
+fn default_normalize() -> bool {
+    true
+}
+
+fn default_pooling() -> String {
+    "mean".to_string()
+}
+
+fn default_provider_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_drift_check_interval_secs() -> u64 {
+    86400
+}
+
+fn default_drift_sample_size() -> usize {
+    50
+}
+
+fn default_drift_threshold() -> f32 {
+    0.02
+}
+
+fn default_provider_timeout_secs() -> u64 {
+    10
+}
+
+fn default_backend() -> String {
+    "local".to_string()
+}
+
+fn default_device() -> String {
+    "cpu".to_string()
+}
+
+fn default_max_connections() -> usize {
+    8
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -42,6 +344,356 @@ pub struct McpConfig {
 pub struct GraphConfig {
     pub max_connections: usize,
     pub similarity_threshold: f32,
+    #[serde(default = "default_prune_min_weight")]
+    pub prune_min_weight: f32,          // Similarity edges below this weight are dropped by maintenance
+    #[serde(default = "default_stale_after_days")]
+    pub stale_after_days: u64,          // Chunks not re-ingested for this long count as stale
+    #[serde(default = "default_decay_half_life_days")]
+    pub decay_half_life_days: f32,      // Similarity edges of stale chunks lose half their weight per half-life
+    #[serde(default = "default_maintenance_interval_secs")]
+    pub maintenance_interval_secs: u64, // 0 disables the background maintenance job
+}
+
+fn default_prune_min_weight() -> f32 {
+    0.5
+}
+
+fn default_stale_after_days() -> u64 {
+    30
+}
+
+fn default_decay_half_life_days() -> f32 {
+    90.0
+}
+
+fn default_maintenance_interval_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct IngestionConfig {
+    #[serde(default = "default_max_file_size_bytes")]
+    pub max_file_size_bytes: u64,
+    #[serde(default = "default_max_chunks_per_document")]
+    pub max_chunks_per_document: usize,
+    #[serde(default = "default_max_corpus_size_bytes")]
+    pub max_corpus_size_bytes: u64,  // Disk usage of data_dir beyond which ingestion is refused
+    #[serde(default)]
+    pub include_extensions: Vec<String>,  // When non-empty, only these extensions are ingested
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,       // Matched against the full path, e.g. "**/node_modules/**"
+    #[serde(default = "default_fetch_timeout_secs")]
+    pub fetch_timeout_secs: u64,          // ingest_url gives up on a download after this long
+    #[serde(default = "default_crawl_max_pages")]
+    pub crawl_max_pages: usize,           // Pages a crawling ingest_url visits unless the call sets max_pages
+    #[serde(default = "default_crawl_max_depth")]
+    pub crawl_max_depth: usize,           // Link hops from the starting page in links mode
+    #[serde(default)]
+    pub refresh_interval_secs: u64,       // Re-fetch URL documents this often and re-ingest changed ones; 0 disables
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,           // Quiet period that ends a burst of file events from a watched source
+}
+
+/// A directory whose matching files make up part of the corpus. `path` may also be an
+/// object-storage location: `s3://bucket/prefix`, or an `http(s)://` URL serving an object
+/// listing (an S3-style XML listing or an HTML directory index).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SourceConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub include: Vec<String>,   // Globs relative to path, e.g. "**/*.md"; empty takes every file
+    #[serde(default)]
+    pub exclude: Vec<String>,   // e.g. "drafts/**"
+    #[serde(default = "default_source_recursive")]
+    pub recursive: bool,
+    #[serde(default = "default_source_on_startup")]
+    pub on_startup: bool,       // Ingest new files when the server starts, not only via ingest_sources
+    #[serde(default)]
+    pub watch: bool,            // Re-ingest changed files and drop deleted ones while the server runs
+    #[serde(default = "default_source_gitignore")]
+    pub gitignore: bool,        // Skip files matched by .gitignore files in the source; .ragignore files always apply
+    #[serde(default)]
+    pub object_store: ObjectStoreConfig,
+}
+
+impl SourceConfig {
+    /// The location when the source is in object storage rather than a local directory
+    pub fn remote_url(&self) -> Option<&str> {
+        self.path.to_str().filter(|path| ["s3://", "http://", "https://"].iter().any(|scheme| path.starts_with(scheme)))
+    }
+}
+
+/// Endpoint and credentials for an object-storage source. Unset S3 credentials fall back to
+/// the standard AWS environment variables; with none at all, requests are sent unsigned.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ObjectStoreConfig {
+    #[serde(default)]
+    pub region: Option<String>,             // AWS_REGION, then us-east-1
+    #[serde(default)]
+    pub endpoint: Option<String>,           // S3-compatible servers (MinIO, R2, ...), addressed path-style
+    #[serde(default)]
+    pub access_key_id: Option<String>,      // AWS_ACCESS_KEY_ID
+    #[serde(default)]
+    pub secret_access_key: Option<String>,  // AWS_SECRET_ACCESS_KEY
+    #[serde(default)]
+    pub session_token: Option<String>,      // AWS_SESSION_TOKEN
+    #[serde(default)]
+    pub headers: HashMap<String, String>,   // Sent with every request, e.g. Authorization for HTTP listings
+}
+
+fn default_source_recursive() -> bool {
+    true
+}
+
+fn default_source_gitignore() -> bool {
+    true
+}
+
+fn default_source_on_startup() -> bool {
+    true
+}
+
+fn default_max_file_size_bytes() -> u64 {
+    50 * 1024 * 1024  // 50MB
+}
+
+fn default_fetch_timeout_secs() -> u64 {
+    30
+}
+
+fn default_crawl_max_pages() -> usize {
+    50
+}
+
+fn default_crawl_max_depth() -> usize {
+    2
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+fn default_max_chunks_per_document() -> usize {
+    10_000
+}
+
+fn default_max_corpus_size_bytes() -> u64 {
+    10 * 1024 * 1024 * 1024  // 10GB
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size_bytes: default_max_file_size_bytes(),
+            max_chunks_per_document: default_max_chunks_per_document(),
+            max_corpus_size_bytes: default_max_corpus_size_bytes(),
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            exclude_globs: Vec::new(),
+            fetch_timeout_secs: default_fetch_timeout_secs(),
+            crawl_max_pages: default_crawl_max_pages(),
+            crawl_max_depth: default_crawl_max_depth(),
+            refresh_interval_secs: 0,
+            watch_debounce_ms: default_watch_debounce_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RuntimeConfig {
+    #[serde(default = "default_ingest_threads")]
+    pub ingest_threads: usize,  // Worker threads for chunking/embedding during ingestion
+    #[serde(default = "default_search_threads")]
+    pub search_threads: usize,  // Worker threads for query embedding and scoring
+    #[serde(default = "default_ingest_nice")]
+    pub ingest_nice: i32,       // Nice increment for ingest threads (Linux only)
+}
+
+fn default_ingest_threads() -> usize {
+    2
+}
+
+fn default_search_threads() -> usize {
+    4
+}
+
+fn default_ingest_nice() -> i32 {
+    10
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            ingest_threads: default_ingest_threads(),
+            search_threads: default_search_threads(),
+            ingest_nice: default_ingest_nice(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchConfig {
+    #[serde(default)]
+    pub vocabulary_file: Option<PathBuf>,  // Extra abbreviations/synonyms merged into query expansion
+    #[serde(default = "default_low_score_threshold")]
+    pub low_score_threshold: f32,          // Queries whose top score is below this count as failed
+    #[serde(default)]
+    pub exclusion_penalty: f32,            // Score multiplier for results with excluded terms; 0 drops them
+}
+
+fn default_low_score_threshold() -> f32 {
+    0.4
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            vocabulary_file: None,
+            low_score_threshold: default_low_score_threshold(),
+            exclusion_penalty: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RankingConfig {
+    #[serde(default)]
+    pub type_weights: HashMap<String, f32>,  // Chunk type -> score multiplier during fusion, e.g. code: 1.2
+    #[serde(default)]
+    pub fusion: Option<FusionWeights>,       // Rank fusion of vector, keyword and graph results; unset keeps vector search with keyword fallback
+    #[serde(default)]
+    pub eval_set: Option<PathBuf>,           // Labeled queries that tune_ranking scores weights against
+    #[serde(default = "default_ranking_pipeline")]
+    pub pipeline: Vec<RankingStage>,         // Search stages in order; leaving one out disables it
+    #[serde(default)]
+    pub collection_pipelines: HashMap<String, Vec<RankingStage>>,  // Collection name -> pipeline for searches scoped to it
+    #[serde(default = "default_mmr_lambda")]
+    pub mmr_lambda: f32,                     // Relevance share of the mmr stage's trade-off; the rest penalizes redundancy
+}
+
+fn default_ranking_pipeline() -> Vec<RankingStage> {
+    DEFAULT_PIPELINE.to_vec()
+}
+
+fn default_mmr_lambda() -> f32 {
+    0.7
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            type_weights: HashMap::new(),
+            fusion: None,
+            eval_set: None,
+            pipeline: default_ranking_pipeline(),
+            collection_pipelines: HashMap::new(),
+            mmr_lambda: default_mmr_lambda(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MemoryConfig {
+    #[serde(default = "default_memory_recency_weight")]
+    pub recency_weight: f32,   // Share of a recall score that comes from recency; the rest is relevance
+    #[serde(default = "default_memory_half_life_days")]
+    pub half_life_days: f32,   // Age at which a memory's recency component halves
+    #[serde(default = "default_memory_consolidation_interval_secs")]
+    pub consolidation_interval_secs: u64,  // 0 disables the background consolidation job
+    #[serde(default = "default_memory_duplicate_similarity")]
+    pub duplicate_similarity: f32,         // Memories at least this similar are merged into the newest
+    #[serde(default = "default_memory_stale_after_days")]
+    pub stale_after_days: u64,             // Memories not recalled for this long start to decay
+    #[serde(default = "default_memory_decay_half_life_days")]
+    pub decay_half_life_days: f32,         // Stale memories lose half their strength per half-life
+    #[serde(default = "default_memory_archive_below_strength")]
+    pub archive_below_strength: f32,       // Decayed memories below this strength are archived
+    #[serde(default)]
+    pub expire_after_days: u64,            // Memories older than this are archived; 0 keeps them
+}
+
+fn default_memory_recency_weight() -> f32 {
+    0.3
+}
+
+fn default_memory_half_life_days() -> f32 {
+    30.0
+}
+
+fn default_memory_consolidation_interval_secs() -> u64 {
+    3600
+}
+
+fn default_memory_duplicate_similarity() -> f32 {
+    0.95
+}
+
+fn default_memory_stale_after_days() -> u64 {
+    30
+}
+
+fn default_memory_decay_half_life_days() -> f32 {
+    60.0
+}
+
+fn default_memory_archive_below_strength() -> f32 {
+    0.1
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            recency_weight: default_memory_recency_weight(),
+            half_life_days: default_memory_half_life_days(),
+            consolidation_interval_secs: default_memory_consolidation_interval_secs(),
+            duplicate_similarity: default_memory_duplicate_similarity(),
+            stale_after_days: default_memory_stale_after_days(),
+            decay_half_life_days: default_memory_decay_half_life_days(),
+            archive_below_strength: default_memory_archive_below_strength(),
+            expire_after_days: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportConfig {
+    #[serde(default = "default_report_template_dir")]
+    pub template_dir: PathBuf,   // generate_report looks up templates by name here
+    #[serde(default = "default_report_output_dir")]
+    pub output_dir: PathBuf,     // generate_report's output_path is resolved under this directory
+    #[serde(default = "default_report_snippet_chars")]
+    pub snippet_chars: usize,    // Excerpt length per result in rendered reports
+    #[serde(default = "default_response_language")]
+    pub response_language: String,  // Language of generated report wording, e.g. "en", "de", "ja"
+}
+
+fn default_report_template_dir() -> PathBuf {
+    PathBuf::from("./reports")
+}
+
+fn default_report_output_dir() -> PathBuf {
+    PathBuf::from("./reports/generated")
+}
+
+fn default_report_snippet_chars() -> usize {
+    400
+}
+
+fn default_response_language() -> String {
+    "en".to_string()
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            template_dir: default_report_template_dir(),
+            output_dir: default_report_output_dir(),
+            snippet_chars: default_report_snippet_chars(),
+            response_language: default_response_language(),
+        }
+    }
 }
 
 impl Config {
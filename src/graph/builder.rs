@@ -1,6 +1,7 @@
 use crate::chunker::Chunk;
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,7 +12,7 @@ pub struct GraphNode {
     pub metadata: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
     Chunk,
     Word,
@@ -19,6 +20,19 @@ pub enum NodeType {
     Document,
 }
 
+impl NodeType {
+    /// Case-insensitive name as used in graph queries
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "chunk" => Some(NodeType::Chunk),
+            "word" => Some(NodeType::Word),
+            "chapter" => Some(NodeType::Chapter),
+            "document" => Some(NodeType::Document),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphEdge {
     pub from: String,
@@ -27,7 +41,7 @@ pub struct GraphEdge {
     pub weight: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EdgeType {
     Similarity,
     Contains,
@@ -36,10 +50,35 @@ pub enum EdgeType {
     Reference,
 }
 
+impl EdgeType {
+    /// Case-insensitive name as used in graph queries ("part_of" and "partof" both work)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['_', '-'], "").as_str() {
+            "similarity" => Some(EdgeType::Similarity),
+            "contains" => Some(EdgeType::Contains),
+            "partof" => Some(EdgeType::PartOf),
+            "sequential" => Some(EdgeType::Sequential),
+            "reference" => Some(EdgeType::Reference),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct GraphBuilder {
-    nodes: HashMap<String, GraphNode>,
-    edges: Vec<GraphEdge>,
+    pub(super) nodes: HashMap<String, GraphNode>,
+    pub(super) edges: Vec<GraphEdge>,
+    adjacency: HashMap<String, Vec<usize>>,  // Node id -> indices into `edges`, both directions
     similarity_threshold: f32,
+    pub(super) last_maintenance: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// On-disk form of the graph: nodes plus each node's outgoing edge list
+#[derive(Serialize, Deserialize)]
+struct GraphSnapshot {
+    nodes: HashMap<String, GraphNode>,
+    adjacency: BTreeMap<String, Vec<GraphEdge>>,
+    last_maintenance: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl GraphBuilder {
@@ -47,8 +86,86 @@ impl GraphBuilder {
         Self {
             nodes: HashMap::new(),
             edges: Vec::new(),
+            adjacency: HashMap::new(),
             similarity_threshold,
+            last_maintenance: None,
+        }
+    }
+
+    /// Load a graph previously written with `save`
+    pub fn load(path: &Path, similarity_threshold: f32) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let snapshot: GraphSnapshot = bincode::deserialize(&bytes)
+            .map_err(|e| anyhow!("Failed to decode graph at {:?}: {}", path, e))?;
+
+        let mut graph = Self::new(similarity_threshold);
+        graph.nodes = snapshot.nodes;
+        graph.last_maintenance = snapshot.last_maintenance;
+        for edge in snapshot.adjacency.into_values().flatten() {
+            graph.add_edge(edge);
         }
+        Ok(graph)
+    }
+
+    /// Persist the graph, replacing any previous file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut adjacency: BTreeMap<String, Vec<GraphEdge>> = BTreeMap::new();
+        for edge in &self.edges {
+            adjacency.entry(edge.from.clone()).or_default().push(edge.clone());
+        }
+
+        let snapshot = GraphSnapshot {
+            nodes: self.nodes.clone(),
+            adjacency,
+            last_maintenance: self.last_maintenance,
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bincode::serialize(&snapshot)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub(super) fn add_edge(&mut self, edge: GraphEdge) {
+        let index = self.edges.len();
+        self.adjacency.entry(edge.from.clone()).or_default().push(index);
+        if edge.to != edge.from {
+            self.adjacency.entry(edge.to.clone()).or_default().push(index);
+        }
+        self.edges.push(edge);
+    }
+
+    /// Re-index after edges were removed or reordered
+    pub(super) fn rebuild_adjacency(&mut self) {
+        let edges = std::mem::take(&mut self.edges);
+        self.adjacency.clear();
+        for edge in edges {
+            self.add_edge(edge);
+        }
+    }
+
+    /// Drop chunk nodes, and every edge touching them, when their chunks are replaced
+    pub fn remove_chunks(&mut self, chunk_ids: &HashSet<String>) {
+        // A document ingested for the first time has nothing to remove; skip the full pass
+        if !chunk_ids.iter().any(|id| self.nodes.contains_key(id)) {
+            return;
+        }
+        self.nodes.retain(|id, _| !chunk_ids.contains(id));
+        self.edges.retain(|edge| !chunk_ids.contains(&edge.from) && !chunk_ids.contains(&edge.to));
+        self.rebuild_adjacency();
+    }
+
+    /// Edges touching `node_id` in either direction, paired with the node on the other end
+    pub fn neighbors<'a>(&'a self, node_id: &str) -> impl Iterator<Item = (&'a str, &'a GraphEdge)> + 'a {
+        let node_id = node_id.to_string();
+        self.adjacency.get(&node_id)
+            .into_iter()
+            .flatten()
+            .map(move |&i| {
+                let edge = &self.edges[i];
+                let other = if edge.from == node_id { edge.to.as_str() } else { edge.from.as_str() };
+                (other, edge)
+            })
     }
 
     pub fn build_relationships(&mut self, chunks: &[Chunk]) -> Result<()> {
@@ -69,6 +186,9 @@ impl GraphBuilder {
         // Build sequential relationships
         self.build_sequential_relationships(chunks);
 
+        // Link replies to their parent messages
+        self.build_reference_relationships(chunks);
+
         Ok(())
     }
 
@@ -76,6 +196,7 @@ impl GraphBuilder {
         let mut metadata = HashMap::new();
         metadata.insert("source_file".to_string(), chunk.metadata.source_file.clone());
         metadata.insert("chunk_type".to_string(), format!("{:?}", chunk.metadata.chunk_type));
+        metadata.insert("timestamp".to_string(), chunk.metadata.timestamp.to_rfc3339());
 
         if let Some(chapter) = &chunk.metadata.chapter {
             metadata.insert("chapter".to_string(), chapter.clone());
@@ -85,6 +206,13 @@ impl GraphBuilder {
             metadata.insert("section".to_string(), section.clone());
         }
 
+        // Thread identifiers and documented code chunks, used for Reference edges
+        for key in ["message_id", "in_reply_to", "documents"] {
+            if let Some(value) = chunk.metadata.attributes.get(key) {
+                metadata.insert(key.to_string(), value.clone());
+            }
+        }
+
         let node = GraphNode {
             id: chunk.id.clone(),
             node_type: NodeType::Chunk,
@@ -107,7 +235,7 @@ impl GraphBuilder {
                         edge_type: EdgeType::Similarity,
                         weight: similarity,
                     };
-                    self.edges.push(edge);
+                    self.add_edge(edge);
                 }
             }
         }
@@ -181,7 +309,7 @@ impl GraphBuilder {
                     edge_type: EdgeType::Contains,
                     weight: 1.0,
                 };
-                self.edges.push(edge);
+                self.add_edge(edge);
             }
         }
     }
@@ -227,7 +355,7 @@ impl GraphBuilder {
                     edge_type: EdgeType::PartOf,
                     weight: 1.0,
                 };
-                self.edges.push(edge);
+                self.add_edge(edge);
             }
         }
 
@@ -250,7 +378,7 @@ impl GraphBuilder {
                     edge_type: EdgeType::PartOf,
                     weight: 1.0,
                 };
-                self.edges.push(edge);
+                self.add_edge(edge);
             }
         }
     }
@@ -259,7 +387,8 @@ impl GraphBuilder {
         // Group chunks by source file and sort by position
         let mut file_chunks: HashMap<String, Vec<&Chunk>> = HashMap::new();
 
-        for chunk in chunks {
+        // Extracted doc comments sit beside their code chunk, not between code chunks
+        for chunk in chunks.iter().filter(|c| !c.metadata.attributes.contains_key("documents")) {
             file_chunks.entry(chunk.metadata.source_file.clone())
                        .or_insert_with(Vec::new)
                        .push(chunk);
@@ -276,11 +405,55 @@ impl GraphBuilder {
                     edge_type: EdgeType::Sequential,
                     weight: 1.0,
                 };
-                self.edges.push(edge);
+                self.add_edge(edge);
             }
         }
     }
 
+    /// Add a Reference edge from each reply chunk to the first chunk of the message it answers,
+    /// and from each extracted doc comment chunk to the code chunk it documents. Replies are
+    /// resolved against the whole graph, so a reply ingested before its parent is linked once
+    /// the parent arrives.
+    fn build_reference_relationships(&mut self, chunks: &[Chunk]) {
+        let new_ids: std::collections::HashSet<&str> = chunks.iter().map(|c| c.id.as_str()).collect();
+
+        // Message id -> representative chunk node (smallest id, for determinism)
+        let mut messages: HashMap<String, String> = HashMap::new();
+        for node in self.nodes.values() {
+            if let Some(message_id) = node.metadata.get("message_id") {
+                let entry = messages.entry(message_id.clone()).or_insert_with(|| node.id.clone());
+                if node.id < *entry {
+                    *entry = node.id.clone();
+                }
+            }
+        }
+
+        let mut links = Vec::new();
+        for node in self.nodes.values() {
+            let Some(parent) = node.metadata.get("in_reply_to").and_then(|id| messages.get(id)) else {
+                continue;
+            };
+            if *parent != node.id && (new_ids.contains(node.id.as_str()) || new_ids.contains(parent.as_str())) {
+                links.push((node.id.clone(), parent.clone()));
+            }
+        }
+        for chunk in chunks {
+            if let Some(code) = chunk.metadata.attributes.get("documents").filter(|id| self.nodes.contains_key(id.as_str())) {
+                links.push((chunk.id.clone(), code.clone()));
+            }
+        }
+
+        links.sort();
+        for (from, to) in links {
+            self.add_edge(GraphEdge {
+                from,
+                to,
+                edge_type: EdgeType::Reference,
+                weight: 1.0,
+            });
+        }
+    }
+
     pub fn find_related_chunks(&self, chunk_id: &str, max_depth: usize) -> Vec<String> {
         let mut related = Vec::new();
         let mut visited = std::collections::HashSet::new();
@@ -294,22 +467,14 @@ impl GraphBuilder {
                 continue;
             }
 
-            for edge in &self.edges {
-                let next_id = if edge.from == current_id {
-                    &edge.to
-                } else if edge.to == current_id {
-                    &edge.from
-                } else {
-                    continue;
-                };
-
+            for (next_id, _) in self.neighbors(&current_id) {
                 if !visited.contains(next_id) {
-                    visited.insert(next_id.clone());
+                    visited.insert(next_id.to_string());
 
                     if let Some(node) = self.nodes.get(next_id) {
                         if matches!(node.node_type, NodeType::Chunk) {
-                            related.push(next_id.clone());
-                            queue.push_back((next_id.clone(), depth + 1));
+                            related.push(next_id.to_string());
+                            queue.push_back((next_id.to_string(), depth + 1));
                         }
                     }
                 }
@@ -319,6 +484,29 @@ impl GraphBuilder {
         related
     }
 
+    /// Up to `count` chunk ids before and after `chunk_id` along Sequential edges, nearest first
+    pub fn sequential_context(&self, chunk_id: &str, count: usize) -> (Vec<String>, Vec<String>) {
+        let walk = |forward: bool| {
+            let mut ids: Vec<String> = Vec::new();
+            let mut current = chunk_id.to_string();
+            while ids.len() < count {
+                let next = self.neighbors(&current)
+                    .find(|(_, edge)| edge.edge_type == EdgeType::Sequential && if forward { edge.from == current } else { edge.to == current })
+                    .map(|(other, _)| other.to_string());
+                match next {
+                    Some(id) if id != chunk_id && !ids.contains(&id) => {
+                        ids.push(id.clone());
+                        current = id;
+                    }
+                    _ => break,
+                }
+            }
+            ids
+        };
+
+        (walk(false), walk(true))
+    }
+
     pub fn get_nodes(&self) -> &HashMap<String, GraphNode> {
         &self.nodes
     }
@@ -326,4 +514,36 @@ impl GraphBuilder {
     pub fn get_edges(&self) -> &[GraphEdge] {
         &self.edges
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjacency_survives_save_and_load() {
+        let mut graph = GraphBuilder::new(0.7);
+        for id in ["a", "b", "c"] {
+            graph.nodes.insert(id.to_string(), GraphNode {
+                id: id.to_string(),
+                node_type: NodeType::Chunk,
+                content: String::new(),
+                metadata: HashMap::new(),
+            });
+        }
+        graph.add_edge(GraphEdge { from: "a".to_string(), to: "b".to_string(), edge_type: EdgeType::Sequential, weight: 1.0 });
+        graph.add_edge(GraphEdge { from: "b".to_string(), to: "c".to_string(), edge_type: EdgeType::Sequential, weight: 1.0 });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.bin");
+        graph.save(&path).unwrap();
+        let loaded = GraphBuilder::load(&path, 0.7).unwrap();
+
+        assert_eq!(loaded.get_edges().len(), 2);
+        assert_eq!(loaded.neighbors("b").count(), 2);
+        let mut related = loaded.find_related_chunks("a", 2);
+        related.sort();
+        assert_eq!(related, vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(loaded.sequential_context("c", 5), (vec!["b".to_string(), "a".to_string()], vec![]));
+    }
 }
\ No newline at end of file
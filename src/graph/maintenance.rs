@@ -0,0 +1,172 @@
+use super::builder::{EdgeType, GraphBuilder, NodeType};
+use crate::config::GraphConfig;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Thresholds for a graph maintenance pass
+#[derive(Debug, Clone)]
+pub struct MaintenancePolicy {
+    pub min_weight: f32,
+    pub max_connections: usize,
+    pub stale_after: chrono::Duration,
+    pub decay_half_life_days: f32,
+}
+
+impl MaintenancePolicy {
+    pub fn from_config(config: &GraphConfig) -> Self {
+        Self {
+            min_weight: config.prune_min_weight,
+            max_connections: config.max_connections,
+            stale_after: chrono::Duration::days(config.stale_after_days as i64),
+            decay_half_life_days: config.decay_half_life_days,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MaintenanceReport {
+    pub decayed: usize,
+    pub pruned_below_weight: usize,
+    pub pruned_over_cap: usize,
+    pub remaining_edges: usize,
+}
+
+impl GraphBuilder {
+    /// Decay, prune and cap similarity edges. Structural edges (part-of, sequential,
+    /// contains, reference) are never touched.
+    ///
+    /// Decay is proportional to the time since the previous pass, so running more often
+    /// does not age stale content faster. The first pass only records its timestamp.
+    pub fn run_maintenance(&mut self, policy: &MaintenancePolicy, now: DateTime<Utc>) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+
+        let elapsed_days = self.last_maintenance
+            .map(|last| (now - last).num_seconds().max(0) as f32 / 86_400.0)
+            .unwrap_or(0.0);
+        self.last_maintenance = Some(now);
+
+        // 1. Decay similarity edges that touch stale chunks
+        if elapsed_days > 0.0 && policy.decay_half_life_days > 0.0 {
+            let stale = self.stale_chunks(now - policy.stale_after);
+            let factor = 0.5f32.powf(elapsed_days / policy.decay_half_life_days);
+
+            for edge in &mut self.edges {
+                if matches!(edge.edge_type, EdgeType::Similarity)
+                    && (stale.contains(&edge.from) || stale.contains(&edge.to))
+                {
+                    edge.weight *= factor;
+                    report.decayed += 1;
+                }
+            }
+        }
+
+        // 2. Drop weak similarity edges
+        let before = self.edges.len();
+        self.edges.retain(|edge| !matches!(edge.edge_type, EdgeType::Similarity) || edge.weight >= policy.min_weight);
+        report.pruned_below_weight = before - self.edges.len();
+
+        // 3. Keep at most max_connections similarity edges per node, strongest first
+        if policy.max_connections > 0 {
+            let mut order: Vec<usize> = (0..self.edges.len())
+                .filter(|&i| matches!(self.edges[i].edge_type, EdgeType::Similarity))
+                .collect();
+            order.sort_by(|&a, &b| self.edges[b].weight.partial_cmp(&self.edges[a].weight).unwrap_or(std::cmp::Ordering::Equal));
+
+            let mut degree: HashMap<&str, usize> = HashMap::new();
+            let mut dropped = HashSet::new();
+            for i in order {
+                let edge = &self.edges[i];
+                let from_degree = degree.get(edge.from.as_str()).copied().unwrap_or(0);
+                let to_degree = degree.get(edge.to.as_str()).copied().unwrap_or(0);
+
+                if from_degree >= policy.max_connections || to_degree >= policy.max_connections {
+                    dropped.insert(i);
+                } else {
+                    degree.insert(edge.from.as_str(), from_degree + 1);
+                    degree.insert(edge.to.as_str(), to_degree + 1);
+                }
+            }
+
+            report.pruned_over_cap = dropped.len();
+            let mut index = 0;
+            self.edges.retain(|_| {
+                let keep = !dropped.contains(&index);
+                index += 1;
+                keep
+            });
+        }
+
+        self.rebuild_adjacency();
+        report.remaining_edges = self.edges.len();
+        report
+    }
+
+    /// Chunk nodes last ingested before `cutoff`
+    fn stale_chunks(&self, cutoff: DateTime<Utc>) -> HashSet<String> {
+        self.nodes.values()
+            .filter(|node| matches!(node.node_type, NodeType::Chunk))
+            .filter(|node| {
+                node.metadata.get("timestamp")
+                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                    .is_some_and(|ts| ts.with_timezone(&Utc) < cutoff)
+            })
+            .map(|node| node.id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphEdge, GraphNode};
+
+    fn chunk_node(id: &str, timestamp: DateTime<Utc>) -> GraphNode {
+        let mut metadata = HashMap::new();
+        metadata.insert("timestamp".to_string(), timestamp.to_rfc3339());
+        GraphNode { id: id.to_string(), node_type: NodeType::Chunk, content: String::new(), metadata }
+    }
+
+    fn similarity(from: &str, to: &str, weight: f32) -> GraphEdge {
+        GraphEdge { from: from.to_string(), to: to.to_string(), edge_type: EdgeType::Similarity, weight }
+    }
+
+    #[test]
+    fn test_prune_cap_and_decay() {
+        let now = Utc::now();
+        let old = now - chrono::Duration::days(365);
+
+        let mut graph = GraphBuilder::new(0.7);
+        for id in ["a", "b", "c", "d"] {
+            graph.nodes.insert(id.to_string(), chunk_node(id, now));
+        }
+        graph.nodes.insert("stale".to_string(), chunk_node("stale", old));
+        graph.edges = vec![
+            similarity("a", "b", 0.95),
+            similarity("a", "c", 0.90),
+            similarity("a", "d", 0.85),  // Over the cap of 2 for "a"
+            similarity("b", "c", 0.30),  // Below min_weight
+            similarity("c", "stale", 0.80),
+            GraphEdge { from: "a".to_string(), to: "doc".to_string(), edge_type: EdgeType::PartOf, weight: 1.0 },
+        ];
+
+        let policy = MaintenancePolicy {
+            min_weight: 0.5,
+            max_connections: 2,
+            stale_after: chrono::Duration::days(30),
+            decay_half_life_days: 20.0,
+        };
+
+        let first = graph.run_maintenance(&policy, now);
+        assert_eq!(first.decayed, 0);
+        assert_eq!(first.pruned_below_weight, 1);
+        assert_eq!(first.pruned_over_cap, 1);
+        assert_eq!(first.remaining_edges, 4);
+
+        // One half-life later the stale edge falls to 0.4 and is pruned
+        let second = graph.run_maintenance(&policy, now + chrono::Duration::days(20));
+        assert_eq!(second.decayed, 1);
+        assert_eq!(second.pruned_below_weight, 1);
+        assert!(graph.get_edges().iter().any(|e| matches!(e.edge_type, EdgeType::PartOf)));
+        assert!(!graph.get_edges().iter().any(|e| e.to == "stale"));
+    }
+}
\ No newline at end of file
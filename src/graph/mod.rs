@@ -1,4 +1,10 @@
 pub mod builder;
 pub mod relationships;
+pub mod maintenance;
+pub mod query;
+pub mod shared;
 
-pub use builder::*;
\ No newline at end of file
+pub use builder::*;
+pub use maintenance::*;
+pub use query::*;
+pub use shared::*;
\ No newline at end of file
@@ -0,0 +1,179 @@
+use super::builder::{EdgeType, GraphBuilder, NodeType};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Structured graph query, e.g.
+/// `{"start": "<chunk id>", "edge_types": ["reference"], "chunk_types": ["code"], "max_depth": 2}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GraphQuery {
+    pub start: String,
+    pub max_depth: usize,
+    pub node_types: Vec<String>,   // Only return these node types (chunk, word, chapter, document)
+    pub chunk_types: Vec<String>,  // Only return chunks of these types (code, markdown, ...)
+    pub edge_types: Vec<String>,   // Only traverse these edges (similarity, contains, part_of, sequential, reference)
+    pub min_weight: f32,           // Skip edges lighter than this
+    pub limit: usize,
+}
+
+impl Default for GraphQuery {
+    fn default() -> Self {
+        Self {
+            start: String::new(),
+            max_depth: 1,
+            node_types: Vec::new(),
+            chunk_types: Vec::new(),
+            edge_types: Vec::new(),
+            min_weight: 0.0,
+            limit: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphQueryHit {
+    pub id: String,
+    pub node_type: NodeType,
+    pub depth: usize,
+    pub via: EdgeType,  // Edge type of the last hop
+    pub content: String,
+    pub metadata: HashMap<String, String>,
+}
+
+/// A query with its type names resolved, ready to run against the adjacency lists
+struct CompiledQuery {
+    node_types: Vec<NodeType>,
+    chunk_types: Vec<String>,
+    edge_types: Vec<EdgeType>,
+}
+
+impl GraphQuery {
+    fn compile(&self) -> Result<CompiledQuery> {
+        let node_types = self.node_types.iter()
+            .map(|name| NodeType::parse(name).ok_or_else(|| anyhow!("Unknown node type '{}'", name)))
+            .collect::<Result<Vec<_>>>()?;
+        let edge_types = self.edge_types.iter()
+            .map(|name| EdgeType::parse(name).ok_or_else(|| anyhow!("Unknown edge type '{}'", name)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CompiledQuery {
+            node_types,
+            chunk_types: self.chunk_types.iter().map(|t| t.to_lowercase()).collect(),
+            edge_types,
+        })
+    }
+}
+
+impl CompiledQuery {
+    fn follows(&self, edge_type: &EdgeType) -> bool {
+        self.edge_types.is_empty() || self.edge_types.contains(edge_type)
+    }
+
+    fn returns(&self, node_type: &NodeType, metadata: &HashMap<String, String>) -> bool {
+        if !self.node_types.is_empty() && !self.node_types.contains(node_type) {
+            return false;
+        }
+        if self.chunk_types.is_empty() {
+            return true;
+        }
+        matches!(node_type, NodeType::Chunk)
+            && metadata.get("chunk_type").is_some_and(|t| self.chunk_types.contains(&t.to_lowercase()))
+    }
+}
+
+impl GraphBuilder {
+    /// Breadth-first traversal from `query.start` over the permitted edges. Every node within
+    /// `max_depth` hops is visited; only nodes passing the type filters are returned.
+    pub fn query(&self, query: &GraphQuery) -> Result<Vec<GraphQueryHit>> {
+        let compiled = query.compile()?;
+        if !self.nodes.contains_key(&query.start) {
+            return Err(anyhow!("Start node '{}' is not in the graph", query.start));
+        }
+
+        let mut hits = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(query.start.clone());
+        queue.push_back((query.start.clone(), 0));
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth >= query.max_depth {
+                continue;
+            }
+
+            for (next_id, edge) in self.neighbors(&current) {
+                if edge.weight < query.min_weight || !compiled.follows(&edge.edge_type) || visited.contains(next_id) {
+                    continue;
+                }
+                visited.insert(next_id.to_string());
+
+                if let Some(node) = self.nodes.get(next_id) {
+                    if compiled.returns(&node.node_type, &node.metadata) {
+                        hits.push(GraphQueryHit {
+                            id: node.id.clone(),
+                            node_type: node.node_type.clone(),
+                            depth: depth + 1,
+                            via: edge.edge_type.clone(),
+                            content: node.content.clone(),
+                            metadata: node.metadata.clone(),
+                        });
+                        if hits.len() >= query.limit {
+                            return Ok(hits);
+                        }
+                    }
+                }
+                queue.push_back((next_id.to_string(), depth + 1));
+            }
+        }
+
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphEdge, GraphNode};
+
+    fn node(id: &str, node_type: NodeType, chunk_type: &str) -> GraphNode {
+        let mut metadata = HashMap::new();
+        metadata.insert("chunk_type".to_string(), chunk_type.to_string());
+        GraphNode { id: id.to_string(), node_type, content: id.to_string(), metadata }
+    }
+
+    fn edge(from: &str, to: &str, edge_type: EdgeType) -> GraphEdge {
+        GraphEdge { from: from.to_string(), to: to.to_string(), edge_type, weight: 1.0 }
+    }
+
+    #[test]
+    fn test_code_chunks_within_two_reference_hops() {
+        let mut graph = GraphBuilder::new(0.7);
+        graph.nodes.insert("spec".to_string(), node("spec", NodeType::Chunk, "Markdown"));
+        graph.nodes.insert("driver".to_string(), node("driver", NodeType::Chunk, "Code"));
+        graph.nodes.insert("notes".to_string(), node("notes", NodeType::Chunk, "Text"));
+        graph.nodes.insert("monitor".to_string(), node("monitor", NodeType::Chunk, "Code"));
+        graph.nodes.insert("far".to_string(), node("far", NodeType::Chunk, "Code"));
+        graph.edges = vec![
+            edge("spec", "notes", EdgeType::Reference),
+            edge("notes", "monitor", EdgeType::Reference),
+            edge("spec", "driver", EdgeType::Reference),
+            edge("spec", "far", EdgeType::Sequential),  // Wrong edge type
+        ];
+        graph.rebuild_adjacency();
+
+        let query: GraphQuery = serde_json::from_value(serde_json::json!({
+            "start": "spec",
+            "edge_types": ["reference"],
+            "chunk_types": ["code"],
+            "max_depth": 2
+        })).unwrap();
+
+        let mut ids: Vec<String> = graph.query(&query).unwrap().into_iter().map(|hit| hit.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["driver".to_string(), "monitor".to_string()]);
+
+        let bad = GraphQuery { start: "spec".to_string(), edge_types: vec!["cites".to_string()], ..Default::default() };
+        assert!(graph.query(&bad).is_err());
+    }
+}
\ No newline at end of file
@@ -15,11 +15,32 @@ impl Ord for OrderedFloat {
 pub struct RelationshipAnalyzer {
     nodes: HashMap<String, GraphNode>,
     edges: Vec<GraphEdge>,
+    adjacency: HashMap<String, Vec<usize>>,  // Node id -> indices into `edges`, both directions
 }
 
 impl RelationshipAnalyzer {
     pub fn new(nodes: HashMap<String, GraphNode>, edges: Vec<GraphEdge>) -> Self {
-        Self { nodes, edges }
+        let mut adjacency: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, edge) in edges.iter().enumerate() {
+            adjacency.entry(edge.from.clone()).or_default().push(i);
+            if edge.to != edge.from {
+                adjacency.entry(edge.to.clone()).or_default().push(i);
+            }
+        }
+
+        Self { nodes, edges, adjacency }
+    }
+
+    /// Edges touching `node_id`, paired with the node on the other end
+    fn neighbors<'a>(&'a self, node_id: &'a str) -> impl Iterator<Item = (&'a String, &'a GraphEdge)> + 'a {
+        self.adjacency.get(node_id)
+            .into_iter()
+            .flatten()
+            .map(move |&i| {
+                let edge = &self.edges[i];
+                let other = if edge.from == node_id { &edge.to } else { &edge.from };
+                (other, edge)
+            })
     }
 
     pub fn find_shortest_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
@@ -45,15 +66,7 @@ impl RelationshipAnalyzer {
                 continue;
             }
 
-            for edge in &self.edges {
-                let neighbor = if edge.from == current_node {
-                    &edge.to
-                } else if edge.to == current_node {
-                    &edge.from
-                } else {
-                    continue;
-                };
-
+            for (neighbor, edge) in self.neighbors(&current_node) {
                 let distance = current_distance + (1.0 / edge.weight); // Invert weight for shortest path
 
                 if distance < *distances.get(neighbor).unwrap_or(&f32::INFINITY) {
@@ -85,13 +98,9 @@ impl RelationshipAnalyzer {
     pub fn get_related_by_type(&self, node_id: &str, edge_type: EdgeType) -> Vec<String> {
         let mut related = Vec::new();
 
-        for edge in &self.edges {
+        for (neighbor, edge) in self.neighbors(node_id) {
             if std::mem::discriminant(&edge.edge_type) == std::mem::discriminant(&edge_type) {
-                if edge.from == node_id {
-                    related.push(edge.to.clone());
-                } else if edge.to == node_id {
-                    related.push(edge.from.clone());
-                }
+                related.push(neighbor.clone());
             }
         }
 
@@ -101,10 +110,8 @@ impl RelationshipAnalyzer {
     pub fn calculate_centrality(&self, node_id: &str) -> f32 {
         let mut centrality = 0.0;
 
-        for edge in &self.edges {
-            if edge.from == node_id || edge.to == node_id {
-                centrality += edge.weight;
-            }
+        for (_, edge) in self.neighbors(node_id) {
+            centrality += edge.weight;
         }
 
         centrality
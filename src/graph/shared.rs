@@ -0,0 +1,169 @@
+use super::GraphBuilder;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// The relationship graph as searches see it. Readers take an immutable snapshot and keep it
+/// for as long as they need; writers build the next graph off to the side and swap it in, so
+/// a search never waits on, or observes half of, an ingest, maintenance run or rebuild.
+pub struct SharedGraph {
+    current: RwLock<Arc<GraphBuilder>>,  // Only held to clone or replace the Arc
+    writer: Mutex<Batch>,                // One writer at a time, so no update is lost
+    path: Option<PathBuf>,               // Where every published graph is saved
+}
+
+/// Updates made while a batch is open go to one working copy, published when the last batch
+/// closes, so a bulk run copies and saves the graph once rather than once per document
+#[derive(Default)]
+struct Batch {
+    open: usize,
+    working: Option<GraphBuilder>,
+}
+
+/// Exclusive right to replace the graph, held from reading the base graph to publishing
+pub struct GraphWriter<'a> {
+    shared: &'a SharedGraph,
+    batch: MutexGuard<'a, Batch>,
+}
+
+impl SharedGraph {
+    pub fn new(graph: GraphBuilder) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(graph)),
+            writer: Mutex::new(Batch::default()),
+            path: None,
+        }
+    }
+
+    /// Save every published graph to `path`
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// The latest published graph; later writes do not affect it
+    pub fn snapshot(&self) -> Arc<GraphBuilder> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Wait for other writers, e.g. to build a replacement graph with `GraphWriter::publish`
+    pub async fn write(&self) -> GraphWriter<'_> {
+        GraphWriter { shared: self, batch: self.writer.lock().await }
+    }
+
+    /// Apply `change` to a copy of the current graph and publish the copy if it succeeds.
+    /// While a batch is open the change goes to the batch's working copy instead.
+    pub async fn update<T>(&self, change: impl FnOnce(&mut GraphBuilder) -> Result<T>) -> Result<T> {
+        let mut writer = self.write().await;
+        if writer.batch.open > 0 {
+            let current = writer.current();
+            return change(writer.batch.working.get_or_insert_with(|| (*current).clone()));
+        }
+        let mut next = (*writer.current()).clone();
+        let output = change(&mut next)?;
+        writer.publish(next)?;
+        Ok(output)
+    }
+
+    /// Hold back updates until the matching `end_batch`; searches see the graph as it was
+    /// until then. Batches nest.
+    pub async fn begin_batch(&self) {
+        self.writer.lock().await.open += 1;
+    }
+
+    /// Close a batch, publishing its working copy once no batch is open
+    pub async fn end_batch(&self) -> Result<()> {
+        let mut writer = self.write().await;
+        writer.batch.open = writer.batch.open.saturating_sub(1);
+        match writer.batch.working.take() {
+            Some(graph) if writer.batch.open == 0 => writer.publish(graph),
+            working => {
+                writer.batch.working = working;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl GraphWriter<'_> {
+    /// The graph this writer replaces; no other writer can publish in the meantime
+    pub fn current(&self) -> Arc<GraphBuilder> {
+        self.shared.snapshot()
+    }
+
+    /// Save `graph` and make it the current graph. It replaces any open batch's working
+    /// copy, whose later updates start from it.
+    pub fn publish(&mut self, graph: GraphBuilder) -> Result<()> {
+        if let Some(path) = &self.shared.path {
+            graph.save(path)?;
+        }
+        self.batch.working = None;
+        *self.shared.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(graph);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{EdgeType, GraphEdge};
+
+    fn edge(from: &str, to: &str) -> GraphEdge {
+        GraphEdge { from: from.to_string(), to: to.to_string(), edge_type: EdgeType::Reference, weight: 1.0 }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_snapshots_survive_concurrent_writes() {
+        let shared = Arc::new(SharedGraph::new(GraphBuilder::new(0.7)));
+        let before = shared.snapshot();
+
+        let writers: Vec<_> = (0..8).map(|i| {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                shared.update(|graph| {
+                    graph.add_edge(edge(&format!("n{}", i), "hub"));
+                    Ok(())
+                }).await
+            })
+        }).collect();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+
+        assert!(before.get_edges().is_empty());
+        assert_eq!(shared.snapshot().get_edges().len(), 8);
+        assert_eq!(shared.snapshot().neighbors("hub").count(), 8);
+
+        // A failed update publishes nothing, and a rebuild replaces the graph wholesale
+        assert!(shared.update::<()>(|graph| { graph.add_edge(edge("x", "y")); anyhow::bail!("disk full") }).await.is_err());
+        assert_eq!(shared.snapshot().get_edges().len(), 8);
+        let mut writer = shared.write().await;
+        let during = writer.current();
+        writer.publish(GraphBuilder::new(0.7)).unwrap();
+        drop(writer);
+        assert_eq!(during.get_edges().len(), 8);
+        assert!(shared.snapshot().get_edges().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batched_updates_publish_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.bin");
+        let shared = SharedGraph::new(GraphBuilder::new(0.7)).with_path(path.clone());
+
+        shared.begin_batch().await;
+        for i in 0..3 {
+            shared.update(|graph| {
+                graph.add_edge(edge(&format!("n{}", i), "hub"));
+                Ok(())
+            }).await.unwrap();
+        }
+        assert!(shared.snapshot().get_edges().is_empty());
+        assert!(!path.exists());
+
+        shared.end_batch().await.unwrap();
+        assert_eq!(shared.snapshot().get_edges().len(), 3);
+        assert_eq!(GraphBuilder::load(&path, 0.7).unwrap().get_edges().len(), 3);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::collections::{HashSet, VecDeque};
+use std::sync::OnceLock;
+use url::Url;
+
+/// How `ingest_url` discovers more pages from the starting URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrawlMode {
+    Sitemap,  // Pages listed in the site's sitemap.xml (or the sitemap URL given)
+    Links,    // Same-origin links followed breadth-first up to a depth
+}
+
+impl CrawlMode {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sitemap" => Ok(CrawlMode::Sitemap),
+            "links" => Ok(CrawlMode::Links),
+            other => Err(anyhow!("Unknown crawl mode '{}' (expected sitemap or links)", other)),
+        }
+    }
+}
+
+/// Pages still to visit in a crawl, restricted to the origin of the URL it was created
+/// for. Pages are handed out breadth-first, each at most once, until `max_pages` have been
+/// visited.
+pub struct CrawlFrontier {
+    origin: url::Origin,
+    max_depth: usize,
+    max_pages: usize,
+    visited: usize,
+    queue: VecDeque<(String, usize)>,
+    seen: HashSet<String>,
+}
+
+impl CrawlFrontier {
+    pub fn new(site: &str, max_depth: usize, max_pages: usize) -> Result<Self> {
+        let site = Url::parse(site).map_err(|e| anyhow!("Invalid URL {}: {}", site, e))?;
+        Ok(Self {
+            origin: site.origin(),
+            max_depth,
+            max_pages,
+            visited: 0,
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Queue a page at `depth`; other origins, pages beyond the depth limit and pages
+    /// already queued are ignored
+    pub fn push(&mut self, url: &str, depth: usize) {
+        let Some(url) = Self::normalize(url) else { return };
+        if depth > self.max_depth || url.origin() != self.origin {
+            return;
+        }
+        if self.seen.insert(url.to_string()) {
+            self.queue.push_back((url.to_string(), depth));
+        }
+    }
+
+    pub fn next_page(&mut self) -> Option<(String, usize)> {
+        if self.visited >= self.max_pages {
+            return None;
+        }
+        let next = self.queue.pop_front()?;
+        self.visited += 1;
+        Some(next)
+    }
+
+    pub fn visited(&self) -> usize {
+        self.visited
+    }
+
+    /// `#fragment`s name parts of one page, so they are dropped before comparing URLs
+    fn normalize(url: &str) -> Option<Url> {
+        let mut url = Url::parse(url).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        url.set_fragment(None);
+        Some(url)
+    }
+}
+
+/// Targets of `<a href>` links in a page, resolved against the page URL (or its `<base href>`)
+pub fn extract_links(html: &str, page_url: &str) -> Vec<String> {
+    static BASE: OnceLock<Regex> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let base_tag = BASE.get_or_init(|| Regex::new(r#"(?is)<base\s[^>]*href\s*=\s*["']([^"']+)["']"#).unwrap());
+    let link = LINK.get_or_init(|| Regex::new(r#"(?is)<a\s[^>]*href\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+
+    let Ok(page) = Url::parse(page_url) else { return Vec::new() };
+    let base = base_tag.captures(html)
+        .and_then(|captures| page.join(captures[1].trim()).ok())
+        .unwrap_or(page);
+
+    link.captures_iter(html)
+        .filter_map(|captures| captures.get(1).or(captures.get(2)).or(captures.get(3)))
+        .map(|href| href.as_str().trim().replace("&amp;", "&"))
+        .filter(|href| !href.is_empty() && !href.starts_with('#') && !href.starts_with("mailto:") && !href.starts_with("javascript:"))
+        .filter_map(|href| base.join(&href).ok())
+        .map(|url| url.to_string())
+        .collect()
+}
+
+/// `<loc>` entries of a sitemap, split into pages and nested sitemaps (from a sitemap index)
+pub fn sitemap_locations(xml: &str) -> (Vec<String>, Vec<String>) {
+    static LOC: OnceLock<Regex> = OnceLock::new();
+    let loc = LOC.get_or_init(|| Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").unwrap());
+
+    let locations = loc.captures_iter(xml).map(|captures| captures[1].replace("&amp;", "&"));
+    if xml.contains("<sitemapindex") {
+        (Vec::new(), locations.collect())
+    } else {
+        (locations.collect(), Vec::new())
+    }
+}
+
+/// Where a site's sitemap is expected when the crawl starts from an ordinary page
+pub fn default_sitemap_url(start: &str) -> Result<String> {
+    if start.to_lowercase().split(['?', '#']).next().is_some_and(|path| path.ends_with(".xml")) {
+        return Ok(start.to_string());
+    }
+    let start = Url::parse(start).map_err(|e| anyhow!("Invalid URL {}: {}", start, e))?;
+    Ok(start.join("/sitemap.xml")?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_stay_on_origin_within_limits() {
+        let html = r##"<a href="/guide/dma.html#reset">DMA</a> <a href='irq.html'>IRQ</a>
+            <a href=https://other.example.com/x>x</a> <a href="#top">top</a> <a href="mailto:a@b.c">mail</a>
+            <a class="nav" href="../index.html?v=1&amp;lang=en">home</a>"##;
+        let links = extract_links(html, "https://docs.example.com/guide/intro.html");
+        assert_eq!(links, vec![
+            "https://docs.example.com/guide/dma.html#reset",
+            "https://docs.example.com/guide/irq.html",
+            "https://other.example.com/x",
+            "https://docs.example.com/index.html?v=1&lang=en",
+        ]);
+
+        let mut frontier = CrawlFrontier::new("https://docs.example.com/guide/intro.html", 1, 3).unwrap();
+        frontier.push("https://docs.example.com/guide/intro.html", 0);
+        assert_eq!(frontier.next_page(), Some(("https://docs.example.com/guide/intro.html".to_string(), 0)));
+        for link in &links {
+            frontier.push(link, 1);
+        }
+        frontier.push("https://docs.example.com/guide/dma.html", 1);   // Same page without the fragment
+        frontier.push("https://docs.example.com/deep.html", 2);        // Beyond max_depth
+        assert_eq!(frontier.next_page().unwrap().0, "https://docs.example.com/guide/dma.html");
+        assert_eq!(frontier.next_page().unwrap().0, "https://docs.example.com/guide/irq.html");
+        assert_eq!(frontier.next_page(), None);  // max_pages reached
+        assert_eq!(frontier.visited(), 3);
+
+        let (pages, nested) = sitemap_locations("<urlset><url><loc> https://docs.example.com/a?x=1&amp;y=2 </loc></url></urlset>");
+        assert_eq!((pages, nested), (vec!["https://docs.example.com/a?x=1&y=2".to_string()], vec![]));
+        assert_eq!(default_sitemap_url("https://docs.example.com/guide/intro.html").unwrap(), "https://docs.example.com/sitemap.xml");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,121 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::io::Read;
+use std::time::Duration;
+
+const MAX_REDIRECTS: u32 = 5;
+
+/// A web page or file downloaded for ingestion
+pub struct FetchedDocument {
+    pub url: String,                    // Final URL after redirects
+    pub content_type: Option<String>,   // Media type without parameters, e.g. `text/html`
+    pub etag: Option<String>,           // Validator from the ETag header, quotes removed
+    pub bytes: Vec<u8>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl FetchedDocument {
+    /// Document type implied by the Content-Type header, falling back to sniffing the body.
+    /// None means neither is conclusive and the URL's extension should decide.
+    pub fn detected_type(&self) -> Option<&'static str> {
+        let from_header = match self.content_type.as_deref() {
+            Some("text/html") | Some("application/xhtml+xml") => Some("html"),
+            Some("application/pdf") => Some("pdf"),
+            Some("text/markdown") | Some("text/x-markdown") => Some("markdown"),
+            Some("application/json") => Some("json"),
+            Some("text/xml") | Some("application/xml") => Some("xml"),
+            Some("text/csv") => Some("csv"),
+            Some("application/yaml") | Some("application/x-yaml") | Some("text/yaml") => Some("yaml"),
+            _ => None,
+        };
+        from_header.or_else(|| self.sniff_type())
+    }
+
+    fn sniff_type(&self) -> Option<&'static str> {
+        if self.bytes.starts_with(b"%PDF-") {
+            return Some("pdf");
+        }
+        let head = String::from_utf8_lossy(&self.bytes[..self.bytes.len().min(512)]).trim_start().to_lowercase();
+        (head.starts_with("<!doctype html") || head.starts_with("<html")).then_some("html")
+    }
+}
+
+/// Download `url`, refusing bodies larger than `max_bytes`
+pub fn fetch_url(url: &str, max_bytes: u64, timeout: Duration) -> Result<FetchedDocument> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(anyhow!("Only http:// and https:// URLs can be ingested: {}", url));
+    }
+
+    let response = call(agent(timeout).get(url), url)?;
+    read_response(response, url, max_bytes)
+}
+
+pub(crate) fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .redirects(MAX_REDIRECTS)
+        .user_agent(concat!("rag-mcp-server/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+pub(crate) fn call(request: ureq::Request, url: &str) -> Result<ureq::Response> {
+    request.call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => anyhow!("Fetching {} failed with HTTP {} {}", url, code, response.status_text()),
+        ureq::Error::Transport(transport) => anyhow!("Fetching {} failed: {}", url, transport),
+    })
+}
+
+/// The body of a successful response as a document, refusing bodies larger than `max_bytes`
+pub(crate) fn read_response(response: ureq::Response, url: &str, max_bytes: u64) -> Result<FetchedDocument> {
+    let final_url = response.get_url().to_string();
+    let content_type = response.header("Content-Type")
+        .and_then(|value| value.split(';').next())
+        .map(|media_type| media_type.trim().to_lowercase())
+        .filter(|media_type| !media_type.is_empty());
+    let etag = response.header("ETag").map(strip_etag_quotes);
+
+    let mut bytes = Vec::new();
+    response.into_reader()
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(|e| anyhow!("Failed to read response from {}: {}", url, e))?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(anyhow!(
+            "{} is larger than ingestion.max_file_size_bytes ({}). Pass force=true to override.",
+            url, max_bytes
+        ));
+    }
+
+    Ok(FetchedDocument { url: final_url, content_type, etag, bytes, fetched_at: Utc::now() })
+}
+
+/// `W/"abc"` and `"abc"` as `abc`, so ETags from headers and listings compare equal
+pub(crate) fn strip_etag_quotes(etag: &str) -> String {
+    etag.trim().trim_start_matches("W/").trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(content_type: Option<&str>, body: &str) -> FetchedDocument {
+        FetchedDocument {
+            url: "https://example.com/manual".to_string(),
+            content_type: content_type.map(String::from),
+            etag: None,
+            bytes: body.as_bytes().to_vec(),
+            fetched_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_type_from_header_then_body() {
+        assert_eq!(document(Some("application/pdf"), "").detected_type(), Some("pdf"));
+        assert_eq!(document(Some("text/html"), "plain words").detected_type(), Some("html"));
+        assert_eq!(document(Some("application/octet-stream"), "%PDF-1.7\n...").detected_type(), Some("pdf"));
+        assert_eq!(document(None, "\n  <!DOCTYPE HTML><html></html>").detected_type(), Some("html"));
+        // Raw markdown is usually served as text/plain; the URL's extension decides
+        assert_eq!(document(Some("text/plain"), "# Title").detected_type(), None);
+        assert!(fetch_url("file:///etc/passwd", 1024, Duration::from_secs(1)).is_err());
+    }
+}
\ No newline at end of file
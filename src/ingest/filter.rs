@@ -0,0 +1,103 @@
+use crate::config::IngestionConfig;
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Declarative allow/deny rules applied to every path before it is ingested,
+/// whether it was named directly, found while walking a directory, or reported by a watcher
+pub struct IngestionFilter {
+    include_extensions: Vec<String>,
+    exclude_extensions: Vec<String>,
+    exclude_globs: GlobSet,
+}
+
+impl IngestionFilter {
+    pub fn from_config(config: &IngestionConfig) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &config.exclude_globs {
+            let glob = Glob::new(pattern)
+                .map_err(|e| anyhow!("Invalid ingestion.exclude_globs pattern '{}': {}", pattern, e))?;
+            builder.add(glob);
+        }
+
+        Ok(Self {
+            include_extensions: Self::normalize_extensions(&config.include_extensions),
+            exclude_extensions: Self::normalize_extensions(&config.exclude_extensions),
+            exclude_globs: builder.build()?,
+        })
+    }
+
+    /// Explain why a path is excluded, or None if it may be ingested
+    pub fn rejection_reason(&self, path: &Path) -> Option<String> {
+        if self.exclude_globs.is_match(path) {
+            return Some(format!("{} matches ingestion.exclude_globs", path.display()));
+        }
+
+        let file_name = path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        if let Some(ext) = self.exclude_extensions.iter().find(|ext| Self::has_extension(&file_name, ext)) {
+            return Some(format!("{} has excluded extension '{}'", path.display(), ext));
+        }
+
+        if !self.include_extensions.is_empty()
+            && !self.include_extensions.iter().any(|ext| Self::has_extension(&file_name, ext))
+        {
+            return Some(format!("{} is not in ingestion.include_extensions", path.display()));
+        }
+
+        None
+    }
+
+    pub fn is_allowed(&self, path: &Path) -> bool {
+        self.rejection_reason(path).is_none()
+    }
+
+    // Compare on the file-name suffix so multi-part extensions like "min.js" work
+    fn has_extension(file_name: &str, ext: &str) -> bool {
+        file_name.len() > ext.len() + 1 && file_name.ends_with(&format!(".{}", ext))
+    }
+
+    fn normalize_extensions(extensions: &[String]) -> Vec<String> {
+        extensions.iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str], globs: &[&str]) -> IngestionFilter {
+        let config = IngestionConfig {
+            include_extensions: include.iter().map(|s| s.to_string()).collect(),
+            exclude_extensions: exclude.iter().map(|s| s.to_string()).collect(),
+            exclude_globs: globs.iter().map(|s| s.to_string()).collect(),
+            ..IngestionConfig::default()
+        };
+        IngestionFilter::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_exclusions_take_precedence() {
+        let f = filter(&["js", "md"], &["min.js"], &["**/node_modules/**"]);
+
+        assert!(f.is_allowed(Path::new("docs/guide.md")));
+        assert!(f.is_allowed(Path::new("src/app.js")));
+        assert!(!f.is_allowed(Path::new("dist/app.min.js")));
+        assert!(!f.is_allowed(Path::new("web/node_modules/pkg/index.js")));
+        assert!(!f.is_allowed(Path::new("Cargo.lock")));
+    }
+
+    #[test]
+    fn test_empty_include_list_allows_everything() {
+        let f = filter(&[], &[".LOCK"], &[]);
+
+        assert!(f.is_allowed(Path::new("notes.txt")));
+        assert!(!f.is_allowed(Path::new("Cargo.lock")));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,147 @@
+use anyhow::{Result, anyhow};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub const GITIGNORE_FILE: &str = ".gitignore";
+pub const RAGIGNORE_FILE: &str = ".ragignore";
+
+/// Patterns from one ignore file, matched against paths relative to the directory holding it
+struct IgnoreFile {
+    dir: PathBuf,
+    globs: GlobSet,
+    rules: Vec<Rule>,   // Parallel to the globs
+}
+
+#[derive(Clone, Copy)]
+struct Rule {
+    negated: bool,      // `!pattern` re-includes what an earlier pattern ignored
+    dir_only: bool,     // `pattern/` only matches directories
+}
+
+/// Ignore files found from a source's root down to one directory, in gitignore syntax:
+/// patterns with a slash are anchored to their file's directory, others match at any depth,
+/// and the last matching pattern wins, with deeper files taking precedence.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+    files: Vec<Arc<IgnoreFile>>,    // Outermost first
+}
+
+impl IgnoreRules {
+    /// These rules extended with the ignore files named `names` in `dir`, given relative to
+    /// the source `root`. Later names take precedence over earlier ones.
+    pub fn enter(&self, root: &Path, dir: &Path, names: &[&str]) -> Result<Self> {
+        let mut rules = self.clone();
+        for name in names {
+            let path = root.join(dir).join(name);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+            };
+            if let Some(file) = IgnoreFile::parse(dir, &content, &path)? {
+                rules.files.push(Arc::new(file));
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Rules for the directory holding `relative`, read from every directory on the way down
+    pub fn for_path(root: &Path, relative: &Path, names: &[&str]) -> Result<Self> {
+        let mut rules = Self::default().enter(root, Path::new(""), names)?;
+        let mut dir = PathBuf::new();
+        for component in relative.parent().into_iter().flat_map(|parent| parent.components()) {
+            dir.push(component);
+            rules = rules.enter(root, &dir, names)?;
+        }
+        Ok(rules)
+    }
+
+    /// Whether `relative` (to the source root) is ignored. Paths inside an ignored directory
+    /// are not checked here; callers skip such directories.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        for file in self.files.iter().rev() {
+            let Ok(path) = relative.strip_prefix(&file.dir) else { continue };
+            let matched = file.globs.matches(path).into_iter()
+                .filter(|&i| is_dir || !file.rules[i].dir_only)
+                .max();
+            if let Some(i) = matched {
+                return !file.rules[i].negated;
+            }
+        }
+        false
+    }
+}
+
+impl IgnoreFile {
+    fn parse(dir: &Path, content: &str, path: &Path) -> Result<Option<Self>> {
+        let mut builder = GlobSetBuilder::new();
+        let mut rules = Vec::new();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let dir_only = pattern.ends_with('/');
+            let pattern = pattern.trim_end_matches('/');
+            // A slash anywhere but the end anchors the pattern to this directory
+            let glob = match pattern.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if pattern.contains('/') => pattern.to_string(),
+                None => format!("**/{}", pattern),
+            };
+            if glob.is_empty() {
+                continue;
+            }
+
+            // As in git, a malformed pattern is skipped rather than failing the scan
+            match GlobBuilder::new(&glob).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                    rules.push(Rule { negated, dir_only });
+                }
+                Err(e) => tracing::warn!("Ignoring invalid pattern '{}' in {}: {}", line, path.display(), e),
+            }
+        }
+
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self { dir: dir.to_path_buf(), globs: builder.build()?, rules }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_patterns_and_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("docs/api")).unwrap();
+        std::fs::write(root.join(".gitignore"), "# build output\ntarget/\n*.log\n/notes.md\ndocs/api/*.html\n").unwrap();
+        std::fs::write(root.join(".ragignore"), "!keep.log\n").unwrap();
+        std::fs::write(root.join("docs/.gitignore"), "!debug.log\n").unwrap();
+
+        let names = [GITIGNORE_FILE, RAGIGNORE_FILE];
+        let top = IgnoreRules::for_path(root, Path::new("notes.md"), &names).unwrap();
+        assert!(top.is_ignored(Path::new("target"), true));
+        assert!(!top.is_ignored(Path::new("target"), false));
+        assert!(top.is_ignored(Path::new("notes.md"), false));
+        assert!(!top.is_ignored(Path::new("docs/notes.md"), false));
+        assert!(top.is_ignored(Path::new("docs/api/dma.html"), false));
+        assert!(!top.is_ignored(Path::new("docs/api/v2/dma.html"), false));
+        assert!(top.is_ignored(Path::new("build.log"), false));
+        assert!(!top.is_ignored(Path::new("keep.log"), false));
+
+        let nested = IgnoreRules::for_path(root, Path::new("docs/api/debug.log"), &names).unwrap();
+        assert!(!nested.is_ignored(Path::new("docs/api/debug.log"), false));
+        assert!(nested.is_ignored(Path::new("docs/api/trace.log"), false));
+        assert!(!IgnoreRules::for_path(root, Path::new("build.log"), &[RAGIGNORE_FILE]).unwrap().is_ignored(Path::new("build.log"), false));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+pub mod crawl;
+pub mod fetch;
+pub mod filter;
+pub mod ignore;
+pub mod object_store;
+pub mod sources;
+pub mod watch;
+
+pub use crawl::*;
+pub use fetch::*;
+pub use filter::*;
+pub use ignore::*;
+pub use object_store::*;
+pub use sources::*;
+pub use watch::*;
\ No newline at end of file
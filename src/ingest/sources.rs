@@ -0,0 +1,149 @@
+use super::ignore::{IgnoreRules, GITIGNORE_FILE, RAGIGNORE_FILE};
+use crate::config::SourceConfig;
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+/// Files under one configured source directory that its include/exclude globs select.
+/// Globs are matched against the path relative to the source directory, e.g.
+/// `**/*.md` or `drafts/**`. Files matched by `.ragignore` files in the source (and
+/// `.gitignore` files, unless turned off) are left out too, as are `.git` directories.
+pub struct SourceScanner {
+    root: PathBuf,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    recursive: bool,
+    ignore_files: Vec<&'static str>,
+}
+
+impl SourceScanner {
+    pub fn from_config(source: &SourceConfig) -> Result<Self> {
+        let include = if source.include.is_empty() {
+            None
+        } else {
+            Some(Self::glob_set(&source.include, &source.path, "include")?)
+        };
+
+        Ok(Self {
+            root: source.path.clone(),
+            include,
+            exclude: Self::glob_set(&source.exclude, &source.path, "exclude")?,
+            recursive: source.recursive,
+            ignore_files: if source.gitignore { vec![GITIGNORE_FILE, RAGIGNORE_FILE] } else { vec![RAGIGNORE_FILE] },
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Selected files, sorted so repeated scans ingest in the same order
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        if !self.root.is_dir() {
+            return Err(anyhow!("Source {} is not a directory", self.root.display()));
+        }
+
+        let mut files = Vec::new();
+        let rules = IgnoreRules::default().enter(&self.root, Path::new(""), &self.ignore_files)?;
+        self.walk(&self.root, &rules, &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// Whether a file at `path`, given under the source directory, belongs to the source.
+    /// Ignore files are read again on every call, so edits to them apply right away.
+    pub fn selects(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        (self.recursive || relative.components().count() == 1) && self.is_selected(relative) && !self.is_ignored(relative)
+    }
+
+    /// Whether the file or any directory above it is ignored
+    fn is_ignored(&self, relative: &Path) -> bool {
+        let rules = match IgnoreRules::for_path(&self.root, relative, &self.ignore_files) {
+            Ok(rules) => rules,
+            Err(e) => {
+                tracing::warn!("Source {}: {}", self.root.display(), e);
+                return false;
+            }
+        };
+        let mut dirs: Vec<&Path> = relative.ancestors().skip(1).filter(|dir| !dir.as_os_str().is_empty()).collect();
+        dirs.reverse();
+        dirs.iter().any(|dir| dir.ends_with(".git") || rules.is_ignored(dir, true)) || rules.is_ignored(relative, false)
+    }
+
+    pub fn is_selected(&self, relative: &Path) -> bool {
+        !self.exclude.is_match(relative) && self.include.as_ref().is_none_or(|include| include.is_match(relative))
+    }
+
+    fn walk(&self, dir: &Path, rules: &IgnoreRules, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                // Excluding or ignoring a directory (`drafts/**`, `target/`) skips walking it at all
+                let skipped = entry.file_name() == ".git" || rules.is_ignored(relative, true)
+                    || self.exclude.is_match(relative) || self.exclude.is_match(relative.join("_"));
+                if self.recursive && !skipped {
+                    self.walk(&path, &rules.enter(&self.root, relative, &self.ignore_files)?, files)?;
+                }
+            } else if file_type.is_file() && self.is_selected(relative) && !rules.is_ignored(relative, false) {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn glob_set(patterns: &[String], root: &Path, kind: &str) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern)
+                .map_err(|e| anyhow!("Invalid {} pattern '{}' for source {}: {}", kind, pattern, root.display(), e))?;
+            builder.add(glob);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_exclude_relative_to_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        for file in ["guide.md", "api/dma.md", "api/dma.pdf", "drafts/wip.md", "api/drafts/old.md"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, "x").unwrap();
+        }
+
+        let source = SourceConfig {
+            path: root.clone(),
+            include: vec!["**/*.md".to_string()],
+            exclude: vec!["drafts/**".to_string()],
+            recursive: true,
+            on_startup: true,
+            watch: false,
+            gitignore: true,
+            object_store: Default::default(),
+        };
+        let files = SourceScanner::from_config(&source).unwrap().files().unwrap();
+        let relative: Vec<_> = files.iter().map(|f| f.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(relative, vec!["api/dma.md", "api/drafts/old.md", "guide.md"]);
+
+        let shallow = SourceConfig { recursive: false, ..source.clone() };
+        assert_eq!(SourceScanner::from_config(&shallow).unwrap().files().unwrap(), vec![root.join("guide.md")]);
+
+        std::fs::write(root.join("api/.ragignore"), "drafts/\n").unwrap();
+        let scanner = SourceScanner::from_config(&source).unwrap();
+        assert_eq!(scanner.files().unwrap(), vec![root.join("api/dma.md"), root.join("guide.md")]);
+        assert!(!scanner.selects(&root.join("api/drafts/new.md")));
+        assert!(scanner.selects(&root.join("drafts2/new.md")));
+    }
+}
\ No newline at end of file
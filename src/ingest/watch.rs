@@ -0,0 +1,108 @@
+use super::SourceScanner;
+use crate::config::SourceConfig;
+use anyhow::{Result, anyhow};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+struct WatchedSource {
+    scanner: SourceScanner,
+    canonical_root: PathBuf,   // Events report absolute paths
+}
+
+/// Filesystem watch over the sources configured with `watch: true`. Changed paths are
+/// delivered on the receiver returned by `start`, as reported by the OS.
+pub struct SourceWatcher {
+    _watcher: RecommendedWatcher,
+    sources: Vec<WatchedSource>,
+}
+
+impl SourceWatcher {
+    /// None when no source is watched. Object-storage sources cannot be watched and are
+    /// left to `ingest_sources`.
+    pub fn start(sources: &[SourceConfig]) -> Result<Option<(Self, UnboundedReceiver<PathBuf>)>> {
+        let watched: Vec<_> = sources.iter().filter(|source| source.watch && source.remote_url().is_none()).collect();
+        if watched.is_empty() {
+            return Ok(None);
+        }
+
+        let (sender, receiver) = unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Source watch error: {}", e),
+        })?;
+
+        let mut sources = Vec::new();
+        for source in watched {
+            let canonical_root = source.path.canonicalize()
+                .map_err(|e| anyhow!("Cannot watch source {}: {}", source.path.display(), e))?;
+            let mode = if source.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+            watcher.watch(&canonical_root, mode)?;
+            sources.push(WatchedSource { scanner: SourceScanner::from_config(source)?, canonical_root });
+        }
+
+        Ok(Some((Self { _watcher: watcher, sources }, receiver)))
+    }
+
+    /// The path as source scans report it (under the configured directory, which may be
+    /// relative), with the source it lies in
+    pub fn source_path(&self, event_path: &Path) -> Option<(PathBuf, &SourceScanner)> {
+        self.sources.iter().find_map(|source| {
+            let relative = event_path.strip_prefix(&source.canonical_root).ok()?;
+            Some((source.scanner.root().join(relative), &source.scanner))
+        })
+    }
+}
+
+/// The next burst of changed paths: waits for one, then keeps collecting until none has
+/// arrived for `quiet`, so an editor's save (often several events) is handled once.
+/// None when the watcher has stopped.
+pub async fn next_batch(receiver: &mut UnboundedReceiver<PathBuf>, quiet: Duration) -> Option<BTreeSet<PathBuf>> {
+    let mut batch = BTreeSet::from([receiver.recv().await?]);
+    while let Ok(Some(path)) = tokio::time::timeout(quiet, receiver.recv()).await {
+        batch.insert(path);
+    }
+    Some(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_are_batched_per_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs/drafts")).unwrap();
+        let source = SourceConfig {
+            path: dir.path().join("docs"),
+            include: vec!["**/*.md".to_string()],
+            exclude: vec!["drafts/**".to_string()],
+            recursive: true,
+            on_startup: false,
+            watch: true,
+            gitignore: true,
+            object_store: Default::default(),
+        };
+        let (watcher, mut receiver) = SourceWatcher::start(std::slice::from_ref(&source)).unwrap().unwrap();
+
+        let root = dir.path().join("docs").canonicalize().unwrap();
+        let (path, scanner) = watcher.source_path(&root.join("drafts/wip.md")).unwrap();
+        assert_eq!(path, source.path.join("drafts/wip.md"));
+        assert!(!scanner.selects(&path));
+        assert!(scanner.selects(&source.path.join("guide.md")));
+        assert!(watcher.source_path(Path::new("/elsewhere/guide.md")).is_none());
+
+        std::fs::write(root.join("guide.md"), "# Guide").unwrap();
+        std::fs::write(root.join("guide.md"), "# Guide, saved twice").unwrap();
+        let batch = tokio::time::timeout(Duration::from_secs(5), next_batch(&mut receiver, Duration::from_millis(200)))
+            .await.unwrap().unwrap();
+        assert_eq!(batch.into_iter().collect::<Vec<_>>(), vec![root.join("guide.md")]);
+    }
+}
\ No newline at end of file
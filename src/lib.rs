@@ -1,7 +1,15 @@
+// The tools/list schema is one large json! literal
+#![recursion_limit = "256"]
+
 pub mod config;
 pub mod chunker;
 pub mod graph;
 pub mod storage;
 pub mod mcp;
 pub mod search;
-pub mod metrics;
\ No newline at end of file
+pub mod metrics;
+pub mod ingest;
+pub mod runtime;
+pub mod collections;
+pub mod memory;
+pub mod report;
\ No newline at end of file
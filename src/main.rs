@@ -1,9 +1,18 @@
+// The tools/list schema is one large json! literal
+#![recursion_limit = "256"]
+
 mod config;
 mod chunker;
 mod graph;
 mod storage;
 mod mcp;
 mod search;
+mod metrics;
+mod ingest;
+mod runtime;
+mod collections;
+mod memory;
+mod report;
 
 use anyhow::Result;
 use std::sync::Arc;
@@ -34,14 +43,16 @@ async fn main() -> Result<()> {
     // Support custom config file via environment variable or command line argument
     let config_file = std::env::var("RAG_CONFIG").unwrap_or_else(|_| "rag_config.yaml".to_string());
 
-    // Load configuration
+    // Load configuration, remembering which file it came from
+    let mut loaded_from = config_file.clone();
     let config = Config::from_file(&config_file)
         .or_else(|e| {
             let abs_path = std::fs::canonicalize(&config_file).unwrap_or_else(|_| {
                 std::env::current_dir().unwrap_or_else(|_| ".".into()).join(&config_file)
             });
             error!("Failed to load {}: {} (absolute path: {})", config_file, e, abs_path.display());
-            Config::from_file("../rag_config.yaml")
+            loaded_from = "../rag_config.yaml".to_string();
+            Config::from_file(&loaded_from)
         })
         .unwrap_or_else(|e| {
             error!("Failed to load ../rag_config.yaml: {} (absolute path: {})", e, std::fs::canonicalize("../rag_config.yaml").unwrap_or_else(|_| "../rag_config.yaml".into()).display());
@@ -49,7 +60,7 @@ async fn main() -> Result<()> {
             std::process::exit(1);
         });
 
-    info!("Loaded configuration from {}", config_file);
+    info!("Loaded configuration from {}", loaded_from);
     if let Some(instance_id) = &config.storage.instance_id {
         info!("Running as instance: {}", instance_id);
     }
@@ -58,8 +69,14 @@ async fn main() -> Result<()> {
     std::fs::create_dir_all(&config.storage.data_dir)?;
 
     // Create MCP server
-    let server = McpServer::new(config.clone()).await?;
+    let server = McpServer::new(config.clone()).await?.with_config_path(&loaded_from);
     let server_arc = Arc::new(server);
+    server_arc.spawn_graph_maintenance();
+    server_arc.spawn_memory_consolidation();
+    server_arc.spawn_source_ingestion();
+    server_arc.spawn_url_refresh();
+    server_arc.spawn_source_watcher();
+    server_arc.spawn_drift_monitor();
 
     info!("RAG MCP Server initialized successfully");
     info!("Storage directory: {}", config.storage.data_dir.canonicalize().unwrap_or(config.storage.data_dir).display());
@@ -70,4 +87,4 @@ async fn main() -> Result<()> {
     start_mcp_server(server_arc).await?;
 
     Ok(())
-}
+}
\ No newline at end of file
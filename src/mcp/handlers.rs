@@ -4,6 +4,9 @@ use serde_json::json;
 
 use super::server::{McpServer, RagMcp};
 
+/// MCP protocol revisions the server speaks, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
 pub fn create_rpc_handler(server: Arc<McpServer>) -> IoHandler {
     let mut io = IoHandler::new();
 
@@ -15,10 +18,19 @@ pub fn create_rpc_handler(server: Arc<McpServer>) -> IoHandler {
 
     // Override/Add manual handler for initialize that accepts any params
     // This will replace any existing handler with the same name
-    io.add_sync_method("initialize", move |_params: Params| {
+    io.add_sync_method("initialize", move |params: Params| {
+        // Agree to the client's protocol version when supported, otherwise offer the latest
+        let requested = match &params {
+            Params::Map(map) => map.get("protocolVersion").and_then(|v| v.as_str()),
+            _ => None,
+        };
+        let protocol_version = requested
+            .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+            .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0]);
+
         // Return standard MCP initialize response
         Ok(json!({
-            "protocolVersion": "2025-06-18",
+            "protocolVersion": protocol_version,
             "capabilities": {
                 "tools": {},
                 "resources": {},
@@ -48,16 +60,133 @@ pub fn create_rpc_handler(server: Arc<McpServer>) -> IoHandler {
                             },
                             "doc_type": {
                                 "type": "string",
-                                "description": "Type of document (pdf, markdown, text, code)",
-                                "enum": ["pdf", "markdown", "text", "code"]
+                                "description": "Type of document (pdf, pptx, email, image, markdown, html, asciidoc, org, log, subtitle, text, code, csv, tsv, json, jsonl, xml, yaml, toml, ini)",
+                                "enum": ["pdf", "pptx", "email", "image", "markdown", "html", "asciidoc", "org", "log", "subtitle", "text", "code", "csv", "tsv", "json", "jsonl", "xml", "yaml", "toml", "ini"]
+                            },
+                            "force": {
+                                "type": "boolean",
+                                "description": "Bypass ingestion size limits (max file size, chunks per document, corpus size)",
+                                "default": false
                             }
                         },
                         "required": ["path"]
                     }
                 },
+                {
+                    "name": "ingest_url",
+                    "description": "Fetch a web page or online document (HTML, PDF, markdown, ...) and ingest it with the URL as its source, e.g. to pull in an online reference manual. With crawl, ingest a whole docs site from its sitemap or by following same-origin links",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "url": {
+                                "type": "string",
+                                "description": "http:// or https:// URL to fetch"
+                            },
+                            "doc_type": {
+                                "type": "string",
+                                "description": "Override the type detected from the Content-Type header, the body and the URL",
+                                "enum": ["pdf", "email", "markdown", "html", "asciidoc", "org", "log", "subtitle", "text", "code", "csv", "tsv", "json", "jsonl", "xml", "yaml", "toml", "ini"]
+                            },
+                            "force": {
+                                "type": "boolean",
+                                "description": "Bypass ingestion size limits",
+                                "default": false
+                            },
+                            "crawl": {
+                                "type": "string",
+                                "description": "Also ingest the rest of the site: 'sitemap' takes the pages listed in the sitemap (url may name the sitemap itself, otherwise /sitemap.xml is used); 'links' follows same-origin links from url",
+                                "enum": ["sitemap", "links"]
+                            },
+                            "max_depth": {
+                                "type": "integer",
+                                "description": "Link hops to follow from url in links mode (default: ingestion.crawl_max_depth)"
+                            },
+                            "max_pages": {
+                                "type": "integer",
+                                "description": "Maximum pages to visit when crawling (default: ingestion.crawl_max_pages)"
+                            }
+                        },
+                        "required": ["url"]
+                    }
+                },
+                {
+                    "name": "ingest_text",
+                    "description": "Ingest raw text (notes, summaries, generated content) under a synthetic source name, without a file",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "source_name": {
+                                "type": "string",
+                                "description": "Name the text is stored and cited under, e.g. notes/dma-review.md; its extension picks the type when doc_type is omitted"
+                            },
+                            "text": {
+                                "type": "string",
+                                "description": "The content to ingest"
+                            },
+                            "doc_type": {
+                                "type": "string",
+                                "description": "Type of the text; file-only types (pdf, pptx, image) are not accepted",
+                                "enum": ["email", "markdown", "html", "asciidoc", "org", "log", "subtitle", "text", "code", "csv", "tsv", "json", "jsonl", "xml", "yaml", "toml", "ini"]
+                            },
+                            "metadata": {
+                                "type": "object",
+                                "description": "Extra key/value pairs stored on every chunk (e.g. author, conversation id)"
+                            },
+                            "force": {
+                                "type": "boolean",
+                                "description": "Bypass ingestion size limits",
+                                "default": false
+                            }
+                        },
+                        "required": ["source_name", "text"]
+                    }
+                },
+                {
+                    "name": "remember",
+                    "description": "Store a short memory (fact, decision, preference) for later recall by agents",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "text": {
+                                "type": "string",
+                                "description": "The memory to store"
+                            },
+                            "tags": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Tags to filter on when recalling"
+                            }
+                        },
+                        "required": ["text"]
+                    }
+                },
+                {
+                    "name": "recall",
+                    "description": "Recall stored memories, ranked by relevance to the query weighted by recency",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "What to recall"
+                            },
+                            "top_k": {
+                                "type": "integer",
+                                "description": "Number of memories to return",
+                                "default": 5
+                            },
+                            "tags": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Only recall memories carrying all of these tags"
+                            }
+                        },
+                        "required": ["query"]
+                    }
+                },
                 {
                     "name": "search_knowledge_chunk",
-                    "description": "Search for relevant knowledge chunks based on a query",
+                    "description": "Search for relevant knowledge chunks based on a query. The response includes result_summary: result counts per chunk type, language and source file. With chunking.summaries enabled, each chunk also has a title and summary",
                     "inputSchema": {
                         "type": "object",
                         "properties": {
@@ -69,14 +198,97 @@ pub fn create_rpc_handler(server: Arc<McpServer>) -> IoHandler {
                                 "type": "integer",
                                 "description": "Number of results to return",
                                 "default": 10
+                            },
+                            "exclude_terms": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Terms or phrases results must not contain (also accepted as -term / -\"phrase\" in the query)"
+                            },
+                            "minimum_should_match": {
+                                "type": ["integer", "string"],
+                                "description": "Query terms a keyword match must contain: a count (2), a percentage (\"75%\"), or negative for terms that may be missing (-1)"
+                            },
+                            "group_by": {
+                                "type": "string",
+                                "enum": ["source_file"],
+                                "description": "Return the top_k files, each with its best chunks nested, instead of a flat chunk list"
+                            },
+                            "chunks_per_group": {
+                                "type": "integer",
+                                "description": "Chunks to keep per group when group_by is set",
+                                "default": 3
+                            },
+                            "expand_context": {
+                                "type": "integer",
+                                "description": "Attach up to N preceding and following chunks (max 5) to each result under 'context'",
+                                "default": 0
+                            },
+                            "filter": {
+                                "type": "object",
+                                "description": "Only search chunks whose metadata matches every given field",
+                                "properties": {
+                                    "source_file": {"type": "string"},
+                                    "chapter": {"type": "string"},
+                                    "language": {"type": "string", "description": "Programming language of code chunks (rust, python, ...) or human language of prose (en, de, zh, ...)"},
+                                    "tag": {"type": "string"},
+                                    "ingested_after": {"type": "string", "description": "Chunks ingested at or after this time: RFC 3339, a date (2024-03-01) or an age before now (7d, 12h, 2w)"},
+                                    "ingested_before": {"type": "string", "description": "Chunks ingested before this time, in the same forms"},
+                                    "dated_after": {"type": "string", "description": "Chunks whose document date (front matter date, email Date) is at or after this time; undated chunks are left out"},
+                                    "dated_before": {"type": "string", "description": "Chunks whose document date is before this time"}
+                                }
+                            },
+                            "collection": {
+                                "type": "string",
+                                "description": "Only search documents in this accepted collection, ranked by its pipeline under ranking.collection_pipelines if one is configured"
+                            },
+                            "debug": {
+                                "type": "boolean",
+                                "description": "Include the time and candidate count of each ranking pipeline stage",
+                                "default": false
                             }
                         },
                         "required": ["query"]
                     }
                 },
+                {
+                    "name": "diagnose_query",
+                    "description": "Explain why a chunk expected for a query did or did not rank in the top_k: embedding similarity and vector rank, query words the chunk lacks for keyword search, filters and exclusions that removed it, type weights, and duplicates that were merged with it or ranked above it",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "The query the chunk should have matched"
+                            },
+                            "chunk_id": {
+                                "type": "string",
+                                "description": "ID of the expected chunk"
+                            },
+                            "top_k": {
+                                "type": "integer",
+                                "description": "Number of results the search returned",
+                                "default": 10
+                            },
+                            "exclude_terms": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Exclusions the search used, as in search_knowledge_chunk"
+                            },
+                            "minimum_should_match": {
+                                "type": ["integer", "string"],
+                                "description": "minimum_should_match the search used, as in search_knowledge_chunk"
+                            },
+                            "filter": {
+                                "type": "object",
+                                "description": "Metadata filter the search used, as in search_knowledge_chunk"
+                            }
+                        },
+                        "required": ["query", "chunk_id"]
+                    }
+                },
                 {
                     "name": "search_knowledge_chapter",
-                    "description": "Search for relevant chapters/sections based on a query",
+                    "description": "Search for relevant chapters/sections based on a query. With chunking.summaries enabled, each chapter has an outline of its matching chunks' titles",
                     "inputSchema": {
                         "type": "object",
                         "properties": {
@@ -88,125 +300,1131 @@ pub fn create_rpc_handler(server: Arc<McpServer>) -> IoHandler {
                                 "type": "integer",
                                 "description": "Number of chapters to return",
                                 "default": 5
+                            },
+                            "exclude_terms": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Terms or phrases results must not contain (also accepted as -term / -\"phrase\" in the query)"
+                            },
+                            "minimum_should_match": {
+                                "type": ["integer", "string"],
+                                "description": "Query terms a keyword match must contain: a count (2), a percentage (\"75%\"), or negative for terms that may be missing (-1)"
+                            },
+                            "filter": {
+                                "type": "object",
+                                "description": "Only search chunks whose metadata matches every given field, as in search_knowledge_chunk (e.g. {\"ingested_after\": \"7d\"})"
                             }
                         },
                         "required": ["query"]
                     }
-                }
-            ]
-        }))
-    });
-
-    // Add prompts/list handler
-    io.add_sync_method("prompts/list", move |_params: Params| {
-        // Return empty prompts list for now
-        Ok(json!({
-            "prompts": []
-        }))
-    });
-
-    // Add resources/list handler
-    io.add_sync_method("resources/list", move |_params: Params| {
-        // Return empty resources list for now
-        Ok(json!({
-            "resources": []
-        }))
-    });
-
-    // Add notifications/initialized handler
-    io.add_notification("notifications/initialized", |_params: Params| {
-        // This is a notification, no response needed
-        // Just log that initialization is complete
-        tracing::debug!("Client initialization complete");
-    });
-
-    // Add tools/call handler to invoke the actual tool methods
-    io.add_method("tools/call", move |params: Params| {
-        let server = server_for_tools.clone();
-
-        async move {
-            // Parse the parameters
-            let params_obj = match params {
-                Params::Map(map) => map,
-                _ => {
-                    return Err(jsonrpc_core::Error::invalid_params("Expected object parameters"));
-                }
-            };
-
-            // Extract tool name and arguments
-            let name = params_obj.get("name")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'name' field"))?;
-
-            let arguments = params_obj.get("arguments")
-                .cloned()
-                .unwrap_or_else(|| json!({}));
-
-            // Call the appropriate tool based on name
-            match name {
-                "ingest" => {
-                    // Extract parameters for ingest
-                    let path = arguments.get("path")
-                        .and_then(|v| v.as_str())
-                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'path' field"))?
-                        .to_string();
-
-                    let doc_type = arguments.get("doc_type")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string());
-
-                    // Call the ingest method
-                    server.ingest(path, doc_type)
-                        .map(|result| json!({
-                            "content": [
-                                {
-                                    "type": "text",
-                                    "text": format!("Successfully ingested document: {}",
-                                        result.get("document_path").and_then(|v| v.as_str()).unwrap_or("unknown"))
-                                }
-                            ]
-                        }))
-                }
-                "search_knowledge_chunk" => {
-                    // Extract parameters for search
-                    let query = arguments.get("query")
-                        .and_then(|v| v.as_str())
-                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
-                        .to_string();
-
-                    let top_k = arguments.get("top_k")
-                        .and_then(|v| v.as_u64())
-                        .map(|k| k as usize);
-
-                    // Call the search method
-                    server.search_knowledge_chunk(query, top_k)
-                        .map(|result| json!({
-                            "content": [
-                                {
-                                    "type": "text",
-                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
-                                }
-                            ]
-                        }))
-                }
-                "search_knowledge_chapter" => {
-                    // Extract parameters for chapter search
-                    let query = arguments.get("query")
-                        .and_then(|v| v.as_str())
-                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
-                        .to_string();
-
-                    let top_k = arguments.get("top_k")
-                        .and_then(|v| v.as_u64())
-                        .map(|k| k as usize);
-
-                    // Call the search chapter method
-                    server.search_knowledge_chapter(query, top_k)
-                        .map(|result| json!({
-                            "content": [
-                                {
-                                    "type": "text",
-                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                },
+                {
+                    "name": "health",
+                    "description": "Report server health: storage status, chunk count, embedding model readiness, graph size, uptime and disk usage",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {}
+                    }
+                },
+                {
+                    "name": "query_graph",
+                    "description": "Traverse the knowledge graph from a node with type, weight and depth filters, e.g. code chunks within 2 reference hops of a chunk",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "start": {
+                                "type": "string",
+                                "description": "Id of the node to start from (chunk id, document path, or document#chapter)"
+                            },
+                            "max_depth": {
+                                "type": "integer",
+                                "description": "Maximum number of hops",
+                                "default": 1
+                            },
+                            "node_types": {
+                                "type": "array",
+                                "items": {"type": "string", "enum": ["chunk", "word", "chapter", "document"]},
+                                "description": "Only return nodes of these types"
+                            },
+                            "chunk_types": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Only return chunks of these types (e.g. code, markdown, pdf)"
+                            },
+                            "edge_types": {
+                                "type": "array",
+                                "items": {"type": "string", "enum": ["similarity", "contains", "part_of", "sequential", "reference"]},
+                                "description": "Only follow edges of these types"
+                            },
+                            "min_weight": {
+                                "type": "number",
+                                "description": "Ignore edges lighter than this weight",
+                                "default": 0.0
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum number of nodes to return",
+                                "default": 50
+                            }
+                        },
+                        "required": ["start"]
+                    }
+                },
+                {
+                    "name": "propose_collections",
+                    "description": "Cluster ingested documents by content and propose named collections (e.g. UVM reference, project notes)",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "count": {
+                                "type": "integer",
+                                "description": "Number of collections to propose (default: based on corpus size)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "accept_collection",
+                    "description": "Accept a proposed collection, assigning its documents to it; search results then carry the collection name",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "proposal_id": {
+                                "type": "integer",
+                                "description": "Id of the proposal returned by propose_collections"
+                            },
+                            "name": {
+                                "type": "string",
+                                "description": "Collection name (defaults to the suggested name)"
+                            }
+                        },
+                        "required": ["proposal_id"]
+                    }
+                },
+                {
+                    "name": "suggest_vocabulary",
+                    "description": "Suggest domain abbreviations and synonyms from terms in empty or low-scoring queries, as a YAML patch to review and load via search.vocabulary_file",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "min_occurrences": {
+                                "type": "integer",
+                                "description": "Minimum number of failed queries a term must appear in (default: 2)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "diff_chunks",
+                    "description": "Compare a chunk with another chunk or with raw text: returns a unified diff, the share of lines in common and the embedding similarity",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "chunk_id": {
+                                "type": "string",
+                                "description": "Id of the chunk to compare"
+                            },
+                            "other_chunk_id": {
+                                "type": "string",
+                                "description": "Id of the chunk to compare against"
+                            },
+                            "text": {
+                                "type": "string",
+                                "description": "Raw text to compare against, instead of other_chunk_id"
+                            },
+                            "context_lines": {
+                                "type": "integer",
+                                "description": "Unchanged lines shown around each change (default: 3)"
+                            }
+                        },
+                        "required": ["chunk_id"]
+                    }
+                },
+                {
+                    "name": "verify_citation",
+                    "description": "Check that a quoted passage actually appears (exactly or nearly) in the cited document and return where, to catch made-up citations",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "quote": {
+                                "type": "string",
+                                "description": "The quoted text"
+                            },
+                            "source_file": {
+                                "type": "string",
+                                "description": "The cited document: its ingested path, or a trailing part of it such as the file name"
+                            },
+                            "min_score": {
+                                "type": "number",
+                                "description": "Share of the quote's words that must match for a fuzzy match to count (default: 0.8)"
+                            }
+                        },
+                        "required": ["quote", "source_file"]
+                    }
+                },
+                {
+                    "name": "ingest_sources",
+                    "description": "Ingest new files from the directories configured under `sources`, applying each source's include/exclude globs and skipping what .gitignore/.ragignore files in it ignore. Files already ingested are re-ingested when their content hash changed and skipped otherwise; counts of new, updated and unchanged files are reported. Object-storage sources (s3:// or HTTP listings) also re-ingest objects whose ETag changed and drop deleted ones",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "source": {
+                                "type": "string",
+                                "description": "Path or URL of one configured source to ingest (default: all sources)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "refresh_urls",
+                    "description": "Re-fetch every document ingested from a URL and re-ingest the ones whose content changed, replacing their chunks; runs automatically every ingestion.refresh_interval_secs when configured",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {}
+                    }
+                },
+                {
+                    "name": "rebuild_index",
+                    "description": "Rebuild the vector index and relationship graph from the stored chunks, re-embedding chunks with missing or invalid vectors; use after a crash or a bulk import that bypassed ingestion. Reports the consistency of the indexes before and after",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "check_only": {
+                                "type": "boolean",
+                                "description": "Only report inconsistencies without rebuilding (default: false)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "list_documents",
+                    "description": "List the stored documents with their chunk count, stored size, collection and when they were last ingested",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "prefix": {
+                                "type": "string",
+                                "description": "Only list documents whose source path starts with this prefix"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "stats",
+                    "description": "Corpus totals (documents, chunks, stored bytes), disk usage against ingestion.max_corpus_size_bytes, and the largest documents",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {}
+                    }
+                },
+                {
+                    "name": "check_embedding_drift",
+                    "description": "Re-embed a sample of stored chunks with the current embedding provider and compare with their stored vectors, reporting drift (e.g. after a provider silently updated its model) and whether re-indexing is suggested",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "sample_size": {
+                                "type": "integer",
+                                "description": "Chunks to re-embed (default: embedding.drift_sample_size)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "reembed",
+                    "description": "Re-embed, in the background, chunks whose stored vectors came from another model or embedding policy than the current configuration, without re-chunking. Reports the stale chunk count and the progress of the latest run",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "check_only": {
+                                "type": "boolean",
+                                "description": "Only report stale chunks and progress, without starting a re-embed (default: false)"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "record_feedback",
+                    "description": "Record whether a search result answered a query. tune_ranking uses the recorded judgements when no eval set is configured",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "The query the result was returned for"
+                            },
+                            "chunk_id": {
+                                "type": "string",
+                                "description": "ID of the result's chunk"
+                            },
+                            "relevant": {
+                                "type": "boolean",
+                                "description": "Whether the chunk answers the query",
+                                "default": true
+                            }
+                        },
+                        "required": ["query", "chunk_id"]
+                    }
+                },
+                {
+                    "name": "tune_ranking",
+                    "description": "Search the vector/keyword/graph fusion weights and RRF k for the ranking that scores best (nDCG, then MRR and recall) on labeled queries: an eval set, or else recorded feedback. Reports the current and recommended weights; with apply, writes better weights to ranking.fusion in the config file, used after a restart",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "eval_set": {
+                                "type": "string",
+                                "description": "YAML file of queries with their relevant chunk IDs or source files (default: ranking.eval_set, else recorded feedback)"
+                            },
+                            "top_k": {
+                                "type": "integer",
+                                "description": "Number of results each ranking is judged on",
+                                "default": 10
+                            },
+                            "apply": {
+                                "type": "boolean",
+                                "description": "Write the recommended weights to the config file if they improve on the current ranking",
+                                "default": false
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "pin_context",
+                    "description": "Pin a chunk or a whole document (e.g. a glossary or coding standard) so it is always included under 'pinned_context' in search results, optionally only for some search tools or for results in some collections",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "chunk_id": {
+                                "type": "string",
+                                "description": "Chunk to pin (give this or source_file)"
+                            },
+                            "source_file": {
+                                "type": "string",
+                                "description": "Document to pin, with all its chunks"
+                            },
+                            "tools": {
+                                "type": "array",
+                                "items": {"type": "string", "enum": ["search_knowledge_chunk", "search_knowledge_chapter"]},
+                                "description": "Tools to pin it to (default: both search tools)"
+                            },
+                            "collections": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Only include it when a result belongs to one of these collections (default: always)"
+                            },
+                            "note": {
+                                "type": "string",
+                                "description": "Why the context is pinned, returned with it"
+                            }
+                        }
+                    }
+                },
+                {
+                    "name": "unpin_context",
+                    "description": "Remove a pin made with pin_context",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "pin_id": {
+                                "type": "integer",
+                                "description": "ID from pin_context or list_pins"
+                            }
+                        },
+                        "required": ["pin_id"]
+                    }
+                },
+                {
+                    "name": "list_pins",
+                    "description": "List pinned chunks and documents with the tools and collections they apply to",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {}
+                    }
+                },
+                {
+                    "name": "extract_glossary",
+                    "description": "Scan the corpus for definition-style sentences (\"X is a ...\", \"X refers to ...\") and rebuild the glossary used by define",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {}
+                    }
+                },
+                {
+                    "name": "define",
+                    "description": "Look up a term in the extracted glossary and return its definitions with the chunks they came from",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "term": {
+                                "type": "string",
+                                "description": "Term to define; case and plural form are ignored"
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum definitions to return (default: 3)"
+                            }
+                        },
+                        "required": ["term"]
+                    }
+                },
+                {
+                    "name": "generate_report",
+                    "description": "Run the queries in a YAML report template and render the results as a markdown report with one section per query and numbered source citations",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "template": {
+                                "type": "string",
+                                "description": "Template path, or a template name looked up in report.template_dir"
+                            },
+                            "template_yaml": {
+                                "type": "string",
+                                "description": "Inline template YAML (title, optional description and top_k, sections of heading/query), instead of template"
+                            },
+                            "output_path": {
+                                "type": "string",
+                                "description": "Also write the markdown report to this path, relative to report.output_dir"
+                            },
+                            "overwrite": {
+                                "type": "boolean",
+                                "description": "Replace an existing file at output_path (default: false)"
+                            },
+                            "response_language": {
+                                "type": "string",
+                                "description": "Language of the report's own wording: en, de, fr, es, ja or zh (default: the template's response_language, then report.response_language). Excerpts are quoted as written."
+                            }
+                        }
+                    }
+                }
+            ]
+        }))
+    });
+
+    // Add prompts/list handler
+    io.add_sync_method("prompts/list", move |_params: Params| {
+        // Return empty prompts list for now
+        Ok(json!({
+            "prompts": []
+        }))
+    });
+
+    // Add resources/list handler
+    io.add_sync_method("resources/list", move |_params: Params| {
+        // Return empty resources list for now
+        Ok(json!({
+            "resources": []
+        }))
+    });
+
+    // Either side may ping; the reply is an empty result
+    io.add_sync_method("ping", |_params: Params| {
+        Ok(json!({}))
+    });
+
+    // Add notifications/initialized handler
+    io.add_notification("notifications/initialized", |_params: Params| {
+        // This is a notification, no response needed
+        // Just log that initialization is complete
+        tracing::debug!("Client initialization complete");
+    });
+
+    // Add tools/call handler to invoke the actual tool methods
+    io.add_method("tools/call", move |params: Params| {
+        let server = server_for_tools.clone();
+
+        async move {
+            // Parse the parameters
+            let params_obj = match params {
+                Params::Map(map) => map,
+                _ => {
+                    return Err(jsonrpc_core::Error::invalid_params("Expected object parameters"));
+                }
+            };
+
+            // Extract tool name and arguments
+            let name = params_obj.get("name")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'name' field"))?;
+
+            let arguments = params_obj.get("arguments")
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+
+            // Call the appropriate tool based on name
+            match name {
+                "ingest" => {
+                    // Extract parameters for ingest
+                    let path = arguments.get("path")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'path' field"))?
+                        .to_string();
+
+                    let doc_type = arguments.get("doc_type")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let force = arguments.get("force")
+                        .and_then(|v| v.as_bool());
+
+                    // Call the ingest method
+                    server.ingest(path, doc_type, force)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": format!("Successfully ingested document: {}",
+                                        result.get("document_path").and_then(|v| v.as_str()).unwrap_or("unknown"))
+                                }
+                            ]
+                        }))
+                }
+                "ingest_url" => {
+                    let url = arguments.get("url")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'url' field"))?
+                        .to_string();
+
+                    let doc_type = arguments.get("doc_type")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let force = arguments.get("force")
+                        .and_then(|v| v.as_bool());
+
+                    let crawl = arguments.get("crawl")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let max_depth = arguments.get("max_depth")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize);
+
+                    let max_pages = arguments.get("max_pages")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize);
+
+                    server.ingest_url(url, doc_type, force, crawl, max_depth, max_pages)
+                        .map(|result| {
+                            // Crawls report every page; single pages get a one-line summary
+                            let text = if result.get("pages").is_some() {
+                                serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                            } else {
+                                format!("Successfully ingested {} chunks from {}",
+                                    result.get("chunks_created").and_then(|v| v.as_u64()).unwrap_or(0),
+                                    result.get("url").and_then(|v| v.as_str()).unwrap_or("unknown"))
+                            };
+                            json!({
+                                "content": [
+                                    {
+                                        "type": "text",
+                                        "text": text
+                                    }
+                                ]
+                            })
+                        })
+                }
+                "ingest_text" => {
+                    let source_name = arguments.get("source_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'source_name' field"))?
+                        .to_string();
+
+                    let text = arguments.get("text")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'text' field"))?
+                        .to_string();
+
+                    let doc_type = arguments.get("doc_type")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    // Non-string metadata values are stored in their JSON form
+                    let metadata = arguments.get("metadata")
+                        .and_then(|v| v.as_object())
+                        .map(|map| map.iter()
+                            .map(|(key, value)| (key.clone(), value.as_str().map(|s| s.to_string()).unwrap_or_else(|| value.to_string())))
+                            .collect());
+
+                    let force = arguments.get("force")
+                        .and_then(|v| v.as_bool());
+
+                    server.ingest_text(source_name, text, doc_type, metadata, force)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": format!("Successfully ingested {} chunks from {}",
+                                        result.get("chunks_created").and_then(|v| v.as_u64()).unwrap_or(0),
+                                        result.get("source_name").and_then(|v| v.as_str()).unwrap_or("unknown"))
+                                }
+                            ]
+                        }))
+                }
+                "remember" => {
+                    let text = arguments.get("text")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'text' field"))?
+                        .to_string();
+
+                    let tags = arguments.get("tags")
+                        .and_then(|v| v.as_array())
+                        .map(|tags| tags.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    server.remember(text, tags)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "recall" => {
+                    let query = arguments.get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
+                        .to_string();
+
+                    let top_k = arguments.get("top_k")
+                        .and_then(|v| v.as_u64())
+                        .map(|k| k as usize);
+
+                    let tags = arguments.get("tags")
+                        .and_then(|v| v.as_array())
+                        .map(|tags| tags.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    server.recall(query, top_k, tags)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "search_knowledge_chunk" => {
+                    // Extract parameters for search
+                    let query = arguments.get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
+                        .to_string();
+
+                    let top_k = arguments.get("top_k")
+                        .and_then(|v| v.as_u64())
+                        .map(|k| k as usize);
+
+                    let exclude_terms = arguments.get("exclude_terms")
+                        .and_then(|v| v.as_array())
+                        .map(|terms| terms.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    let minimum_should_match = arguments.get("minimum_should_match")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'minimum_should_match': {}", e)))?;
+
+                    let group_by = arguments.get("group_by")
+                        .and_then(|v| v.as_str())
+                        .map(|g| g.to_string());
+
+                    let chunks_per_group = arguments.get("chunks_per_group")
+                        .and_then(|v| v.as_u64())
+                        .map(|n| n as usize);
+
+                    let expand_context = arguments.get("expand_context")
+                        .and_then(|v| v.as_u64())
+                        .map(|n| n as usize);
+
+                    let filter = arguments.get("filter")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'filter': {}", e)))?;
+
+                    let collection = arguments.get("collection")
+                        .and_then(|v| v.as_str())
+                        .map(|c| c.to_string());
+
+                    let debug = arguments.get("debug")
+                        .and_then(|v| v.as_bool());
+
+                    // Call the search method
+                    server.search_knowledge_chunk(query, top_k, exclude_terms, minimum_should_match, group_by, chunks_per_group, expand_context, filter, collection, debug)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "diagnose_query" => {
+                    let query = arguments.get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
+                        .to_string();
+
+                    let chunk_id = arguments.get("chunk_id")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'chunk_id' field"))?
+                        .to_string();
+
+                    let top_k = arguments.get("top_k")
+                        .and_then(|v| v.as_u64())
+                        .map(|k| k as usize);
+
+                    let exclude_terms = arguments.get("exclude_terms")
+                        .and_then(|v| v.as_array())
+                        .map(|terms| terms.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    let minimum_should_match = arguments.get("minimum_should_match")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'minimum_should_match': {}", e)))?;
+
+                    let filter = arguments.get("filter")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'filter': {}", e)))?;
+
+                    server.diagnose_query(query, chunk_id, top_k, exclude_terms, minimum_should_match, filter)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "search_knowledge_chapter" => {
+                    // Extract parameters for chapter search
+                    let query = arguments.get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
+                        .to_string();
+
+                    let top_k = arguments.get("top_k")
+                        .and_then(|v| v.as_u64())
+                        .map(|k| k as usize);
+
+                    let exclude_terms = arguments.get("exclude_terms")
+                        .and_then(|v| v.as_array())
+                        .map(|terms| terms.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    let minimum_should_match = arguments.get("minimum_should_match")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'minimum_should_match': {}", e)))?;
+
+                    // Call the search chapter method
+                    let filter = arguments.get("filter")
+                        .filter(|v| !v.is_null())
+                        .map(|v| serde_json::from_value(v.clone()))
+                        .transpose()
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(format!("Invalid 'filter': {}", e)))?;
+
+                    server.search_knowledge_chapter(query, top_k, exclude_terms, minimum_should_match, filter)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "health" => {
+                    server.health()
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "query_graph" => {
+                    server.query_graph(arguments)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "propose_collections" => {
+                    let count = arguments.get("count")
+                        .and_then(|v| v.as_u64())
+                        .map(|k| k as usize);
+
+                    server.propose_collections(count)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "accept_collection" => {
+                    let proposal_id = arguments.get("proposal_id")
+                        .and_then(|v| v.as_u64())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'proposal_id' field"))? as usize;
+
+                    let name = arguments.get("name")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    server.accept_collection(proposal_id, name)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": format!("Assigned {} documents to collection '{}'",
+                                        result.get("documents_assigned").and_then(|v| v.as_u64()).unwrap_or(0),
+                                        result.get("collection").and_then(|v| v.as_str()).unwrap_or("unknown"))
+                                }
+                            ]
+                        }))
+                }
+                "suggest_vocabulary" => {
+                    let min_occurrences = arguments.get("min_occurrences")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize);
+
+                    server.suggest_vocabulary(min_occurrences)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": format!("{} of {} queries were empty or low-scoring. Queries by intent: {}\n\n{}",
+                                        result.get("failed_queries").and_then(|v| v.as_u64()).unwrap_or(0),
+                                        result.get("total_queries").and_then(|v| v.as_u64()).unwrap_or(0),
+                                        result.get("queries_by_intent").cloned().unwrap_or(json!({})),
+                                        result.get("patch").and_then(|v| v.as_str()).unwrap_or(""))
+                                }
+                            ]
+                        }))
+                }
+                "diff_chunks" => {
+                    let chunk_id = arguments.get("chunk_id")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'chunk_id' field"))?
+                        .to_string();
+
+                    let other_chunk_id = arguments.get("other_chunk_id")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let text = arguments.get("text")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let context_lines = arguments.get("context_lines")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize);
+
+                    server.diff_chunks(chunk_id, other_chunk_id, text, context_lines)
+                        .map(|result| {
+                            let diff = result.get("diff").and_then(|v| v.as_str()).unwrap_or("");
+                            json!({
+                                "content": [
+                                    {
+                                        "type": "text",
+                                        "text": format!("Line similarity: {:.2}, semantic similarity: {:.2}\n\n{}",
+                                            result.get("similarity").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                                            result.get("semantic_similarity").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                                            if diff.is_empty() { "No differences" } else { diff })
+                                    }
+                                ]
+                            })
+                        })
+                }
+                "verify_citation" => {
+                    let quote = arguments.get("quote")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'quote' field"))?
+                        .to_string();
+
+                    let source_file = arguments.get("source_file")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'source_file' field"))?
+                        .to_string();
+
+                    let min_score = arguments.get("min_score")
+                        .and_then(|v| v.as_f64())
+                        .map(|v| v as f32);
+
+                    server.verify_citation(quote, source_file, min_score)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "ingest_sources" => {
+                    let source = arguments.get("source")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    server.ingest_sources(source)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "refresh_urls" => {
+                    server.refresh_urls()
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "rebuild_index" => {
+                    let check_only = arguments.get("check_only")
+                        .and_then(|v| v.as_bool());
+
+                    server.rebuild_index(check_only)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "list_documents" => {
+                    let prefix = arguments.get("prefix")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    server.list_documents(prefix)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "stats" => {
+                    server.stats()
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "check_embedding_drift" => {
+                    let sample_size = arguments.get("sample_size").and_then(|v| v.as_u64()).map(|v| v as usize);
+
+                    server.check_embedding_drift(sample_size)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "reembed" => {
+                    let check_only = arguments.get("check_only").and_then(|v| v.as_bool());
+
+                    server.reembed(check_only)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "record_feedback" => {
+                    let query = arguments.get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'query' field"))?
+                        .to_string();
+
+                    let chunk_id = arguments.get("chunk_id")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'chunk_id' field"))?
+                        .to_string();
+
+                    let relevant = arguments.get("relevant")
+                        .and_then(|v| v.as_bool());
+
+                    server.record_feedback(query, chunk_id, relevant)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "tune_ranking" => {
+                    let eval_set = arguments.get("eval_set")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let top_k = arguments.get("top_k")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize);
+
+                    let apply = arguments.get("apply")
+                        .and_then(|v| v.as_bool());
+
+                    server.tune_ranking(eval_set, top_k, apply)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "pin_context" => {
+                    let chunk_id = arguments.get("chunk_id")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let source_file = arguments.get("source_file")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let tools = arguments.get("tools")
+                        .and_then(|v| v.as_array())
+                        .map(|tools| tools.iter().filter_map(|t| t.as_str()).map(|t| t.to_string()).collect());
+
+                    let collections = arguments.get("collections")
+                        .and_then(|v| v.as_array())
+                        .map(|collections| collections.iter().filter_map(|c| c.as_str()).map(|c| c.to_string()).collect());
+
+                    let note = arguments.get("note")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    server.pin_context(chunk_id, source_file, tools, collections, note)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "unpin_context" => {
+                    let pin_id = arguments.get("pin_id")
+                        .and_then(|v| v.as_u64())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'pin_id' field"))? as usize;
+
+                    server.unpin_context(pin_id)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "list_pins" => {
+                    server.list_pins()
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "extract_glossary" => {
+                    server.extract_glossary()
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "define" => {
+                    let term = arguments.get("term")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing 'term' field"))?;
+                    let limit = arguments.get("limit").and_then(|v| v.as_u64()).map(|v| v as usize);
+
+                    server.define(term.to_string(), limit)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&result).unwrap_or_else(|_| "Error formatting results".to_string())
+                                }
+                            ]
+                        }))
+                }
+                "generate_report" => {
+                    let template = arguments.get("template")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let template_yaml = arguments.get("template_yaml")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let output_path = arguments.get("output_path")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let response_language = arguments.get("response_language")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let overwrite = arguments.get("overwrite")
+                        .and_then(|v| v.as_bool());
+
+                    server.generate_report(template, template_yaml, output_path, response_language, overwrite)
+                        .map(|result| json!({
+                            "content": [
+                                {
+                                    "type": "text",
+                                    "text": result.get("markdown").and_then(|v| v.as_str()).unwrap_or("").to_string()
                                 }
                             ]
                         }))
@@ -1,300 +1,3231 @@
 use jsonrpc_core::{Value, Error as JsonRpcError};
 use jsonrpc_derive::rpc;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 use serde_json::json;
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 
-use crate::storage::{Storage, SearchResult};
-use crate::chunker::{SemanticChunker, pdf::PdfProcessor, markdown::MarkdownProcessor, text::TextProcessor, code::CodeProcessor};
-use crate::graph::GraphBuilder;
-use crate::storage::embeddings::EmbeddingModel;
-use crate::config::Config;
+use crate::storage::{ChunkQuery, MetadataFilter, Storage, SearchResult};
+use crate::chunker::{Chunk, ChunkMetadata, SemanticChunker, pdf::PdfProcessor, code::CodeProcessor, email::EmailProcessor, encoding::EncodingDetector, summary::ChunkSummarizer, split::Splitter, strategy::{ChunkInput, ChunkingStrategy, StrategyRegistry, FILE_DOC_TYPES}, natural_language, ChunkType, EmbeddedField};
+use crate::graph::{GraphBuilder, GraphQuery, MaintenancePolicy, NodeType, SharedGraph};
+use crate::search::{diff_texts, embedding_similarity, graph_neighbours, keyword_coverage, rank_of, Finding, group_results, interleave_by_rank, locate_quote, maximal_marginal_relevance, merge_overlapping_results, tune, write_fusion_weights, BM25Search, EvalSet, ExclusionFilter, MinimumShouldMatch, QueryEnhancer, QueryTerms, RankingPipeline, RankingPipelines, RankingStage, ResultSummary, RetrievalLegs, TypeWeights, DEFAULT_OVERLAP_MERGE_RATIO};
+use crate::metrics::{feedback::{Feedback, FeedbackLog}, vocabulary::suggest_vocabulary, PerformanceMetrics, StageTiming};
+use crate::storage::embeddings::EmbeddingPolicy;
+use crate::storage::consistency::{audit, provider_dimensions, reembed_reason, ConsistencyReport};
+use crate::storage::drift::{drift_report, sample_chunks, DriftReport};
+use crate::storage::providers::{ProviderChain, LOCAL_PROVIDER};
+use crate::storage::quantize::EmbeddingStorage;
+use crate::storage::sparse::{sparse_encoder, SparseEncoder};
+use crate::storage::reembed::{recorded_dimension, stale_reason, ReembedProgress, EMBEDDING_DIMENSION_ATTRIBUTE, EMBEDDING_MODEL_ATTRIBUTE};
+use crate::config::{Config, SourceConfig};
+use crate::ingest::{default_sitemap_url, extract_links, fetch_url, next_batch, sitemap_locations, CrawlFrontier, CrawlMode, FetchedDocument, IngestionFilter, ObjectSource, SourceScanner, SourceWatcher};
+use crate::runtime::WorkerPools;
+use crate::memory::{build_memory_chunk, consolidate, is_memory, rank_memories, ConsolidationPolicy, RecallWeights, MEMORY_COLLECTION, MEMORY_SOURCE_PREFIX};
+use crate::report::{render_report, write_report, ReportTemplate, ResponseLanguage};
+use crate::collections::{cluster_documents, default_cluster_count, CollectionProposal, CollectionStore, DocumentProfile, GlossaryStore, PinStore};
 
-#[rpc]
+// The search RPCs take the MCP tool arguments positionally. Only the server side is
+// generated: nothing uses a client, and its methods could not carry this allow.
+#[allow(clippy::too_many_arguments)]
+#[rpc(server)]
 pub trait RagMcp {
     #[rpc(name = "ingest")]
-    fn ingest(&self, path: String, doc_type: Option<String>) -> Result<Value, JsonRpcError>;
+    fn ingest(&self, path: String, doc_type: Option<String>, force: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "ingest_url")]
+    fn ingest_url(&self, url: String, doc_type: Option<String>, force: Option<bool>, crawl: Option<String>, max_depth: Option<usize>, max_pages: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "ingest_text")]
+    fn ingest_text(&self, source_name: String, text: String, doc_type: Option<String>, metadata: Option<std::collections::HashMap<String, String>>, force: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "remember")]
+    fn remember(&self, text: String, tags: Option<Vec<String>>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "recall")]
+    fn recall(&self, query: String, top_k: Option<usize>, tags: Option<Vec<String>>) -> Result<Value, JsonRpcError>;
 
     #[rpc(name = "search_knowledge_chunk")]
-    fn search_knowledge_chunk(&self, query: String, top_k: Option<usize>) -> Result<Value, JsonRpcError>;
+    fn search_knowledge_chunk(&self, query: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, group_by: Option<String>, chunks_per_group: Option<usize>, expand_context: Option<usize>, filter: Option<MetadataFilter>, collection: Option<String>, debug: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "diagnose_query")]
+    fn diagnose_query(&self, query: String, chunk_id: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, filter: Option<MetadataFilter>) -> Result<Value, JsonRpcError>;
 
     #[rpc(name = "search_knowledge_chapter")]
-    fn search_knowledge_chapter(&self, query: String, top_k: Option<usize>) -> Result<Value, JsonRpcError>;
+    fn search_knowledge_chapter(&self, query: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, filter: Option<MetadataFilter>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "health")]
+    fn health(&self) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "query_graph")]
+    fn query_graph(&self, query: Value) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "propose_collections")]
+    fn propose_collections(&self, count: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "accept_collection")]
+    fn accept_collection(&self, proposal_id: usize, name: Option<String>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "pin_context")]
+    fn pin_context(&self, chunk_id: Option<String>, source_file: Option<String>, tools: Option<Vec<String>>, collections: Option<Vec<String>>, note: Option<String>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "unpin_context")]
+    fn unpin_context(&self, pin_id: usize) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "list_pins")]
+    fn list_pins(&self) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "extract_glossary")]
+    fn extract_glossary(&self) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "define")]
+    fn define(&self, term: String, limit: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "suggest_vocabulary")]
+    fn suggest_vocabulary(&self, min_occurrences: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "diff_chunks")]
+    fn diff_chunks(&self, chunk_id: String, other_chunk_id: Option<String>, text: Option<String>, context_lines: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "verify_citation")]
+    fn verify_citation(&self, quote: String, source_file: String, min_score: Option<f32>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "ingest_sources")]
+    fn ingest_sources(&self, source: Option<String>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "refresh_urls")]
+    fn refresh_urls(&self) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "rebuild_index")]
+    fn rebuild_index(&self, check_only: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "list_documents")]
+    fn list_documents(&self, prefix: Option<String>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "stats")]
+    fn stats(&self) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "reembed")]
+    fn reembed(&self, check_only: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "check_embedding_drift")]
+    fn check_embedding_drift(&self, sample_size: Option<usize>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "record_feedback")]
+    fn record_feedback(&self, query: String, chunk_id: String, relevant: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "tune_ranking")]
+    fn tune_ranking(&self, eval_set: Option<String>, top_k: Option<usize>, apply: Option<bool>) -> Result<Value, JsonRpcError>;
+
+    #[rpc(name = "generate_report")]
+    fn generate_report(&self, template: Option<String>, template_yaml: Option<String>, output_path: Option<String>, response_language: Option<String>, overwrite: Option<bool>) -> Result<Value, JsonRpcError>;
+}
+
+/// Chunk attribute marking chunks embedded below their context header
+const CONTEXT_HEADER_ATTRIBUTE: &str = "embedding_context_header";
+
+/// Chunk attribute listing the metadata fields embedded below a chunk's content
+const EMBEDDED_FIELDS_ATTRIBUTE: &str = "embedding_metadata_fields";
+
+/// Chunk attribute marking vectors embedded with late chunking, which depend on the chunks
+/// around them and so cannot be reproduced one chunk at a time
+const LATE_CHUNKING_ATTRIBUTE: &str = "embedding_late_chunked";
+
+/// Upper bound on `expand_context`, keeping responses from ballooning
+const MAX_EXPAND_CONTEXT: usize = 5;
+
+/// Unchanged lines shown around each change in `diff_chunks`
+const DEFAULT_DIFF_CONTEXT: usize = 3;
+
+/// Share of a quote's words that must match for `verify_citation` to accept a fuzzy match
+const DEFAULT_CITATION_MIN_SCORE: f32 = 0.8;
+
+/// Embedding similarity above which `diagnose_query` counts a higher-ranked chunk as a near-duplicate
+const NEAR_DUPLICATE_SIMILARITY: f32 = 0.95;
+
+/// Upper bound on pinned chunks prepended to one tool response, so a pinned document cannot
+/// crowd out the results
+const MAX_PINNED_CHUNKS: usize = 20;
+
+/// Documents listed by size in `stats`
+const STATS_LARGEST_DOCUMENTS: usize = 10;
+
+/// Per-query retrieval options from the search tools
+#[derive(Debug, Clone, Default)]
+struct SearchOptions {
+    exclude_terms: Vec<String>,
+    minimum_should_match: Option<MinimumShouldMatch>,  // Applies to the keyword leg only
+    filter: Option<MetadataFilter>,                     // Only chunks whose metadata matches are candidates
+    collection: Option<String>,                         // Only chunks of this collection's documents, ranked by its pipeline
 }
 
 #[derive(Clone)]
 pub struct McpServer {
     storage: Arc<Storage>, // Storage is now thread-safe internally
     chunker: Arc<SemanticChunker>,
-    graph: Arc<RwLock<GraphBuilder>>,
-    embedder: Arc<EmbeddingModel>,
+    type_chunkers: Arc<std::collections::HashMap<String, SemanticChunker>>,  // Chunkers for doc types with their own chunking.size_limits
+    splitters: Arc<Vec<Splitter>>,  // chunking.splitters, in order
+    strategies: Arc<StrategyRegistry>,  // Chunking strategy for each doc type
+    summarizer: Option<Arc<ChunkSummarizer>>,  // Chunk titles and summaries, when chunking.summaries is enabled
+    graph: Arc<SharedGraph>,
+    embedder: Arc<ProviderChain>,
+    code_embedder: Option<Arc<ProviderChain>>,  // embedding.code_providers, for Code chunks
+    sparse_encoder: Option<Arc<dyn SparseEncoder>>,  // embedding.sparse, for the sparse retrieval list
     config: Config,
+    ingestion_filter: Arc<IngestionFilter>,
+    pipelines: Arc<RankingPipelines>,
+    pools: Arc<WorkerPools>,
+    collections: Arc<RwLock<CollectionStore>>,
+    pins: Arc<RwLock<PinStore>>,
+    glossary: Arc<RwLock<GlossaryStore>>,
+    metrics: Arc<PerformanceMetrics>,
+    query_enhancer: Arc<QueryEnhancer>,
+    feedback: Arc<FeedbackLog>,
+    drift: Arc<RwLock<Option<DriftReport>>>,  // Latest embedding drift check
+    reembed: Arc<RwLock<Option<ReembedProgress>>>,  // Latest background re-embed
+    query_dimensions: Arc<std::sync::Mutex<std::collections::HashMap<String, (usize, bool)>>>,  // Query vector length each provider last returned, and whether its chunks all match it
+    source_sync: Arc<tokio::sync::Mutex<()>>,  // One source sync at a time, so files are not ingested twice
+    memory_writes: Arc<std::sync::Mutex<()>>,  // Held by recalls and consolidation while they rewrite memories, so neither overwrites the other
+    corpus_bytes: Arc<std::sync::Mutex<Option<u64>>>,  // Data directory size when last measured plus the chunk bytes stored since
+    config_path: Option<std::path::PathBuf>,  // Where tune_ranking writes recommended weights
+    start_time: Instant,
 }
 
 impl McpServer {
     pub async fn new(config: Config) -> Result<Self> {
         // SQLite supports concurrent multi-process access, no instance_id needed
-        let storage = Arc::new(Storage::new(&config.storage.data_dir)?);
+        let storage = Arc::new(Storage::open(&config.storage.data_dir, None, EmbeddingStorage::from_config(&config.storage)?)?);
 
         let chunker = Arc::new(SemanticChunker::new(
             config.storage.max_chunk_size,
             config.storage.min_chunk_size,
             config.chunking.overlap_tokens,
-        ));
+        ).with_adaptive_sizing(config.chunking.adaptive_sizing)
+         .with_language_overrides(config.chunking.languages.clone()));
+        let type_chunkers = Arc::new(Self::type_chunkers(&config, &chunker)?);
+        let splitters = Arc::new(config.chunking.splitters.iter().map(Splitter::from_config).collect::<Result<Vec<_>>>()?);
+        let strategies = Arc::new(StrategyRegistry::from_config(&config.chunking));
+        let summarizer = ChunkSummarizer::from_config(&config.chunking.summaries)?.map(Arc::new);
+
+        let graph_path = Self::graph_path(&config);
+        let graph = if graph_path.exists() {
+            GraphBuilder::load(&graph_path, config.graph.similarity_threshold).unwrap_or_else(|e| {
+                tracing::error!("{}; starting with an empty graph. Re-ingest documents to rebuild it.", e);
+                GraphBuilder::new(config.graph.similarity_threshold)
+            })
+        } else {
+            GraphBuilder::new(config.graph.similarity_threshold)
+        };
+        let graph = Arc::new(SharedGraph::new(graph).with_path(graph_path));
+
+        let ingestion_filter = Arc::new(IngestionFilter::from_config(&config.ingestion)?);
+        let pipelines = Arc::new(RankingPipelines::from_config(&config.ranking)?);
+        let pools = Arc::new(WorkerPools::from_config(&config.runtime)?);
+        let collections = Arc::new(RwLock::new(CollectionStore::open(storage.data_dir())?));
+        let pins = Arc::new(RwLock::new(PinStore::open(storage.data_dir())?));
+        let glossary = Arc::new(RwLock::new(GlossaryStore::open(storage.data_dir())?));
+
+        let mut query_enhancer = QueryEnhancer::new();
+        if let Some(path) = &config.search.vocabulary_file {
+            query_enhancer.load_vocabulary_file(path)
+                .map_err(|e| anyhow::anyhow!("Failed to load vocabulary file {:?}: {}", path, e))?;
+        }
+        for chunk in storage.scan(ChunkQuery::all()).filter(|chunk| !chunk.metadata.source_file.starts_with(MEMORY_SOURCE_PREFIX)) {
+            query_enhancer.learn_acronyms(&chunk.content);
+        }
+        tracing::info!("Learned {} acronyms from the corpus", query_enhancer.corpus_acronym_count());
 
-        let graph = Arc::new(RwLock::new(GraphBuilder::new(
-            config.graph.similarity_threshold,
-        )));
+        // Configured providers are tried in order, ending with the deterministic local model by default
+        let policy = EmbeddingPolicy::from_config(&config.embedding)?;
+        let embedder = Arc::new(ProviderChain::from_config(&config.embedding, policy).await?);
+        let code_embedder = ProviderChain::code_chain(&config.embedding, policy).await?.map(Arc::new);
+        // Chunks record only a provider name, so it must say which chain embedded them
+        if let Some(shared) = code_embedder.iter().flat_map(|code| code.status()).find(|status| embedder.model_of(&status.name).is_some()) {
+            return Err(anyhow::anyhow!("embedding.code_providers reuses the provider name '{}'; give code providers their own names", shared.name));
+        }
+        let sparse = sparse_encoder(&config.embedding)?;
 
-        // Try to load a real transformer model, fall back to deterministic embeddings
-        let embedder = Arc::new(EmbeddingModel::new(&config.embedding.model_name).await?);
+        // Indexes built before the policy was recorded were always normalized with mean pooling
+        let recorded = match EmbeddingPolicy::recorded(storage.data_dir())? {
+            None if storage.count_chunks() > 0 => {
+                EmbeddingPolicy::default().record(storage.data_dir())?;
+                Some(EmbeddingPolicy::default())
+            }
+            recorded => recorded,
+        };
+        if let Err(e) = policy.ensure_matches(recorded.as_ref()) {
+            tracing::error!("{}; searches and ingestion will be rejected until this is resolved", e);
+        }
 
-        Ok(Self {
+        let feedback = Arc::new(FeedbackLog::new(storage.data_dir()));
+        let server = Self {
             storage,
             chunker,
+            type_chunkers,
+            splitters,
+            strategies,
+            summarizer,
             graph,
             embedder,
+            code_embedder,
+            sparse_encoder: sparse,
             config,
-        })
+            ingestion_filter,
+            pipelines,
+            pools,
+            collections,
+            pins,
+            glossary,
+            metrics: Arc::new(PerformanceMetrics::new()),
+            query_enhancer: Arc::new(query_enhancer),
+            feedback,
+            drift: Arc::new(RwLock::new(None)),
+            reembed: Arc::new(RwLock::new(None)),
+            query_dimensions: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+            source_sync: Arc::new(tokio::sync::Mutex::new(())),
+            memory_writes: Arc::new(std::sync::Mutex::new(())),
+            corpus_bytes: Arc::new(std::sync::Mutex::new(None)),
+            config_path: None,
+            start_time: Instant::now(),
+        };
+
+        // A changed model or policy leaves stored vectors behind; the reembed tool catches them up
+        let stale = server.stale_chunk_ids().len();
+        if stale > 0 {
+            let (_, model) = server.embedder.primary();
+            tracing::warn!("{} chunks were embedded with another model or policy than {}; run the reembed tool to update them", stale, model);
+        }
+
+        // Vectors of another length than the configured model's score 0 against every query
+        let (primary, _) = server.embedder.primary();
+        let embedding = &server.config.embedding;
+        let expected = embedding.truncate_dimension.map_or(embedding.dimension, |truncated| truncated.min(embedding.dimension));
+        let mismatched = server.storage.matching_ids(ChunkQuery::all().with_predicate(|metadata| {
+            recorded_dimension(&metadata.attributes, primary).is_some_and(|dimension| dimension != expected)
+        })).count();
+        if mismatched > 0 {
+            tracing::error!(
+                "{} chunks embedded by '{}' are not {}-dimensional as embedding.dimension configures; correct the setting or run the reembed tool",
+                mismatched, primary, expected
+            );
+        }
+        Ok(server)
     }
 
-    async fn process_document(&self, path: &str, doc_type: Option<&str>) -> Result<usize> {
+    /// The file the config was loaded from, which `tune_ranking` may update
+    pub fn with_config_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Make a custom chunking strategy available under `name`, for doc types of that name and
+    /// for `chunking.strategies` to assign
+    pub fn with_chunking_strategy(mut self, name: &str, strategy: impl ChunkingStrategy + 'static) -> Self {
+        Arc::make_mut(&mut self.strategies).register(name, strategy);
+        self
+    }
+
+    async fn process_document(&self, path: &str, doc_type: Option<&str>, force: bool) -> Result<usize> {
+        if let Some(reason) = self.ingestion_filter.rejection_reason(std::path::Path::new(path)) {
+            return Err(anyhow::anyhow!("Skipped by ingestion filter: {}", reason));
+        }
+
+        if !force {
+            self.check_ingestion_limits(path)?;
+        }
+
         // Determine document type
-        let detected_type = doc_type.unwrap_or_else(|| {
-            match std::path::Path::new(path).extension().and_then(|s| s.to_str()) {
-                Some("pdf") => "pdf",
-                Some("md") | Some("markdown") => "markdown",
-                Some("txt") => "text",
-                Some(ext) if CodeProcessor::detect_language(path).is_some() => "code",
-                _ => "text"
-            }
-        });
+        let detected_type = doc_type.unwrap_or_else(|| Self::detect_type(path));
 
-        // Read file content
-        let content = if detected_type == "pdf" {
-            // PDF processing handled separately
-            String::new()
+        // Read file content, transcoding legacy encodings (Latin-1, UTF-16, ...) to UTF-8
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        let file_hash = format!("{:x}", Sha256::digest(&bytes));
+        let decoded = if matches!(detected_type, "pdf" | "pptx" | "email" | "image") {
+            // Binary formats are read by their processors
+            None
         } else {
-            std::fs::read_to_string(path)
-                .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?
+            let decoded = EncodingDetector::decode(&bytes);
+            if decoded.had_errors {
+                tracing::warn!("{} contained bytes invalid in detected encoding {}; they were replaced", path, decoded.encoding);
+            }
+            Some(decoded)
         };
+        let content = decoded.as_ref().map(|d| d.text.as_str()).unwrap_or("");
 
-        // Process based on type
-        let mut chunks = match detected_type {
-            "pdf" => PdfProcessor::extract_and_chunk(path, &self.chunker)?,
-            "markdown" => MarkdownProcessor::extract_and_chunk(&content, path, &self.chunker)?,
-            "code" => {
-                let language = CodeProcessor::detect_language(path).unwrap_or_else(|| "text".to_string());
-                CodeProcessor::extract_and_chunk(&content, &language, path, &self.chunker)?
-            },
-            _ => TextProcessor::extract_and_chunk(&content, path, &self.chunker)?,
-        };
+        // Chunk on the ingest pool so bulk ingestion cannot starve search
+        let mut chunks = self.pools.ingest.install(|| self.chunk_document(path, detected_type, content))?;
 
-        // Generate embeddings for chunks
+        // Record the source encoding so transcoded documents are identifiable
+        if let Some(decoded) = &decoded {
+            for chunk in &mut chunks {
+                chunk.metadata.attributes.insert("encoding".to_string(), decoded.encoding.clone());
+                if decoded.had_bom {
+                    chunk.metadata.attributes.insert("encoding_bom".to_string(), "true".to_string());
+                }
+            }
+        }
+        // Hash the file as read rather than the decoded text, so re-ingestion can compare it
         for chunk in &mut chunks {
-            let embedding = self.embedder.embed_text(&chunk.content)?;
-            chunk.embedding = embedding;
+            chunk.metadata.file_hash = Some(file_hash.clone());
         }
 
-        // Store chunks (Storage is now thread-safe, no need for write lock)
-        let chunk_count = chunks.len();
-        for chunk in &chunks {
-            self.storage.store_chunk(chunk)?;
+        self.store_chunks(path, chunks, force).await
+    }
+
+    /// Ingest text supplied directly in a request under a synthetic source name. The type is
+    /// taken from `doc_type` or the name's extension; `metadata` is stored as chunk attributes.
+    async fn process_text(&self, source: &str, doc_type: Option<&str>, text: &str, metadata: &std::collections::HashMap<String, String>, force: bool) -> Result<usize> {
+        if source.trim().is_empty() {
+            return Err(anyhow::anyhow!("A source name is required for raw text"));
         }
 
-        // Build graph relationships
-        {
-            let mut graph = self.graph.write().await;
-            graph.build_relationships(&chunks)?;
+        let limits = &self.config.ingestion;
+        if !force && text.len() as u64 > limits.max_file_size_bytes {
+            return Err(anyhow::anyhow!(
+                "Text for {} is {} bytes, exceeding ingestion.max_file_size_bytes ({}). Pass force=true to override.",
+                source, text.len(), limits.max_file_size_bytes
+            ));
         }
 
-        Ok(chunk_count)
+        let detected_type = doc_type.unwrap_or_else(|| Self::detect_type(source));
+        let mut chunks = match detected_type {
+            "email" => EmailProcessor::chunk_bytes(text.as_bytes(), source, self.chunker_for("email"))?,
+            "pdf" | "pptx" | "image" => {
+                return Err(anyhow::anyhow!("{} documents cannot be ingested from raw text; ingest the file instead", detected_type));
+            }
+            _ => self.pools.ingest.install(|| self.chunk_document(source, detected_type, text))?,
+        };
+
+        for chunk in &mut chunks {
+            chunk.metadata.attributes.insert("ingested_from".to_string(), "text".to_string());
+            for (key, value) in metadata {
+                chunk.metadata.attributes.insert(key.clone(), value.clone());
+            }
+        }
+
+        self.store_chunks(source, chunks, force).await
     }
 
-    async fn search_chunks(&self, query: &str, top_k: usize) -> Result<Vec<SearchResult>> {
-        // Generate query embedding
-        let query_embedding = self.embedder.embed_text(query)?;
+    /// Fetch a web page or file and ingest it with the URL as its source. The type comes from
+    /// `doc_type`, the Content-Type header, the body, or the URL's extension, in that order.
+    async fn process_url(&self, url: &str, doc_type: Option<&str>, force: bool) -> Result<(usize, Value)> {
+        let fetched = self.fetch(url, force).await?;
+        self.ingest_fetched(url, &fetched, doc_type, force, "url").await
+    }
+
+    async fn fetch(&self, url: &str, force: bool) -> Result<FetchedDocument> {
+        let max_bytes = if force { u64::MAX } else { self.config.ingestion.max_file_size_bytes };
+        let timeout = std::time::Duration::from_secs(self.config.ingestion.fetch_timeout_secs);
+        let request_url = url.to_string();
+        tokio::task::spawn_blocking(move || fetch_url(&request_url, max_bytes, timeout)).await?
+    }
+
+    fn fetched_type<'a>(url: &str, fetched: &FetchedDocument, doc_type: Option<&'a str>) -> &'a str {
+        let url_path = url.split(['?', '#']).next().unwrap_or(url);
+        doc_type
+            .or_else(|| fetched.detected_type())
+            .unwrap_or_else(|| Self::detect_type(url_path))
+    }
 
-        // Search for similar chunks (Storage is now thread-safe)
-        let mut results = self.storage.search_similar(&query_embedding, top_k * 2); // Get more for reranking
+    /// `origin` is recorded as `ingested_from`: "url" documents are re-fetched by
+    /// `refresh_urls`, "object_store" ones are kept up to date by source syncs
+    async fn ingest_fetched(&self, url: &str, fetched: &FetchedDocument, doc_type: Option<&str>, force: bool, origin: &str) -> Result<(usize, Value)> {
+        let detected_type = Self::fetched_type(url, fetched, doc_type);
 
-        // If vector search doesn't find enough results, fallback to text search
-        if results.len() < top_k {
-            let mut text_results = self.storage.search_by_text(query, top_k);
-            results.append(&mut text_results);
+        let mut chunks = match detected_type {
+            "pdf" => self.pools.ingest.install(|| PdfProcessor::chunk_bytes(&fetched.bytes, url, self.chunker_for("pdf")))?,
+            "email" => EmailProcessor::chunk_bytes(&fetched.bytes, url, self.chunker_for("email"))?,
+            "pptx" | "image" => {
+                return Err(anyhow::anyhow!("{} documents cannot be ingested from a URL; download and ingest the file instead", detected_type));
+            }
+            _ => {
+                let decoded = EncodingDetector::decode(&fetched.bytes);
+                self.pools.ingest.install(|| self.chunk_document(url, detected_type, &decoded.text))?
+            }
+        };
 
-            // Remove duplicates and sort by score
-            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-            results.dedup_by(|a, b| a.chunk_id == b.chunk_id);
+        let fetched_at = fetched.fetched_at.to_rfc3339();
+        let file_hash = Self::file_hash(fetched);
+        for chunk in &mut chunks {
+            chunk.metadata.file_hash = Some(file_hash.clone());
+            let attributes = &mut chunk.metadata.attributes;
+            attributes.insert("ingested_from".to_string(), origin.to_string());
+            attributes.insert("fetched_at".to_string(), fetched_at.clone());
+            if let Some(content_type) = &fetched.content_type {
+                attributes.insert("content_type".to_string(), content_type.clone());
+            }
+            if let Some(etag) = &fetched.etag {
+                attributes.insert("etag".to_string(), etag.clone());
+            }
+            if fetched.url != url {
+                attributes.insert("redirected_to".to_string(), fetched.url.clone());
+            }
         }
 
-        // Apply graph-based reranking if needed
-        results = self.apply_graph_reranking(results).await;
+        let chunk_count = self.store_chunks(url, chunks, force).await?;
+        Ok((chunk_count, json!({
+            "doc_type": detected_type,
+            "content_type": fetched.content_type,
+            "fetched_at": fetched_at,
+            "final_url": fetched.url
+        })))
+    }
 
-        Ok(results.into_iter().take(top_k).collect())
+    fn file_hash(fetched: &FetchedDocument) -> String {
+        format!("{:x}", Sha256::digest(&fetched.bytes))
     }
 
-    async fn search_chapters(&self, query: &str, top_k: usize) -> Result<Vec<Value>> {
-        // First find relevant chunks - get more results to ensure we capture chapters
-        let chunk_results = self.search_chunks(query, top_k * 5).await?;
+    /// Re-fetch every document ingested from a URL and re-ingest the ones whose content hash
+    /// changed, replacing their chunks. Documents that fail to download keep their chunks;
+    /// documents ingested before hashes were recorded are re-ingested once.
+    async fn refresh_url_documents(&self) -> Result<Value> {
+        let mut documents: std::collections::BTreeMap<String, (Vec<Chunk>, Option<String>)> = std::collections::BTreeMap::new();
+        let from_url = ChunkQuery::all().with_predicate(|m| m.attributes.get("ingested_from").is_some_and(|from| from == "url"));
+        for chunk in self.storage.scan(from_url) {
+            let (chunks, hash) = documents.entry(chunk.metadata.source_file.clone()).or_default();
+            if hash.is_none() {
+                *hash = chunk.metadata.file_hash.clone();
+            }
+            chunks.push(chunk);
+        }
 
-        // Group by chapter and aggregate scores
-        let mut chapter_scores: std::collections::HashMap<String, (f32, Vec<SearchResult>)> = std::collections::HashMap::new();
+        let total = documents.len();
+        let mut reports = Vec::new();
+        let (mut refreshed, mut unchanged, mut failed) = (0, 0, 0);
+        self.with_graph_batch(async {
+            for (i, (url, (old_chunks, old_hash))) in documents.into_iter().enumerate() {
+                let outcome = async {
+                    let fetched = self.fetch(&url, false).await?;
+                    if old_hash.as_deref() == Some(Self::file_hash(&fetched).as_str()) {
+                        return Ok(None);
+                    }
+                    let (count, _) = self.ingest_fetched(&url, &fetched, None, false, "url").await?;
+                    self.remove_superseded(&old_chunks).await?;
+                    Ok::<_, anyhow::Error>(Some(count))
+                }.await;
 
-        let chunk_results_clone = chunk_results.clone();
+                let report = match outcome {
+                    Ok(Some(count)) => {
+                        refreshed += 1;
+                        json!({"url": url, "status": "refreshed", "chunks_replaced": old_chunks.len(), "chunks_created": count})
+                    }
+                    Ok(None) => {
+                        unchanged += 1;
+                        json!({"url": url, "status": "unchanged"})
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        json!({"url": url, "status": "failed", "error": e.to_string()})
+                    }
+                };
+                tracing::info!("Refresh {}/{}: {} {}", i + 1, total, url, report["status"].as_str().unwrap_or(""));
+                reports.push(report);
+            }
+            Ok(())
+        }).await?;
 
-        for result in chunk_results {
-            // Check both chapter and section fields for chapter information
-            let chapter_name = result.metadata.get("chapter")
-                .or_else(|| result.metadata.get("section"))
-                .cloned();
+        Ok(json!({
+            "documents_checked": total,
+            "refreshed": refreshed,
+            "unchanged": unchanged,
+            "failed": failed,
+            "documents": reports
+        }))
+    }
 
-            if let Some(chapter) = chapter_name {
-                let source_file = result.metadata.get("source_file")
-                    .map(|s| s.as_str())
-                    .unwrap_or("unknown");
-                let chapter_key = format!("{}#{}", source_file, chapter);
-                let entry = chapter_scores.entry(chapter_key).or_insert((0.0, Vec::new()));
-                entry.0 += result.score;
-                entry.1.push(result);
-            }
+    /// Ingest a changed file again and drop its previous chunks. Returns None when the
+    /// content is unchanged; files ingested before their hash was recorded are always
+    /// re-ingested.
+    async fn reingest_file(&self, path: &str) -> Result<Option<usize>> {
+        let previous: Vec<Chunk> = self.storage.scan(ChunkQuery::all().with_file(path)).collect();
+        if Self::content_changed(path, &previous)? == Some(false) {
+            return Ok(None);
         }
+        self.replace_file(path, previous).await.map(Some)
+    }
 
-        // Also try to include chunks that don't have explicit chapter metadata but are relevant
-        // This helps with documents that might not have perfect chapter extraction
-        if chapter_scores.is_empty() {
-            // If no chapters found, group by section or file as fallback
-            for result in &chunk_results_clone[0..std::cmp::min(chunk_results_clone.len(), top_k * 2)] {
-                let section_or_file = result.metadata.get("section")
-                    .or_else(|| result.metadata.get("source_file"))
-                    .cloned()
-                    .unwrap_or_else(|| "Unknown Section".to_string());
+    /// Whether a file differs from the content its chunks were made from, by the
+    /// `file_hash` recorded on them. None when no hash was recorded.
+    fn content_changed(path: &str, previous: &[Chunk]) -> Result<Option<bool>> {
+        let Some(recorded_hash) = previous.first().and_then(|chunk| chunk.metadata.file_hash.as_ref()) else {
+            return Ok(None);
+        };
+        let bytes = std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        Ok(Some(*recorded_hash != format!("{:x}", Sha256::digest(&bytes))))
+    }
 
-                let source_file = result.metadata.get("source_file")
-                    .map(|s| s.as_str())
-                    .unwrap_or("unknown");
-                let chapter_key = format!("{}#{}", source_file, section_or_file);
-                let entry = chapter_scores.entry(chapter_key).or_insert((0.0, Vec::new()));
-                entry.0 += result.score;
-                entry.1.push(result.clone());
+    /// Ingest a file and drop the chunks of its previous version
+    async fn replace_file(&self, path: &str, previous: Vec<Chunk>) -> Result<usize> {
+        let count = self.process_document(path, None, false).await?;
+        self.remove_superseded(&previous).await?;
+        Ok(count)
+    }
+
+    /// Drop the chunks of a deleted file, or of every file under a deleted directory
+    async fn remove_file_chunks(&self, path: &str) -> Result<usize> {
+        let under = |file: &str| file == path || file.strip_prefix(path).is_some_and(|rest| rest.starts_with(std::path::MAIN_SEPARATOR));
+        let ids: Vec<String> = self.storage.scan(ChunkQuery::all().with_file_prefix(path).with_predicate(|m| under(&m.source_file)))
+            .map(|chunk| chunk.id)
+            .collect();
+        self.remove_chunks(&ids).await?;
+        Ok(ids.len())
+    }
+
+    /// Bring the index up to date with a batch of changed paths from watched sources
+    async fn apply_source_changes(&self, watcher: &SourceWatcher, changed: std::collections::BTreeSet<std::path::PathBuf>) {
+        for event_path in changed {
+            let Some((path, scanner)) = watcher.source_path(&event_path) else { continue };
+            let path_str = path.to_string_lossy().to_string();
+            let outcome = if event_path.is_file() {
+                if !scanner.selects(&path) || !self.ingestion_filter.is_allowed(&path) {
+                    continue;
+                }
+                match self.reingest_file(&path_str).await {
+                    Ok(Some(count)) => Ok(format!("ingested, {} chunks", count)),
+                    Ok(None) => continue,
+                    Err(e) => Err(e),
+                }
+            } else if event_path.is_dir() {
+                // A directory moved into the source: pick up the files it brought along
+                let root = scanner.root().to_string_lossy().to_string();
+                self.sync_sources(false, Some(&root)).await.map(|_| "new and changed files ingested".to_string())
+            } else {
+                match self.remove_file_chunks(&path_str).await {
+                    Ok(0) => continue,
+                    Ok(count) => Ok(format!("removed, {} chunks", count)),
+                    Err(e) => Err(e),
+                }
+            };
+
+            match outcome {
+                Ok(summary) => tracing::info!("Source watch: {} {}", path_str, summary),
+                Err(e) => tracing::error!("Source watch: failed to update {}: {}", path_str, e),
             }
         }
+    }
 
-        // Sort chapters by aggregated score
-        let mut sorted_chapters: Vec<_> = chapter_scores.into_iter().collect();
-        sorted_chapters.sort_by(|a, b| b.1.0.partial_cmp(&a.1.0).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Return chapter information
-        let results: Vec<Value> = sorted_chapters
-            .into_iter()
-            .take(top_k)
-            .map(|(chapter_key, (score, chunks))| {
-                let parts: Vec<&str> = chapter_key.split('#').collect();
-                let file_path = parts.get(0).unwrap_or(&"unknown");
-                let chapter_name = parts.get(1).unwrap_or(&"unknown");
+    /// Run a bulk ingest with its graph updates made on one working copy of the graph,
+    /// published and saved once when it finishes, whether or not it succeeds
+    async fn with_graph_batch<T>(&self, work: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        self.graph.begin_batch().await;
+        let result = work.await;
+        self.graph.end_batch().await.and(result)
+    }
 
-                // Average the score by number of chunks for better ranking
-                let avg_score = if chunks.is_empty() { 0.0 } else { score / chunks.len() as f32 };
+    /// Delete replaced chunks from storage and the relationship graph
+    async fn remove_chunks(&self, chunk_ids: &[String]) -> Result<()> {
+        for chunk_id in chunk_ids {
+            self.storage.remove_chunk(chunk_id)?;
+        }
+        self.graph.update(|graph| {
+            graph.remove_chunks(&chunk_ids.iter().cloned().collect());
+            Ok(())
+        }).await
+    }
 
-                json!({
-                    "chapter": chapter_name,
-                    "file": file_path,
-                    "score": avg_score,
-                    "total_score": score,
-                    "chunk_count": chunks.len(),
-                    "chunks": chunks.iter().map(|c| json!({
-                        "id": c.chunk_id,
-                        "content": c.content,
-                        "score": c.score,
-                        "metadata": c.metadata
-                    })).collect::<Vec<_>>()
-                })
+    /// Drop the chunks of a document's previous version once the new version is stored.
+    /// Chunks the new version produced again were stored under the same id with a fresh
+    /// timestamp, so those are kept.
+    async fn remove_superseded(&self, previous: &[Chunk]) -> Result<()> {
+        let superseded: Vec<String> = previous.iter()
+            .filter(|old| {
+                self.storage.get_chunk(&old.id).ok().flatten()
+                    .is_none_or(|stored| stored.metadata.timestamp == old.metadata.timestamp)
             })
+            .map(|old| old.id.clone())
             .collect();
+        self.remove_chunks(&superseded).await
+    }
+
+    /// Ingest the pages of a site starting at `url`: those its sitemap lists, or those reached
+    /// by following same-origin links breadth-first. Pages already ingested are not ingested
+    /// again (their links are still followed); every page's outcome is reported.
+    async fn crawl_site(&self, url: &str, mode: CrawlMode, max_depth: usize, max_pages: usize, force: bool) -> Result<Value> {
+        let mut frontier = match mode {
+            CrawlMode::Links => {
+                let mut frontier = CrawlFrontier::new(url, max_depth, max_pages)?;
+                frontier.push(url, 0);
+                frontier
+            }
+            CrawlMode::Sitemap => {
+                let mut frontier = CrawlFrontier::new(url, 0, max_pages)?;
+                for page in self.sitemap_pages(url, max_pages, force).await? {
+                    frontier.push(&page, 0);
+                }
+                frontier
+            }
+        };
+
+        let mut ingested_pages: std::collections::HashSet<String> = self.storage.list_files()?.into_iter().collect();
+        let mut pages = Vec::new();
+        let (mut ingested, mut chunks) = (0, 0);
+        self.with_graph_batch(async {
+            while let Some((page, depth)) = frontier.next_page() {
+                let outcome = async {
+                    let fetched = self.fetch(&page, force).await?;
+                    if mode == CrawlMode::Links && depth < max_depth && Self::fetched_type(&page, &fetched, None) == "html" {
+                        let html = EncodingDetector::decode(&fetched.bytes).text;
+                        for link in extract_links(&html, &fetched.url) {
+                            frontier.push(&link, depth + 1);
+                        }
+                    }
+                    if ingested_pages.contains(&page) {
+                        return Ok(None);
+                    }
+                    self.ingest_fetched(&page, &fetched, None, force, "url").await.map(|(count, _)| Some(count))
+                }.await;
+
+                let report = match outcome {
+                    Ok(Some(count)) => {
+                        ingested += 1;
+                        chunks += count;
+                        ingested_pages.insert(page.clone());
+                        json!({"url": page, "depth": depth, "status": "ingested", "chunks_created": count})
+                    }
+                    Ok(None) => json!({"url": page, "depth": depth, "status": "already_ingested"}),
+                    Err(e) => json!({"url": page, "depth": depth, "status": "failed", "error": e.to_string()}),
+                };
+                tracing::info!("Crawl {}/{}: {} {}", frontier.visited(), max_pages, page, report["status"].as_str().unwrap_or(""));
+                pages.push(report);
+            }
+            Ok(())
+        }).await?;
+
+        Ok(json!({
+            "pages_visited": pages.len(),
+            "pages_ingested": ingested,
+            "chunks_created": chunks,
+            "pages": pages
+        }))
+    }
+
+    /// Page URLs listed by the sitemap for `url`, following a sitemap index one level down
+    async fn sitemap_pages(&self, url: &str, max_pages: usize, force: bool) -> Result<Vec<String>> {
+        let sitemap_url = default_sitemap_url(url)?;
+        let (mut pages, nested) = sitemap_locations(&EncodingDetector::decode(&self.fetch(&sitemap_url, force).await?.bytes).text);
+        for sitemap in nested {
+            if pages.len() >= max_pages {
+                break;
+            }
+            match self.fetch(&sitemap, force).await {
+                Ok(fetched) => pages.extend(sitemap_locations(&EncodingDetector::decode(&fetched.bytes).text).0),
+                Err(e) => tracing::warn!("Skipping sitemap {}: {}", sitemap, e),
+            }
+        }
 
-        Ok(results)
+        if pages.is_empty() {
+            return Err(anyhow::anyhow!("Sitemap {} lists no pages", sitemap_url));
+        }
+        Ok(pages)
     }
 
-    async fn apply_graph_reranking(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
-        // For now, return results as-is
-        // In a full implementation, this would use graph relationships to boost related content
-        results
+    /// Store a short memory as its own chunk in the agent-memory collection
+    async fn store_memory(&self, text: &str, tags: &[String]) -> Result<Chunk> {
+        if text.trim().is_empty() {
+            return Err(anyhow::anyhow!("Memory text is empty"));
+        }
+
+        let chunk = build_memory_chunk(text, tags, chrono::Utc::now());
+        let source = chunk.metadata.source_file.clone();
+        self.store_chunks(&source, vec![chunk.clone()], false).await?;
+        self.collections.write().await.assign(&source, MEMORY_COLLECTION)?;
+        Ok(chunk)
     }
-}
 
-impl RagMcp for McpServer {
-    fn ingest(&self, path: String, doc_type: Option<String>) -> Result<Value, JsonRpcError> {
-        // Use a blocking approach to avoid runtime conflicts
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                self.process_document(&path, doc_type.as_deref()).await
-            })
-        });
+    /// Memories ranked by relevance to `query` blended with recency
+    async fn recall_memories(&self, query: &str, top_k: usize, tags: &[String]) -> Result<(Vec<SearchResult>, String)> {
+        self.check_embedding_policy()?;
+        let (candidates, provider) = self.pools.search.install(|| -> Result<(Vec<SearchResult>, String)> {
+            let (query_embedding, provider) = self.embedder.embed_query(query)?;
+            self.check_query_dimension(&provider, query_embedding.len())?;
+            // Memories share the index with documents; only they are scored
+            let memory_ids: std::collections::HashSet<String> = self.storage.matching_ids(ChunkQuery::all().with_file_prefix(MEMORY_SOURCE_PREFIX)).collect();
+            let mut candidates = self.storage.search_similar_in(&query_embedding, &memory_ids, memory_ids.len());
+            self.retain_same_space(&mut candidates, &provider);
+            Ok((candidates, provider))
+        })?;
 
-        match result {
-            Ok(chunk_count) => Ok(json!({
-                "status": "success",
-                "chunks_created": chunk_count,
-                "document_path": path
-            })),
-            Err(e) => {
-                let mut error = JsonRpcError::internal_error();
-                error.message = format!("Ingestion failed: {}", e);
-                error.data = Some(json!({"path": path}));
-                Err(error)
+        let weights = RecallWeights {
+            recency_weight: self.config.memory.recency_weight,
+            half_life_days: self.config.memory.half_life_days,
+        };
+        let now = chrono::Utc::now();
+        let memories = rank_memories(candidates, tags, weights, now, top_k);
+
+        // Recalling a memory resets its decay
+        let _writes = self.memory_writes.lock().unwrap();
+        for memory in &memories {
+            if let Some(mut chunk) = self.storage.get_chunk(&memory.chunk_id)? {
+                chunk.metadata.attributes.insert("last_recalled".to_string(), now.to_rfc3339());
+                chunk.metadata.attributes.remove("strength");
+                self.storage.store_chunk(&chunk)?;
             }
         }
+        Ok((memories, provider))
     }
 
-    fn search_knowledge_chunk(&self, query: String, top_k: Option<usize>) -> Result<Value, JsonRpcError> {
-        let k = top_k.unwrap_or(10);
+    /// Document type from a file name's extension, defaulting to plain text
+    fn detect_type(path: &str) -> &'static str {
+        match std::path::Path::new(path).extension().and_then(|s| s.to_str()) {
+            Some("pdf") => "pdf",
+            Some("md") | Some("markdown") => "markdown",
+            Some("html") | Some("htm") | Some("xhtml") => "html",
+            Some("txt") => "text",
+            Some("csv") => "csv",
+            Some("tsv") | Some("tab") => "tsv",
+            Some("json") => "json",
+            Some("jsonl") | Some("ndjson") => "jsonl",
+            Some("xml") => "xml",
+            Some("adoc") | Some("asciidoc") | Some("asc") => "asciidoc",
+            Some("org") => "org",
+            Some("pptx") => "pptx",
+            Some("eml") | Some("mbox") | Some("mbx") => "email",
+            Some("log") => "log",
+            Some("png") | Some("jpg") | Some("jpeg") => "image",
+            Some("srt") | Some("vtt") => "subtitle",
+            Some("yaml") | Some("yml") => "yaml",
+            Some("toml") => "toml",
+            Some("ini") | Some("cfg") => "ini",
+            Some("proto") => "proto",
+            Some("thrift") => "thrift",
+            Some("avdl") => "avdl",
+            Some(ext) if CodeProcessor::detect_language(path).is_some() => "code",
+            _ => "text"
+        }
+    }
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                self.search_chunks(&query, k).await
-            })
-        });
+    /// Embed chunks, store them and link them into the graph
+    async fn store_chunks(&self, path: &str, mut chunks: Vec<Chunk>, force: bool) -> Result<usize> {
+        let limits = &self.config.ingestion;
+        if !force && chunks.len() > limits.max_chunks_per_document {
+            return Err(anyhow::anyhow!(
+                "{} produced {} chunks, exceeding ingestion.max_chunks_per_document ({}). Pass force=true to override.",
+                path, chunks.len(), limits.max_chunks_per_document
+            ));
+        }
 
-        match result {
-            Ok(results) => Ok(json!({
-                "query": query,
-                "chunks": results.iter().map(|r| json!({
-                    "id": r.chunk_id,
-                    "content": r.content,
-                    "score": r.score,
-                    "metadata": r.metadata
-                })).collect::<Vec<_>>(),
-                "total_found": results.len()
-            })),
-            Err(e) => {
-                let mut error = JsonRpcError::internal_error();
-                error.message = format!("Search failed: {}", e);
-                error.data = Some(json!({"query": query}));
-                Err(error)
+        // New chunks must be embedded like the ones already indexed; the first ingest fixes the policy
+        let policy = self.embedder.policy();
+        match EmbeddingPolicy::recorded(self.storage.data_dir())? {
+            None => policy.record(self.storage.data_dir())?,
+            recorded => policy.ensure_matches(recorded.as_ref())?,
+        }
+        // Prose records its human language, for language filters and keyword tokenization
+        if self.config.chunking.detect_language {
+            for chunk in chunks.iter_mut().filter(|chunk| chunk.metadata.language.is_none() && !matches!(chunk.metadata.chunk_type, ChunkType::Code)) {
+                chunk.metadata.language = natural_language::detect(&chunk.content).map(str::to_string);
             }
         }
-    }
 
-    fn search_knowledge_chapter(&self, query: String, top_k: Option<usize>) -> Result<Value, JsonRpcError> {
-        let k = top_k.unwrap_or(5);
+        // Titles and summaries for result presentation; memories are short enough as they are
+        if let Some(summarizer) = self.summarizer.as_ref().filter(|_| !path.starts_with(MEMORY_SOURCE_PREFIX)) {
+            self.pools.ingest.install(|| summarizer.annotate(&mut chunks));
+        }
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                self.search_chapters(&query, k).await
-            })
-        });
+        // Generate embeddings in token-packed batches (or late-chunked passages) on the lower-priority ingest pool
+        let all: Vec<usize> = (0..chunks.len()).collect();
+        self.embed_chunks(&mut chunks, &all)?;
 
-        match result {
-            Ok(chapters) => Ok(json!({
-                "query": query,
-                "chapters": chapters,
-                "total_found": chapters.len()
-            })),
-            Err(e) => {
-                let mut error = JsonRpcError::internal_error();
-                error.message = format!("Chapter search failed: {}", e);
-                error.data = Some(json!({"query": query}));
+        // Every ingest path ends here, so the corpus cap is enforced once, on the bytes stored
+        let stored_bytes = chunks.iter().map(|chunk| self.storage.encoded_size(chunk)).sum::<Result<u64>>()?;
+        self.reserve_corpus_bytes(path, stored_bytes, force)?;
+
+        // Store chunks (Storage is now thread-safe, no need for write lock)
+        let chunk_count = chunks.len();
+        for chunk in &chunks {
+            self.storage.store_chunk(chunk)?;
+            self.query_enhancer.learn_acronyms(&chunk.content);
+        }
+
+        // Learned sparse term weights; chunks the model fails on are still found by the other lists
+        if let Some(encoder) = &self.sparse_encoder {
+            let texts: Vec<String> = chunks.iter().map(|chunk| self.embedding_text(chunk)).collect();
+            let batch_size = self.config.embedding.batch_size.max(1);
+            let encoded = self.pools.ingest.install(|| texts.chunks(batch_size).map(|batch| encoder.encode(batch)).collect::<Result<Vec<_>>>());
+            match encoded {
+                Ok(batches) => {
+                    for (chunk, vector) in chunks.iter().zip(batches.into_iter().flatten()) {
+                        self.storage.store_sparse(&chunk.id, &vector)?;
+                    }
+                }
+                Err(e) => tracing::warn!("Sparse encoding of {} failed, leaving its chunks out of the sparse list: {}", path, e),
+            }
+        }
+
+        // Build graph relationships; chunks stored again replace their old nodes and edges
+        let ids: std::collections::HashSet<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+        self.graph.update(|graph| {
+            graph.remove_chunks(&ids);
+            graph.build_relationships(&chunks)
+        }).await?;
+
+        Ok(chunk_count)
+    }
+
+    /// A chunker per doc type in `chunking.size_limits`, bounded by its limits
+    fn type_chunkers(config: &Config, chunker: &SemanticChunker) -> Result<std::collections::HashMap<String, SemanticChunker>> {
+        config.chunking.size_limits.iter()
+            .map(|(doc_type, limits)| {
+                let max = limits.max_chunk_size.unwrap_or(config.storage.max_chunk_size);
+                let min = limits.min_chunk_size.unwrap_or(config.storage.min_chunk_size);
+                if min > max {
+                    return Err(anyhow::anyhow!("chunking.size_limits.{}: min_chunk_size {} exceeds max_chunk_size {}", doc_type, min, max));
+                }
+                Ok((doc_type.clone(), chunker.clone().with_size_limits(max, min)))
+            })
+            .collect()
+    }
+
+    /// The chunker for a detected doc type
+    fn chunker_for(&self, doc_type: &str) -> &SemanticChunker {
+        self.type_chunkers.get(doc_type).unwrap_or(&self.chunker)
+    }
+
+    /// Chunk a document's text, segmented first by the first configured splitter that applies
+    fn chunk_document(&self, path: &str, detected_type: &str, content: &str) -> Result<Vec<Chunk>> {
+        // Types chunked from the file itself have no text to segment
+        let from_file = FILE_DOC_TYPES.contains(&detected_type);
+        let Some(splitter) = self.splitters.iter().find(|s| !from_file && s.applies_to(path, detected_type)) else {
+            return self.chunk_content(path, detected_type, content);
+        };
+
+        let mut chunks = Vec::new();
+        for (i, segment) in splitter.segments(content).iter().enumerate() {
+            let mut segment_chunks = self.chunk_content(path, detected_type, &segment.text)?;
+            segment.place(&mut segment_chunks, i);
+            chunks.extend(segment_chunks);
+        }
+        Ok(chunks)
+    }
+
+    fn chunk_content(&self, path: &str, detected_type: &str, content: &str) -> Result<Vec<Chunk>> {
+        let input = ChunkInput { path, doc_type: detected_type, content };
+        self.strategies.chunk(&input, self.chunker_for(detected_type))
+    }
+
+    /// Refuse directories and files too large to ingest before reading them
+    fn check_ingestion_limits(&self, path: &str) -> Result<()> {
+        let limits = &self.config.ingestion;
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+        if metadata.is_dir() {
+            return Err(anyhow::anyhow!("{} is a directory; ingest individual files", path));
+        }
+
+        if metadata.len() > limits.max_file_size_bytes {
+            return Err(anyhow::anyhow!(
+                "{} is {} bytes, exceeding ingestion.max_file_size_bytes ({}). Pass force=true to override.",
+                path, metadata.len(), limits.max_file_size_bytes
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Account for `stored_bytes` about to be written for `path`, refusing them unless `force`
+    /// when the corpus would outgrow `ingestion.max_corpus_size_bytes`. Walking the data
+    /// directory per document would dominate a large sync, so its size is measured once and
+    /// again only when the running total says the limit is reached.
+    fn reserve_corpus_bytes(&self, path: &str, stored_bytes: u64, force: bool) -> Result<()> {
+        let limit = self.config.ingestion.max_corpus_size_bytes;
+        let mut corpus_bytes = self.corpus_bytes.lock().unwrap();
+        let mut corpus_size = *corpus_bytes.get_or_insert_with(|| Self::dir_size(self.storage.data_dir()));
+        if !force && corpus_size + stored_bytes > limit {
+            corpus_size = Self::dir_size(self.storage.data_dir());
+            *corpus_bytes = Some(corpus_size);
+            if corpus_size + stored_bytes > limit {
+                return Err(anyhow::anyhow!(
+                    "Storing {} would bring the corpus to {} bytes, exceeding ingestion.max_corpus_size_bytes ({}). Pass force=true to override.",
+                    path, corpus_size + stored_bytes, limit
+                ));
+            }
+        }
+        *corpus_bytes = Some(corpus_size + stored_bytes);
+        Ok(())
+    }
+
+    /// Query vectors are only comparable to chunk vectors embedded under the same policy
+    fn check_embedding_policy(&self) -> Result<()> {
+        let recorded = EmbeddingPolicy::recorded(self.storage.data_dir())?;
+        self.embedder.policy().ensure_matches(recorded.as_ref())
+    }
+
+    /// The chain that embeds chunks of a type: the code chain for Code chunks when configured
+    fn embedder_for(&self, chunk_type: &ChunkType) -> &ProviderChain {
+        match (&self.code_embedder, chunk_type) {
+            (Some(code), ChunkType::Code) => code.as_ref(),
+            _ => self.embedder.as_ref(),
+        }
+    }
+
+    /// The model of a provider in either chain
+    fn model_of(&self, provider: &str) -> Option<&str> {
+        self.embedder.model_of(provider).or_else(|| self.code_embedder.as_ref().and_then(|code| code.model_of(provider)))
+    }
+
+    /// The text embedded for a chunk under the current config
+    fn embedding_text(&self, chunk: &Chunk) -> String {
+        let embedding = &self.config.embedding;
+        chunk.embedding_text(embedding.context_headers, &embedding.metadata_fields)
+    }
+
+    /// The text a chunk was embedded from, rebuilt from the attributes recorded at the time
+    fn recorded_embedding_text(chunk: &Chunk) -> String {
+        let attributes = &chunk.metadata.attributes;
+        let fields: Vec<EmbeddedField> = attributes.get(EMBEDDED_FIELDS_ATTRIBUTE)
+            .map(|names| names.split(',').filter_map(EmbeddedField::from_name).collect())
+            .unwrap_or_default();
+        chunk.embedding_text(attributes.contains_key(CONTEXT_HEADER_ATTRIBUTE), &fields)
+    }
+
+    /// Embed the chunks at `targets` with the chain for their type, one call per chain so a
+    /// document's chunks of a type share a provider, and record how each vector and the text
+    /// behind it were produced
+    fn embed_chunks(&self, chunks: &mut [Chunk], targets: &[usize]) -> Result<()> {
+        let config = &self.config.embedding;
+        let fields = config.metadata_fields.iter().map(|field| field.name()).collect::<Vec<_>>().join(",");
+        let (code, other): (Vec<usize>, Vec<usize>) = targets.iter()
+            .partition(|&&j| self.code_embedder.is_some() && matches!(chunks[j].metadata.chunk_type, ChunkType::Code));
+        for (embedder, group) in [(self.embedder.as_ref(), other), (self.embedder_for(&ChunkType::Code), code)] {
+            if group.is_empty() {
+                continue;
+            }
+            let texts: Vec<String> = group.iter().map(|&j| self.embedding_text(&chunks[j])).collect();
+            let (embeddings, provider, late) = self.pools.ingest.install(|| embedder.embed_document_chunks(&texts))?;
+            for (&j, embedding) in group.iter().zip(embeddings) {
+                chunks[j].embedding = embedding;
+                self.mark_embedded(&mut chunks[j], &provider);
+                let attributes = &mut chunks[j].metadata.attributes;
+                if late {
+                    attributes.insert(LATE_CHUNKING_ATTRIBUTE.to_string(), "true".to_string());
+                } else {
+                    attributes.remove(LATE_CHUNKING_ATTRIBUTE);
+                }
+                if config.context_headers {
+                    attributes.insert(CONTEXT_HEADER_ATTRIBUTE.to_string(), "true".to_string());
+                } else {
+                    attributes.remove(CONTEXT_HEADER_ATTRIBUTE);
+                }
+                if fields.is_empty() {
+                    attributes.remove(EMBEDDED_FIELDS_ATTRIBUTE);
+                } else {
+                    attributes.insert(EMBEDDED_FIELDS_ATTRIBUTE.to_string(), fields.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record how a chunk's new vector was produced: provider, model, dimension and policy
+    fn mark_embedded(&self, chunk: &mut Chunk, provider: &str) {
+        let policy = self.embedder.policy();
+        let model = self.model_of(provider).unwrap_or(provider).to_string();
+        let attributes = &mut chunk.metadata.attributes;
+        attributes.insert("embedding_provider".to_string(), provider.to_string());
+        attributes.insert(EMBEDDING_MODEL_ATTRIBUTE.to_string(), model);
+        attributes.insert(EMBEDDING_DIMENSION_ATTRIBUTE.to_string(), chunk.embedding.len().to_string());
+        attributes.insert("embedding_normalized".to_string(), policy.normalize.to_string());
+        attributes.insert("embedding_pooling".to_string(), policy.pooling.name().to_string());
+    }
+
+    /// Ids of chunks whose vectors the current model and policy would not reproduce, decided
+    /// from stored metadata alone
+    fn stale_chunk_ids(&self) -> Vec<String> {
+        let query = ChunkQuery::all().with_predicate(|metadata| self.chunk_stale_reason(metadata).is_some());
+        self.storage.matching_ids(query).collect()
+    }
+
+    /// Why a chunk's vector is not what its type's chain would produce now, if it is not
+    fn chunk_stale_reason(&self, metadata: &ChunkMetadata) -> Option<&'static str> {
+        let embedder = self.embedder_for(&metadata.chunk_type);
+        let (primary, model) = embedder.primary();
+        stale_reason(&metadata.attributes, model, |provider| self.model_of(provider), embedder.policy()).or_else(|| {
+            // The model kept its name but now returns vectors of another length
+            let (current, _) = self.query_dimensions.lock().unwrap().get(primary).copied()?;
+            recorded_dimension(&metadata.attributes, primary).filter(|&dimension| dimension != current).map(|_| "dimension")
+        })
+    }
+
+    /// Refuse to compare a query vector from `provider` with stored vectors of another length,
+    /// which would all score 0. Once its chunks match, a provider is not checked again until
+    /// its query length changes.
+    fn check_query_dimension(&self, provider: &str, dimension: usize) -> Result<()> {
+        if self.query_dimensions.lock().unwrap().get(provider) == Some(&(dimension, true)) {
+            return Ok(());
+        }
+        let mismatched = self.storage.matching_ids(ChunkQuery::all().with_predicate(|metadata| {
+            recorded_dimension(&metadata.attributes, provider).is_some_and(|recorded| recorded != dimension)
+        })).count();
+        // Recorded either way, so the reembed tool treats the mismatched chunks as stale
+        self.query_dimensions.lock().unwrap().insert(provider.to_string(), (dimension, mismatched == 0));
+        if mismatched > 0 {
+            return Err(anyhow::anyhow!(
+                "Embedding dimension mismatch: '{}' now returns {}-dimensional vectors but {} chunks it embedded have another length, so they cannot be compared. Run the reembed tool to re-embed them",
+                provider, dimension, mismatched
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drop vector hits whose chunks were embedded by a provider in a different vector space
+    fn retain_same_space(&self, results: &mut Vec<SearchResult>, provider: &str) {
+        results.retain(|r| self.embedder.same_space(r.metadata.get("embedding_provider").map(|p| p.as_str()), provider));
+    }
+
+    /// The language a search filters on, else the query's own, for keyword tokenization
+    fn query_language(query: &str, options: &SearchOptions) -> Option<String> {
+        options.filter.as_ref().and_then(|filter| filter.language.clone())
+            .or_else(|| natural_language::detect(query).map(str::to_string))
+    }
+
+    /// Results and the embedding provider that served the query
+    async fn search_chunks(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<(Vec<SearchResult>, String)> {
+        let (results, provider, _) = self.search_chunks_traced(query, top_k, options).await?;
+        Ok((results, provider))
+    }
+
+    /// `search_chunks`, also returning the time and candidate counts of each pipeline stage
+    async fn search_chunks_traced(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<(Vec<SearchResult>, String, Vec<StageTiming>)> {
+        self.check_embedding_policy()?;
+        let started = Instant::now();
+
+        // `-term` exclusions are stripped from the text that is embedded and keyword-matched
+        let terms = QueryTerms::parse(query, &options.exclude_terms);
+        let pipeline = self.pipelines.for_collection(options.collection.as_deref());
+        let mut timings = Vec::with_capacity(pipeline.stages().len());
+        let (_, mut results, provider) = self.run_pipeline(&terms, options, top_k, pipeline, false, &mut timings).await?;
+        self.metrics.record_stage_timings(&timings);
+
+        // Tag results with the collection their document was accepted into
+        {
+            let collections = self.collections.read().await;
+            for result in &mut results {
+                let collection = result.metadata.get("source_file").and_then(|file| collections.collection_of(file)).cloned();
+                if let Some(collection) = collection {
+                    result.metadata.insert("collection".to_string(), collection);
+                }
+            }
+        }
+
+        let results: Vec<SearchResult> = results.into_iter().take(top_k).collect();
+
+        // Record the query so intent and low-score statistics can drive vocabulary suggestions
+        let top_score = results.iter().map(|r| r.score).fold(0.0, f32::max);
+        let intent = self.query_enhancer.enhance(&terms.positive).intent;
+        self.metrics.record_query(query, top_score, results.len(), started.elapsed(), "hybrid", intent.as_str());
+
+        Ok((results, provider, timings))
+    }
+
+    /// The retrieval lists after `pipeline`'s list stages, the combined ranking after all of
+    /// its stages (untruncated), and the embedding provider that served the query. With
+    /// `complete`, every list is fetched in full even when the ranking would not use it. Each
+    /// stage that runs adds its timing to `timings`.
+    async fn run_pipeline(&self, terms: &QueryTerms, options: &SearchOptions, top_k: usize, pipeline: &RankingPipeline, complete: bool, timings: &mut Vec<StageTiming>) -> Result<(RetrievalLegs, Vec<SearchResult>, String)> {
+        let fusion = self.config.ranking.fusion.filter(|_| pipeline.contains(RankingStage::Fusion));
+        let (legs, provider) = self.retrieval_legs(terms, options, top_k, pipeline, complete || fusion.is_some(), timings).await?;
+
+        let started = Instant::now();
+        let mut results = match fusion {
+            Some(weights) => weights.fuse(&legs),
+            None => legs.fallback(top_k),
+        };
+        if pipeline.contains(RankingStage::Fusion) {
+            timings.push(StageTiming::new(RankingStage::Fusion.name(), started, legs.candidate_count(), results.len()));
+        }
+
+        for stage in pipeline.stages().iter().filter(|stage| !stage.on_lists() && **stage != RankingStage::Fusion) {
+            let (started, candidates_in) = (Instant::now(), results.len());
+            match stage {
+                // Bias results toward the chunk types configured under ranking.type_weights
+                RankingStage::Rerank => TypeWeights::new(&self.config.ranking.type_weights).apply(&mut results),
+                RankingStage::Mmr => results = self.diversify(results),
+                // Merge chunks that cover the same passage before they crowd out other results
+                RankingStage::Packing => results = merge_overlapping_results(results, DEFAULT_OVERLAP_MERGE_RATIO),
+                // List stages ran during retrieval, and fusion combined the lists above
+                RankingStage::Candidates | RankingStage::Filters | RankingStage::GraphBoost | RankingStage::Fusion => {}
+            }
+            timings.push(StageTiming::new(stage.name(), started, candidates_in, results.len()));
+        }
+        Ok((legs, results, provider))
+    }
+
+    /// Ranked candidates from vector, keyword, graph and sparse retrieval after `pipeline`'s
+    /// list stages, and the embedding provider that served the query. Unless `complete`,
+    /// keyword results are only fetched when vector search finds fewer than `top_k` and the
+    /// graph and sparse lists are left empty, as the unfused ranking needs no more.
+    async fn retrieval_legs(&self, terms: &QueryTerms, options: &SearchOptions, top_k: usize, pipeline: &RankingPipeline, complete: bool, timings: &mut Vec<StageTiming>) -> Result<(RetrievalLegs, String)> {
+        let keyword_matcher = BM25Search::new()
+            .with_minimum_should_match(options.minimum_should_match)
+            .with_language(Self::query_language(&terms.positive, options));
+        let filtered = pipeline.contains(RankingStage::Filters);
+        let exclusions = ExclusionFilter::new(if filtered { &terms.excluded } else { &[] }, self.config.search.exclusion_penalty);
+        let allowed = self.allowed_ids(options).await?;
+        // Over-fetch when exclusions will thin the lists
+        let candidates = if exclusions.is_empty() { top_k * 2 } else { top_k * 4 };
+
+        // CPU-bound retrieval runs on the search pool, isolated from ingestion work
+        let started = Instant::now();
+        let (mut legs, provider) = self.pools.search.install(|| -> Result<(RetrievalLegs, String)> {
+            let (mut vector, provider) = self.vector_candidates(&terms.positive, allowed.as_ref(), candidates)?;
+
+            let mut text = Vec::new();
+            let usable = vector.iter().filter(|r| !(exclusions.drops() && exclusions.matches(r))).count();
+            if complete || usable < top_k {
+                text = self.storage.search_by_text(&terms.positive, candidates);
+                if let Some(ids) = &allowed {
+                    text.retain(|r| ids.contains(&r.chunk_id));
+                }
+            }
+
+            // Learned sparse matches only count in fusion
+            let mut sparse = Vec::new();
+            if let Some(encoder) = self.sparse_encoder.as_ref().filter(|_| complete) {
+                match encoder.encode(std::slice::from_ref(&terms.positive)) {
+                    Ok(query) => {
+                        if let Some(query) = query.first() {
+                            sparse = self.storage.search_sparse(query, allowed.as_ref(), candidates);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Sparse encoding of the query failed, searching without the sparse list: {}", e),
+                }
+            }
+
+            // Agent memories live in the same index but are only returned by recall
+            vector.retain(|r| !is_memory(r));
+            text.retain(|r| !is_memory(r));
+            sparse.retain(|r| !is_memory(r));
+            Ok((RetrievalLegs { vector, text, graph: Vec::new(), sparse }, provider))
+        })?;
+        timings.push(StageTiming::new(RankingStage::Candidates.name(), started, 0, legs.candidate_count()));
+
+        for stage in pipeline.stages().iter().filter(|stage| stage.on_lists() && **stage != RankingStage::Candidates) {
+            let (started, candidates_in) = (Instant::now(), legs.candidate_count());
+            match stage {
+                RankingStage::Filters => {
+                    legs.vector = exclusions.apply(std::mem::take(&mut legs.vector));
+                    legs.text = exclusions.apply(std::mem::take(&mut legs.text));
+                    legs.text.retain(|r| keyword_matcher.meets_minimum_should_match(&terms.positive, &r.content));
+                    legs.graph = exclusions.apply(std::mem::take(&mut legs.graph));
+                    legs.sparse = exclusions.apply(std::mem::take(&mut legs.sparse));
+                }
+                RankingStage::GraphBoost if complete => {
+                    let neighbours = graph_neighbours(&self.graph.snapshot(), &legs.graph_seeds(), candidates);
+                    legs.graph = neighbours.into_iter()
+                        .filter(|(id, _)| allowed.as_ref().is_none_or(|ids| ids.contains(id)))
+                        .filter_map(|(id, score)| self.storage.get_search_result(&id, score))
+                        .filter(|r| !is_memory(r))
+                        .collect();
+                    // Exclusions hold for every list, whichever stage comes first
+                    legs.graph = exclusions.apply(std::mem::take(&mut legs.graph));
+                }
+                _ => continue,
+            }
+            timings.push(StageTiming::new(stage.name(), started, candidates_in, legs.candidate_count()));
+        }
+        Ok((legs, provider))
+    }
+
+    /// Vector search results and the provider that embedded the query. With a code chain the
+    /// query is embedded by both chains, each list keeps the chunks in its own vector space,
+    /// and the two are merged by rank since their models score on different scales.
+    fn vector_candidates(&self, query: &str, allowed: Option<&std::collections::HashSet<String>>, candidates: usize) -> Result<(Vec<SearchResult>, String)> {
+        let search = |embedder: &ProviderChain| -> Result<(Vec<SearchResult>, String)> {
+            // Generate query embedding, failing over across the chain's providers
+            let (query_embedding, provider) = embedder.embed_query(query)?;
+            self.check_query_dimension(&provider, query_embedding.len())?;
+            let mut results = match allowed {
+                Some(ids) => self.storage.search_similar_in(&query_embedding, ids, candidates),
+                None => self.storage.search_similar(&query_embedding, candidates),
+            };
+            // A fallback provider's vectors only match chunks it embedded; keyword search fills the gap
+            results.retain(|r| embedder.same_space(r.metadata.get("embedding_provider").map(|p| p.as_str()), &provider));
+            Ok((results, provider))
+        };
+
+        let (results, provider) = search(&self.embedder)?;
+        let Some(code) = &self.code_embedder else {
+            return Ok((results, provider));
+        };
+        match search(code) {
+            Ok((code_results, _)) => Ok((interleave_by_rank(vec![results, code_results], candidates), provider)),
+            // Without the code chain, code chunks are left to keyword search
+            Err(e) => {
+                tracing::warn!("Code embedding chain failed, searching without it: {}", e);
+                Ok((results, provider))
+            }
+        }
+    }
+
+    /// Chunks a search may return under its metadata filter and collection; `None` allows all
+    async fn allowed_ids(&self, options: &SearchOptions) -> Result<Option<std::collections::HashSet<String>>> {
+        let mut allowed: Option<std::collections::HashSet<String>> = options.filter.as_ref().map(|filter| self.storage.matching_ids(filter.query()).collect());
+        if let Some(collection) = &options.collection {
+            let documents = self.collections.read().await.documents_in(collection);
+            if documents.is_empty() {
+                return Err(anyhow::anyhow!("No documents are assigned to collection '{}'", collection));
+            }
+            let in_collection: std::collections::HashSet<String> = documents.iter()
+                .flat_map(|document| self.storage.matching_ids(ChunkQuery::all().with_file(document)).collect::<Vec<_>>())
+                .collect();
+            allowed = Some(match allowed {
+                Some(ids) => ids.intersection(&in_collection).cloned().collect(),
+                None => in_collection,
+            });
+        }
+        Ok(allowed)
+    }
+
+    /// Reorder results by maximal marginal relevance over their stored embeddings
+    fn diversify(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let embeddings: std::collections::HashMap<String, Vec<f32>> = results.iter()
+            .filter_map(|r| self.storage.get_chunk(&r.chunk_id).ok().flatten().map(|chunk| (r.chunk_id.clone(), chunk.embedding)))
+            .filter(|(_, embedding)| !embedding.is_empty())
+            .collect();
+        maximal_marginal_relevance(results, &embeddings, self.config.ranking.mmr_lambda)
+    }
+
+    /// Why `chunk_id` did or did not make the top `top_k` for a query: each retrieval list is
+    /// rebuilt as `search_chunks` builds it and the chunk is followed through every stage.
+    async fn diagnose(&self, query: &str, chunk_id: &str, top_k: usize, options: &SearchOptions) -> Result<Value> {
+        self.check_embedding_policy()?;
+        let chunk = self.storage.get_chunk(chunk_id)?.ok_or_else(|| anyhow::anyhow!("Chunk not found: {}", chunk_id))?;
+        let target = self.storage.get_search_result(chunk_id, 0.0).ok_or_else(|| anyhow::anyhow!("Chunk not found: {}", chunk_id))?;
+
+        let terms = QueryTerms::parse(query, &options.exclude_terms);
+        let pipeline = self.pipelines.for_collection(options.collection.as_deref());
+        let fusion = self.config.ranking.fusion.filter(|_| pipeline.contains(RankingStage::Fusion));
+        let (legs, ranked, provider) = self.run_pipeline(&terms, options, top_k, pipeline, true, &mut Vec::new()).await?;
+        let keyword_consulted = fusion.is_some() || legs.vector.len() < top_k;
+        let type_weights = if pipeline.contains(RankingStage::Rerank) { TypeWeights::new(&self.config.ranking.type_weights) } else { TypeWeights::default() };
+
+        let mut findings = Vec::new();
+        let position = |leg: &[SearchResult]| leg.iter().position(|r| r.chunk_id == chunk_id);
+
+        // Stages that remove the chunk from every list
+        if is_memory(&target) {
+            findings.push(Finding::new("memory", "The chunk is an agent memory, returned only by recall"));
+        }
+        if let Some(filter) = &options.filter {
+            if !self.storage.matching_ids(filter.query()).any(|id| id == chunk_id) {
+                findings.push(Finding::new("filtered", "The chunk's metadata does not match the filter"));
+            }
+        }
+        let exclusions = ExclusionFilter::new(&terms.excluded, self.config.search.exclusion_penalty);
+        if pipeline.contains(RankingStage::Filters) && exclusions.matches(&target) {
+            let effect = if exclusions.drops() { "removed".to_string() } else { format!("scored x{}", self.config.search.exclusion_penalty) };
+            findings.push(Finding::new("excluded", format!("The chunk mentions an excluded term ({}), so it is {}", terms.excluded.join(", "), effect)));
+        }
+
+        // Vector search
+        let chunk_provider = target.metadata.get("embedding_provider").map(|p| p.as_str());
+        // Code chunks are compared with the query as the code chain embeds it
+        let embedder = self.embedder_for(&chunk.metadata.chunk_type);
+        let (query_embedding, query_provider) = if chunk.embedding.is_empty() {
+            (Vec::new(), provider.clone())
+        } else {
+            self.pools.search.install(|| embedder.embed_query(&terms.positive))?
+        };
+        let mut similarity = None;
+        if chunk.embedding.is_empty() {
+            findings.push(Finding::new("no_embedding", "The chunk has no embedding, so vector search cannot find it; rebuild_index re-embeds it"));
+        } else if !embedder.same_space(chunk_provider, &query_provider) {
+            findings.push(Finding::new("embedding_space", format!(
+                "The chunk was embedded by {} but the query by {}; their vectors are not comparable, so only keyword search can find it",
+                chunk_provider.unwrap_or(LOCAL_PROVIDER), query_provider
+            )));
+        } else {
+            let score = embedding_similarity(&query_embedding, &chunk.embedding);
+            similarity = Some(score);
+            match (position(&legs.vector), legs.vector.last()) {
+                (Some(_), _) => {}
+                (None, Some(cutoff)) => findings.push(Finding::new("below_vector_cutoff", format!(
+                    "Embedding similarity {:.3} is below {:.3}, the lowest of the {} vector candidates",
+                    score, cutoff.score, legs.vector.len()
+                ))),
+                (None, None) => {}
+            }
+            if score < self.config.search.low_score_threshold {
+                findings.push(Finding::new("low_similarity", format!(
+                    "Embedding similarity {:.3} is below search.low_score_threshold ({})", score, self.config.search.low_score_threshold
+                )));
+            }
+        }
+
+        // Keyword search
+        let keywords = keyword_coverage(&terms.positive, &chunk.content);
+        if !keywords.missing.is_empty() {
+            let mut detail = format!("Keyword search matches whole words only; the chunk lacks: {}", keywords.missing.join(", "));
+            if !keywords.attached_to_punctuation.is_empty() {
+                detail.push_str(&format!(" ({} only with punctuation attached)", keywords.attached_to_punctuation.join(", ")));
+            }
+            findings.push(Finding::new("missing_keywords", detail));
+        }
+        let keyword_matcher = BM25Search::new()
+            .with_minimum_should_match(options.minimum_should_match)
+            .with_language(Self::query_language(&terms.positive, options));
+        if pipeline.contains(RankingStage::Filters) && !keyword_matcher.meets_minimum_should_match(&terms.positive, &chunk.content) {
+            findings.push(Finding::new("minimum_should_match", "The chunk contains too few query terms for minimum_should_match"));
+        }
+        if !keyword_consulted {
+            findings.push(Finding::new("keyword_search_skipped", format!(
+                "Vector search found at least {} results, so keyword results were not used (ranking.fusion is not configured)", top_k
+            )));
+        }
+
+        // Final ranking
+        let weight = type_weights.weight(&target);
+        if weight < 1.0 {
+            findings.push(Finding::new("type_weight", format!(
+                "Scores of {} chunks are scaled by {} (ranking.type_weights)", target.metadata.get("chunk_type").map(|t| t.as_str()).unwrap_or("these"), weight
+            )));
+        }
+        let rank = rank_of(&ranked, chunk_id);
+        match rank {
+            Some((rank, Some(kept))) => findings.push(Finding::new("merged", format!(
+                "The chunk overlaps {} from the same file, which was kept in its place at rank {}", kept.chunk_id, rank + 1
+            ))),
+            Some((rank, None)) if rank < top_k => findings.push(Finding::new("ranked", format!("The chunk ranks {} of {}", rank + 1, top_k))),
+            Some((rank, None)) => {
+                findings.push(Finding::new("outranked", format!(
+                    "The chunk ranks {} with score {:.4}; result {} scores {:.4}",
+                    rank + 1, ranked[rank].score, top_k, ranked[top_k - 1].score
+                )));
+                let duplicates: Vec<&str> = ranked[..top_k].iter()
+                    .filter(|r| self.storage.get_chunk(&r.chunk_id).ok().flatten()
+                        .is_some_and(|other| embedding_similarity(&other.embedding, &chunk.embedding) >= NEAR_DUPLICATE_SIMILARITY))
+                    .map(|r| r.chunk_id.as_str())
+                    .collect();
+                if !duplicates.is_empty() {
+                    findings.push(Finding::new("near_duplicates", format!(
+                        "{} of the top {} results are near-duplicates of the chunk: {}", duplicates.len(), top_k, duplicates.join(", ")
+                    )));
+                }
+            }
+            None => findings.push(Finding::new("not_retrieved", "No retrieval list returned the chunk")),
+        }
+
+        Ok(json!({
+            "query": query,
+            "chunk_id": chunk_id,
+            "source_file": chunk.metadata.source_file,
+            "top_k": top_k,
+            "ranking": if fusion.is_some() { "fusion" } else { "vector_with_keyword_fallback" },
+            "pipeline": pipeline.names(),
+            "embedding_provider": provider,
+            "rank": rank.map(|(rank, _)| rank + 1),
+            "returned": rank.is_some_and(|(rank, _)| rank < top_k),
+            "signals": {
+                "vector_similarity": similarity,
+                "vector_rank": position(&legs.vector).map(|rank| rank + 1),
+                "keyword_rank": position(&legs.text).map(|rank| rank + 1),
+                "graph_rank": position(&legs.graph).map(|rank| rank + 1),
+                "sparse_rank": position(&legs.sparse).map(|rank| rank + 1),
+                "type_weight": weight
+            },
+            "keywords": keywords,
+            "findings": findings,
+            "top_results": ranked.iter().take(top_k).map(|r| json!({
+                "chunk_id": r.chunk_id,
+                "score": r.score,
+                "source_file": r.metadata.get("source_file")
+            })).collect::<Vec<_>>()
+        }))
+    }
+
+    /// Search fusion weights against labeled queries: the eval set at `eval_set` (default
+    /// `ranking.eval_set`), or else the recorded feedback. With `apply`, better weights are
+    /// written to the config file, taking effect on restart.
+    async fn tune_fusion(&self, eval_set: Option<&str>, top_k: usize, apply: bool) -> Result<Value> {
+        self.check_embedding_policy()?;
+        let eval_path = eval_set.map(std::path::PathBuf::from).or_else(|| self.config.ranking.eval_set.clone());
+        let (source, cases) = match &eval_path {
+            Some(path) => (path.display().to_string(), EvalSet::load(path)?.queries),
+            None => ("feedback".to_string(), self.feedback.eval_cases()?),
+        };
+        if cases.is_empty() {
+            return Err(anyhow::anyhow!("No labeled queries in {}; record some with record_feedback or configure ranking.eval_set", source));
+        }
+
+        let mut labeled = Vec::new();
+        for case in cases {
+            let terms = QueryTerms::parse(&case.query, &[]);
+            let (legs, _) = self.retrieval_legs(&terms, &SearchOptions::default(), top_k, self.pipelines.for_collection(None), true, &mut Vec::new()).await?;
+            labeled.push((legs, case.relevant));
+        }
+        let report = tune(&labeled, self.config.ranking.fusion, top_k);
+        tracing::info!(
+            "Ranking tuning over {} queries: nDCG@{} {:.3} -> {:.3} with {:?}",
+            report.queries, top_k, report.before.ndcg, report.after.ndcg, report.recommended
+        );
+
+        let applied = match (&self.config_path, apply && report.improved()) {
+            (Some(path), true) => {
+                write_fusion_weights(path, &report.recommended)?;
+                Some(path.display().to_string())
+            }
+            (None, true) => return Err(anyhow::anyhow!("The config file location is unknown, so weights cannot be applied; set ranking.fusion by hand")),
+            (_, false) => None,
+        };
+        Ok(json!({
+            "source": source,
+            "top_k": top_k,
+            "improved": report.improved(),
+            "report": report,
+            "applied_to": applied,
+            "restart_required": applied.is_some()
+        }))
+    }
+
+    async fn search_chapters(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<(Vec<Value>, String)> {
+        // First find relevant chunks - get more results to ensure we capture chapters
+        let (chunk_results, provider) = self.search_chunks(query, top_k * 5, options).await?;
+
+        // Group by chapter and aggregate scores
+        let mut chapter_scores: std::collections::HashMap<String, (f32, Vec<SearchResult>)> = std::collections::HashMap::new();
+
+        let chunk_results_clone = chunk_results.clone();
+
+        for result in chunk_results {
+            // Check both chapter and section fields for chapter information
+            let chapter_name = result.metadata.get("chapter")
+                .or_else(|| result.metadata.get("section"))
+                .cloned();
+
+            if let Some(chapter) = chapter_name {
+                let source_file = result.metadata.get("source_file")
+                    .map(|s| s.as_str())
+                    .unwrap_or("unknown");
+                let chapter_key = format!("{}#{}", source_file, chapter);
+                let entry = chapter_scores.entry(chapter_key).or_insert((0.0, Vec::new()));
+                entry.0 += result.score;
+                entry.1.push(result);
+            }
+        }
+
+        // Also try to include chunks that don't have explicit chapter metadata but are relevant
+        // This helps with documents that might not have perfect chapter extraction
+        if chapter_scores.is_empty() {
+            // If no chapters found, group by section or file as fallback
+            for result in &chunk_results_clone[0..std::cmp::min(chunk_results_clone.len(), top_k * 2)] {
+                let section_or_file = result.metadata.get("section")
+                    .or_else(|| result.metadata.get("source_file"))
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown Section".to_string());
+
+                let source_file = result.metadata.get("source_file")
+                    .map(|s| s.as_str())
+                    .unwrap_or("unknown");
+                let chapter_key = format!("{}#{}", source_file, section_or_file);
+                let entry = chapter_scores.entry(chapter_key).or_insert((0.0, Vec::new()));
+                entry.0 += result.score;
+                entry.1.push(result.clone());
+            }
+        }
+
+        // Sort chapters by aggregated score
+        let mut sorted_chapters: Vec<_> = chapter_scores.into_iter().collect();
+        sorted_chapters.sort_by(|a, b| b.1.0.partial_cmp(&a.1.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Return chapter information
+        let results: Vec<Value> = sorted_chapters
+            .into_iter()
+            .take(top_k)
+            .map(|(chapter_key, (score, chunks))| {
+                let parts: Vec<&str> = chapter_key.split('#').collect();
+                let file_path = parts.get(0).unwrap_or(&"unknown");
+                let chapter_name = parts.get(1).unwrap_or(&"unknown");
+
+                // Average the score by number of chunks for better ranking
+                let avg_score = if chunks.is_empty() { 0.0 } else { score / chunks.len() as f32 };
+
+                // Titles of the matching chunks, best first, as a quick outline of the chapter
+                let mut outline: Vec<&String> = Vec::new();
+                for title in chunks.iter().filter_map(|c| c.metadata.get("chunk_title")) {
+                    if !outline.contains(&title) {
+                        outline.push(title);
+                    }
+                }
+
+                json!({
+                    "chapter": chapter_name,
+                    "file": file_path,
+                    "score": avg_score,
+                    "total_score": score,
+                    "chunk_count": chunks.len(),
+                    "outline": outline,
+                    "chunks": chunks.iter().map(|c| json!({
+                        "id": c.chunk_id,
+                        "title": c.metadata.get("chunk_title"),
+                        "summary": c.metadata.get("chunk_summary"),
+                        "content": c.content,
+                        "score": c.score,
+                        "metadata": c.metadata
+                    })).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        Ok((results, provider))
+    }
+
+    async fn collect_health(&self) -> Value {
+        // Storage: reading a chunk back and flushing proves the backend is usable
+        let storage_error = self.storage.check().err().map(|e| e.to_string());
+        if let Some(e) = &storage_error {
+            tracing::error!("Storage health check failed: {}", e);
+        }
+        let chunk_count = self.storage.count_chunks();
+        let embedding_count = self.storage.count_embeddings();
+
+        // Embedding providers: embed a probe string with each; any one available keeps search working
+        let providers = self.embedder.probe();
+        for provider in providers.iter().filter(|p| !p.available) {
+            tracing::warn!("Embedding provider '{}' health probe failed: {}", provider.name, provider.last_error.as_deref().unwrap_or("unknown error"));
+        }
+        let code_providers = self.code_embedder.as_ref().map(|code| code.probe());
+        for provider in code_providers.iter().flatten().filter(|p| !p.available) {
+            tracing::warn!("Code embedding provider '{}' health probe failed: {}", provider.name, provider.last_error.as_deref().unwrap_or("unknown error"));
+        }
+        let embedding_ready = providers.iter().any(|p| p.available)
+            && code_providers.as_ref().is_none_or(|code| code.iter().any(|p| p.available));
+
+        let (graph_nodes, graph_edges) = {
+            let graph = self.graph.snapshot();
+            (graph.get_nodes().len(), graph.get_edges().len())
+        };
+
+        let data_dir = self.storage.data_dir();
+        let disk_usage_bytes = Self::dir_size(data_dir);
+
+        json!({
+            "status": if storage_error.is_some() { "error" } else if embedding_ready { "ok" } else { "degraded" },
+            "storage": {
+                "status": if storage_error.is_some() { "error" } else { "ok" },
+                "error": storage_error,
+                "data_dir": data_dir.display().to_string(),
+                "chunk_count": chunk_count,
+                "embedding_count": embedding_count,
+                "disk_usage_bytes": disk_usage_bytes
+            },
+            "embedding": {
+                "ready": embedding_ready,
+                "model_name": self.config.embedding.model_name,
+                "dimension": self.config.embedding.dimension,
+                "policy": self.embedder.policy().to_string(),
+                "providers": providers,
+                "code_providers": code_providers,
+                "query_cache": self.embedder.query_cache_stats(),
+                "sparse": self.sparse_encoder.as_ref().map(|encoder| json!({"model": encoder.model(), "chunks": self.storage.count_sparse()})),
+                "drift": *self.drift.read().await,
+                "stale_chunks": self.stale_chunk_ids().len(),
+                "reembed": *self.reembed.read().await
+            },
+            "graph": {
+                "nodes": graph_nodes,
+                "edges": graph_edges
+            },
+            "search": {
+                "stages": self.metrics.stage_summary()
+            },
+            "uptime_seconds": self.start_time.elapsed().as_secs()
+        })
+    }
+
+    /// Stored documents from the per-document aggregates, optionally under a source prefix
+    async fn document_listing(&self, prefix: Option<&str>) -> Result<Value> {
+        let documents = self.storage.list_documents()?;
+        let collections = self.collections.read().await;
+        let documents: Vec<Value> = documents.into_iter()
+            .filter(|(source_file, _)| prefix.is_none_or(|prefix| source_file.starts_with(prefix)))
+            .map(|(source_file, stats)| json!({
+                "collection": collections.collection_of(&source_file),
+                "source_file": source_file,
+                "chunks": stats.chunks,
+                "bytes": stats.bytes,
+                "last_ingested": stats.last_ingested
+            }))
+            .collect();
+        Ok(json!({"count": documents.len(), "documents": documents}))
+    }
+
+    /// Corpus totals and quota use, summed from the per-document aggregates
+    fn corpus_stats(&self) -> Result<Value> {
+        let mut documents = self.storage.list_documents()?;
+        let chunks: usize = documents.iter().map(|(_, stats)| stats.chunks).sum();
+        let chunk_bytes: u64 = documents.iter().map(|(_, stats)| stats.bytes).sum();
+        let disk_usage_bytes = Self::dir_size(self.storage.data_dir());
+        let max_corpus_size_bytes = self.config.ingestion.max_corpus_size_bytes;
+
+        let count = documents.len();
+        documents.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+        documents.truncate(STATS_LARGEST_DOCUMENTS);
+        let largest: Vec<Value> = documents.into_iter()
+            .map(|(source_file, stats)| json!({"source_file": source_file, "chunks": stats.chunks, "bytes": stats.bytes}))
+            .collect();
+
+        Ok(json!({
+            "documents": count,
+            "chunks": chunks,
+            "chunk_bytes": chunk_bytes,
+            "disk_usage_bytes": disk_usage_bytes,
+            "quota": {
+                "max_corpus_size_bytes": max_corpus_size_bytes,
+                "used_ratio": disk_usage_bytes as f64 / max_corpus_size_bytes.max(1) as f64
+            },
+            "largest_documents": largest
+        }))
+    }
+
+    /// Recursively sum file sizes under a directory, ignoring unreadable entries
+    fn dir_size(path: &std::path::Path) -> u64 {
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| match entry.metadata() {
+                Ok(meta) if meta.is_dir() => Self::dir_size(&entry.path()),
+                Ok(meta) => meta.len(),
+                Err(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Pinned chunks for `tool`'s output, given the source files of its results: pinned
+    /// documents expand to their chunks in order. Pins whose chunk or document is gone are
+    /// skipped.
+    async fn pinned_context<'a>(&self, tool: &str, result_files: impl Iterator<Item = &'a str>) -> Vec<Value> {
+        let collections: Vec<String> = {
+            let store = self.collections.read().await;
+            result_files.filter_map(|file| store.collection_of(file).cloned()).collect()
+        };
+        let collections: Vec<&str> = collections.iter().map(|c| c.as_str()).collect();
+
+        let pins = self.pins.read().await;
+        let mut seen = std::collections::HashSet::new();
+        let mut pinned = Vec::new();
+        for pin in pins.applicable(tool, &collections) {
+            let chunks = match (&pin.chunk_id, &pin.source_file) {
+                (Some(chunk_id), _) => self.storage.get_chunk(chunk_id).ok().flatten().into_iter().collect(),
+                (None, Some(file)) => self.storage.get_chunks_by_file(file).unwrap_or_default(),
+                (None, None) => Vec::new(),
+            };
+            for chunk in chunks.into_iter().filter(|chunk| seen.insert(chunk.id.clone())) {
+                if pinned.len() == MAX_PINNED_CHUNKS {
+                    tracing::warn!("Pinned context for {} exceeds {} chunks; the rest is left out", tool, MAX_PINNED_CHUNKS);
+                    return pinned;
+                }
+                pinned.push(json!({
+                    "pin_id": pin.id,
+                    "id": chunk.id,
+                    "content": chunk.content,
+                    "source_file": chunk.metadata.source_file,
+                    "note": pin.note
+                }));
+            }
+        }
+        pinned
+    }
+
+    /// Chunks adjacent to each result along Sequential edges, keyed by result chunk id
+    async fn sequential_context<'a>(&self, results: impl IntoIterator<Item = &'a SearchResult>, count: usize) -> std::collections::HashMap<String, Value> {
+        let graph = self.graph.snapshot();
+        let neighbour_json = |id: &String| -> Option<Value> {
+            let chunk = self.storage.get_chunk(id).ok()??;
+            Some(json!({
+                "id": chunk.id,
+                "content": chunk.content,
+                "line_start": chunk.metadata.line_start,
+                "line_end": chunk.metadata.line_end
+            }))
+        };
+
+        results.into_iter()
+            .map(|result| {
+                let (before, after) = graph.sequential_context(&result.chunk_id, count);
+                // Preceding chunks are listed in document order
+                let before: Vec<Value> = before.iter().rev().filter_map(neighbour_json).collect();
+                let after: Vec<Value> = after.iter().filter_map(neighbour_json).collect();
+                (result.chunk_id.clone(), json!({"before": before, "after": after}))
+            })
+            .collect()
+    }
+
+    /// Cluster documents by their mean chunk embedding and store the result as proposals
+    async fn compute_collection_proposals(&self, count: Option<usize>) -> Result<Vec<CollectionProposal>> {
+        // Only the head of each document is used for labelling
+        const LABEL_TEXT_CHARS: usize = 20_000;
+
+        let files = self.storage.list_files()?;
+        let mut profiles = Vec::new();
+        for file in files {
+            let chunks = self.storage.get_chunks_by_file(&file)?;
+            let embedded: Vec<_> = chunks.iter().filter(|c| !c.embedding.is_empty()).collect();
+            if embedded.is_empty() {
+                continue;
+            }
+
+            let mut embedding = vec![0.0; embedded[0].embedding.len()];
+            for chunk in &embedded {
+                for (sum, x) in embedding.iter_mut().zip(&chunk.embedding) {
+                    *sum += x;
+                }
+            }
+
+            let mut text = String::new();
+            for chunk in &chunks {
+                if text.len() >= LABEL_TEXT_CHARS {
+                    break;
+                }
+                text.push_str(&chunk.content);
+                text.push('\n');
+            }
+
+            profiles.push(DocumentProfile { path: file, embedding, text });
+        }
+
+        let k = count.unwrap_or_else(|| default_cluster_count(profiles.len()));
+        let clusters = self.pools.ingest.install(|| cluster_documents(&profiles, k));
+
+        let mut collections = self.collections.write().await;
+        Ok(collections.propose(clusters)?.to_vec())
+    }
+
+    fn graph_path(config: &Config) -> std::path::PathBuf {
+        config.storage.data_dir.join("graph.bin")
+    }
+
+    /// Periodically decay, prune and cap graph edges in the background
+    pub fn spawn_graph_maintenance(&self) {
+        let interval_secs = self.config.graph.maintenance_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        let graph = self.graph.clone();
+        let policy = MaintenancePolicy::from_config(&self.config.graph);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                let report = graph.update(|graph| Ok(graph.run_maintenance(&policy, chrono::Utc::now()))).await;
+                let report = match report {
+                    Ok(report) => report,
+                    Err(e) => {
+                        tracing::error!("Failed to persist graph after maintenance: {}", e);
+                        continue;
+                    }
+                };
+                tracing::info!(
+                    "Graph maintenance: {} decayed, {} pruned below weight, {} pruned over cap, {} edges remain",
+                    report.decayed, report.pruned_below_weight, report.pruned_over_cap, report.remaining_edges
+                );
+            }
+        });
+    }
+
+    /// Periodically merge duplicate memories, decay ones that are no longer recalled and
+    /// archive faded or expired ones
+    pub fn spawn_memory_consolidation(&self) {
+        let interval_secs = self.config.memory.consolidation_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        let storage = self.storage.clone();
+        let memory_writes = self.memory_writes.clone();
+        let policy = ConsolidationPolicy::from_config(&self.config.memory);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                // Sled I/O under a lock recalls wait on belongs on a blocking thread, not a runtime worker
+                let (storage, memory_writes, policy) = (storage.clone(), memory_writes.clone(), policy.clone());
+                let consolidated = tokio::task::spawn_blocking(move || {
+                    // A recall between loading and storing the memories would otherwise be overwritten
+                    let _writes = memory_writes.lock().unwrap();
+                    Self::consolidate_memories(&storage, &policy)
+                }).await;
+                match consolidated {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => tracing::error!("Memory consolidation failed: {}", e),
+                    Err(e) => tracing::error!("Memory consolidation task failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Ingest files from the configured sources that are new or whose content hash changed
+    /// since they were ingested, replacing the chunks of changed ones; unchanged files are
+    /// skipped without being chunked or embedded. Files ingested before hashes were recorded
+    /// count as unchanged. Restricted to sources marked `on_startup` when `startup` is set,
+    /// or to the one at `only`. Object-storage sources are synced by `sync_object_source`.
+    async fn sync_sources(&self, startup: bool, only: Option<&str>) -> Result<Vec<Value>> {
+        let sources: Vec<_> = self.config.sources.iter()
+            .filter(|source| !startup || source.on_startup)
+            .filter(|source| only.is_none_or(|path| source.path == std::path::Path::new(path)))
+            .collect();
+        if let Some(path) = only.filter(|_| sources.is_empty()) {
+            return Err(anyhow::anyhow!("{} is not a configured source", path));
+        }
+        let _sync = self.source_sync.lock().await;
+
+        self.with_graph_batch(async {
+            let mut ingested_files: std::collections::HashSet<String> = self.storage.list_files()?.into_iter().collect();
+            let mut summaries = Vec::new();
+            for source in sources {
+                let timeout = std::time::Duration::from_secs(self.config.ingestion.fetch_timeout_secs);
+                if let Some(objects) = ObjectSource::from_config(source, timeout)? {
+                    summaries.push(self.sync_object_source(source, objects).await?);
+                    continue;
+                }
+
+                let scanner = SourceScanner::from_config(source)?;
+                let files = match scanner.files() {
+                    Ok(files) => files,
+                    Err(e) => {
+                        summaries.push(json!({"source": source.path, "error": e.to_string()}));
+                        continue;
+                    }
+                };
+
+                let (mut ingested, mut updated, mut chunks, mut unchanged, mut filtered) = (0, 0, 0, 0, 0);
+                let mut failed = Vec::new();
+                for file in &files {
+                    let path = file.to_string_lossy().to_string();
+                    if !self.ingestion_filter.is_allowed(file) {
+                        filtered += 1;
+                        continue;
+                    }
+                    let outcome = if ingested_files.contains(&path) {
+                        let previous: Vec<Chunk> = self.storage.scan(ChunkQuery::all().with_file(&path)).collect();
+                        match Self::content_changed(&path, &previous) {
+                            Ok(Some(true)) => self.replace_file(&path, previous).await.map(|count| (false, count)),
+                            Ok(_) => {
+                                unchanged += 1;
+                                continue;
+                            }
+                            Err(e) => Err(e),
+                        }
+                    } else {
+                        self.process_document(&path, None, false).await.map(|count| (true, count))
+                    };
+
+                    match outcome {
+                        Ok((new, count)) => {
+                            if new {
+                                ingested += 1;
+                            } else {
+                                updated += 1;
+                            }
+                            chunks += count;
+                            ingested_files.insert(path);
+                        }
+                        Err(e) => failed.push(json!({"path": path, "error": e.to_string()})),
+                    }
+                }
+
+                summaries.push(json!({
+                    "source": scanner.root(),
+                    "files_found": files.len(),
+                    "ingested": ingested,
+                    "updated": updated,
+                    "chunks_created": chunks,
+                    "already_ingested": unchanged,
+                    "filtered": filtered,
+                    "failed": failed
+                }));
+            }
+            Ok(summaries)
+        }).await
+    }
+
+    /// Ingest the objects of an object-storage source that are new or whose ETag changed since
+    /// they were ingested, and drop documents whose object is gone from the listing
+    async fn sync_object_source(&self, source: &SourceConfig, objects: ObjectSource) -> Result<Value> {
+        let scanner = SourceScanner::from_config(source)?;
+        let listing = {
+            let objects = objects.clone();
+            tokio::task::spawn_blocking(move || objects.list()).await?
+        };
+        let entries = match listing {
+            Ok(entries) => entries,
+            Err(e) => return Ok(json!({"source": source.path, "error": e.to_string()})),
+        };
+
+        let listed: std::collections::HashSet<&str> = entries.iter().map(|entry| entry.url.as_str()).collect();
+        let mut removed = 0;
+        for file in self.storage.list_files()?.into_iter().filter(|file| file.starts_with(&objects.url_prefix()) && !listed.contains(file.as_str())) {
+            let ids: Vec<String> = self.storage.scan(ChunkQuery::all().with_file(&file)).map(|chunk| chunk.id).collect();
+            self.remove_chunks(&ids).await?;
+            removed += 1;
+        }
+
+        let selected: Vec<_> = entries.iter().filter(|entry| scanner.is_selected(std::path::Path::new(&entry.key))).collect();
+        let (mut ingested, mut updated, mut chunks, mut unchanged, mut filtered) = (0, 0, 0, 0, 0);
+        let mut failed = Vec::new();
+        for entry in &selected {
+            if !self.ingestion_filter.is_allowed(std::path::Path::new(&entry.key)) {
+                filtered += 1;
+                continue;
+            }
+            let previous: Vec<Chunk> = self.storage.scan(ChunkQuery::all().with_file(&entry.url)).collect();
+            let recorded_etag = previous.first().and_then(|chunk| chunk.metadata.attributes.get("etag"));
+            if !previous.is_empty() && (entry.etag.is_none() || recorded_etag == entry.etag.as_ref()) {
+                unchanged += 1;
+                continue;
+            }
+
+            let outcome = async {
+                let max_bytes = self.config.ingestion.max_file_size_bytes;
+                let (objects, object) = (objects.clone(), (*entry).clone());
+                let fetched = tokio::task::spawn_blocking(move || objects.get(&object, max_bytes)).await??;
+                let (count, _) = self.ingest_fetched(&entry.url, &fetched, None, false, "object_store").await?;
+                self.remove_superseded(&previous).await?;
+                Ok::<_, anyhow::Error>(count)
+            }.await;
+            match outcome {
+                Ok(count) if previous.is_empty() => {
+                    ingested += 1;
+                    chunks += count;
+                }
+                Ok(count) => {
+                    updated += 1;
+                    chunks += count;
+                }
+                Err(e) => failed.push(json!({"path": entry.url, "error": e.to_string()})),
+            }
+        }
+
+        Ok(json!({
+            "source": source.path,
+            "files_found": selected.len(),
+            "ingested": ingested,
+            "updated": updated,
+            "removed": removed,
+            "chunks_created": chunks,
+            "already_ingested": unchanged,
+            "filtered": filtered,
+            "failed": failed
+        }))
+    }
+
+    /// Periodically refresh documents ingested from URLs. The first refresh runs one interval
+    /// after startup rather than immediately.
+    pub fn spawn_url_refresh(&self) {
+        let interval_secs = self.config.ingestion.refresh_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let period = std::time::Duration::from_secs(interval_secs);
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                match server.refresh_url_documents().await {
+                    Ok(summary) => tracing::info!(
+                        "URL refresh: {} checked, {} refreshed, {} unchanged, {} failed",
+                        summary["documents_checked"], summary["refreshed"], summary["unchanged"], summary["failed"]
+                    ),
+                    Err(e) => tracing::error!("URL refresh failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Follow changes to sources configured with `watch: true` until the server exits
+    pub fn spawn_source_watcher(&self) {
+        let (watcher, mut receiver) = match SourceWatcher::start(&self.config.sources) {
+            Ok(Some(started)) => started,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to watch sources: {}", e);
+                return;
+            }
+        };
+
+        let server = self.clone();
+        let quiet = std::time::Duration::from_millis(self.config.ingestion.watch_debounce_ms);
+        tokio::spawn(async move {
+            while let Some(changed) = next_batch(&mut receiver, quiet).await {
+                server.apply_source_changes(&watcher, changed).await;
+            }
+        });
+    }
+
+    /// Ingest new files from `on_startup` sources in the background so the server is
+    /// ready for queries while a large corpus loads
+    pub fn spawn_source_ingestion(&self) {
+        if !self.config.sources.iter().any(|source| source.on_startup) {
+            return;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            match server.sync_sources(true, None).await {
+                Ok(summaries) => {
+                    for summary in summaries {
+                        tracing::info!("Source ingestion: {}", summary);
+                    }
+                }
+                Err(e) => tracing::error!("Source ingestion failed: {}", e),
+            }
+        });
+    }
+
+    /// Periodically re-embed a sample of chunks and compare with their stored vectors, so a
+    /// provider that silently changed its model is noticed. The first check runs one interval
+    /// after startup.
+    pub fn spawn_drift_monitor(&self) {
+        let interval_secs = self.config.embedding.drift_check_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let period = std::time::Duration::from_secs(interval_secs);
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            for round in 1.. {
+                interval.tick().await;
+                match server.check_drift(server.config.embedding.drift_sample_size, round).await {
+                    Ok(report) if report.reindex_suggested => tracing::warn!(
+                        "Embedding drift {:.4} exceeds {} across {} chunks (provider '{}'); consider re-ingesting or running rebuild_index on a fresh data_dir",
+                        report.drift, report.threshold, report.compared, report.provider
+                    ),
+                    Ok(report) => tracing::info!("Embedding drift {:.4} across {} chunks", report.drift, report.compared),
+                    Err(e) => tracing::error!("Embedding drift check failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Re-embed a sample of stored chunks with the current provider chain and record how far
+    /// the fresh vectors are from the stored ones
+    async fn check_drift(&self, sample_size: usize, round: u64) -> Result<DriftReport> {
+        self.check_embedding_policy()?;
+        let sample = sample_chunks(
+            // Late-chunked vectors depend on neighbouring chunks, so re-embedding one alone would read as drift
+            self.storage.scan(ChunkQuery::all()).filter(|chunk| {
+                !chunk.metadata.source_file.starts_with(MEMORY_SOURCE_PREFIX) && !chunk.metadata.attributes.contains_key(LATE_CHUNKING_ATTRIBUTE)
+            }),
+            sample_size.max(1),
+            round,
+        );
+        if sample.is_empty() {
+            return Err(anyhow::anyhow!("No embedded chunks to check (late-chunked chunks are not sampled)"));
+        }
+
+        // Each chunk is embedded again the way it was at ingest, by the chain for its type
+        let mut fresh: Vec<Option<Vec<f32>>> = vec![None; sample.len()];
+        let mut provider = None;
+        let (code, other): (Vec<usize>, Vec<usize>) = (0..sample.len())
+            .partition(|&i| self.code_embedder.is_some() && matches!(sample[i].metadata.chunk_type, ChunkType::Code));
+        for (embedder, group) in [(self.embedder.as_ref(), other), (self.embedder_for(&ChunkType::Code), code)] {
+            if group.is_empty() {
+                continue;
+            }
+            let texts: Vec<String> = group.iter().map(|&i| Self::recorded_embedding_text(&sample[i])).collect();
+            let (vectors, served) = self.pools.ingest.install(|| embedder.embed_documents(&texts))?;
+            for (&i, vector) in group.iter().zip(vectors) {
+                let stored_by = sample[i].metadata.attributes.get("embedding_provider").map(|p| p.as_str());
+                fresh[i] = embedder.same_space(stored_by, &served).then_some(vector);
+            }
+            provider.get_or_insert(served);
+        }
+        let provider = provider.unwrap_or_default();
+        let pairs: Vec<(&Chunk, Option<&[f32]>)> = sample.iter().zip(&fresh)
+            .map(|(chunk, vector)| (chunk, vector.as_deref()))
+            .collect();
+
+        let report = drift_report(&pairs, &provider, self.config.embedding.drift_threshold, chrono::Utc::now());
+        *self.drift.write().await = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Every readable chunk in the store, grouped by source file, and the number of files
+    fn stored_chunks(&self) -> Result<(usize, Vec<Chunk>)> {
+        let files = self.storage.list_files()?;
+        let mut chunks = Vec::new();
+        for file in &files {
+            chunks.extend(self.storage.get_chunks_by_file(file)?);
+        }
+        Ok((files.len(), chunks))
+    }
+
+    /// Audit the vector index and relationship graph against the chunk store
+    fn check_consistency(&self, graph: &GraphBuilder, files: usize, chunks: &[Chunk]) -> ConsistencyReport {
+        let graph_chunks = graph.get_nodes().values()
+            .filter(|node| matches!(node.node_type, NodeType::Chunk))
+            .map(|node| node.id.as_str())
+            .collect();
+        audit(files, chunks, self.storage.count_chunks(), self.storage.count_embeddings(), &graph_chunks)
+    }
+
+    /// Reconstruct the indexes derived from the chunk store, for recovery after a crash or
+    /// an import that bypassed ingestion: chunks with missing or unusable vectors are
+    /// embedded again, every chunk is re-stored to refresh the vector and metadata indexes,
+    /// and the relationship graph is rebuilt file by file. Searches keep using the old graph
+    /// until the new one is swapped in; concurrent ingestion waits for the rebuild to finish.
+    async fn rebuild_indexes(&self, check_only: bool) -> Result<Value> {
+        if check_only {
+            let graph = self.graph.snapshot();
+            let (files, chunks) = self.stored_chunks()?;
+            let report = self.check_consistency(&graph, files, &chunks);
+            return Ok(json!({"consistent": report.is_consistent(), "report": report}));
+        }
+        self.check_embedding_policy()?;
+
+        let mut writer = self.graph.write().await;
+        let (files, mut chunks) = self.stored_chunks()?;
+        let before = self.check_consistency(&writer.current(), files, &chunks);
+        let mut rebuilt = GraphBuilder::new(self.config.graph.similarity_threshold);
+        let dimensions = provider_dimensions(&chunks);
+        let mut reembedded = 0;
+        for (i, file_chunks) in chunks.chunk_by_mut(|a, b| a.metadata.source_file == b.metadata.source_file).enumerate() {
+            let stale: Vec<usize> = (0..file_chunks.len())
+                .filter(|&j| reembed_reason(&file_chunks[j], &dimensions).is_some())
+                .collect();
+            if !stale.is_empty() {
+                self.embed_chunks(file_chunks, &stale)?;
+                reembedded += stale.len();
+            }
+
+            for chunk in file_chunks.iter() {
+                self.storage.store_chunk(chunk)?;
+            }
+            rebuilt.build_relationships(file_chunks)?;
+            tracing::info!(
+                "Rebuild {}/{}: {} ({} chunks, {} re-embedded)",
+                i + 1, files, file_chunks[0].metadata.source_file, file_chunks.len(), stale.len()
+            );
+        }
+        let after = self.check_consistency(&rebuilt, files, &chunks);
+        writer.publish(rebuilt)?;
+
+        Ok(json!({
+            "consistent": after.is_consistent(),
+            "chunks_reindexed": chunks.len(),
+            "chunks_reembedded": reembedded,
+            "before": before,
+            "after": after
+        }))
+    }
+
+    /// Stale chunk ids and, sorted, the files they belong to
+    fn plan_reembed(&self) -> Result<(std::collections::HashSet<String>, Vec<String>)> {
+        // Chunks are only known to have the wrong length once their provider's current length is seen
+        for embedder in std::iter::once(self.embedder.as_ref()).chain(self.code_embedder.as_deref()) {
+            if let Ok((vector, provider)) = embedder.embed_query("dimension probe") {
+                let _ = self.check_query_dimension(&provider, vector.len());
+            }
+        }
+        let stale: std::collections::HashSet<String> = self.stale_chunk_ids().into_iter().collect();
+        let mut files = std::collections::BTreeSet::new();
+        for chunk_id in &stale {
+            if let Some(chunk) = self.storage.get_chunk(chunk_id)? {
+                files.insert(chunk.metadata.source_file);
+            }
+        }
+        Ok((stale, files.into_iter().collect()))
+    }
+
+    /// Re-embed the `stale` chunks file by file with the current model and policy, keeping
+    /// their content and boundaries. A file's stale chunks are embedded together, late-chunked
+    /// when the provider does that, and its similarity edges rebuilt. When done the current
+    /// policy is recorded, so searches rejected over a policy change resume.
+    async fn reembed_files(&self, stale: &std::collections::HashSet<String>, files: &[String]) -> Result<()> {
+        self.with_graph_batch(async {
+            for file in files {
+                let mut chunks = self.storage.get_chunks_by_file(file)?;
+                let targets: Vec<usize> = (0..chunks.len()).filter(|&j| stale.contains(&chunks[j].id)).collect();
+                self.embed_chunks(&mut chunks, &targets)?;
+                for &j in &targets {
+                    self.storage.store_chunk(&chunks[j])?;
+                }
+                // Chunks a fallback provider served are still not on their chain's model
+                let still_stale = targets.iter().filter(|&&j| self.chunk_stale_reason(&chunks[j].metadata).is_some()).count();
+
+                let ids: std::collections::HashSet<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+                self.graph.update(|graph| {
+                    graph.remove_chunks(&ids);
+                    graph.build_relationships(&chunks)
+                }).await?;
+
+                if let Some(progress) = self.reembed.write().await.as_mut() {
+                    progress.files_done += 1;
+                    progress.reembedded += targets.len();
+                    progress.still_stale += still_stale;
+                }
+            }
+            Ok(())
+        }).await?;
+
+        self.embedder.policy().record(self.storage.data_dir())
+    }
+
+    /// Run each section's query and render the results as a markdown report
+    async fn build_report(&self, template: &ReportTemplate, language: ResponseLanguage) -> Result<String> {
+        let mut results = Vec::with_capacity(template.sections.len());
+        for section in &template.sections {
+            let options = SearchOptions {
+                exclude_terms: section.exclude_terms.clone(),
+                minimum_should_match: None,
+                filter: None,
+                collection: None,
+            };
+            results.push(self.search_chunks(&section.query, template.section_top_k(section), &options).await?.0);
+        }
+        Ok(render_report(template, &results, chrono::Utc::now(), self.config.report.snippet_chars, language))
+    }
+
+    /// Diff a stored chunk against another chunk or against raw text
+    fn compare_chunk(&self, chunk_id: &str, other_chunk_id: Option<&str>, text: Option<&str>, context: usize) -> Result<Value> {
+        let chunk = self.storage.get_chunk(chunk_id)?
+            .ok_or_else(|| anyhow::anyhow!("Chunk '{}' not found", chunk_id))?;
+
+        let provider_of = |chunk: &Chunk| chunk.metadata.attributes.get("embedding_provider").cloned().unwrap_or_else(|| LOCAL_PROVIDER.to_string());
+        let embedder = self.embedder_for(&chunk.metadata.chunk_type);
+        let (other_label, other_content, other_embedding, other_provider) = match (other_chunk_id, text) {
+            (Some(other_id), _) => {
+                let other = self.storage.get_chunk(other_id)?
+                    .ok_or_else(|| anyhow::anyhow!("Chunk '{}' not found", other_id))?;
+                let provider = provider_of(&other);
+                (Self::chunk_label(&other), other.content, other.embedding, provider)
+            }
+            (None, Some(text)) => {
+                self.check_embedding_policy()?;
+                let (embedding, provider) = self.pools.search.install(|| embedder.embed_query(text))?;
+                ("text".to_string(), text.to_string(), embedding, provider)
+            }
+            (None, None) => return Err(anyhow::anyhow!("Either other_chunk_id or text is required")),
+        };
+
+        // Vectors from different providers are not comparable
+        let semantic_similarity = embedder.same_space(Some(&provider_of(&chunk)), &other_provider)
+            .then(|| embedding_similarity(&chunk.embedding, &other_embedding));
+
+        let diff = diff_texts(&Self::chunk_label(&chunk), &chunk.content, &other_label, &other_content, context);
+        Ok(json!({
+            "chunk_id": chunk_id,
+            "other_chunk_id": other_chunk_id,
+            "similarity": diff.similarity,
+            "semantic_similarity": semantic_similarity,
+            "lines_added": diff.added,
+            "lines_removed": diff.removed,
+            "diff": diff.unified
+        }))
+    }
+
+    /// Best location of `quote` in the chunks of `source_file`. Citations often name just the
+    /// file, so when no document is stored under that exact path, every document whose path
+    /// ends with it is searched.
+    fn locate_citation(&self, quote: &str, source_file: &str) -> Result<(Vec<String>, Option<Value>)> {
+        let mut sources = vec![source_file.to_string()];
+        if self.storage.scan(ChunkQuery::all().with_file(source_file)).next().is_none() {
+            let suffix = format!("/{}", source_file.trim_start_matches("./"));
+            sources = self.storage.list_files()?.into_iter().filter(|f| f.ends_with(&suffix)).collect();
+        }
+
+        let mut best: Option<(f32, Value)> = None;
+        for source in &sources {
+            for chunk in self.storage.get_chunks_by_file(source)? {
+                let Some(found) = locate_quote(quote, &chunk.content) else {
+                    continue;
+                };
+                if best.as_ref().is_some_and(|(score, _)| *score >= found.score) {
+                    continue;
+                }
+
+                let metadata = &chunk.metadata;
+                let line = (metadata.line_start > 0).then(|| metadata.line_start + chunk.content[..found.byte_start].trim_start().matches('\n').count());
+                best = Some((found.score, json!({
+                    "chunk_id": chunk.id,
+                    "source_file": metadata.source_file,
+                    "exact": found.exact,
+                    "score": found.score,
+                    "line": line,
+                    "page": metadata.page_start,
+                    "chapter": metadata.chapter,
+                    "section": metadata.section,
+                    "matched_text": &chunk.content[found.byte_start..found.byte_end]
+                })));
+            }
+        }
+
+        Ok((sources, best.map(|(_, location)| location)))
+    }
+
+    /// `source:start-end` header for a chunk in a diff
+    fn chunk_label(chunk: &Chunk) -> String {
+        format!("{}:{}-{}", chunk.metadata.source_file, chunk.metadata.line_start, chunk.metadata.line_end)
+    }
+
+    fn consolidate_memories(storage: &Storage, policy: &ConsolidationPolicy) -> Result<()> {
+        let mut memories: Vec<Chunk> = storage.scan(ChunkQuery::all().with_file_prefix(MEMORY_SOURCE_PREFIX)).collect();
+
+        let (report, changed) = consolidate(&mut memories, policy, chrono::Utc::now());
+        for index in changed {
+            storage.store_chunk(&memories[index])?;
+        }
+        tracing::info!(
+            "Memory consolidation: {} merged, {} decayed, {} archived, {} active",
+            report.merged, report.decayed, report.archived, report.active
+        );
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+impl RagMcp for McpServer {
+    fn ingest(&self, path: String, doc_type: Option<String>, force: Option<bool>) -> Result<Value, JsonRpcError> {
+        // Use a blocking approach to avoid runtime conflicts
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.process_document(&path, doc_type.as_deref(), force.unwrap_or(false)).await
+            })
+        });
+
+        match result {
+            Ok(chunk_count) => Ok(json!({
+                "status": "success",
+                "chunks_created": chunk_count,
+                "document_path": path
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Ingestion failed: {}", e);
+                error.data = Some(json!({"path": path}));
+                Err(error)
+            }
+        }
+    }
+
+    fn ingest_text(&self, source_name: String, text: String, doc_type: Option<String>, metadata: Option<std::collections::HashMap<String, String>>, force: Option<bool>) -> Result<Value, JsonRpcError> {
+        let metadata = metadata.unwrap_or_default();
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.process_text(&source_name, doc_type.as_deref(), &text, &metadata, force.unwrap_or(false)).await
+            })
+        });
+
+        match result {
+            Ok(chunk_count) => Ok(json!({
+                "status": "success",
+                "chunks_created": chunk_count,
+                "source_name": source_name
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Ingestion failed: {}", e);
+                error.data = Some(json!({"source_name": source_name}));
+                Err(error)
+            }
+        }
+    }
+
+    fn remember(&self, text: String, tags: Option<Vec<String>>) -> Result<Value, JsonRpcError> {
+        let tags = tags.unwrap_or_default();
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.store_memory(&text, &tags).await
+            })
+        });
+
+        match result {
+            Ok(chunk) => Ok(json!({
+                "status": "success",
+                "memory_id": chunk.id,
+                "created_at": chunk.metadata.timestamp.to_rfc3339(),
+                "tags": chunk.metadata.attributes.get("memory_tags").map(|t| t.split(',').filter(|t| !t.is_empty()).collect::<Vec<_>>()).unwrap_or_default()
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Failed to store memory: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn recall(&self, query: String, top_k: Option<usize>, tags: Option<Vec<String>>) -> Result<Value, JsonRpcError> {
+        let k = top_k.unwrap_or(5);
+        let tags = tags.unwrap_or_default();
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.recall_memories(&query, k, &tags).await
+            })
+        });
+
+        match result {
+            Ok((memories, provider)) => Ok(json!({
+                "query": query,
+                "embedding_provider": provider,
+                "memories": memories.iter().map(|m| json!({
+                    "id": m.chunk_id,
+                    "text": m.content,
+                    "score": m.score,
+                    "relevance": m.metadata.get("relevance").and_then(|r| r.parse::<f32>().ok()),
+                    "created_at": m.metadata.get("created_at"),
+                    "tags": m.metadata.get("memory_tags").map(|t| t.split(',').filter(|t| !t.is_empty()).collect::<Vec<_>>()).unwrap_or_default()
+                })).collect::<Vec<_>>(),
+                "total_found": memories.len()
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Recall failed: {}", e);
+                error.data = Some(json!({"query": query}));
+                Err(error)
+            }
+        }
+    }
+
+    fn search_knowledge_chunk(&self, query: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, group_by: Option<String>, chunks_per_group: Option<usize>, expand_context: Option<usize>, filter: Option<MetadataFilter>, collection: Option<String>, debug: Option<bool>) -> Result<Value, JsonRpcError> {
+        let k = top_k.unwrap_or(10);
+        let options = SearchOptions {
+            exclude_terms: exclude_terms.unwrap_or_default(),
+            minimum_should_match,
+            filter,
+            collection,
+        };
+
+        // Grouped searches return the top_k files, each with its best chunks nested
+        if let Some(key) = group_by.as_deref() {
+            if key != "source_file" {
+                return Err(JsonRpcError::invalid_params(format!("Unsupported group_by '{}': expected \"source_file\"", key)));
+            }
+        }
+        let per_group = chunks_per_group.unwrap_or(3).max(1);
+        let candidates = if group_by.is_some() { k * per_group * 3 } else { k };
+
+        let expand = expand_context.unwrap_or(0).min(MAX_EXPAND_CONTEXT);
+
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let (results, provider, timings) = self.search_chunks_traced(&query, candidates, &options).await?;
+                // Grouped results are cut to the top_k files first, so only returned chunks get context
+                let (results, groups) = if group_by.is_some() {
+                    (Vec::new(), Some(group_results(results, "source_file", per_group).into_iter().take(k).collect::<Vec<_>>()))
+                } else {
+                    (results, None)
+                };
+                let returned = || results.iter().chain(groups.iter().flatten().flat_map(|group| &group.results));
+                let context = if expand > 0 {
+                    self.sequential_context(returned(), expand).await
+                } else {
+                    std::collections::HashMap::new()
+                };
+                let pinned = self.pinned_context("search_knowledge_chunk", returned().filter_map(|r| r.metadata.get("source_file").map(|f| f.as_str()))).await;
+                Ok::<_, anyhow::Error>(((results, groups), provider, timings, context, pinned))
+            })
+        });
+
+        let (result, provider, timings, context, pinned) = match result {
+            Ok((results, provider, timings, context, pinned)) => (Ok(results), Some(provider), timings, context, pinned),
+            Err(e) => (Err(e), None, Vec::new(), std::collections::HashMap::new(), Vec::new()),
+        };
+        // Per-stage timings and candidate counts, on request
+        let debug = debug.unwrap_or(false).then(|| json!({
+            "pipeline": self.pipelines.for_collection(options.collection.as_deref()).names(),
+            "stages": timings,
+            "total_ms": timings.iter().map(|t| t.elapsed_ms).sum::<f64>()
+        }));
+
+        let chunk_json = |r: &SearchResult| {
+            let mut chunk = json!({
+                "id": r.chunk_id,
+                "content": r.content,
+                "score": r.score,
+                "metadata": r.metadata
+            });
+            for (key, attribute) in [("title", "chunk_title"), ("summary", "chunk_summary")] {
+                if let Some(value) = r.metadata.get(attribute) {
+                    chunk[key] = json!(value);
+                }
+            }
+            if let Some(neighbours) = context.get(&r.chunk_id) {
+                chunk["context"] = neighbours.clone();
+            }
+            chunk
+        };
+
+        match result {
+            Ok((_, Some(groups))) => {
+                let result_summary = ResultSummary::of(groups.iter().flat_map(|group| &group.results));
+                let groups: Vec<Value> = groups.into_iter()
+                    .map(|group| json!({
+                        "source_file": group.key,
+                        "score": group.score,
+                        "total_matches": group.total_matches,
+                        "chunks": group.results.iter().map(chunk_json).collect::<Vec<_>>()
+                    }))
+                    .collect();
+                let mut response = json!({
+                    "query": query,
+                    "embedding_provider": provider,
+                    "pinned_context": pinned,
+                    "group_by": "source_file",
+                    "groups": groups,
+                    "result_summary": result_summary,
+                    "total_found": groups.len()
+                });
+                if let Some(debug) = debug {
+                    response["debug"] = debug;
+                }
+                Ok(response)
+            }
+            Ok((results, None)) => {
+                let mut response = json!({
+                    "query": query,
+                    "embedding_provider": provider,
+                    "pinned_context": pinned,
+                    "chunks": results.iter().map(chunk_json).collect::<Vec<_>>(),
+                    "result_summary": ResultSummary::of(&results),
+                    "total_found": results.len()
+                });
+                if let Some(debug) = debug {
+                    response["debug"] = debug;
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Search failed: {}", e);
+                error.data = Some(json!({"query": query}));
+                Err(error)
+            }
+        }
+    }
+
+    fn diagnose_query(&self, query: String, chunk_id: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, filter: Option<MetadataFilter>) -> Result<Value, JsonRpcError> {
+        let k = top_k.unwrap_or(10).max(1);
+        let options = SearchOptions {
+            exclude_terms: exclude_terms.unwrap_or_default(),
+            minimum_should_match,
+            filter,
+            collection: None,
+        };
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.diagnose(&query, &chunk_id, k, &options).await
+            })
+        });
+
+        match result {
+            Ok(mut diagnosis) => {
+                diagnosis["status"] = json!("success");
+                Ok(diagnosis)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Diagnosis failed: {}", e);
+                error.data = Some(json!({"query": query, "chunk_id": chunk_id}));
+                Err(error)
+            }
+        }
+    }
+
+    fn search_knowledge_chapter(&self, query: String, top_k: Option<usize>, exclude_terms: Option<Vec<String>>, minimum_should_match: Option<MinimumShouldMatch>, filter: Option<MetadataFilter>) -> Result<Value, JsonRpcError> {
+        let k = top_k.unwrap_or(5);
+        let options = SearchOptions {
+            exclude_terms: exclude_terms.unwrap_or_default(),
+            minimum_should_match,
+            filter,
+            collection: None,
+        };
+
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let (chapters, provider) = self.search_chapters(&query, k, &options).await?;
+                let pinned = self.pinned_context("search_knowledge_chapter", chapters.iter().filter_map(|c| c["file"].as_str())).await;
+                Ok::<_, anyhow::Error>((chapters, provider, pinned))
+            })
+        });
+
+        match result {
+            Ok((chapters, provider, pinned)) => Ok(json!({
+                "query": query,
+                "embedding_provider": provider,
+                "pinned_context": pinned,
+                "chapters": chapters,
+                "total_found": chapters.len()
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Chapter search failed: {}", e);
+                error.data = Some(json!({"query": query}));
+                Err(error)
+            }
+        }
+    }
+
+    fn health(&self) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.collect_health().await
+            })
+        });
+
+        Ok(result)
+    }
+
+    fn query_graph(&self, query: Value) -> Result<Value, JsonRpcError> {
+        let query: GraphQuery = serde_json::from_value(query)
+            .map_err(|e| JsonRpcError::invalid_params(format!("Invalid graph query: {}", e)))?;
+
+        let result = self.graph.snapshot().query(&query);
+
+        match result {
+            Ok(hits) => Ok(json!({
+                "start": query.start,
+                "nodes": hits,
+                "total_found": hits.len()
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Graph query failed: {}", e);
+                error.data = Some(json!({"start": query.start}));
+                Err(error)
+            }
+        }
+    }
+
+    fn propose_collections(&self, count: Option<usize>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.compute_collection_proposals(count).await
+            })
+        });
+
+        match result {
+            Ok(proposals) => Ok(json!({
+                "proposals": proposals,
+                "total_found": proposals.len()
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Collection analysis failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn accept_collection(&self, proposal_id: usize, name: Option<String>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.collections.write().await.accept(proposal_id, name)
+            })
+        });
+
+        match result {
+            Ok((collection, documents)) => Ok(json!({
+                "status": "success",
+                "collection": collection,
+                "documents_assigned": documents
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Accepting collection failed: {}", e);
+                error.data = Some(json!({"proposal_id": proposal_id}));
+                Err(error)
+            }
+        }
+    }
+
+    fn pin_context(&self, chunk_id: Option<String>, source_file: Option<String>, tools: Option<Vec<String>>, collections: Option<Vec<String>>, note: Option<String>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // Pins are checked against the store when made; ones that go stale later are skipped
+                let found = match (&chunk_id, &source_file) {
+                    (Some(chunk_id), _) => self.storage.get_chunk(chunk_id)?.is_some(),
+                    (None, Some(file)) => self.storage.document_stats(file)?.is_some(),
+                    (None, None) => true,
+                };
+                if !found {
+                    return Err(anyhow::anyhow!("Nothing ingested as {}", chunk_id.as_deref().or(source_file.as_deref()).unwrap_or_default()));
+                }
+                self.pins.write().await.pin(chunk_id.clone(), source_file.clone(), tools.unwrap_or_default(), collections.unwrap_or_default(), note)
+            })
+        });
+
+        match result {
+            Ok(pin) => Ok(json!({
+                "status": "success",
+                "pin": pin
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Pinning failed: {}", e);
+                error.data = Some(json!({"chunk_id": chunk_id, "source_file": source_file}));
+                Err(error)
+            }
+        }
+    }
+
+    fn unpin_context(&self, pin_id: usize) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.pins.write().await.unpin(pin_id)
+            })
+        });
+
+        match result {
+            Ok(pin) => Ok(json!({
+                "status": "success",
+                "unpinned": pin
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Unpinning failed: {}", e);
+                error.data = Some(json!({"pin_id": pin_id}));
+                Err(error)
+            }
+        }
+    }
+
+    fn list_pins(&self) -> Result<Value, JsonRpcError> {
+        let pins = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.pins.read().await.pins().to_vec()
+            })
+        });
+
+        Ok(json!({
+            "status": "success",
+            "pins": pins,
+            "total": pins.len()
+        }))
+    }
+
+    fn extract_glossary(&self) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                // Memories are the user's notes, not corpus definitions
+                let chunks: Vec<Chunk> = self.storage.scan(ChunkQuery::all())
+                    .filter(|chunk| !chunk.metadata.source_file.starts_with(MEMORY_SOURCE_PREFIX))
+                    .collect();
+                let mut glossary = self.glossary.write().await;
+                let counts = self.pools.ingest.install(|| glossary.rebuild(chunks.iter()))?;
+                Ok::<_, anyhow::Error>((chunks.len(), counts))
+            })
+        });
+
+        match result {
+            Ok((chunks_scanned, (terms, definitions))) => Ok(json!({
+                "status": "success",
+                "chunks_scanned": chunks_scanned,
+                "terms": terms,
+                "definitions": definitions
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Glossary extraction failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn define(&self, term: String, limit: Option<usize>) -> Result<Value, JsonRpcError> {
+        let limit = limit.unwrap_or(3).max(1);
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let glossary = self.glossary.read().await;
+                if glossary.built_at().is_none() {
+                    let mut error = JsonRpcError::internal_error();
+                    error.message = "No glossary has been extracted yet; run extract_glossary first".to_string();
+                    return Err(error);
+                }
+
+                let definitions: Vec<_> = glossary.define(&term).iter().take(limit).collect();
+                let similar = if definitions.is_empty() { glossary.similar_terms(&term, 10) } else { vec![] };
+                Ok(json!({
+                    "status": "success",
+                    "term": term,
+                    "found": !definitions.is_empty(),
+                    "definitions": definitions,
+                    "similar_terms": similar,
+                    "glossary_built_at": glossary.built_at()
+                }))
+            })
+        })
+    }
+
+    fn suggest_vocabulary(&self, min_occurrences: Option<usize>) -> Result<Value, JsonRpcError> {
+        let min_occurrences = min_occurrences.unwrap_or(2).max(1);
+        let suggestions = suggest_vocabulary(
+            &self.metrics.queries(),
+            &self.query_enhancer,
+            self.config.search.low_score_threshold,
+            min_occurrences,
+        );
+
+        Ok(json!({
+            "status": "success",
+            "total_queries": suggestions.total_queries,
+            "failed_queries": suggestions.failed_queries,
+            "queries_by_intent": suggestions.queries_by_intent,
+            "failed_by_intent": suggestions.failed_by_intent,
+            "suggested_abbreviations": suggestions.abbreviations.len(),
+            "suggested_synonyms": suggestions.synonyms.len(),
+            "patch": suggestions.to_yaml_patch()
+        }))
+    }
+
+    fn diff_chunks(&self, chunk_id: String, other_chunk_id: Option<String>, text: Option<String>, context_lines: Option<usize>) -> Result<Value, JsonRpcError> {
+        if other_chunk_id.is_some() == text.is_some() {
+            return Err(JsonRpcError::invalid_params("Pass exactly one of other_chunk_id or text"));
+        }
+
+        let context = context_lines.unwrap_or(DEFAULT_DIFF_CONTEXT);
+        match self.compare_chunk(&chunk_id, other_chunk_id.as_deref(), text.as_deref(), context) {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Diff failed: {}", e);
+                error.data = Some(json!({"chunk_id": chunk_id, "other_chunk_id": other_chunk_id}));
+                Err(error)
+            }
+        }
+    }
+
+    fn verify_citation(&self, quote: String, source_file: String, min_score: Option<f32>) -> Result<Value, JsonRpcError> {
+        if quote.trim().is_empty() {
+            return Err(JsonRpcError::invalid_params("Quote is empty"));
+        }
+
+        let min_score = min_score.unwrap_or(DEFAULT_CITATION_MIN_SCORE).clamp(0.0, 1.0);
+        match self.locate_citation(&quote, &source_file) {
+            Ok((sources, location)) => {
+                let score = location.as_ref().and_then(|l| l.get("score")).and_then(|s| s.as_f64()).unwrap_or(0.0);
+                Ok(json!({
+                    "verified": location.is_some() && score >= min_score as f64,
+                    "source_found": !sources.is_empty(),
+                    "searched_sources": sources,
+                    "best_match": location
+                }))
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Citation check failed: {}", e);
+                error.data = Some(json!({"source_file": source_file}));
+                Err(error)
+            }
+        }
+    }
+
+    fn generate_report(&self, template: Option<String>, template_yaml: Option<String>, output_path: Option<String>, response_language: Option<String>, overwrite: Option<bool>) -> Result<Value, JsonRpcError> {
+        let parsed = match (&template, &template_yaml) {
+            (Some(name), None) => ReportTemplate::load(name, &self.config.report.template_dir),
+            (None, Some(yaml)) => ReportTemplate::from_yaml(yaml),
+            _ => return Err(JsonRpcError::invalid_params("Pass exactly one of template or template_yaml")),
+        };
+        let report_template = parsed.map_err(|e| JsonRpcError::invalid_params(format!("{:#}", e)))?;
+        let language = response_language.as_deref()
+            .or(report_template.response_language.as_deref())
+            .unwrap_or(&self.config.report.response_language);
+        let language = ResponseLanguage::parse(language).map_err(|e| JsonRpcError::invalid_params(e.to_string()))?;
+
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let markdown = self.build_report(&report_template, language).await?;
+                let written = output_path.as_deref()
+                    .map(|path| write_report(&self.config.report.output_dir, path, &markdown, overwrite.unwrap_or(false)))
+                    .transpose()?;
+                Ok::<_, anyhow::Error>((markdown, written))
+            })
+        });
+
+        match result {
+            Ok((markdown, written)) => Ok(json!({
+                "status": "success",
+                "title": report_template.title,
+                "sections": report_template.sections.len(),
+                "response_language": language.code(),
+                "output_path": written.map(|path| path.display().to_string()),
+                "markdown": markdown
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Report generation failed: {}", e);
+                error.data = Some(json!({"template": template, "output_path": output_path}));
+                Err(error)
+            }
+        }
+    }
+
+    fn ingest_sources(&self, source: Option<String>) -> Result<Value, JsonRpcError> {
+        if self.config.sources.is_empty() {
+            return Err(JsonRpcError::invalid_params("No sources are configured; add them under `sources` in the config file"));
+        }
+
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.sync_sources(false, source.as_deref()).await
+            })
+        });
+
+        match result {
+            Ok(sources) => Ok(json!({
+                "status": "success",
+                "files_ingested": sources.iter().filter_map(|s| s.get("ingested").and_then(|n| n.as_u64())).sum::<u64>(),
+                "files_updated": sources.iter().filter_map(|s| s.get("updated").and_then(|n| n.as_u64())).sum::<u64>(),
+                "files_unchanged": sources.iter().filter_map(|s| s.get("already_ingested").and_then(|n| n.as_u64())).sum::<u64>(),
+                "sources": sources
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Source ingestion failed: {}", e);
+                error.data = Some(json!({"source": source}));
+                Err(error)
+            }
+        }
+    }
+
+    fn ingest_url(&self, url: String, doc_type: Option<String>, force: Option<bool>, crawl: Option<String>, max_depth: Option<usize>, max_pages: Option<usize>) -> Result<Value, JsonRpcError> {
+        if let Some(crawl) = crawl {
+            let mode = CrawlMode::parse(&crawl).map_err(|e| JsonRpcError::invalid_params(e.to_string()))?;
+            let max_depth = max_depth.unwrap_or(self.config.ingestion.crawl_max_depth);
+            let max_pages = max_pages.unwrap_or(self.config.ingestion.crawl_max_pages).max(1);
+            let result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    self.crawl_site(&url, mode, max_depth, max_pages, force.unwrap_or(false)).await
+                })
+            });
+
+            return match result {
+                Ok(mut summary) => {
+                    summary["status"] = json!("success");
+                    summary["url"] = json!(url);
+                    summary["crawl"] = json!(crawl);
+                    Ok(summary)
+                }
+                Err(e) => {
+                    let mut error = JsonRpcError::internal_error();
+                    error.message = format!("Crawl failed: {}", e);
+                    error.data = Some(json!({"url": url, "crawl": crawl}));
+                    Err(error)
+                }
+            };
+        }
+
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.process_url(&url, doc_type.as_deref(), force.unwrap_or(false)).await
+            })
+        });
+
+        match result {
+            Ok((chunk_count, fetch)) => Ok(json!({
+                "status": "success",
+                "chunks_created": chunk_count,
+                "url": url,
+                "fetch": fetch
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("URL ingestion failed: {}", e);
+                error.data = Some(json!({"url": url}));
+                Err(error)
+            }
+        }
+    }
+
+    fn rebuild_index(&self, check_only: Option<bool>) -> Result<Value, JsonRpcError> {
+        let check_only = check_only.unwrap_or(false);
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.rebuild_indexes(check_only).await
+            })
+        });
+
+        match result {
+            Ok(mut report) => {
+                report["status"] = json!("success");
+                report["check_only"] = json!(check_only);
+                Ok(report)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Index rebuild failed: {}", e);
+                error.data = Some(json!({"check_only": check_only}));
+                Err(error)
+            }
+        }
+    }
+
+    fn refresh_urls(&self) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.refresh_url_documents().await
+            })
+        });
+
+        match result {
+            Ok(mut summary) => {
+                summary["status"] = json!("success");
+                Ok(summary)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("URL refresh failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn list_documents(&self, prefix: Option<String>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.document_listing(prefix.as_deref()).await
+            })
+        });
+
+        match result {
+            Ok(mut listing) => {
+                listing["status"] = json!("success");
+                Ok(listing)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Listing documents failed: {}", e);
+                error.data = Some(json!({"prefix": prefix}));
+                Err(error)
+            }
+        }
+    }
+
+    fn stats(&self) -> Result<Value, JsonRpcError> {
+        match self.corpus_stats() {
+            Ok(mut stats) => {
+                stats["status"] = json!("success");
+                Ok(stats)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Collecting stats failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn reembed(&self, check_only: Option<bool>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let (_, model) = self.embedder.primary();
+                let mut current = self.reembed.write().await;
+                if check_only.unwrap_or(false) || current.as_ref().is_some_and(ReembedProgress::is_running) {
+                    return Ok(json!({"model": model, "stale_chunks": self.stale_chunk_ids().len(), "progress": *current, "started": false}));
+                }
+
+                let (stale, files) = self.plan_reembed()?;
+                if stale.is_empty() {
+                    return Ok(json!({"model": model, "stale_chunks": 0, "progress": *current, "started": false}));
+                }
+                let stale_chunks = stale.len();
+                let progress = ReembedProgress::new(model, stale_chunks, files.len());
+                *current = Some(progress.clone());
+                drop(current);
+
+                let server = self.clone();
+                tokio::spawn(async move {
+                    let outcome = server.reembed_files(&stale, &files).await;
+                    if let Some(progress) = server.reembed.write().await.as_mut() {
+                        progress.finished_at = Some(chrono::Utc::now());
+                        if let Err(e) = &outcome {
+                            progress.error = Some(e.to_string());
+                        }
+                        tracing::info!("Re-embed finished: {} of {} chunks re-embedded, {} still stale", progress.reembedded, progress.stale_chunks, progress.still_stale);
+                    }
+                    if let Err(e) = outcome {
+                        tracing::error!("Re-embed failed: {}", e);
+                    }
+                });
+                anyhow::Ok(json!({"model": model, "stale_chunks": stale_chunks, "progress": progress, "started": true}))
+            })
+        });
+
+        match result {
+            Ok(mut report) => {
+                report["status"] = json!("success");
+                Ok(report)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Re-embed failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn check_embedding_drift(&self, sample_size: Option<usize>) -> Result<Value, JsonRpcError> {
+        let sample_size = sample_size.unwrap_or(self.config.embedding.drift_sample_size);
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let round = chrono::Utc::now().timestamp() as u64;
+                self.check_drift(sample_size, round).await
+            })
+        });
+
+        match result {
+            Ok(report) => Ok(json!({
+                "status": "success",
+                "report": report
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Embedding drift check failed: {}", e);
+                Err(error)
+            }
+        }
+    }
+
+    fn record_feedback(&self, query: String, chunk_id: String, relevant: Option<bool>) -> Result<Value, JsonRpcError> {
+        let feedback = Feedback { query, chunk_id, relevant: relevant.unwrap_or(true), recorded_at: chrono::Utc::now() };
+        let result = match self.storage.get_chunk(&feedback.chunk_id) {
+            Ok(Some(_)) => self.feedback.record(&feedback),
+            Ok(None) => Err(anyhow::anyhow!("Chunk not found: {}", feedback.chunk_id)),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => Ok(json!({
+                "status": "success",
+                "query": feedback.query,
+                "chunk_id": feedback.chunk_id,
+                "relevant": feedback.relevant
+            })),
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Recording feedback failed: {}", e);
+                error.data = Some(json!({"chunk_id": feedback.chunk_id}));
+                Err(error)
+            }
+        }
+    }
+
+    fn tune_ranking(&self, eval_set: Option<String>, top_k: Option<usize>, apply: Option<bool>) -> Result<Value, JsonRpcError> {
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                self.tune_fusion(eval_set.as_deref(), top_k.unwrap_or(10).max(1), apply.unwrap_or(false)).await
+            })
+        });
+
+        match result {
+            Ok(mut report) => {
+                report["status"] = json!("success");
+                Ok(report)
+            }
+            Err(e) => {
+                let mut error = JsonRpcError::internal_error();
+                error.message = format!("Ranking tuning failed: {}", e);
+                error.data = Some(json!({"eval_set": eval_set}));
                 Err(error)
             }
         }
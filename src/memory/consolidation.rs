@@ -0,0 +1,212 @@
+use crate::chunker::Chunk;
+use crate::config::MemoryConfig;
+use chrono::{DateTime, Duration, Utc};
+
+/// Thresholds for a memory consolidation pass
+#[derive(Debug, Clone)]
+pub struct ConsolidationPolicy {
+    pub duplicate_similarity: f32,
+    pub stale_after: Duration,
+    pub decay_half_life_days: f32,
+    pub archive_below_strength: f32,
+    pub expire_after: Option<Duration>,
+}
+
+impl ConsolidationPolicy {
+    pub fn from_config(config: &MemoryConfig) -> Self {
+        Self {
+            duplicate_similarity: config.duplicate_similarity,
+            stale_after: Duration::days(config.stale_after_days as i64),
+            decay_half_life_days: config.decay_half_life_days,
+            archive_below_strength: config.archive_below_strength,
+            expire_after: (config.expire_after_days > 0).then(|| Duration::days(config.expire_after_days as i64)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConsolidationReport {
+    pub merged: usize,
+    pub decayed: usize,
+    pub archived: usize,
+    pub active: usize,
+}
+
+/// Merge near-duplicate memories, decay stale ones and archive expired or faded ones.
+/// Archived memories keep their chunk (with `archived` set) so nothing is lost, but recall
+/// skips them. Returns the report and the indices of memories that changed and must be
+/// written back.
+///
+/// Strength is recomputed from the time since a memory was last recalled (or created), so
+/// running the pass more often does not fade memories faster.
+pub fn consolidate(memories: &mut [Chunk], policy: &ConsolidationPolicy, now: DateTime<Utc>) -> (ConsolidationReport, Vec<usize>) {
+    let mut report = ConsolidationReport::default();
+    let mut changed = vec![false; memories.len()];
+
+    // 1. Merge near-duplicates into the most recent memory of each pair
+    let mut order: Vec<usize> = (0..memories.len()).filter(|&i| !is_archived(&memories[i])).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(created_at(&memories[i], now)));
+
+    for (position, &keeper) in order.iter().enumerate() {
+        if is_archived(&memories[keeper]) {
+            continue;
+        }
+        for &duplicate in &order[position + 1..] {
+            if is_archived(&memories[duplicate])
+                || cosine(&memories[keeper].embedding, &memories[duplicate].embedding) < policy.duplicate_similarity
+            {
+                continue;
+            }
+
+            let (merged_tags, merged_strength, merged_recall) = {
+                let older = &memories[duplicate];
+                (tags(older), strength(older), last_access(older, now))
+            };
+            let keeper_id = memories[keeper].id.clone();
+            let duplicate_id = memories[duplicate].id.clone();
+
+            let kept = &mut memories[keeper];
+            let mut all_tags = tags(kept);
+            for tag in merged_tags {
+                if !all_tags.contains(&tag) {
+                    all_tags.push(tag.clone());
+                    kept.metadata.tags.push(tag);
+                }
+            }
+            let kept_strength = strength(kept).max(merged_strength);
+            kept.metadata.attributes.insert("memory_tags".to_string(), all_tags.join(","));
+            kept.metadata.attributes.insert("strength".to_string(), format!("{:.4}", kept_strength));
+            if merged_recall > last_access(kept, now) {
+                kept.metadata.attributes.insert("last_recalled".to_string(), merged_recall.to_rfc3339());
+            }
+            kept.metadata.attributes
+                .entry("merged_ids".to_string())
+                .and_modify(|ids| {
+                    ids.push(',');
+                    ids.push_str(&duplicate_id);
+                })
+                .or_insert_with(|| duplicate_id.clone());
+
+            let older = &mut memories[duplicate];
+            archive(older, now, &format!("merged into {}", keeper_id));
+            older.metadata.attributes.insert("merged_into".to_string(), keeper_id.clone());
+
+            changed[keeper] = true;
+            changed[duplicate] = true;
+            report.merged += 1;
+        }
+    }
+
+    // 2. Decay memories not recalled for a while, then 3. archive expired and faded ones
+    for (index, memory) in memories.iter_mut().enumerate() {
+        if is_archived(memory) {
+            continue;
+        }
+
+        let idle = now - last_access(memory, now);
+        if idle > policy.stale_after && policy.decay_half_life_days > 0.0 {
+            let stale_days = (idle - policy.stale_after).num_seconds() as f32 / 86_400.0;
+            let decayed = 0.5f32.powf(stale_days / policy.decay_half_life_days);
+            if (decayed - strength(memory)).abs() > 1e-4 {
+                memory.metadata.attributes.insert("strength".to_string(), format!("{:.4}", decayed));
+                changed[index] = true;
+                report.decayed += 1;
+            }
+        }
+
+        let expired = policy.expire_after.is_some_and(|expire_after| now - created_at(memory, now) > expire_after);
+        if expired || strength(memory) < policy.archive_below_strength {
+            archive(memory, now, if expired { "expired" } else { "faded" });
+            changed[index] = true;
+            report.archived += 1;
+        }
+    }
+
+    report.active = memories.iter().filter(|m| !is_archived(m)).count();
+    let changed = changed.iter().enumerate().filter(|(_, &c)| c).map(|(i, _)| i).collect();
+    (report, changed)
+}
+
+pub fn is_archived(memory: &Chunk) -> bool {
+    memory.metadata.attributes.get("archived").is_some_and(|a| a == "true")
+}
+
+/// Recall weight multiplier, 1.0 until a memory has decayed
+pub fn strength(memory: &Chunk) -> f32 {
+    memory.metadata.attributes.get("strength").and_then(|s| s.parse().ok()).unwrap_or(1.0)
+}
+
+fn archive(memory: &mut Chunk, now: DateTime<Utc>, reason: &str) {
+    let attributes = &mut memory.metadata.attributes;
+    attributes.insert("archived".to_string(), "true".to_string());
+    attributes.insert("archived_at".to_string(), now.to_rfc3339());
+    attributes.insert("archive_reason".to_string(), reason.to_string());
+}
+
+fn tags(memory: &Chunk) -> Vec<String> {
+    memory.metadata.attributes.get("memory_tags")
+        .map(|tags| tags.split(',').filter(|t| !t.is_empty()).map(|t| t.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn timestamp_attribute(memory: &Chunk, key: &str) -> Option<DateTime<Utc>> {
+    memory.metadata.attributes.get(key)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn created_at(memory: &Chunk, now: DateTime<Utc>) -> DateTime<Utc> {
+    timestamp_attribute(memory, "created_at").unwrap_or(now)
+}
+
+fn last_access(memory: &Chunk, now: DateTime<Utc>) -> DateTime<Utc> {
+    timestamp_attribute(memory, "last_recalled").unwrap_or_else(|| created_at(memory, now))
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::build_memory_chunk;
+
+    fn memory(text: &str, tag: &str, embedding: Vec<f32>, age_days: i64, now: DateTime<Utc>) -> Chunk {
+        let mut chunk = build_memory_chunk(text, &[tag.to_string()], now - Duration::days(age_days));
+        chunk.embedding = embedding;
+        chunk
+    }
+
+    #[test]
+    fn test_merge_decay_and_archive() {
+        let now = Utc::now();
+        let policy = ConsolidationPolicy {
+            duplicate_similarity: 0.95,
+            stale_after: Duration::days(30),
+            decay_half_life_days: 30.0,
+            archive_below_strength: 0.1,
+            expire_after: None,
+        };
+        let mut memories = vec![
+            memory("Bursts are 16 beats", "dma", vec![1.0, 0.0, 0.0], 10, now),
+            memory("Bursts are 16 beats long", "axi", vec![0.99, 0.05, 0.0], 2, now),
+            memory("UART runs at 115200", "uart", vec![0.0, 1.0, 0.0], 60, now),
+            memory("Old clock plan", "clk", vec![0.0, 0.0, 1.0], 200, now),
+        ];
+
+        let (report, changed) = consolidate(&mut memories, &policy, now);
+
+        assert_eq!(report, ConsolidationReport { merged: 1, decayed: 2, archived: 1, active: 2 });
+        assert_eq!(changed, vec![0, 1, 2, 3]);
+        assert_eq!(memories[0].metadata.attributes.get("merged_into"), Some(&memories[1].id));
+        assert_eq!(memories[1].metadata.attributes.get("memory_tags").map(|s| s.as_str()), Some("axi,dma"));
+        assert!((strength(&memories[2]) - 0.5).abs() < 1e-3);
+        assert_eq!(memories[3].metadata.attributes.get("archive_reason").map(|s| s.as_str()), Some("faded"));
+    }
+}
\ No newline at end of file
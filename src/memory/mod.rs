@@ -0,0 +1,5 @@
+pub mod recall;
+pub mod consolidation;
+
+pub use recall::*;
+pub use consolidation::*;
\ No newline at end of file
@@ -0,0 +1,129 @@
+use crate::chunker::{Chunk, ChunkStrategy, ChunkType, SemanticChunker};
+use crate::storage::SearchResult;
+use chrono::{DateTime, Utc};
+use sha2::{Sha256, Digest};
+
+/// Collection every memory is assigned to
+pub const MEMORY_COLLECTION: &str = "agent-memory";
+
+/// Memories are stored under synthetic source names with this prefix
+pub const MEMORY_SOURCE_PREFIX: &str = "memory://";
+
+/// Build the chunk for one memory. It is stored like any other chunk, under its own
+/// `memory://<id>` source, with the caller's tags and its creation time as attributes.
+pub fn build_memory_chunk(text: &str, tags: &[String], now: DateTime<Utc>) -> Chunk {
+    let text = text.trim();
+    let file_hash = format!("{:x}", Sha256::digest(text.as_bytes()));
+    let mut chunk = SemanticChunker::build_text_chunk(text, "", &file_hash, (0, 0), ChunkStrategy::NaturalSection);
+
+    let tags: Vec<String> = tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+    chunk.metadata.source_file = format!("{}{}", MEMORY_SOURCE_PREFIX, chunk.id);
+    chunk.metadata.chunk_type = ChunkType::Memory;
+    chunk.metadata.timestamp = now;
+    for tag in &tags {
+        if !chunk.metadata.tags.contains(tag) {
+            chunk.metadata.tags.push(tag.clone());
+        }
+    }
+
+    chunk.metadata.attributes.insert("created_at".to_string(), now.to_rfc3339());
+    chunk.metadata.attributes.insert("memory_tags".to_string(), tags.join(","));
+    chunk
+}
+
+pub fn is_memory(result: &SearchResult) -> bool {
+    result.metadata.get("source_file").is_some_and(|source| source.starts_with(MEMORY_SOURCE_PREFIX))
+}
+
+/// Weighting between how well a memory matches and how recent it is
+#[derive(Debug, Clone, Copy)]
+pub struct RecallWeights {
+    pub recency_weight: f32,   // Share of the score from recency; the rest is relevance
+    pub half_life_days: f32,   // Age at which the recency component halves
+}
+
+impl RecallWeights {
+    pub fn recency(&self, created_at: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+        let age_days = (now - created_at).num_seconds().max(0) as f32 / 86_400.0;
+        0.5f32.powf(age_days / self.half_life_days.max(f32::EPSILON))
+    }
+
+    pub fn score(&self, relevance: f32, created_at: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+        let weight = self.recency_weight.clamp(0.0, 1.0);
+        (1.0 - weight) * relevance + weight * self.recency(created_at, now)
+    }
+}
+
+/// Re-rank similarity results into recalled memories: non-memory chunks are dropped, as are
+/// archived memories and those missing any of `required_tags`. Scores blend relevance and
+/// recency, scaled by the memory's decayed strength, and the raw similarity is kept under
+/// `relevance`.
+pub fn rank_memories(results: Vec<SearchResult>, required_tags: &[String], weights: RecallWeights, now: DateTime<Utc>, top_k: usize) -> Vec<SearchResult> {
+    let required: Vec<String> = required_tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+
+    let mut memories: Vec<SearchResult> = results.into_iter()
+        .filter(is_memory)
+        .filter(|result| result.metadata.get("archived").is_none_or(|a| a != "true"))
+        .filter(|result| {
+            let tags: Vec<&str> = result.metadata.get("memory_tags").map(|t| t.split(',').collect()).unwrap_or_default();
+            required.iter().all(|tag| tags.contains(&tag.as_str()))
+        })
+        .map(|mut result| {
+            let created_at = result.metadata.get("created_at")
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or(now);
+            result.metadata.insert("relevance".to_string(), format!("{:.4}", result.score));
+            let strength = result.metadata.get("strength").and_then(|s| s.parse::<f32>().ok()).unwrap_or(1.0);
+            result.score = weights.score(result.score, created_at, now) * strength;
+            result
+        })
+        .collect();
+
+    memories.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    memories.truncate(top_k);
+    memories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::collections::HashMap;
+
+    fn memory(id: &str, score: f32, age_days: i64, tags: &str, now: DateTime<Utc>) -> SearchResult {
+        let mut metadata = HashMap::new();
+        metadata.insert("source_file".to_string(), format!("{}{}", MEMORY_SOURCE_PREFIX, id));
+        metadata.insert("created_at".to_string(), (now - Duration::days(age_days)).to_rfc3339());
+        metadata.insert("memory_tags".to_string(), tags.to_string());
+        SearchResult { chunk_id: id.to_string(), score, content: String::new(), metadata }
+    }
+
+    #[test]
+    fn test_recency_breaks_relevance_ties_and_tags_filter() {
+        let now = Utc::now();
+        let weights = RecallWeights { recency_weight: 0.3, half_life_days: 30.0 };
+        let mut document = memory("doc", 0.95, 0, "dma", now);
+        document.metadata.insert("source_file".to_string(), "docs/dma.md".to_string());
+
+        let mut archived = memory("archived", 0.99, 0, "dma", now);
+        archived.metadata.insert("archived".to_string(), "true".to_string());
+
+        let results = vec![
+            memory("old", 0.8, 60, "dma,decision", now),
+            archived,
+            memory("new", 0.75, 1, "dma", now),
+            memory("other", 0.9, 0, "uart", now),
+            document,
+        ];
+
+        let recalled = rank_memories(results.clone(), &["DMA".to_string()], weights, now, 10);
+        assert_eq!(recalled.iter().map(|r| r.chunk_id.as_str()).collect::<Vec<_>>(), vec!["new", "old"]);
+        assert_eq!(recalled[1].metadata.get("relevance").map(|s| s.as_str()), Some("0.8000"));
+        assert!((weights.recency(now - Duration::days(30), now) - 0.5).abs() < 1e-4);
+
+        let chunk = build_memory_chunk("  Use 64-beat bursts  ", &["Decision".to_string()], now);
+        assert!(chunk.metadata.source_file.starts_with(MEMORY_SOURCE_PREFIX));
+        assert_eq!(chunk.metadata.attributes.get("memory_tags").map(|s| s.as_str()), Some("decision"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,66 @@
+use crate::search::EvalCase;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const FEEDBACK_FILE: &str = "feedback.jsonl";
+
+/// A judgement on whether a search result answered a query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feedback {
+    pub query: String,
+    pub chunk_id: String,
+    pub relevant: bool,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Append-only log of result feedback in the data directory, one JSON object per line
+pub struct FeedbackLog {
+    path: PathBuf,
+}
+
+impl FeedbackLog {
+    pub fn new(data_dir: &Path) -> Self {
+        Self { path: data_dir.join(FEEDBACK_FILE) }
+    }
+
+    pub fn record(&self, feedback: &Feedback) -> Result<()> {
+        let mut line = serde_json::to_vec(feedback)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?.write_all(&line)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Result<Vec<Feedback>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        std::fs::read_to_string(&self.path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| anyhow!("Invalid feedback entry in {}: {}", self.path.display(), e)))
+            .collect()
+    }
+
+    /// Feedback as labeled queries: per query (compared case-insensitively), the chunks whose
+    /// latest judgement is relevant. Queries without one are dropped.
+    pub fn eval_cases(&self) -> Result<Vec<EvalCase>> {
+        let mut judgements: BTreeMap<String, (String, BTreeMap<String, bool>)> = BTreeMap::new();
+        for feedback in self.entries()? {
+            let (_, chunks) = judgements.entry(feedback.query.trim().to_lowercase())
+                .or_insert_with(|| (feedback.query.trim().to_string(), BTreeMap::new()));
+            chunks.insert(feedback.chunk_id, feedback.relevant);
+        }
+
+        Ok(judgements.into_values()
+            .map(|(query, chunks)| EvalCase {
+                query,
+                relevant: chunks.into_iter().filter(|(_, relevant)| *relevant).map(|(chunk_id, _)| chunk_id).collect(),
+            })
+            .filter(|case| !case.relevant.is_empty())
+            .collect())
+    }
+}
\ No newline at end of file
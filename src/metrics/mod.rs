@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 
+pub mod feedback;
+pub mod vocabulary;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryMetrics {
     pub query: String,
@@ -15,6 +18,42 @@ pub struct QueryMetrics {
     pub intent: String,
 }
 
+/// Time one search spent in a pipeline stage, and how many candidates went in and came out.
+/// Stages that work on the retrieval lists count the entries of every list.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    pub elapsed_ms: f64,
+    pub candidates_in: usize,
+    pub candidates_out: usize,
+}
+
+impl StageTiming {
+    pub fn new(stage: &'static str, started: Instant, candidates_in: usize, candidates_out: usize) -> Self {
+        Self { stage, elapsed_ms: started.elapsed().as_secs_f64() * 1000.0, candidates_in, candidates_out }
+    }
+}
+
+/// Averages over every search that ran a stage
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+    pub stage: &'static str,
+    pub runs: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub avg_candidates_in: f64,
+    pub avg_candidates_out: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct StageTotals {
+    runs: u64,
+    total_ms: f64,
+    max_ms: f64,
+    candidates_in: u64,
+    candidates_out: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceStats {
     pub total_queries: usize,
@@ -33,20 +72,33 @@ pub struct ScoreDistribution {
     pub poor: usize,         // < 0.4
 }
 
+/// Queries kept for stats and vocabulary suggestions; older ones are dropped as new ones arrive
+const DEFAULT_MAX_QUERIES: usize = 10_000;
+
 /// Performance metrics collector for RAG system
 pub struct PerformanceMetrics {
-    queries: Arc<Mutex<Vec<QueryMetrics>>>,
+    queries: Arc<Mutex<VecDeque<QueryMetrics>>>,
+    max_queries: usize,
+    stages: Arc<Mutex<Vec<(&'static str, StageTotals)>>>,  // In the order stages were first seen
     start_time: Instant,
 }
 
 impl PerformanceMetrics {
     pub fn new() -> Self {
         Self {
-            queries: Arc::new(Mutex::new(Vec::new())),
+            queries: Arc::new(Mutex::new(VecDeque::new())),
+            max_queries: DEFAULT_MAX_QUERIES,
+            stages: Arc::new(Mutex::new(Vec::new())),
             start_time: Instant::now(),
         }
     }
 
+    /// Keep at most `max_queries` recorded queries (at least one)
+    pub fn with_max_queries(mut self, max_queries: usize) -> Self {
+        self.max_queries = max_queries.max(1);
+        self
+    }
+
     /// Record a search query and its results
     pub fn record_query(
         &self,
@@ -68,7 +120,10 @@ impl PerformanceMetrics {
         };
 
         if let Ok(mut queries) = self.queries.lock() {
-            queries.push(metric);
+            if queries.len() >= self.max_queries {
+                queries.pop_front();
+            }
+            queries.push_back(metric);
 
             // Log for monitoring
             eprintln!(
@@ -87,6 +142,44 @@ impl PerformanceMetrics {
         }
     }
 
+    /// Add one search's stage timings to the per-stage totals
+    pub fn record_stage_timings(&self, timings: &[StageTiming]) {
+        if let Ok(mut stages) = self.stages.lock() {
+            for timing in timings {
+                let totals = match stages.iter().position(|(stage, _)| *stage == timing.stage) {
+                    Some(i) => &mut stages[i].1,
+                    None => {
+                        stages.push((timing.stage, StageTotals::default()));
+                        &mut stages.last_mut().unwrap().1
+                    }
+                };
+                totals.runs += 1;
+                totals.total_ms += timing.elapsed_ms;
+                totals.max_ms = totals.max_ms.max(timing.elapsed_ms);
+                totals.candidates_in += timing.candidates_in as u64;
+                totals.candidates_out += timing.candidates_out as u64;
+            }
+        }
+    }
+
+    /// Per-stage latency and candidate counts over every recorded search
+    pub fn stage_summary(&self) -> Vec<StageSummary> {
+        let Ok(stages) = self.stages.lock() else { return Vec::new() };
+        stages.iter()
+            .map(|(stage, totals)| {
+                let runs = totals.runs.max(1) as f64;
+                StageSummary {
+                    stage,
+                    runs: totals.runs,
+                    avg_ms: totals.total_ms / runs,
+                    max_ms: totals.max_ms,
+                    avg_candidates_in: totals.candidates_in as f64 / runs,
+                    avg_candidates_out: totals.candidates_out as f64 / runs,
+                }
+            })
+            .collect()
+    }
+
     /// Get comprehensive performance statistics
     pub fn get_stats(&self) -> PerformanceStats {
         if let Ok(queries) = self.queries.lock() {
@@ -320,6 +413,11 @@ impl PerformanceMetrics {
         }
     }
 
+    /// Snapshot of every recorded query
+    pub fn queries(&self) -> Vec<QueryMetrics> {
+        self.queries.lock().map(|queries| queries.iter().cloned().collect()).unwrap_or_default()
+    }
+
     /// Clear old metrics (keep last N)
     pub fn cleanup_old_metrics(&self, keep_last: usize) {
         if let Ok(mut queries) = self.queries.lock() {
@@ -372,6 +470,30 @@ mod tests {
         assert_eq!(stats.avg_response_time_ms, 100.0);
     }
 
+    #[test]
+    fn test_oldest_queries_are_dropped() {
+        let metrics = PerformanceMetrics::new().with_max_queries(2);
+        for query in ["dma reset", "uart baud", "pcie link"] {
+            metrics.record_query(query, 0.5, 1, Duration::from_millis(10), "hybrid", "concept");
+        }
+        let kept: Vec<String> = metrics.queries().into_iter().map(|q| q.query).collect();
+        assert_eq!(kept, vec!["uart baud", "pcie link"]);
+    }
+
+    #[test]
+    fn test_stage_summary() {
+        let metrics = PerformanceMetrics::new();
+        let timing = |stage, elapsed_ms, candidates_in, candidates_out| StageTiming { stage, elapsed_ms, candidates_in, candidates_out };
+        metrics.record_stage_timings(&[timing("candidates", 4.0, 0, 20), timing("packing", 1.0, 20, 18)]);
+        metrics.record_stage_timings(&[timing("candidates", 2.0, 0, 10)]);
+
+        let summary = metrics.stage_summary();
+        assert_eq!(summary.iter().map(|s| s.stage).collect::<Vec<_>>(), vec!["candidates", "packing"]);
+        assert_eq!((summary[0].runs, summary[0].avg_ms, summary[0].max_ms), (2, 3.0, 4.0));
+        assert_eq!(summary[0].avg_candidates_out, 15.0);
+        assert_eq!(summary[1].avg_candidates_in, 20.0);
+    }
+
     #[test]
     fn test_timer() {
         let timer = Timer::new();
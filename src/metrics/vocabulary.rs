@@ -0,0 +1,248 @@
+use super::QueryMetrics;
+use crate::search::QueryEnhancer;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "how", "what", "why", "when", "where", "which", "does", "use",
+    "using", "from", "into", "that", "this", "are", "can", "get", "set", "show", "find", "about",
+    "between", "difference", "example", "explain", "is", "in", "of", "to", "a", "an", "on", "or",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbbreviationSuggestion {
+    pub term: String,
+    pub expansion: Option<String>,  // Guessed from phrases whose initials match, if any
+    pub occurrences: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynonymSuggestion {
+    pub term: String,
+    pub group: Option<String>,  // Existing synonym group it co-occurs with most
+    pub occurrences: usize,
+    pub failed: usize,
+    pub intents: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VocabularySuggestions {
+    pub total_queries: usize,
+    pub failed_queries: usize,
+    pub queries_by_intent: BTreeMap<String, usize>,
+    pub failed_by_intent: BTreeMap<String, usize>,
+    pub abbreviations: Vec<AbbreviationSuggestion>,
+    pub synonyms: Vec<SynonymSuggestion>,
+}
+
+#[derive(Default)]
+struct TermStats {
+    occurrences: usize,
+    failed: usize,
+    uppercase: bool,
+    intents: HashSet<String>,
+    co_terms: HashMap<String, usize>,
+}
+
+/// Mine recorded queries for terms missing from the domain vocabulary. Only terms seen in
+/// at least `min_occurrences` failed (empty or low-scoring) queries are suggested.
+pub fn suggest_vocabulary(
+    queries: &[QueryMetrics],
+    enhancer: &QueryEnhancer,
+    low_score: f32,
+    min_occurrences: usize,
+) -> VocabularySuggestions {
+    let mut suggestions = VocabularySuggestions {
+        total_queries: queries.len(),
+        ..Default::default()
+    };
+
+    let mut stats: HashMap<String, TermStats> = HashMap::new();
+    let mut phrases: Vec<Vec<String>> = Vec::new();
+    let groups: HashSet<&String> = enhancer.synonym_groups().collect();
+
+    for query in queries {
+        let failed = query.result_count == 0 || query.top_score < low_score;
+        *suggestions.queries_by_intent.entry(query.intent.clone()).or_insert(0) += 1;
+        if failed {
+            suggestions.failed_queries += 1;
+            *suggestions.failed_by_intent.entry(query.intent.clone()).or_insert(0) += 1;
+        }
+
+        let raw_words: Vec<&str> = query.query.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|w| !w.is_empty()).collect();
+        let words: Vec<String> = raw_words.iter().map(|w| w.to_lowercase()).collect();
+        let known_in_query: Vec<&String> = words.iter().filter(|w| groups.contains(w)).collect();
+
+        for (raw, word) in raw_words.iter().zip(&words) {
+            if word.len() < 2 || word.chars().all(|c| c.is_ascii_digit()) || STOP_WORDS.contains(&word.as_str()) {
+                continue;
+            }
+            if enhancer.is_known_term(word) {
+                continue;
+            }
+
+            let entry = stats.entry(word.clone()).or_default();
+            entry.occurrences += 1;
+            entry.uppercase |= raw.len() > 1 && raw.chars().all(|c| c.is_ascii_uppercase());
+            if failed {
+                entry.failed += 1;
+                entry.intents.insert(query.intent.clone());
+            }
+            for group in &known_in_query {
+                *entry.co_terms.entry((*group).clone()).or_insert(0) += 1;
+            }
+        }
+
+        phrases.push(words);
+    }
+
+    for (term, term_stats) in stats {
+        if term_stats.failed < min_occurrences {
+            continue;
+        }
+
+        if looks_like_abbreviation(&term, term_stats.uppercase) && !enhancer.has_abbreviation(&term) {
+            suggestions.abbreviations.push(AbbreviationSuggestion {
+                expansion: guess_expansion(&term, &phrases),
+                term,
+                occurrences: term_stats.occurrences,
+                failed: term_stats.failed,
+            });
+        } else if term.len() > 3 {
+            let group = term_stats.co_terms.iter()
+                .filter(|(_, &count)| count >= min_occurrences)
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(group, _)| group.clone());
+            let mut intents: Vec<String> = term_stats.intents.into_iter().collect();
+            intents.sort();
+
+            suggestions.synonyms.push(SynonymSuggestion {
+                term,
+                group,
+                occurrences: term_stats.occurrences,
+                failed: term_stats.failed,
+                intents,
+            });
+        }
+    }
+
+    suggestions.abbreviations.sort_by(|a, b| b.failed.cmp(&a.failed).then_with(|| a.term.cmp(&b.term)));
+    suggestions.synonyms.sort_by(|a, b| b.failed.cmp(&a.failed).then_with(|| a.term.cmp(&b.term)));
+    suggestions
+}
+
+/// Short all-caps words, or short words without vowels (cfg, drv, sva)
+fn looks_like_abbreviation(term: &str, seen_uppercase: bool) -> bool {
+    let short = (2..=5).contains(&term.len()) && term.chars().all(|c| c.is_ascii_alphabetic());
+    short && (seen_uppercase || !term.chars().any(|c| "aeiou".contains(c)))
+}
+
+/// Most frequent run of words in any query whose initials spell the abbreviation
+fn guess_expansion(term: &str, phrases: &[Vec<String>]) -> Option<String> {
+    let initials: Vec<char> = term.chars().collect();
+    let mut candidates: HashMap<String, usize> = HashMap::new();
+
+    for words in phrases {
+        for window in words.windows(initials.len()) {
+            let matches = window.iter().zip(&initials).all(|(word, initial)| word.starts_with(*initial));
+            if matches && window.iter().all(|w| w.len() > 1) {
+                *candidates.entry(window.join(" ")).or_insert(0) += 1;
+            }
+        }
+    }
+
+    candidates.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(phrase, _)| phrase)
+}
+
+impl VocabularySuggestions {
+    /// Render as a YAML patch for the query vocabulary. Blank entries are skipped when the
+    /// patch is loaded, so reviewers only need to fill in or delete lines.
+    pub fn to_yaml_patch(&self) -> String {
+        let mut yaml = String::new();
+        yaml.push_str("# Suggested domain vocabulary additions - review before applying.\n");
+        yaml.push_str(&format!("# Based on {} queries, {} of them empty or low-scoring.\n", self.total_queries, self.failed_queries));
+        for (intent, count) in &self.queries_by_intent {
+            let failed = self.failed_by_intent.get(intent).copied().unwrap_or(0);
+            yaml.push_str(&format!("#   intent {}: {} queries, {} failed\n", intent, count, failed));
+        }
+
+        yaml.push_str("abbreviations:");
+        if self.abbreviations.is_empty() {
+            yaml.push_str(" {}\n");
+        } else {
+            yaml.push('\n');
+            for suggestion in &self.abbreviations {
+                let expansion = suggestion.expansion.as_deref().unwrap_or("");
+                let note = if suggestion.expansion.is_some() { "" } else { ", expansion unknown" };
+                yaml.push_str(&format!(
+                    "  {}: {:?}  # seen {}x, {} failed{}\n",
+                    suggestion.term, expansion, suggestion.occurrences, suggestion.failed, note
+                ));
+            }
+        }
+
+        yaml.push_str("synonyms:");
+        if self.synonyms.is_empty() {
+            yaml.push_str(" {}\n");
+        } else {
+            yaml.push('\n');
+            for suggestion in &self.synonyms {
+                let comment = format!(
+                    "seen {}x, {} failed, intents: {}",
+                    suggestion.occurrences, suggestion.failed, suggestion.intents.join(", ")
+                );
+                match &suggestion.group {
+                    Some(group) => yaml.push_str(&format!("  {}: [{:?}]  # {}\n", group, suggestion.term, comment)),
+                    None => yaml.push_str(&format!("  {}: []  # new group; {}\n", suggestion.term, comment)),
+                }
+            }
+        }
+
+        yaml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::VocabularyPatch;
+    use chrono::Utc;
+
+    fn query(text: &str, top_score: f32, intent: &str) -> QueryMetrics {
+        QueryMetrics {
+            query: text.to_string(),
+            top_score,
+            result_count: 3,
+            response_time_ms: 10,
+            timestamp: Utc::now(),
+            search_method: "hybrid".to_string(),
+            intent: intent.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_failed_queries_produce_loadable_patch() {
+        let queries = vec![
+            query("SVA for fifo overflow", 0.2, "code_example"),
+            query("SVA property syntax", 0.3, "mixed"),
+            query("system verilog assertion examples", 0.9, "code_example"),
+            query("driver handshake protocol", 0.1, "concept"),
+            query("driver handshake timing", 0.2, "concept"),
+        ];
+        let enhancer = QueryEnhancer::new();
+
+        let suggestions = suggest_vocabulary(&queries, &enhancer, 0.4, 2);
+
+        assert_eq!(suggestions.failed_queries, 4);
+        assert_eq!(suggestions.abbreviations[0].term, "sva");
+        assert_eq!(suggestions.abbreviations[0].expansion.as_deref(), Some("system verilog assertion"));
+        let handshake = suggestions.synonyms.iter().find(|s| s.term == "handshake").unwrap();
+        assert_eq!(handshake.group.as_deref(), Some("driver"));
+
+        let patch: VocabularyPatch = serde_yaml::from_str(&suggestions.to_yaml_patch()).unwrap();
+        assert_eq!(patch.abbreviations.get("sva").map(|s| s.as_str()), Some("system verilog assertion"));
+        assert_eq!(patch.synonyms.get("driver"), Some(&vec!["handshake".to_string()]));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use anyhow::{Result, anyhow};
+
+/// Language of the prose generated around retrieved content. Retrieval itself is unaffected;
+/// excerpts are always quoted as written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseLanguage {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Japanese,
+    Chinese,
+}
+
+/// Fixed wording in a rendered report
+#[derive(Debug, Clone, Copy)]
+pub struct ReportLabels {
+    pub generated: &'static str,
+    pub query: &'static str,
+    pub no_results: &'static str,
+    pub sources: &'static str,
+    pub section: &'static str,
+    pub page: &'static str,
+    pub line: &'static str,
+    pub lines: &'static str,
+}
+
+const LANGUAGES: &[(ResponseLanguage, &str, &str)] = &[
+    (ResponseLanguage::English, "en", "english"),
+    (ResponseLanguage::German, "de", "german"),
+    (ResponseLanguage::French, "fr", "french"),
+    (ResponseLanguage::Spanish, "es", "spanish"),
+    (ResponseLanguage::Japanese, "ja", "japanese"),
+    (ResponseLanguage::Chinese, "zh", "chinese"),
+];
+
+impl ResponseLanguage {
+    /// An ISO 639-1 code ("de"), optionally with a region ("de-CH"), or an English name ("German")
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim().to_lowercase();
+        let code = text.split(['-', '_']).next().unwrap_or_default();
+        LANGUAGES.iter()
+            .find(|(_, iso, name)| *iso == code || *name == text)
+            .map(|(language, _, _)| *language)
+            .ok_or_else(|| anyhow!(
+                "Unsupported response language '{}'; expected one of: {}",
+                text, LANGUAGES.iter().map(|(_, iso, _)| *iso).collect::<Vec<_>>().join(", ")
+            ))
+    }
+
+    pub fn code(&self) -> &'static str {
+        LANGUAGES.iter().find(|(language, _, _)| language == self).map(|(_, iso, _)| *iso).unwrap_or("en")
+    }
+
+    pub fn report_labels(&self) -> ReportLabels {
+        match self {
+            ResponseLanguage::English => ReportLabels {
+                generated: "Generated", query: "Query", no_results: "No matching content found.",
+                sources: "Sources", section: "section", page: "p.", line: "line", lines: "lines",
+            },
+            ResponseLanguage::German => ReportLabels {
+                generated: "Erstellt", query: "Anfrage", no_results: "Keine passenden Inhalte gefunden.",
+                sources: "Quellen", section: "Abschnitt", page: "S.", line: "Zeile", lines: "Zeilen",
+            },
+            ResponseLanguage::French => ReportLabels {
+                generated: "Généré le", query: "Requête", no_results: "Aucun contenu correspondant trouvé.",
+                sources: "Sources", section: "section", page: "p.", line: "ligne", lines: "lignes",
+            },
+            ResponseLanguage::Spanish => ReportLabels {
+                generated: "Generado el", query: "Consulta", no_results: "No se encontró contenido coincidente.",
+                sources: "Fuentes", section: "sección", page: "pág.", line: "línea", lines: "líneas",
+            },
+            ResponseLanguage::Japanese => ReportLabels {
+                generated: "作成日時", query: "クエリ", no_results: "該当する内容は見つかりませんでした。",
+                sources: "出典", section: "セクション", page: "ページ", line: "行", lines: "行",
+            },
+            ResponseLanguage::Chinese => ReportLabels {
+                generated: "生成于", query: "查询", no_results: "未找到匹配的内容。",
+                sources: "来源", section: "章节", page: "页", line: "行", lines: "行",
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_codes_and_names() {
+        assert_eq!(ResponseLanguage::parse("de-CH").unwrap(), ResponseLanguage::German);
+        assert_eq!(ResponseLanguage::parse("Japanese").unwrap(), ResponseLanguage::Japanese);
+        assert_eq!(ResponseLanguage::parse(" FR ").unwrap().code(), "fr");
+        assert!(ResponseLanguage::parse("klingon").unwrap_err().to_string().contains("en, de, fr, es, ja, zh"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod template;
+pub mod render;
+pub mod language;
+
+pub use template::*;
+pub use render::*;
+pub use language::*;
\ No newline at end of file
@@ -0,0 +1,174 @@
+use super::{ReportLabels, ReportTemplate, ResponseLanguage};
+use crate::storage::SearchResult;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Render a template's sections, each with the results of its query, as markdown. Every
+/// excerpt cites a numbered source; sources are listed once at the end, with the lines,
+/// pages and section they come from. The report's own wording is in `language`; titles,
+/// headings, queries and excerpts are used as written.
+pub fn render_report(template: &ReportTemplate, results: &[Vec<SearchResult>], generated_at: DateTime<Utc>, snippet_chars: usize, language: ResponseLanguage) -> String {
+    let labels = language.report_labels();
+    let mut markdown = format!("# {}\n\n_{} {}_\n\n", template.title, labels.generated, generated_at.format("%Y-%m-%d %H:%M UTC"));
+    if let Some(description) = &template.description {
+        markdown.push_str(description.trim());
+        markdown.push_str("\n\n");
+    }
+
+    let mut sources: Vec<String> = Vec::new();
+    for (section, section_results) in template.sections.iter().zip(results) {
+        markdown.push_str(&format!("## {}\n\n_{}: `{}`_\n\n", section.heading, labels.query, section.query));
+        if section_results.is_empty() {
+            markdown.push_str(&format!("_{}_\n\n", labels.no_results));
+            continue;
+        }
+
+        for result in section_results {
+            let source = citation(result, &labels);
+            let number = match sources.iter().position(|s| *s == source) {
+                Some(index) => index + 1,
+                None => {
+                    sources.push(source);
+                    sources.len()
+                }
+            };
+            markdown.push_str(&format!("- {} [{}]\n", snippet(&result.content, snippet_chars), number));
+        }
+        markdown.push('\n');
+    }
+
+    if !sources.is_empty() {
+        markdown.push_str(&format!("## {}\n\n", labels.sources));
+        for (index, source) in sources.iter().enumerate() {
+            markdown.push_str(&format!("{}. {}\n", index + 1, source));
+        }
+    }
+
+    markdown.trim_end().to_string() + "\n"
+}
+
+/// `path` — section "X", pages 3-4 / lines 10-24
+fn citation(result: &SearchResult, labels: &ReportLabels) -> String {
+    let metadata = &result.metadata;
+    let mut parts = Vec::new();
+    if let Some(section) = metadata.get("section").or_else(|| metadata.get("chapter")) {
+        parts.push(format!("{} \"{}\"", labels.section, section));
+    }
+
+    let range = |start: &str, end: &str| {
+        let start = metadata.get(start).filter(|s| s.as_str() != "0")?;
+        let end = metadata.get(end).filter(|e| *e != start);
+        Some(match end {
+            Some(end) => format!("{}-{}", start, end),
+            None => start.clone(),
+        })
+    };
+    if let Some(pages) = range("page_start", "page_end") {
+        parts.push(format!("{} {}", labels.page, pages));
+    } else if let Some(lines) = range("line_start", "line_end") {
+        let label = if lines.contains('-') { labels.lines } else { labels.line };
+        parts.push(format!("{} {}", label, lines));
+    }
+
+    let source = metadata.get("source_file").map(|s| s.as_str()).unwrap_or(&result.chunk_id);
+    if parts.is_empty() {
+        format!("`{}`", source)
+    } else {
+        format!("`{}` — {}", source, parts.join(", "))
+    }
+}
+
+/// Chunk text on one line, cut at a word boundary after `max_chars`
+fn snippet(content: &str, max_chars: usize) -> String {
+    let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end_matches([',', ';', ':']))
+}
+
+/// Write a rendered report to `requested`, a relative path under `output_dir`. Absolute
+/// paths and `..` are refused so callers cannot write elsewhere, and an existing file is
+/// only replaced with `overwrite`. Returns the path written.
+pub fn write_report(output_dir: &Path, requested: &str, markdown: &str, overwrite: bool) -> Result<PathBuf> {
+    let relative = Path::new(requested);
+    if relative.as_os_str().is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(anyhow!("output_path '{}' must be a relative path inside report.output_dir without '..'", requested));
+    }
+    let path = output_dir.join(relative);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = options.open(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => anyhow!("{} already exists; pass overwrite=true to replace it", path.display()),
+        _ => anyhow!("Failed to write report to {}: {}", path.display(), e),
+    })?;
+    file.write_all(markdown.as_bytes()).with_context(|| format!("Failed to write report to {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(source: &str, lines: (usize, usize), section: &str, content: &str) -> SearchResult {
+        let mut metadata = HashMap::new();
+        metadata.insert("source_file".to_string(), source.to_string());
+        metadata.insert("line_start".to_string(), lines.0.to_string());
+        metadata.insert("line_end".to_string(), lines.1.to_string());
+        metadata.insert("section".to_string(), section.to_string());
+        SearchResult { chunk_id: format!("{}:{}", source, lines.0), score: 0.9, content: content.to_string(), metadata }
+    }
+
+    #[test]
+    fn test_sections_share_numbered_sources() {
+        let template = ReportTemplate::from_yaml("title: DMA digest\nsections:\n  - heading: Reset\n    query: dma reset\n  - heading: Errors\n    query: dma errors\n  - heading: Power\n    query: dma power\n").unwrap();
+        let reset = result("docs/dma.md", (10, 24), "Reset", "Assert   rst_n for\n16 cycles before programming descriptors.");
+        let results = vec![vec![reset.clone()], vec![result("spec.md", (3, 3), "Errors", "Errors raise irq[2]."), reset], vec![]];
+
+        let generated_at = DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z").unwrap().with_timezone(&Utc);
+        let markdown = render_report(&template, &results, generated_at, 30, ResponseLanguage::English);
+
+        assert!(markdown.starts_with("# DMA digest\n\n_Generated 2024-03-01 09:30 UTC_"));
+        assert!(markdown.contains("## Reset\n\n_Query: `dma reset`_\n\n- Assert rst_n for 16 cycles… [1]\n"));
+        assert!(markdown.contains("- Errors raise irq[2]. [2]\n- Assert rst_n for 16 cycles… [1]\n"));
+        assert!(markdown.contains("## Power\n\n_Query: `dma power`_\n\n_No matching content found._"));
+        assert!(markdown.ends_with("## Sources\n\n1. `docs/dma.md` — section \"Reset\", lines 10-24\n2. `spec.md` — section \"Errors\", line 3\n"));
+
+        let markdown = render_report(&template, &results, generated_at, 30, ResponseLanguage::German);
+        assert!(markdown.contains("_Erstellt 2024-03-01 09:30 UTC_"));
+        assert!(markdown.contains("## Power\n\n_Anfrage: `dma power`_\n\n_Keine passenden Inhalte gefunden._"));
+        assert!(markdown.ends_with("## Quellen\n\n1. `docs/dma.md` — Abschnitt \"Reset\", Zeilen 10-24\n2. `spec.md` — Abschnitt \"Errors\", Zeile 3\n"));
+    }
+
+    #[test]
+    fn test_reports_stay_inside_the_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_report(dir.path(), "weekly/dma.md", "# DMA", false).unwrap();
+        assert_eq!(written, dir.path().join("weekly/dma.md"));
+        assert_eq!(std::fs::read_to_string(&written).unwrap(), "# DMA");
+
+        // Existing reports are only replaced on request
+        assert!(write_report(dir.path(), "weekly/dma.md", "# DMA v2", false).is_err());
+        write_report(dir.path(), "weekly/dma.md", "# DMA v2", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&written).unwrap(), "# DMA v2");
+
+        for escape in ["../dma.md", "weekly/../../dma.md", "/tmp/dma.md", ""] {
+            assert!(write_report(dir.path(), escape, "# DMA", true).is_err(), "{}", escape);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A report definition: a title and one section per query
+///
+/// ```yaml
+/// title: What our docs say about DMA
+/// top_k: 3
+/// response_language: de
+/// sections:
+///   - heading: Reset sequence
+///     query: DMA reset sequence
+///   - heading: Error handling
+///     query: DMA error interrupt -deprecated
+///     top_k: 5
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,   // Results per section unless the section overrides it
+    #[serde(default)]
+    pub response_language: Option<String>,  // Overrides report.response_language
+    pub sections: Vec<ReportSection>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportSection {
+    pub heading: String,
+    pub query: String,
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    #[serde(default)]
+    pub exclude_terms: Vec<String>,
+}
+
+fn default_top_k() -> usize {
+    3
+}
+
+impl ReportTemplate {
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let template: Self = serde_yaml::from_str(yaml).context("Invalid report template")?;
+        if template.sections.is_empty() {
+            return Err(anyhow::anyhow!("Report template '{}' has no sections", template.title));
+        }
+        if let Some(section) = template.sections.iter().find(|s| s.query.trim().is_empty()) {
+            return Err(anyhow::anyhow!("Report section '{}' has an empty query", section.heading));
+        }
+        Ok(template)
+    }
+
+    /// Load a template by path, or by name from `template_dir` (`dma_digest` ->
+    /// `<template_dir>/dma_digest.yaml`)
+    pub fn load(name_or_path: &str, template_dir: &Path) -> Result<Self> {
+        let path = Self::resolve(name_or_path, template_dir)
+            .ok_or_else(|| anyhow::anyhow!("Report template '{}' not found (looked in {})", name_or_path, template_dir.display()))?;
+        let yaml = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read report template {}", path.display()))?;
+        Self::from_yaml(&yaml)
+    }
+
+    fn resolve(name_or_path: &str, template_dir: &Path) -> Option<PathBuf> {
+        let direct = PathBuf::from(name_or_path);
+        let candidates = [
+            direct.clone(),
+            template_dir.join(name_or_path),
+            template_dir.join(format!("{}.yaml", name_or_path)),
+            template_dir.join(format!("{}.yml", name_or_path)),
+        ];
+        candidates.into_iter().find(|path| path.is_file())
+    }
+
+    pub fn section_top_k(&self, section: &ReportSection) -> usize {
+        section.top_k.unwrap_or(self.top_k).max(1)
+    }
+}
\ No newline at end of file
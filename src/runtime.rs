@@ -0,0 +1,42 @@
+use crate::config::RuntimeConfig;
+use anyhow::Result;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Dedicated CPU pools so bulk ingestion cannot starve interactive search
+pub struct WorkerPools {
+    pub ingest: ThreadPool,
+    pub search: ThreadPool,
+}
+
+impl WorkerPools {
+    pub fn from_config(config: &RuntimeConfig) -> Result<Self> {
+        let nice = config.ingest_nice;
+
+        let ingest = ThreadPoolBuilder::new()
+            .num_threads(config.ingest_threads.max(1))
+            .thread_name(|i| format!("rag-ingest-{}", i))
+            .start_handler(move |_| lower_thread_priority(nice))
+            .build()?;
+
+        let search = ThreadPoolBuilder::new()
+            .num_threads(config.search_threads.max(1))
+            .thread_name(|i| format!("rag-search-{}", i))
+            .build()?;
+
+        Ok(Self { ingest, search })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn lower_thread_priority(nice: i32) {
+    if nice > 0 {
+        // On Linux the nice value is per-thread, so this only affects the calling worker
+        unsafe {
+            libc::nice(nice);
+        }
+    }
+}
+
+// Elsewhere nice() lowers the whole process, search pool included, so workers keep their priority
+#[cfg(not(target_os = "linux"))]
+fn lower_thread_priority(_nice: i32) {}
\ No newline at end of file
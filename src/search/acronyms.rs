@@ -0,0 +1,142 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Words an expansion may contain that do not contribute a letter ("System on Chip" is SoC,
+/// but "Bus Functional Model" is BFM either way)
+const MINOR_WORDS: &[&str] = &["of", "and", "the", "for", "to", "in", "on", "a", "an", "by", "with", "&"];
+
+/// Acronyms spelled out in the corpus, e.g. "Transaction Level Modeling (TLM)" or
+/// "TLM (Transaction Level Modeling)". Keys and expansions are lowercase; each acronym keeps
+/// every expansion seen with how often it was seen.
+#[derive(Debug, Default)]
+pub struct AcronymTable {
+    expansions: HashMap<String, HashMap<String, usize>>,
+}
+
+impl AcronymTable {
+    pub fn learn(&mut self, text: &str) {
+        for (acronym, expansion) in find_acronyms(text) {
+            *self.expansions.entry(acronym).or_default().entry(expansion).or_default() += 1;
+        }
+    }
+
+    /// The most frequent expansion of `acronym`
+    pub fn expansion(&self, acronym: &str) -> Option<&str> {
+        let expansions = self.expansions.get(&acronym.to_lowercase())?;
+        expansions.iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(expansion, _)| expansion.as_str())
+    }
+
+    pub fn contains(&self, term: &str) -> bool {
+        self.expansions.contains_key(term) || self.expansions.values().any(|e| e.contains_key(term))
+    }
+
+    pub fn len(&self) -> usize {
+        self.expansions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+}
+
+/// (acronym, expansion) pairs written as "Long Form (LF)" or "LF (Long Form)", kept only when
+/// the acronym's letters are the initials of the long form
+pub fn find_acronyms(text: &str) -> Vec<(String, String)> {
+    static PARENTHESIZED: OnceLock<Regex> = OnceLock::new();
+    let parenthesized = PARENTHESIZED.get_or_init(|| Regex::new(r"\(([^()\n]{1,80})\)").unwrap());
+
+    let mut found = Vec::new();
+    for captures in parenthesized.captures_iter(text) {
+        let whole = captures.get(0).unwrap();
+        let inside = captures[1].trim();
+        let before: Vec<&str> = text[..whole.start()].split_whitespace().rev().take(12).collect();
+
+        if let Some(letters) = acronym_letters(inside) {
+            // Long Form (LF): the long form is the shortest run of preceding words that fits
+            let words: Vec<&str> = before.iter()
+                .take_while(|word| !word.ends_with(['.', ',', ';', ':', ')']))
+                .copied()
+                .collect();
+            let expansion = (1..=words.len())
+                .map(|n| words[..n].iter().rev().copied().collect::<Vec<_>>())
+                .find(|candidate| spells(&letters, candidate));
+            if let Some(expansion) = expansion {
+                found.push((letters_key(inside), expansion.join(" ").to_lowercase()));
+            }
+        } else if let Some(acronym) = before.first().map(|word| word.trim_matches(|c: char| !c.is_alphanumeric())) {
+            // LF (Long Form)
+            let Some(letters) = acronym_letters(acronym) else { continue };
+            let words: Vec<&str> = inside.split_whitespace().collect();
+            if spells(&letters, &words) {
+                found.push((letters_key(acronym), words.join(" ").to_lowercase()));
+            }
+        }
+    }
+    found
+}
+
+/// Lowercase letters of a word that looks like an acronym: 2-10 characters, at least two
+/// capitals, alphanumeric. A trailing plural "s" ("DMAs") is dropped.
+fn acronym_letters(word: &str) -> Option<Vec<char>> {
+    let word = word.strip_suffix('s').filter(|w| w.chars().last().is_some_and(|c| c.is_uppercase())).unwrap_or(word);
+    let valid = (2..=10).contains(&word.chars().count())
+        && word.chars().next().is_some_and(|c| c.is_uppercase())
+        && word.chars().filter(|c| c.is_uppercase()).count() >= 2
+        && word.chars().all(|c| c.is_alphanumeric());
+    valid.then(|| word.chars().map(|c| c.to_ascii_lowercase()).collect())
+}
+
+fn letters_key(acronym: &str) -> String {
+    acronym_letters(acronym).map(|letters| letters.into_iter().collect()).unwrap_or_default()
+}
+
+/// Whether the initials of `words` (hyphenated parts counted separately) spell `letters`,
+/// with or without the minor words
+fn spells(letters: &[char], words: &[&str]) -> bool {
+    let parts: Vec<String> = words.iter()
+        .flat_map(|word| word.split('-'))
+        .map(|part| part.trim_matches(|c: char| !c.is_alphanumeric() && c != '&').to_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts.first().is_none_or(|part| MINOR_WORDS.contains(&part.as_str())) {
+        return false;
+    }
+
+    let initials = |skip_minor: bool| -> Vec<char> {
+        parts.iter()
+            .filter(|part| !skip_minor || !MINOR_WORDS.contains(&part.as_str()))
+            .filter_map(|part| part.chars().next())
+            .collect()
+    };
+    initials(false) == letters || initials(true) == letters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acronyms_are_found_in_both_forms() {
+        let text = "The testbench talks to the DUT over Transaction Level Modeling (TLM) ports. \
+            Each SoC (System on Chip) has DMA (direct memory access) engines. \
+            Register the driver (see BFM) and set the timeout (in ms). \
+            Bus Functional Models (BFMs) are reused, and TLM (transaction-level modeling) again.";
+        assert_eq!(find_acronyms(text), vec![
+            ("tlm".to_string(), "transaction level modeling".to_string()),
+            ("soc".to_string(), "system on chip".to_string()),
+            ("dma".to_string(), "direct memory access".to_string()),
+            ("bfm".to_string(), "bus functional models".to_string()),
+            ("tlm".to_string(), "transaction-level modeling".to_string()),
+        ]);
+
+        let mut table = AcronymTable::default();
+        table.learn(text);
+        table.learn("Use Transaction Level Modeling (TLM) everywhere.");
+        assert_eq!(table.expansion("TLM"), Some("transaction level modeling"));
+        assert_eq!(table.expansion("ms"), None);
+        assert!(table.contains("system on chip"));
+    }
+}
\ No newline at end of file
@@ -1,8 +1,77 @@
+use crate::chunker::natural_language;
 use crate::storage::SearchResult;
-use anyhow::Result;
+use super::retrieval::{merge_overlapping_results, DEFAULT_OVERLAP_MERGE_RATIO};
+use super::exclusion::{ExclusionFilter, QueryTerms};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+/// How many distinct query terms a document needs to match the keyword leg: a count (`2`),
+/// a percentage (`"75%"`), or a negative value for how many terms may be missing (`-1`, `"-25%"`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MinimumShouldMatchSpec", into = "MinimumShouldMatchSpec")]
+pub enum MinimumShouldMatch {
+    Count(i64),
+    Percent(f32),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MinimumShouldMatchSpec {
+    Count(i64),
+    Text(String),
+}
+
+impl TryFrom<MinimumShouldMatchSpec> for MinimumShouldMatch {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: MinimumShouldMatchSpec) -> Result<Self> {
+        match spec {
+            MinimumShouldMatchSpec::Count(count) => Ok(Self::Count(count)),
+            MinimumShouldMatchSpec::Text(text) => Self::parse(&text),
+        }
+    }
+}
+
+impl From<MinimumShouldMatch> for MinimumShouldMatchSpec {
+    fn from(minimum: MinimumShouldMatch) -> Self {
+        match minimum {
+            MinimumShouldMatch::Count(count) => Self::Count(count),
+            MinimumShouldMatch::Percent(percent) => Self::Text(format!("{}%", percent)),
+        }
+    }
+}
+
+impl MinimumShouldMatch {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let invalid = || anyhow!("Invalid minimum_should_match '{}': expected a count like 2 or a percentage like 75%", spec);
+
+        match spec.strip_suffix('%') {
+            Some(percent) => {
+                let percent: f32 = percent.trim().parse().map_err(|_| invalid())?;
+                if !(-100.0..=100.0).contains(&percent) {
+                    return Err(invalid());
+                }
+                Ok(Self::Percent(percent))
+            }
+            None => spec.parse().map(Self::Count).map_err(|_| invalid()),
+        }
+    }
+
+    /// Number of the `total` query terms that must match; always at least one
+    pub fn required(&self, total: usize) -> usize {
+        let required = match *self {
+            Self::Count(count) if count >= 0 => count as usize,
+            Self::Count(count) => total.saturating_sub(count.unsigned_abs() as usize),
+            Self::Percent(percent) if percent >= 0.0 => (total as f32 * percent / 100.0).floor() as usize,
+            Self::Percent(percent) => total - (total as f32 * -percent / 100.0).floor() as usize,
+        };
+        required.clamp(1, total.max(1))
+    }
+}
+
 /// BM25 keyword search implementation for exact term matching
 pub struct BM25Search {
     // Document frequency for each term
@@ -14,6 +83,8 @@ pub struct BM25Search {
     // BM25 parameters
     k1: f32,
     b: f32,
+    minimum_should_match: Option<MinimumShouldMatch>,
+    language: Option<String>,  // Human language whose stopwords and word splitting apply
 }
 
 impl BM25Search {
@@ -24,9 +95,35 @@ impl BM25Search {
             avg_doc_length: 0.0,
             k1: 1.2,  // Controls term frequency normalization
             b: 0.75,  // Controls document length normalization
+            minimum_should_match: None,
+            language: None,
         }
     }
 
+    pub fn with_minimum_should_match(mut self, minimum: Option<MinimumShouldMatch>) -> Self {
+        self.minimum_should_match = minimum;
+        self
+    }
+
+    /// Tokenize for a human language (ISO 639-1) as `natural_language::tokenize` does; other
+    /// values, such as programming languages, keep the default tokenization
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language.filter(|language| natural_language::is_natural(language));
+        self
+    }
+
+    /// Whether a document contains enough distinct query terms; always true without a minimum
+    pub fn meets_minimum_should_match(&self, query: &str, document: &str) -> bool {
+        let Some(minimum) = self.minimum_should_match else {
+            return true;
+        };
+
+        let query_terms: HashSet<String> = self.tokenize(query).into_iter().collect();
+        let doc_terms: HashSet<String> = self.tokenize(document).into_iter().collect();
+        let matched = query_terms.iter().filter(|term| doc_terms.contains(*term)).count();
+        matched >= minimum.required(query_terms.len())
+    }
+
     /// Index a document for BM25 search
     pub fn index_document(&mut self, doc_id: &str, content: &str) {
         let terms = self.tokenize(content);
@@ -44,12 +141,18 @@ impl BM25Search {
         self.avg_doc_length = (self.avg_doc_length * (self.total_docs - 1) as f32 + doc_length) / self.total_docs as f32;
     }
 
-    /// Search documents using BM25 scoring
+    /// Search documents using BM25 scoring. Documents containing a `-term` from the query are skipped.
     pub fn search(&self, query: &str, documents: &[(String, String)], top_k: usize) -> Vec<SearchResult> {
-        let query_terms = self.tokenize(query);
+        let terms = QueryTerms::parse(query, &[]);
+        let exclusions = ExclusionFilter::new(&terms.excluded, 0.0);
+        let query_terms = self.tokenize(&terms.positive);
         let mut scored_docs = Vec::new();
 
         for (doc_id, content) in documents {
+            if exclusions.matches_text(content) || !self.meets_minimum_should_match(&terms.positive, content) {
+                continue;
+            }
+
             let score = self.calculate_bm25_score(&query_terms, content);
             if score > 0.0 {
                 scored_docs.push(SearchResult {
@@ -98,6 +201,9 @@ impl BM25Search {
     }
 
     fn tokenize(&self, text: &str) -> Vec<String> {
+        if let Some(language) = &self.language {
+            return natural_language::tokenize(text, language).into_iter().filter(|word| word.len() > 2).collect();
+        }
         text.to_lowercase()
             .split_whitespace()
             .map(|word| {
@@ -230,7 +336,12 @@ impl HybridSearch {
             .collect();
 
         final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        final_results.into_iter().take(top_k).collect()
+
+        // Overlapping chunks of the same passage should not count as independent evidence
+        merge_overlapping_results(final_results, DEFAULT_OVERLAP_MERGE_RATIO)
+            .into_iter()
+            .take(top_k)
+            .collect()
     }
 }
 
@@ -261,6 +372,21 @@ mod tests {
         assert!(results[0].score > 0.0);
     }
 
+    #[test]
+    fn test_minimum_should_match() {
+        assert_eq!(MinimumShouldMatch::parse("75%").unwrap().required(4), 3);
+        assert_eq!(MinimumShouldMatch::parse("-1").unwrap().required(4), 3);
+        assert_eq!(MinimumShouldMatch::parse("-50%").unwrap().required(3), 2);
+        assert_eq!(MinimumShouldMatch::Count(5).required(2), 2);
+        assert!(MinimumShouldMatch::parse("most").is_err());
+        let parsed: MinimumShouldMatch = serde_json::from_value(serde_json::json!("60%")).unwrap();
+        assert_eq!(parsed, MinimumShouldMatch::Percent(60.0));
+
+        let bm25 = BM25Search::new().with_minimum_should_match(Some(MinimumShouldMatch::Count(2)));
+        assert!(bm25.meets_minimum_should_match("axi burst length", "The AXI burst length is 16"));
+        assert!(!bm25.meets_minimum_should_match("axi burst length", "AXI lite has no bursts"));
+    }
+
     #[test]
     fn test_tokenization() {
         let bm25 = BM25Search::new();
@@ -0,0 +1,98 @@
+/// Where a quoted passage was found in a piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteMatch {
+    pub exact: bool,
+    pub score: f32,          // 1.0 for exact matches, otherwise the share of words in common
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+/// A word and its byte span in the original text
+struct Word {
+    normalized: String,
+    span: (usize, usize),
+}
+
+/// Find `quote` in `text`. Matching is on words, ignoring case, punctuation and line
+/// breaks, so a quote copied from rendered output still lines up with the source. When the
+/// words do not appear verbatim, the best window of the same length is scored by the
+/// share of words the two have in common (in order).
+pub fn locate_quote(quote: &str, text: &str) -> Option<QuoteMatch> {
+    let quote: Vec<String> = words(quote).into_iter().map(|w| w.normalized).collect();
+    let text = words(text);
+    if quote.is_empty() || text.is_empty() {
+        return None;
+    }
+
+    let span = |start: usize, len: usize| (text[start].span.0, text[(start + len).min(text.len()) - 1].span.1);
+
+    if let Some(start) = text.windows(quote.len()).position(|window| window.iter().zip(&quote).all(|(w, q)| &w.normalized == q)) {
+        let (byte_start, byte_end) = span(start, quote.len());
+        return Some(QuoteMatch { exact: true, score: 1.0, byte_start, byte_end });
+    }
+
+    let window = quote.len().min(text.len());
+    let mut best: Option<(f32, usize)> = None;
+    for start in 0..=text.len() - window {
+        let candidate: Vec<&str> = text[start..start + window].iter().map(|w| w.normalized.as_str()).collect();
+        let common = common_subsequence(&quote, &candidate);
+        let score = 2.0 * common as f32 / (quote.len() + window) as f32;
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, start));
+        }
+    }
+
+    best.map(|(score, start)| {
+        let (byte_start, byte_end) = span(start, window);
+        QuoteMatch { exact: false, score, byte_start, byte_end }
+    })
+}
+
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_alphanumeric() || c == '_', start) {
+            (true, None) => start = Some(index),
+            (false, Some(begin)) => {
+                words.push(Word { normalized: text[begin..index].to_lowercase(), span: (begin, index) });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn common_subsequence(a: &[String], b: &[&str]) -> usize {
+    let mut previous = vec![0usize; b.len() + 1];
+    for word in a {
+        let mut current = vec![0usize; b.len() + 1];
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if word == other { previous[j] + 1 } else { previous[j + 1].max(current[j]) };
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_and_fuzzy_quotes() {
+        let text = "The sequencer arbitrates between sequences.\nEach driver pulls items\nwith get_next_item() and calls item_done().";
+
+        let exact = locate_quote("each driver pulls items with GET_NEXT_ITEM()", text).unwrap();
+        assert!(exact.exact);
+        assert_eq!(&text[exact.byte_start..exact.byte_end], "Each driver pulls items\nwith get_next_item");
+
+        let fuzzy = locate_quote("every driver pulls items with get_next_item", text).unwrap();
+        assert!(!fuzzy.exact);
+        assert!((fuzzy.score - 5.0 / 6.0).abs() < 1e-6);
+        assert_eq!(&text[fuzzy.byte_start..fuzzy.byte_end], "Each driver pulls items\nwith get_next_item");
+
+        assert!(locate_quote("   ", text).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+use crate::storage::SearchResult;
+use serde::Serialize;
+
+/// One reason an expected chunk ranked where it did, for `diagnose_query`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub reason: &'static str,
+    pub detail: String,
+}
+
+impl Finding {
+    pub fn new(reason: &'static str, detail: impl Into<String>) -> Self {
+        Self { reason, detail: detail.into() }
+    }
+}
+
+/// Which query words a text contains, split the way keyword search splits them: lowercased,
+/// on whitespace, with punctuation left attached
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KeywordCoverage {
+    pub matched: Vec<String>,
+    pub missing: Vec<String>,
+    pub attached_to_punctuation: Vec<String>,  // Missing words the text has only as e.g. "reset," or "(reset)"
+}
+
+pub fn keyword_coverage(query: &str, text: &str) -> KeywordCoverage {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut coverage = KeywordCoverage::default();
+    for term in query.to_lowercase().split_whitespace() {
+        if coverage.matched.iter().chain(&coverage.missing).any(|seen| seen == term) {
+            continue;
+        }
+        if words.contains(&term) {
+            coverage.matched.push(term.to_string());
+            continue;
+        }
+        if words.iter().any(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_') == term) {
+            coverage.attached_to_punctuation.push(term.to_string());
+        }
+        coverage.missing.push(term.to_string());
+    }
+    coverage
+}
+
+/// Position of a chunk in a ranked list, and the result it was merged into when overlap
+/// merging folded it into another chunk covering the same passage
+pub fn rank_of<'a>(results: &'a [SearchResult], chunk_id: &str) -> Option<(usize, Option<&'a SearchResult>)> {
+    results.iter().enumerate().find_map(|(rank, result)| {
+        if result.chunk_id == chunk_id {
+            return Some((rank, None));
+        }
+        let merged = result.metadata.get("merged_chunk_ids").is_some_and(|ids| ids.split(',').any(|id| id == chunk_id));
+        merged.then_some((rank, Some(result)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_keyword_coverage_and_merged_rank() {
+        let coverage = keyword_coverage("DMA reset sequence reset", "Before a reset, the DMA engine drains its queue.");
+        assert_eq!(coverage.matched, vec!["dma"]);
+        assert_eq!(coverage.missing, vec!["reset", "sequence"]);
+        assert_eq!(coverage.attached_to_punctuation, vec!["reset"]);
+
+        let merged: HashMap<String, String> = [("merged_chunk_ids".to_string(), "b,c".to_string())].into_iter().collect();
+        let results = vec![
+            SearchResult { chunk_id: "a".to_string(), score: 0.9, content: String::new(), metadata: HashMap::new() },
+            SearchResult { chunk_id: "d".to_string(), score: 0.8, content: String::new(), metadata: merged },
+        ];
+        assert_eq!(rank_of(&results, "a").map(|(rank, into)| (rank, into.is_some())), Some((0, false)));
+        assert_eq!(rank_of(&results, "c").map(|(rank, into)| (rank, into.map(|r| r.chunk_id.as_str()))), Some((1, Some("d"))));
+        assert!(rank_of(&results, "e").is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+/// Line-level comparison of two texts, e.g. two chunks describing the same procedure
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextDiff {
+    pub unified: String,
+    pub similarity: f32,   // Share of lines the texts have in common, 0.0 - 1.0
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Unified diff of `old` against `new` with `context` unchanged lines around each change.
+/// Similarity is 2 * common lines / total lines. Trailing whitespace is ignored when
+/// matching lines.
+pub fn diff_texts(old_label: &str, old: &str, new_label: &str, new: &str, context: usize) -> TextDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = line_ops(&old_lines, &new_lines);
+
+    let common = ops.iter().filter(|op| matches!(op, Op::Equal(..))).count();
+    let total = old_lines.len() + new_lines.len();
+    let similarity = if total == 0 { 1.0 } else { 2.0 * common as f32 / total as f32 };
+
+    // Zero-based old/new line positions at which each op starts
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for op in &ops {
+        positions.push((old_pos, new_pos));
+        match op {
+            Op::Equal(..) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Op::Delete(_) => old_pos += 1,
+            Op::Insert(_) => new_pos += 1,
+        }
+    }
+
+    let mut unified = String::new();
+    let hunks = hunks(&ops, context);
+    if !hunks.is_empty() {
+        unified.push_str(&format!("--- {}\n+++ {}\n", old_label, new_label));
+    }
+    for hunk in hunks {
+        let (old_start, new_start) = positions[hunk.start];
+        let ops = &ops[hunk];
+        let old_count = ops.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+        let new_count = ops.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+        unified.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+
+        for op in ops {
+            match *op {
+                Op::Equal(i, _) => unified.push_str(&format!(" {}\n", old_lines[i])),
+                Op::Delete(i) => unified.push_str(&format!("-{}\n", old_lines[i])),
+                Op::Insert(j) => unified.push_str(&format!("+{}\n", new_lines[j])),
+            }
+        }
+    }
+
+    TextDiff {
+        unified,
+        similarity,
+        added: ops.iter().filter(|op| matches!(op, Op::Insert(_))).count(),
+        removed: ops.iter().filter(|op| matches!(op, Op::Delete(_))).count(),
+    }
+}
+
+/// Edit script from the longest common subsequence of lines. Chunks are at most a few
+/// hundred lines, so the quadratic table is fine.
+fn line_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i].trim_end() == new[j].trim_end() {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i].trim_end() == new[j].trim_end() {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete(i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(j));
+            j += 1;
+        }
+    }
+    ops.extend((i..n).map(Op::Delete));
+    ops.extend((j..m).map(Op::Insert));
+    ops
+}
+
+/// Ranges of `ops` to print: each change plus `context` lines either side, with hunks whose
+/// context would overlap merged
+fn hunks(ops: &[Op], context: usize) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Equal(..)) {
+            continue;
+        }
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+/// Cosine similarity of two embeddings, 0.0 when their dimensions differ
+pub fn embedding_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+/// `start,count` in unified diff notation (1-based; an empty range names the line before it)
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_and_similarity() {
+        let old = "Reset the DUT\nProgram the DMA descriptors\nStart the transfer\nWait for done\nCheck the scoreboard";
+        let new = "Reset the DUT\nProgram the DMA descriptors\nEnable interrupts\nStart the transfer\nWait for done\nCheck the scoreboard";
+
+        let diff = diff_texts("a", old, "b", new, 1);
+        assert_eq!(diff.unified, "--- a\n+++ b\n@@ -2,2 +2,3 @@\n Program the DMA descriptors\n+Enable interrupts\n Start the transfer\n");
+        assert_eq!((diff.added, diff.removed), (1, 0));
+        assert!((diff.similarity - 10.0 / 11.0).abs() < 1e-6);
+
+        let same = diff_texts("a", old, "b", old, 3);
+        assert!(same.unified.is_empty());
+        assert_eq!(same.similarity, 1.0);
+    }
+}
\ No newline at end of file
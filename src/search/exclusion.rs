@@ -0,0 +1,149 @@
+use crate::storage::SearchResult;
+
+/// A query split into the text to search for and the terms results must not contain.
+/// `-term` and `-"quoted phrase"` in the query are exclusions; hyphens inside words
+/// (`read-only`) and negative numbers are left alone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryTerms {
+    pub positive: String,
+    pub excluded: Vec<String>,
+}
+
+impl QueryTerms {
+    pub fn parse(query: &str, extra_excluded: &[String]) -> Self {
+        let mut positive = Vec::new();
+        let mut excluded = Vec::new();
+        let mut rest = query.trim_start();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("-\"") {
+                let end = after.find('"').unwrap_or(after.len());
+                excluded.push(after[..end].to_string());
+                rest = after.get(end + 1..).unwrap_or("");
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let token = &rest[..end];
+                match token.strip_prefix('-') {
+                    Some(term) if term.starts_with(|c: char| c.is_alphabetic() || c == '_') => excluded.push(term.to_string()),
+                    _ => positive.push(token),
+                }
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start();
+        }
+
+        excluded.extend(extra_excluded.iter().cloned());
+        let mut terms = Self { positive: positive.join(" "), excluded: Vec::new() };
+        for term in excluded {
+            let normalized = normalize(&term).join(" ");
+            if !normalized.is_empty() && !terms.excluded.contains(&normalized) {
+                terms.excluded.push(normalized);
+            }
+        }
+        terms
+    }
+}
+
+/// Drops or down-weights results mentioning excluded terms in their content, file path or section
+#[derive(Debug, Clone)]
+pub struct ExclusionFilter {
+    phrases: Vec<Vec<String>>,
+    penalty: f32,  // 0 drops matching results; otherwise their score is multiplied by it
+}
+
+impl ExclusionFilter {
+    pub fn new(excluded: &[String], penalty: f32) -> Self {
+        Self {
+            phrases: excluded.iter().map(|term| normalize(term)).filter(|p| !p.is_empty()).collect(),
+            penalty: penalty.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+    }
+
+    /// Whether matching results are removed outright rather than penalized
+    pub fn drops(&self) -> bool {
+        self.penalty == 0.0
+    }
+
+    pub fn matches_text(&self, text: &str) -> bool {
+        if self.phrases.is_empty() {
+            return false;
+        }
+
+        let words = normalize(text);
+        self.phrases.iter().any(|phrase| words.windows(phrase.len()).any(|window| window == phrase.as_slice()))
+    }
+
+    pub fn matches(&self, result: &SearchResult) -> bool {
+        self.matches_text(&result.content)
+            || ["source_file", "section", "chapter"].iter()
+                .filter_map(|key| result.metadata.get(*key))
+                .any(|value| self.matches_text(value))
+    }
+
+    /// Remove or penalize excluded results, keeping the list sorted by score
+    pub fn apply(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        if self.is_empty() {
+            return results;
+        }
+
+        let mut kept: Vec<SearchResult> = results.into_iter()
+            .filter_map(|mut result| {
+                if !self.matches(&result) {
+                    Some(result)
+                } else if self.drops() {
+                    None
+                } else {
+                    result.score *= self.penalty;
+                    Some(result)
+                }
+            })
+            .collect();
+
+        kept.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        kept
+    }
+}
+
+/// Lowercased words, split on anything but alphanumerics and underscores
+fn normalize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(id: &str, content: &str, file: &str, score: f32) -> SearchResult {
+        let mut metadata = HashMap::new();
+        metadata.insert("source_file".to_string(), file.to_string());
+        SearchResult { chunk_id: id.to_string(), score, content: content.to_string(), metadata }
+    }
+
+    #[test]
+    fn test_parse_and_filter_exclusions() {
+        let terms = QueryTerms::parse("fifo read-only -testbench -\"Scoreboard Model\" depth -1", &["UVM".to_string()]);
+        assert_eq!(terms.positive, "fifo read-only depth -1");
+        assert_eq!(terms.excluded, vec!["testbench", "scoreboard model", "uvm"]);
+
+        let results = vec![
+            result("rtl", "FIFO depth is set by the DEPTH parameter", "rtl/fifo.sv", 0.9),
+            result("tb", "The FIFO driver pushes random depth values", "testbench/fifo_driver.sv", 0.8),
+            result("model", "The scoreboard model tracks fifo depth", "docs/fifo.md", 0.7),
+        ];
+
+        let filtered = ExclusionFilter::new(&terms.excluded, 0.0).apply(results.clone());
+        assert_eq!(filtered.iter().map(|r| r.chunk_id.as_str()).collect::<Vec<_>>(), vec!["rtl"]);
+
+        let penalized = ExclusionFilter::new(&terms.excluded, 0.5).apply(results);
+        assert_eq!(penalized.len(), 3);
+        assert!((penalized[1].score - 0.4).abs() < 1e-6);
+    }
+}
\ No newline at end of file
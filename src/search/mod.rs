@@ -2,8 +2,22 @@ pub mod semantic;
 pub mod retrieval;
 pub mod bm25;
 pub mod query_enhancer;
+pub mod exclusion;
+pub mod diff;
+pub mod citation;
+pub mod tuning;
+pub mod diagnose;
+pub mod acronyms;
+pub mod pipeline;
 
 pub use semantic::*;
 pub use retrieval::*;
 pub use bm25::*;
-pub use query_enhancer::*;
\ No newline at end of file
+pub use query_enhancer::*;
+pub use exclusion::*;
+pub use diff::*;
+pub use citation::*;
+pub use tuning::*;
+pub use diagnose::*;
+pub use acronyms::*;
+pub use pipeline::*;
\ No newline at end of file
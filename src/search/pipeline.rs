@@ -0,0 +1,181 @@
+use crate::config::RankingConfig;
+use crate::storage::SearchResult;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One step of the search path. List stages work on the separate vector, keyword and graph
+/// lists; the others rank the combined list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingStage {
+    Candidates,  // Vector and keyword retrieval, restricted to the metadata filter and collection
+    Filters,     // Exclusion terms and minimum_should_match
+    GraphBoost,  // Graph neighbours of the top results, fused as a third list
+    Fusion,      // Rank fusion under ranking.fusion; without it, vector results topped up with keyword results
+    Rerank,      // ranking.type_weights; re-sorts by score
+    Mmr,         // Maximal marginal relevance under ranking.mmr_lambda
+    Packing,     // Overlapping chunks of one passage merged into one result
+}
+
+impl RankingStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RankingStage::Candidates => "candidates",
+            RankingStage::Filters => "filters",
+            RankingStage::GraphBoost => "graph_boost",
+            RankingStage::Fusion => "fusion",
+            RankingStage::Rerank => "rerank",
+            RankingStage::Mmr => "mmr",
+            RankingStage::Packing => "packing",
+        }
+    }
+
+    /// Whether the stage works on the retrieval lists rather than the combined ranking
+    pub fn on_lists(&self) -> bool {
+        matches!(self, RankingStage::Candidates | RankingStage::Filters | RankingStage::GraphBoost)
+    }
+}
+
+/// The search path when `ranking.pipeline` is not configured
+pub const DEFAULT_PIPELINE: &[RankingStage] = &[
+    RankingStage::Candidates,
+    RankingStage::Filters,
+    RankingStage::GraphBoost,
+    RankingStage::Fusion,
+    RankingStage::Rerank,
+    RankingStage::Packing,
+];
+
+/// An ordered, validated list of search stages. Candidates come first, then the other list
+/// stages, then fusion, which combines the lists, then the remaining ranking stages in any
+/// order, except that packing merges overlapping chunks before mmr diversifies them.
+/// Without a fusion stage the lists are combined as if `ranking.fusion` were unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingPipeline {
+    stages: Vec<RankingStage>,
+}
+
+impl Default for RankingPipeline {
+    fn default() -> Self {
+        Self { stages: DEFAULT_PIPELINE.to_vec() }
+    }
+}
+
+impl RankingPipeline {
+    pub fn new(stages: &[RankingStage]) -> Result<Self> {
+        if stages.first() != Some(&RankingStage::Candidates) {
+            return Err(anyhow!("A ranking pipeline must start with candidates"));
+        }
+        for (i, stage) in stages.iter().enumerate() {
+            if stages[..i].contains(stage) {
+                return Err(anyhow!("Stage {} appears more than once", stage.name()));
+            }
+            if let Some(earlier) = stages[..i].iter().find(|earlier| !earlier.on_lists()) {
+                if stage.on_lists() {
+                    return Err(anyhow!("Stage {} works on the retrieval lists, so it must come before {}", stage.name(), earlier.name()));
+                }
+                if *stage == RankingStage::Fusion {
+                    return Err(anyhow!("Stage fusion combines the retrieval lists, so it must come before {}", earlier.name()));
+                }
+            }
+            if *stage == RankingStage::Packing && stages[..i].contains(&RankingStage::Mmr) {
+                return Err(anyhow!("Stage packing merges overlapping results, so it must come before mmr"));
+            }
+        }
+        Ok(Self { stages: stages.to_vec() })
+    }
+
+    pub fn contains(&self, stage: RankingStage) -> bool {
+        self.stages.contains(&stage)
+    }
+
+    pub fn stages(&self) -> &[RankingStage] {
+        &self.stages
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+}
+
+/// The configured pipeline and the per-collection ones that replace it for searches scoped
+/// to a collection
+#[derive(Debug, Clone, Default)]
+pub struct RankingPipelines {
+    default: RankingPipeline,
+    collections: HashMap<String, RankingPipeline>,
+}
+
+impl RankingPipelines {
+    pub fn from_config(config: &RankingConfig) -> Result<Self> {
+        let default = RankingPipeline::new(&config.pipeline)
+            .map_err(|e| anyhow!("Invalid ranking.pipeline: {}", e))?;
+        let collections = config.collection_pipelines.iter()
+            .map(|(collection, stages)| {
+                RankingPipeline::new(stages)
+                    .map(|pipeline| (collection.clone(), pipeline))
+                    .map_err(|e| anyhow!("Invalid ranking.collection_pipelines.{}: {}", collection, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { default, collections })
+    }
+
+    pub fn for_collection(&self, collection: Option<&str>) -> &RankingPipeline {
+        collection.and_then(|c| self.collections.get(c)).unwrap_or(&self.default)
+    }
+}
+
+/// Reorder `results` by maximal marginal relevance: each pick maximizes
+/// `lambda * score - (1 - lambda) * (highest similarity to an earlier pick)`, so
+/// near-duplicates sink below different passages. Results without an embedding are never
+/// redundant. Scores are left unchanged.
+pub fn maximal_marginal_relevance(results: Vec<SearchResult>, embeddings: &HashMap<String, Vec<f32>>, lambda: f32) -> Vec<SearchResult> {
+    let lambda = lambda.clamp(0.0, 1.0);
+    let mut remaining = results;
+    let mut redundancy = vec![0.0f32; remaining.len()];
+    let mut picked = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let value = |i: usize| lambda * remaining[i].score - (1.0 - lambda) * redundancy[i];
+        let best = (1..remaining.len()).fold(0, |best, i| if value(i) > value(best) { i } else { best });
+        let chosen = remaining.remove(best);
+        redundancy.remove(best);
+
+        if let Some(chosen_embedding) = embeddings.get(&chosen.chunk_id) {
+            for (result, redundancy) in remaining.iter().zip(redundancy.iter_mut()) {
+                if let Some(embedding) = embeddings.get(&result.chunk_id) {
+                    *redundancy = redundancy.max(super::embedding_similarity(chosen_embedding, embedding));
+                }
+            }
+        }
+        picked.push(chosen);
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RankingStage::*;
+
+    #[test]
+    fn test_pipeline_order_and_mmr() {
+        assert!(RankingPipeline::new(&[Candidates, GraphBoost, Filters, Mmr, Rerank]).is_ok());
+        assert!(RankingPipeline::new(&[Filters, Candidates]).is_err());
+        assert!(RankingPipeline::new(&[Candidates, Fusion, Fusion]).is_err());
+        assert!(RankingPipeline::new(&[Candidates, Rerank, Filters]).unwrap_err().to_string().contains("before rerank"));
+        assert!(RankingPipeline::new(&[Candidates, Packing, Fusion]).is_err());
+        assert!(RankingPipeline::new(&[Candidates, Fusion, Packing, Mmr]).is_ok());
+        assert!(RankingPipeline::new(&[Candidates, Fusion, Mmr, Packing]).unwrap_err().to_string().contains("before mmr"));
+
+        let result = |id: &str, score: f32| SearchResult { chunk_id: id.to_string(), score, content: String::new(), metadata: HashMap::new() };
+        let embeddings: HashMap<String, Vec<f32>> = [("a", vec![1.0, 0.0]), ("a2", vec![1.0, 0.01]), ("b", vec![0.0, 1.0])]
+            .into_iter().map(|(id, e)| (id.to_string(), e)).collect();
+        let ranked = vec![result("a", 0.9), result("a2", 0.85), result("b", 0.6), result("c", 0.1)];
+
+        let ids = |results: Vec<SearchResult>| results.into_iter().map(|r| r.chunk_id).collect::<Vec<_>>();
+        assert_eq!(ids(maximal_marginal_relevance(ranked.clone(), &embeddings, 0.5)), vec!["a", "b", "c", "a2"]);
+        assert_eq!(ids(maximal_marginal_relevance(ranked, &embeddings, 1.0)), vec!["a", "a2", "b", "c"]);
+    }
+}
\ No newline at end of file
@@ -1,4 +1,9 @@
+use super::AcronymTable;
+use anyhow::Result;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
 
 #[derive(Debug, Clone)]
 pub enum QueryIntent {
@@ -8,6 +13,26 @@ pub enum QueryIntent {
     Mixed,          // Multiple intents
 }
 
+impl QueryIntent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryIntent::CodeExample => "code_example",
+            QueryIntent::Concept => "concept",
+            QueryIntent::Configuration => "configuration",
+            QueryIntent::Mixed => "mixed",
+        }
+    }
+}
+
+/// Additional domain vocabulary, in the YAML format produced by vocabulary suggestions
+#[derive(Debug, Default, Deserialize)]
+pub struct VocabularyPatch {
+    #[serde(default)]
+    pub abbreviations: HashMap<String, String>,
+    #[serde(default)]
+    pub synonyms: HashMap<String, Vec<String>>,  // Appended to an existing group or starts a new one
+}
+
 #[derive(Debug, Clone)]
 pub struct EnhancedQuery {
     pub original: String,
@@ -21,6 +46,7 @@ pub struct EnhancedQuery {
 pub struct QueryEnhancer {
     uvm_synonyms: HashMap<String, Vec<String>>,
     abbreviations: HashMap<String, String>,
+    corpus_acronyms: RwLock<AcronymTable>,  // Spelled out in ingested documents; the table above wins
     code_indicators: Vec<String>,
     concept_indicators: Vec<String>,
 }
@@ -139,11 +165,68 @@ impl QueryEnhancer {
         Self {
             uvm_synonyms,
             abbreviations,
+            corpus_acronyms: RwLock::new(AcronymTable::default()),
             code_indicators,
             concept_indicators,
         }
     }
 
+    /// Merge a vocabulary YAML file into the built-in tables
+    pub fn load_vocabulary_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        let patch: VocabularyPatch = serde_yaml::from_str(&content)?;
+        self.merge_vocabulary(patch);
+        Ok(())
+    }
+
+    /// Entries left blank for review (empty expansion or synonym list) are ignored
+    pub fn merge_vocabulary(&mut self, patch: VocabularyPatch) {
+        for (abbrev, expansion) in patch.abbreviations {
+            if !expansion.trim().is_empty() {
+                self.abbreviations.insert(abbrev.to_lowercase(), expansion);
+            }
+        }
+
+        for (term, synonyms) in patch.synonyms {
+            if synonyms.is_empty() {
+                continue;
+            }
+            let group = self.uvm_synonyms.entry(term.to_lowercase()).or_default();
+            for synonym in synonyms {
+                if !group.contains(&synonym) {
+                    group.push(synonym);
+                }
+            }
+        }
+    }
+
+    /// Record acronyms spelled out in ingested text, e.g. "Transaction Level Modeling (TLM)"
+    pub fn learn_acronyms(&self, text: &str) {
+        self.corpus_acronyms.write().unwrap().learn(text);
+    }
+
+    pub fn corpus_acronym_count(&self) -> usize {
+        self.corpus_acronyms.read().unwrap().len()
+    }
+
+    pub fn has_abbreviation(&self, term: &str) -> bool {
+        self.abbreviations.contains_key(term) || self.corpus_acronyms.read().unwrap().expansion(term).is_some()
+    }
+
+    /// Whether a term already appears anywhere in the vocabulary tables
+    pub fn is_known_term(&self, term: &str) -> bool {
+        self.abbreviations.contains_key(term)
+            || self.abbreviations.values().any(|expansion| expansion == term)
+            || self.uvm_synonyms.contains_key(term)
+            || self.uvm_synonyms.values().flatten().any(|synonym| synonym == term)
+            || self.corpus_acronyms.read().unwrap().contains(term)
+    }
+
+    /// Synonym group keys, for relating new terms to existing groups
+    pub fn synonym_groups(&self) -> impl Iterator<Item = &String> {
+        self.uvm_synonyms.keys()
+    }
+
     pub fn enhance(&self, query: &str) -> EnhancedQuery {
         let original = query.to_string();
         let normalized = query.to_lowercase();
@@ -186,6 +269,22 @@ impl QueryEnhancer {
             }
         }
 
+        // Acronyms the corpus spells out keep the acronym and gain its expansion
+        let acronyms = self.corpus_acronyms.read().unwrap();
+        let mut seen = Vec::new();
+        for word in query.split(|c: char| !c.is_alphanumeric()) {
+            if word.is_empty() || seen.contains(&word) || self.abbreviations.contains_key(word) {
+                continue;
+            }
+            seen.push(word);
+            if let Some(expansion) = acronyms.expansion(word) {
+                let pattern = format!(r"\b{}\b", regex::escape(word));
+                if let Ok(re) = regex::Regex::new(&pattern) {
+                    result = re.replace_all(&result, format!("{} {}", word, expansion).as_str()).to_string();
+                }
+            }
+        }
+
         result
     }
 
@@ -348,6 +447,12 @@ mod tests {
         let result = enhancer.enhance("cfg db setup");
         assert!(result.enhanced.contains("configuration"));
         assert!(result.enhanced.contains("database"));
+
+        // Acronyms spelled out in the corpus are expanded alongside the built-in table
+        enhancer.learn_acronyms("Each SoC (System on Chip) has a DMA (direct memory access) engine.");
+        let result = enhancer.enhance("soc dma cfg");
+        assert!(result.enhanced.contains("soc system on chip dma direct memory access configuration"));
+        assert!(enhancer.has_abbreviation("dma"));
     }
 
     #[test]
@@ -1,12 +1,156 @@
 use crate::storage::{Storage, SearchResult};
-use crate::graph::GraphBuilder;
+use crate::graph::{GraphBuilder, NodeType};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Default share of the shorter chunk's range that must overlap before two results are merged
+pub const DEFAULT_OVERLAP_MERGE_RATIO: f32 = 0.5;
+
+/// Top results of the vector and keyword lists whose graph neighbours make up the graph list
+const GRAPH_SEEDS: usize = 5;
+
+/// Ranked candidates from each retrieval method for one query, before they are combined
+#[derive(Debug, Clone, Default)]
+pub struct RetrievalLegs {
+    pub vector: Vec<SearchResult>,
+    pub text: Vec<SearchResult>,
+    pub graph: Vec<SearchResult>,  // Chunks linked to the top vector/keyword results
+    pub sparse: Vec<SearchResult>,  // Learned sparse term matches, when embedding.sparse is configured
+}
+
+impl RetrievalLegs {
+    /// Vector results topped up with keyword results when there are fewer than `top_k`: the
+    /// ranking used when `ranking.fusion` is not configured
+    pub fn fallback(&self, top_k: usize) -> Vec<SearchResult> {
+        let mut results = self.vector.clone();
+        if results.len() < top_k {
+            results.extend(self.text.iter().cloned());
+            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+            results.dedup_by(|a, b| a.chunk_id == b.chunk_id);
+        }
+        results
+    }
+
+    /// Entries across all lists
+    pub fn candidate_count(&self) -> usize {
+        self.vector.len() + self.text.len() + self.graph.len() + self.sparse.len()
+    }
+
+    /// Seeds for the graph list: the top vector and keyword results, interleaved
+    pub fn graph_seeds(&self) -> Vec<&str> {
+        let mut seeds: Vec<&str> = Vec::new();
+        for i in 0..GRAPH_SEEDS {
+            for leg in [&self.vector, &self.text] {
+                if let Some(result) = leg.get(i).filter(|r| !seeds.contains(&r.chunk_id.as_str())) {
+                    seeds.push(&result.chunk_id);
+                }
+            }
+        }
+        seeds
+    }
+}
+
+/// Chunks linked to `seeds` in the relationship graph, best first: each link scores its
+/// edge weight divided by the seed's position plus one
+pub fn graph_neighbours(graph: &GraphBuilder, seeds: &[&str], limit: usize) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    for (position, seed) in seeds.iter().enumerate() {
+        for (neighbour, edge) in graph.neighbors(seed) {
+            if graph.get_nodes().get(neighbour).is_some_and(|node| matches!(node.node_type, NodeType::Chunk)) {
+                *scores.entry(neighbour).or_default() += edge.weight / (position + 1) as f32;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(String, f32)> = scores.into_iter().map(|(id, score)| (id.to_string(), score)).collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+    ranked
+}
+
+/// Merge vector lists whose scores come from different embedding models, and so are not
+/// comparable, by rank: every list's first result comes before any list's second, ties going
+/// to the higher score. A chunk found by several lists keeps its best rank and score.
+pub fn interleave_by_rank(lists: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let mut best: HashMap<String, (usize, SearchResult)> = HashMap::new();
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            match best.get(&result.chunk_id) {
+                Some((kept, existing)) if (*kept, -existing.score) <= (rank, -result.score) => {}
+                _ => {
+                    best.insert(result.chunk_id.clone(), (rank, result));
+                }
+            }
+        }
+    }
+
+    let mut ranked: Vec<(usize, SearchResult)> = best.into_values().collect();
+    ranked.sort_by(|(rank_a, a), (rank_b, b)| {
+        rank_a.cmp(rank_b)
+            .then_with(|| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal))
+            .then_with(|| a.chunk_id.cmp(&b.chunk_id))
+    });
+    ranked.into_iter().take(limit).map(|(_, result)| result).collect()
+}
+
+/// Weighted reciprocal rank fusion of the vector, keyword, graph and sparse lists
+/// (`ranking.fusion`). A result earns `weight / (rrf_k + rank)` from each list it appears in;
+/// only the ratios between the weights matter, and a larger `rrf_k` flattens the advantage
+/// of the first ranks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FusionWeights {
+    pub vector: f32,
+    pub text: f32,
+    pub graph: f32,
+    pub sparse: f32,
+    pub rrf_k: f32,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self { vector: 1.0, text: 1.0, graph: 0.25, sparse: 1.0, rrf_k: 60.0 }
+    }
+}
+
+impl FusionWeights {
+    /// Fused results, best first. Scores are scaled so a result ranked first by every
+    /// weighted list scores 1.0; an empty sparse list counts as not retrieved (no sparse
+    /// model), so it does not lower every score.
+    pub fn fuse(&self, legs: &RetrievalLegs) -> Vec<SearchResult> {
+        let sparse = if legs.sparse.is_empty() { 0.0 } else { self.sparse };
+        let weighted = [(self.vector, &legs.vector), (self.text, &legs.text), (self.graph, &legs.graph), (sparse, &legs.sparse)];
+        let rrf_k = self.rrf_k.max(0.0);
+        let best: f32 = weighted.iter().map(|(weight, _)| weight.max(0.0) / (rrf_k + 1.0)).sum();
+
+        let mut fused: HashMap<&str, SearchResult> = HashMap::new();
+        for (weight, leg) in weighted.into_iter().filter(|(weight, _)| *weight > 0.0) {
+            for (rank, result) in leg.iter().enumerate() {
+                fused.entry(&result.chunk_id)
+                    .or_insert_with(|| SearchResult { score: 0.0, ..result.clone() })
+                    .score += weight / (rrf_k + rank as f32 + 1.0);
+            }
+        }
+
+        let mut results: Vec<SearchResult> = fused.into_values()
+            .map(|mut result| {
+                result.score /= best;
+                result
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.chunk_id.cmp(&b.chunk_id)));
+        results
+    }
+}
 
 pub struct HybridRetriever {
     vector_weight: f32,
     text_weight: f32,
     graph_weight: f32,
     min_similarity_threshold: f32,  // Minimum similarity score to include results
+    overlap_merge_ratio: f32,       // Overlap share at which two chunks count as the same passage
+    type_weights: TypeWeights,
 }
 
 impl HybridRetriever {
@@ -16,6 +160,8 @@ impl HybridRetriever {
             text_weight,
             graph_weight,
             min_similarity_threshold: 0.3,  // Default threshold
+            overlap_merge_ratio: DEFAULT_OVERLAP_MERGE_RATIO,
+            type_weights: TypeWeights::default(),
         }
     }
 
@@ -24,6 +170,16 @@ impl HybridRetriever {
         self
     }
 
+    pub fn with_overlap_merge_ratio(mut self, ratio: f32) -> Self {
+        self.overlap_merge_ratio = ratio;
+        self
+    }
+
+    pub fn with_type_weights(mut self, type_weights: TypeWeights) -> Self {
+        self.type_weights = type_weights;
+        self
+    }
+
     pub fn retrieve(
         &self,
         storage: &Storage,
@@ -100,7 +256,10 @@ impl HybridRetriever {
             })
             .collect();
 
-        final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        self.type_weights.apply(&mut final_results);
+
+        // Overlapping chunks carry the same evidence, so fold them before diversity reranking
+        let final_results = merge_overlapping_results(final_results, self.overlap_merge_ratio);
 
         // Log low-quality matches for debugging
         for result in &final_results {
@@ -114,4 +273,294 @@ impl HybridRetriever {
 
         final_results
     }
+}
+
+/// Score multipliers per chunk type (`ranking.type_weights`), applied when result lists are
+/// fused. Types are matched case-insensitively against the `chunk_type` metadata; unlisted
+/// types keep a weight of 1.0.
+#[derive(Debug, Clone, Default)]
+pub struct TypeWeights {
+    weights: HashMap<String, f32>,
+}
+
+impl TypeWeights {
+    pub fn new(weights: &HashMap<String, f32>) -> Self {
+        Self {
+            weights: weights.iter().map(|(chunk_type, weight)| (chunk_type.to_lowercase(), weight.max(0.0))).collect(),
+        }
+    }
+
+    pub fn weight(&self, result: &SearchResult) -> f32 {
+        result.metadata.get("chunk_type")
+            .and_then(|chunk_type| self.weights.get(&chunk_type.to_lowercase()))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Scale each result's score by its type weight and re-sort
+    pub fn apply(&self, results: &mut [SearchResult]) {
+        if !self.weights.is_empty() {
+            for result in results.iter_mut() {
+                result.score *= self.weight(result);
+            }
+        }
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    }
+}
+
+/// Merge results from the same source file whose boundary ranges overlap by at least
+/// `min_overlap_ratio` of the shorter range. The higher-scoring result is kept with the
+/// stronger score, and the absorbed chunk IDs are listed under `merged_chunk_ids`.
+pub fn merge_overlapping_results(mut results: Vec<SearchResult>, min_overlap_ratio: f32) -> Vec<SearchResult> {
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut merged: Vec<SearchResult> = Vec::with_capacity(results.len());
+
+    'results: for result in results {
+        if let Some(range) = boundary_range(&result) {
+            for kept in merged.iter_mut() {
+                if kept.metadata.get("source_file") != result.metadata.get("source_file") {
+                    continue;
+                }
+
+                let Some(kept_range) = boundary_range(kept) else {
+                    continue;
+                };
+
+                if overlap_ratio(range, kept_range) >= min_overlap_ratio {
+                    kept.score = kept.score.max(result.score);
+                    kept.metadata
+                        .entry("merged_chunk_ids".to_string())
+                        .and_modify(|ids| {
+                            ids.push(',');
+                            ids.push_str(&result.chunk_id);
+                        })
+                        .or_insert_with(|| result.chunk_id.clone());
+                    continue 'results;
+                }
+            }
+        }
+
+        merged.push(result);
+    }
+
+    merged
+}
+
+/// A result's line range; inverted ranges in stored metadata are treated as unknown
+fn boundary_range(result: &SearchResult) -> Option<(usize, usize)> {
+    let start = result.metadata.get("line_start")?.parse().ok()?;
+    let end = result.metadata.get("line_end")?.parse().ok()?;
+    (start <= end).then_some((start, end))
+}
+
+fn overlap_ratio(a: (usize, usize), b: (usize, usize)) -> f32 {
+    let overlap = a.1.min(b.1).saturating_sub(a.0.max(b.0));
+    let shorter = (a.1 - a.0).min(b.1 - b.0);
+
+    if shorter == 0 {
+        0.0
+    } else {
+        overlap as f32 / shorter as f32
+    }
+}
+
+/// Results sharing one metadata value (e.g. `source_file`), ranked by their best chunk
+#[derive(Debug, Clone)]
+pub struct ResultGroup {
+    pub key: String,
+    pub score: f32,
+    pub total_matches: usize,  // Matching chunks in the group before truncation to the per-group limit
+    pub results: Vec<SearchResult>,
+}
+
+/// Group score-sorted results by a metadata key, keeping the best `per_group` chunks of each
+/// so one verbose document cannot take every slot. Results without the key are grouped
+/// under "unknown".
+pub fn group_results(results: Vec<SearchResult>, key: &str, per_group: usize) -> Vec<ResultGroup> {
+    let mut groups: Vec<ResultGroup> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        let value = result.metadata.get(key).cloned().unwrap_or_else(|| "unknown".to_string());
+        let position = *index.entry(value.clone()).or_insert_with(|| {
+            groups.push(ResultGroup { key: value, score: 0.0, total_matches: 0, results: Vec::new() });
+            groups.len() - 1
+        });
+
+        let group = &mut groups[position];
+        group.total_matches += 1;
+        group.score = group.score.max(result.score);
+        group.results.push(result);
+    }
+
+    for group in &mut groups {
+        group.results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        group.results.truncate(per_group.max(1));
+    }
+    groups.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    groups
+}
+
+/// How many results fall under each chunk type, language and source file, so clients can
+/// show facets without another call. Results without a language are left out of `languages`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResultSummary {
+    pub chunk_types: BTreeMap<String, usize>,
+    pub languages: BTreeMap<String, usize>,
+    pub sources: BTreeMap<String, usize>,
+}
+
+impl ResultSummary {
+    pub fn of<'a>(results: impl IntoIterator<Item = &'a SearchResult>) -> Self {
+        let mut summary = Self::default();
+        for result in results {
+            let facets = [
+                (&mut summary.chunk_types, "chunk_type"),
+                (&mut summary.languages, "language"),
+                (&mut summary.sources, "source_file"),
+            ];
+            for (counts, key) in facets {
+                if let Some(value) = result.metadata.get(key) {
+                    *counts.entry(value.clone()).or_default() += 1;
+                }
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(id: &str, score: f32, file: &str, start: usize, end: usize) -> SearchResult {
+        let mut metadata = HashMap::new();
+        metadata.insert("source_file".to_string(), file.to_string());
+        metadata.insert("line_start".to_string(), start.to_string());
+        metadata.insert("line_end".to_string(), end.to_string());
+        SearchResult {
+            chunk_id: id.to_string(),
+            score,
+            content: String::new(),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_overlapping_chunks_are_merged() {
+        let results = vec![
+            result("a", 0.8, "doc.md", 0, 500),
+            result("b", 0.9, "doc.md", 100, 550),
+            result("c", 0.7, "other.md", 100, 550),
+        ];
+
+        let merged = merge_overlapping_results(results, DEFAULT_OVERLAP_MERGE_RATIO);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].chunk_id, "b");
+        assert_eq!(merged[0].score, 0.9);
+        assert_eq!(merged[0].metadata.get("merged_chunk_ids").map(|s| s.as_str()), Some("a"));
+        assert_eq!(merged[1].chunk_id, "c");
+    }
+
+    #[test]
+    fn test_small_window_overlap_is_kept_separate() {
+        let results = vec![
+            result("a", 0.8, "doc.md", 0, 500),
+            result("b", 0.7, "doc.md", 450, 950),
+        ];
+
+        let merged = merge_overlapping_results(results, DEFAULT_OVERLAP_MERGE_RATIO);
+        assert_eq!(merged.len(), 2);
+
+        // An inverted range is never merged
+        let results = vec![result("a", 0.8, "doc.md", 0, 500), result("b", 0.7, "doc.md", 400, 100)];
+        assert_eq!(merge_overlapping_results(results, DEFAULT_OVERLAP_MERGE_RATIO).len(), 2);
+    }
+
+    #[test]
+    fn test_type_weights_reorder_results() {
+        let mut code = result("code", 0.7, "dma.sv", 0, 100);
+        code.metadata.insert("chunk_type".to_string(), "Code".to_string());
+        let mut pdf = result("pdf", 0.8, "spec.pdf", 0, 100);
+        pdf.metadata.insert("chunk_type".to_string(), "Pdf".to_string());
+        let plain = result("plain", 0.75, "notes.txt", 0, 100);
+
+        let weights: HashMap<String, f32> = [("code".to_string(), 1.2), ("PDF".to_string(), 0.9)].into_iter().collect();
+        let mut results = vec![pdf, plain, code];
+        TypeWeights::new(&weights).apply(&mut results);
+
+        assert_eq!(results.iter().map(|r| r.chunk_id.as_str()).collect::<Vec<_>>(), vec!["code", "plain", "pdf"]);
+        assert!((results[0].score - 0.84).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_weighted_rank_fusion() {
+        let legs = RetrievalLegs {
+            vector: vec![result("a", 0.9, "a.md", 0, 10), result("b", 0.8, "b.md", 0, 10)],
+            text: vec![result("b", 3.0, "b.md", 0, 10), result("c", 2.0, "c.md", 0, 10)],
+            graph: vec![],
+            sparse: vec![],
+        };
+        let ids = |results: Vec<SearchResult>| results.into_iter().map(|r| r.chunk_id).collect::<Vec<_>>();
+
+        // Found by both lists, b overtakes a; without the keyword list a stays first
+        let fused = FusionWeights { vector: 1.0, text: 1.0, graph: 0.0, sparse: 1.0, rrf_k: 60.0 }.fuse(&legs);
+        assert_eq!(ids(fused.clone()), vec!["b", "a", "c"]);
+        assert!(fused[0].score < 1.0 && fused[0].score > 0.99);
+        assert_eq!(ids(FusionWeights { text: 0.0, ..Default::default() }.fuse(&legs)), vec!["a", "b"]);
+        assert_eq!(ids(legs.fallback(2)), vec!["a", "b"]);
+        assert_eq!(legs.graph_seeds(), vec!["a", "b", "c"]);
+
+        // A sparse list that finds c first lifts it above a
+        let with_sparse = RetrievalLegs { sparse: vec![result("c", 4.2, "c.md", 0, 10)], ..legs.clone() };
+        assert_eq!(ids(FusionWeights::default().fuse(&with_sparse)), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_interleave_lists_from_two_models() {
+        // The code model scores on a lower scale, yet its top result ranks alongside the text model's
+        let text_model = vec![result("prose", 0.82, "a.md", 0, 10), result("both", 0.80, "b.rs", 0, 10), result("tail", 0.75, "c.md", 0, 10)];
+        let code_model = vec![result("both", 0.45, "b.rs", 0, 10), result("code", 0.40, "d.rs", 0, 10)];
+
+        let merged = interleave_by_rank(vec![text_model, code_model], 3);
+        assert_eq!(merged.iter().map(|r| r.chunk_id.as_str()).collect::<Vec<_>>(), vec!["prose", "both", "code"]);
+        assert_eq!(merged[1].score, 0.45);
+    }
+
+    #[test]
+    fn test_group_results_by_source_file() {
+        let results = vec![
+            result("a1", 0.9, "verbose.md", 0, 10),
+            result("a2", 0.85, "verbose.md", 20, 30),
+            result("a3", 0.8, "verbose.md", 40, 50),
+            result("b1", 0.7, "short.md", 0, 10),
+        ];
+
+        let groups = group_results(results, "source_file", 2);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "verbose.md");
+        assert_eq!(groups[0].total_matches, 3);
+        assert_eq!(groups[0].results.iter().map(|r| r.chunk_id.as_str()).collect::<Vec<_>>(), vec!["a1", "a2"]);
+        assert_eq!(groups[1].key, "short.md");
+        assert!((groups[1].score - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_result_summary_counts_facets() {
+        let mut code = result("code", 0.7, "dma.sv", 0, 100);
+        code.metadata.insert("chunk_type".to_string(), "Code".to_string());
+        code.metadata.insert("language".to_string(), "systemverilog".to_string());
+        let mut notes = result("notes", 0.6, "dma.sv", 200, 300);
+        notes.metadata.insert("chunk_type".to_string(), "Code".to_string());
+        let spec = result("spec", 0.5, "spec.md", 0, 10);
+
+        let summary = ResultSummary::of(&[code, notes, spec]);
+        assert_eq!(summary.chunk_types, BTreeMap::from([("Code".to_string(), 2)]));
+        assert_eq!(summary.languages, BTreeMap::from([("systemverilog".to_string(), 1)]));
+        assert_eq!(summary.sources, BTreeMap::from([("dma.sv".to_string(), 2), ("spec.md".to_string(), 1)]));
+    }
 }
\ No newline at end of file
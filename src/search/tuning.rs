@@ -0,0 +1,231 @@
+use super::retrieval::{FusionWeights, RetrievalLegs};
+use crate::storage::SearchResult;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const VECTOR_WEIGHTS: &[f32] = &[0.0, 1.0];
+const TEXT_WEIGHTS: &[f32] = &[0.0, 0.25, 0.5, 1.0, 2.0, 4.0];
+const GRAPH_WEIGHTS: &[f32] = &[0.0, 0.1, 0.25, 0.5, 1.0];
+const RRF_KS: &[f32] = &[1.0, 10.0, 30.0, 60.0, 120.0];
+
+/// A query with the chunks (by id) or documents (by source file) that should answer it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    pub relevant: Vec<String>,
+}
+
+/// Labeled queries for `tune_ranking`, kept in a YAML file:
+///
+/// ```yaml
+/// queries:
+///   - query: how is the DMA engine reset
+///     relevant: [docs/dma.md]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalSet {
+    pub queries: Vec<EvalCase>,
+}
+
+impl EvalSet {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read eval set {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid eval set {}: {}", path.display(), e))
+    }
+}
+
+/// Mean ranking quality over a set of queries, each measured on its top k results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RankingQuality {
+    pub ndcg: f32,
+    pub mrr: f32,
+    pub recall: f32,
+}
+
+impl RankingQuality {
+    /// nDCG first, then MRR and recall to break ties
+    fn beats(&self, other: &RankingQuality) -> bool {
+        const EPSILON: f32 = 1e-6;
+        for (a, b) in [(self.ndcg, other.ndcg), (self.mrr, other.mrr), (self.recall, other.recall)] {
+            if (a - b).abs() > EPSILON {
+                return a > b;
+            }
+        }
+        false
+    }
+}
+
+/// Quality of one ranking. Each label is credited once, to the first result matching it
+/// by chunk id or source file.
+pub fn evaluate(ranking: &[SearchResult], relevant: &[String], top_k: usize) -> RankingQuality {
+    if relevant.is_empty() {
+        return RankingQuality::default();
+    }
+    let mut credited = vec![false; relevant.len()];
+    let (mut dcg, mut first_hit) = (0.0, None);
+    for (rank, result) in ranking.iter().take(top_k).enumerate() {
+        let source_file = result.metadata.get("source_file");
+        let label = relevant.iter().enumerate()
+            .position(|(i, label)| !credited[i] && (*label == result.chunk_id || Some(label) == source_file));
+        if let Some(label) = label {
+            credited[label] = true;
+            dcg += 1.0 / (rank as f32 + 2.0).log2();
+            first_hit.get_or_insert(rank);
+        }
+    }
+    let ideal: f32 = (0..relevant.len().min(top_k)).map(|rank| 1.0 / (rank as f32 + 2.0).log2()).sum();
+
+    RankingQuality {
+        ndcg: dcg / ideal,
+        mrr: first_hit.map_or(0.0, |rank| 1.0 / (rank as f32 + 1.0)),
+        recall: credited.iter().filter(|&&hit| hit).count() as f32 / relevant.len() as f32,
+    }
+}
+
+fn mean_quality(cases: &[(RetrievalLegs, Vec<String>)], top_k: usize, rank: impl Fn(&RetrievalLegs) -> Vec<SearchResult>) -> RankingQuality {
+    let mut total = RankingQuality::default();
+    for (legs, relevant) in cases {
+        let quality = evaluate(&rank(legs), relevant, top_k);
+        total.ndcg += quality.ndcg;
+        total.mrr += quality.mrr;
+        total.recall += quality.recall;
+    }
+    let count = cases.len().max(1) as f32;
+    RankingQuality { ndcg: total.ndcg / count, mrr: total.mrr / count, recall: total.recall / count }
+}
+
+/// Outcome of a weight search, comparing the configured ranking with the best weights found
+#[derive(Debug, Clone, Serialize)]
+pub struct TuningReport {
+    pub queries: usize,
+    pub candidates_evaluated: usize,
+    pub current: Option<FusionWeights>,  // None: vector search with keyword fallback, no fusion
+    pub recommended: FusionWeights,
+    pub before: RankingQuality,
+    pub after: RankingQuality,
+}
+
+impl TuningReport {
+    pub fn improved(&self) -> bool {
+        self.after.beats(&self.before)
+    }
+}
+
+/// Grid search over fusion weights and RRF k, each candidate scored on the retrieval lists
+/// of every labeled query (fetched once, so candidates only differ in how they fuse).
+/// Rankings are compared before type weights and passage merging.
+pub fn tune(cases: &[(RetrievalLegs, Vec<String>)], current: Option<FusionWeights>, top_k: usize) -> TuningReport {
+    let before = match current {
+        Some(weights) => mean_quality(cases, top_k, |legs| weights.fuse(legs)),
+        None => mean_quality(cases, top_k, |legs| legs.fallback(top_k)),
+    };
+
+    let mut best: Option<(FusionWeights, RankingQuality)> = None;
+    let mut evaluated = 0;
+    for &vector in VECTOR_WEIGHTS {
+        for &text in TEXT_WEIGHTS {
+            for &graph in GRAPH_WEIGHTS {
+                if vector + text == 0.0 {
+                    continue;  // The graph list alone only echoes the (unweighted) seeds
+                }
+                for &rrf_k in RRF_KS {
+                    // The sparse list, when there is one, keeps its current weight
+                    let weights = FusionWeights { vector, text, graph, rrf_k, ..current.unwrap_or_default() };
+                    let quality = mean_quality(cases, top_k, |legs| weights.fuse(legs));
+                    evaluated += 1;
+                    if best.as_ref().is_none_or(|(_, best)| quality.beats(best)) {
+                        best = Some((weights, quality));
+                    }
+                }
+            }
+        }
+    }
+    let (recommended, after) = best.unwrap_or((current.unwrap_or_default(), before));
+
+    TuningReport { queries: cases.len(), candidates_evaluated: evaluated, current, recommended, before, after }
+}
+
+/// Set `ranking.fusion` in a YAML config file, keeping the rest of the file (comments
+/// included) as it is. The edited file must still parse as a config.
+pub fn write_fusion_weights(config_path: &Path, weights: &FusionWeights) -> Result<()> {
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", config_path.display(), e))?;
+    let value = format!(
+        "{{vector: {}, text: {}, graph: {}, sparse: {}, rrf_k: {}}}",
+        weights.vector, weights.text, weights.graph, weights.sparse, weights.rrf_k
+    );
+    let updated = set_ranking_fusion(&content, &value);
+
+    let parsed: crate::config::Config = serde_yaml::from_str(&updated)
+        .map_err(|e| anyhow!("Updating {} would leave it invalid: {}", config_path.display(), e))?;
+    if parsed.ranking.fusion.as_ref() != Some(weights) {
+        return Err(anyhow!("Could not set ranking.fusion in {}; set it by hand to {}", config_path.display(), value));
+    }
+    std::fs::write(config_path, updated)?;
+    Ok(())
+}
+
+fn set_ranking_fusion(content: &str, value: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let Some(section) = lines.iter().position(|line| line.trim_end() == "ranking:" || line.starts_with("ranking: ")) else {
+        lines.extend(["".to_string(), "ranking:".to_string(), format!("  fusion: {}", value)]);
+        return lines.join("\n");
+    };
+    let indented = |line: &str| line.starts_with(' ') || line.trim().is_empty() || line.trim_start().starts_with('#');
+    let end = lines[section + 1..].iter().position(|line| !indented(line)).map_or(lines.len(), |i| section + 1 + i);
+
+    let Some(fusion) = (section + 1..end).find(|&i| lines[i].trim_start().starts_with("fusion:")) else {
+        lines.insert(section + 1, format!("  fusion: {}", value));
+        return lines.join("\n");
+    };
+    let indent = lines[fusion].len() - lines[fusion].trim_start().len();
+    let comment = lines[fusion].find(" #").map(|i| lines[fusion][i..].to_string()).unwrap_or_default();
+    // A block mapping under `fusion:` is replaced along with its lines
+    let block_end = lines[fusion + 1..end].iter().enumerate()
+        .take_while(|(_, line)| line.trim().is_empty() || line.len() - line.trim_start().len() > indent)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .last()
+        .map_or(fusion + 1, |(i, _)| fusion + 2 + i);
+    lines.splice(fusion..block_end, [format!("{}fusion: {}{}", " ".repeat(indent), value, comment)]);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(id: &str, file: &str) -> SearchResult {
+        let metadata: HashMap<String, String> = [("source_file".to_string(), file.to_string())].into_iter().collect();
+        SearchResult { chunk_id: id.to_string(), score: 0.5, content: String::new(), metadata }
+    }
+
+    #[test]
+    fn test_tuning_prefers_the_list_that_finds_relevant_chunks() {
+        let ranking = vec![result("a", "x.md"), result("b", "dma.md"), result("c", "dma.md")];
+        let quality = evaluate(&ranking, &["dma.md".to_string()], 3);
+        assert_eq!(quality.mrr, 0.5);
+        assert!((quality.ndcg - 1.0 / 3f32.log2()).abs() < 1e-6);
+
+        // Vector search keeps missing what keyword search ranks first
+        let cases: Vec<(RetrievalLegs, Vec<String>)> = (0..3).map(|i| (RetrievalLegs {
+            vector: vec![result(&format!("noise{}", i), "noise.md"), result(&format!("v{}", i), "other.md")],
+            text: vec![result(&format!("hit{}", i), "dma.md")],
+            graph: vec![],
+            sparse: vec![],
+        }, vec!["dma.md".to_string()])).collect();
+        let report = tune(&cases, None, 2);
+        assert_eq!(report.before.recall, 0.0);
+        assert!(report.improved());
+        assert_eq!(report.after.mrr, 1.0);
+        assert!(report.recommended.text > report.recommended.vector);
+
+        let config = "ranking:\n  type_weights: {}\n  fusion:   # tuned\n    vector: 1.0\n    text: 1.0\n\nmemory:\n  recency_weight: 0.3";
+        assert_eq!(
+            set_ranking_fusion(config, "{vector: 1, text: 2, graph: 0, rrf_k: 10}"),
+            "ranking:\n  type_weights: {}\n  fusion: {vector: 1, text: 2, graph: 0, rrf_k: 10} # tuned\n\nmemory:\n  recency_weight: 0.3"
+        );
+    }
+}
\ No newline at end of file
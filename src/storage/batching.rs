@@ -0,0 +1,236 @@
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Rough characters-per-token ratio used when no tokenizer is available
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How many times a rate-limited single-item request is retried before giving up
+const MAX_RATE_LIMIT_RETRIES: usize = 5;
+
+/// Per-request limits of an embedding provider
+#[derive(Debug, Clone, Copy)]
+pub struct BatchLimits {
+    pub max_items: usize,
+    pub max_tokens: usize,
+}
+
+impl BatchLimits {
+    pub fn new(max_items: usize, max_tokens: usize) -> Self {
+        Self {
+            max_items: max_items.max(1),
+            max_tokens: max_tokens.max(1),
+        }
+    }
+}
+
+/// Why a provider rejected a batch
+#[derive(Debug)]
+pub enum BatchError {
+    TooLarge,                                       // HTTP 413
+    RateLimited { retry_after: Option<Duration> },  // HTTP 429
+    Failed(anyhow::Error),
+}
+
+impl BatchError {
+    /// Map an HTTP error response from a provider onto a batch error
+    pub fn from_status(status: u16, retry_after: Option<Duration>, body: &str) -> Self {
+        match status {
+            413 => BatchError::TooLarge,
+            429 => BatchError::RateLimited { retry_after },
+            _ => BatchError::Failed(anyhow!("Embedding request failed with HTTP {}: {}", status, body)),
+        }
+    }
+}
+
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN).max(1)
+}
+
+/// Greedily pack consecutive texts into batches that stay within both the item and token
+/// limits. A single text larger than the token limit gets a batch of its own.
+pub fn pack_batches(texts: &[String], limits: &BatchLimits) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut tokens = 0;
+
+    for (i, text) in texts.iter().enumerate() {
+        let text_tokens = estimate_tokens(text);
+        let items = i - start;
+
+        if items > 0 && (items >= limits.max_items || tokens + text_tokens > limits.max_tokens) {
+            batches.push(start..i);
+            start = i;
+            tokens = 0;
+        }
+        tokens += text_tokens;
+    }
+
+    if start < texts.len() {
+        batches.push(start..texts.len());
+    }
+
+    batches
+}
+
+/// Embed `texts` in packed batches through `send`. Batches rejected as too large or rate
+/// limited are split in half and retried, and the token limit is lowered so later batches
+/// are packed smaller. Embeddings are returned in input order.
+pub fn embed_in_batches<F>(texts: &[String], limits: &BatchLimits, mut send: F) -> Result<Vec<Vec<f32>>>
+where
+    F: FnMut(&[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError>,
+{
+    let mut limits = *limits;
+    let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+
+    // Pending ranges, processed front to back; splits are pushed back to the front
+    let mut pending: Vec<Range<usize>> = pack_batches(texts, &limits);
+    pending.reverse();
+    let mut rate_limit_retries = 0;
+
+    while let Some(range) = pending.pop() {
+        let batch = &texts[range.clone()];
+
+        match send(batch) {
+            Ok(vectors) => {
+                if vectors.len() != batch.len() {
+                    return Err(anyhow!(
+                        "Embedding provider returned {} vectors for a batch of {}",
+                        vectors.len(), batch.len()
+                    ));
+                }
+                for (slot, vector) in embeddings[range].iter_mut().zip(vectors) {
+                    *slot = Some(vector);
+                }
+                rate_limit_retries = 0;
+            }
+            Err(BatchError::TooLarge) => {
+                if batch.len() == 1 {
+                    return Err(anyhow!(
+                        "Embedding provider rejected a single text of ~{} tokens as too large",
+                        estimate_tokens(&batch[0])
+                    ));
+                }
+                let batch_tokens: usize = batch.iter().map(|t| estimate_tokens(t)).sum();
+                limits.max_tokens = limits.max_tokens.min(batch_tokens / 2).max(1);
+                tracing::debug!("Batch of {} texts too large, splitting (max_tokens now {})", batch.len(), limits.max_tokens);
+                split_and_requeue(range, &mut pending);
+            }
+            Err(BatchError::RateLimited { retry_after }) => {
+                if let Some(delay) = retry_after {
+                    std::thread::sleep(delay);
+                }
+                if batch.len() > 1 {
+                    limits.max_items = (batch.len() / 2).max(1);
+                    tracing::debug!("Rate limited on batch of {} texts, splitting", batch.len());
+                    split_and_requeue(range, &mut pending);
+                } else {
+                    rate_limit_retries += 1;
+                    if rate_limit_retries > MAX_RATE_LIMIT_RETRIES {
+                        return Err(anyhow!("Embedding provider kept rate limiting after {} retries", MAX_RATE_LIMIT_RETRIES));
+                    }
+                    pending.push(range);
+                }
+            }
+            Err(BatchError::Failed(e)) => return Err(e),
+        }
+
+        // Re-pack whatever is still queued whole under the (possibly lowered) limits
+        if let Some(next) = pending.last().cloned() {
+            let repacked = pack_batches(&texts[next.clone()], &limits);
+            if repacked.len() > 1 {
+                pending.pop();
+                for sub in repacked.into_iter().rev() {
+                    pending.push(next.start + sub.start..next.start + sub.end);
+                }
+            }
+        }
+    }
+
+    Ok(embeddings.into_iter().map(|e| e.expect("every range is embedded or the call fails")).collect())
+}
+
+/// `embed_in_batches` with up to `concurrency` packed batches in flight at once, on the
+/// current rayon pool. Each batch is split and retried on its own; the result keeps input order.
+pub fn embed_concurrently<F>(texts: &[String], limits: &BatchLimits, concurrency: usize, send: F) -> Result<Vec<Vec<f32>>>
+where
+    F: Fn(&[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> + Sync,
+{
+    let batches = pack_batches(texts, limits);
+    if concurrency <= 1 || batches.len() <= 1 {
+        return embed_in_batches(texts, limits, send);
+    }
+
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for wave in batches.chunks(concurrency) {
+        let results: Vec<Result<Vec<Vec<f32>>>> = wave.par_iter()
+            .map(|range| embed_in_batches(&texts[range.clone()], limits, &send))
+            .collect();
+        for vectors in results {
+            embeddings.extend(vectors?);
+        }
+    }
+    Ok(embeddings)
+}
+
+fn split_and_requeue(range: Range<usize>, pending: &mut Vec<Range<usize>>) {
+    let mid = range.start + (range.end - range.start) / 2;
+    pending.push(mid..range.end);
+    pending.push(range.start..mid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packing_respects_item_and_token_limits() {
+        let texts = vec!["a".repeat(40), "b".repeat(40), "c".repeat(40), "d".repeat(200), "e".repeat(4)];
+
+        // 10 tokens each for the first three, 50 for the fourth
+        let batches = pack_batches(&texts, &BatchLimits::new(2, 30));
+
+        assert_eq!(batches, vec![0..2, 2..3, 3..4, 4..5]);
+    }
+
+    #[test]
+    fn test_too_large_batches_are_split_until_accepted() {
+        let texts: Vec<String> = (0..8).map(|i| format!("chunk {}", i)).collect();
+        let mut request_sizes = Vec::new();
+
+        let embeddings = embed_in_batches(&texts, &BatchLimits::new(8, 1000), |batch| {
+            request_sizes.push(batch.len());
+            if batch.len() > 2 {
+                Err(BatchError::TooLarge)
+            } else {
+                Ok(batch.iter().map(|t| vec![t.len() as f32]).collect())
+            }
+        })
+        .unwrap();
+
+        assert_eq!(embeddings.len(), 8);
+        assert_eq!(request_sizes[0], 8);
+        assert!(request_sizes.iter().skip(1).all(|&n| n < 8));
+        assert_eq!(embeddings[7], vec![texts[7].len() as f32]);
+    }
+
+    #[test]
+    fn test_concurrent_batches_keep_input_order() {
+        let texts: Vec<String> = (0..50).map(|i| format!("chunk {}", i)).collect();
+        let in_flight = std::sync::atomic::AtomicUsize::new(0);
+        let peak = std::sync::atomic::AtomicUsize::new(0);
+
+        let embeddings = embed_concurrently(&texts, &BatchLimits::new(4, 1000), 3, |batch| {
+            let now = in_flight.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            peak.fetch_max(now, std::sync::atomic::Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            in_flight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(batch.iter().map(|t| vec![t[6..].parse::<f32>().unwrap()]).collect())
+        })
+        .unwrap();
+
+        assert_eq!(embeddings, (0..50).map(|i| vec![i as f32]).collect::<Vec<_>>());
+        assert!(peak.load(std::sync::atomic::Ordering::SeqCst) <= 3);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::chunker::Chunk;
+use super::recovery::open_sled_checked;
 use anyhow::Result;
 use std::path::Path;
 use sha2::{Sha256, Digest};
@@ -14,8 +15,9 @@ impl ChunkStore {
         let chunks_dir = data_dir.join("chunks");
         std::fs::create_dir_all(&chunks_dir)?;
 
-        let db = sled::open(&chunks_dir)?;
-        let hash_index = sled::open(chunks_dir.join("hash_index"))?;
+        let (db, _) = open_sled_checked(&sled::Config::new().path(&chunks_dir), &chunks_dir, "chunk")?;
+        let hash_index_dir = chunks_dir.join("hash_index");
+        let (hash_index, _) = open_sled_checked(&sled::Config::new().path(&hash_index_dir), &hash_index_dir, "hash index")?;
 
         Ok(Self { db, hash_index })
     }
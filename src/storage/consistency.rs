@@ -0,0 +1,122 @@
+use crate::chunker::Chunk;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Disagreements between the canonical chunk store and the indexes derived from it
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ConsistencyReport {
+    pub files: usize,
+    pub chunks: usize,                 // Readable chunks, reached through their source files
+    pub stored_chunks: usize,          // Entries in the chunk store, readable or not
+    pub vector_entries: usize,         // Entries in the vector index
+    pub missing_embeddings: usize,     // Chunks with no vector, e.g. from imports that skipped embedding
+    pub invalid_embeddings: usize,     // Vectors with NaN/infinite values or an odd dimension
+    pub graph_nodes: usize,
+    pub ungraphed_chunks: usize,       // Chunks the relationship graph does not know about
+    pub orphaned_graph_nodes: usize,   // Graph nodes whose chunk no longer exists
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.chunks == self.stored_chunks
+            && self.vector_entries == self.chunks
+            && self.missing_embeddings == 0
+            && self.invalid_embeddings == 0
+            && self.ungraphed_chunks == 0
+            && self.orphaned_graph_nodes == 0
+    }
+}
+
+/// Why a chunk has to be embedded again before the vector index can use it. Each embedding
+/// provider has its own dimension, so a vector is odd when its length differs from the most
+/// common length among chunks of the same provider.
+pub fn reembed_reason(chunk: &Chunk, dimensions: &HashMap<String, usize>) -> Option<&'static str> {
+    if chunk.embedding.is_empty() {
+        return Some("missing");
+    }
+    if chunk.embedding.iter().any(|v| !v.is_finite()) {
+        return Some("non-finite");
+    }
+    match dimensions.get(provider_of(chunk)) {
+        Some(&dimension) if dimension != chunk.embedding.len() => Some("dimension"),
+        _ => None,
+    }
+}
+
+/// Most common embedding length per provider
+pub fn provider_dimensions(chunks: &[Chunk]) -> HashMap<String, usize> {
+    let mut counts: HashMap<&str, HashMap<usize, usize>> = HashMap::new();
+    for chunk in chunks.iter().filter(|c| !c.embedding.is_empty()) {
+        *counts.entry(provider_of(chunk)).or_default().entry(chunk.embedding.len()).or_default() += 1;
+    }
+    counts.into_iter()
+        .filter_map(|(provider, lengths)| {
+            let (dimension, _) = lengths.into_iter().max_by_key(|&(length, count)| (count, length))?;
+            Some((provider.to_string(), dimension))
+        })
+        .collect()
+}
+
+/// Compare the chunks read from the store against index and graph sizes
+pub fn audit(files: usize, chunks: &[Chunk], stored_chunks: usize, vector_entries: usize, graph_nodes: &HashSet<&str>) -> ConsistencyReport {
+    let dimensions = provider_dimensions(chunks);
+    let chunk_ids: HashSet<&str> = chunks.iter().map(|c| c.id.as_str()).collect();
+
+    let mut report = ConsistencyReport {
+        files,
+        chunks: chunks.len(),
+        stored_chunks,
+        vector_entries,
+        graph_nodes: graph_nodes.len(),
+        ..Default::default()
+    };
+    for chunk in chunks {
+        match reembed_reason(chunk, &dimensions) {
+            Some("missing") => report.missing_embeddings += 1,
+            Some(_) => report.invalid_embeddings += 1,
+            None => {}
+        }
+        if !graph_nodes.contains(chunk.id.as_str()) {
+            report.ungraphed_chunks += 1;
+        }
+    }
+    report.orphaned_graph_nodes = graph_nodes.iter().filter(|id| !chunk_ids.contains(*id)).count();
+    report
+}
+
+fn provider_of(chunk: &Chunk) -> &str {
+    chunk.metadata.attributes.get("embedding_provider").map(|p| p.as_str()).unwrap_or(super::providers::LOCAL_PROVIDER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    fn chunk(id: &str, embedding: Vec<f32>) -> Chunk {
+        let mut chunk = SemanticChunker::build_text_chunk("DMA reset sequence", "dma.md", "hash", (1, 1), ChunkStrategy::NaturalSection);
+        chunk.id = id.to_string();
+        chunk.embedding = embedding;
+        chunk
+    }
+
+    #[test]
+    fn test_audit_finds_unindexed_chunks() {
+        let chunks = vec![
+            chunk("a", vec![0.5; 4]),
+            chunk("b", vec![0.5; 4]),
+            chunk("c", Vec::new()),
+            chunk("d", vec![0.5; 3]),
+            chunk("e", vec![f32::NAN; 4]),
+        ];
+        let graph: HashSet<&str> = ["a", "b", "gone"].into_iter().collect();
+
+        let report = audit(1, &chunks, 6, 4, &graph);
+        assert_eq!((report.missing_embeddings, report.invalid_embeddings), (1, 2));
+        assert_eq!((report.ungraphed_chunks, report.orphaned_graph_nodes), (3, 1));
+        assert!(!report.is_consistent());
+
+        assert_eq!(reembed_reason(&chunks[0], &provider_dimensions(&chunks)), None);
+        assert_eq!(reembed_reason(&chunks[3], &provider_dimensions(&chunks)), Some("dimension"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use crate::chunker::Chunk;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BinaryHeap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Chunks listed individually in a drift report, most drifted first
+const WORST_SAMPLES: usize = 5;
+
+/// How far one stored vector is from the same text embedded again
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftSample {
+    pub chunk_id: String,
+    pub source_file: String,
+    pub similarity: f32,
+}
+
+/// Stored vectors compared with fresh embeddings of the same chunks. Drift is one minus the
+/// mean cosine similarity; a deterministic local model never drifts, a remote provider that
+/// silently changed its model does.
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub checked_at: DateTime<Utc>,
+    pub provider: String,          // Provider that produced the fresh embeddings
+    pub sampled: usize,
+    pub compared: usize,
+    pub skipped: usize,            // Stored by a provider in another vector space
+    pub mean_similarity: f32,
+    pub min_similarity: f32,
+    pub drift: f32,
+    pub threshold: f32,
+    pub reindex_suggested: bool,
+    pub worst: Vec<DriftSample>,
+}
+
+/// Pick up to `size` chunks with embeddings. Each `round` picks a different pseudo-random
+/// sample, so periodic checks cover the corpus over time without reading it all into memory.
+pub fn sample_chunks(chunks: impl Iterator<Item = Chunk>, size: usize, round: u64) -> Vec<Chunk> {
+    // Keep the `size` chunks with the smallest hash of (round, id)
+    struct Ranked(u64, Chunk);
+    impl PartialEq for Ranked { fn eq(&self, other: &Self) -> bool { self.0 == other.0 } }
+    impl Eq for Ranked {}
+    impl PartialOrd for Ranked { fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) } }
+    impl Ord for Ranked { fn cmp(&self, other: &Self) -> std::cmp::Ordering { self.0.cmp(&other.0) } }
+
+    let mut heap = BinaryHeap::with_capacity(size + 1);
+    for chunk in chunks.filter(|chunk| !chunk.embedding.is_empty()) {
+        let mut hasher = DefaultHasher::new();
+        (round, &chunk.id).hash(&mut hasher);
+        heap.push(Ranked(hasher.finish(), chunk));
+        if heap.len() > size {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec().into_iter().map(|Ranked(_, chunk)| chunk).collect()
+}
+
+/// Compare each sampled chunk's stored vector with its fresh one; `None` marks chunks whose
+/// stored vector is not comparable with the fresh provider's
+pub fn drift_report(samples: &[(&Chunk, Option<&[f32]>)], provider: &str, threshold: f32, checked_at: DateTime<Utc>) -> DriftReport {
+    let mut compared: Vec<DriftSample> = samples.iter()
+        .filter_map(|(chunk, fresh)| Some(DriftSample {
+            chunk_id: chunk.id.clone(),
+            source_file: chunk.metadata.source_file.clone(),
+            similarity: crate::search::embedding_similarity(&chunk.embedding, (*fresh)?),
+        }))
+        .collect();
+    compared.sort_by(|a, b| a.similarity.total_cmp(&b.similarity));
+
+    let mean_similarity = if compared.is_empty() {
+        1.0
+    } else {
+        compared.iter().map(|s| s.similarity).sum::<f32>() / compared.len() as f32
+    };
+    let drift = (1.0 - mean_similarity).max(0.0);
+    DriftReport {
+        checked_at,
+        provider: provider.to_string(),
+        sampled: samples.len(),
+        compared: compared.len(),
+        skipped: samples.len() - compared.len(),
+        mean_similarity,
+        min_similarity: compared.first().map(|s| s.similarity).unwrap_or(1.0),
+        drift,
+        threshold,
+        reindex_suggested: !compared.is_empty() && drift > threshold,
+        worst: compared.into_iter().take(WORST_SAMPLES).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    fn chunk(embedding: Vec<f32>) -> Chunk {
+        let mut chunk = SemanticChunker::build_text_chunk("DMA reset sequence", "dma.md", "hash", (1, 1), ChunkStrategy::NaturalSection);
+        chunk.embedding = embedding;
+        chunk
+    }
+
+    #[test]
+    fn test_sampling_and_drift_threshold() {
+        let chunks: Vec<Chunk> = (0..20).map(|i| chunk(vec![1.0, i as f32])).chain([chunk(vec![])]).collect();
+        let first = sample_chunks(chunks.clone().into_iter(), 5, 1);
+        assert_eq!(first.len(), 5);
+        assert!(first.iter().all(|c| !c.embedding.is_empty()));
+        let again: Vec<String> = sample_chunks(chunks.clone().into_iter(), 5, 1).into_iter().map(|c| c.id).collect();
+        assert_eq!(first.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), again);
+        assert_eq!(sample_chunks(chunks.into_iter(), 50, 2).len(), 20);
+
+        let (same, moved, other) = (chunk(vec![1.0, 0.0]), chunk(vec![1.0, 0.0]), chunk(vec![0.0, 1.0]));
+        let now = Utc::now();
+        let unchanged = drift_report(&[(&same, Some(&[2.0, 0.0][..])), (&other, None)], "openai", 0.02, now);
+        assert_eq!((unchanged.compared, unchanged.skipped, unchanged.reindex_suggested), (1, 1, false));
+        assert!(unchanged.drift.abs() < 1e-6);
+
+        let drifted = drift_report(&[(&same, Some(&[1.0, 0.0][..])), (&moved, Some(&[0.0, 1.0][..]))], "openai", 0.02, now);
+        assert!(drifted.reindex_suggested);
+        assert!((drifted.drift - 0.5).abs() < 1e-6);
+        assert_eq!(drifted.worst[0].chunk_id, moved.id);
+    }
+}
\ No newline at end of file
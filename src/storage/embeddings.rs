@@ -1,11 +1,110 @@
-use anyhow::Result;
+use super::batching::{self, BatchError, BatchLimits};
+use crate::config::EmbeddingConfig;
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher, DefaultHasher};
+use std::path::Path;
+
+const POLICY_FILE: &str = "embedding_policy.json";
+
+/// Tokens on each side whose vectors are mixed into a token's vector under late chunking
+const LATE_CHUNKING_WINDOW: usize = 16;
+
+/// How token and n-gram vectors are combined into one text vector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pooling {
+    Mean,
+    Max,
+}
+
+impl Pooling {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "mean" => Ok(Pooling::Mean),
+            "max" => Ok(Pooling::Max),
+            other => Err(anyhow!("Unknown embedding pooling '{}' (expected mean or max)", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pooling::Mean => "mean",
+            Pooling::Max => "max",
+        }
+    }
+}
+
+/// Treatment applied to every embedding. Query vectors are only comparable to chunk vectors
+/// embedded under the same policy, so the policy an index was built with is recorded in its
+/// data dir and checked before embedding queries or new chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingPolicy {
+    pub normalize: bool,
+    pub pooling: Pooling,
+    #[serde(default)]
+    pub truncate_dimension: Option<usize>,  // Matryoshka truncation, applied by the provider chain
+}
+
+impl Default for EmbeddingPolicy {
+    fn default() -> Self {
+        Self { normalize: true, pooling: Pooling::Mean, truncate_dimension: None }
+    }
+}
+
+impl std::fmt::Display for EmbeddingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let normalization = if self.normalize { "L2-normalized" } else { "unnormalized" };
+        write!(f, "{}, {} pooling", normalization, self.pooling.name())?;
+        match self.truncate_dimension {
+            Some(dimension) => write!(f, ", truncated to {} dimensions", dimension),
+            None => Ok(()),
+        }
+    }
+}
+
+impl EmbeddingPolicy {
+    pub fn from_config(config: &EmbeddingConfig) -> Result<Self> {
+        let truncate_dimension = config.truncate_dimension.filter(|&dimension| dimension > 0);
+        Ok(Self { normalize: config.normalize, pooling: Pooling::parse(&config.pooling)?, truncate_dimension })
+    }
+
+    /// The policy recorded for the index in `data_dir`, if anything has been embedded yet
+    pub fn recorded(data_dir: &Path) -> Result<Option<Self>> {
+        let path = data_dir.join(POLICY_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        serde_json::from_slice(&std::fs::read(&path)?)
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to read embedding policy from {:?}: {}", path, e))
+    }
+
+    pub fn record(&self, data_dir: &Path) -> Result<()> {
+        std::fs::write(data_dir.join(POLICY_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Err if vectors embedded under `self` would be compared against an index built under `recorded`
+    pub fn ensure_matches(&self, recorded: Option<&EmbeddingPolicy>) -> Result<()> {
+        match recorded {
+            Some(recorded) if recorded != self => Err(anyhow!(
+                "Embedding policy mismatch: the index was embedded {} but embedding config is {}. \
+                 Restore embedding.normalize/pooling/truncate_dimension, run the reembed tool, or re-ingest into a fresh data_dir.",
+                recorded, self
+            )),
+            _ => Ok(()),
+        }
+    }
+}
 
 /// Advanced deterministic embedding model that creates semantically meaningful embeddings
 /// This approach uses multiple linguistic features to create better embeddings than simple hashing
 pub struct EmbeddingModel {
     dimension: usize,
+    policy: EmbeddingPolicy,
     // Pre-computed semantic word vectors for common words
     word_vectors: HashMap<String, Vec<f32>>,
 }
@@ -22,10 +121,20 @@ impl EmbeddingModel {
 
         Ok(Self {
             dimension,
+            policy: EmbeddingPolicy::default(),
             word_vectors,
         })
     }
 
+    pub fn with_policy(mut self, policy: EmbeddingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &EmbeddingPolicy {
+        &self.policy
+    }
+
     /// Build a semantic vocabulary with pre-computed vectors for common words
     fn build_semantic_vocabulary(dimension: usize) -> HashMap<String, Vec<f32>> {
         let mut word_vectors = HashMap::new();
@@ -101,6 +210,46 @@ impl EmbeddingModel {
 
         // 2. Create embedding using multiple strategies
         let semantic_embedding = self.create_semantic_embedding(&words);
+        Ok(self.combine(semantic_embedding, text))
+    }
+
+    /// Late chunking: embed `chunks`, consecutive pieces of one passage, so that the token
+    /// vectors pooled for each chunk also carry the tokens around it in the passage
+    pub fn embed_spans(&self, chunks: &[String]) -> Result<Vec<Vec<f32>>> {
+        let spans: Vec<Vec<String>> = chunks.iter().map(|chunk| self.tokenize_text(chunk)).collect();
+        let tokens: Vec<Vec<f32>> = spans.iter().flatten().map(|word| self.word_vector(word)).collect();
+
+        // Each token's vector is mixed with the mean of its neighbours, across chunk boundaries
+        let mut prefix = vec![vec![0.0; self.dimension]];
+        for vector in &tokens {
+            let mut sum = prefix[prefix.len() - 1].clone();
+            for (total, value) in sum.iter_mut().zip(vector) {
+                *total += value;
+            }
+            prefix.push(sum);
+        }
+        let contextual: Vec<Vec<f32>> = tokens.iter().enumerate()
+            .map(|(i, vector)| {
+                let (start, end) = (i.saturating_sub(LATE_CHUNKING_WINDOW), (i + LATE_CHUNKING_WINDOW + 1).min(tokens.len()));
+                let count = (end - start) as f32;
+                vector.iter().enumerate()
+                    .map(|(d, value)| 0.5 * value + 0.5 * (prefix[end][d] - prefix[start][d]) / count)
+                    .collect()
+            })
+            .collect();
+
+        let mut offset = 0;
+        Ok(chunks.iter().zip(&spans)
+            .map(|(chunk, span)| {
+                let pooled = self.pool(contextual[offset..offset + span.len()].iter().cloned());
+                offset += span.len();
+                self.combine(pooled, chunk)
+            })
+            .collect())
+    }
+
+    /// The final vector for `text` from its pooled word vectors
+    fn combine(&self, semantic_embedding: Vec<f32>, text: &str) -> Vec<f32> {
         let ngram_embedding = self.create_ngram_embedding(text);
         let structural_embedding = self.create_structural_embedding(text);
 
@@ -114,10 +263,12 @@ impl EmbeddingModel {
                 0.1 * structural_embedding[i];    // Document structure
         }
 
-        // 4. Normalize final embedding
-        Self::normalize_vector(&mut final_embedding);
+        // 4. Normalize final embedding unless the policy keeps raw magnitudes
+        if self.policy.normalize {
+            Self::normalize_vector(&mut final_embedding);
+        }
 
-        Ok(final_embedding)
+        final_embedding
     }
 
     fn tokenize_text(&self, text: &str) -> Vec<String> {
@@ -134,34 +285,42 @@ impl EmbeddingModel {
     }
 
     fn create_semantic_embedding(&self, words: &[String]) -> Vec<f32> {
-        let mut embedding = vec![0.0; self.dimension];
-        let mut word_count = 0;
+        self.pool(words.iter().map(|word| self.word_vector(word)))
+    }
 
-        for word in words {
-            if let Some(word_vector) = self.word_vectors.get(word) {
-                // Add known word vector
-                for i in 0..self.dimension {
-                    embedding[i] += word_vector[i];
-                }
-                word_count += 1;
-            } else {
-                // Generate vector for unknown words based on characters
-                let word_vector = self.generate_word_vector(word);
-                for i in 0..self.dimension {
-                    embedding[i] += word_vector[i];
-                }
-                word_count += 1;
+    fn word_vector(&self, word: &str) -> Vec<f32> {
+        match self.word_vectors.get(word) {
+            // Known word vector
+            Some(word_vector) => word_vector.clone(),
+            // Generate vector for unknown words based on characters
+            None => self.generate_word_vector(word),
+        }
+    }
+
+    /// Combine vectors per the policy's pooling; empty input pools to zeros
+    fn pool(&self, vectors: impl Iterator<Item = Vec<f32>>) -> Vec<f32> {
+        let mut pooled = vec![0.0; self.dimension];
+        let mut count = 0;
+
+        for vector in vectors {
+            for (value, component) in pooled.iter_mut().zip(vector) {
+                *value = match self.policy.pooling {
+                    Pooling::Mean => *value + component,
+                    Pooling::Max if count == 0 => component,
+                    Pooling::Max => value.max(component),
+                };
             }
+            count += 1;
         }
 
         // Average the embeddings
-        if word_count > 0 {
-            for value in &mut embedding {
-                *value /= word_count as f32;
+        if self.policy.pooling == Pooling::Mean && count > 0 {
+            for value in &mut pooled {
+                *value /= count as f32;
             }
         }
 
-        embedding
+        pooled
     }
 
     fn generate_word_vector(&self, word: &str) -> Vec<f32> {
@@ -192,30 +351,14 @@ impl EmbeddingModel {
     }
 
     fn create_ngram_embedding(&self, text: &str) -> Vec<f32> {
-        let mut embedding = vec![0.0; self.dimension];
-
         // Character trigrams
         let chars: Vec<char> = text.chars().collect();
-        let mut ngram_count = 0;
+        let trigrams = chars.windows(3).map(|window| {
+            let trigram: String = window.iter().collect();
+            self.generate_ngram_vector(&trigram)
+        });
 
-        for i in 0..chars.len().saturating_sub(2) {
-            let trigram: String = chars[i..i+3].iter().collect();
-            let trigram_vector = self.generate_ngram_vector(&trigram);
-
-            for j in 0..self.dimension {
-                embedding[j] += trigram_vector[j];
-            }
-            ngram_count += 1;
-        }
-
-        // Average
-        if ngram_count > 0 {
-            for value in &mut embedding {
-                *value /= ngram_count as f32;
-            }
-        }
-
-        embedding
+        self.pool(trigrams)
     }
 
     fn generate_ngram_vector(&self, ngram: &str) -> Vec<f32> {
@@ -263,7 +406,7 @@ impl EmbeddingModel {
         embedding
     }
 
-    fn normalize_vector(vector: &mut [f32]) {
+    pub(crate) fn normalize_vector(vector: &mut [f32]) {
         let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
         if norm > 0.0 {
             for value in vector {
@@ -273,12 +416,46 @@ impl EmbeddingModel {
     }
 
     pub fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        texts.iter()
+        texts.par_iter()
             .map(|text| self.embed_text(text))
             .collect()
     }
 
+    /// Embed many texts in batches packed to the given item/token limits
+    pub fn embed_packed(&self, texts: &[String], limits: &BatchLimits) -> Result<Vec<Vec<f32>>> {
+        batching::embed_in_batches(texts, limits, |batch| {
+            self.embed_batch(batch).map_err(BatchError::Failed)
+        })
+    }
+
     pub fn get_dimension(&self) -> usize {
         self.dimension
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_policy_controls_embedding_and_rejects_mismatch() {
+        let text = "DMA reset sequence";
+        let default = EmbeddingModel::new("test").await.unwrap();
+        let norm: f32 = default.embed_text(text).unwrap().iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+
+        let raw = EmbeddingModel::new("test").await.unwrap()
+            .with_policy(EmbeddingPolicy { normalize: false, pooling: Pooling::Max, truncate_dimension: None });
+        assert_ne!(raw.embed_text(text).unwrap(), default.embed_text(text).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(EmbeddingPolicy::recorded(dir.path()).unwrap(), None);
+        default.policy().record(dir.path()).unwrap();
+        let recorded = EmbeddingPolicy::recorded(dir.path()).unwrap();
+        assert_eq!(recorded, Some(EmbeddingPolicy::default()));
+
+        assert!(default.policy().ensure_matches(recorded.as_ref()).is_ok());
+        let err = raw.policy().ensure_matches(recorded.as_ref()).unwrap_err().to_string();
+        assert!(err.contains("embedded L2-normalized, mean pooling but embedding config is unnormalized, max pooling"), "{}", err);
+    }
 }
\ No newline at end of file
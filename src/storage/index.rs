@@ -1,9 +1,14 @@
-use crate::chunker::Chunk;
-use anyhow::{Result, anyhow};
+use crate::chunker::{natural_language, parse_date, Chunk, ChunkMetadata};
+use super::quantize::{self, BinaryEmbedding, CachedEmbedding, CachedVector, EmbeddingStorage};
+use super::recovery::open_sled_checked;
+use super::sparse::SparseVector;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use std::path::Path;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
-use serde::{Serialize, Deserialize};
+use serde::{Deserializer, Serialize, Deserialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError, Transactional, TransactionalTree};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -13,10 +18,339 @@ pub struct SearchResult {
     pub metadata: HashMap<String, String>,
 }
 
+/// Per-source-file aggregates, kept in step with every chunk write
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentStats {
+    pub chunks: usize,
+    pub bytes: u64,                    // Stored size of the chunk records, embeddings included
+    #[serde(skip)]
+    pub last_ingested: DateTime<Utc>,  // Newest chunk timestamp, read from the file index
+}
+
+type MetadataPredicate<'a> = Box<dyn Fn(&ChunkMetadata) -> bool + 'a>;
+
+/// Version of the secondary index layout; stores indexed under an older one are
+/// re-indexed when opened
+const INDEX_VERSION: u8 = 3;
+
+/// Which chunks `Storage::scan` yields. File, chapter, tag, language and time constraints
+/// are answered from the secondary indexes; the predicate runs on the stored metadata
+/// before the chunk itself (content and embedding) is read.
+#[derive(Default)]
+pub struct ChunkQuery<'a> {
+    file: Option<FileFilter<'a>>,
+    chapter: Option<&'a str>,
+    language: Option<&'a str>,
+    tag: Option<&'a str>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    predicate: Option<MetadataPredicate<'a>>,
+}
+
+enum FileFilter<'a> {
+    Exact(&'a str),
+    Prefix(&'a str),
+}
+
+impl<'a> ChunkQuery<'a> {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, source_file: &'a str) -> Self {
+        self.file = Some(FileFilter::Exact(source_file));
+        self
+    }
+
+    /// Chunks of every source file whose path starts with `prefix`
+    pub fn with_file_prefix(mut self, prefix: &'a str) -> Self {
+        self.file = Some(FileFilter::Prefix(prefix));
+        self
+    }
+
+    pub fn with_chapter(mut self, chapter: &'a str) -> Self {
+        self.chapter = Some(chapter);
+        self
+    }
+
+    pub fn with_language(mut self, language: &'a str) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &'a str) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Chunks created within `[since, until)`; either bound may be open
+    pub fn with_time_range(mut self, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    pub fn with_predicate(mut self, predicate: impl Fn(&ChunkMetadata) -> bool + 'a) -> Self {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    /// Index entries can be stale after a crash, so candidates are checked again
+    fn accepts(&self, metadata: &ChunkMetadata) -> bool {
+        let file_matches = match self.file {
+            Some(FileFilter::Exact(file)) => metadata.source_file == file,
+            Some(FileFilter::Prefix(prefix)) => metadata.source_file.starts_with(prefix),
+            None => true,
+        };
+        file_matches
+            && self.chapter.is_none_or(|chapter| metadata.chapter.as_deref() == Some(chapter))
+            && self.language.is_none_or(|language| metadata.language.as_deref() == Some(language))
+            && self.tag.is_none_or(|tag| metadata.tags.iter().any(|t| t == tag))
+            && self.since.is_none_or(|since| metadata.timestamp >= since)
+            && self.until.is_none_or(|until| metadata.timestamp < until)
+            && self.predicate.as_ref().is_none_or(|predicate| predicate(metadata))
+    }
+}
+
+/// Metadata constraints a search can be restricted to, as given to the search tools. Time
+/// bounds take anything `parse_time_bound` accepts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataFilter {
+    pub source_file: Option<String>,
+    pub chapter: Option<String>,
+    pub language: Option<String>,
+    pub tag: Option<String>,
+    #[serde(default, alias = "since", deserialize_with = "deserialize_time_bound")]
+    pub ingested_after: Option<DateTime<Utc>>,    // Ingested at or after
+    #[serde(default, alias = "until", deserialize_with = "deserialize_time_bound")]
+    pub ingested_before: Option<DateTime<Utc>>,   // Ingested before
+    #[serde(default, deserialize_with = "deserialize_time_bound")]
+    pub dated_after: Option<DateTime<Utc>>,       // Document date at or after; undated chunks never match
+    #[serde(default, deserialize_with = "deserialize_time_bound")]
+    pub dated_before: Option<DateTime<Utc>>,      // Document date before
+}
+
+impl MetadataFilter {
+    /// Ingestion times are answered from the time index; document dates are not indexed
+    /// and are checked on each candidate's metadata
+    pub fn query(&self) -> ChunkQuery<'_> {
+        let mut query = ChunkQuery::all().with_time_range(self.ingested_after, self.ingested_before);
+        query.file = self.source_file.as_deref().map(FileFilter::Exact);
+        query.chapter = self.chapter.as_deref();
+        query.language = self.language.as_deref();
+        query.tag = self.tag.as_deref();
+        if self.dated_after.is_some() || self.dated_before.is_some() {
+            let (after, before) = (self.dated_after, self.dated_before);
+            query = query.with_predicate(move |metadata| metadata.document_date().is_some_and(|date| {
+                after.is_none_or(|after| date >= after) && before.is_none_or(|before| date < before)
+            }));
+        }
+        query
+    }
+}
+
+/// A filter time: anything `parse_date` accepts (RFC 3339, or a date such as 2024-03-01 for
+/// midnight UTC), or an age before `now` such as "36h", "7d" or "2w"
+pub fn parse_time_bound(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Some(time) = parse_date(text) {
+        return Ok(time);
+    }
+    let text = text.trim();
+    let split = text.len() - text.chars().last().map_or(0, |unit| unit.len_utf8());
+    let age = text[..split].parse::<i64>().ok().filter(|amount| *amount >= 0).and_then(|amount| match &text[split..] {
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => None,
+    });
+    age.and_then(|age| now.checked_sub_signed(age))
+        .ok_or_else(|| anyhow::anyhow!("Invalid time '{}': expected RFC 3339, a date (2024-03-01) or an age (7d, 12h, 2w)", text))
+}
+
+fn deserialize_time_bound<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|text| parse_time_bound(&text, Utc::now()).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Secondary indexes over the chunk store, kept in trees of the metadata database. Keys
+/// are NUL-separated and end with the chunk id: `source_file \0 timestamp \0 id`,
+/// `source_file \0 chapter \0 id`, `language \0 id`, `tag \0 id` and `timestamp \0 id`,
+/// with timestamps formatted to sort chronologically.
+struct SecondaryIndexes {
+    by_file: sled::Tree,
+    by_chapter: sled::Tree,
+    by_language: sled::Tree,
+    by_tag: sled::Tree,
+    by_time: sled::Tree,
+    info: sled::Tree,
+}
+
+impl SecondaryIndexes {
+    fn open(metadata_store: &sled::Db) -> Result<Self> {
+        Ok(Self {
+            by_file: metadata_store.open_tree("by_file")?,
+            by_chapter: metadata_store.open_tree("by_chapter")?,
+            by_language: metadata_store.open_tree("by_language")?,
+            by_tag: metadata_store.open_tree("by_tag")?,
+            by_time: metadata_store.open_tree("by_time")?,
+            info: metadata_store.open_tree("index_info")?,
+        })
+    }
+
+    fn is_current(&self) -> Result<bool> {
+        Ok(self.info.get("version")?.is_some_and(|version| version.as_ref() == [INDEX_VERSION]))
+    }
+
+    /// Start over before indexing the store under the current layout
+    fn reset(&self) -> Result<()> {
+        for tree in [&self.by_file, &self.by_chapter, &self.by_language, &self.by_tag, &self.by_time] {
+            tree.clear()?;
+        }
+        Ok(())
+    }
+
+    fn mark_current(&self) -> Result<()> {
+        self.info.insert("version", &[INDEX_VERSION])?;
+        Ok(())
+    }
+
+    /// `value \0 chunk_id`; with an empty id, the prefix of every entry for `value`
+    fn value_key(value: &str, chunk_id: &str) -> Vec<u8> {
+        [value, "\0", chunk_id].concat().into_bytes()
+    }
+
+    fn file_key(file: &str, timestamp: &DateTime<Utc>, chunk_id: &str) -> Vec<u8> {
+        [file, "\0", &Self::sortable_time(timestamp), "\0", chunk_id].concat().into_bytes()
+    }
+
+    fn chapter_key(file: &str, chapter: &str, chunk_id: &str) -> Vec<u8> {
+        [file, "\0", chapter, "\0", chunk_id].concat().into_bytes()
+    }
+
+    fn time_key(timestamp: &DateTime<Utc>, chunk_id: &str) -> Vec<u8> {
+        Self::value_key(&Self::sortable_time(timestamp), chunk_id)
+    }
+
+    fn sortable_time(timestamp: &DateTime<Utc>) -> String {
+        timestamp.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+    }
+
+    /// Every index entry for a chunk
+    fn entries(&self, chunk_id: &str, metadata: &ChunkMetadata) -> Vec<(&sled::Tree, Vec<u8>)> {
+        let mut entries = vec![
+            (&self.by_file, Self::file_key(&metadata.source_file, &metadata.timestamp, chunk_id)),
+            (&self.by_time, Self::time_key(&metadata.timestamp, chunk_id)),
+        ];
+        if let Some(chapter) = &metadata.chapter {
+            entries.push((&self.by_chapter, Self::chapter_key(&metadata.source_file, chapter, chunk_id)));
+        }
+        if let Some(language) = &metadata.language {
+            entries.push((&self.by_language, Self::value_key(language, chunk_id)));
+        }
+        for tag in &metadata.tags {
+            entries.push((&self.by_tag, Self::value_key(tag, chunk_id)));
+        }
+        entries
+    }
+
+    fn contains(&self, chunk_id: &str, metadata: &ChunkMetadata) -> Result<bool> {
+        Ok(self.by_file.contains_key(Self::file_key(&metadata.source_file, &metadata.timestamp, chunk_id))?)
+    }
+
+    /// Timestamp of the newest chunk of a file: the last entry under its prefix
+    fn newest(&self, file: &str) -> Result<Option<DateTime<Utc>>> {
+        let Some(entry) = self.by_file.scan_prefix(Self::value_key(file, "")).next_back() else {
+            return Ok(None);
+        };
+        let (key, _) = entry?;
+        let time = key[file.len() + 1..].split(|&b| b == 0).next().unwrap_or_default();
+        Ok(DateTime::parse_from_rfc3339(&String::from_utf8_lossy(time)).ok().map(|time| time.with_timezone(&Utc)))
+    }
+
+    fn insert(&self, chunk_id: &str, metadata: &ChunkMetadata) -> Result<()> {
+        for (tree, key) in self.entries(chunk_id, metadata) {
+            tree.insert(key, &[])?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, chunk_id: &str, metadata: &ChunkMetadata) -> Result<()> {
+        for (tree, key) in self.entries(chunk_id, metadata) {
+            tree.remove(key)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the entries of a chunk whose metadata changed
+    fn update(&self, chunk_id: &str, previous: &ChunkMetadata, metadata: &ChunkMetadata) -> Result<()> {
+        let current: Vec<Vec<u8>> = self.entries(chunk_id, metadata).into_iter().map(|(_, key)| key).collect();
+        for (tree, key) in self.entries(chunk_id, previous) {
+            if !current.contains(&key) {
+                tree.remove(key)?;
+            }
+        }
+        self.insert(chunk_id, metadata)
+    }
+
+    /// Candidate chunk ids for a query from its most selective indexed constraint, or
+    /// None when it has none and the whole store must be scanned
+    fn candidates<'a>(&self, query: &ChunkQuery) -> Option<Box<dyn Iterator<Item = sled::IVec> + 'a>> {
+        let ids: Box<dyn Iterator<Item = sled::IVec> + 'a> = match (&query.file, query.chapter) {
+            (Some(FileFilter::Exact(file)), Some(chapter)) => Box::new(Self::ids(self.by_chapter.scan_prefix(Self::chapter_key(file, chapter, "")))),
+            (Some(FileFilter::Exact(file)), None) => Box::new(Self::ids(self.by_file.scan_prefix(Self::value_key(file, "")))),
+            (Some(FileFilter::Prefix(prefix)), _) => Box::new(Self::ids(self.by_file.scan_prefix(prefix.as_bytes()))),
+            (None, _) => match (query.tag, query.language) {
+                (Some(tag), _) => Box::new(Self::ids(self.by_tag.scan_prefix(Self::value_key(tag, "")))),
+                (None, Some(language)) => Box::new(Self::ids(self.by_language.scan_prefix(Self::value_key(language, "")))),
+                (None, None) if query.since.is_some() || query.until.is_some() => {
+                    let start = query.since.map(|since| Self::time_key(&since, "")).unwrap_or_default();
+                    match query.until {
+                        Some(until) => Box::new(Self::ids(self.by_time.range(start..Self::time_key(&until, "")))),
+                        None => Box::new(Self::ids(self.by_time.range(start..))),
+                    }
+                }
+                (None, None) => return None,
+            },
+        };
+        Some(ids)
+    }
+
+    /// Chunk ids from index keys; the id is the key's last component
+    fn ids(entries: sled::Iter) -> impl Iterator<Item = sled::IVec> {
+        entries.keys().filter_map(|key| {
+            let key = key.ok()?;
+            let start = key.iter().rposition(|&b| b == 0).map_or(0, |i| i + 1);
+            Some(sled::IVec::from(&key[start..]))
+        })
+    }
+
+    /// Distinct key components following `prefix`, seeking past each one rather than
+    /// visiting every entry, so the cost grows with the number of components, not chunks
+    fn distinct(tree: &sled::Tree, prefix: &[u8]) -> Result<Vec<String>> {
+        let mut components = Vec::new();
+        let mut start = prefix.to_vec();
+        while let Some(entry) = tree.range(start.as_slice()..).next() {
+            let (key, _) = entry?;
+            let Some(rest) = key.strip_prefix(prefix) else { break };
+            let component = &rest[..rest.iter().position(|&b| b == 0).unwrap_or(rest.len())];
+            components.push(String::from_utf8_lossy(component).into_owned());
+            start = [prefix, component, &[1]].concat();
+        }
+        Ok(components)
+    }
+}
+
 pub struct Storage {
     chunk_store: sled::Db,
     metadata_store: sled::Db,
-    embeddings: Arc<RwLock<HashMap<String, Vec<f32>>>>, // Thread-safe in-memory cache
+    indexes: SecondaryIndexes,
+    documents: sled::Tree,  // source_file -> DocumentStats, in the metadata database
+    embeddings: Arc<RwLock<HashMap<String, CachedEmbedding>>>, // Thread-safe in-memory cache
+    embedding_storage: EmbeddingStorage,
+    sparse_store: sled::Tree,  // chunk_id -> learned sparse vector, in the metadata database
+    sparse: Arc<RwLock<HashMap<String, SparseVector>>>,
     data_dir: std::path::PathBuf,
 }
 
@@ -26,6 +360,12 @@ impl Storage {
     }
 
     pub fn new_with_instance(data_dir: &Path, instance_id: Option<&str>) -> Result<Self> {
+        Self::open(data_dir, instance_id, EmbeddingStorage::default())
+    }
+
+    /// Open the store keeping embeddings per `embedding_storage`. Switching to int8 rewrites
+    /// chunks still holding f32 embeddings; switching back leaves int8 records as they are.
+    pub fn open(data_dir: &Path, instance_id: Option<&str>, embedding_storage: EmbeddingStorage) -> Result<Self> {
         // If instance_id is provided, create a subdirectory for this instance
         // This allows multiple MCP servers to run with isolated databases
         let effective_data_dir = if let Some(id) = instance_id {
@@ -48,47 +388,96 @@ impl Storage {
             .flush_every_ms(Some(100))
             .cache_capacity(32 * 1024 * 1024);  // 32MB cache
 
-        let chunk_store = chunk_config.open()
-            .map_err(|e| anyhow!("Failed to open chunk store at {:?}: {}. Is another instance already running with the same data_dir?", effective_data_dir.join("chunks"), e))?;
-        let metadata_store = metadata_config.open()
-            .map_err(|e| anyhow!("Failed to open metadata store at {:?}: {}. Is another instance already running with the same data_dir?", effective_data_dir.join("metadata"), e))?;
+        // A crash can leave a corrupted tree behind; verify and quarantine instead of failing startup
+        let (chunk_store, _) = open_sled_checked(&chunk_config, &effective_data_dir.join("chunks"), "chunk")?;
+        let (metadata_store, _) = open_sled_checked(&metadata_config, &effective_data_dir.join("metadata"), "metadata")?;
 
-        // Load existing embeddings from disk into memory cache
+        // Load existing embeddings from disk into memory cache, indexing chunks stored
+        // before the secondary indexes (or the current layout of them) existed
+        let indexes = SecondaryIndexes::open(&metadata_store)?;
+        let reindex = !indexes.is_current()?;
+        if reindex {
+            indexes.reset()?;
+        }
         let mut embeddings = HashMap::new();
+        let mut documents: HashMap<String, DocumentStats> = HashMap::new();
+        let mut quantized = 0;
         for chunk_result in chunk_store.iter() {
-            if let Ok((chunk_id, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
+            if let Ok((chunk_id, mut chunk_data)) = chunk_result {
+                if let Ok((chunk, int8)) = quantize::decode_record(&chunk_data) {
+                    if embedding_storage.int8 && !int8 && !chunk.embedding.is_empty() {
+                        chunk_data = quantize::encode_chunk(&chunk, true)?.into();
+                        chunk_store.insert(&chunk_id, chunk_data.clone())?;
+                        quantized += 1;
+                    }
+                    let document = documents.entry(chunk.metadata.source_file.clone()).or_default();
+                    document.chunks += 1;
+                    document.bytes += chunk_data.len() as u64;
+                    if reindex || !indexes.contains(&chunk.id, &chunk.metadata)? {
+                        indexes.insert(&chunk.id, &chunk.metadata)?;
+                    }
                     if !chunk.embedding.is_empty() {
                         embeddings.insert(
                             String::from_utf8_lossy(&chunk_id).to_string(),
-                            chunk.embedding.clone()
+                            CachedEmbedding::new(&chunk.embedding, embedding_storage)
                         );
                     }
                 }
             }
         }
+        if quantized > 0 {
+            chunk_store.flush()?;
+            tracing::info!("Migrated {} stored embeddings to int8", quantized);
+        }
+
+        if reindex {
+            indexes.mark_current()?;
+        }
+
+        // Document aggregates are recomputed from the pass above, undoing any drift a crash
+        // between a chunk write and its aggregate update left behind
+        let documents_tree = metadata_store.open_tree("documents")?;
+        documents_tree.clear()?;
+        for (source_file, stats) in &documents {
+            documents_tree.insert(source_file.as_bytes(), serde_json::to_vec(stats)?)?;
+        }
+
+        let sparse_store = metadata_store.open_tree("sparse")?;
+        let sparse = sparse_store.iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|(chunk_id, vector)| Some((String::from_utf8_lossy(&chunk_id).to_string(), serde_json::from_slice(&vector).ok()?)))
+            .collect();
 
         Ok(Self {
             chunk_store,
             metadata_store,
+            indexes,
+            documents: documents_tree,
             embeddings: Arc::new(RwLock::new(embeddings)),
+            embedding_storage,
+            sparse_store,
+            sparse: Arc::new(RwLock::new(sparse)),
             data_dir: effective_data_dir,
         })
     }
 
     pub fn store_chunk(&self, chunk: &Chunk) -> Result<()> {
         // Store chunk content
-        let chunk_data = serde_json::to_vec(chunk)?;
-        self.chunk_store.insert(&chunk.id, chunk_data)?;
+        let chunk_data = quantize::encode_chunk(chunk, self.embedding_storage.int8)?;
+        let bytes = chunk_data.len() as u64;
+        let previous_bytes = self.chunk_store.insert(&chunk.id, chunk_data)?.map_or(0, |previous| previous.len() as u64);
 
-        // Store metadata separately for faster lookup
-        let metadata = serde_json::to_vec(&chunk.metadata)?;
-        self.metadata_store.insert(&chunk.id, metadata)?;
+        // Store metadata separately for faster lookup; an update may change indexed fields,
+        // leaving index entries behind
+        match self.write_metadata(&chunk.id, Some((&chunk.metadata, bytes)), previous_bytes)? {
+            Some(previous) => self.indexes.update(&chunk.id, &previous, &chunk.metadata)?,
+            None => self.indexes.insert(&chunk.id, &chunk.metadata)?,
+        }
 
         // Store embedding in memory cache (thread-safe)
         if !chunk.embedding.is_empty() {
             let mut embeddings = self.embeddings.write().unwrap();
-            embeddings.insert(chunk.id.clone(), chunk.embedding.clone());
+            embeddings.insert(chunk.id.clone(), CachedEmbedding::new(&chunk.embedding, self.embedding_storage));
         }
 
         // Sled handles its own flushing, no need to call flush explicitly
@@ -97,22 +486,164 @@ impl Storage {
         Ok(())
     }
 
+    /// Bytes `chunk` would take in the chunk store
+    pub fn encoded_size(&self, chunk: &Chunk) -> Result<u64> {
+        Ok(quantize::encode_chunk(chunk, self.embedding_storage.int8)?.len() as u64)
+    }
+
+    /// Delete a chunk and its index entries; false if it was not stored
+    pub fn remove_chunk(&self, chunk_id: &str) -> Result<bool> {
+        let Some(data) = self.chunk_store.remove(chunk_id)? else {
+            return Ok(false);
+        };
+        let metadata = self.write_metadata(chunk_id, None, data.len() as u64)?;
+        if let Some(metadata) = metadata.or_else(|| quantize::decode_chunk(&data).ok().map(|chunk| chunk.metadata)) {
+            self.indexes.remove(chunk_id, &metadata)?;
+        }
+        self.embeddings.write().unwrap().remove(chunk_id);
+        self.sparse_store.remove(chunk_id)?;
+        self.sparse.write().unwrap().remove(chunk_id);
+        Ok(true)
+    }
+
+    /// Keep a chunk's learned sparse vector, replacing any earlier one
+    pub fn store_sparse(&self, chunk_id: &str, vector: &SparseVector) -> Result<()> {
+        self.sparse_store.insert(chunk_id.as_bytes(), serde_json::to_vec(vector)?)?;
+        self.sparse.write().unwrap().insert(chunk_id.to_string(), vector.clone());
+        Ok(())
+    }
+
+    /// Chunks whose sparse vectors share weighted terms with `query`, by dot product, best
+    /// first; restricted to `chunk_ids` when given
+    pub fn search_sparse(&self, query: &SparseVector, chunk_ids: Option<&HashSet<String>>, top_k: usize) -> Vec<SearchResult> {
+        let mut scores: Vec<(String, f32)> = {
+            let sparse = self.sparse.read().unwrap();
+            sparse.iter()
+                .filter(|(chunk_id, _)| chunk_ids.is_none_or(|ids| ids.contains(*chunk_id)))
+                .map(|(chunk_id, vector)| (chunk_id.clone(), vector.dot(query)))
+                .filter(|(_, score)| *score > 0.0)
+                .collect()
+        };
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores.into_iter()
+            .take(top_k)
+            .filter_map(|(chunk_id, score)| self.get_search_result(&chunk_id, score))
+            .collect()
+    }
+
+    pub fn count_sparse(&self) -> usize {
+        self.sparse.read().unwrap().len()
+    }
+
+    /// Replace (or with None, delete) a chunk's metadata record and move its share of the
+    /// document aggregates in the same transaction. Returns the previous metadata.
+    fn write_metadata(&self, chunk_id: &str, metadata: Option<(&ChunkMetadata, u64)>, previous_bytes: u64) -> Result<Option<ChunkMetadata>> {
+        let record = metadata.map(|(metadata, _)| serde_json::to_vec(metadata)).transpose()?;
+        let metadata_tree: &sled::Tree = &self.metadata_store;
+
+        let result: Result<Option<ChunkMetadata>, TransactionError<serde_json::Error>> = (metadata_tree, &self.documents).transaction(|(metadata_tree, documents)| {
+            let previous = match &record {
+                Some(record) => metadata_tree.insert(chunk_id.as_bytes(), record.as_slice())?,
+                None => metadata_tree.remove(chunk_id.as_bytes())?,
+            };
+            let previous = previous.and_then(|previous| serde_json::from_slice::<ChunkMetadata>(&previous).ok());
+
+            if let Some(previous) = &previous {
+                Self::update_document(documents, &previous.source_file, |stats| {
+                    stats.chunks = stats.chunks.saturating_sub(1);
+                    stats.bytes = stats.bytes.saturating_sub(previous_bytes);
+                })?;
+            }
+            if let Some((metadata, bytes)) = metadata {
+                Self::update_document(documents, &metadata.source_file, |stats| {
+                    stats.chunks += 1;
+                    stats.bytes += bytes;
+                })?;
+            }
+            Ok(previous)
+        });
+        result.map_err(|e| anyhow::anyhow!("Failed to update metadata of chunk {}: {:?}", chunk_id, e))
+    }
+
+    fn update_document(documents: &TransactionalTree, source_file: &str, update: impl Fn(&mut DocumentStats)) -> Result<(), ConflictableTransactionError<serde_json::Error>> {
+        let mut stats = match documents.get(source_file.as_bytes())? {
+            Some(stats) => serde_json::from_slice(&stats).map_err(ConflictableTransactionError::Abort)?,
+            None => DocumentStats::default(),
+        };
+        update(&mut stats);
+        if stats.chunks == 0 {
+            documents.remove(source_file.as_bytes())?;
+        } else {
+            documents.insert(source_file.as_bytes(), serde_json::to_vec(&stats).map_err(ConflictableTransactionError::Abort)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn document_stats(&self, source_file: &str) -> Result<Option<DocumentStats>> {
+        match self.documents.get(source_file)? {
+            Some(stats) => Ok(Some(self.read_document(source_file, &stats)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Aggregates of every stored document, ordered by source file
+    pub fn list_documents(&self) -> Result<Vec<(String, DocumentStats)>> {
+        self.documents.iter()
+            .map(|entry| {
+                let (source_file, stats) = entry?;
+                let source_file = String::from_utf8_lossy(&source_file).into_owned();
+                let stats = self.read_document(&source_file, &stats)?;
+                Ok((source_file, stats))
+            })
+            .collect()
+    }
+
+    fn read_document(&self, source_file: &str, stats: &[u8]) -> Result<DocumentStats> {
+        let mut stats: DocumentStats = serde_json::from_slice(stats)?;
+        stats.last_ingested = self.indexes.newest(source_file)?.unwrap_or_default();
+        Ok(stats)
+    }
+
     pub fn get_chunk(&self, chunk_id: &str) -> Result<Option<Chunk>> {
         if let Some(data) = self.chunk_store.get(chunk_id)? {
-            let chunk: Chunk = serde_json::from_slice(&data)?;
-            Ok(Some(chunk))
+            Ok(Some(quantize::decode_chunk(&data)?))
         } else {
             Ok(None)
         }
     }
 
     pub fn search_similar(&self, query_embedding: &[f32], top_k: usize) -> Vec<SearchResult> {
+        self.rank_similar(query_embedding, top_k, |_| true)
+    }
+
+    /// Vector search restricted to the given chunks, e.g. those a metadata filter selected
+    pub fn search_similar_in(&self, query_embedding: &[f32], chunk_ids: &HashSet<String>, top_k: usize) -> Vec<SearchResult> {
+        self.rank_similar(query_embedding, top_k, |chunk_id| chunk_ids.contains(chunk_id))
+    }
+
+    fn rank_similar(&self, query_embedding: &[f32], top_k: usize, eligible: impl Fn(&str) -> bool) -> Vec<SearchResult> {
         let mut similarities = Vec::new();
 
         // Read lock on embeddings cache for concurrent access
         let embeddings = self.embeddings.read().unwrap();
-        for (chunk_id, embedding) in embeddings.iter() {
-            let similarity = self.cosine_similarity(query_embedding, embedding);
+        let mut candidates: Vec<(&String, &CachedEmbedding)> = embeddings.iter().filter(|(chunk_id, _)| eligible(chunk_id)).collect();
+
+        // Hamming prefilter: only the nearest top_k * oversample by sign bits are scored exactly
+        if let Some(oversample) = self.embedding_storage.binary_oversample {
+            let keep = top_k.saturating_mul(oversample);
+            if keep > 0 && candidates.len() > keep {
+                let query_bits = BinaryEmbedding::from_vector(query_embedding);
+                let distance = |embedding: &CachedEmbedding| embedding.bits.as_ref().map_or(u32::MAX, |bits| bits.hamming(&query_bits));
+                candidates.select_nth_unstable_by_key(keep - 1, |(_, embedding)| distance(embedding));
+                candidates.truncate(keep);
+            }
+        }
+
+        for (chunk_id, embedding) in candidates {
+            let similarity = match &embedding.vector {
+                CachedVector::F32(embedding) => self.cosine_similarity(query_embedding, embedding),
+                CachedVector::Int8(embedding) => embedding.cosine_similarity(query_embedding),
+            };
             similarities.push((chunk_id.clone(), similarity));
         }
         drop(embeddings); // Release read lock early
@@ -124,17 +655,20 @@ impl Storage {
         similarities
             .into_iter()
             .take(top_k)
-            .filter_map(|(chunk_id, score)| {
-                self.get_chunk(&chunk_id).ok().flatten().map(|chunk| SearchResult {
-                    chunk_id: chunk.id,
-                    score,
-                    content: chunk.content,
-                    metadata: self.chunk_metadata_to_map(&chunk.metadata),
-                })
-            })
+            .filter_map(|(chunk_id, score)| self.get_search_result(&chunk_id, score))
             .collect()
     }
 
+    /// A stored chunk as a search result with the given score
+    pub fn get_search_result(&self, chunk_id: &str, score: f32) -> Option<SearchResult> {
+        self.get_chunk(chunk_id).ok().flatten().map(|chunk| SearchResult {
+            chunk_id: chunk.id,
+            score,
+            content: chunk.content,
+            metadata: self.chunk_metadata_to_map(&chunk.metadata),
+        })
+    }
+
     pub fn search_by_text(&self, query: &str, top_k: usize) -> Vec<SearchResult> {
         let mut results = Vec::new();
         let mut total_chunks = 0;
@@ -145,10 +679,10 @@ impl Storage {
         for chunk_result in self.chunk_store.iter() {
             eprintln!("Debug: Got chunk_result: {:?}", chunk_result.is_ok());
             if let Ok((chunk_id, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
+                if let Ok(chunk) = quantize::decode_chunk(&chunk_data) {
                     total_chunks += 1;
                     // Simple text matching - in production would use better text search
-                    let score = self.text_similarity(&chunk.content, query);
+                    let score = self.text_similarity(&chunk.content, query, chunk.metadata.language.as_deref());
                     eprintln!("Debug: Chunk {} score: {} (content preview: {})",
                              String::from_utf8_lossy(&chunk_id), score,
                              &chunk.content.chars().take(50).collect::<String>());
@@ -171,18 +705,42 @@ impl Storage {
         results.into_iter().take(top_k).collect()
     }
 
-    pub fn get_chunks_by_file(&self, file_path: &str) -> Result<Vec<Chunk>> {
-        let mut chunks = Vec::new();
+    /// Stream the chunks selected by `query` without loading the rest of the store
+    pub fn scan<'a>(&'a self, query: ChunkQuery<'a>) -> impl Iterator<Item = Chunk> + 'a {
+        let ids = self.indexes.candidates(&query)
+            .unwrap_or_else(|| Box::new(self.chunk_store.iter().keys().filter_map(|key| key.ok())));
 
-        for chunk_result in self.chunk_store.iter() {
-            if let Ok((_, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
-                    if chunk.metadata.source_file == file_path {
-                        chunks.push(chunk);
-                    }
-                }
+        ids.filter_map(move |chunk_id| {
+            let metadata = self.stored_metadata(&chunk_id);
+            if metadata.as_ref().is_some_and(|metadata| !query.accepts(metadata)) {
+                return None;
             }
-        }
+            let chunk = self.chunk_store.get(&chunk_id).ok().flatten()
+                .and_then(|data| quantize::decode_chunk(&data).ok())?;
+            (metadata.is_some() || query.accepts(&chunk.metadata)).then_some(chunk)
+        })
+    }
+
+    /// Ids of the chunks selected by `query`, decided from metadata alone
+    pub fn matching_ids<'a>(&'a self, query: ChunkQuery<'a>) -> impl Iterator<Item = String> + 'a {
+        let ids = self.indexes.candidates(&query)
+            .unwrap_or_else(|| Box::new(self.chunk_store.iter().keys().filter_map(|key| key.ok())));
+
+        ids.filter(move |chunk_id| {
+            let metadata = self.stored_metadata(chunk_id)
+                .or_else(|| self.get_chunk(&String::from_utf8_lossy(chunk_id)).ok().flatten().map(|chunk| chunk.metadata));
+            metadata.is_some_and(|metadata| query.accepts(&metadata))
+        })
+        .map(|chunk_id| String::from_utf8_lossy(&chunk_id).into_owned())
+    }
+
+    fn stored_metadata(&self, chunk_id: &[u8]) -> Option<ChunkMetadata> {
+        self.metadata_store.get(chunk_id).ok().flatten()
+            .and_then(|data| serde_json::from_slice::<ChunkMetadata>(&data).ok())
+    }
+
+    pub fn get_chunks_by_file(&self, file_path: &str) -> Result<Vec<Chunk>> {
+        let mut chunks: Vec<Chunk> = self.scan(ChunkQuery::all().with_file(file_path)).collect();
 
         // Sort by boundaries to maintain order
         chunks.sort_by_key(|c| c.boundaries.0);
@@ -190,56 +748,41 @@ impl Storage {
     }
 
     pub fn get_chunks_by_chapter(&self, file_path: &str, chapter: &str) -> Result<Vec<Chunk>> {
-        let mut chunks = Vec::new();
-
-        for chunk_result in self.chunk_store.iter() {
-            if let Ok((_, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
-                    if chunk.metadata.source_file == file_path {
-                        if let Some(chunk_chapter) = &chunk.metadata.chapter {
-                            if chunk_chapter == chapter {
-                                chunks.push(chunk);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
+        let mut chunks: Vec<Chunk> = self.scan(ChunkQuery::all().with_file(file_path).with_chapter(chapter)).collect();
         chunks.sort_by_key(|c| c.boundaries.0);
         Ok(chunks)
     }
 
     pub fn list_files(&self) -> Result<Vec<String>> {
-        let mut files = std::collections::HashSet::new();
-
-        for chunk_result in self.chunk_store.iter() {
-            if let Ok((_, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
-                    files.insert(chunk.metadata.source_file);
-                }
-            }
-        }
-
-        Ok(files.into_iter().collect())
+        SecondaryIndexes::distinct(&self.indexes.by_file, &[])
     }
 
     pub fn list_chapters(&self, file_path: &str) -> Result<Vec<String>> {
-        let mut chapters = std::collections::HashSet::new();
+        SecondaryIndexes::distinct(&self.indexes.by_chapter, &SecondaryIndexes::value_key(file_path, ""))
+    }
 
-        for chunk_result in self.chunk_store.iter() {
-            if let Ok((_, chunk_data)) = chunk_result {
-                if let Ok(chunk) = serde_json::from_slice::<Chunk>(&chunk_data) {
-                    if chunk.metadata.source_file == file_path {
-                        if let Some(chapter) = &chunk.metadata.chapter {
-                            chapters.insert(chapter.clone());
-                        }
-                    }
-                }
-            }
+    /// Read a stored chunk back and flush both databases, failing when the store cannot be
+    /// read or written
+    pub fn check(&self) -> Result<()> {
+        if let Some(entry) = self.chunk_store.iter().next() {
+            let (_, data) = entry?;
+            quantize::decode_chunk(&data)?;
         }
+        self.chunk_store.flush()?;
+        self.metadata_store.flush()?;
+        Ok(())
+    }
+
+    pub fn count_chunks(&self) -> usize {
+        self.chunk_store.len()
+    }
 
-        Ok(chapters.into_iter().collect())
+    pub fn count_embeddings(&self) -> usize {
+        self.embeddings.read().unwrap().len()
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
     }
 
     fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
@@ -258,14 +801,15 @@ impl Storage {
         }
     }
 
-    fn text_similarity(&self, text: &str, query: &str) -> f32 {
+    fn text_similarity(&self, text: &str, query: &str, language: Option<&str>) -> f32 {
         // Enhanced text similarity with BM25-like scoring
-        let text_lower = text.to_lowercase();
-        let query_lower = query.to_lowercase();
-
-        // Tokenize both text and query
-        let text_words: Vec<&str> = text_lower.split_whitespace().collect();
-        let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+        // Tokenize both text and query, the way the chunk's human language splits words
+        let tokenize = |text: &str| match language.filter(|language| natural_language::is_natural(language)) {
+            Some(language) => natural_language::tokenize(text, language),
+            None => text.to_lowercase().split_whitespace().map(str::to_string).collect(),
+        };
+        let text_words: Vec<String> = tokenize(text);
+        let query_words: Vec<String> = tokenize(query);
 
         if query_words.is_empty() {
             return 0.0;
@@ -302,6 +846,12 @@ impl Storage {
         let mut map = HashMap::new();
         map.insert("source_file".to_string(), metadata.source_file.clone());
         map.insert("chunk_type".to_string(), format!("{:?}", metadata.chunk_type));
+        map.insert("line_start".to_string(), metadata.line_start.to_string());
+        map.insert("line_end".to_string(), metadata.line_end.to_string());
+        if let (Some(byte_start), Some(byte_end)) = (metadata.byte_start, metadata.byte_end) {
+            map.insert("byte_start".to_string(), byte_start.to_string());
+            map.insert("byte_end".to_string(), byte_end.to_string());
+        }
 
         if let Some(chapter) = &metadata.chapter {
             map.insert("chapter".to_string(), chapter.clone());
@@ -315,6 +865,181 @@ impl Storage {
             map.insert("language".to_string(), language.clone());
         }
 
+        if let Some(strategy) = &metadata.chunk_strategy {
+            map.insert("chunk_strategy".to_string(), format!("{:?}", strategy));
+        }
+
+        if let Some(page_start) = metadata.page_start {
+            map.insert("page_start".to_string(), page_start.to_string());
+            map.insert("page_end".to_string(), metadata.page_end.unwrap_or(page_start).to_string());
+        }
+
+        for (key, value) in [("title", &metadata.title), ("author", &metadata.author), ("date", &metadata.date)] {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value.clone());
+            }
+        }
+
+        for (key, value) in &metadata.attributes {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
         map
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    fn chunk(file: &str, chapter: Option<&str>, line: usize) -> Chunk {
+        let mut chunk = SemanticChunker::build_text_chunk("DMA reset sequence", file, "hash", (line, line), ChunkStrategy::NaturalSection);
+        chunk.metadata.chapter = chapter.map(String::from);
+        chunk.metadata.language = file.ends_with(".md").then(|| "markdown".to_string());
+        chunk.metadata.tags = vec![format!("line{}", line)];
+        chunk.metadata.timestamp = chrono::DateTime::from_timestamp(line as i64 * 60, 0).unwrap();
+        chunk.boundaries = (line, line);
+        chunk
+    }
+
+    #[test]
+    fn test_indexed_lookups_follow_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path()).unwrap();
+        let mut moved = chunk("dma.md", Some("Reset"), 3);
+        for chunk in [chunk("dma.md", Some("Reset"), 1), chunk("dma.md", Some("IRQ"), 2), chunk("dma.md.bak", None, 1), chunk("irq.md", None, 1), moved.clone()] {
+            storage.store_chunk(&chunk).unwrap();
+        }
+
+        assert_eq!(storage.list_files().unwrap(), vec!["dma.md", "dma.md.bak", "irq.md"]);
+        assert_eq!(storage.list_chapters("dma.md").unwrap(), vec!["IRQ", "Reset"]);
+        assert_eq!(storage.get_chunks_by_chapter("dma.md", "Reset").unwrap().len(), 2);
+
+        // Re-storing a chunk under another file drops it from the old file's entries
+        moved.metadata.source_file = "irq.md".to_string();
+        storage.store_chunk(&moved).unwrap();
+        let lines: Vec<usize> = storage.get_chunks_by_file("dma.md").unwrap().iter().map(|c| c.boundaries.0).collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert_eq!(storage.get_chunks_by_chapter("dma.md", "Reset").unwrap().len(), 1);
+
+        let in_irq = storage.scan(ChunkQuery::all().with_file_prefix("irq").with_predicate(|m| m.chapter.is_some())).count();
+        assert_eq!(in_irq, 1);
+        assert_eq!(storage.scan(ChunkQuery::all().with_chapter("IRQ")).count(), 1);
+
+        // Language, tag and time lookups, with updates moving entries along
+        let filter = MetadataFilter { language: Some("markdown".to_string()), ..Default::default() };
+        assert_eq!(storage.matching_ids(filter.query()).count(), 4);
+        moved.metadata.tags = vec!["moved".to_string()];
+        storage.store_chunk(&moved).unwrap();
+        assert_eq!(storage.scan(ChunkQuery::all().with_tag("line3")).count(), 0);
+        assert_eq!(storage.matching_ids(ChunkQuery::all().with_tag("moved")).collect::<Vec<_>>(), vec![moved.id.clone()]);
+        let minute = |m: i64| chrono::DateTime::from_timestamp(m * 60, 0);
+        assert_eq!(storage.matching_ids(ChunkQuery::all().with_time_range(minute(2), minute(3))).count(), 1);
+        assert_eq!(storage.matching_ids(ChunkQuery::all().with_time_range(minute(2), None)).count(), 2);
+
+        let filter: MetadataFilter = serde_json::from_value(serde_json::json!({"since": "1970-01-01T00:02:00Z", "ingested_before": "1970-01-01"})).unwrap();
+        assert_eq!((filter.ingested_after, filter.ingested_before), (minute(2), minute(0)));
+        assert_eq!(parse_time_bound("2d", minute(3 * 24 * 60).unwrap()).unwrap(), minute(24 * 60).unwrap());
+        assert!(parse_time_bound("soon", Utc::now()).is_err());
+
+        // Document dates are read from the stored metadata
+        let mut dated = chunk("notes.md", None, 4);
+        dated.metadata.date = Some("2024-03-01".to_string());
+        storage.store_chunk(&dated).unwrap();
+        let filter = MetadataFilter { dated_after: parse_date("2024-02-01"), dated_before: parse_date("2024-03-02"), ..Default::default() };
+        assert_eq!(storage.matching_ids(filter.query()).collect::<Vec<_>>(), vec![dated.id.clone()]);
+        assert!(storage.remove_chunk(&dated.id).unwrap());
+
+        let irq = storage.document_stats("irq.md").unwrap().unwrap();
+        assert_eq!((irq.chunks, irq.last_ingested), (2, minute(3).unwrap()));
+
+        assert!(storage.remove_chunk(&moved.id).unwrap());
+        assert_eq!(storage.matching_ids(ChunkQuery::all().with_tag("moved")).count(), 0);
+        assert_eq!(storage.list_chapters("irq.md").unwrap(), Vec::<String>::new());
+        assert_eq!((storage.count_chunks(), storage.count_embeddings()), (4, 0));
+
+        // Aggregates follow updates and deletes, and match a recount on reopening
+        let documents = storage.list_documents().unwrap();
+        let counts: Vec<(&str, usize)> = documents.iter().map(|(file, stats)| (file.as_str(), stats.chunks)).collect();
+        assert_eq!(counts, vec![("dma.md", 2), ("dma.md.bak", 1), ("irq.md", 1)]);
+        drop(storage);
+        assert_eq!(Storage::new(dir.path()).unwrap().list_documents().unwrap(), documents);
+    }
+
+    #[test]
+    fn test_int8_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path()).unwrap();
+        let mut near = chunk("dma.md", None, 1);
+        near.embedding = (0..384).map(|i| (i as f32 * 0.1).cos()).collect();
+        let mut far = chunk("irq.md", None, 2);
+        far.embedding = (0..384).map(|i| (i as f32 * 0.7).sin()).collect();
+        storage.store_chunk(&near).unwrap();
+        storage.store_chunk(&far).unwrap();
+        let f32_bytes = storage.document_stats("dma.md").unwrap().unwrap().bytes;
+        drop(storage);
+
+        // Reopening with int8 rewrites the f32 records; search still ranks on the cached int8 vectors
+        let int8 = EmbeddingStorage { int8: true, ..Default::default() };
+        let storage = Storage::open(dir.path(), None, int8).unwrap();
+        assert!(storage.document_stats("dma.md").unwrap().unwrap().bytes * 3 < f32_bytes);
+        let results = storage.search_similar(&near.embedding, 2);
+        assert_eq!(results[0].chunk_id, near.id);
+        assert!(results[0].score > 0.999);
+        let restored = storage.get_chunk(&near.id).unwrap().unwrap().embedding;
+        assert!(restored.iter().zip(&near.embedding).all(|(a, b)| (a - b).abs() < 0.01));
+        drop(storage);
+
+        // Switching back reads the int8 records as they are
+        let storage = Storage::new(dir.path()).unwrap();
+        assert_eq!(storage.count_embeddings(), 2);
+        assert_eq!(storage.get_chunk(&near.id).unwrap().unwrap().embedding, restored);
+    }
+
+    #[test]
+    fn test_binary_prefilter_keeps_nearest() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefilter = EmbeddingStorage { binary_oversample: Some(2), ..Default::default() };
+        let storage = Storage::open(dir.path(), None, prefilter).unwrap();
+        let vector = |seed: usize| -> Vec<f32> { (0..128).map(|i| ((i * (seed + 1)) as f32 * 0.37).sin()).collect() };
+        let mut stored = Vec::new();
+        for line in 0..20 {
+            let mut chunk = chunk("dma.md", None, line + 1);
+            chunk.embedding = vector(line);
+            storage.store_chunk(&chunk).unwrap();
+            stored.push(chunk);
+        }
+
+        // Only top_k * 2 candidates are scored, and the exact match survives the prefilter
+        let results = storage.search_similar(&vector(7), 3);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].chunk_id, stored[7].id);
+        assert!(results[0].score > 0.999);
+    }
+
+    #[test]
+    fn test_sparse_vectors_persist_and_rank() {
+        let dir = tempfile::tempdir().unwrap();
+        let sparse = |terms: &[(&str, f32)]| SparseVector::from_weights(terms.iter().map(|(term, weight)| (term.to_string(), *weight)), 16);
+        let (sequencer, driver) = (chunk("env.sv", None, 1), chunk("env.sv", None, 2));
+        {
+            let storage = Storage::new(dir.path()).unwrap();
+            for chunk in [&sequencer, &driver] {
+                storage.store_chunk(chunk).unwrap();
+            }
+            storage.store_sparse(&sequencer.id, &sparse(&[("sequencer", 2.0), ("uvm", 0.5)])).unwrap();
+            storage.store_sparse(&driver.id, &sparse(&[("driver", 2.0), ("uvm", 0.5)])).unwrap();
+        }
+
+        let storage = Storage::new(dir.path()).unwrap();
+        let query = sparse(&[("sequencer", 1.0), ("uvm", 1.0)]);
+        let ids = |results: Vec<SearchResult>| results.into_iter().map(|r| r.chunk_id).collect::<Vec<_>>();
+        assert_eq!(ids(storage.search_sparse(&query, None, 5)), vec![sequencer.id.clone(), driver.id.clone()]);
+        let only_driver: HashSet<String> = [driver.id.clone()].into_iter().collect();
+        assert_eq!(ids(storage.search_sparse(&query, Some(&only_driver), 5)), vec![driver.id.clone()]);
+
+        storage.remove_chunk(&sequencer.id).unwrap();
+        assert_eq!(storage.count_sparse(), 1);
+    }
+}
@@ -1,10 +1,22 @@
 pub mod embeddings;
+pub mod batching;
+pub mod providers;
+pub mod query_cache;
+#[cfg(feature = "transformer")]
+pub mod transformer;
 pub mod chunks;
 pub mod index;
+pub mod quantize;
+pub mod sparse;
+pub mod recovery;
+pub mod consistency;
+pub mod drift;
+pub mod reembed;
 pub mod sqlite_storage;
 
 // Export both implementations
 pub use index::Storage as SledStorage;
+pub use index::{ChunkQuery, MetadataFilter};
 pub use sqlite_storage::SqliteStorage;
 
 // Default to SQLite for multi-process support
@@ -0,0 +1,818 @@
+use super::batching::{self, BatchError, BatchLimits};
+use super::embeddings::{EmbeddingModel, EmbeddingPolicy};
+use super::query_cache::{QueryCache, QueryCacheStats};
+#[cfg(feature = "transformer")]
+use super::transformer::TransformerProvider;
+use crate::config::{EmbeddingConfig, EmbeddingProviderConfig};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Name of the built-in provider used when no providers are configured and the local backend
+/// has no transformer feature. Chunks without an `embedding_provider` attribute were embedded by it.
+pub const LOCAL_PROVIDER: &str = "local";
+
+/// Names of the default chain: the `embedding.backend` (for "local", `embedding.model_name` run
+/// with the transformer feature), then the deterministic model when it cannot serve
+pub const TRANSFORMER_PROVIDER: &str = "transformer";
+pub const FALLBACK_PROVIDER: &str = "fallback";
+
+/// Model recorded for vectors from the deterministic hash-based model
+pub const HASH_MODEL: &str = "hash";
+
+/// A source of embeddings, tried in order by `ProviderChain`
+pub trait EmbeddingProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn kind(&self) -> &'static str;
+
+    /// The model behind the provider, recorded on the chunks it embeds
+    fn model(&self) -> &str {
+        self.kind()
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError>;
+
+    /// Longest passage, in estimated tokens, the provider late-chunks; None when it embeds
+    /// every text on its own
+    fn max_passage_tokens(&self) -> Option<usize> {
+        None
+    }
+
+    /// Embed `chunks`, consecutive pieces of one passage, in the context of the whole passage
+    fn embed_passage(&self, chunks: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.embed_batch(chunks)
+    }
+
+    /// Whether the provider can serve requests; by default it must embed a probe string
+    fn health_check(&self) -> Result<()> {
+        let vectors = self.embed_batch(&["health check".to_string()]).map_err(|e| match e {
+            BatchError::Failed(e) => e,
+            BatchError::TooLarge => anyhow!("probe rejected as too large"),
+            BatchError::RateLimited { .. } => anyhow!("rate limited"),
+        })?;
+        match vectors.first() {
+            Some(vector) if !vector.is_empty() => Ok(()),
+            _ => Err(anyhow!("returned an empty embedding")),
+        }
+    }
+}
+
+/// The deterministic hash-based in-process model; never unavailable
+pub struct LocalProvider {
+    name: String,
+    model: EmbeddingModel,
+    max_passage_tokens: Option<usize>,
+}
+
+impl LocalProvider {
+    pub fn new(name: &str, model: EmbeddingModel) -> Self {
+        Self { name: name.to_string(), model, max_passage_tokens: None }
+    }
+
+    /// Pool chunk vectors from token vectors that see up to `max_passage_tokens` of context
+    pub fn with_late_chunking(mut self, max_passage_tokens: usize) -> Self {
+        self.max_passage_tokens = Some(max_passage_tokens);
+        self
+    }
+}
+
+impl EmbeddingProvider for LocalProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "local"
+    }
+
+    fn model(&self) -> &str {
+        HASH_MODEL
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.model.embed_batch(texts).map_err(BatchError::Failed)
+    }
+
+    fn max_passage_tokens(&self) -> Option<usize> {
+        self.max_passage_tokens
+    }
+
+    fn embed_passage(&self, chunks: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.model.embed_spans(chunks).map_err(BatchError::Failed)
+    }
+}
+
+/// An OpenAI-compatible `/embeddings` endpoint: `{"model", "input": [...]}` in,
+/// `{"data": [{"index", "embedding"}]}` out. With late chunking a passage's chunks are sent
+/// together with `"late_chunking": true`, as endpoints such as Jina's accept.
+pub struct HttpProvider {
+    name: String,
+    endpoint: String,
+    model: Option<String>,
+    api_key: Option<String>,
+    normalize: bool,
+    max_passage_tokens: Option<usize>,
+    agent: ureq::Agent,
+}
+
+impl HttpProvider {
+    pub fn from_config(config: &EmbeddingProviderConfig, policy: &EmbeddingPolicy) -> Result<Self> {
+        let endpoint = config.endpoint.clone()
+            .ok_or_else(|| anyhow!("Embedding provider '{}' needs an endpoint", config.name))?;
+        let api_key = match &config.api_key_env {
+            Some(variable) => Some(std::env::var(variable)
+                .map_err(|_| anyhow!("Embedding provider '{}': environment variable {} is not set", config.name, variable))?),
+            None => None,
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            endpoint,
+            model: config.model.clone(),
+            api_key,
+            normalize: policy.normalize,
+            max_passage_tokens: config.late_chunking.then_some(config.max_passage_tokens),
+            agent: pooled_agent(config),
+        })
+    }
+
+    fn parse_response(body: &str, expected: usize, normalize: bool) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        let invalid = |reason: &str| BatchError::Failed(anyhow!("Invalid embedding response: {}", reason));
+        let response: Value = serde_json::from_str(body).map_err(|e| invalid(&e.to_string()))?;
+        let data = response.get("data").and_then(|d| d.as_array()).ok_or_else(|| invalid("missing data array"))?;
+
+        let mut vectors: Vec<Option<Vec<f32>>> = vec![None; expected];
+        for (position, item) in data.iter().enumerate() {
+            let index = item.get("index").and_then(|i| i.as_u64()).map(|i| i as usize).unwrap_or(position);
+            let mut vector: Vec<f32> = item.get("embedding")
+                .and_then(|e| e.as_array())
+                .ok_or_else(|| invalid("item without an embedding"))?
+                .iter()
+                .map(|v| v.as_f64().map(|v| v as f32).ok_or_else(|| invalid("non-numeric embedding value")))
+                .collect::<std::result::Result<_, _>>()?;
+            if normalize {
+                EmbeddingModel::normalize_vector(&mut vector);
+            }
+            *vectors.get_mut(index).ok_or_else(|| invalid("index out of range"))? = Some(vector);
+        }
+
+        // A short response is reported as a count mismatch by the batching layer
+        Ok(vectors.into_iter().flatten().collect())
+    }
+
+    fn send(&self, texts: &[String], late_chunking: bool) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        let mut request = self.agent.post(&self.endpoint).set("Content-Type", "application/json");
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let mut body = json!({ "input": texts });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+        if late_chunking {
+            body["late_chunking"] = json!(true);
+        }
+
+        let response = send_json(request, &body, &self.endpoint)?;
+        Self::parse_response(&response, texts.len(), self.normalize)
+    }
+}
+
+/// An agent that keeps up to `max_connections` idle connections to the endpoint open, so
+/// consecutive batches reuse them instead of reconnecting
+fn pooled_agent(config: &EmbeddingProviderConfig) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .max_idle_connections_per_host(config.max_connections)
+        .build()
+}
+
+/// POST `body` and return the response body, mapping 413 and 429 so the batching layer
+/// splits or backs off
+fn send_json(request: ureq::Request, body: &Value, endpoint: &str) -> std::result::Result<String, BatchError> {
+    match request.send_string(&body.to_string()) {
+        Ok(response) => response.into_string().map_err(|e| BatchError::Failed(e.into())),
+        Err(ureq::Error::Status(status, response)) => {
+            let retry_after = response.header("Retry-After")
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            let body = response.into_string().unwrap_or_default();
+            Err(BatchError::from_status(status, retry_after, &body))
+        }
+        Err(ureq::Error::Transport(transport)) => {
+            Err(BatchError::Failed(anyhow!("Embedding request to {} failed: {}", endpoint, transport)))
+        }
+    }
+}
+
+impl EmbeddingProvider for HttpProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "http"
+    }
+
+    fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(&self.endpoint)
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.send(texts, false)
+    }
+
+    fn max_passage_tokens(&self) -> Option<usize> {
+        self.max_passage_tokens
+    }
+
+    fn embed_passage(&self, chunks: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.send(chunks, true)
+    }
+}
+
+/// A Hugging Face text-embeddings-inference server, or another self-hosted server with its
+/// API: `POST /embed` with `{"inputs": [...]}` returns one vector per input, and `GET /health`
+/// answers 200 once the model is loaded. The server truncates over-long inputs itself.
+pub struct TeiProvider {
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    normalize: bool,
+    agent: ureq::Agent,
+}
+
+impl TeiProvider {
+    pub fn from_config(config: &EmbeddingProviderConfig, policy: &EmbeddingPolicy) -> Result<Self> {
+        let endpoint = config.endpoint.as_deref()
+            .ok_or_else(|| anyhow!("Embedding provider '{}' needs an endpoint", config.name))?;
+        let api_key = match &config.api_key_env {
+            Some(variable) => Some(std::env::var(variable)
+                .map_err(|_| anyhow!("Embedding provider '{}': environment variable {} is not set", config.name, variable))?),
+            None => None,
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            // Accept the server root or its /embed route
+            base_url: endpoint.trim_end_matches('/').trim_end_matches("/embed").to_string(),
+            api_key,
+            normalize: policy.normalize,
+            agent: pooled_agent(config),
+        })
+    }
+
+    fn request(&self, request: ureq::Request) -> ureq::Request {
+        match &self.api_key {
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+
+    fn parse_response(body: &str, normalize: bool) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        let invalid = |reason: &str| BatchError::Failed(anyhow!("Invalid embedding response: {}", reason));
+        let mut vectors: Vec<Vec<f32>> = serde_json::from_str(body).map_err(|e| invalid(&e.to_string()))?;
+        // The server normalizes too when asked, but not every compatible server does
+        if normalize {
+            vectors.iter_mut().for_each(|vector| EmbeddingModel::normalize_vector(vector));
+        }
+        Ok(vectors)
+    }
+}
+
+impl EmbeddingProvider for TeiProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "tei"
+    }
+
+    /// The server decides the model; its address identifies it
+    fn model(&self) -> &str {
+        &self.base_url
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        let url = format!("{}/embed", self.base_url);
+        let request = self.request(self.agent.post(&url)).set("Content-Type", "application/json");
+        let body = json!({ "inputs": texts, "normalize": self.normalize, "truncate": true });
+        let response = send_json(request, &body, &url)?;
+        Self::parse_response(&response, self.normalize)
+    }
+
+    /// Ask the server instead of running the model on a probe
+    fn health_check(&self) -> Result<()> {
+        let url = format!("{}/health", self.base_url);
+        match self.request(self.agent.get(&url)).call() {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, _)) => Err(anyhow!("{} answered HTTP {}", url, status)),
+            Err(ureq::Error::Transport(transport)) => Err(anyhow!("{} is unreachable: {}", url, transport)),
+        }
+    }
+}
+
+/// Instruction templates some models are trained with, e.g. E5's "query: " and "passage: "
+/// or BGE's query instruction. "{text}" marks where the text goes; a template without it is
+/// a prefix.
+#[derive(Debug, Clone, Default)]
+pub struct Instructions {
+    query: Option<String>,
+    passage: Option<String>,
+}
+
+impl Instructions {
+    pub fn new(query: Option<&str>, passage: Option<&str>) -> Self {
+        let template = |template: Option<&str>| template.filter(|template| !template.is_empty()).map(String::from);
+        Self { query: template(query), passage: template(passage) }
+    }
+
+    pub fn queries<'a>(&self, texts: &'a [String]) -> Cow<'a, [String]> {
+        Self::apply(self.query.as_deref(), texts)
+    }
+
+    pub fn passages<'a>(&self, texts: &'a [String]) -> Cow<'a, [String]> {
+        Self::apply(self.passage.as_deref(), texts)
+    }
+
+    fn apply<'a>(template: Option<&str>, texts: &'a [String]) -> Cow<'a, [String]> {
+        match template {
+            Some(template) if template.contains("{text}") => Cow::Owned(texts.iter().map(|text| template.replace("{text}", text)).collect()),
+            Some(prefix) => Cow::Owned(texts.iter().map(|text| format!("{}{}", prefix, text)).collect()),
+            None => Cow::Borrowed(texts),
+        }
+    }
+}
+
+/// Availability of one provider as seen by the chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub name: String,
+    pub kind: &'static str,
+    pub available: bool,            // False while cooling down after a failure
+    pub served: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct ProviderState {
+    unavailable_until: Option<Instant>,
+    served: u64,
+    failures: u64,
+    last_error: Option<String>,
+}
+
+struct ProviderSlot {
+    provider: Box<dyn EmbeddingProvider>,
+    instructions: Instructions,
+    state: Mutex<ProviderState>,
+}
+
+impl ProviderSlot {
+    fn available(&self, now: Instant) -> bool {
+        self.state.lock().unwrap().unavailable_until.is_none_or(|until| now >= until)
+    }
+
+    fn record(&self, outcome: &Result<Vec<Vec<f32>>>, cooldown: Duration) {
+        let mut state = self.state.lock().unwrap();
+        match outcome {
+            Ok(_) => {
+                state.served += 1;
+                state.unavailable_until = None;
+            }
+            Err(e) => {
+                state.failures += 1;
+                state.unavailable_until = Some(Instant::now() + cooldown);
+                state.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Embedding providers in order of preference. Each request goes to the first available
+/// provider; one that fails is skipped for `cooldown` and the next provider serves instead.
+/// Vectors from different providers live in different spaces, so callers record which
+/// provider served each request and only compare vectors within one space.
+pub struct ProviderChain {
+    providers: Vec<ProviderSlot>,
+    limits: BatchLimits,
+    concurrency: usize,             // Batches of one document in flight at once
+    cooldown: Duration,
+    policy: EmbeddingPolicy,
+    query_cache: QueryCache,        // Recent query vectors from the primary provider
+}
+
+impl ProviderChain {
+    pub async fn from_config(config: &EmbeddingConfig, policy: EmbeddingPolicy) -> Result<Self> {
+        let (mut providers, mut instructions) = Self::build_providers(config, &config.providers, policy).await?;
+        if providers.is_empty() {
+            let model = EmbeddingModel::new(&config.model_name).await?.with_policy(policy);
+            match config.backend.as_str() {
+                #[cfg(feature = "transformer")]
+                "local" => providers.push(Box::new(TransformerProvider::new(TRANSFORMER_PROVIDER, &config.model_name, policy).with_device(&config.device))),
+                #[cfg(not(feature = "transformer"))]
+                "local" => {}
+                "tei" => providers.push(Box::new(TeiProvider::from_config(&Self::backend_config(config), &policy)?)),
+                "http" => providers.push(Box::new(HttpProvider::from_config(&Self::backend_config(config), &policy)?)),
+                other => return Err(anyhow!("Unknown embedding backend '{}' (expected local, tei or http)", other)),
+            }
+            if let Some(backend) = providers.first() {
+                instructions.push((backend.name().to_string(), Instructions::new(config.query_template.as_deref(), config.passage_template.as_deref())));
+            }
+            // The deterministic model serves whenever the backend cannot
+            let fallback = if providers.is_empty() { LOCAL_PROVIDER } else { FALLBACK_PROVIDER };
+            providers.push(Box::new(LocalProvider::new(fallback, model)));
+        }
+        Self::assemble(config, providers, instructions, policy)
+    }
+
+    /// The chain for Code chunks from `embedding.code_providers`, if any. It embeds under the
+    /// same policy and batch limits as the main chain, with no implicit fallback.
+    pub async fn code_chain(config: &EmbeddingConfig, policy: EmbeddingPolicy) -> Result<Option<Self>> {
+        if config.code_providers.is_empty() {
+            return Ok(None);
+        }
+        let (providers, instructions) = Self::build_providers(config, &config.code_providers, policy).await?;
+        Self::assemble(config, providers, instructions, policy).map(Some)
+    }
+
+    /// Providers for configured chain entries, with the instruction templates of each
+    async fn build_providers(config: &EmbeddingConfig, entries: &[EmbeddingProviderConfig], policy: EmbeddingPolicy) -> Result<(Vec<Box<dyn EmbeddingProvider>>, Vec<(String, Instructions)>)> {
+        let mut providers: Vec<Box<dyn EmbeddingProvider>> = Vec::new();
+        let mut instructions = Vec::new();
+        for provider in entries {
+            instructions.push((provider.name.clone(), Instructions::new(provider.query_template.as_deref(), provider.passage_template.as_deref())));
+            match provider.kind.as_str() {
+                "local" => {
+                    let model = EmbeddingModel::new(&config.model_name).await?.with_policy(policy);
+                    let mut local = LocalProvider::new(&provider.name, model);
+                    if provider.late_chunking {
+                        local = local.with_late_chunking(provider.max_passage_tokens);
+                    }
+                    providers.push(Box::new(local));
+                }
+                "http" => providers.push(Box::new(HttpProvider::from_config(provider, &policy)?)),
+                "tei" => providers.push(Box::new(TeiProvider::from_config(provider, &policy)?)),
+                #[cfg(feature = "transformer")]
+                "transformer" => {
+                    let model_name = provider.model.as_deref().unwrap_or(&config.model_name);
+                    let mut transformer = TransformerProvider::new(&provider.name, model_name, policy).with_device(&config.device);
+                    if provider.late_chunking {
+                        transformer = transformer.with_late_chunking(provider.max_passage_tokens);
+                    }
+                    providers.push(Box::new(transformer));
+                }
+                #[cfg(not(feature = "transformer"))]
+                "transformer" => return Err(anyhow!("Embedding provider '{}' needs a build with the transformer feature", provider.name)),
+                other => return Err(anyhow!("Embedding provider '{}' has unknown kind '{}' (expected transformer, tei, http or local)", provider.name, other)),
+            }
+        }
+        Ok((providers, instructions))
+    }
+
+    fn assemble(config: &EmbeddingConfig, providers: Vec<Box<dyn EmbeddingProvider>>, instructions: Vec<(String, Instructions)>, policy: EmbeddingPolicy) -> Result<Self> {
+        let limits = BatchLimits::new(config.batch_size, config.max_batch_tokens);
+        let chain = Self::new(providers, limits, Duration::from_secs(config.provider_cooldown_secs), policy)?
+            .with_concurrency(config.max_concurrent_batches)
+            .with_query_cache(config.query_cache_size);
+        Ok(instructions.into_iter().fold(chain, |chain, (provider, instructions)| chain.with_instructions(&provider, instructions)))
+    }
+
+    /// The provider settings of a remote `embedding.backend`, taken from the top-level keys
+    fn backend_config(config: &EmbeddingConfig) -> EmbeddingProviderConfig {
+        EmbeddingProviderConfig {
+            name: config.backend.clone(),
+            kind: config.backend.clone(),
+            endpoint: config.endpoint.clone(),
+            model: Some(config.model_name.clone()),
+            api_key_env: config.api_key_env.clone(),
+            timeout_secs: config.timeout_secs,
+            max_connections: config.max_connections,
+            late_chunking: false,
+            max_passage_tokens: 0,
+            query_template: config.query_template.clone(),
+            passage_template: config.passage_template.clone(),
+        }
+    }
+
+    pub fn new(providers: Vec<Box<dyn EmbeddingProvider>>, limits: BatchLimits, cooldown: Duration, policy: EmbeddingPolicy) -> Result<Self> {
+        if providers.is_empty() {
+            return Err(anyhow!("At least one embedding provider is required"));
+        }
+        let providers = providers.into_iter()
+            .map(|provider| ProviderSlot { provider, instructions: Instructions::default(), state: Mutex::new(ProviderState::default()) })
+            .collect();
+        Ok(Self { providers, limits, concurrency: 1, cooldown, policy, query_cache: QueryCache::new(0) })
+    }
+
+    /// Wrap texts sent to `provider` in its instruction templates
+    pub fn with_instructions(mut self, provider: &str, instructions: Instructions) -> Self {
+        if let Some(slot) = self.providers.iter_mut().find(|slot| slot.provider.name() == provider) {
+            slot.instructions = instructions;
+        }
+        self
+    }
+
+    /// Send up to `concurrency` batches of one document at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Reuse the vectors of up to `capacity` recent queries; 0 embeds every query
+    pub fn with_query_cache(mut self, capacity: usize) -> Self {
+        self.query_cache = QueryCache::new(capacity);
+        self
+    }
+
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        self.query_cache.stats()
+    }
+
+    /// Keep only the first `policy.truncate_dimension` components of every vector served, for
+    /// models trained with Matryoshka representation learning, re-normalized when the policy
+    /// normalizes. Chunks and queries pass through the chain alike, so both sides match.
+    fn truncate(&self, mut vectors: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        if let Some(dimension) = self.policy.truncate_dimension {
+            for vector in vectors.iter_mut().filter(|vector| vector.len() > dimension) {
+                vector.truncate(dimension);
+                if self.policy.normalize {
+                    EmbeddingModel::normalize_vector(vector);
+                }
+            }
+        }
+        vectors
+    }
+
+    pub fn policy(&self) -> &EmbeddingPolicy {
+        &self.policy
+    }
+
+    /// Embed a query, returning the vector and the provider that served it. Only the primary
+    /// provider's vectors are cached, so a provider that recovers serves repeated queries again.
+    pub fn embed_query(&self, text: &str) -> Result<(Vec<f32>, String)> {
+        let (primary, _) = self.primary();
+        if let Some(vector) = self.query_cache.get(text) {
+            return Ok((vector, primary.to_string()));
+        }
+
+        let texts = [text.to_string()];
+        let (mut vectors, provider) = self.serve(|provider, instructions| {
+            let texts = instructions.queries(&texts);
+            batching::embed_in_batches(&texts, &self.limits, |batch| provider.embed_batch(batch))
+        })?;
+        let vector = vectors.pop().unwrap_or_default();
+        if provider == primary {
+            self.query_cache.insert(text, &vector);
+        }
+        Ok((vector, provider))
+    }
+
+    /// Embed texts in packed batches, several in flight at once, with a single provider, so
+    /// one document's chunks always share a vector space
+    pub fn embed_documents(&self, texts: &[String]) -> Result<(Vec<Vec<f32>>, String)> {
+        self.serve(|provider, instructions| {
+            let texts = instructions.passages(texts);
+            batching::embed_concurrently(&texts, &self.limits, self.concurrency, |batch| provider.embed_batch(batch))
+        })
+    }
+
+    /// Embed one document's chunks, in order. Providers with late chunking embed them in
+    /// passages of consecutive chunks; the others embed each chunk on its own. Also returns
+    /// whether the vectors were late-chunked.
+    pub fn embed_document_chunks(&self, chunks: &[String]) -> Result<(Vec<Vec<f32>>, String, bool)> {
+        let (vectors, provider) = self.serve(|provider, instructions| {
+            let chunks = instructions.passages(chunks);
+            match provider.max_passage_tokens() {
+                // Passages are packed like batches, and split when the provider rejects one as too large
+                Some(max_tokens) => batching::embed_concurrently(&chunks, &BatchLimits::new(usize::MAX, max_tokens), self.concurrency, |passage| provider.embed_passage(passage)),
+                None => batching::embed_concurrently(&chunks, &self.limits, self.concurrency, |batch| provider.embed_batch(batch)),
+            }
+        })?;
+        let late = self.providers.iter().any(|slot| slot.provider.name() == provider && slot.provider.max_passage_tokens().is_some());
+        Ok((vectors, provider, late))
+    }
+
+    /// The result of `embed` with the first provider that succeeds
+    fn serve(&self, embed: impl Fn(&dyn EmbeddingProvider, &Instructions) -> Result<Vec<Vec<f32>>>) -> Result<(Vec<Vec<f32>>, String)> {
+        // Providers cooling down are still tried, last, rather than failing outright
+        let now = Instant::now();
+        let (available, cooling): (Vec<&ProviderSlot>, Vec<&ProviderSlot>) = self.providers.iter().partition(|slot| slot.available(now));
+
+        let mut errors = Vec::new();
+        for slot in available.into_iter().chain(cooling) {
+            let outcome = embed(slot.provider.as_ref(), &slot.instructions);
+            slot.record(&outcome, self.cooldown);
+            match outcome {
+                Ok(vectors) => {
+                    if !errors.is_empty() {
+                        tracing::warn!("Embedding served by fallback provider '{}' after: {}", slot.provider.name(), errors.join("; "));
+                    }
+                    return Ok((self.truncate(vectors), slot.provider.name().to_string()));
+                }
+                Err(e) => errors.push(format!("{}: {}", slot.provider.name(), e)),
+            }
+        }
+        Err(anyhow!("Every embedding provider failed: {}", errors.join("; ")))
+    }
+
+    /// The provider tried first and its model: what every chunk should be embedded with
+    pub fn primary(&self) -> (&str, &str) {
+        let provider = self.providers[0].provider.as_ref();
+        (provider.name(), provider.model())
+    }
+
+    pub fn model_of(&self, provider: &str) -> Option<&str> {
+        self.providers.iter().find(|slot| slot.provider.name() == provider).map(|slot| slot.provider.model())
+    }
+
+    /// Whether a chunk embedded by `chunk_provider` (None for chunks that predate provider
+    /// tracking) can be compared with a vector from `provider`. Local providers share one
+    /// deterministic model, so their vectors are interchangeable.
+    pub fn same_space(&self, chunk_provider: Option<&str>, provider: &str) -> bool {
+        let kind = |name: &str| self.providers.iter().find(|slot| slot.provider.name() == name).map(|slot| slot.provider.kind());
+        let chunk_kind = match chunk_provider {
+            Some(name) if name == provider => return true,
+            Some(name) => kind(name).unwrap_or(if name == LOCAL_PROVIDER { "local" } else { "unknown" }),
+            None => "local",
+        };
+        chunk_kind == "local" && kind(provider) == Some("local")
+    }
+
+    /// Health-check every provider, updating availability; used by the health tool
+    pub fn probe(&self) -> Vec<ProviderStatus> {
+        for slot in &self.providers {
+            let outcome = slot.provider.health_check().map(|_| Vec::new());
+            slot.record(&outcome, self.cooldown);
+        }
+        self.status()
+    }
+
+    pub fn status(&self) -> Vec<ProviderStatus> {
+        let now = Instant::now();
+        self.providers.iter()
+            .map(|slot| {
+                let state = slot.state.lock().unwrap();
+                ProviderStatus {
+                    name: slot.provider.name().to_string(),
+                    kind: slot.provider.kind(),
+                    available: state.unavailable_until.is_none_or(|until| now >= until),
+                    served: state.served,
+                    failures: state.failures,
+                    last_error: state.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Flaky {
+        name: &'static str,
+        down: AtomicBool,
+    }
+
+    impl EmbeddingProvider for Flaky {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn kind(&self) -> &'static str {
+            "http"
+        }
+
+        fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(BatchError::from_status(503, None, "unavailable"));
+            }
+            Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_and_cools_down() {
+        let remote = Box::new(Flaky { name: "remote", down: AtomicBool::new(true) });
+        let local = Box::new(LocalProvider::new("hash", EmbeddingModel::new("test").await.unwrap()));
+        let chain = ProviderChain::new(vec![remote, local], BatchLimits::new(8, 1024), Duration::from_secs(60), EmbeddingPolicy::default()).unwrap();
+
+        let (vector, provider) = chain.embed_query("dma reset").unwrap();
+        assert_eq!(provider, "hash");
+        assert_eq!(vector.len(), 384);
+
+        let status = chain.status();
+        assert!(!status[0].available);
+        assert!(status[0].last_error.as_deref().unwrap().contains("HTTP 503"));
+        assert_eq!((status[1].served, status[1].failures), (1, 0));
+
+        // While cooling down the remote provider is not retried first
+        chain.embed_query("dma reset").unwrap();
+        assert_eq!(chain.status()[0].failures, 1);
+
+        assert!(chain.same_space(None, "hash"));
+        assert!(chain.same_space(Some("remote"), "remote"));
+        assert!(!chain.same_space(Some("remote"), "hash"));
+        assert!(!chain.same_space(None, "remote"));
+    }
+
+    #[tokio::test]
+    async fn test_late_chunking_pools_in_context() {
+        let model = || async { EmbeddingModel::new("test").await.unwrap() };
+        let limits = BatchLimits::new(8, 1024);
+        let late = ProviderChain::new(vec![Box::new(LocalProvider::new("late", model().await).with_late_chunking(64))], limits, Duration::from_secs(60), EmbeddingPolicy::default()).unwrap();
+        let plain = ProviderChain::new(vec![Box::new(LocalProvider::new("plain", model().await))], limits, Duration::from_secs(60), EmbeddingPolicy::default()).unwrap();
+
+        let chunks: Vec<String> = ["The DMA engine copies buffers between devices.", "It must be held in reset first.", "Unrelated: the UART baud rate is fixed."]
+            .iter().map(|s| s.to_string()).collect();
+        let (in_context, provider, is_late) = late.embed_document_chunks(&chunks).unwrap();
+        let (alone, _, plain_late) = plain.embed_document_chunks(&chunks).unwrap();
+        assert_eq!((provider.as_str(), is_late, plain_late), ("late", true, false));
+        assert_eq!(alone, plain.embed_documents(&chunks).unwrap().0);
+
+        // "It must be held in reset" picks up the DMA context from the chunk before it
+        let cosine = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        assert!(cosine(&in_context[1], &alone[0]) > cosine(&alone[1], &alone[0]));
+        assert!(cosine(&in_context[1], &alone[1]) > 0.8);
+
+        // Passages longer than the limit are split rather than rejected
+        let small = ProviderChain::new(vec![Box::new(LocalProvider::new("late", model().await).with_late_chunking(8))], limits, Duration::from_secs(60), EmbeddingPolicy::default()).unwrap();
+        assert_eq!(small.embed_document_chunks(&chunks).unwrap().0.len(), 3);
+    }
+
+    #[test]
+    fn test_tei_provider() {
+        let config: EmbeddingProviderConfig = serde_yaml::from_str("{name: tei, kind: tei, endpoint: 'http://127.0.0.1:9/embed/'}").unwrap();
+        let provider = TeiProvider::from_config(&config, &EmbeddingPolicy::default()).unwrap();
+        assert_eq!(provider.base_url, "http://127.0.0.1:9");
+
+        let vectors = TeiProvider::parse_response("[[3.0, 4.0], [0.0, 2.0]]", true).unwrap();
+        assert_eq!(vectors, vec![vec![0.6, 0.8], vec![0.0, 1.0]]);
+        assert!(TeiProvider::parse_response(r#"{"error": "overloaded"}"#, true).is_err());
+
+        // Nothing listens on the discard port, so the server is reported down
+        assert!(provider.health_check().unwrap_err().to_string().contains("unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_skips_the_provider() {
+        let chain = |down: bool| async move {
+            let remote = Box::new(Flaky { name: "remote", down: AtomicBool::new(down) });
+            let local = Box::new(LocalProvider::new("hash", EmbeddingModel::new("test").await.unwrap()));
+            ProviderChain::new(vec![remote, local], BatchLimits::new(8, 1024), Duration::from_secs(60), EmbeddingPolicy::default()).unwrap()
+                .with_query_cache(8)
+        };
+
+        let up = chain(false).await;
+        let first = up.embed_query("dma reset").unwrap();
+        assert_eq!(up.embed_query("dma reset").unwrap(), first);
+        assert_eq!(up.status()[0].served, 1);
+        let stats = up.query_cache_stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+
+        // A fallback's vectors are not cached
+        let down = chain(true).await;
+        down.embed_query("dma reset").unwrap();
+        down.embed_query("dma reset").unwrap();
+        assert_eq!((down.status()[1].served, down.query_cache_stats().entries), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_truncation_applies_to_chunks_and_queries() {
+        let local = Box::new(LocalProvider::new("hash", EmbeddingModel::new("test").await.unwrap()));
+        let policy = EmbeddingPolicy { truncate_dimension: Some(128), ..Default::default() };
+        let chain = ProviderChain::new(vec![local], BatchLimits::new(8, 1024), Duration::from_secs(60), policy).unwrap();
+
+        let (query, _) = chain.embed_query("dma reset").unwrap();
+        let (chunks, _, _) = chain.embed_document_chunks(&["The DMA engine is held in reset.".to_string()]).unwrap();
+        assert_eq!((query.len(), chunks[0].len()), (128, 128));
+        assert!((query.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+
+    #[tokio::test]
+    async fn test_instructions_wrap_queries_and_passages() {
+        let instructions = Instructions::new(Some("Represent this sentence for searching relevant passages: {text}"), Some("passage: "));
+        let texts = vec!["dma reset".to_string()];
+        assert_eq!(instructions.queries(&texts)[0], "Represent this sentence for searching relevant passages: dma reset");
+        assert_eq!(instructions.passages(&texts)[0], "passage: dma reset");
+        assert!(matches!(Instructions::new(Some(""), None).queries(&texts), Cow::Borrowed(_)));
+
+        // Only the provider they were configured for sees them
+        let model = || async { EmbeddingModel::new("test").await.unwrap() };
+        let chain = |instructions: Instructions| async move {
+            ProviderChain::new(vec![Box::new(LocalProvider::new("hash", model().await))], BatchLimits::new(8, 1024), Duration::from_secs(60), EmbeddingPolicy::default()).unwrap()
+                .with_instructions("hash", instructions)
+        };
+        let plain = chain(Instructions::default()).await;
+        let e5 = chain(Instructions::new(Some("query: "), Some("passage: "))).await;
+        assert_eq!(e5.embed_query("dma reset").unwrap().0, plain.embed_documents(&["query: dma reset".to_string()]).unwrap().0[0]);
+        assert_eq!(e5.embed_documents(&texts).unwrap().0[0], plain.embed_documents(&["passage: dma reset".to_string()]).unwrap().0[0]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::chunker::Chunk;
+use crate::config::StorageConfig;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How chunk embeddings are kept on disk and in the in-memory search cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingStorage {
+    pub int8: bool,       // Store int8 values with a per-vector scale instead of f32
+    pub cache_f32: bool,  // With int8 storage, still keep dequantized f32 vectors in memory
+    pub binary_oversample: Option<usize>,  // Keep sign bits per vector and prefilter to top_k times this many by Hamming distance
+}
+
+impl EmbeddingStorage {
+    pub fn from_config(config: &StorageConfig) -> Result<Self> {
+        let int8 = match config.embedding_format.as_str() {
+            "f32" => false,
+            "int8" => true,
+            other => return Err(anyhow!("Unknown storage.embedding_format '{}' (expected f32 or int8)", other)),
+        };
+        let binary_oversample = config.binary_prefilter.then_some(config.binary_oversample.max(1));
+        Ok(Self { int8, cache_f32: config.cache_f32, binary_oversample })
+    }
+}
+
+/// A vector as int8 values times one scale, about a quarter of the f32 size. The largest
+/// component maps to ±127, so the rounding error is at most half a step per component.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedEmbedding {
+    pub scale: f32,
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+    pub values: Vec<i8>,
+}
+
+impl QuantizedEmbedding {
+    pub fn quantize(vector: &[f32]) -> Self {
+        let max = vector.iter().fold(0.0f32, |max, value| max.max(value.abs()));
+        let scale = max / 127.0;
+        let values = vector.iter()
+            .map(|value| if scale > 0.0 { (value / scale).round().clamp(-127.0, 127.0) as i8 } else { 0 })
+            .collect();
+        Self { scale, values }
+    }
+
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values.iter().map(|&value| value as f32 * self.scale).collect()
+    }
+
+    /// Cosine similarity with an f32 vector, without dequantizing: the scale cancels out
+    pub fn cosine_similarity(&self, other: &[f32]) -> f32 {
+        if self.values.len() != other.len() {
+            return 0.0;
+        }
+        let (mut dot, mut norm_self, mut norm_other) = (0.0f32, 0.0f32, 0.0f32);
+        for (&value, &component) in self.values.iter().zip(other) {
+            let value = value as f32;
+            dot += value * component;
+            norm_self += value * value;
+            norm_other += component * component;
+        }
+        if norm_self == 0.0 || norm_other == 0.0 {
+            0.0
+        } else {
+            dot / (norm_self.sqrt() * norm_other.sqrt())
+        }
+    }
+}
+
+/// The sign bits of a vector, 64 components per word. The Hamming distance between two
+/// of them approximates the angle between the vectors at a fraction of the cost of a dot product.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryEmbedding {
+    bits: Vec<u64>,
+}
+
+impl BinaryEmbedding {
+    pub fn from_vector(vector: &[f32]) -> Self {
+        let bits = vector.chunks(64)
+            .map(|components| components.iter().enumerate().fold(0u64, |word, (i, &value)| if value > 0.0 { word | (1 << i) } else { word }))
+            .collect();
+        Self { bits }
+    }
+
+    /// Differing bits; vectors of another dimension are as far apart as possible
+    pub fn hamming(&self, other: &BinaryEmbedding) -> u32 {
+        if self.bits.len() != other.bits.len() {
+            return u32::MAX;
+        }
+        self.bits.iter().zip(&other.bits).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+}
+
+/// One chunk's vector in the search cache
+#[derive(Debug, Clone)]
+pub enum CachedVector {
+    F32(Vec<f32>),
+    Int8(QuantizedEmbedding),
+}
+
+/// A cached vector, with its sign bits when the Hamming prefilter is on
+#[derive(Debug, Clone)]
+pub struct CachedEmbedding {
+    pub vector: CachedVector,
+    pub bits: Option<BinaryEmbedding>,
+}
+
+impl CachedEmbedding {
+    pub fn new(embedding: &[f32], storage: EmbeddingStorage) -> Self {
+        let vector = if storage.int8 && !storage.cache_f32 {
+            CachedVector::Int8(QuantizedEmbedding::quantize(embedding))
+        } else {
+            CachedVector::F32(embedding.to_vec())
+        };
+        let bits = storage.binary_oversample.map(|_| BinaryEmbedding::from_vector(embedding));
+        Self { vector, bits }
+    }
+}
+
+/// The stored form of a chunk: the chunk itself, with an empty `embedding` when the vector is
+/// kept quantized alongside
+#[derive(Serialize, Deserialize)]
+struct StoredChunk<'a> {
+    #[serde(flatten)]
+    chunk: std::borrow::Cow<'a, Chunk>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding_int8: Option<QuantizedEmbedding>,
+}
+
+/// Serialize a chunk for the chunk store, quantizing its embedding with `int8`
+pub fn encode_chunk(chunk: &Chunk, int8: bool) -> Result<Vec<u8>> {
+    if !int8 || chunk.embedding.is_empty() {
+        return Ok(serde_json::to_vec(chunk)?);
+    }
+    let mut stripped = chunk.clone();
+    let quantized = QuantizedEmbedding::quantize(&std::mem::take(&mut stripped.embedding));
+    Ok(serde_json::to_vec(&StoredChunk { chunk: std::borrow::Cow::Owned(stripped), embedding_int8: Some(quantized) })?)
+}
+
+/// Read a stored chunk in either format, dequantizing an int8 embedding
+pub fn decode_chunk(data: &[u8]) -> Result<Chunk> {
+    Ok(decode_record(data)?.0)
+}
+
+/// `decode_chunk`, also telling whether the embedding was stored as int8
+pub fn decode_record(data: &[u8]) -> Result<(Chunk, bool)> {
+    let stored: StoredChunk = serde_json::from_slice(data)?;
+    let mut chunk = stored.chunk.into_owned();
+    let quantized = stored.embedding_int8.is_some();
+    if let Some(embedding) = stored.embedding_int8 {
+        chunk.embedding = embedding.dequantize();
+    }
+    Ok((chunk, quantized))
+}
+
+fn serialize_hex<S: Serializer>(values: &[i8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    let bytes: Vec<u8> = values.iter().map(|&value| value as u8).collect();
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<i8>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let bytes = hex::decode(text).map_err(serde::de::Error::custom)?;
+    Ok(bytes.into_iter().map(|byte| byte as i8).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{ChunkStrategy, SemanticChunker};
+
+    #[test]
+    fn test_int8_records_round_trip() {
+        let vector: Vec<f32> = (0..384).map(|i| ((i as f32) * 0.37).sin()).collect();
+        let quantized = QuantizedEmbedding::quantize(&vector);
+        let restored = quantized.dequantize();
+        assert!(vector.iter().zip(&restored).all(|(a, b)| (a - b).abs() <= quantized.scale * 0.51));
+        assert!(quantized.cosine_similarity(&vector) > 0.999);
+
+        let mut chunk = SemanticChunker::build_text_chunk("DMA reset sequence", "dma.md", "hash", (1, 1), ChunkStrategy::NaturalSection);
+        chunk.embedding = vector;
+        let f32_record = encode_chunk(&chunk, false).unwrap();
+        let int8_record = encode_chunk(&chunk, true).unwrap();
+        assert!(int8_record.len() * 3 < f32_record.len());
+
+        let (decoded, quantized) = decode_record(&int8_record).unwrap();
+        assert_eq!((decoded.id.as_str(), decoded.embedding, quantized), (chunk.id.as_str(), restored, true));
+        let (decoded, quantized) = decode_record(&f32_record).unwrap();
+        assert_eq!((decoded.embedding, quantized), (chunk.embedding, false));
+    }
+
+    #[test]
+    fn test_hamming_distance_follows_angle() {
+        let base: Vec<f32> = (0..384).map(|i| ((i as f32) * 0.37).sin()).collect();
+        let near: Vec<f32> = base.iter().enumerate().map(|(i, v)| if i % 50 == 1 { -v } else { *v }).collect();
+        let opposite: Vec<f32> = base.iter().map(|v| -v).collect();
+
+        let bits = BinaryEmbedding::from_vector(&base);
+        assert_eq!(bits.hamming(&bits), 0);
+        assert_eq!(bits.hamming(&BinaryEmbedding::from_vector(&near)), 8);
+        assert!(bits.hamming(&BinaryEmbedding::from_vector(&opposite)) > 370);
+        assert_eq!(bits.hamming(&BinaryEmbedding::from_vector(&base[..128])), u32::MAX);
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Hit and size counters of a `QueryCache`, reported by the health tool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueryCacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Vectors of recently embedded queries, so repeated searches (paging, diagnose after a
+/// search, agents retrying) skip the provider. The least recently used entry is evicted when
+/// full; a capacity of 0 disables the cache.
+pub struct QueryCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, (Vec<f32>, u64)>,  // Vector and the tick it was last used
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl QueryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, state: Mutex::new(CacheState::default()) }
+    }
+
+    pub fn get(&self, query: &str) -> Option<Vec<f32>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        match state.entries.get_mut(query) {
+            Some((vector, last_used)) => {
+                *last_used = tick;
+                let vector = vector.clone();
+                state.hits += 1;
+                Some(vector)
+            }
+            None => {
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, query: &str, vector: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(query) {
+            let oldest = state.entries.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(query.to_string(), (vector.to_vec(), tick));
+    }
+
+    pub fn stats(&self) -> QueryCacheStats {
+        let state = self.state.lock().unwrap();
+        QueryCacheStats { capacity: self.capacity, entries: state.entries.len(), hits: state.hits, misses: state.misses }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_query_is_evicted() {
+        let cache = QueryCache::new(2);
+        cache.insert("dma reset", &[1.0]);
+        cache.insert("uart baud", &[2.0]);
+        assert_eq!(cache.get("dma reset"), Some(vec![1.0]));
+
+        // "uart baud" was used longest ago
+        cache.insert("pcie link", &[3.0]);
+        assert_eq!(cache.get("uart baud"), None);
+        assert_eq!(cache.get("pcie link"), Some(vec![3.0]));
+        assert_eq!(cache.stats(), QueryCacheStats { capacity: 2, entries: 2, hits: 2, misses: 1 });
+
+        let disabled = QueryCache::new(0);
+        disabled.insert("dma reset", &[1.0]);
+        assert_eq!(disabled.get("dma reset"), None);
+        assert_eq!(disabled.stats().misses, 0);
+    }
+}
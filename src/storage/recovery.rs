@@ -0,0 +1,135 @@
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// Result of checking a store at startup
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryOutcome {
+    Healthy,
+    Quarantined(PathBuf),  // The corrupted store was moved here and a fresh one created
+}
+
+/// Open a sled store, verifying its checksum. A corrupted store is moved aside
+/// and replaced with an empty one instead of failing startup.
+pub fn open_sled_checked(config: &sled::Config, path: &Path, label: &str) -> Result<(sled::Db, RecoveryOutcome)> {
+    match config.open() {
+        Ok(db) => match db.checksum() {
+            Ok(_) => return Ok((db, RecoveryOutcome::Healthy)),
+            Err(e) => {
+                tracing::error!("{} store at {:?} failed checksum verification: {}", label, path, e);
+                drop(db);
+            }
+        },
+        Err(sled::Error::Corruption { .. }) => {
+            tracing::error!("{} store at {:?} is corrupted", label, path);
+        }
+        Err(e) => {
+            return Err(anyhow!("Failed to open {} store at {:?}: {}. Is another instance already running with the same data_dir?", label, path, e));
+        }
+    }
+
+    let quarantined = quarantine(path)?;
+    tracing::error!(
+        "Moved corrupted {} store to {:?} and started with an empty store. Re-ingest documents to rebuild it; \
+         delete the quarantined copy once it is no longer needed.",
+        label, quarantined
+    );
+
+    let db = config.open()
+        .map_err(|e| anyhow!("Failed to recreate {} store at {:?} after quarantine: {}", label, path, e))?;
+    Ok((db, RecoveryOutcome::Quarantined(quarantined)))
+}
+
+/// Check an SQLite database file after a possible crash. Opening the connection replays
+/// any hot journal; `PRAGMA integrity_check` then verifies the pages. A database that
+/// fails is quarantined together with its -wal/-shm/-journal sidecars.
+pub fn check_sqlite(path: &Path, label: &str) -> Result<RecoveryOutcome> {
+    if !path.exists() {
+        return Ok(RecoveryOutcome::Healthy);
+    }
+
+    let status = rusqlite::Connection::open(path)
+        .and_then(|conn| conn.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0)));
+
+    match status {
+        Ok(result) if result == "ok" => return Ok(RecoveryOutcome::Healthy),
+        Ok(result) => tracing::error!("{} database {:?} failed integrity_check: {}", label, path, result),
+        Err(e) => tracing::error!("{} database {:?} could not be verified: {}", label, path, e),
+    }
+
+    let quarantined = quarantine(path)?;
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let sidecar = PathBuf::from(format!("{}{}", path.display(), suffix));
+        if sidecar.exists() {
+            std::fs::rename(&sidecar, format!("{}{}", quarantined.display(), suffix))?;
+        }
+    }
+
+    tracing::error!(
+        "Moved corrupted {} database to {:?}; a fresh database will be created. Re-ingest documents to rebuild it.",
+        label, quarantined
+    );
+    Ok(RecoveryOutcome::Quarantined(quarantined))
+}
+
+/// Rename a store to `<name>.corrupt-<timestamp>` next to the original
+fn quarantine(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Cannot quarantine store with invalid path {:?}", path))?;
+    let stamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let target = path.with_file_name(format!("{}.corrupt-{}", file_name, stamp));
+
+    std::fs::rename(path, &target)
+        .map_err(|e| anyhow!("Failed to quarantine corrupted store {:?}: {}", path, e))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_healthy_sled_store_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        let config = sled::Config::new().path(&path);
+        {
+            let (db, outcome) = open_sled_checked(&config, &path, "test").unwrap();
+            assert_eq!(outcome, RecoveryOutcome::Healthy);
+            db.insert("chunk", "uart").unwrap();
+            db.flush().unwrap();
+        }
+
+        let (db, outcome) = open_sled_checked(&config, &path, "test").unwrap();
+        assert_eq!(outcome, RecoveryOutcome::Healthy);
+        assert_eq!(db.get("chunk").unwrap().as_deref(), Some(&b"uart"[..]));
+    }
+
+    #[test]
+    fn test_corrupted_sqlite_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+        std::fs::write(&db_path, b"this is not an sqlite database, just garbage bytes").unwrap();
+
+        let outcome = check_sqlite(&db_path, "test").unwrap();
+
+        match outcome {
+            RecoveryOutcome::Quarantined(moved) => {
+                assert!(moved.exists());
+                assert!(!db_path.exists());
+            }
+            RecoveryOutcome::Healthy => panic!("garbage file should not pass integrity_check"),
+        }
+    }
+
+    #[test]
+    fn test_healthy_sqlite_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+        rusqlite::Connection::open(&db_path).unwrap()
+            .execute_batch("CREATE TABLE chunks (id TEXT PRIMARY KEY);").unwrap();
+
+        assert_eq!(check_sqlite(&db_path, "test").unwrap(), RecoveryOutcome::Healthy);
+        assert!(db_path.exists());
+    }
+}
@@ -0,0 +1,125 @@
+use super::embeddings::{EmbeddingPolicy, Pooling};
+use super::providers::LOCAL_PROVIDER;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Chunk attribute recording the model that produced the chunk's vector
+pub const EMBEDDING_MODEL_ATTRIBUTE: &str = "embedding_model";
+
+/// Chunk attribute recording the length of the chunk's vector
+pub const EMBEDDING_DIMENSION_ATTRIBUTE: &str = "embedding_dimension";
+
+/// Why a chunk's vector differs from what the current configuration produces: "model" when
+/// another model embedded it, "policy" when it was normalized or pooled differently. Chunks
+/// from before models were recorded are attributed to their provider's current model, and
+/// those from before providers were recorded to the local one. `model_of` maps a provider
+/// name to its model in the current chain.
+pub fn stale_reason<'a>(
+    attributes: &HashMap<String, String>,
+    model: &str,
+    model_of: impl Fn(&str) -> Option<&'a str>,
+    policy: &EmbeddingPolicy,
+) -> Option<&'static str> {
+    let recorded_model = attributes.get(EMBEDDING_MODEL_ATTRIBUTE).map(String::as_str)
+        .or_else(|| model_of(attributes.get("embedding_provider").map_or(LOCAL_PROVIDER, String::as_str)));
+    if recorded_model != Some(model) {
+        return Some("model");
+    }
+
+    // Chunks from before the policy was recorded were normalized with mean pooling
+    let normalized = attributes.get("embedding_normalized").is_none_or(|normalized| normalized == "true");
+    let pooling = attributes.get("embedding_pooling").map_or(Pooling::Mean.name(), String::as_str);
+    if normalized != policy.normalize || pooling != policy.pooling.name() {
+        return Some("policy");
+    }
+    None
+}
+
+/// The recorded length of a chunk's vector when `provider` embedded it; chunks from before
+/// dimensions were recorded have none
+pub fn recorded_dimension(attributes: &HashMap<String, String>, provider: &str) -> Option<usize> {
+    let embedded_by = attributes.get("embedding_provider").map_or(LOCAL_PROVIDER, String::as_str);
+    if embedded_by != provider {
+        return None;
+    }
+    attributes.get(EMBEDDING_DIMENSION_ATTRIBUTE)?.parse().ok()
+}
+
+/// Progress of the latest background re-embed, as reported by the `reembed` tool
+#[derive(Debug, Clone, Serialize)]
+pub struct ReembedProgress {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub model: String,               // Model the chunks are re-embedded with
+    pub stale_chunks: usize,         // Chunks found stale when it started
+    pub files: usize,
+    pub files_done: usize,
+    pub reembedded: usize,
+    pub still_stale: usize,          // Served by a fallback provider, so still not on `model`
+    pub error: Option<String>,
+}
+
+impl ReembedProgress {
+    pub fn new(model: &str, stale_chunks: usize, files: usize) -> Self {
+        Self {
+            started_at: Utc::now(),
+            finished_at: None,
+            model: model.to_string(),
+            stale_chunks,
+            files,
+            files_done: 0,
+            reembedded: 0,
+            still_stale: 0,
+            error: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished_at.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_stale_reasons() {
+        let policy = EmbeddingPolicy::default();
+        let chain = |provider: &str| match provider {
+            "transformer" => Some("BAAI/bge-small-en-v1.5"),
+            "fallback" => Some("hash"),
+            _ => None,
+        };
+        let current = "BAAI/bge-small-en-v1.5";
+
+        let fresh = attributes(&[("embedding_provider", "transformer"), (EMBEDDING_MODEL_ATTRIBUTE, current), ("embedding_normalized", "true"), ("embedding_pooling", "mean")]);
+        assert_eq!(stale_reason(&fresh, current, chain, &policy), None);
+
+        // The model changed under the same provider name
+        let old_model = attributes(&[("embedding_provider", "transformer"), (EMBEDDING_MODEL_ATTRIBUTE, "sentence-transformers/all-MiniLM-L6-v2")]);
+        assert_eq!(stale_reason(&old_model, current, chain, &policy), Some("model"));
+
+        // Served by the fallback, or from before providers were recorded
+        assert_eq!(stale_reason(&attributes(&[("embedding_provider", "fallback")]), current, chain, &policy), Some("model"));
+        assert_eq!(stale_reason(&attributes(&[]), current, chain, &policy), Some("model"));
+        assert_eq!(stale_reason(&attributes(&[("embedding_provider", "transformer")]), current, chain, &policy), None);
+
+        let max_pooled = EmbeddingPolicy { pooling: Pooling::Max, ..Default::default() };
+        assert_eq!(stale_reason(&fresh, current, chain, &max_pooled), Some("policy"));
+    }
+
+    #[test]
+    fn test_recorded_dimension_per_provider() {
+        let transformer = attributes(&[("embedding_provider", "transformer"), (EMBEDDING_DIMENSION_ATTRIBUTE, "768")]);
+        assert_eq!(recorded_dimension(&transformer, "transformer"), Some(768));
+        assert_eq!(recorded_dimension(&transformer, "fallback"), None);
+        assert_eq!(recorded_dimension(&attributes(&[(EMBEDDING_DIMENSION_ATTRIBUTE, "384")]), LOCAL_PROVIDER), Some(384));
+        assert_eq!(recorded_dimension(&attributes(&[("embedding_provider", "transformer")]), "transformer"), None);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::EmbeddingConfig;
+use anyhow::Result;
+#[cfg(not(feature = "transformer"))]
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Learned term weights for one text, sorted by term. Unlike BM25 weights they come from a
+/// model, so a text also weighs related terms it does not contain (e.g. "sequencer" for a
+/// chunk about `uvm_sequence_item`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    terms: Vec<(String, f32)>,
+}
+
+impl SparseVector {
+    /// The `max_terms` heaviest positive weights
+    pub fn from_weights(weights: impl IntoIterator<Item = (String, f32)>, max_terms: usize) -> Self {
+        let mut terms: Vec<(String, f32)> = weights.into_iter().filter(|(_, weight)| *weight > 0.0).collect();
+        if terms.len() > max_terms {
+            terms.select_nth_unstable_by(max_terms, |a, b| b.1.total_cmp(&a.1));
+            terms.truncate(max_terms);
+        }
+        terms.sort_by(|a, b| a.0.cmp(&b.0));
+        terms.dedup_by(|a, b| a.0 == b.0);
+        Self { terms }
+    }
+
+    pub fn terms(&self) -> &[(String, f32)] {
+        &self.terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Sum of weight products over shared terms
+    pub fn dot(&self, other: &SparseVector) -> f32 {
+        let (mut i, mut j, mut dot) = (0, 0, 0.0);
+        while i < self.terms.len() && j < other.terms.len() {
+            match self.terms[i].0.cmp(&other.terms[j].0) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    dot += self.terms[i].1 * other.terms[j].1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        dot
+    }
+}
+
+/// A model producing `SparseVector`s for chunks and queries alike
+pub trait SparseEncoder: Send + Sync {
+    /// The model, recorded on the chunks it encodes
+    fn model(&self) -> &str;
+
+    fn encode(&self, texts: &[String]) -> Result<Vec<SparseVector>>;
+}
+
+/// The encoder configured under `embedding.sparse`, if any
+pub fn sparse_encoder(config: &EmbeddingConfig) -> Result<Option<Arc<dyn SparseEncoder>>> {
+    let Some(sparse) = &config.sparse else {
+        return Ok(None);
+    };
+    #[cfg(feature = "transformer")]
+    {
+        let encoder = super::transformer::SpladeEncoder::new(&sparse.model, sparse.max_terms).with_device(&config.device);
+        Ok(Some(Arc::new(encoder)))
+    }
+    #[cfg(not(feature = "transformer"))]
+    {
+        Err(anyhow!("embedding.sparse ({}) needs a build with the transformer feature", sparse.model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(terms: &[(&str, f32)], max_terms: usize) -> SparseVector {
+        SparseVector::from_weights(terms.iter().map(|(term, weight)| (term.to_string(), *weight)), max_terms)
+    }
+
+    #[test]
+    fn test_sparse_vectors_keep_heaviest_terms() {
+        let chunk = vector(&[("uvm", 2.0), ("sequence", 1.5), ("item", 0.5), ("driver", 0.0), ("##er", 0.1)], 3);
+        assert_eq!(chunk.terms().iter().map(|(term, _)| term.as_str()).collect::<Vec<_>>(), vec!["item", "sequence", "uvm"]);
+
+        // Expansion terms match even when the query words differ
+        let query = vector(&[("sequencer", 1.0), ("sequence", 0.8), ("uvm", 0.5)], 8);
+        assert!((chunk.dot(&query) - (1.5 * 0.8 + 2.0 * 0.5)).abs() < 1e-6);
+        assert_eq!(chunk.dot(&SparseVector::default()), 0.0);
+    }
+}
@@ -0,0 +1,418 @@
+use super::batching::BatchError;
+use super::embeddings::{EmbeddingModel, EmbeddingPolicy, Pooling};
+use super::providers::EmbeddingProvider;
+use super::sparse::{SparseEncoder, SparseVector};
+use anyhow::{Result, anyhow};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertForMaskedLM, BertModel, Config, DTYPE};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, TryLockError};
+use tokenizers::{Tokenizer, TruncationParams};
+
+/// The device named by `embedding.device`: "cpu", "cuda" or "metal" (optionally with an
+/// ordinal, e.g. "cuda:1"), or "auto" for the first GPU this build supports, else the CPU.
+/// GPUs need a build with the cuda or metal feature.
+pub fn select_device(name: &str) -> Result<Device> {
+    let (kind, ordinal) = match name.split_once(':') {
+        Some((kind, ordinal)) => (kind, ordinal.parse::<usize>().map_err(|_| anyhow!("Invalid device ordinal in '{}'", name))?),
+        None => (name, 0),
+    };
+    match kind {
+        "cpu" => Ok(Device::Cpu),
+        "cuda" => Ok(Device::new_cuda(ordinal)?),
+        "metal" => Ok(Device::new_metal(ordinal)?),
+        "auto" if candle_core::utils::cuda_is_available() => Ok(Device::new_cuda(ordinal)?),
+        "auto" if candle_core::utils::metal_is_available() => Ok(Device::new_metal(ordinal)?),
+        "auto" => Ok(Device::Cpu),
+        other => Err(anyhow!("Unknown embedding device '{}' (expected auto, cpu, cuda or metal)", other)),
+    }
+}
+
+/// A BERT-family sentence-transformers model (all-MiniLM-L6-v2, bge, e5, ...) run on the CPU or a GPU.
+/// Chunk vectors are pooled from the model's token embeddings per the embedding policy.
+pub struct TransformerModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    policy: EmbeddingPolicy,
+    max_tokens: usize,  // The model's position embeddings; longer texts are truncated
+    dimension: usize,
+}
+
+impl TransformerModel {
+    /// Load `model`: a directory holding config.json, tokenizer.json and model.safetensors (or
+    /// pytorch_model.bin), or a Hugging Face model id, downloaded into the hf-hub cache
+    /// (HF_HOME) the first time it is used. It runs on `device` (see `select_device`), or on
+    /// the CPU when that device cannot run it
+    pub fn load(model: &str, policy: EmbeddingPolicy, device: &str) -> Result<Self> {
+        let (config, tokenizer, weights) = Self::files(model)?;
+        let config: Config = serde_json::from_slice(&std::fs::read(&config)?)
+            .map_err(|e| anyhow!("Model {} is not a BERT-family model: {}", model, e))?;
+
+        let bert = match select_device(device).and_then(|device| Self::load_weights(&weights, &config, &device)) {
+            Ok(bert) => bert,
+            Err(e) if device != "cpu" => {
+                tracing::warn!("Embedding model {} cannot run on {}, using the CPU: {}", model, device, e);
+                Self::load_weights(&weights, &config, &Device::Cpu)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer).map_err(|e| anyhow!("Failed to load tokenizer of {}: {}", model, e))?;
+        tokenizer.with_padding(None);
+        tokenizer.with_truncation(Some(TruncationParams { max_length: config.max_position_embeddings, ..Default::default() }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer of {}: {}", model, e))?;
+
+        tracing::info!("Loaded embedding model {} ({} dimensions) on {:?}", model, config.hidden_size, bert.device);
+        Ok(Self { model: bert, tokenizer, policy, max_tokens: config.max_position_embeddings, dimension: config.hidden_size })
+    }
+
+    fn load_weights(weights: &Path, config: &Config, device: &Device) -> Result<BertModel> {
+        Ok(BertModel::load(Self::vars(weights, device)?, config)?)
+    }
+
+    fn vars(weights: &Path, device: &Device) -> Result<VarBuilder<'static>> {
+        Ok(if weights.extension().is_some_and(|ext| ext == "safetensors") {
+            // Safety: the weights file is not modified while the model is loaded
+            unsafe { VarBuilder::from_mmaped_safetensors(&[weights], DTYPE, device)? }
+        } else {
+            VarBuilder::from_pth(weights, DTYPE, device)?
+        })
+    }
+
+    fn files(model: &str) -> Result<(PathBuf, PathBuf, PathBuf)> {
+        let dir = Path::new(model);
+        if dir.is_absolute() || model.starts_with('.') {
+            let weights = ["model.safetensors", "pytorch_model.bin"].iter().map(|name| dir.join(name)).find(|path| path.exists())
+                .ok_or_else(|| anyhow!("No model.safetensors or pytorch_model.bin in {:?}", dir))?;
+            return Ok((dir.join("config.json"), dir.join("tokenizer.json"), weights));
+        }
+
+        // Progress bars would be written into the JSON-RPC stream on stdout
+        let api = hf_hub::api::sync::ApiBuilder::from_env().with_progress(false).build()?;
+        let repo = api.model(model.to_string());
+        let fetch = |file: &str| repo.get(file).map_err(|e| anyhow!("Failed to fetch {} of model {}: {}", file, model, e));
+        let weights = fetch("model.safetensors").or_else(|_| fetch("pytorch_model.bin"))?;
+        Ok((fetch("config.json")?, fetch("tokenizer.json")?, weights))
+    }
+
+    pub fn get_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        let ids: Vec<Vec<u32>> = encodings.iter().map(|encoding| encoding.get_ids().to_vec()).collect();
+        let tokens = self.token_embeddings(&ids)?;
+        Ok(tokens.into_iter().zip(&ids).map(|(tokens, ids)| self.pool(&tokens[..ids.len()])).collect())
+    }
+
+    /// Late chunking: run `chunks`, consecutive pieces of one passage, through the model as one
+    /// sequence and pool each chunk's vector from its own tokens. Passages longer than the model
+    /// accepts are rejected as too large, so the caller splits them.
+    pub fn embed_spans(&self, chunks: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        let failed = |e: anyhow::Error| BatchError::Failed(e);
+        let encode = |text: &str, special: bool| self.tokenizer.encode(text, special)
+            .map(|encoding| encoding.get_ids().to_vec())
+            .map_err(|e| failed(anyhow!("Tokenization failed: {}", e)));
+
+        // The special tokens a single text is wrapped in, e.g. [CLS] before and [SEP] after
+        let wrapper = encode("", true)?;
+        let (prefix, suffix) = wrapper.split_at(wrapper.len().min(1));
+        let spans = chunks.iter().map(|chunk| encode(chunk, false)).collect::<std::result::Result<Vec<_>, _>>()?;
+        let length = wrapper.len() + spans.iter().map(Vec::len).sum::<usize>();
+        if length > self.max_tokens {
+            return match chunks.len() {
+                1 => self.embed_batch(chunks).map_err(failed),
+                _ => Err(BatchError::TooLarge),
+            };
+        }
+
+        let ids: Vec<u32> = prefix.iter().chain(spans.iter().flatten()).chain(suffix).copied().collect();
+        let tokens = self.token_embeddings(&[ids]).map_err(failed)?.remove(0);
+
+        let mut offset = prefix.len();
+        Ok(spans.iter()
+            .map(|span| {
+                let vector = self.pool(&tokens[offset..offset + span.len()]);
+                offset += span.len();
+                vector
+            })
+            .collect())
+    }
+
+    /// The model's output for each token of each (unpadded) sequence in `ids`
+    fn token_embeddings(&self, ids: &[Vec<u32>]) -> Result<Vec<Vec<Vec<f32>>>> {
+        let length = ids.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        let mut padded = Vec::with_capacity(ids.len() * length);
+        let mut mask = Vec::with_capacity(ids.len() * length);
+        for sequence in ids {
+            padded.extend(sequence.iter().copied().chain(std::iter::repeat(0)).take(length));
+            mask.extend((0..length).map(|i| u32::from(i < sequence.len())));
+        }
+
+        let shape = (ids.len(), length);
+        let input = Tensor::from_vec(padded, shape, &self.model.device)?;
+        let mask = Tensor::from_vec(mask, shape, &self.model.device)?;
+        let output = self.model.forward(&input, &input.zeros_like()?, Some(&mask))?;
+        Ok(output.to_vec3::<f32>()?)
+    }
+
+    /// Combine token vectors per the policy's pooling; an empty span pools to zeros
+    fn pool(&self, tokens: &[Vec<f32>]) -> Vec<f32> {
+        let mut pooled = match (self.policy.pooling, tokens.first()) {
+            (Pooling::Max, Some(first)) => first.clone(),
+            _ => vec![0.0; self.dimension],
+        };
+        for token in tokens {
+            for (value, component) in pooled.iter_mut().zip(token) {
+                *value = match self.policy.pooling {
+                    Pooling::Mean => *value + component,
+                    Pooling::Max => value.max(*component),
+                };
+            }
+        }
+        if self.policy.pooling == Pooling::Mean && !tokens.is_empty() {
+            pooled.iter_mut().for_each(|value| *value /= tokens.len() as f32);
+        }
+
+        if self.policy.normalize {
+            EmbeddingModel::normalize_vector(&mut pooled);
+        }
+        pooled
+    }
+}
+
+/// A `TransformerModel` loaded, or downloaded, on first use. Until that succeeds every request
+/// fails, including those made while another caller is loading it, so the provider chain serves
+/// them with the next provider and retries after the cooldown.
+pub struct TransformerProvider {
+    name: String,
+    model_name: String,
+    policy: EmbeddingPolicy,
+    max_passage_tokens: Option<usize>,
+    device: String,
+    model: OnceLock<TransformerModel>,
+    loading: Mutex<()>,  // Held by the one caller loading the model
+}
+
+impl TransformerProvider {
+    pub fn new(name: &str, model_name: &str, policy: EmbeddingPolicy) -> Self {
+        Self { name: name.to_string(), model_name: model_name.to_string(), policy, max_passage_tokens: None, device: "cpu".to_string(), model: OnceLock::new(), loading: Mutex::new(()) }
+    }
+
+    /// Pool chunk vectors from the token embeddings of passages of up to `max_passage_tokens`,
+    /// capped by the model's own limit
+    pub fn with_late_chunking(mut self, max_passage_tokens: usize) -> Self {
+        self.max_passage_tokens = Some(max_passage_tokens);
+        self
+    }
+
+    /// Run the model on `device` (see `select_device`) instead of the CPU
+    pub fn with_device(mut self, device: &str) -> Self {
+        self.device = device.to_string();
+        self
+    }
+
+    fn model(&self) -> std::result::Result<&TransformerModel, BatchError> {
+        if let Some(model) = self.model.get() {
+            return Ok(model);
+        }
+        // A download can take minutes; fail over instead of queueing behind it
+        let _loading = match self.loading.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                return Err(BatchError::Failed(anyhow!("Embedding model {} is still loading", self.model_name)));
+            }
+        };
+        if let Some(model) = self.model.get() {
+            return Ok(model);
+        }
+        let loaded = TransformerModel::load(&self.model_name, self.policy, &self.device).map_err(BatchError::Failed)?;
+        Ok(self.model.get_or_init(|| loaded))
+    }
+}
+
+impl EmbeddingProvider for TransformerProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "transformer"
+    }
+
+    fn model(&self) -> &str {
+        &self.model_name
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.model()?.embed_batch(texts).map_err(BatchError::Failed)
+    }
+
+    fn max_passage_tokens(&self) -> Option<usize> {
+        self.max_passage_tokens
+    }
+
+    fn embed_passage(&self, chunks: &[String]) -> std::result::Result<Vec<Vec<f32>>, BatchError> {
+        self.model()?.embed_spans(chunks)
+    }
+}
+
+/// A SPLADE model: BERT with its masked-language-model head, e.g.
+/// naver/splade-cocondenser-ensembledistil. A text's weight for a vocabulary term is the
+/// largest log(1 + relu(logit)) the term gets at any of its tokens.
+pub struct SpladeModel {
+    model: BertForMaskedLM,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl SpladeModel {
+    /// Load `model` like `TransformerModel::load`, on `device` or else the CPU
+    pub fn load(model: &str, device: &str) -> Result<Self> {
+        let (config, tokenizer, weights) = TransformerModel::files(model)?;
+        let config: Config = serde_json::from_slice(&std::fs::read(&config)?)
+            .map_err(|e| anyhow!("Model {} is not a BERT-family model: {}", model, e))?;
+        let load = |device: Device| -> Result<(BertForMaskedLM, Device)> {
+            Ok((BertForMaskedLM::load(TransformerModel::vars(&weights, &device)?, &config)?, device))
+        };
+        let (splade, device) = match select_device(device).and_then(&load) {
+            Ok(loaded) => loaded,
+            Err(e) if device != "cpu" => {
+                tracing::warn!("Sparse model {} cannot run on {}, using the CPU: {}", model, device, e);
+                load(Device::Cpu)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer).map_err(|e| anyhow!("Failed to load tokenizer of {}: {}", model, e))?;
+        tokenizer.with_padding(None);
+        tokenizer.with_truncation(Some(TruncationParams { max_length: config.max_position_embeddings, ..Default::default() }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer of {}: {}", model, e))?;
+
+        tracing::info!("Loaded sparse model {} on {:?}", model, device);
+        Ok(Self { model: splade, tokenizer, device })
+    }
+
+    pub fn encode(&self, texts: &[String], max_terms: usize) -> Result<Vec<SparseVector>> {
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        let length = encodings.iter().map(|encoding| encoding.get_ids().len()).max().unwrap_or(0).max(1);
+        let mut padded = Vec::with_capacity(texts.len() * length);
+        let mut mask = Vec::with_capacity(texts.len() * length);
+        for encoding in &encodings {
+            let ids = encoding.get_ids();
+            padded.extend(ids.iter().copied().chain(std::iter::repeat(0)).take(length));
+            mask.extend((0..length).map(|i| u32::from(i < ids.len())));
+        }
+
+        let shape = (texts.len(), length);
+        let input = Tensor::from_vec(padded, shape, &self.device)?;
+        let mask = Tensor::from_vec(mask, shape, &self.device)?;
+        let logits = self.model.forward(&input, &input.zeros_like()?, Some(&mask))?;
+        // Padding positions weigh nothing, so they never win the max
+        let weights = logits.relu()?.affine(1.0, 1.0)?.log()?
+            .broadcast_mul(&mask.to_dtype(DType::F32)?.unsqueeze(2)?)?
+            .max(1)?
+            .to_vec2::<f32>()?;
+
+        Ok(weights.into_iter()
+            .map(|row| {
+                let terms = row.into_iter().enumerate()
+                    .filter(|(_, weight)| *weight > 0.0)
+                    .filter_map(|(id, weight)| Some((self.tokenizer.id_to_token(id as u32)?, weight)));
+                SparseVector::from_weights(terms, max_terms)
+            })
+            .collect())
+    }
+}
+
+/// A `SpladeModel` loaded, or downloaded, on first use; until that succeeds encoding fails
+pub struct SpladeEncoder {
+    model_name: String,
+    max_terms: usize,
+    device: String,
+    model: Mutex<Option<Arc<SpladeModel>>>,
+}
+
+impl SpladeEncoder {
+    pub fn new(model_name: &str, max_terms: usize) -> Self {
+        Self { model_name: model_name.to_string(), max_terms, device: "cpu".to_string(), model: Mutex::new(None) }
+    }
+
+    /// Run the model on `device` (see `select_device`) instead of the CPU
+    pub fn with_device(mut self, device: &str) -> Self {
+        self.device = device.to_string();
+        self
+    }
+
+    fn loaded(&self) -> Result<Arc<SpladeModel>> {
+        let mut model = self.model.lock().unwrap();
+        if let Some(model) = model.as_ref() {
+            return Ok(model.clone());
+        }
+        let loaded = Arc::new(SpladeModel::load(&self.model_name, &self.device)?);
+        *model = Some(loaded.clone());
+        Ok(loaded)
+    }
+}
+
+impl SparseEncoder for SpladeEncoder {
+    fn model(&self) -> &str {
+        &self.model_name
+    }
+
+    fn encode(&self, texts: &[String]) -> Result<Vec<SparseVector>> {
+        self.loaded()?.encode(texts, self.max_terms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unavailable_model_fails_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = TransformerProvider::new("minilm", dir.path().to_str().unwrap(), EmbeddingPolicy::default());
+        match provider.embed_batch(&["dma reset".to_string()]) {
+            Err(BatchError::Failed(e)) => assert!(e.to_string().contains("No model.safetensors"), "{}", e),
+            _ => panic!("expected the load to fail"),
+        }
+        assert_eq!(provider.kind(), "transformer");
+    }
+
+    #[test]
+    fn test_model_still_loading_fails_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = TransformerProvider::new("minilm", dir.path().to_str().unwrap(), EmbeddingPolicy::default());
+        let _loading = provider.loading.lock().unwrap();
+        match provider.embed_batch(&["dma reset".to_string()]) {
+            Err(BatchError::Failed(e)) => assert!(e.to_string().contains("still loading"), "{}", e),
+            _ => panic!("expected the request to fail over while the model loads"),
+        }
+    }
+
+    #[test]
+    fn test_unavailable_sparse_model_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let encoder = SpladeEncoder::new(dir.path().to_str().unwrap(), 64);
+        let e = encoder.encode(&["uvm_sequence_item".to_string()]).unwrap_err();
+        assert!(e.to_string().contains("No model.safetensors"), "{}", e);
+        assert_eq!(encoder.model(), dir.path().to_str().unwrap());
+    }
+
+    #[test]
+    fn test_device_selection() {
+        assert!(matches!(select_device("cpu").unwrap(), Device::Cpu));
+        assert!(select_device("tpu").is_err());
+        assert!(select_device("cuda:x").is_err());
+        // Without a GPU "auto" settles on the CPU rather than failing
+        if !candle_core::utils::cuda_is_available() && !candle_core::utils::metal_is_available() {
+            assert!(matches!(select_device("auto").unwrap(), Device::Cpu));
+        }
+    }
+}
\ No newline at end of file
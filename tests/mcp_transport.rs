@@ -0,0 +1,209 @@
+//! Drives the server binary over stdio the way desktop MCP clients do: an `initialize`
+//! handshake, the `notifications/initialized` notification, `tools/list`, then `tools/call`,
+//! with notifications and batched frames mixed in.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Generous, as the first response waits for the embedding model to load
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Wait for a response that should never arrive, e.g. to a notification
+const SILENCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct McpClient {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<String>,
+    next_id: u64,
+    _data_dir: TempDir,
+}
+
+impl McpClient {
+    /// Start the server with the repository config, an empty data dir and no background jobs
+    fn spawn() -> Self {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut config: serde_yaml::Value = serde_yaml::from_str(
+            &std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/rag_config.yaml")).unwrap(),
+        ).unwrap();
+        config["storage"]["data_dir"] = data_dir.path().join("data").to_string_lossy().into_owned().into();
+        config["sources"] = serde_yaml::Value::Sequence(vec![]);
+        config["embedding"]["drift_check_interval_secs"] = 0.into();
+        // The deterministic model, so runs never download a transformer model
+        config["embedding"]["providers"] = serde_yaml::from_str("[{name: local, kind: local}]").unwrap();
+        let config_path = data_dir.path().join("config.yaml");
+        std::fs::write(&config_path, serde_yaml::to_string(&config).unwrap()).unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rag-mcp-server"))
+            .env("RAG_CONFIG", &config_path)
+            .current_dir(data_dir.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start the server binary");
+
+        // Every stdout line must be one complete JSON-RPC frame
+        let stdout = child.stdout.take().unwrap();
+        let (sender, responses) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let stdin = child.stdin.take().unwrap();
+        Self { child, stdin, responses, next_id: 1, _data_dir: data_dir }
+    }
+
+    fn send_raw(&mut self, frame: &str) {
+        writeln!(self.stdin, "{}", frame).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&self) -> Value {
+        let line = self.responses.recv_timeout(RESPONSE_TIMEOUT).expect("no response from server");
+        assert!(!line.trim().is_empty(), "server wrote an empty line");
+        serde_json::from_str(&line).unwrap_or_else(|e| panic!("server wrote invalid JSON ({}): {}", e, line))
+    }
+
+    fn assert_silent(&self) {
+        if let Ok(line) = self.responses.recv_timeout(SILENCE_TIMEOUT) {
+            panic!("expected no output, got: {}", line);
+        }
+    }
+
+    fn request_frame(&mut self, method: &str, params: Value) -> (u64, Value) {
+        let id = self.next_id;
+        self.next_id += 1;
+        (id, json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+    }
+
+    /// Send a request and return its response, checking the envelope
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let (id, frame) = self.request_frame(method, params);
+        self.send_raw(&frame.to_string());
+        let response = self.receive();
+        assert_eq!(response["jsonrpc"], "2.0", "{}", response);
+        assert_eq!(response["id"], id, "{}", response);
+        assert!(response.get("result").is_some() != response.get("error").is_some(), "exactly one of result/error: {}", response);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send_raw(&json!({"jsonrpc": "2.0", "method": method, "params": params}).to_string());
+    }
+
+    fn call_tool(&mut self, name: &str, arguments: Value) -> Value {
+        self.request("tools/call", json!({"name": name, "arguments": arguments}))
+    }
+
+    /// The handshake a desktop client performs before anything else
+    fn initialize(&mut self, protocol_version: &str) -> Value {
+        let response = self.request("initialize", json!({
+            "protocolVersion": protocol_version,
+            "capabilities": {"roots": {"listChanged": true}, "sampling": {}},
+            "clientInfo": {"name": "transport-harness", "version": "1.0.0"}
+        }));
+        self.notify("notifications/initialized", json!({}));
+        response["result"].clone()
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Text content of a successful tools/call
+fn tool_text(response: &Value) -> String {
+    let content = &response["result"]["content"];
+    assert_eq!(content[0]["type"], "text", "{}", response);
+    content[0]["text"].as_str().unwrap_or_else(|| panic!("no text content: {}", response)).to_string()
+}
+
+#[test]
+fn test_handshake_and_tool_listing() {
+    let mut client = McpClient::spawn();
+
+    let init = client.initialize("2024-11-05");
+    assert_eq!(init["protocolVersion"], "2024-11-05");
+    assert_eq!(init["serverInfo"]["name"], "rag-mcp-server");
+    assert!(init["capabilities"]["tools"].is_object());
+    client.assert_silent();
+
+    // Unsupported revisions are answered with one the server speaks
+    let mut other = McpClient::spawn();
+    assert_eq!(other.initialize("1999-01-01")["protocolVersion"], "2025-06-18");
+
+    let ping = client.request("ping", json!({}));
+    assert_eq!(ping["result"], json!({}));
+
+    let tools = client.request("tools/list", json!({}))["result"]["tools"].as_array().unwrap().clone();
+    let mut names: Vec<&str> = tools.iter().map(|tool| tool["name"].as_str().unwrap()).collect();
+    for tool in &tools {
+        assert!(tool["description"].as_str().is_some_and(|d| !d.is_empty()), "{}", tool);
+        assert_eq!(tool["inputSchema"]["type"], "object", "{}", tool);
+        for required in tool["inputSchema"]["required"].as_array().into_iter().flatten() {
+            assert!(tool["inputSchema"]["properties"].get(required.as_str().unwrap()).is_some(), "{}", tool);
+        }
+    }
+    let count = names.len();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), count, "duplicate tool names");
+    assert!(names.contains(&"search_knowledge_chunk") && names.contains(&"ingest_text"));
+
+    assert!(client.request("resources/list", json!({}))["result"]["resources"].is_array());
+    assert!(client.request("prompts/list", json!({}))["result"]["prompts"].is_array());
+}
+
+#[test]
+fn test_tool_calls_batches_and_errors() {
+    let mut client = McpClient::spawn();
+    client.initialize("2025-06-18");
+
+    let ingested = client.call_tool("ingest_text", json!({
+        "source_name": "notes/dma.md",
+        "text": "The DMA engine must be held in reset until the clocks are stable. Release the reset \
+            only after the PLL reports lock, then program the descriptor ring before enabling transfers.",
+        "doc_type": "text"
+    }));
+    assert!(tool_text(&ingested).starts_with("Successfully ingested 1 chunks"), "{}", ingested);
+
+    let found = tool_text(&client.call_tool("search_knowledge_chunk", json!({"query": "DMA reset clocks", "top_k": 3})));
+    assert!(found.contains("held in reset"), "{}", found);
+
+    // A batch answers its requests in one array frame and skips its notifications
+    let (first, search) = client.request_frame("tools/call", json!({"name": "search_knowledge_chunk", "arguments": {"query": "DMA"}}));
+    let (second, ping) = client.request_frame("ping", json!({}));
+    let cancelled = json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 99}});
+    client.send_raw(&json!([search, cancelled, ping]).to_string());
+    let batch = client.receive();
+    let ids: Vec<u64> = batch.as_array().expect("batch response must be an array").iter().map(|r| r["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, vec![first, second]);
+
+    // Unknown notifications are ignored without a reply
+    client.notify("notifications/roots/list_changed", json!({}));
+    client.assert_silent();
+
+    assert_eq!(client.request("no/such/method", json!({}))["error"]["code"], -32601);
+    assert_eq!(client.call_tool("no_such_tool", json!({}))["error"]["code"], -32602);
+    assert_eq!(client.call_tool("search_knowledge_chunk", json!({}))["error"]["code"], -32602);
+
+    client.send_raw("{\"jsonrpc\": \"2.0\", \"id\": 7, \"method\": ");
+    let parse_error = client.receive();
+    assert_eq!(parse_error["error"]["code"], -32700);
+    assert!(parse_error["id"].is_null());
+
+    // The server keeps serving after errors
+    assert_eq!(client.request("ping", json!({}))["result"], json!({}));
+}
\ No newline at end of file